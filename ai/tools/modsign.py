@@ -0,0 +1,236 @@
+#!/usr/bin/env python3
+"""
+modsign.py — Sign VernisOS kernel modules.
+
+Usage:
+    python3 ai/tools/modsign.py genkey -o modsign_key.json
+    python3 ai/tools/modsign.py sign mod.bin -k modsign_key.json -o mod.signed.bin \
+        [--version 1.0] [--caps 0x10000] \
+        [--param NAME,KIND,OFFSET[,def=V][,min=N][,max=N][,ro][,read=P][,write=P] ...] \
+        [--notify OFFSET]
+
+genkey writes an RSA-2048 key (JSON: n, e, d as hex) and prints the public
+modulus as a Rust array for MODSIGN_PUBKEY in
+kernel/core/verniskernel/src/modsign.rs. Keep the key file out of the tree.
+
+sign reads the module header (v1-v4, see include/module.h) for the name,
+dependencies and code section, then appends a signed ModManifest
+(little-endian, 428 bytes) after the code:
+
+    [0..4]     magic: "MANF"
+    [4..8]     version: u32 (major << 16 | minor)
+    [8..16]    caps: u64 (capabilities the loading process must hold)
+    [16..40]   name: char[24]
+    [40..44]   dep_count: u32
+    [44..140]  deps: char[4][24]
+    [140..172] image_sha256: SHA-256 of the module header and code section
+    [172..428] signature: RSA-2048 PKCS#1 v1.5 over SHA-256 of bytes 0..172
+
+With --param (or --notify) the manifest is v2 instead ("MNF2", 852 bytes):
+the same fields, then the parameter table, then the signature over bytes
+0..596:
+
+    [172..176] param count: u32
+    [176..180] notify callback code offset: u32 (0xFFFFFFFF: none)
+    [180..596] params: 8 x ModParamDecl (52 bytes each)
+
+KIND is int, bool or string; OFFSET is where the variable lives in the code
+section. read/write are CLI privileges (0 root, 50 admin, 100 user) and
+default to 100 and 0; ro makes the parameter settable at load time only.
+For a string, max is the longest allowed value.
+"""
+
+import argparse
+import hashlib
+import json
+import secrets
+import struct
+import sys
+
+MOD_MAGIC = 0x4C444F4D
+MANIFEST_MAGIC = 0x464E414D
+MANIFEST_MAGIC_V2 = 0x32464E4D
+NAME_LEN = 24
+MAX_DEPS = 4
+HDR_V1 = 72
+HDR_V2 = 172
+HDR_V3 = 436
+HDR_V4 = 444
+KEY_BITS = 2048
+SIG_LEN = KEY_BITS // 8
+MAX_PARAMS = 8
+PARAM_NAME_LEN = 16
+PARAM_STR_LEN = 16
+PARAM_KINDS = {"int": 0, "bool": 1, "string": 2}
+PARAM_RO = 1
+NO_NOTIFY = 0xFFFFFFFF
+
+SHA256_DIGEST_INFO = bytes.fromhex("3031300d060960864801650304020105000420")
+
+
+def is_probable_prime(n, rounds=40):
+    if n < 2:
+        return False
+    for p in (2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37):
+        if n % p == 0:
+            return n == p
+    d, r = n - 1, 0
+    while d % 2 == 0:
+        d //= 2
+        r += 1
+    for _ in range(rounds):
+        a = secrets.randbelow(n - 3) + 2
+        x = pow(a, d, n)
+        if x in (1, n - 1):
+            continue
+        for _ in range(r - 1):
+            x = pow(x, 2, n)
+            if x == n - 1:
+                break
+        else:
+            return False
+    return True
+
+
+def gen_prime(bits, e):
+    while True:
+        p = secrets.randbits(bits) | (1 << (bits - 1)) | (1 << (bits - 2)) | 1
+        if (p - 1) % e != 0 and is_probable_prime(p):
+            return p
+
+
+def genkey(args):
+    e = 65537
+    while True:
+        p = gen_prime(KEY_BITS // 2, e)
+        q = gen_prime(KEY_BITS // 2, e)
+        n = p * q
+        if p != q and n.bit_length() == KEY_BITS:
+            break
+    d = pow(e, -1, (p - 1) * (q - 1))
+    with open(args.output, "w") as f:
+        json.dump({"n": hex(n), "e": hex(e), "d": hex(d)}, f)
+    raw = n.to_bytes(SIG_LEN, "big")
+    print(f"// public key for {args.output}")
+    for i in range(0, SIG_LEN, 16):
+        print("    " + ", ".join(f"0x{b:02x}" for b in raw[i:i + 16]) + ",")
+
+
+def rsa_sign(key, msg):
+    n, d = int(key["n"], 16), int(key["d"], 16)
+    h = hashlib.sha256(msg).digest()
+    t = SHA256_DIGEST_INFO + h
+    em = b"\x00\x01" + b"\xff" * (SIG_LEN - len(t) - 3) + b"\x00" + t
+    return pow(int.from_bytes(em, "big"), d, n).to_bytes(SIG_LEN, "big")
+
+
+def parse_version(s):
+    major, _, minor = s.partition(".")
+    return (int(major) << 16) | int(minor or 0)
+
+
+def parse_param(spec):
+    fields = spec.split(",")
+    if len(fields) < 3:
+        sys.exit(f"modsign: bad --param '{spec}' (need NAME,KIND,OFFSET)")
+    name, kind, offset = fields[:3]
+    if kind not in PARAM_KINDS:
+        sys.exit(f"modsign: --param {name}: unknown kind '{kind}'")
+    if not name or len(name) >= PARAM_NAME_LEN:
+        sys.exit(f"modsign: --param name '{name}' empty or too long")
+    opts = {"def": "", "min": "0", "max": "0", "read": "100", "write": "0"}
+    flags = 0
+    for f in fields[3:]:
+        if f == "ro":
+            flags |= PARAM_RO
+            continue
+        k, eq, v = f.partition("=")
+        if not eq or k not in opts:
+            sys.exit(f"modsign: --param {name}: bad option '{f}'")
+        opts[k] = v
+    def_int, def_str = 0, b""
+    if kind == "string":
+        def_str = opts["def"].encode()
+        if len(def_str) >= PARAM_STR_LEN:
+            sys.exit(f"modsign: --param {name}: default too long")
+    elif kind == "bool":
+        def_int = 1 if opts["def"] in ("1", "y", "yes", "on", "true") else 0
+        opts["min"], opts["max"] = "0", "1"
+    else:
+        def_int = int(opts["def"] or "0", 0)
+    return struct.pack("<16sBBBBIiii16s", name.encode(), PARAM_KINDS[kind], flags,
+                       int(opts["read"]), int(opts["write"]), int(offset, 0),
+                       int(opts["min"], 0), int(opts["max"], 0), def_int, def_str)
+
+
+def sign(args):
+    with open(args.module, "rb") as f:
+        blob = f.read()
+    with open(args.key) as f:
+        key = json.load(f)
+
+    magic, version, _fn_count, code_size = struct.unpack_from("<IIII", blob, 0)
+    if magic != MOD_MAGIC:
+        sys.exit("modsign: not a module (bad magic)")
+    name = blob[16:16 + NAME_LEN]
+    deps, dep_count = [], 0
+    if version == 1:
+        code_off = HDR_V1
+    elif version in (2, 3, 4):
+        code_off = {2: HDR_V2, 3: HDR_V3, 4: HDR_V4}[version]
+        dep_count = struct.unpack_from("<I", blob, HDR_V1)[0]
+        for i in range(min(dep_count, MAX_DEPS)):
+            at = HDR_V1 + 4 + i * NAME_LEN
+            deps.append(blob[at:at + NAME_LEN])
+    else:
+        sys.exit(f"modsign: unsupported module version {version}")
+    if len(blob) < code_off + code_size:
+        sys.exit("modsign: module truncated")
+    image = blob[:code_off + code_size]
+
+    dep_table = b"".join(deps) + b"\x00" * (NAME_LEN * (MAX_DEPS - len(deps)))
+    v2 = bool(args.param) or args.notify is not None
+    if len(args.param) > MAX_PARAMS:
+        sys.exit(f"modsign: at most {MAX_PARAMS} parameters")
+    magic = MANIFEST_MAGIC_V2 if v2 else MANIFEST_MAGIC
+    signed = struct.pack("<IIQ", magic, parse_version(args.version), int(args.caps, 0))
+    signed += name + struct.pack("<I", dep_count) + dep_table
+    signed += hashlib.sha256(image).digest()
+    if v2:
+        decls = b"".join(parse_param(p) for p in args.param)
+        notify = NO_NOTIFY if args.notify is None else int(args.notify, 0)
+        signed += struct.pack("<II", len(args.param), notify)
+        signed += decls + b"\x00" * (52 * (MAX_PARAMS - len(args.param)))
+    manifest = signed + rsa_sign(key, signed)
+
+    with open(args.output, "wb") as f:
+        f.write(image + manifest)
+    label = name.rstrip(b"\0").decode(errors="replace")
+    print(f"modsign: signed {label} -> {args.output}")
+
+
+def main():
+    ap = argparse.ArgumentParser(description="Sign VernisOS kernel modules")
+    sub = ap.add_subparsers(dest="cmd", required=True)
+
+    g = sub.add_parser("genkey", help="generate an RSA-2048 signing key")
+    g.add_argument("-o", "--output", required=True)
+    g.set_defaults(func=genkey)
+
+    s = sub.add_parser("sign", help="append a signed manifest to a module")
+    s.add_argument("module")
+    s.add_argument("-k", "--key", required=True)
+    s.add_argument("-o", "--output", required=True)
+    s.add_argument("--version", default="1.0")
+    s.add_argument("--caps", default="0")
+    s.add_argument("--param", action="append", default=[],
+                   help="declare a parameter (v2 manifest)")
+    s.add_argument("--notify", help="code offset of the parameter change callback")
+    s.set_defaults(func=sign)
+
+    args = ap.parse_args()
+    args.func(args)
+
+
+if __name__ == "__main__":
+    main()
@@ -368,6 +368,8 @@ vbe_setup:
     mov dword [0x5314], 0
     mov dword [0x5318], 0
     mov dword [0x531C], 0           ; fb_type = 0 (text mode)
+    mov dword [0x5320], 0           ; fb_channels = unknown
+    mov dword [0x5324], 0
 
     ; Query VBE Controller Info at 0x5000
     xor ax, ax
@@ -379,16 +381,14 @@ vbe_setup:
     cmp ax, 0x004F
     jne .vbe_done
 
-    ; Try mode 0x118 (1024x768x32/24bpp)
+    ; Try mode 0x118 (1024x768)
     mov ax, 0x4F01
     mov cx, 0x0118
     mov di, 0x5200
     int 0x10
     cmp ax, 0x004F
     jne .try_115
-    cmp byte [0x5219], 32
-    je .set_118
-    cmp byte [0x5219], 24
+    call vbe_bpp_ok
     jne .try_115
 .set_118:
     mov ax, 0x4F02
@@ -404,9 +404,7 @@ vbe_setup:
     int 0x10
     cmp ax, 0x004F
     jne .try_112
-    cmp byte [0x5219], 32
-    je .set_115
-    cmp byte [0x5219], 24
+    call vbe_bpp_ok
     jne .try_112
 .set_115:
     mov ax, 0x4F02
@@ -423,9 +421,7 @@ vbe_setup:
     int 0x10
     cmp ax, 0x004F
     jne .vbe_done
-    cmp byte [0x5219], 32
-    je .set_112
-    cmp byte [0x5219], 24
+    call vbe_bpp_ok
     jne .vbe_done
 .set_112:
     mov ax, 0x4F02
@@ -447,12 +443,29 @@ vbe_setup:
     movzx eax, byte [0x5219]        ; BitsPerPixel
     mov [0x5318], eax
     mov dword [0x531C], 1           ; fb_type = 1 (framebuffer)
+    mov eax, [0x521F]               ; Red/Green MaskSize+FieldPosition
+    mov [0x5320], eax
+    movzx eax, word [0x5223]        ; BlueMaskSize+FieldPosition
+    mov [0x5324], eax
 
 .vbe_done:
     pop es
     popa
     ret
 
+; ZF set if the mode info at 0x5200 has a depth the kernel can draw to
+vbe_bpp_ok:
+    mov al, [0x5219]                ; BitsPerPixel
+    cmp al, 32
+    je .ok
+    cmp al, 24
+    je .ok
+    cmp al, 16
+    je .ok
+    cmp al, 15
+.ok:
+    ret
+
 hang:
     cli
     hlt
@@ -766,6 +766,8 @@ vbe_setup:
     mov dword [0x5314], 0           ; fb_pitch
     mov dword [0x5318], 0           ; fb_bpp
     mov dword [0x531C], 0           ; fb_type = 0 (text mode)
+    mov dword [0x5320], 0           ; fb_channels = unknown
+    mov dword [0x5324], 0
 
     ; Query VBE Controller Info at 0x5000
     xor ax, ax
@@ -777,7 +779,7 @@ vbe_setup:
     cmp ax, 0x004F
     jne .vbe_done
 
-    ; Try mode 0x118 (1024x768x32bpp)
+    ; Try mode 0x118 (1024x768)
     mov ax, 0x4F01
     mov cx, 0x0118
     mov di, 0x5200
@@ -785,9 +787,7 @@ vbe_setup:
     cmp ax, 0x004F
     jne .try_mode_115
     ; Accept 32bpp or 24bpp
-    cmp byte [0x5219], 32
-    je .set_mode_118
-    cmp byte [0x5219], 24
+    call vbe_bpp_ok
     jne .try_mode_115
 .set_mode_118:
     ; Set mode 0x118 + LFB (bit 14)
@@ -798,16 +798,14 @@ vbe_setup:
     je .vbe_store
 
 .try_mode_115:
-    ; Try mode 0x115 (800x600x32bpp)
+    ; Try mode 0x115 (800x600)
     mov ax, 0x4F01
     mov cx, 0x0115
     mov di, 0x5200
     int 0x10
     cmp ax, 0x004F
     jne .try_mode_112
-    cmp byte [0x5219], 32
-    je .set_mode_115
-    cmp byte [0x5219], 24
+    call vbe_bpp_ok
     jne .try_mode_112
 .set_mode_115:
     mov ax, 0x4F02
@@ -818,16 +816,14 @@ vbe_setup:
     jmp .vbe_store
 
 .try_mode_112:
-    ; Try mode 0x112 (640x480x32bpp)
+    ; Try mode 0x112 (640x480)
     mov ax, 0x4F01
     mov cx, 0x0112
     mov di, 0x5200
     int 0x10
     cmp ax, 0x004F
     jne .vbe_done
-    cmp byte [0x5219], 32
-    je .set_mode_112
-    cmp byte [0x5219], 24
+    call vbe_bpp_ok
     jne .vbe_done
 .set_mode_112:
     mov ax, 0x4F02
@@ -850,12 +846,29 @@ vbe_setup:
     movzx eax, byte [0x5219]        ; BitsPerPixel (offset 25)
     mov [0x5318], eax
     mov dword [0x531C], 1           ; fb_type = 1 (framebuffer)
+    mov eax, [0x521F]               ; Red/Green MaskSize+FieldPosition
+    mov [0x5320], eax
+    movzx eax, word [0x5223]        ; BlueMaskSize+FieldPosition
+    mov [0x5324], eax
 
 .vbe_done:
     pop es
     popa
     ret
 
+; ZF set if the mode info at 0x5200 has a depth the kernel can draw to
+vbe_bpp_ok:
+    mov al, [0x5219]                ; BitsPerPixel
+    cmp al, 32
+    je .ok
+    cmp al, 24
+    je .ok
+    cmp al, 16
+    je .ok
+    cmp al, 15
+.ok:
+    ret
+
 hang:
     mov si, hang_msg
     call print_message
@@ -4,6 +4,7 @@
 #include <stdint.h>
 #include <stddef.h>
 #include <stdbool.h>
+#include "kerrno.h"
 
 #ifdef __cplusplus
 extern "C" {
@@ -74,6 +75,20 @@ AiBridgeStatus ai_bridge_status(void);
 // Returns response length, or -1 on timeout/error
 int  ai_query_sync(const char *query, char *resp_buf, size_t max_len);
 
+// ---- Asynchronous queries (used by the uring AI_QUERY op) ----
+#define AI_ASYNC_MAX        4           // queries in flight at once
+
+// Send a query without waiting. Returns its sequence number (> 0), -EINVAL,
+// -ENODEV when COM2 is absent, or -EBUSY with AI_ASYNC_MAX outstanding.
+int32_t ai_query_submit(const char *query);
+
+// Non-blocking: response length (copied NUL-terminated into resp_buf, which
+// frees the slot), -EAGAIN while still outstanding, -ENOENT for unknown seq.
+int32_t ai_query_collect(uint32_t seq, char *resp_buf, size_t max_len);
+
+// Forget seq; a late response is dropped.
+void    ai_query_cancel(uint32_t seq);
+
 // Send event notification (fire-and-forget)
 void ai_send_event(const char *event_type, const char *data);
 
@@ -121,6 +136,9 @@ void ai_bridge_set_status(AiBridgeStatus status);
 // Initialize the in-kernel AI engine (pass kernel scheduler pointer)
 void ai_kernel_engine_init(void *scheduler);
 
+// Raw AiEngine pointer (NULL before init) — used by Rust heap OOM policy
+void *ai_kernel_engine_handle(void);
+
 // Feed an event into the AI engine (called from exception/syscall/module handlers)
 void ai_kernel_engine_feed(const char *event_type, const char *data, uint64_t now);
 
@@ -140,8 +158,8 @@ uint32_t ai_kernel_engine_active_procs(void);
 // =============================================================================
 
 // Load a binary policy blob (VPOL format) into the AI engine.
-// Returns 1 on success, 0 on failure.
-uint32_t ai_kernel_engine_load_policy(const uint8_t *blob, size_t len);
+// Returns 0 on success, -EINVAL for a malformed blob, -ENODEV before init.
+int32_t ai_kernel_engine_load_policy(const uint8_t *blob, size_t len);
 
 // Get the currently loaded policy version.
 uint16_t ai_kernel_engine_policy_version(void);
@@ -154,8 +172,9 @@ void policy_load_from_disk(void);
 // =============================================================================
 
 // Check if a command is allowed at the given privilege level.
-// Returns required privilege (0=root, 50=admin, 100=user), or 255 if no rule.
-uint8_t ai_kernel_engine_check_access(const char *command, size_t len);
+// Returns required privilege (0=root, 50=admin, 100=user), -ENOENT if no
+// rule matches, or another -errno on error.
+int32_t ai_kernel_engine_check_access(const char *command, size_t len);
 
 // Get number of loaded access rules.
 uint32_t ai_kernel_engine_access_rule_count(void);
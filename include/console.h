@@ -0,0 +1,84 @@
+#ifndef VERNISOS_CONSOLE_H
+#define VERNISOS_CONSOLE_H
+
+#include <stdint.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Framebuffer text console (Rust: console.rs, scrollback.rs)
+//
+// Output goes through the VT100/ANSI interpreter. Cells are mirrored into a
+// scrollback ring so Shift+PageUp/PageDown can page back through lines that
+// left the screen; new output snaps the view back to the bottom.
+//
+// There are CONSOLE_VT_COUNT virtual consoles; Alt+F1..F6 picks the one
+// shown. Each can be owned by the kernel log, a process or a CLI session:
+// keyboard input on the active console goes to its owner, and the owner's
+// output goes to its console. Each process console has its own input queue,
+// which stdin reads of the owning process drain. The calls without an index act on the output
+// console (the one owned by a session, else console 0).
+// =============================================================================
+
+#define CONSOLE_VT_COUNT         6
+#define CONSOLE_HISTORY_DEFAULT  200   // console 0
+#define CONSOLE_HISTORY_VT       50    // the others
+#define CONSOLE_HISTORY_MAX      4000
+
+// Console owners (console_assign kind)
+#define VT_OWNER_NONE     0
+#define VT_OWNER_KERNEL   1   // klog output
+#define VT_OWNER_PROCESS  2   // id = pid; stdout and stdin
+#define VT_OWNER_SESSION  3   // id = CLI session id
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+void console_init(uint32_t width, uint32_t height);
+void console_putchar(uint8_t c);
+void console_writestring(const uint8_t *s, uint32_t len);
+void console_clear(void);
+void console_set_color(uint32_t fg, uint32_t bg);
+void console_set_color_vga(uint8_t fg, uint8_t bg);
+void console_set_pos(uint32_t row, uint32_t col);
+void console_get_pos(uint32_t *row, uint32_t *col);
+void console_clear_to_eol(uint32_t row, uint32_t col);
+
+// Page the active console's view: positive pages go back into history, negative toward the
+// live screen, half a screen each.
+void console_scroll_view(int32_t pages);
+
+// Keep `lines` of history on the active console (at most
+// CONSOLE_HISTORY_MAX). 0, -EINVAL, -ENODEV (no console buffer) or -ENOMEM.
+int32_t console_set_scrollback(uint32_t lines);
+
+// History lines held by the active console and its limit.
+void console_get_scrollback(uint32_t *used, uint32_t *limit);
+
+// Show console `index` (0-based). 0, -EINVAL or -ENODEV (no console).
+int32_t  console_switch(uint32_t index);
+uint32_t console_active(void);
+
+// Give console `index` to an owner, releasing any console it held. 0 or
+// -EINVAL.
+int32_t  console_assign(uint32_t index, uint32_t kind, uint32_t id);
+
+// Console held by an owner, or -ENOENT.
+int32_t  console_lookup(uint32_t kind, uint32_t id);
+
+// Owner kind of the active console (the input consumer); stores its id.
+uint32_t console_input_owner(uint32_t *id);
+
+void     console_write_to(uint32_t index, const uint8_t *s, uint32_t len);
+
+// Write to the console owned by VT_OWNER_KERNEL, if any.
+void     console_log(const uint8_t *s, uint32_t len);
+
+// One line per console into buf. Returns length.
+uint32_t console_render_list(uint8_t *buf, uint32_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_CONSOLE_H
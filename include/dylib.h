@@ -20,7 +20,8 @@ extern "C" {
 #define DYLIB_ERR_FORMAT   (-5)
 
 void    dylib_init(void);
-int32_t dylib_open(const char *path, const char *name);
+// params: load-time module parameters ("name=value ..."), may be NULL
+int32_t dylib_open(const char *path, const char *name, const char *params);
 int32_t dylib_close(uint32_t handle);
 int32_t dylib_list(void);
 int32_t dylib_resolve(uint32_t handle, const char *symbol);
@@ -0,0 +1,37 @@
+#ifndef VERNISOS_FONT_H
+#define VERNISOS_FONT_H
+
+#include <stdint.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Console/GUI fonts (Rust: font.rs)
+//
+// Text is drawn from the active PSF2 font through its Unicode table, with
+// the built-in VGA font (ASCII) as fallback. Combining marks (Thai vowels
+// and tone marks) share the cell of the character before them. The cell
+// grid is fixed at 8x16, so only 8x16 fonts load.
+// =============================================================================
+
+#define FONT_PSF2_MAGIC   0x864AB572u
+#define FONT_MAX_FILE     (64 * 1024)
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Load a PSF2 font from VernisFS and make it active. 0, -ENOENT (unreadable),
+// -EINVAL (not an 8x16 PSF2 font) or -ENOMEM.
+int32_t  font_load(const char *path);
+
+// Back to the built-in font.
+void     font_reset(void);
+
+// Active font and glyph cache statistics into buf. Returns length.
+uint32_t font_render_info(uint8_t *buf, uint32_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_FONT_H
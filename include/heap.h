@@ -0,0 +1,101 @@
+#ifndef VERNISOS_HEAP_H
+#define VERNISOS_HEAP_H
+
+#include <stdint.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// =============================================================================
+// Kernel heap accounting and OOM handling (Rust: heap.rs)
+//
+// The Rust global allocator tracks bytes in use, peak usage and per-size-class
+// counts. When the heap is exhausted it reaps empty slabs, asks C for more
+// memory through the grow callback, and as a last resort kills the largest
+// untrusted or suspicious user task (chosen from the OOM-tasks callback, torn
+// down by the OOM-kill callback). The kill does not return the victim's
+// frames, which the bump frame allocator never frees, so the allocation
+// only succeeds if the teardown freed heap objects (shm segments, rings).
+// =============================================================================
+
+#define HEAP_SIZE_CLASSES  11   // <=8, 16, 32 ... 4096, >4096 bytes
+
+// Subsystem tags for leak tracking
+#define HEAP_TAG_UNTAGGED   0
+#define HEAP_TAG_SCHEDULER  1
+#define HEAP_TAG_AI         2
+#define HEAP_TAG_GUI        3
+#define HEAP_TAG_MODULE     4
+#define HEAP_TAG_SLAB       5
+#define HEAP_TAG_IPC        6
+#define HEAP_TAG_FS         7
+#define HEAP_TAG_COUNT      8
+
+typedef struct {
+    size_t   heap_size;        // bytes managed (initial + grown)
+    size_t   bytes_in_use;     // requested bytes currently allocated
+    size_t   peak_bytes;       // high-water mark of bytes_in_use
+    size_t   allocator_bytes;  // bytes in use including buddy rounding
+    uint32_t alloc_count;
+    uint32_t free_count;
+    uint32_t failed_allocs;
+    uint32_t grow_count;
+    uint32_t oom_kills;
+    uint32_t reaped_bytes;
+    uint32_t class_live[HEAP_SIZE_CLASSES];
+    uint32_t class_total[HEAP_SIZE_CLASSES];
+} HeapStats;
+
+typedef struct {
+    uint32_t live_allocs;
+    size_t   live_bytes;
+    uint32_t total_allocs;
+} HeapTagStats;
+
+// Grow callback: hand the heap a physically contiguous, identity-mapped
+// region of at least min_bytes. Store its start in *out_start and return its
+// size, or return 0 to refuse.
+typedef size_t (*heap_grow_fn)(size_t min_bytes, uintptr_t *out_start);
+
+// A task the OOM policy may kill.
+typedef struct {
+    uint32_t pid;
+    uint32_t pages;   // user pages the task holds
+} OomCandidate;
+
+// Fill out with up to max killable tasks (live tasks running a user program,
+// never the current one) and return how many were written. Called from
+// inside a failing allocation: must not allocate or enter the scheduler.
+typedef uint32_t (*heap_oom_tasks_fn)(OomCandidate *out, uint32_t max);
+
+// Called with the OOM victim: tear down its task slot and release its shared
+// memory and swap. The Rust scheduler marks it killed on the next syscall.
+typedef void (*heap_oom_kill_fn)(uint32_t pid);
+
+void     heap_set_grow_cb(heap_grow_fn cb);
+void     heap_set_oom_tasks_cb(heap_oom_tasks_fn cb);
+void     heap_set_oom_kill_cb(heap_oom_kill_fn cb);
+
+// Returns 1 on success, 0 on a NULL out pointer / bad tag.
+uint8_t  heap_get_stats(HeapStats *out);
+uint8_t  heap_get_tag_stats(uint8_t tag, HeapTagStats *out);
+
+// Attribute subsequent allocations to tag; returns the previous tag.
+uint8_t  heap_set_tag(uint8_t tag);
+
+// Enable/disable leak tracking (clears the live-allocation table).
+void     heap_set_leak_tracking(uint8_t enable);
+
+// Render heap totals into buf (NUL-terminated). Returns length.
+uint32_t heap_render_info(uint8_t *buf, uint32_t buf_len);
+
+// Render per-tag usage and live allocations older than min_age_ticks.
+uint32_t heap_render_leaks(uint8_t *buf, uint32_t buf_len, uint32_t min_age_ticks);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_HEAP_H
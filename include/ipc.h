@@ -3,6 +3,7 @@
 
 #include <stdint.h>
 #include <stddef.h>
+#include "kerrno.h"
 
 // =============================================================================
 // IPC Constants
@@ -22,12 +23,12 @@
 #define IPC_TYPE_CTRL     0x03  // control / command
 #define IPC_TYPE_AI       0x10  // reserved for AI IPC bridge (Phase 8)
 
-// Return codes
+// Return codes (negative errno, see kerrno.h)
 #define IPC_OK            0
-#define IPC_ERR_FULL    (-1)    // queue or channel is full
-#define IPC_ERR_EMPTY   (-2)    // queue or channel is empty
-#define IPC_ERR_INVAL   (-3)    // invalid argument or id
-#define IPC_ERR_NOSLOT  (-4)    // no free slot available
+#define IPC_ERR_FULL    (-EAGAIN)   // queue or channel is full
+#define IPC_ERR_EMPTY   (-ENOMSG)   // queue or channel is empty
+#define IPC_ERR_INVAL   (-EINVAL)   // invalid argument or id
+#define IPC_ERR_NOSLOT  (-ENOSPC)   // no free slot available
 
 // IPC syscall numbers (20-27, handled in C before Rust syscall_handler)
 #define SYS_IPC_SEND          20
@@ -0,0 +1,47 @@
+#ifndef VERNISOS_KERRNO_H
+#define VERNISOS_KERRNO_H
+
+#include <stdint.h>
+
+// =============================================================================
+// Kernel error codes (Rust: errno.rs, enum KernelError)
+//
+// Every Rust FFI entry point and every syscall uses one convention:
+//
+//     >= 0   success (0, or a count / pid / handle)
+//     <  0   -errno
+//
+// The values follow Linux/POSIX and are ABI: user space receives them as
+// syscall return values. Keep in sync with errno.rs; never renumber.
+// =============================================================================
+
+#define EPERM            1      // operation not permitted (missing capability)
+#define ENOENT           2      // no such file, module, rule or syscall slot
+#define ESRCH            3      // no such process
+#define EINTR            4      // interrupted
+#define EIO              5      // I/O error
+#define E2BIG            7      // argument or payload too long
+#define ENOEXEC          8      // bad image magic or version
+#define EBADF            9      // bad file descriptor
+#define ECHILD          10      // no child process
+#define EAGAIN          11      // try again
+#define ENOMEM          12      // out of memory
+#define EACCES          13      // access refused
+#define EFAULT          14      // bad address
+#define EBUSY           16      // resource busy
+#define EEXIST          17      // already exists
+#define ENODEV          19      // no such device
+#define EINVAL          22      // invalid argument
+#define EMFILE          24      // too many open files
+#define ENOSPC          28      // no space left
+#define ERANGE          34      // value or buffer size out of range
+#define ENAMETOOLONG    36      // name too long
+#define ENOSYS          38      // syscall not implemented
+#define ENOMSG          42      // queue or channel is empty
+#define ETIMEDOUT      110      // no answer in time
+#define ECANCELED      125      // operation abandoned before it completed
+
+// Symbolic name ("EINVAL") for an errno of either sign; "E?" if unknown.
+const char *kernel_strerror(int32_t err);
+
+#endif // VERNISOS_KERRNO_H
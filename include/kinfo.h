@@ -0,0 +1,75 @@
+#ifndef VERNISOS_KINFO_H
+#define VERNISOS_KINFO_H
+
+#include <stdint.h>
+
+// =============================================================================
+// Read-only kernel info page (Rust: kinfo.rs)
+//
+// Mapped read-only at KINFO_VADDR into every user address space so that
+// getpid() and time() need no syscall (userlib/libc.c, which includes this
+// header). The arch code keeps
+// it current: kinfo_write_tick() from the timer IRQ, kinfo_write_pid() on
+// every task switch. Both are seqlock writers and must run with interrupts
+// off (true in IRQ and int 0x80 context).
+// =============================================================================
+
+#define KINFO_MAGIC    0x50494B56u   // "VKIP"
+#define KINFO_VERSION  1
+#define KINFO_VADDR    0x0FFFF000u   // just below the user range
+
+typedef struct {
+    uint32_t magic;
+    uint32_t version;
+    uint32_t seq;         // odd while an update is in progress
+    uint32_t timer_hz;
+    uint64_t ticks;       // timer ticks since boot
+    uint64_t wall_base;   // Unix time at tick 0
+    uint32_t pid;         // running task, 0 for the kernel
+    uint32_t _pad;
+    uint8_t  boot_id[16];
+} KernelInfoPage;         // 56 bytes
+
+// Same layout on i386 and x86_64; kinfo.rs asserts the same size.
+_Static_assert(sizeof(KernelInfoPage) == 56, "KernelInfoPage layout changed");
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Fill in the page and return it (page-aligned, identity-mapped).
+KernelInfoPage *kinfo_init(uint32_t timer_hz, uint64_t wall_base, uint64_t seed);
+
+// Unix time for an RTC reading (UTC).
+uint64_t kinfo_unix_time(uint32_t year, uint32_t month, uint32_t day,
+                         uint32_t hour, uint32_t min, uint32_t sec);
+
+#ifdef __cplusplus
+}
+#endif
+
+static inline void kinfo_write_begin(volatile KernelInfoPage *p) {
+    p->seq++;
+    __asm__ volatile("" ::: "memory");
+}
+
+static inline void kinfo_write_end(volatile KernelInfoPage *p) {
+    __asm__ volatile("" ::: "memory");
+    p->seq++;
+}
+
+static inline void kinfo_write_tick(volatile KernelInfoPage *p, uint64_t ticks) {
+    if (!p) return;
+    kinfo_write_begin(p);
+    p->ticks = ticks;
+    kinfo_write_end(p);
+}
+
+static inline void kinfo_write_pid(volatile KernelInfoPage *p, uint32_t pid) {
+    if (!p) return;
+    kinfo_write_begin(p);
+    p->pid = pid;
+    kinfo_write_end(p);
+}
+
+#endif // VERNISOS_KINFO_H
@@ -2,12 +2,48 @@
 #define VERNISOS_MODULE_H
 
 #include <stdint.h>
+#include "kerrno.h"
 
 // =============================================================================
 // Module Loader — Phase 5: Module Loader + Dynamic Linking
 //
 // Module binary format (flat, no ELF):
-//   [ModHeader 72 bytes][code/data bytes]
+//   v1: [ModHeader 72 bytes][code/data bytes]
+//   v2: [ModHeaderV2 172 bytes][code/data bytes]   (adds dependencies)
+//   v3: [ModHeaderV3 436 bytes][code/data bytes]   (adds named exports)
+//   v4: [ModHeaderV4 444 bytes][code/data bytes]   (adds init/exit hooks)
+//
+// A v2+ module names the modules it calls into. They must be loaded first,
+// and a module cannot be unloaded while others depend on it or a call into
+// it is in progress (see module_registry.rs).
+//
+// A v3 module also names its exports. They go into the registry's global
+// symbol table, shared by every module and by dylib; a load whose export
+// clashes with an existing symbol is refused with -EEXIST.
+//
+// Any version may be followed by a signed ModManifest right after its code
+// (see modsign.rs and ai/tools/modsign.py). It carries the SHA-256 of the
+// header and code, so the function table, hooks and exports are signed along
+// with the code. The registry checks it before accepting the module;
+// MOD_SIG_ENFORCE (the default) rejects unsigned or badly signed modules
+// with -EACCES, MOD_SIG_WARN only reports them.
+//
+// A loaded module is Loading until its init hook returns, then Live (or
+// Failed if init returned a negative value or faulted). Only Live modules
+// can be called; unloading runs the exit hook first. A fault inside a
+// module call is contained: the call returns MOD_ERR_FAULT and the fault is
+// counted against the module. When the AI engine's bad-module pattern (a
+// MOD event followed by an exception) fires, the module that just faulted
+// is Quarantined and its calls fail with MOD_ERR_ACCESS until it is
+// unloaded.
+//
+// A ModManifestV2 also declares typed parameters (int, bool, string) bound
+// to variables in the module's code section. The registry stores their
+// values: defaults and the "name=value" assignments passed to
+// module_load_params() are written before init, and module_param_set()
+// changes one at runtime ("module.param", checked against the caller's
+// privilege) and then calls the manifest's notify callback with the
+// parameter's index.
 //
 // All modules share kernel address space (no sandbox — that's Phase 6).
 // Modules are "loaded" by registering an already-memory-resident binary
@@ -16,22 +52,75 @@
 
 // ---- Constants ----
 #define MOD_MAGIC         0x4C444F4D   // "MODL" little-endian
-#define MOD_VERSION       1
+#define MOD_VERSION       4
+#define MOD_VERSION_V1    1            // legacy header without dependencies
+#define MOD_VERSION_V2    2            // dependencies, no named exports
+#define MOD_VERSION_V3    3            // named exports, no hooks
 #define MOD_MAX_MODULES   8            // concurrent loaded modules
 #define MOD_MAX_EXPORTS   8            // exported functions per module
 #define MOD_NAME_LEN      24           // including null terminator
+#define MOD_MAX_DEPS      4            // declared dependencies per module
+
+// module_unload / SYS_MOD_UNLOAD flags
+#define MOD_UNLOAD_CASCADE  0x1        // unload dependents first
+
+// ModHeaderV3.mod_flags
+#define MOD_FLAG_GPL        0x1        // GPL-compatible: may use GPL-only symbols
+
+// ModExportDecl.flags / ModSymbol.flags
+#define MOD_SYM_GPL         0x1        // GPL-only export
+
+// ModHeaderV4.init_fn / exit_fn: no hook
+#define MOD_FN_NONE         0xFFFFFFFFu
+
+// Lifecycle states (Rust: module_registry.rs ModState)
+#define MOD_STATE_LOADING      0
+#define MOD_STATE_LIVE         1
+#define MOD_STATE_UNLOADING    2
+#define MOD_STATE_FAILED       3       // init hook failed
+#define MOD_STATE_QUARANTINED  4       // faulted; calls refused
+
+// module_resolve requester meaning the kernel itself
+#define MOD_RESOLVE_KERNEL  0xFFFFFFFFu
+
+// Signature policy (module_sig_set_policy)
+#define MOD_SIG_OFF         0
+#define MOD_SIG_WARN        1
+#define MOD_SIG_ENFORCE     2
+
+#define MOD_MANIFEST_MAGIC  0x464E414D // "MANF" little-endian
+#define MOD_MANIFEST_MAGIC_V2 0x32464E4D // "MNF2": manifest with parameters
+#define MOD_SIG_LEN         256        // RSA-2048
+
+// Module parameters (ModParamDecl)
+#define MOD_MAX_PARAMS      8
+#define MOD_PARAM_NAME_LEN  16         // including the NUL
+#define MOD_PARAM_STR_LEN   16         // string value, including the NUL
+#define MOD_PARAMS_MAX      128        // load-time "name=value ..." string
+#define MOD_PARAM_INT       0
+#define MOD_PARAM_BOOL      1
+#define MOD_PARAM_STR       2
+#define MOD_PARAM_RO        0x1        // settable at load time only
+#define MOD_PARAM_NO_NOTIFY 0xFFFFFFFFu
 
-// ---- Return codes ----
+// ---- Return codes (negative errno, see kerrno.h) ----
 #define MOD_OK             0
-#define MOD_ERR_INVAL    (-1)          // bad argument / null pointer
-#define MOD_ERR_NOSLOT   (-2)          // no free module slot
-#define MOD_ERR_MAGIC    (-3)          // wrong magic or version
-#define MOD_ERR_RANGE    (-4)          // fn_id out of range
-#define MOD_ERR_NOTFOUND (-5)          // mid not found
+#define MOD_ERR_INVAL    (-EINVAL)     // bad argument / null pointer
+#define MOD_ERR_NOSLOT   (-ENOSPC)     // no free module slot
+#define MOD_ERR_MAGIC    (-ENOEXEC)    // wrong magic or version
+#define MOD_ERR_RANGE    (-ERANGE)     // fn_id or parameter value out of range
+#define MOD_ERR_NOTFOUND (-ENOENT)     // mid (or a dependency) not found
+#define MOD_ERR_BUSY     (-EBUSY)      // has dependents or active callers
+#define MOD_ERR_EXISTS   (-EEXIST)     // exported symbol already defined
+#define MOD_ERR_PERM     (-EPERM)      // GPL-only symbol, non-GPL requester
+#define MOD_ERR_ACCESS   (-EACCES)     // rejected by the signature policy, or quarantined
+#define MOD_ERR_FAULT    (-EFAULT)     // module code faulted during the call
+#define MOD_ERR_IO       (-EIO)        // module init failed
+#define MOD_ERR_AGAIN    (-EAGAIN)     // module still loading
 
 // ---- Syscall numbers (follow IPC 20-27) ----
-#define SYS_MOD_LOAD    28   // a1=addr, a2=size → mid (or error)
-#define SYS_MOD_UNLOAD  29   // a1=mid → MOD_OK or error
+#define SYS_MOD_LOAD    28   // a1=addr, a2=size, a3=params string or 0 → mid (or error)
+#define SYS_MOD_UNLOAD  29   // a1=mid, a2=flags → MOD_OK or error
 #define SYS_MOD_LIST    30   // (no args) → count of loaded modules
 #define SYS_MOD_CALL    31   // a1=mid, a2=fn_id, a3=arg → result
 #define SYS_MOD_INFO    32   // a1=mid, a2=ptr to ModInfo → MOD_OK or error
@@ -49,6 +138,141 @@ typedef struct {
     uint32_t fn_offsets[MOD_MAX_EXPORTS]; // byte offsets into code section per function
 } __attribute__((packed)) ModHeader;      // 72 bytes total
 
+// Version 2 header: the v1 header (version = MOD_VERSION) plus a dependency
+// table of NUL-padded module names. Total size: 72+4+96 = 172 bytes
+typedef struct {
+    ModHeader base;
+    uint32_t  dep_count;                  // ≤ MOD_MAX_DEPS
+    char      deps[MOD_MAX_DEPS][MOD_NAME_LEN];
+} __attribute__((packed)) ModHeaderV2;    // 172 bytes total
+
+// One named export in a v3 header
+typedef struct {
+    char     name[MOD_NAME_LEN];          // NUL-padded symbol name
+    uint32_t fn_id;                       // index into fn_offsets
+    uint16_t version;
+    uint16_t flags;                       // MOD_SYM_*
+} __attribute__((packed)) ModExportDecl;  // 32 bytes
+
+// Version 3 header: v2 plus module flags and named exports.
+// Total size: 172+4+4+256 = 436 bytes
+typedef struct {
+    ModHeaderV2   v2;
+    uint32_t      mod_flags;              // MOD_FLAG_*
+    uint32_t      sym_count;              // ≤ MOD_MAX_EXPORTS
+    ModExportDecl syms[MOD_MAX_EXPORTS];
+} __attribute__((packed)) ModHeaderV3;    // 436 bytes total
+
+// Version 4 header: v3 plus lifecycle hooks, each an index into fn_offsets
+// or MOD_FN_NONE. init runs before the module goes Live and exit before it
+// is removed; both get the module's mid as argument, and a negative return
+// from init fails the load. Total size: 436+4+4 = 444 bytes
+typedef struct {
+    ModHeaderV3 v3;
+    uint32_t    init_fn;
+    uint32_t    exit_fn;
+} __attribute__((packed)) ModHeaderV4;    // 444 bytes total
+
+// Signed manifest appended after the code section. image_sha256 covers the
+// module header and code section; the signature is RSA-2048 PKCS#1 v1.5 over
+// the SHA-256 of all preceding manifest bytes.
+// Total size: 4+4+8+24+4+96+32+256 = 428 bytes
+typedef struct {
+    uint32_t magic;                       // MOD_MANIFEST_MAGIC
+    uint32_t version;                     // module version, major << 16 | minor
+    uint64_t caps;                        // CAP_* the loading process must hold
+    char     name[MOD_NAME_LEN];          // must match the header name
+    uint32_t dep_count;                   // must match the header dependencies
+    char     deps[MOD_MAX_DEPS][MOD_NAME_LEN];
+    uint8_t  image_sha256[32];            // header + code section
+    uint8_t  signature[MOD_SIG_LEN];
+} __attribute__((packed)) ModManifest;    // 428 bytes total
+
+// One declared parameter (Rust: modparam.rs). Privileges use the CLI scale
+// (0 root, 50 admin, 100 user): a caller at or below read_priv may read it,
+// at or below write_priv may change it at runtime.
+typedef struct {
+    char     name[MOD_PARAM_NAME_LEN];    // letters, digits and '_'
+    uint8_t  kind;                        // MOD_PARAM_INT / BOOL / STR
+    uint8_t  flags;                       // MOD_PARAM_RO
+    uint8_t  read_priv;
+    uint8_t  write_priv;
+    uint32_t offset;                      // variable in the code section:
+                                          // int32_t, or char[MOD_PARAM_STR_LEN]
+    int32_t  min;                         // int range, inclusive; string:
+    int32_t  max;                         // max = longest value (0: any)
+    int32_t  def;                         // int / bool default
+    char     def_str[MOD_PARAM_STR_LEN];  // string default
+} __attribute__((packed)) ModParamDecl;   // 52 bytes
+
+typedef struct {
+    uint32_t     count;                   // ≤ MOD_MAX_PARAMS
+    uint32_t     notify_off;              // code offset of the change
+                                          // callback, or MOD_PARAM_NO_NOTIFY
+    ModParamDecl decls[MOD_MAX_PARAMS];
+} __attribute__((packed)) ModParamTable;  // 424 bytes
+
+// Version 2 manifest: the v1 fields, the parameter table, then the
+// signature over everything before it. Total size: 172+424+256 = 852 bytes
+typedef struct {
+    uint32_t      magic;                  // MOD_MANIFEST_MAGIC_V2
+    uint32_t      version;
+    uint64_t      caps;
+    char          name[MOD_NAME_LEN];
+    uint32_t      dep_count;
+    char          deps[MOD_MAX_DEPS][MOD_NAME_LEN];
+    uint8_t       image_sha256[32];
+    ModParamTable params;
+    uint8_t       signature[MOD_SIG_LEN];
+} __attribute__((packed)) ModManifestV2;  // 852 bytes total
+
+// A resolved symbol (Rust: module_registry.rs ModSymbol)
+typedef struct {
+    char     name[MOD_NAME_LEN];
+    uint32_t mid;
+    uint32_t fn_id;
+    uint32_t addr;                        // absolute function address
+    uint16_t version;
+    uint16_t flags;                       // MOD_SYM_*
+} ModSymbol;                              // 40 bytes
+
+// Snapshot of one module (Rust: module_registry.rs ModRecord)
+typedef struct {
+    uint32_t mid;
+    uint32_t state;                       // MOD_STATE_*
+    char     name[MOD_NAME_LEN];          // NUL-padded, truncated to fit
+    uint32_t base_addr;
+    uint32_t code_size;
+    uint32_t fn_count;
+    uint32_t refcount;                    // active callers
+    uint32_t faults;                      // contained faults
+    uint32_t reserved;
+    uint64_t loaded_at;                   // tick of registration
+    uint64_t calls;                       // calls made via module_call
+} ModRecord;                              // 72 bytes
+
+// Registration request passed to the Rust registry (ModuleDesc)
+typedef struct {
+    uint32_t         mid;
+    const char      *name;
+    uint32_t         base_addr;
+    uint32_t         code_size;
+    uint32_t         fn_count;
+    uint32_t         flags;               // MOD_FLAG_*
+    const char      *deps;                // dep_count × MOD_NAME_LEN bytes
+    uint32_t         dep_count;
+    const ModSymbol *syms;
+    uint32_t         sym_count;
+    const uint8_t   *code;                // code_size bytes
+    const uint8_t   *image;               // header + code, image_len bytes
+    uint32_t         image_len;
+    const uint8_t   *manifest;            // ModManifest(V2), or NULL if none
+    uint32_t         manifest_len;        // bytes available at manifest
+    uint32_t         init_fn;             // hook addresses, 0 for none
+    uint32_t         exit_fn;
+    const char      *params;              // load-time assignments, or NULL
+} ModuleDesc;
+
 // Module info — filled by SYS_MOD_INFO / module_info()
 typedef struct {
     uint32_t mid;
@@ -69,20 +293,75 @@ typedef uint32_t (*ModFn)(uint32_t arg);
 void    module_init(void);
 
 // Register a module binary already present at [addr, addr+size).
-// Validates header, allocates a slot, notifies Rust registry.
-// Returns mid (0-based slot index) or MOD_ERR_*.
+// Validates header, allocates a slot, notifies Rust registry and runs the
+// init hook. Returns mid (0-based slot index) or MOD_ERR_*; if init fails
+// the module stays loaded in MOD_STATE_FAILED so it can be inspected.
 int32_t module_load(uint32_t addr, uint32_t size);
 
-// Unload module by mid. Returns MOD_OK or MOD_ERR_*.
-int32_t module_unload(uint32_t mid);
+// module_load with load-time parameter assignments ("name=value ...", may be
+// NULL), applied before init. An unknown name fails the load with
+// MOD_ERR_NOTFOUND, a bad value with MOD_ERR_INVAL or MOD_ERR_RANGE.
+int32_t module_load_params(uint32_t addr, uint32_t size, const char *params);
+
+// Module parameters by "module.param" name, on behalf of a caller at
+// privilege (CLI_PRIV_*). get writes the value as text and returns its
+// length; set returns MOD_OK, MOD_ERR_PERM (not privileged), MOD_ERR_ACCESS
+// (load-time only), MOD_ERR_INVAL / MOD_ERR_RANGE (bad value) or MOD_ERR_NOTFOUND.
+int32_t  module_param_get(const char *path, uint8_t privilege, char *buf, uint32_t len);
+int32_t  module_param_set(const char *path, const char *value, uint8_t privilege);
+
+// Render every parameter readable at privilege into buf. Returns length.
+uint32_t module_render_params(uint8_t privilege, char *buf, uint32_t len);
+
+// Unload module by mid (flags: MOD_UNLOAD_CASCADE). Returns MOD_OK or
+// MOD_ERR_*; MOD_ERR_BUSY while it has dependents or active callers.
+int32_t module_unload(uint32_t mid, uint32_t flags);
 
 // Print all loaded modules to serial. Returns count.
 int32_t module_list(void);
 
+// Resolve an exported symbol on behalf of requester (a mid, or
+// MOD_RESOLVE_KERNEL). Returns MOD_OK, MOD_ERR_NOTFOUND or MOD_ERR_PERM.
+int32_t module_resolve(const char *name, uint32_t requester, ModSymbol *out);
+
+// Iterate the symbol table: start with *cursor = 0. Returns MOD_OK with the
+// next symbol in *out, or MOD_ERR_NOTFOUND at the end.
+int32_t module_symbol_next(uint32_t *cursor, ModSymbol *out);
+
+// Iterate loaded modules: start with *cursor = 0. Returns MOD_OK with the
+// next module (in mid order) in *out, or MOD_ERR_NOTFOUND at the end.
+int32_t module_next(uint32_t *cursor, ModRecord *out);
+
+// Signature policy (Rust: modsign.rs). set returns 0 or -EINVAL.
+int32_t  module_sig_set_policy(uint32_t policy);
+uint32_t module_sig_get_policy(void);
+
+// Render the module dependency tree into buf (NUL-terminated). Returns length.
+uint32_t module_render_tree(char *buf, uint32_t len);
+
+// Render /proc/modules ("name size refcount users state base" per line)
+// into buf (NUL-terminated). Returns length.
+uint32_t module_render_proc(char *buf, uint32_t len);
+
 // Call exported function fn_id of module mid with arg.
-// Returns function's return value or MOD_ERR_*.
+// Returns function's return value or MOD_ERR_* (MOD_ERR_FAULT if it faulted,
+// MOD_ERR_ACCESS if the module is quarantined).
 int32_t module_call(uint32_t mid, uint32_t fn_id, uint32_t arg);
 
+// Exception path (arch code), for a kernel-mode fault: if a module call is
+// in progress, charge the fault to the module and return 1. The caller then
+// reports the exception as usual and calls module_fault_unwind(), which
+// returns from the interrupted call with MOD_ERR_FAULT.
+int     module_fault_enter(uint32_t vec);
+void    module_fault_unwind(void) __attribute__((noreturn));
+
+// A task slot is being torn down while the task may be inside a module call
+// (OOM kill of a preempted task): drop its call frames.
+void    module_task_exit(uint32_t task);
+
+// Called by the AI engine when its bad-module pattern fires.
+void    module_quarantine_suspect(uint64_t now);
+
 // Fill *out with info about module mid. Returns MOD_OK or MOD_ERR_*.
 int32_t module_info(uint32_t mid, ModInfo *out);
 
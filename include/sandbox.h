@@ -55,6 +55,9 @@ typedef uint64_t Capability;
 #define CAP_IPC_RECEIVE         0x0000000000002000ULL  // Receive IPC messages
 #define CAP_CHANNEL_CREATE      0x0000000000004000ULL  // Create IPC channels
 
+// Filesystem Capabilities
+#define CAP_FILE_IO             0x0000000000008000ULL  // File I/O through uring
+
 // Module Capabilities
 #define CAP_MODULE_LOAD         0x0000000000010000ULL  // Load kernel modules
 #define CAP_MODULE_UNLOAD       0x0000000000020000ULL  // Unload kernel modules
@@ -71,10 +74,11 @@ typedef uint64_t Capability;
 #define CAP_SYSTEM_DEFAULT      (CAP_SERIAL_WRITE | CAP_SERIAL_READ | \
                                  CAP_ALLOC_MEMORY | CAP_MAP_MEMORY | \
                                  CAP_IPC_SEND | CAP_IPC_RECEIVE | \
-                                 CAP_SYS_DEBUG)
+                                 CAP_FILE_IO | CAP_SYS_DEBUG)
 
 #define CAP_USER_DEFAULT        (CAP_SERIAL_WRITE | CAP_ALLOC_MEMORY | \
-                                 CAP_IPC_SEND | CAP_IPC_RECEIVE)
+                                 CAP_IPC_SEND | CAP_IPC_RECEIVE | \
+                                 CAP_FILE_IO)
 
 // ---- Memory Regions ----
 // Kernel space: [0x0, 0x100000)
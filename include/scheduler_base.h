@@ -4,6 +4,7 @@
 #include <stddef.h>
 #include <stdint.h>
 #include <stdbool.h>
+#include "kerrno.h"
 
 #ifdef __cplusplus
 extern "C" {
@@ -60,14 +61,32 @@ typedef struct {
     size_t  mem_virt;       // Virtual memory size (bytes)
     uint64_t uptime_secs;   // Seconds since process creation
     char    command[32];
+    size_t  kstack_peak;    // Kernel stack high-water mark (bytes)
+    size_t  ustack_peak;    // User stack high-water mark (bytes)
 } PsRow;
 
+// CpuContext — registers saved at syscall entry, for the register dump.
+// On i386 the r* fields hold the e* registers and r8-r15 are 0.
+typedef struct {
+    size_t rip, rsp, rbp;
+    size_t rax, rbx, rcx, rdx, rsi, rdi;
+    size_t r8, r9, r10, r11, r12, r13, r14, r15;
+    size_t rflags;
+} CpuContext;
+
 // Forward declaration
 struct Scheduler;
 
+// Calls returning int32_t give 0 on success or -errno (kerrno.h):
+// -ESRCH for an unknown pid, -EINVAL for a bad argument or process state.
+
 // ps FFI
-size_t scheduler_get_pid_list(const struct Scheduler *sched, size_t *pids_out, size_t max_count);
-bool   scheduler_get_ps_row(const struct Scheduler *sched, size_t pid, PsRow *out);
+size_t  scheduler_get_pid_list(const struct Scheduler *sched, size_t *pids_out, size_t max_count);
+int32_t scheduler_get_ps_row(const struct Scheduler *sched, size_t pid, PsRow *out);
+
+// Fold sampled stack usage (bytes) into the process's high-water marks
+int32_t scheduler_record_stack_usage(struct Scheduler *sched, size_t pid,
+                                     size_t kernel_used, size_t user_used);
 
 // Kernel-side accessor (implemented in kernel_x86.c / kernel_x64.c)
 struct Scheduler *get_kernel_scheduler(void);
@@ -75,22 +94,34 @@ struct Scheduler *get_kernel_scheduler(void);
 // FFI functions
 struct Scheduler* scheduler_new(void);
 void scheduler_free(struct Scheduler* ptr);
-size_t scheduler_create_process(struct Scheduler* sched, uint8_t priority, const char* command);
+// Returns the new pid, or -EINVAL for a NULL scheduler or command.
+ssize_t scheduler_create_process(struct Scheduler* sched, uint8_t priority, const char* command);
 size_t scheduler_schedule(struct Scheduler* sched);
-void scheduler_terminate_current(struct Scheduler* sched, int32_t exit_code);int32_t scheduler_get_exit_code(const struct Scheduler* sched, size_t pid);bool scheduler_get_process_info(const struct Scheduler* sched, size_t pid, FfiProcessInfo* out_info);
+void scheduler_terminate_current(struct Scheduler* sched, int32_t exit_code);int32_t scheduler_get_exit_code(const struct Scheduler* sched, size_t pid);int32_t scheduler_get_process_info(const struct Scheduler* sched, size_t pid, FfiProcessInfo* out_info);
 void scheduler_block_current(struct Scheduler* sched, const char* reason);
-bool scheduler_wake_process(struct Scheduler* sched, size_t pid);
-bool scheduler_suspend_process(struct Scheduler* sched, size_t pid);
-bool scheduler_resume_process(struct Scheduler* sched, size_t pid);
-bool scheduler_kill_process(struct Scheduler* sched, size_t pid);
+int32_t scheduler_wake_process(struct Scheduler* sched, size_t pid);
+// 1 if pid is Waiting (parked by a blocking syscall), else 0
+int32_t scheduler_is_waiting(const struct Scheduler* sched, size_t pid);
+int32_t scheduler_suspend_process(struct Scheduler* sched, size_t pid);
+int32_t scheduler_resume_process(struct Scheduler* sched, size_t pid);
+int32_t scheduler_kill_process(struct Scheduler* sched, size_t pid);
 size_t scheduler_get_process_count(const struct Scheduler* sched);
 size_t scheduler_get_running_process_count(const struct Scheduler* sched);
 size_t scheduler_get_standby_process_count(const struct Scheduler* sched);
 size_t scheduler_get_waiting_process_count(const struct Scheduler* sched);
-bool scheduler_get_scheduler_stats(const struct Scheduler* sched, SchedulerStats* out_stats);
+int32_t scheduler_get_scheduler_stats(const struct Scheduler* sched, SchedulerStats* out_stats);
 size_t scheduler_cleanup_zombies(struct Scheduler* sched);
-bool scheduler_set_priority(struct Scheduler* sched, size_t pid, uint8_t priority);
-bool scheduler_set_nice(struct Scheduler* sched, size_t pid, int8_t nice);
+int32_t scheduler_set_priority(struct Scheduler* sched, size_t pid, uint8_t priority);
+int32_t scheduler_set_nice(struct Scheduler* sched, size_t pid, int8_t nice);
+// Region usermem.rs accepts syscall pointers in, [base, base+size)
+int32_t scheduler_set_user_region(struct Scheduler* sched, size_t pid, size_t base, size_t size);
+// Make ppid the parent of pid; pid joins the parent's process group and
+// takes its uid
+int32_t scheduler_set_parent(struct Scheduler* sched, size_t pid, size_t ppid);
+// uid pid acts as for file permission checks
+int32_t scheduler_set_uid(struct Scheduler* sched, size_t pid, uint16_t uid);
+// Store the registers pid had at syscall entry
+int32_t scheduler_save_context(struct Scheduler* sched, size_t pid, const CpuContext* ctx);
 const void* scheduler_get_current_process(const struct Scheduler* sched);
 
 
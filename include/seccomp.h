@@ -0,0 +1,63 @@
+#ifndef VERNISOS_SECCOMP_H
+#define VERNISOS_SECCOMP_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Per-process syscall filters (Rust: seccomp.rs)
+//
+// A filter is up to SECCOMP_MAX_RULES rules and a default action. The first
+// rule whose number and argument comparison match decides; otherwise the
+// default applies. Filters stack (at most SECCOMP_MAX_DEPTH per process) and
+// the most restrictive verdict of the stack wins, so installing a filter can
+// only tighten. Children inherit the stack at fork (scheduler_set_parent).
+//
+// Evaluated after the capability check for every syscall: by syscall_handler
+// for table calls and by syscall_gate for the ones the arch dispatcher
+// handles itself. Refusals are reported as DENY events "pid|num|name".
+// =============================================================================
+
+#define SECCOMP_MAX_RULES   32
+#define SECCOMP_MAX_DEPTH   8
+
+#define SECCOMP_NR_ANY      0xFFFFFFFFu   // rule matches every syscall
+#define SECCOMP_ARG_NONE    0xFF          // rule has no argument comparison
+
+// Argument comparisons: args[arg] <op> value
+#define SECCOMP_OP_EQ          0
+#define SECCOMP_OP_NE          1
+#define SECCOMP_OP_LT          2
+#define SECCOMP_OP_LE          3
+#define SECCOMP_OP_GT          4
+#define SECCOMP_OP_GE          5
+#define SECCOMP_OP_BITS_SET    6    // (arg & value) != 0
+#define SECCOMP_OP_BITS_CLEAR  7    // (arg & value) == 0
+
+// Actions
+#define SECCOMP_RET_KILL     0x00000000u   // SIGKILL, process marked killed, -EPERM
+#define SECCOMP_RET_TRAP     0x00030000u   // SIGSYS, -ENOSYS
+#define SECCOMP_RET_ERRNO(e) (0x00050000u | ((uint32_t)(e) & 0xFFFFu))   // -e
+#define SECCOMP_RET_ALLOW    0x7FFF0000u
+
+typedef struct {
+    uint32_t nr;        // syscall number or SECCOMP_NR_ANY
+    uint8_t  arg;       // argument index 0-2 or SECCOMP_ARG_NONE
+    uint8_t  op;        // SECCOMP_OP_*
+    uint16_t _pad;
+    uint32_t action;    // SECCOMP_RET_*
+    size_t   value;
+} seccomp_rule_t;
+
+struct Scheduler;
+
+// Stack a filter on pid. 0, -EINVAL (bad rule/action), -ESRCH, -E2BIG (too
+// many rules), -ENOSPC (stack full) or -ENOMEM.
+int32_t seccomp_install(struct Scheduler *sched, size_t pid, const seccomp_rule_t *rules,
+                        size_t count, uint32_t default_action);
+
+// Filters stacked on pid, or -ESRCH.
+int32_t seccomp_depth(const struct Scheduler *sched, size_t pid);
+
+#endif // VERNISOS_SECCOMP_H
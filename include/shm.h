@@ -0,0 +1,82 @@
+#ifndef VERNISOS_SHM_H
+#define VERNISOS_SHM_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Shared memory segments (Rust: shm.rs)
+//
+// Named, page-aligned segments that several processes can map for zero-copy
+// data exchange. Rust keeps names, handles, refcounts and permission checks;
+// the arch code installs the pages into the task's address space.
+//
+// Capabilities: create needs CAP_ALLOC_MEMORY, opening for read needs
+// CAP_IPC_RECEIVE, opening for write also needs CAP_IPC_SEND. Non-owners
+// additionally need the segment's SHM_MODE_OTHER_* bit.
+// =============================================================================
+
+// ---- Constants ----
+#define SHM_NAME_MAX          32            // including null terminator
+#define SHM_MAX_SEGMENTS      32
+#define SHM_MAX_SIZE          (1024 * 1024)
+
+#define SHM_MODE_OTHER_READ   0x1           // other processes may map read-only
+#define SHM_MODE_OTHER_WRITE  0x2           // other processes may map read/write
+
+#define SHM_O_WRITE           0x1           // shm_open flag: request write access
+
+// ---- Return codes (negative errno, see kerrno.h) ----
+#define SHM_OK                0
+#define SHM_ERR_INVAL       (-EINVAL)       // bad argument / unknown mapping
+#define SHM_ERR_NOENT       (-ENOENT)       // no such segment / no handle
+#define SHM_ERR_EXIST       (-EEXIST)       // name already in use
+#define SHM_ERR_PERM        (-EACCES)       // capability or mode check failed
+#define SHM_ERR_NOMEM       (-ENOMEM)       // heap exhausted
+#define SHM_ERR_LIMIT       (-ENOSPC)       // too many segments / VMAs
+
+// ---- Syscall numbers (follow MOD 28-32) ----
+#define SYS_SHM_CREATE  33   // a1=name, a2=size, a3=mode → id (or error)
+#define SYS_SHM_OPEN    34   // a1=name, a2=SHM_O_* flags → id (or error)
+#define SYS_SHM_MAP     35   // a1=id, a2=PROT_* → user address (or error)
+#define SYS_SHM_UNMAP   36   // a1=address → SHM_OK or error
+#define SYS_SHM_UNLINK  37   // a1=name → SHM_OK or error
+#define SYS_SHM_CLOSE   38   // a1=id → SHM_OK or error
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// `sched` is the kernel scheduler (get_kernel_scheduler()).
+int32_t  shm_create(void *sched, size_t pid, const char *name, size_t size, uint32_t mode);
+int32_t  shm_open(void *sched, size_t pid, const char *name, uint8_t write);
+
+// Page-rounded size of segment id, or 0 if it does not exist.
+size_t   shm_size(uint32_t id);
+
+// Record a mapping at vaddr; *out_base receives the segment's physical base.
+int32_t  shm_map(void *sched, size_t pid, uint32_t id, size_t vaddr, uint8_t write,
+                 size_t *out_base);
+
+// Drop the mapping at vaddr. Returns the unmapped size or SHM_ERR_*.
+int32_t  shm_unmap(void *sched, size_t pid, size_t vaddr);
+
+int32_t  shm_close(size_t pid, uint32_t id);
+int32_t  shm_unlink(void *sched, size_t pid, const char *name);
+
+// Duplicate parent's handles and mappings into child (fork).
+void     shm_fork(void *sched, size_t parent_pid, size_t child_pid);
+
+// Drop all handles/mappings of pid (exit/kill); pages must already be unmapped.
+// sched may be NULL (OOM kill): the shared-size accounting is then skipped.
+uint32_t shm_release_process(void *sched, size_t pid);
+
+// Render a segment table into buf (NUL-terminated). Returns length.
+uint32_t shm_render_info(uint8_t *buf, uint32_t buf_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_SHM_H
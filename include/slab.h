@@ -0,0 +1,45 @@
+#ifndef VERNISOS_SLAB_H
+#define VERNISOS_SLAB_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// =============================================================================
+// Slab / object-cache allocator (Rust: slab.rs)
+//
+// Fixed-size kernel objects are carved from page-sized slabs taken from the
+// Rust kernel heap and recycled through per-cache free lists. The caches
+// below are created when the heap is initialised; C code may add its own
+// with slab_cache_create().
+// =============================================================================
+
+#define SLAB_CACHE_PCB           0   // scheduler ProcessControlBlock
+#define SLAB_CACHE_EVENT_RECORD  1   // AI engine EventRecord
+
+// Create or look up a named cache (name at most 16 bytes). Returns cache id,
+// or -1 on error.
+int32_t  slab_cache_create(const char *name, uint32_t obj_size, uint32_t align);
+
+// Allocate one object from cache id. Returns NULL if the heap is exhausted.
+void    *slab_cache_alloc(uint32_t id);
+
+// Return an object to its cache. Returns 0, or -1 if ptr is not from that cache.
+int32_t  slab_cache_free(uint32_t id, void *ptr);
+
+// Number of live objects in cache id.
+uint32_t slab_cache_active(uint32_t id);
+
+// Release completely unused slabs back to the heap. Returns bytes freed.
+uint32_t slab_reap_all(void);
+
+// Render /proc/slabinfo text into buf (NUL-terminated). Returns length.
+uint32_t slab_render_info(uint8_t *buf, uint32_t buf_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_SLAB_H
@@ -0,0 +1,49 @@
+#ifndef VERNISOS_STRACE_H
+#define VERNISOS_STRACE_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Per-process syscall tracing (Rust: strace.rs)
+//
+// Attach to a pid, a process group or everything; each traced call that
+// passes the number filter is kept in the process's ring (number, args,
+// return value, start tick, duration in ticks). The arch syscall paths call
+// strace_record() after every syscall; it returns at once while nothing is
+// attached. Optionally each traced call is also fed to the in-kernel AI
+// engine as a SYSCALL event.
+// =============================================================================
+
+#define STRACE_RING_SIZE     64     // records kept per process
+#define STRACE_MAX_PROCS     16     // processes with a ring
+#define STRACE_MAX_TARGETS   8
+
+#define STRACE_TARGET_PID    0
+#define STRACE_TARGET_GROUP  1
+#define STRACE_TARGET_ALL    2      // detach(ALL) stops all tracing
+
+// 0, -EINVAL, or -ENOSPC when all target slots are used.
+int32_t strace_attach(uint32_t kind, size_t id);
+// 0, -EINVAL, or -ENOENT if the target was not attached.
+int32_t strace_detach(uint32_t kind, size_t id);
+
+// Number filter; every syscall is traced by default. 0 or -EINVAL.
+int32_t strace_set_filter(uint32_t num, uint8_t on);
+void    strace_filter_all(uint8_t on);
+
+void    strace_set_ai_forward(uint8_t on);
+
+// start_tick: kernel_get_ticks() when the syscall was entered.
+void    strace_record(size_t pid, uint32_t num, size_t a1, size_t a2, size_t a3,
+                      intptr_t ret, uint32_t start_tick);
+
+// strace-style lines for pid, oldest first; pid 0 renders the tracer state
+// and per-process counters. Returns bytes written (excluding NUL).
+uint32_t strace_render(size_t pid, uint8_t *buf, uint32_t buf_len);
+
+// Free the ring of pid, or all rings for pid 0.
+void    strace_clear(size_t pid);
+
+#endif // VERNISOS_STRACE_H
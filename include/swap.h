@@ -0,0 +1,81 @@
+#ifndef VERNISOS_SWAP_H
+#define VERNISOS_SWAP_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Anonymous-page swap (Rust: swap.rs)
+//
+// Rust runs a second-chance clock over tracked anonymous user pages and keeps
+// the swap-slot bitmap; the arch code supplies PTE access and swap-file I/O
+// through swap_ops_t. The swap file is a preallocated VernisFS file (/swap)
+// accessed with kfs_read_at()/kfs_write_at().
+// =============================================================================
+
+#define SWAP_FILE_PATH      "/swap"
+#define SWAP_FILE_PAGES     64
+#define SWAP_MAX_RESIDENT   1024
+#define SWAP_MAX_SLOTS      4096
+
+// ---- Return codes (negative errno, see kerrno.h) ----
+#define SWAP_OK              0
+#define SWAP_ERR_INVAL     (-EINVAL)    // bad argument / already enabled
+#define SWAP_ERR_NOENT     (-ENOENT)    // address not swapped out / nothing to evict
+#define SWAP_ERR_IO        (-EIO)       // swap file read/write failed
+#define SWAP_ERR_NOMEM     (-ENOMEM)    // no free slot / heap exhausted
+
+typedef struct {
+    // Test and clear the PTE accessed bit at vaddr if it maps frame.
+    uint8_t (*page_referenced)(size_t vaddr, size_t frame);
+    // Clear the PTE at vaddr if it still maps frame; 0 if it did not.
+    uint8_t (*page_unmap)(size_t vaddr, size_t frame);
+    // Copy one page between frame and swap slot. 0 on success.
+    int32_t (*write_slot)(uint32_t slot, size_t frame);
+    int32_t (*read_slot)(uint32_t slot, size_t frame);
+} swap_ops_t;
+
+typedef struct {
+    uint32_t slots_total;
+    uint32_t slots_used;
+    uint32_t resident_pages;
+    uint32_t swap_outs;
+    uint32_t swap_ins;
+    uint32_t second_chances;
+    uint32_t io_errors;
+    uint32_t evict_failures;
+} swap_stats_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+int32_t  swap_init(uint32_t slot_count, const swap_ops_t *ops);
+uint8_t  swap_enabled(void);
+
+// Track a freshly mapped anonymous page of pid. Returns 0 if not tracked.
+uint8_t  swap_track(size_t pid, size_t vaddr, size_t frame);
+
+// Evict one page to swap. Returns the freed frame, or 0 if none.
+size_t   swap_evict(void);
+
+uint8_t  swap_is_swapped(size_t vaddr);
+
+// Read the page swapped out at vaddr into frame (caller maps it).
+int32_t  swap_in(size_t vaddr, size_t frame);
+
+uint32_t swap_untrack_range(size_t start, size_t len);
+uint32_t swap_release_process(size_t pid);
+
+uint8_t  swap_get_stats(swap_stats_t *out);
+uint32_t swap_render_info(uint8_t *buf, uint32_t buf_len);
+
+// Arch code: reserve SWAP_FILE_PATH and enable swap. SWAP_OK or SWAP_ERR_*.
+int      kernel_swap_on(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_SWAP_H
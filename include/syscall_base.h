@@ -3,6 +3,7 @@
 
 #include <stddef.h>
 #include <stdint.h>
+#include "kerrno.h"
 
 #ifdef __cplusplus
 extern "C" {
@@ -16,17 +17,65 @@ typedef long ssize_t;
 // Syscall numbers used by user-mode code
 #define SYS_READ   64u   // read(path_ptr, user_buf_ptr, max_len)
 #define SYS_WRITE  65u   // write(path_ptr, user_buf_ptr, len)
+#define SYS_SECCOMP 79u  // seccomp(rules_ptr, count, default_action), see seccomp.h
+
+// Rust syscall table (syscall.rs): numbers 0..SYSCALL_MAX-1
+// Every syscall returns a non-negative value or -errno (kerrno.h).
+#define SYSCALL_MAX        128
+#define SYSCALL_ERR_PERM   (-EPERM)    // caller lacks a required capability
+#define SYSCALL_ERR_NOSYS  (-ENOSYS)   // no handler registered
+
+// C handler; receives the syscall number so one function can serve a range.
+// Returns a value or -errno.
+typedef ssize_t (*syscall_native_fn)(uint32_t num, size_t a1, size_t a2, size_t a3);
+
+// Install the Rust built-ins (getpid, kill, dump_*). Call once at boot.
+void syscall_init(void);
+
+// Register a C handler. name must be a string literal; argc <= 3 (extra
+// arguments reach the handler as 0); caps are CAP_* bits the caller needs.
+// Returns 0, -EEXIST if num is taken, or -EINVAL if num is out of range or an
+// argument is invalid.
+int32_t syscall_register(uint32_t num, const char *name, uint8_t argc,
+                         uint64_t caps, syscall_native_fn handler);
+
+// Name a syscall the arch trap dispatcher handles itself (exit, fork, ...),
+// so it shows in the syscall dump and strace. Same return values as above.
+int32_t syscall_register_trap(uint32_t num, const char *name, uint8_t argc);
+
+// Dispatch through the table for the task pid (0 = kernel context):
+// capability check, seccomp filter, then the handler.
+ssize_t syscall_handler(size_t pid, uint32_t sys_num, size_t arg1, size_t arg2, size_t arg3);
+
+// Capability check and seccomp filter for a trap entry, run by the arch
+// dispatcher before handling it. Returns 0 to go ahead or the -errno to
+// return (already counted); 0 for any other number.
+ssize_t syscall_gate(size_t pid, uint32_t sys_num, size_t arg1, size_t arg2, size_t arg3);
+
+// Count a syscall the arch trap dispatcher handled itself (ret: its result),
+// so the dump's call/error counters cover every syscall.
+void syscall_account(uint32_t num, ssize_t ret);
 
 // FFI functions
-ssize_t syscall_handler(uint32_t sys_num, size_t arg1, size_t arg2, size_t arg3);
 ssize_t ffi_sys_write(const uint8_t* ptr, size_t len);
 void ffi_sys_exit(int32_t code);
 ssize_t ffi_sys_getpid(void);
-void ffi_sys_dump_registers(void);
-void ffi_sys_dump_scheduler(void);
-void ffi_sys_dump_memory(size_t addr);
-void ffi_sys_dump_syscalls(void);
-void ffi_sys_dump_all(void);
+
+// Debug dumps into a kernel buffer, NUL-terminated. Return the bytes
+// written without the NUL, or -EINVAL for a null/empty buffer. The
+// dump_* syscalls (10-14) render the same text into a user buffer:
+// (buf, len) plus the address for dump_memory, capped at SYS_DUMP_MAX.
+#define SYS_DUMP_MAX           8192
+#define SYS_DUMP_MEMORY_BYTES  256
+// pid 0: the running task
+ssize_t ffi_sys_dump_registers(size_t pid, char *buf, size_t len);
+ssize_t ffi_sys_dump_scheduler(char *buf, size_t len);
+// count bytes (at most SYS_DUMP_MEMORY_BYTES) from addr, 16 per line; stops
+// at the first unreadable or, in kernel context, unmapped line
+ssize_t ffi_sys_dump_memory(size_t addr, size_t count, char *buf, size_t len);
+// Table, per-syscall call/error/denied counters and the strace summary
+ssize_t ffi_sys_dump_syscalls(char *buf, size_t len);
+ssize_t ffi_sys_dump_all(char *buf, size_t len);
 
 #ifdef __cplusplus
 }
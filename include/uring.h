@@ -0,0 +1,97 @@
+#ifndef VERNISOS_URING_H
+#define VERNISOS_URING_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Asynchronous submission/completion rings (Rust: uring.rs)
+//
+// A process maps a writable shm segment and registers it as a ring with
+// SYS_URING_SETUP. The segment starts with a UringHeader, followed by the
+// submission ring, the completion ring and a data area; the header gives the
+// offsets. The process queues UringSqe entries (advancing sq_tail) and calls
+// SYS_URING_ENTER; finished operations appear as UringCqe entries (reaped by
+// advancing cq_head). Buffers and paths are offsets into the data area.
+//
+// IPC ops need CAP_IPC_SEND / CAP_IPC_RECEIVE and KFS ops CAP_FILE_IO, else
+// they complete with -EPERM. KFS ops are also checked against the mode bits
+// for the owner's uid, and system files (the swap file) are refused; both
+// complete with -EACCES.
+//
+// Operations still pending after SYS_URING_ENTER are run from the kernel
+// idle loop (uring_poll). A caller that asked for min_complete completions
+// is set Waiting and taken off the CPU until they are posted, then woken.
+// =============================================================================
+
+#define URING_MAX_RINGS        8
+#define URING_MAX_ENTRIES      256    // per ring, power of two
+#define URING_AI_TIMEOUT_SECS  5
+
+// ---- Syscall numbers (follow SYS_SECCOMP 79) ----
+#define SYS_URING_SETUP    80   // a1=shm id, a2=sq entries, a3=cq entries → ring id
+#define SYS_URING_ENTER    81   // a1=ring id, a2=to_submit, a3=min_complete → CQEs ready
+#define SYS_URING_DESTROY  82   // a1=ring id → 0; pending ops are abandoned
+
+// ---- Opcodes ----
+#define URING_OP_NOP        0
+#define URING_OP_IPC_SEND   1   // target=dst pid, addr/len=payload (<= 48), arg=type
+#define URING_OP_IPC_RECV   2   // target=queue id, addr=IpcMessage out (len >= 64)
+#define URING_OP_KFS_READ   3   // target=path offset, addr/len=buffer, arg=file offset
+#define URING_OP_KFS_WRITE  4   // target=path offset, addr/len=data, arg=file offset
+#define URING_OP_AI_QUERY   5   // addr/len=query in, NUL-terminated answer out
+#define URING_OP_SLEEP      6   // arg=ticks
+
+typedef struct {
+    uint32_t sq_head;     // kernel
+    uint32_t sq_tail;     // process
+    uint32_t sq_mask;
+    uint32_t sq_off;
+    uint32_t cq_head;     // process
+    uint32_t cq_tail;     // kernel
+    uint32_t cq_mask;
+    uint32_t cq_off;
+    uint32_t data_off;
+    uint32_t data_len;
+    uint32_t _reserved[6];
+} UringHeader;            // 64 bytes
+
+typedef struct {
+    uint8_t  opcode;
+    uint8_t  flags;
+    uint16_t _pad;
+    uint32_t target;
+    uint32_t addr;        // segment offsets
+    uint32_t len;
+    uint64_t arg;
+    uint64_t user_data;
+} __attribute__((packed)) UringSqe;   // 32 bytes
+
+typedef struct {
+    uint64_t user_data;
+    int32_t  res;         // result or -errno
+    uint32_t flags;
+} __attribute__((packed)) UringCqe;   // 16 bytes
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// `sched` is the kernel scheduler (get_kernel_scheduler()).
+// Run pending operations and wake owners waiting on them; call from the
+// idle loop with interrupts off.
+void     uring_poll(void *sched, uint32_t now);
+
+// Drop all rings of pid (exit/kill). Returns how many were dropped. sched may
+// be NULL (OOM kill).
+uint32_t uring_release_process(void *sched, size_t pid);
+
+// Render the ring table into buf (NUL-terminated). Returns length.
+uint32_t uring_render_info(uint8_t *buf, uint32_t buf_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // VERNISOS_URING_H
@@ -0,0 +1,56 @@
+#ifndef VERNISOS_USERMEM_H
+#define VERNISOS_USERMEM_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include "kerrno.h"
+
+// =============================================================================
+// Validated user-memory access (Rust: usermem.rs)
+//
+// Syscall handlers never dereference a user pointer. Each page of a range is
+// checked against the calling process — PCB user region, sbrk heap or mmap
+// VMA — and its PTE (present + user, + writable for writes) before it is
+// copied. Non-present pages are faulted in through usermem_ops_t and
+// re-checked. Failures return -EFAULT.
+//
+// The arch code installs usermem_ops_t at boot; kernel tasks (pid 0) only get
+// the null / wrap-around checks.
+// =============================================================================
+
+#define USERMEM_PTE_PRESENT   0x01
+#define USERMEM_PTE_WRITABLE  0x02
+#define USERMEM_PTE_USER      0x04
+
+typedef struct {
+    size_t  start;      // [start, end)
+    size_t  end;
+    uint8_t writable;
+} usermem_range_t;
+
+typedef struct {
+    // Pid of the calling user task, 0 in kernel context.
+    size_t   (*current_pid)(void);
+    // sbrk heap or VMA of the current task containing vaddr; 0 if none.
+    uint8_t  (*find_range)(size_t vaddr, usermem_range_t *out);
+    // Low flag bits of the PTE (or large-page entry) mapping vaddr, 0 if
+    // there is none.
+    uint32_t (*page_flags)(size_t vaddr);
+    // Make the page at vaddr present (swap-in / demand fill). 1 on success.
+    uint8_t  (*fault_in)(size_t vaddr);
+} usermem_ops_t;
+
+int32_t usermem_init(const usermem_ops_t *ops);
+
+// 0 if the caller may access [uaddr, uaddr+len) (for writing if write != 0).
+int32_t usermem_access_ok(size_t uaddr, size_t len, uint8_t write);
+
+// 0 or -errno. Nothing is copied past the first bad page.
+int32_t usermem_copy_from_user(void *dst, size_t usrc, size_t len);
+int32_t usermem_copy_to_user(size_t udst, const void *src, size_t len);
+
+// Copy a NUL-terminated string of at most max bytes (NUL included). Returns
+// the length without the NUL, -EFAULT, or -ENAMETOOLONG if it did not fit.
+intptr_t usermem_strncpy_from_user(char *dst, size_t usrc, size_t max);
+
+#endif // VERNISOS_USERMEM_H
@@ -114,6 +114,15 @@ int vfs_write_file(const char *path, const uint8_t *data, size_t len);
 // Returns new total size, or -1 on error.
 int vfs_append_file(const char *path, const uint8_t *data, size_t len);
 
+// Reserve a contiguous extent of at least size bytes without writing data.
+// Keeps an existing file if it is large enough. Returns the file size or -1.
+int vfs_reserve_file(const char *path, uint32_t size);
+
+// Positional I/O within an existing file (offset/len multiples of 512).
+// Returns len, or -1 on error. Files are never resized.
+int vfs_read_at(const char *path, uint32_t offset, uint8_t *buf, uint32_t len);
+int vfs_write_at(const char *path, uint32_t offset, const uint8_t *data, uint32_t len);
+
 // Create a directory entry. Returns 0 on success, -1 on error.
 int vfs_mkdir(const char *path);
 
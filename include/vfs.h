@@ -33,6 +33,15 @@ int kfs_delete_file(const char *path);
 int kfs_list_dir(const char *dir_path, char out[][VFS_MAX_FILENAME], int max);
 uint16_t kfs_file_count(void);
 
+// Preallocated files with sector-granular positional I/O (swap file). A
+// reserved file is VFS_FLAG_SYSTEM: the path-based calls above refuse it, and
+// these do not check permissions, so they are for kernel use only.
+int kfs_reserve_file(const char *path, uint32_t size);
+// Nonzero if path is a VFS_FLAG_SYSTEM file.
+int kfs_path_is_system(const char *path);
+int kfs_read_at(const char *path, uint32_t offset, uint8_t *buf, uint32_t len);
+int kfs_write_at(const char *path, uint32_t offset, const uint8_t *data, uint32_t len);
+
 // Phase 47: File permissions
 int kfs_chmod(const char *path, uint16_t mode);
 int kfs_chown(const char *path, uint16_t uid, uint16_t gid);
@@ -11,6 +11,15 @@
 #include "userdb.h"
 #include "auditlog.h"
 #include "klog.h"
+#include "heap.h"
+#include "shm.h"
+#include "swap.h"
+#include "kerrno.h"
+#include "usermem.h"
+#include "strace.h"
+#include "uring.h"
+#include "kinfo.h"
+#include "console.h"
 
 #include "tcp.h"
 
@@ -50,19 +59,19 @@ struct boot_info {
     uint32_t fb_pitch;
     uint32_t fb_bpp;
     uint32_t fb_type;   // 0 = VGA text, 1 = framebuffer
+    // VBE channel layout (mode info RedMaskSize.. BlueFieldPosition), 0 = unknown
+    struct fb_channels {
+        uint8_t red_size, red_pos;
+        uint8_t green_size, green_pos;
+        uint8_t blue_size, blue_pos;
+    } fb_channels;
+    uint8_t  fb_reserved[2];
 };
 static int display_mode = 0;  // 0 = VGA text, 1 = framebuffer, 2 = GUI
 
-// Rust framebuffer/console FFI
-extern void fb_init(uint32_t addr, uint32_t width, uint32_t height,
-                    uint32_t pitch, uint32_t bpp);
-extern void console_init(uint32_t width, uint32_t height);
-extern void console_putchar(uint8_t c);
-extern void console_clear(void);
-extern void console_set_color_vga(uint8_t fg, uint8_t bg);
-extern void console_set_pos(uint32_t row, uint32_t col);
-extern void console_get_pos(uint32_t *row, uint32_t *col);
-extern void console_clear_to_eol(uint32_t row, uint32_t col);
+// Rust framebuffer FFI (console: console.h)
+extern int32_t fb_init(uint32_t addr, uint32_t width, uint32_t height,
+                       uint32_t pitch, uint32_t bpp, const struct fb_channels *channels);
 
 // Rust mouse FFI
 extern void mouse_init(uint32_t screen_w, uint32_t screen_h);
@@ -326,7 +335,7 @@ void serial_print_dec(uint32_t val) {
 }
 
 // =============================================================================
-// GDT — 6 entries (32-bit flat) + TSS
+// GDT — 6 entries (32-bit flat) + TSS + double-fault TSS
 // =============================================================================
 
 // x86 TSS structure (software portion only; I/O bitmap not used)
@@ -345,6 +354,13 @@ typedef struct {
 
 static Tss32 kernel_tss32 __attribute__((aligned(16)));
 
+// #DF is delivered through a task gate onto its own TSS and stack, so a
+// kernel stack overflow (ESP inside a guard page) can still be reported.
+#define DF_STACK_SIZE 4096
+static Tss32   df_tss32 __attribute__((aligned(16)));
+static uint8_t df_stack32[DF_STACK_SIZE] __attribute__((aligned(16)));
+static void double_fault_task_32(void);
+
 typedef struct {
     uint16_t limit_low;
     uint16_t base_low;
@@ -359,7 +375,7 @@ typedef struct {
     uint32_t base;
 } __attribute__((packed)) GdtPointer32;
 
-static GdtEntry32 gdt32[6];
+static GdtEntry32 gdt32[7];
 static GdtPointer32 gdt32_ptr;
 
 static void gdt32_set(int idx, uint32_t base, uint32_t limit,
@@ -391,6 +407,26 @@ static void gdt_init(void) {
     // access byte: P=1,DPL=0,0,type=10B1 (32-bit TSS available) = 0x89
     gdt32_set(5, tss_base, tss_limit, 0x89, 0x00);
 
+    // Double-fault TSS (selector 0x30). Paging is already on, so CR3 holds
+    // the kernel page directory the handler must run with.
+    for (int i = 0; i < (int)sizeof(df_tss32); i++)
+        ((uint8_t *)&df_tss32)[i] = 0;
+    {
+        uint32_t cr3;
+        __asm__ volatile("mov %%cr3, %0" : "=r"(cr3));
+        df_tss32.cr3 = cr3;
+    }
+    df_tss32.eip    = (uint32_t)double_fault_task_32;
+    df_tss32.eflags = 0x2;                                  // IF=0
+    df_tss32.esp    = (uint32_t)(df_stack32 + DF_STACK_SIZE);
+    df_tss32.cs     = 0x08;
+    df_tss32.ss     = df_tss32.ds = df_tss32.es = 0x10;
+    df_tss32.fs     = df_tss32.gs = 0x10;
+    df_tss32.ss0    = 0x10;
+    df_tss32.esp0   = df_tss32.esp;
+    df_tss32.iomap_base = sizeof(df_tss32);
+    gdt32_set(6, (uint32_t)&df_tss32, sizeof(df_tss32) - 1, 0x89, 0x00);
+
     gdt32_ptr.limit = sizeof(gdt32) - 1;
     gdt32_ptr.base  = (uint32_t)gdt32;
 
@@ -485,6 +521,10 @@ static void idt_init(void) {
     idt32_set(4,  isr4,  0x8E); idt32_set(5,  isr5,  0x8E);
     idt32_set(6,  isr6,  0x8E); idt32_set(7,  isr7,  0x8E);
     idt32_set(8,  isr8,  0x8E); idt32_set(9,  isr9,  0x8E);
+    // #DF → task gate (0x85) on the double-fault TSS: fresh stack + CR3
+    idt32[8].offset_low  = idt32[8].offset_high = 0;
+    idt32[8].selector    = 0x30;
+    idt32[8].type_attr   = 0x85;
     idt32_set(10, isr10, 0x8E); idt32_set(11, isr11, 0x8E);
     idt32_set(12, isr12, 0x8E); idt32_set(13, isr13, 0x8E);
     idt32_set(14, isr14, 0x8E); idt32_set(15, isr15, 0x8E);
@@ -547,6 +587,9 @@ static void pic_init(void) {
 
 static volatile uint32_t kernel_tick = 0;
 
+// Read-only info page mapped into user space (kinfo.rs); null until mapped.
+static volatile KernelInfoPage *kinfo_page = 0;
+
 #define PIT_FREQUENCY 1193182UL
 #define TIMER_HZ 240
 #define SYS_USER_TEST 0xF0
@@ -585,6 +628,7 @@ static volatile uint32_t kernel_tick = 0;
 
 #define USER_VADDR_MIN_32 0x10000000U
 #define USER_VADDR_MAX_32 0x40000000U
+#define USER_HEAP_BASE_32 0x20000000U   // sbrk start when a task has no break yet
 #define SYS_IO_PATH_MAX   64U
 #define SYS_IO_BUF_MAX    4096U
 
@@ -595,48 +639,40 @@ static int user_ptr_range_valid_32(uint32_t addr, uint32_t len) {
     return addr >= USER_VADDR_MIN_32 && end <= USER_VADDR_MAX_32;
 }
 
+// Copy a NUL-terminated path (at most SYS_IO_PATH_MAX bytes) from user space.
 static int copy_user_path_32(char *dst, uint32_t user_ptr) {
-    if (!dst || user_ptr == 0) return -1;
-    if (user_ptr < USER_VADDR_MIN_32 || user_ptr >= USER_VADDR_MAX_32) return -1;
-
-    const char *src = (const char *)(uintptr_t)user_ptr;
-    for (uint32_t i = 0; i < SYS_IO_PATH_MAX; i++) {
-        char c = src[i];
-        dst[i] = c;
-        if (c == '\0') return 0;
-    }
-    dst[SYS_IO_PATH_MAX - 1] = '\0';
-    return -1;
+    if (!dst) return -EINVAL;
+    intptr_t n = usermem_strncpy_from_user(dst, user_ptr, SYS_IO_PATH_MAX);
+    return n < 0 ? (int)n : 0;
 }
 
 static int32_t syscall_vfs_read_32(uint32_t path_ptr, uint32_t user_buf_ptr, uint32_t max_len) {
-    if (max_len == 0 || max_len > SYS_IO_BUF_MAX) return -1;
-    if (!user_ptr_range_valid_32(user_buf_ptr, max_len)) return -1;
+    if (max_len == 0 || max_len > SYS_IO_BUF_MAX) return -EINVAL;
+    if (usermem_access_ok(user_buf_ptr, max_len, 1) < 0) return -EFAULT;
 
     char path[SYS_IO_PATH_MAX];
-    if (copy_user_path_32(path, path_ptr) < 0) return -1;
+    if (copy_user_path_32(path, path_ptr) < 0) return -EFAULT;
 
     static uint8_t kbuf[SYS_IO_BUF_MAX];
     int n = kfs_read_file(path, kbuf, max_len);
-    if (n < 0) return -1;
+    if (n < 0) return -ENOENT;
 
-    uint8_t *dst = (uint8_t *)(uintptr_t)user_buf_ptr;
-    for (int i = 0; i < n; i++) dst[i] = kbuf[i];
-    return n;
+    int32_t rc = usermem_copy_to_user(user_buf_ptr, kbuf, (size_t)n);
+    return rc < 0 ? rc : n;
 }
 
 static int32_t syscall_vfs_write_32(uint32_t path_ptr, uint32_t user_buf_ptr, uint32_t len) {
-    if (len == 0 || len > SYS_IO_BUF_MAX) return -1;
-    if (!user_ptr_range_valid_32(user_buf_ptr, len)) return -1;
+    if (len == 0 || len > SYS_IO_BUF_MAX) return -EINVAL;
 
     char path[SYS_IO_PATH_MAX];
-    if (copy_user_path_32(path, path_ptr) < 0) return -1;
+    if (copy_user_path_32(path, path_ptr) < 0) return -EFAULT;
 
     static uint8_t kbuf[SYS_IO_BUF_MAX];
-    const uint8_t *src = (const uint8_t *)(uintptr_t)user_buf_ptr;
-    for (uint32_t i = 0; i < len; i++) kbuf[i] = src[i];
+    int32_t rc = usermem_copy_from_user(kbuf, user_buf_ptr, len);
+    if (rc < 0) return rc;
 
-    return kfs_write_file(path, kbuf, len);
+    int n = kfs_write_file(path, kbuf, len);
+    return n < 0 ? -EIO : n;
 }
 
 static void pit_init(void) {
@@ -754,6 +790,7 @@ typedef struct {
     uint8_t read_pos;
     uint8_t write_pos;
     uint8_t shift_held;
+    uint8_t alt_held;
     uint8_t caps_lock;
     uint8_t extended;   // 0xE0 prefix received
 } KbdState;
@@ -764,10 +801,20 @@ static KbdState kbd;
 typedef struct KernelTTY32_ KernelTTY32;
 static void tty_push_char_32(KernelTTY32 *tty, char c);
 static KernelTTY32 kernel_tty0_32;
+static KernelTTY32 *console_tty_32(uint32_t vt, uint32_t pid);
 
 static void keyboard_init(void) {
     kbd.read_pos = kbd.write_pos = 0;
-    kbd.shift_held = kbd.caps_lock = 0;
+    kbd.shift_held = kbd.alt_held = kbd.caps_lock = 0;
+}
+
+// Framebuffer console: keys go to the owner of the active virtual console.
+// The shell's console also feeds kernel_tty0_32, as text mode always has; a
+// process's console feeds its own TTY.
+static uint32_t keyboard_input_owner(uint32_t *id) {
+    *id = 0;
+    if (display_mode != 1) return VT_OWNER_SESSION;
+    return console_input_owner(id);
 }
 
 static void keyboard_irq_handler(void) {
@@ -779,6 +826,11 @@ static void keyboard_irq_handler(void) {
     if (kbd.extended) {
         kbd.extended = 0;
         if (sc & 0x80) return;  // extended key release — ignore
+        // Shift+PageUp/PageDown page the framebuffer console's scrollback
+        if (kbd.shift_held && display_mode == 1 && (sc == 0x49 || sc == 0x51)) {
+            console_scroll_view(sc == 0x49 ? 1 : -1);
+            return;
+        }
         char code = 0;
         switch (sc) {
             case 0x48: code = (char)0x80; break;  // Up
@@ -789,26 +841,42 @@ static void keyboard_irq_handler(void) {
             case 0x47: code = (char)0x85; break;  // Home
             case 0x4F: code = (char)0x86; break;  // End
         }
-        if (code) kbd.buf[kbd.write_pos++] = code;
+        uint32_t id;
+        if (code && keyboard_input_owner(&id) == VT_OWNER_SESSION) kbd.buf[kbd.write_pos++] = code;
         return;
     }
 
     if (sc & 0x80) {  // key release
         uint8_t key = sc & 0x7F;
         if (key == 0x2A || key == 0x36) kbd.shift_held = 0;
+        if (key == 0x38) kbd.alt_held = 0;
         return;
     }
     if (sc == 0x2A || sc == 0x36) { kbd.shift_held = 1; return; }
+    if (sc == 0x38) { kbd.alt_held = 1; return; }
     if (sc == 0x3A) { kbd.caps_lock ^= 1; return; }
 
+    // Alt+F1..F6 switch virtual consoles
+    if (kbd.alt_held && display_mode == 1 && sc >= 0x3B && sc <= 0x40) {
+        console_switch((uint32_t)(sc - 0x3B));
+        return;
+    }
+
     char c;
     if (kbd.shift_held) c = scancode_table_shift[sc];
     else                c = scancode_table[sc];
 
     if (c >= 'a' && c <= 'z' && kbd.caps_lock) c = (char)(c - 32);
-    if (c) kbd.buf[kbd.write_pos++] = c;
-    // Phase 42: also push to TTY for user-space stdin
-    if (c) tty_push_char_32(&kernel_tty0_32, c);
+    if (!c) return;
+    uint32_t id;
+    uint32_t owner = keyboard_input_owner(&id);
+    if (owner == VT_OWNER_SESSION) {
+        kbd.buf[kbd.write_pos++] = c;
+        // Phase 42: also push to TTY for user-space stdin
+        tty_push_char_32(&kernel_tty0_32, c);
+    } else if (owner == VT_OWNER_PROCESS) {
+        tty_push_char_32(console_tty_32(console_active(), id), c);
+    }
 }
 
 // In GUI mode, keyboard IRQs are routed to GUI event handling.
@@ -938,19 +1006,44 @@ typedef struct {
     uint32_t user_esp, user_ss;   // only valid on ring-3 → ring-0
 } __attribute__((packed)) InterruptFrame32;
 
+// Layout of CpuContext (scheduler_base.h): e* registers in the r* slots
+typedef struct {
+    uint32_t eip, esp, ebp;
+    uint32_t eax, ebx, ecx, edx, esi, edi;
+    uint32_t r8_r15[8];   // always 0 on i386
+    uint32_t eflags;
+} SyscallContext32;
+extern int32_t scheduler_save_context(void *sched, size_t pid, const SyscallContext32 *ctx);
+
 // Forward declarations — defined later or in Rust FFI
-extern int32_t syscall_handler(uint32_t num, uint32_t arg1, uint32_t arg2, uint32_t arg3);
+extern int32_t syscall_handler(uint32_t pid, uint32_t num, uint32_t arg1, uint32_t arg2, uint32_t arg3);
+extern int32_t syscall_gate(uint32_t pid, uint32_t num, uint32_t arg1, uint32_t arg2, uint32_t arg3);
+extern void syscall_account(uint32_t num, int32_t ret);
 extern uint32_t scheduler_get_process_count(const void *sched);
 extern uint32_t scheduler_get_current_pid(const void *sched);
 extern int scheduler_kill_process(void *sched, uint32_t pid);
 extern uint32_t scheduler_schedule(void *sched);
 extern void *get_kernel_scheduler(void);
+extern uint32_t kernel_current_pid(void);
 extern void scheduler_terminate_current(void *sched, int32_t exit_code);
 extern int32_t scheduler_get_exit_code(const void *sched, size_t pid);
+extern int scheduler_record_stack_usage(void *sched, size_t pid,
+                                        size_t kernel_used, size_t user_used);
+extern int32_t scheduler_get_user_memory_layout(const void *sched, size_t pid,
+                                                uint32_t *out_base, uint32_t *out_size);
+extern int32_t scheduler_set_user_region(void *sched, size_t pid, size_t base, size_t size);
+extern int32_t scheduler_set_parent(void *sched, size_t pid, size_t ppid);
+extern int32_t scheduler_set_uid(void *sched, size_t pid, uint16_t uid);
+extern int32_t scheduler_wake_process(void *sched, size_t pid);
+extern int32_t scheduler_is_waiting(const void *sched, size_t pid);
 
 // Phase 18 types & state — must be visible to interrupt_dispatch below
 #define MAX_TASKS          8
 #define TASK_STACK_SIZE    0x4000   // 16 KB per task
+#define USER_STACK_PAGES_32 4       // 16 KB user stack
+#define KSTACK_REGION_32   0x0F000000U   // 240 MB — guarded kernel stacks (4 KB pages)
+#define KSTACK_SPAN_32     (0x1000U + TASK_STACK_SIZE)   // guard page + stack
+#define STACK_PAINT_BYTE   0xA5     // untouched-stack fill for high-water marks
 
 // =============================================================================
 // Phase 41: Per-process file descriptor table (x86)
@@ -974,14 +1067,38 @@ typedef struct KernelTTY32_ {
     char     line_buf[TTY_BUF_SIZE];
     uint32_t line_len;
     uint8_t  line_ready;
+    uint32_t owner;              // console TTYs: pid the queued input is for
 } KernelTTY32;
 
+// Input queue of each framebuffer virtual console, for the process that
+// owns it (kernel_tty0_32 is the shell's).
+static KernelTTY32 console_ttys_32[CONSOLE_VT_COUNT];
+
 static void tty_init_32(KernelTTY32 *tty) {
     for (int i = 0; i < TTY_BUF_SIZE; i++) {
         tty->input_buf[i] = 0; tty->line_buf[i] = 0;
     }
     tty->input_read = tty->input_write = tty->input_count = 0;
     tty->cooked = 1; tty->line_len = 0; tty->line_ready = 0;
+    tty->owner = 0;
+}
+
+// TTY of console vt for process pid. A console handed to another process
+// starts with an empty queue, so keys typed for the old owner stay unread.
+static KernelTTY32 *console_tty_32(uint32_t vt, uint32_t pid) {
+    KernelTTY32 *tty = &console_ttys_32[vt % CONSOLE_VT_COUNT];
+    if (tty->owner != pid) {
+        tty_init_32(tty);
+        tty->owner = pid;
+    }
+    return tty;
+}
+
+// Console owned by the running process, or -1 (text mode, or none).
+static int32_t tty_console_32(void) {
+    if (display_mode != 1) return -1;
+    int32_t vt = console_lookup(VT_OWNER_PROCESS, kernel_current_pid());
+    return vt >= 0 ? vt : -1;
 }
 
 static void tty_push_char_32(KernelTTY32 *tty, char c) {
@@ -1027,8 +1144,12 @@ static int tty_read_32(KernelTTY32 *tty, char *buf, int max) {
 }
 
 static int tty_write_32(const char *buf, int len) {
+    // Framebuffer console: a process that owns a virtual console writes there
+    int32_t vt = tty_console_32();
+    if (vt >= 0) console_write_to((uint32_t)vt, (const uint8_t *)buf, (uint32_t)len);
+
     for (int i = 0; i < len; i++) {
-        terminal_putchar(buf[i]);
+        if (vt < 0) terminal_putchar(buf[i]);
         serial_putchar(buf[i]);
     }
     return len;
@@ -1065,6 +1186,7 @@ typedef struct {
 #define VMA_TYPE_NONE    0
 #define VMA_TYPE_ANON    1
 #define VMA_TYPE_FILE    2
+#define VMA_TYPE_SHM     3   // shared memory segment (file_offset = shm id)
 
 #define PROT_READ    0x01
 #define PROT_WRITE   0x02
@@ -1089,6 +1211,7 @@ typedef struct {
     uint32_t esp;                  // saved stack pointer (into stack[])
     uint32_t pid;                  // associated scheduler PID
     uint8_t  active;               // 1 = slot in use
+    uint8_t  blocked;              // parked by a syscall until the scheduler wakes it
     uint16_t ticks_remaining;
     uint16_t ticks_total;
     FdEntry32 fd_table[FD_MAX];
@@ -1096,7 +1219,8 @@ typedef struct {
     uint32_t brk;
     VmaEntry32 vma_list[VMA_MAX_PER_TASK]; // Phase 46: mmap regions
     uint32_t mmap_next;            // Phase 46: next mmap virtual address
-    uint8_t  stack[TASK_STACK_SIZE] __attribute__((aligned(16)));
+    uint32_t ustack_frames[USER_STACK_PAGES_32]; // user stack frames, lowest first
+    uint8_t *stack;                // TASK_STACK_SIZE bytes above an unmapped guard page
 } TaskSlot32;
 
 static TaskSlot32 task_slots[MAX_TASKS];
@@ -1159,20 +1283,39 @@ static int32_t sys_open_32(uint32_t path_ptr, uint32_t flags) {
 static int32_t sys_read_fd_32(uint32_t fd_num, uint32_t buf_ptr, uint32_t count) {
     if (current_task_idx < 0) return -1;
     if (fd_num >= FD_MAX) return -1;
-    if (!user_ptr_range_valid_32(buf_ptr, count)) return -1;
+    if (count == 0) return 0;
+    if (count > SYS_IO_BUF_MAX) count = SYS_IO_BUF_MAX;
+    if (usermem_access_ok(buf_ptr, count, 1) < 0) return -EFAULT;
     FdEntry32 *e = &task_slots[current_task_idx].fd_table[fd_num];
     if (e->type == FD_TYPE_NONE) return -1;
-    if (e->type == FD_TYPE_TTY)
-        return tty_read_32(&kernel_tty0_32, (char *)buf_ptr, (int)count);
+    if (e->type == FD_TYPE_TTY) {
+        // A process that owns a framebuffer console reads that console's keys
+        int32_t vt = tty_console_32();
+        KernelTTY32 *tty = (vt >= 0) ? console_tty_32((uint32_t)vt, kernel_current_pid())
+                                     : &kernel_tty0_32;
+        char tmp[SYS_IO_BUF_MAX];
+        int n = tty_read_32(tty, tmp, (int)count);
+        if (n > 0) {
+            int32_t rc = usermem_copy_to_user(buf_ptr, tmp, (size_t)n);
+            if (rc < 0) return rc;
+        }
+        return n;
+    }
     if (e->type == FD_TYPE_PIPE_R) {
         if (e->pipe_idx >= MAX_PIPES) return -1;
         KernelPipe32 *p = &kernel_pipes_32[e->pipe_idx];
+        // Stage into a kernel buffer; only consume once the copy succeeded
+        static char pipe_tmp[SYS_IO_BUF_MAX];
         int n = 0;
-        while (n < (int)count && p->count > 0) {
-            ((char *)buf_ptr)[n++] = p->buf[p->read_pos];
-            p->read_pos = (p->read_pos + 1) % PIPE_BUF_SIZE;
-            p->count--;
+        uint32_t pos = p->read_pos;
+        while (n < (int)count && n < (int)p->count) {
+            pipe_tmp[n++] = p->buf[pos];
+            pos = (pos + 1) % PIPE_BUF_SIZE;
         }
+        int32_t rc = usermem_copy_to_user(buf_ptr, pipe_tmp, (size_t)n);
+        if (rc < 0) return rc;
+        p->read_pos = pos;
+        p->count -= (uint32_t)n;
         return n;
     }
     if (e->type == FD_TYPE_FILE) {
@@ -1182,7 +1325,8 @@ static int32_t sys_read_fd_32(uint32_t fd_num, uint32_t buf_ptr, uint32_t count)
         int avail = sz - (int)e->offset;
         if (avail <= 0) return 0;
         int n = (avail < (int)count) ? avail : (int)count;
-        for (int i = 0; i < n; i++) ((uint8_t *)buf_ptr)[i] = file_tmp[e->offset + i];
+        int32_t rc = usermem_copy_to_user(buf_ptr, file_tmp + e->offset, (size_t)n);
+        if (rc < 0) return rc;
         e->offset += n;
         return n;
     }
@@ -1192,23 +1336,30 @@ static int32_t sys_read_fd_32(uint32_t fd_num, uint32_t buf_ptr, uint32_t count)
 static int32_t sys_write_fd_32(uint32_t fd_num, uint32_t buf_ptr, uint32_t count) {
     if (current_task_idx < 0) return -1;
     if (fd_num >= FD_MAX) return -1;
-    if (!user_ptr_range_valid_32(buf_ptr, count)) return -1;
+    if (count == 0) return 0;
+    if (count > SYS_IO_BUF_MAX) count = SYS_IO_BUF_MAX;
     FdEntry32 *e = &task_slots[current_task_idx].fd_table[fd_num];
-    if (e->type == FD_TYPE_NONE) return -1;
-    if (e->type == FD_TYPE_TTY)
-        return tty_write_32((const char *)buf_ptr, (int)count);
+    if (e->type != FD_TYPE_TTY && e->type != FD_TYPE_PIPE_W) return -1;
+
+    static char wr_tmp[SYS_IO_BUF_MAX];
     if (e->type == FD_TYPE_PIPE_W) {
         if (e->pipe_idx >= MAX_PIPES) return -1;
         KernelPipe32 *p = &kernel_pipes_32[e->pipe_idx];
-        int n = 0;
-        while (n < (int)count && p->count < PIPE_BUF_SIZE) {
-            p->buf[p->write_pos] = ((const char *)buf_ptr)[n++];
-            p->write_pos = (p->write_pos + 1) % PIPE_BUF_SIZE;
-            p->count++;
-        }
-        return n;
+        uint32_t space = PIPE_BUF_SIZE - p->count;
+        if (count > space) count = space;
     }
-    return -1;
+    int32_t rc = usermem_copy_from_user(wr_tmp, buf_ptr, count);
+    if (rc < 0) return rc;
+    if (e->type == FD_TYPE_TTY)
+        return tty_write_32(wr_tmp, (int)count);
+
+    KernelPipe32 *p = &kernel_pipes_32[e->pipe_idx];
+    for (uint32_t n = 0; n < count; n++) {
+        p->buf[p->write_pos] = wr_tmp[n];
+        p->write_pos = (p->write_pos + 1) % PIPE_BUF_SIZE;
+        p->count++;
+    }
+    return (int32_t)count;
 }
 
 static int32_t sys_close_32(uint32_t fd_num) {
@@ -1239,7 +1390,7 @@ static int32_t sys_dup2_32(uint32_t old_fd, uint32_t new_fd) {
 
 static int32_t sys_pipe_32(uint32_t fds_ptr) {
     if (current_task_idx < 0) return -1;
-    if (!user_ptr_range_valid_32(fds_ptr, 8)) return -1;
+    if (usermem_access_ok(fds_ptr, 2 * sizeof(int32_t), 1) < 0) return -EFAULT;
     int pi = -1;
     for (int i = 0; i < MAX_PIPES; i++)
         if (!kernel_pipes_32[i].active) { pi = i; break; }
@@ -1257,9 +1408,8 @@ static int32_t sys_pipe_32(uint32_t fds_ptr) {
     task_slots[current_task_idx].fd_table[rfd].pipe_idx = (uint8_t)pi;
     task_slots[current_task_idx].fd_table[wfd].type = FD_TYPE_PIPE_W;
     task_slots[current_task_idx].fd_table[wfd].pipe_idx = (uint8_t)pi;
-    ((int32_t *)fds_ptr)[0] = rfd;
-    ((int32_t *)fds_ptr)[1] = wfd;
-    return 0;
+    int32_t ufds[2] = { rfd, wfd };
+    return usermem_copy_to_user(fds_ptr, ufds, sizeof(ufds));
 }
 
 // Phase 43: fork/execve/sbrk — defined after ELF types (see below elf_exec_32)
@@ -1285,13 +1435,24 @@ static inline uint32_t read_cr2_32(void) {
 #endif
 static uint32_t frame_alloc_32(void);
 static void paging_map_4k_32(uint32_t *pd, uint32_t virt, uint32_t phys, uint32_t flags);
+static void paging_unmap_4k_32(uint32_t *pd, uint32_t virt);
 static void paging_flush_tlb_32(void);
 static uint32_t kernel_page_dir[1024] __attribute__((aligned(4096)));
 static uint8_t mmap_file_tmp_32[65536];
 static VmaEntry32 *vma_find_32(VmaEntry32 *vmas, uint32_t addr);
 static void vma_init_32(VmaEntry32 *vmas);
+static int vma_demand_fill_32(TaskSlot32 *t, uint32_t addr);
 static int32_t sys_mmap_32(uint32_t length, uint32_t prot_flags, uint32_t path_ptr);
 static int32_t sys_munmap_32(uint32_t addr, uint32_t length);
+static int32_t sys_shm_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3);
+static int32_t sys_shm_unmap_32(uint32_t addr);
+static void shm_task_release_32(TaskSlot32 *t, void *sched);
+static uint32_t user_frame_alloc_32(void);
+static int  swap_fault_in_32(uint32_t addr);
+static int  stack_guard_slot_32(uint32_t addr, uint8_t *kernel_stack);
+static void report_stack_overflow_32(int slot, uint8_t kernel_stack);
+static void stack_sync_usage_32(int slot);
+static void stack_paint_32(uint8_t *base, uint32_t size);
 
 static void log_page_fault_detail_32(uint32_t error_code) {
     uint32_t fault_addr = read_cr2_32();
@@ -1328,10 +1489,49 @@ static void log_page_fault_detail_32(uint32_t error_code) {
     terminal_writestring("\n");
 }
 
+// Take the current task off the CPU after a syscall parked it in Waiting
+// (uring_enter with min_complete); frame already holds its return value.
+// Returns the ESP of the next runnable task, or 0 to return to the caller
+// when nothing else can run, in which case it is woken again at once.
+static uint32_t task_block_current_32(InterruptFrame32 *frame) {
+    TaskSlot32 *cur = &task_slots[current_task_idx];
+    int next = -1;
+    for (int i = 1; i < MAX_TASKS; i++) {
+        int idx = (current_task_idx + i) % MAX_TASKS;
+        if (task_slots[idx].active && !task_slots[idx].blocked) { next = idx; break; }
+    }
+    if (!context_switch_enabled || next < 0) {
+        (void)scheduler_wake_process(get_kernel_scheduler(), cur->pid);
+        return 0;
+    }
+    cur->blocked = 1;
+    cur->esp     = (uint32_t)frame;
+    context_switch_count++;
+    current_task_idx = next;
+    kernel_tss32.esp0 = (uint32_t)(task_slots[next].stack + TASK_STACK_SIZE);
+    kinfo_write_pid(kinfo_page, task_slots[next].pid);
+    return task_slots[next].esp;
+}
+
+// Let parked tasks run again once the scheduler has woken them
+// (Scheduler::wake_process, called from uring_poll).
+static void task_wake_unblocked_32(void) {
+    void *sched = get_kernel_scheduler();
+    for (int i = 0; i < MAX_TASKS; i++) {
+        TaskSlot32 *t = &task_slots[i];
+        if (t->active && t->blocked && !scheduler_is_waiting(sched, t->pid))
+            t->blocked = 0;
+    }
+}
+
 uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
     uint32_t n = frame->int_no;
 
     if (n < 32) {
+        // A swapped-out page is not an error: read it back and retry.
+        if (n == 14 && swap_fault_in_32(read_cr2_32()))
+            return 0;
+
         // CPU exception
         serial_print("[EXCEPTION] vec=");
         serial_print_hex(n);
@@ -1343,13 +1543,22 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
         terminal_writestring("[EXCEPTION] ");
 
         if (n == 14) {
-            log_page_fault_detail_32(frame->error_code);
+            uint8_t kguard = 0;
+            int gslot = stack_guard_slot_32(read_cr2_32(), &kguard);
+            if (gslot >= 0)
+                report_stack_overflow_32(gslot, kguard);
+            else
+                log_page_fault_detail_32(frame->error_code);
         } else if (n == 13) {
             serial_print("[GP] general protection fault detected\n");
         } else if (n == 8) {
             serial_print("[DF] double fault detected\n");
         }
 
+        // A fault in module code is charged to the module; the call is
+        // unwound once the exception has been reported below.
+        int mod_fault = (frame->cs & 0x3) == 0 && n != 8 && module_fault_enter(n);
+
         // Phase 10: Notify AI of CPU exception
         {
             char evbuf[48];
@@ -1367,44 +1576,17 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
             ai_send_event(AI_EVT_EXCEPTION, evbuf);
             ai_kernel_engine_feed(AI_EVT_EXCEPTION, evbuf, kernel_tick);        }
 
+        if (mod_fault)
+            module_fault_unwind();
+
         if ((frame->cs & 0x3) == 0x3) {
             // Phase 46: demand paging — check VMAs before killing
             if (n == 14 && current_task_idx >= 0) {
                 uint32_t fault_addr;
                 __asm__ volatile("mov %%cr2, %0" : "=r"(fault_addr));
                 TaskSlot32 *t = &task_slots[current_task_idx];
-                VmaEntry32 *vma = vma_find_32(t->vma_list, fault_addr);
-                if (vma) {
-                    uint32_t page_va = fault_addr & ~0xFFFU;
-                    uint32_t fr = frame_alloc_32();
-                    if (fr) {
-                        uint32_t pg_flags = PAGE_USER_32;
-                        if (vma->prot & PROT_WRITE) pg_flags |= PAGE_WRITABLE_32;
-
-                        if (vma->type == VMA_TYPE_FILE && vma->path[0]) {
-                            uint32_t page_off = (page_va - vma->start) + vma->file_offset;
-                            uint32_t need = page_off + PAGE_SIZE;
-                            if (need > sizeof(mmap_file_tmp_32)) need = sizeof(mmap_file_tmp_32);
-                            int total = kfs_read_file(vma->path, mmap_file_tmp_32, need);
-                            if (total > (int)page_off) {
-                                int ncp = total - (int)page_off;
-                                if (ncp > PAGE_SIZE) ncp = PAGE_SIZE;
-                                uint8_t *dst = (uint8_t *)fr;
-                                for (int b = 0; b < ncp; b++)
-                                    dst[b] = mmap_file_tmp_32[page_off + b];
-                            }
-                        }
-
-                        paging_map_4k_32(kernel_page_dir, page_va, fr, pg_flags);
-                        paging_flush_tlb_32();
-                        serial_print("[demand-page] va=0x");
-                        serial_print_hex(page_va);
-                        serial_print(" -> frame=0x");
-                        serial_print_hex(fr);
-                        serial_print("\n");
-                        return 0;  // Resume user execution
-                    }
-                }
+                if (vma_demand_fill_32(t, fault_addr))
+                    return 0;  // Resume user execution
             }
 
             // User-mode fault: kill the faulting process and switch to next task
@@ -1421,17 +1603,21 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
             }
             // Deactivate the faulting task slot and switch to next
             if (context_switch_enabled && current_task_idx >= 0) {
+                stack_sync_usage_32(current_task_idx);
+                shm_task_release_32(&task_slots[current_task_idx], get_kernel_scheduler());
+                (void)swap_release_process(task_slots[current_task_idx].pid);
                 task_slots[current_task_idx].active = 0;
                 int next = -1;
                 for (int i = 0; i < MAX_TASKS; i++) {
                     int idx = (current_task_idx + 1 + i) % MAX_TASKS;
-                    if (task_slots[idx].active) { next = idx; break; }
+                    if (task_slots[idx].active && !task_slots[idx].blocked) { next = idx; break; }
                 }
                 if (next >= 0) {
                     serial_print("[EXCEPTION] switching to task ");
                     serial_print_dec(next);
                     serial_print("\n");
                     current_task_idx = next;
+                    kinfo_write_pid(kinfo_page, task_slots[next].pid);
                     return task_slots[next].esp;
                 }
             }
@@ -1446,6 +1632,7 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
     if (n == 0x20) {   // IRQ0: timer — keep FAST: no Rust/heap calls from IRQ
         kernel_tick++;
         outb(0x20, 0x20);  // EOI first so keyboard IRQ is not delayed
+        kinfo_write_tick(kinfo_page, kernel_tick);
 
         // COM2 polling is simple port I/O — safe in IRQ
         const uint32_t cmd_poll_div = (TIMER_HZ / 10) ? (TIMER_HZ / 10) : 1;
@@ -1468,14 +1655,16 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
                 int next = current_task_idx;
                 for (int tries = 0; tries < MAX_TASKS; tries++) {
                     next = (next + 1) % MAX_TASKS;
-                    if (task_slots[next].active) break;
+                    if (task_slots[next].active && !task_slots[next].blocked) break;
                 }
-                if (next != current_task_idx && task_slots[next].active) {
+                if (next != current_task_idx && task_slots[next].active &&
+                    !task_slots[next].blocked) {
                     context_switch_count++;
                     current_task_idx = next;
                     // Update TSS esp0 so Ring 3→Ring 0 transitions
                     // land on the correct per-task kernel stack.
                     kernel_tss32.esp0 = (uint32_t)(task_slots[next].stack + TASK_STACK_SIZE);
+                    kinfo_write_pid(kinfo_page, task_slots[next].pid);
                     return task_slots[next].esp;   // asm switches ESP
                 }
             }
@@ -1509,8 +1698,30 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
         uint32_t a1  = frame->ebx;
         uint32_t a2  = frame->ecx;
         uint32_t a3  = frame->edx;
+        uint32_t pid = (current_task_idx >= 0) ? task_slots[current_task_idx].pid : 0;
+        uint32_t start = kernel_tick;
         int32_t  ret;
-        if (num == SYS_USER_TEST) {
+        int      in_table = 0;   // dispatched through the Rust table, which counts it
+        if (pid) {
+            // Registers at entry, for the dump_registers syscall. A ring-0
+            // caller's stack continues right where user_esp would be.
+            SyscallContext32 ctx = {
+                frame->eip,
+                (frame->cs & 3) == 3 ? frame->user_esp : (uint32_t)&frame->user_esp,
+                frame->ebp,
+                frame->eax, frame->ebx, frame->ecx, frame->edx, frame->esi, frame->edi,
+                {0}, frame->eflags,
+            };
+            (void)scheduler_save_context(get_kernel_scheduler(), pid, &ctx);
+        }
+        // The calls handled below are checked against the caller's
+        // capabilities and seccomp filter first; table calls are checked
+        // inside syscall_handler.
+        int32_t gate = syscall_gate(pid, num, a1, a2, a3);
+        if (gate < 0) {
+            ret = gate;
+            in_table = 1;   // syscall_gate counted the refusal
+        } else if (num == SYS_USER_TEST) {
             // Phase 17: Ring 3 user heartbeat — print once per second
             static uint32_t user_test_count = 0;
             user_test_count++;
@@ -1528,12 +1739,17 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
             void *sched = get_kernel_scheduler();
             if (sched)
                 scheduler_terminate_current(sched, (int32_t)a1);
+            if (current_task_idx >= 0) {
+                stack_sync_usage_32(current_task_idx);
+                shm_task_release_32(&task_slots[current_task_idx], get_kernel_scheduler());
+                (void)swap_release_process(task_slots[current_task_idx].pid);
+            }
             if (context_switch_enabled && current_task_idx >= 0) {
                 task_slots[current_task_idx].active = 0;
                 int next = -1;
                 for (int i = 0; i < MAX_TASKS; i++) {
                     int idx = (current_task_idx + 1 + i) % MAX_TASKS;
-                    if (task_slots[idx].active) { next = idx; break; }
+                    if (task_slots[idx].active && !task_slots[idx].blocked) { next = idx; break; }
                 }
                 serial_print("[exit] pid=");
                 serial_print_dec(exit_pid);
@@ -1543,6 +1759,7 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
                 if (next >= 0) {
                     current_task_idx = next;
                     kernel_tss32.esp0 = (uint32_t)(task_slots[next].stack + TASK_STACK_SIZE);
+                    kinfo_write_pid(kinfo_page, task_slots[next].pid);
                     return task_slots[next].esp;
                 }
             }
@@ -1557,27 +1774,6 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
             } else {
                 ret = -1;
             }
-        } else if (num == SYS_GETPID) {
-            // Phase 20: getpid() — return current PID
-            void *sched = get_kernel_scheduler();
-            ret = sched ? (int32_t)scheduler_get_current_pid(sched) : 0;
-        } else if (num == SYS_KILL) {
-            // Phase 23: kill(pid, sig) — send signal to process
-            uint32_t dst_pid = (uint32_t)a1;
-            uint8_t sig = (uint8_t)a2;
-            void *sched = get_kernel_scheduler();
-            if (sched) {
-                extern int scheduler_signal_send(void *sched, uint32_t dst_pid, uint8_t sig);
-                ret = scheduler_signal_send(sched, dst_pid, sig);
-            } else {
-                ret = -1;
-            }
-        } else if (num == SYS_READ) {
-            // Phase 25: read(path_ptr, user_buf_ptr, max_len) -> bytes read
-            ret = syscall_vfs_read_32(a1, a2, a3);
-        } else if (num == SYS_WRITE) {
-            // Phase 25: write(path_ptr, user_buf_ptr, len) -> bytes written
-            ret = syscall_vfs_write_32(a1, a2, a3);
         } else if (num == SYS_OPEN) {
             ret = sys_open_32(a1, a2);
         } else if (num == SYS_READ_FD) {
@@ -1608,20 +1804,20 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
         } else if (num == SYS_SYNC) {
             // Phase 48: sync() -> 0 or -1
             ret = (int32_t)bcache_sync();
-        } else if (num >= SYS_IPC_SEND && num <= SYS_IPC_CHAN_CLOSE) {
-            // Phase 4: IPC syscalls handled in C
-            ret = ipc_syscall(num, a1, a2, a3);
-        } else if (num >= SYS_MOD_LOAD && num <= SYS_MOD_INFO) {
-            // Phase 5: Module syscalls handled in C
-            ret = module_syscall(num, a1, a2, a3);
-        } else if (num >= SYS_AI_QUERY && num <= SYS_AI_EVENT) {
-            // Phase 8: AI syscalls handled in C
-            ret = (int32_t)ai_syscall(num, a1, a2, a3);
         } else {
-            // Phase 3: all other syscalls handled in Rust
-            ret = syscall_handler(num, a1, a2, a3);
+            // Everything else goes through the Rust syscall table
+            ret = syscall_handler(pid, num, a1, a2, a3);
+            in_table = 1;
         }
         frame->eax = (uint32_t)ret;
+        // exit and a successful execve returned above and are not traced
+        if (!in_table)
+            syscall_account(num, ret);
+        strace_record(pid, num, a1, a2, a3, ret, start);
+        // The call parked the caller: run something else until it is woken
+        if (pid && current_task_idx >= 0 &&
+            scheduler_is_waiting(get_kernel_scheduler(), pid))
+            return task_block_current_32(frame);
         return 0;
     }
     return 0;
@@ -1634,18 +1830,133 @@ uint32_t interrupt_dispatch(InterruptFrame32 *frame) {
 extern void verniskernel_init_heap(uint32_t start, uint32_t size);
 extern void verniskernel_register_print(void (*cb)(const uint8_t *, uint32_t));
 extern void syscall_init(void);
-extern int32_t syscall_handler(uint32_t num, uint32_t arg1, uint32_t arg2, uint32_t arg3);
+extern int32_t syscall_handler(uint32_t pid, uint32_t num, uint32_t arg1, uint32_t arg2, uint32_t arg3);
+typedef int32_t (*syscall_native_fn_32)(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3);
+extern int32_t syscall_register(uint32_t num, const char *name, uint8_t argc,
+                                uint64_t caps, syscall_native_fn_32 handler);
+extern int32_t syscall_register_trap(uint32_t num, const char *name, uint8_t argc);
 extern void *scheduler_new(void);
-extern uint32_t scheduler_create_process(void *sched, uint8_t priority, const char *command);
+extern int32_t scheduler_create_process(void *sched, uint8_t priority, const char *command);
 extern uint32_t scheduler_schedule(void *sched);
 extern uint32_t scheduler_get_process_count(const void *sched);
 extern int scheduler_set_priority(void *sched, uint32_t pid, uint8_t priority);
 extern void scheduler_set_quantum(void *sched, uint32_t quantum_ms);
 
+// ---- C handlers registered into the Rust syscall table ----
+
+static int32_t sysent_ipc_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
+    return ipc_syscall(num, a1, a2, a3);
+}
+
+static int32_t sysent_module_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
+    return module_syscall(num, a1, a2, a3);
+}
+
+static int32_t sysent_ai_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
+    return (int32_t)ai_syscall(num, a1, a2, a3);
+}
+
+static int32_t sysent_vfs_read_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
+    (void)num;
+    return syscall_vfs_read_32(a1, a2, a3);
+}
+
+static int32_t sysent_vfs_write_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
+    (void)num;
+    return syscall_vfs_write_32(a1, a2, a3);
+}
+
+static const struct {
+    uint32_t num;
+    const char *name;
+    uint8_t argc;
+    uint64_t caps;
+    syscall_native_fn_32 handler;
+} syscall_natives_32[] = {
+    { SYS_IPC_SEND,         "ipc_send",         2, CAP_IPC_SEND,       sysent_ipc_32 },
+    { SYS_IPC_RECV,         "ipc_recv",         2, CAP_IPC_RECEIVE,    sysent_ipc_32 },
+    { SYS_IPC_QUEUE_CREATE, "ipc_queue_create", 1, CAP_IPC_RECEIVE,    sysent_ipc_32 },
+    { SYS_IPC_QUEUE_CLOSE,  "ipc_queue_close",  1, CAP_IPC_RECEIVE,    sysent_ipc_32 },
+    { SYS_IPC_CHAN_CREATE,  "ipc_chan_create",  2, CAP_CHANNEL_CREATE, sysent_ipc_32 },
+    { SYS_IPC_CHAN_WRITE,   "ipc_chan_write",   3, CAP_IPC_SEND,       sysent_ipc_32 },
+    { SYS_IPC_CHAN_READ,    "ipc_chan_read",    3, CAP_IPC_RECEIVE,    sysent_ipc_32 },
+    { SYS_IPC_CHAN_CLOSE,   "ipc_chan_close",   1, CAP_CHANNEL_CREATE, sysent_ipc_32 },
+    { SYS_MOD_LOAD,         "mod_load",         2, CAP_MODULE_LOAD,    sysent_module_32 },
+    { SYS_MOD_UNLOAD,       "mod_unload",       1, CAP_MODULE_UNLOAD,  sysent_module_32 },
+    { SYS_MOD_LIST,         "mod_list",         0, CAP_NONE,           sysent_module_32 },
+    { SYS_MOD_CALL,         "mod_call",         3, CAP_MODULE_EXECUTE, sysent_module_32 },
+    { SYS_MOD_INFO,         "mod_info",         2, CAP_NONE,           sysent_module_32 },
+    { SYS_SHM_CREATE,       "shm_create",       3, CAP_NONE,           sys_shm_32 },
+    { SYS_SHM_OPEN,         "shm_open",         2, CAP_NONE,           sys_shm_32 },
+    { SYS_SHM_MAP,          "shm_map",          2, CAP_NONE,           sys_shm_32 },
+    { SYS_SHM_UNMAP,        "shm_unmap",        1, CAP_NONE,           sys_shm_32 },
+    { SYS_SHM_UNLINK,       "shm_unlink",       1, CAP_NONE,           sys_shm_32 },
+    { SYS_SHM_CLOSE,        "shm_close",        1, CAP_NONE,           sys_shm_32 },
+    { SYS_AI_QUERY,         "ai_query",         2, CAP_NONE,           sysent_ai_32 },
+    { SYS_AI_STATUS,        "ai_status",        0, CAP_NONE,           sysent_ai_32 },
+    { SYS_AI_EVENT,         "ai_event",         2, CAP_NONE,           sysent_ai_32 },
+    { SYS_READ,             "read",             3, CAP_NONE,           sysent_vfs_read_32 },
+    { SYS_WRITE,            "write",            3, CAP_NONE,           sysent_vfs_write_32 },
+};
+
+// Handled in the INT 0x80 dispatcher with the trap frame; registered by name
+// only so the syscall dump and strace can show them.
+static const struct {
+    uint32_t num;
+    const char *name;
+    uint8_t argc;
+} syscall_traps_32[] = {
+    { SYS_EXIT,     "exit",     1 },
+    { SYS_WAITPID,  "waitpid",  1 },
+    { SYS_OPEN,     "open",     2 },
+    { SYS_READ_FD,  "read_fd",  3 },
+    { SYS_WRITE_FD, "write_fd", 3 },
+    { SYS_CLOSE,    "close",    1 },
+    { SYS_DUP,      "dup",      1 },
+    { SYS_DUP2,     "dup2",     2 },
+    { SYS_PIPE,     "pipe",     1 },
+    { SYS_FORK,     "fork",     0 },
+    { SYS_EXECVE,   "execve",   1 },
+    { SYS_SBRK,     "sbrk",     1 },
+    { SYS_MMAP,     "mmap",     3 },
+    { SYS_MUNMAP,   "munmap",   2 },
+    { SYS_SYNC,     "sync",     0 },
+};
+
+// Register the C-implemented syscalls. Runs right after syscall_init(),
+// which installs the Rust built-ins (getpid, kill, dumps).
+static void syscall_table_init_32(void) {
+    for (uint32_t i = 0; i < sizeof(syscall_natives_32) / sizeof(syscall_natives_32[0]); i++) {
+        if (syscall_register(syscall_natives_32[i].num, syscall_natives_32[i].name,
+                             syscall_natives_32[i].argc, syscall_natives_32[i].caps,
+                             syscall_natives_32[i].handler) != 0) {
+            serial_print("[syscall] register failed: ");
+            serial_print(syscall_natives_32[i].name);
+            serial_print("\n");
+        }
+    }
+    for (uint32_t i = 0; i < sizeof(syscall_traps_32) / sizeof(syscall_traps_32[0]); i++) {
+        if (syscall_register_trap(syscall_traps_32[i].num, syscall_traps_32[i].name,
+                                  syscall_traps_32[i].argc) != 0) {
+            serial_print("[syscall] register failed: ");
+            serial_print(syscall_traps_32[i].name);
+            serial_print("\n");
+        }
+    }
+}
+
 // Global scheduler instance
 static void *kernel_scheduler = (void *)0;
 void *get_kernel_scheduler(void) { return kernel_scheduler; }
 uint32_t kernel_get_ticks(void)  { return kernel_tick; }
+// Pid of the running task, 0 before tasking starts.
+uint32_t kernel_current_pid(void) {
+    return (current_task_idx >= 0) ? task_slots[current_task_idx].pid : 0;
+}
+// Task slot of the running task, 0 (the kernel thread) before tasking starts.
+uint32_t kernel_current_task(void) {
+    return (current_task_idx >= 0) ? (uint32_t)current_task_idx : 0;
+}
 uint32_t kernel_is_gui_mode(void) { return (display_mode == 2) ? 1u : 0u; }
 
 // Serial write callback — registered into Rust so kernel_print() works
@@ -1706,6 +2017,15 @@ static void paging_map_4k_32(uint32_t *pd, uint32_t virt, uint32_t phys, uint32_
     pt[pt_idx] = (phys & 0xFFFFF000) | flags | PAGE_PRESENT_32;
 }
 
+// Remove a 4KB mapping (no-op for absent tables or 4MB PSE entries).
+static void paging_unmap_4k_32(uint32_t *pd, uint32_t virt) {
+    int pd_idx = virt >> 22;
+    int pt_idx = (virt >> 12) & 0x3FF;
+    if (!(pd[pd_idx] & PAGE_PRESENT_32) || (pd[pd_idx] & PAGE_PS_32)) return;
+    uint32_t *pt = (uint32_t *)(pd[pd_idx] & 0xFFFFF000);
+    pt[pt_idx] = 0;
+}
+
 // Create a new address space for a user process (copies kernel entries).
 uint32_t *paging_create_address_space_32(void) {
     uint32_t frame = frame_alloc_32();
@@ -1726,6 +2046,32 @@ static void paging_flush_tlb_32(void) {
 uint32_t *paging_get_kernel_pd(void) { return kernel_page_dir; }
 uint32_t  paging_get_frames_used_32(void) { return phys_frames_used; }
 
+// Back every task slot's kernel stack with 4 KB pages in KSTACK_REGION_32.
+// The lowest page of each KSTACK_SPAN_32 window is never mapped, so running
+// off the bottom of a stack faults instead of corrupting the next slot.
+static void kstack_init_32(void) {
+    for (int i = 0; i < MAX_TASKS; i++) {
+        uint32_t base = KSTACK_REGION_32 + (uint32_t)i * KSTACK_SPAN_32;
+        for (uint32_t off = 0; off < TASK_STACK_SIZE; off += PAGE_SIZE) {
+            uint32_t frame = frame_alloc_32();
+            if (!frame) {
+                serial_print("[kstack] FATAL: out of frames for kernel stacks\n");
+                __asm__ volatile("cli");
+                while (1) __asm__ volatile("hlt");
+            }
+            paging_map_4k_32(kernel_page_dir, base + PAGE_SIZE + off, frame,
+                             PAGE_WRITABLE_32);
+        }
+        task_slots[i].stack = (uint8_t *)(base + PAGE_SIZE);
+    }
+    paging_flush_tlb_32();
+    serial_print("[kstack] ");
+    serial_print_dec(MAX_TASKS);
+    serial_print(" kernel stacks with guard pages at ");
+    serial_print_hex(KSTACK_REGION_32);
+    serial_print("\n");
+}
+
 // Initialize x86 paging with PSE 4MB pages for kernel identity mapping.
 // Enables paging (CR0.PG) — must be called AFTER BSS zeroing.
 static void paging_init_32(void) {
@@ -1784,6 +2130,26 @@ static void paging_init_32(void) {
     }
 
     serial_print("[paging] enabled: 128MB identity (PSE 4MB pages)\n");
+
+    kstack_init_32();
+}
+
+// Fill in the kernel info page and map it read-only for user space at
+// KINFO_VADDR. All tasks share kernel_page_dir, so one mapping covers them all.
+static void kinfo_page_init_32(void) {
+    RtcTime t;
+    rtc_read(&t);
+    uint32_t lo, hi;
+    __asm__ volatile("rdtsc" : "=a"(lo), "=d"(hi));
+    uint64_t wall = kinfo_unix_time(t.year, t.month, t.day, t.hour, t.minute, t.second);
+    KernelInfoPage *p = kinfo_init(TIMER_HZ, wall, ((uint64_t)hi << 32) | lo);
+    paging_map_4k_32(kernel_page_dir, KINFO_VADDR, (uint32_t)(uintptr_t)p, PAGE_USER_32);
+    paging_flush_tlb_32();
+    kinfo_page = p;
+    kinfo_write_pid(kinfo_page, current_task_idx >= 0 ? task_slots[current_task_idx].pid : 0);
+    serial_print("[kinfo] info page mapped at ");
+    serial_print_hex(KINFO_VADDR);
+    serial_print("\n");
 }
 
 // =============================================================================
@@ -1798,6 +2164,217 @@ static int task_find(uint32_t pid) {
     return -1;
 }
 
+// =============================================================================
+// Heap growth + OOM kill callbacks (Rust heap.rs)
+// =============================================================================
+
+// Frames kept back for page tables; the heap may only take what is above this.
+#define HEAP_GROW_RESERVE (512 * 1024)
+
+// Hand the Rust heap a contiguous run of frames from the bump allocator.
+// Frames lie in the 128MB identity map, so physical == virtual.
+static size_t kernel_heap_grow_cb(size_t min_bytes, uintptr_t *out_start) {
+    uint32_t avail = phys_alloc_end - phys_next_free;
+    if (!out_start || min_bytes == 0 || min_bytes > avail ||
+        avail - min_bytes < HEAP_GROW_RESERVE) {
+        serial_print("[heap] grow refused: frame pool exhausted\n");
+        return 0;
+    }
+    uint32_t bytes = ((uint32_t)min_bytes + PAGE_SIZE - 1) & ~((uint32_t)PAGE_SIZE - 1);
+    *out_start = (uintptr_t)phys_next_free;
+    phys_next_free   += bytes;
+    phys_frames_used += bytes / PAGE_SIZE;
+    return (size_t)bytes;
+}
+
+// User pages a task holds: its mmap regions, heap and user stack.
+static uint32_t task_user_pages_32(const TaskSlot32 *t) {
+    uint32_t bytes = USER_STACK_PAGES_32 * PAGE_SIZE;
+    if (t->brk > USER_HEAP_BASE_32)
+        bytes += t->brk - USER_HEAP_BASE_32;
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type != VMA_TYPE_NONE)
+            bytes += t->vma_list[i].length;
+    }
+    return bytes / PAGE_SIZE;
+}
+
+// OOM candidates: live tasks running a user program (kernel threads have no
+// user stack), never the running task, which cannot be torn down from
+// inside its own allocation.
+static uint32_t kernel_oom_tasks_cb(OomCandidate *out, uint32_t max) {
+    uint32_t n = 0;
+    for (int i = 0; i < MAX_TASKS && n < max; i++) {
+        const TaskSlot32 *t = &task_slots[i];
+        if (!t->active || i == current_task_idx || !t->ustack_frames[0])
+            continue;
+        out[n].pid   = t->pid;
+        out[n].pages = task_user_pages_32(t);
+        n++;
+    }
+    return n;
+}
+
+// Tear down the OOM victim's task slot and release what it holds. The Rust
+// scheduler marks it killed later, outside the allocator.
+static void kernel_oom_kill_cb(uint32_t pid) {
+    serial_print("[heap] OOM killed pid=");
+    serial_print_dec(pid);
+    serial_print("\n");
+    int slot = task_find(pid);
+    if (slot >= 0 && slot != current_task_idx) {
+        shm_task_release_32(&task_slots[slot], (void *)0);
+        (void)swap_release_process(pid);
+        module_task_exit((uint32_t)slot);
+        task_slots[slot].active = 0;
+    }
+}
+
+// =============================================================================
+// Anonymous-page swap (Rust swap.rs runs the clock, we do PTEs and disk I/O)
+// =============================================================================
+
+// Once the frame pool drops below this, anonymous user pages are taken from
+// the clock instead, leaving the rest for page tables and heap growth.
+#define SWAP_FRAME_RESERVE (1024 * 1024)
+
+// A swapped-out PTE keeps its permission bits with PAGE_PRESENT_32 clear, so
+// swap_fault_in_32 can restore the original protection.
+#define PTE_FLAGS_MASK_32 0xFFFU
+#define PAGE_ACCESSED_32  0x20U
+
+static uint32_t *paging_pte_lookup_32(uint32_t *pd, uint32_t virt) {
+    uint32_t pde = pd[virt >> 22];
+    if (!(pde & PAGE_PRESENT_32) || (pde & PAGE_PS_32)) return (uint32_t *)0;
+    return &((uint32_t *)(pde & 0xFFFFF000))[(virt >> 12) & 0x3FF];
+}
+
+static uint8_t swap_page_referenced_32(size_t vaddr, size_t frame) {
+    uint32_t *pte = paging_pte_lookup_32(kernel_page_dir, (uint32_t)vaddr);
+    if (!pte || !(*pte & PAGE_PRESENT_32) || (*pte & 0xFFFFF000) != frame)
+        return 0;
+    if (!(*pte & PAGE_ACCESSED_32)) return 0;
+    *pte &= ~PAGE_ACCESSED_32;
+    __asm__ volatile("invlpg (%0)" : : "r"(vaddr) : "memory");
+    return 1;
+}
+
+static uint8_t swap_page_unmap_32(size_t vaddr, size_t frame) {
+    uint32_t *pte = paging_pte_lookup_32(kernel_page_dir, (uint32_t)vaddr);
+    if (!pte || !(*pte & PAGE_PRESENT_32) || (*pte & 0xFFFFF000) != frame)
+        return 0;
+    *pte &= PTE_FLAGS_MASK_32 & ~PAGE_PRESENT_32;
+    __asm__ volatile("invlpg (%0)" : : "r"(vaddr) : "memory");
+    return 1;
+}
+
+static int32_t swap_write_slot_32(uint32_t slot, size_t frame) {
+    return kfs_write_at(SWAP_FILE_PATH, slot * PAGE_SIZE,
+                        (const uint8_t *)frame, PAGE_SIZE) < 0 ? -1 : 0;
+}
+
+static int32_t swap_read_slot_32(uint32_t slot, size_t frame) {
+    return kfs_read_at(SWAP_FILE_PATH, slot * PAGE_SIZE,
+                       (uint8_t *)frame, PAGE_SIZE) < 0 ? -1 : 0;
+}
+
+static const swap_ops_t swap_ops_32 = {
+    swap_page_referenced_32, swap_page_unmap_32, swap_write_slot_32, swap_read_slot_32,
+};
+
+// Reserve /swap on the root filesystem and hand it to the clock.
+int kernel_swap_on(void) {
+    if (swap_enabled()) return SWAP_ERR_INVAL;
+    if (kfs_reserve_file(SWAP_FILE_PATH, SWAP_FILE_PAGES * PAGE_SIZE) < 0) {
+        serial_print("[swap] cannot reserve " SWAP_FILE_PATH "\n");
+        return SWAP_ERR_IO;
+    }
+    int32_t rc = swap_init(SWAP_FILE_PAGES, &swap_ops_32);
+    if (rc == SWAP_OK) {
+        serial_print("[swap] enabled: ");
+        serial_print_dec(SWAP_FILE_PAGES);
+        serial_print(" slots on " SWAP_FILE_PATH "\n");
+    }
+    return rc;
+}
+
+// Frame for an anonymous user page. Already zeroed, or 0 if none is left.
+static uint32_t user_frame_alloc_32(void) {
+    if (swap_enabled() && phys_alloc_end - phys_next_free < SWAP_FRAME_RESERVE) {
+        uint32_t fr = (uint32_t)swap_evict();
+        if (fr) {
+            volatile uint8_t *p = (volatile uint8_t *)fr;
+            for (int i = 0; i < PAGE_SIZE; i++) p[i] = 0;
+            return fr;
+        }
+    }
+    return frame_alloc_32();
+}
+
+// Bring back the page at addr if the clock swapped it out. Works for kernel
+// faults too (a syscall touching a user buffer). Returns 1 if resolved.
+static int swap_fault_in_32(uint32_t addr) {
+    uint32_t page_va = addr & ~0xFFFU;
+    uint32_t *pte = paging_pte_lookup_32(kernel_page_dir, page_va);
+    if (!pte || (*pte & PAGE_PRESENT_32) || !swap_is_swapped(page_va)) return 0;
+    uint32_t flags = *pte & PTE_FLAGS_MASK_32;
+    uint32_t fr = user_frame_alloc_32();
+    if (!fr || swap_in(page_va, fr) != SWAP_OK) {
+        serial_print("[swap] swap-in failed va=0x");
+        serial_print_hex(page_va);
+        serial_print("\n");
+        return 0;
+    }
+    paging_map_4k_32(kernel_page_dir, page_va, fr, flags);
+    __asm__ volatile("invlpg (%0)" : : "r"(page_va) : "memory");
+    return 1;
+}
+
+// ---- User-memory access primitives (usermem.rs) ----
+
+// Kernel tasks have no user stack; syscalls they make pass kernel pointers.
+static size_t usermem_current_pid_32(void) {
+    if (current_task_idx < 0) return 0;
+    TaskSlot32 *t = &task_slots[current_task_idx];
+    return t->ustack_frames[0] ? t->pid : 0;
+}
+
+static uint8_t usermem_find_range_32(size_t vaddr, usermem_range_t *out) {
+    if (current_task_idx < 0) return 0;
+    TaskSlot32 *t = &task_slots[current_task_idx];
+    uint32_t heap_end = (t->brk + 0xFFFU) & ~0xFFFU;
+    if (t->brk && vaddr >= USER_HEAP_BASE_32 && vaddr < heap_end) {
+        out->start = USER_HEAP_BASE_32;
+        out->end = heap_end;
+        out->writable = 1;
+        return 1;
+    }
+    VmaEntry32 *vma = vma_find_32(t->vma_list, (uint32_t)vaddr);
+    if (!vma) return 0;
+    out->start = vma->start;
+    out->end = vma->start + vma->length;
+    out->writable = (vma->prot & PROT_WRITE) ? 1 : 0;
+    return 1;
+}
+
+// Flags of the PTE, or of the 4 MB entry (the identity map), that maps vaddr
+static uint32_t usermem_page_flags_32(size_t vaddr) {
+    uint32_t pde = kernel_page_dir[(uint32_t)vaddr >> 22];
+    if ((pde & PAGE_PRESENT_32) && (pde & PAGE_PS_32)) return pde & PTE_FLAGS_MASK_32;
+    uint32_t *pte = paging_pte_lookup_32(kernel_page_dir, (uint32_t)vaddr);
+    return pte ? (*pte & PTE_FLAGS_MASK_32) : 0;
+}
+
+static uint8_t usermem_fault_in_32(size_t vaddr) {
+    if (swap_fault_in_32((uint32_t)vaddr)) return 1;
+    if (current_task_idx < 0) return 0;
+    return vma_demand_fill_32(&task_slots[current_task_idx], (uint32_t)vaddr) ? 1 : 0;
+}
+
+static const usermem_ops_t usermem_ops_32 = {
+    usermem_current_pid_32, usermem_find_range_32, usermem_page_flags_32, usermem_fault_in_32,
+};
+
 // Build a fake InterruptFrame32 on the task's stack so the first
 // context-switch into it pops+iret as if it was a resumed task.
 static int task_create_32(void (*entry)(void), uint32_t pid, uint16_t ticks) {
@@ -1807,6 +2384,7 @@ static int task_create_32(void (*entry)(void), uint32_t pid, uint16_t ticks) {
     if (slot < 0) return -1;
 
     task_slots[slot].active          = 1;
+    task_slots[slot].blocked         = 0;
     task_slots[slot].pid             = pid;
     task_slots[slot].ticks_remaining = ticks;
     task_slots[slot].ticks_total     = ticks;
@@ -1815,6 +2393,9 @@ static int task_create_32(void (*entry)(void), uint32_t pid, uint16_t ticks) {
     fd_table_init_32(slot);
     vma_init_32(task_slots[slot].vma_list);
     task_slots[slot].mmap_next       = 0;
+    for (int i = 0; i < USER_STACK_PAGES_32; i++)
+        task_slots[slot].ustack_frames[i] = 0;   // kernel task: no user stack
+    stack_paint_32(task_slots[slot].stack, TASK_STACK_SIZE);
 
     uint32_t *sp = (uint32_t*)(task_slots[slot].stack + TASK_STACK_SIZE);
 
@@ -1877,11 +2458,108 @@ static void phase18_worker_entry_32(void) {
 // (32 × 4 MB PSE pages) so they don't collide with the kernel's huge-page mappings.
 #define USER_CODE_VADDR_32  0x10000000U   // 256 MB — user .text page
 #define USER_STACK_TOP_32   0x10800000U   // 264 MB — top of user stack
-#define USER_STACK_SIZE_32  0x4000U       // 16 KB user stack
+#define USER_STACK_SIZE_32  (USER_STACK_PAGES_32 * 0x1000U)  // 16 KB user stack
+#define USER_STACK_GUARD_32 (USER_STACK_TOP_32 - USER_STACK_SIZE_32 - 0x1000U)  // unmapped
 
 // Legacy phase17 heartbeat task helpers removed.
 // User-space boot path is now shell-only via ELF loader (/bin/vsh32).
 
+// =============================================================================
+// Stack guard pages + high-water marks (x86)
+// =============================================================================
+
+// Fresh stacks are filled with STACK_PAINT_BYTE; the lowest overwritten byte
+// gives the deepest use so far without any per-call instrumentation.
+static void stack_paint_32(uint8_t *base, uint32_t size) {
+    for (uint32_t i = 0; i < size; i++) base[i] = STACK_PAINT_BYTE;
+}
+
+static uint32_t stack_untouched_32(const uint8_t *base, uint32_t size) {
+    uint32_t n = 0;
+    while (n < size && base[n] == STACK_PAINT_BYTE) n++;
+    return n;
+}
+
+static uint32_t kstack_used_32(const TaskSlot32 *t) {
+    return TASK_STACK_SIZE - stack_untouched_32(t->stack, TASK_STACK_SIZE);
+}
+
+// User stack frames lie in the identity map, so scan them physically —
+// every task maps its stack at the same virtual address.
+static uint32_t ustack_used_32(const TaskSlot32 *t) {
+    uint32_t untouched = 0;
+    for (int p = 0; p < USER_STACK_PAGES_32; p++) {
+        if (!t->ustack_frames[p]) return 0;   // kernel-only task
+        uint32_t n = stack_untouched_32((const uint8_t *)t->ustack_frames[p], PAGE_SIZE);
+        untouched += n;
+        if (n < PAGE_SIZE) break;
+    }
+    return USER_STACK_SIZE_32 - untouched;
+}
+
+// Push the slot's current high-water marks into its PCB (not IRQ-safe).
+static void stack_sync_usage_32(int slot) {
+    if (slot < 0 || slot >= MAX_TASKS || !task_slots[slot].active) return;
+    void *sched = get_kernel_scheduler();
+    if (!sched || task_slots[slot].pid == 0) return;
+    (void)scheduler_record_stack_usage(sched, task_slots[slot].pid,
+                                       kstack_used_32(&task_slots[slot]),
+                                       ustack_used_32(&task_slots[slot]));
+}
+
+// Map a fault address to the slot whose stack guard page it hit, or -1.
+// *kernel_stack is set to 1 for kernel-stack guards, 0 for the user stack.
+static int stack_guard_slot_32(uint32_t addr, uint8_t *kernel_stack) {
+    if (addr >= KSTACK_REGION_32 &&
+        addr <  KSTACK_REGION_32 + MAX_TASKS * KSTACK_SPAN_32 &&
+        (addr - KSTACK_REGION_32) % KSTACK_SPAN_32 < PAGE_SIZE) {
+        *kernel_stack = 1;
+        return (int)((addr - KSTACK_REGION_32) / KSTACK_SPAN_32);
+    }
+    if (addr >= USER_STACK_GUARD_32 && addr < USER_STACK_GUARD_32 + PAGE_SIZE &&
+        current_task_idx >= 0) {
+        *kernel_stack = 0;
+        return current_task_idx;
+    }
+    return -1;
+}
+
+static void report_stack_overflow_32(int slot, uint8_t kernel_stack) {
+    uint32_t pid = task_slots[slot].pid;
+    const char *which = kernel_stack ? " (kernel stack)\n" : " (user stack)\n";
+    serial_print("[PF] stack overflow in pid ");
+    serial_print_dec(pid);
+    serial_print(which);
+    terminal_writestring("stack overflow in pid ");
+    vga_print_dec(pid);
+    terminal_writestring(which);
+}
+
+// #DF task-gate entry. Runs on df_stack32 with the faulting context saved in
+// kernel_tss32; a kernel stack overflow usually ends up here because the CPU
+// cannot push the #PF frame once ESP is inside the guard page.
+static void double_fault_task_32(void) {
+    uint8_t kguard = 0;
+    int slot = stack_guard_slot_32(read_cr2_32(), &kguard);
+    if (slot < 0)
+        slot = stack_guard_slot_32(kernel_tss32.esp, &kguard);
+
+    serial_print("[DF] double fault eip=");
+    serial_print_hex(kernel_tss32.eip);
+    serial_print(" esp=");
+    serial_print_hex(kernel_tss32.esp);
+    serial_print("\n");
+    terminal_writestring("[EXCEPTION] ");
+    if (slot >= 0) {
+        report_stack_overflow_32(slot, kguard);
+    } else {
+        terminal_writestring("double fault\n");
+    }
+    serial_print("[DF] system halted\n");
+    __asm__ volatile("cli");
+    while (1) __asm__ volatile("hlt");
+}
+
 // =============================================================================
 // Phase 19: ELF Loader (32-bit)
 // =============================================================================
@@ -1961,10 +2639,15 @@ static int elf_exec_32(const char *path, uint16_t ticks) {
         return -1;
     }
 
-    uint32_t pid = scheduler_create_process(kernel_scheduler, 5, path);
+    int32_t pid = scheduler_create_process(kernel_scheduler, 5, path);
+    if (pid < 0) {
+        serial_print("[elf] create_process failed\n");
+        return -1;
+    }
 
     task_slots[slot].active          = 1;
-    task_slots[slot].pid             = pid;
+    task_slots[slot].blocked         = 0;
+    task_slots[slot].pid             = (uint32_t)pid;
     task_slots[slot].ticks_remaining = ticks;
     task_slots[slot].ticks_total     = ticks;
     task_slots[slot].ppid_slot       = 0;
@@ -1972,6 +2655,8 @@ static int elf_exec_32(const char *path, uint16_t ticks) {
     fd_table_init_32(slot);
     vma_init_32(task_slots[slot].vma_list);    // Phase 46: init VMAs
     task_slots[slot].mmap_next       = 0;
+    (void)scheduler_set_user_region(kernel_scheduler, pid, USER_CODE_VADDR_32,
+                                    USER_STACK_TOP_32 - USER_CODE_VADDR_32);
     Elf32_Phdr *phdr = (Elf32_Phdr *)(elf_load_buf_32 + ehdr->e_phoff);
     for (int i = 0; i < ehdr->e_phnum; i++) {
         if (phdr[i].p_type != PT_LOAD) continue;
@@ -2009,7 +2694,7 @@ static int elf_exec_32(const char *path, uint16_t ticks) {
         }
     }
 
-    // Map user stack (16 KB = 4 pages)
+    // Map user stack (16 KB = 4 pages) above an unmapped guard page
     for (uint32_t i = 0; i < USER_STACK_SIZE_32; i += PAGE_SIZE) {
         uint32_t frame = frame_alloc_32();
         if (!frame) {
@@ -2017,12 +2702,16 @@ static int elf_exec_32(const char *path, uint16_t ticks) {
             task_slots[slot].active = 0;
             return -1;
         }
+        stack_paint_32((uint8_t *)frame, PAGE_SIZE);
+        task_slots[slot].ustack_frames[i / PAGE_SIZE] = frame;
         paging_map_4k_32(kernel_page_dir, USER_STACK_TOP_32 - USER_STACK_SIZE_32 + i,
                          frame, PAGE_USER_32);
     }
+    paging_unmap_4k_32(kernel_page_dir, USER_STACK_GUARD_32);
     __asm__ volatile("mov %%cr3, %%eax; mov %%eax, %%cr3" ::: "eax", "memory");
 
     // Build iret frame on kernel stack
+    stack_paint_32(task_slots[slot].stack, TASK_STACK_SIZE);
     uint32_t *sp = (uint32_t*)(task_slots[slot].stack + TASK_STACK_SIZE);
 
     *(--sp) = 0x20 | 3;                         // SS  (0x23)
@@ -2072,15 +2761,16 @@ static int elf_exec_32(const char *path, uint16_t ticks) {
     return slot;
 }
 
-static int kernel_elf_exec_32(const char *path) {
+static int kernel_elf_exec_32(const char *path, uint16_t uid) {
     int slot = elf_exec_32(path, 24);
     if (slot >= 0) {
+        (void)scheduler_set_uid(kernel_scheduler, task_slots[slot].pid, uid);
         kernel_tss32.esp0 = (uint32_t)(task_slots[slot].stack + TASK_STACK_SIZE);
     }
     return slot >= 0 ? 0 : -1;
 }
 
-int (*g_elf_exec_fn)(const char *path) = (void *)0;
+int (*g_elf_exec_fn)(const char *path, uint16_t uid) = (void *)0;
 
 // =============================================================================
 // Phase 43: fork / execve / sbrk implementations (x86)
@@ -2094,7 +2784,8 @@ static int32_t sys_fork_32(InterruptFrame32 *frame) {
         if (!task_slots[i].active) { child_slot = i; break; }
     if (child_slot < 0) return -1;
 
-    uint32_t child_pid = scheduler_create_process(kernel_scheduler, 5, "fork-child");
+    int32_t child_pid = scheduler_create_process(kernel_scheduler, 5, "fork-child");
+    if (child_pid < 0) return -1;
 
     // Copy parent kernel stack
     for (int i = 0; i < TASK_STACK_SIZE; i++)
@@ -2104,7 +2795,8 @@ static int32_t sys_fork_32(InterruptFrame32 *frame) {
     fd_copy_32(current_task_idx, child_slot);
 
     task_slots[child_slot].active = 1;
-    task_slots[child_slot].pid = child_pid;
+    task_slots[child_slot].blocked = 0;
+    task_slots[child_slot].pid = (uint32_t)child_pid;
     task_slots[child_slot].ticks_remaining = task_slots[current_task_idx].ticks_total;
     task_slots[child_slot].ticks_total = task_slots[current_task_idx].ticks_total;
     task_slots[child_slot].ppid_slot = (uint32_t)current_task_idx;
@@ -2114,6 +2806,14 @@ static int32_t sys_fork_32(InterruptFrame32 *frame) {
     for (int i = 0; i < VMA_MAX_PER_TASK; i++)
         task_slots[child_slot].vma_list[i] = task_slots[current_task_idx].vma_list[i];
     task_slots[child_slot].mmap_next = task_slots[current_task_idx].mmap_next;
+    for (int i = 0; i < USER_STACK_PAGES_32; i++)   // same VA, same frames
+        task_slots[child_slot].ustack_frames[i] = task_slots[current_task_idx].ustack_frames[i];
+    shm_fork(kernel_scheduler, task_slots[current_task_idx].pid, child_pid);
+    (void)scheduler_set_parent(kernel_scheduler, child_pid, task_slots[current_task_idx].pid);
+    uint32_t ubase, usize;
+    if (scheduler_get_user_memory_layout(kernel_scheduler, task_slots[current_task_idx].pid,
+                                         &ubase, &usize) == 0)
+        (void)scheduler_set_user_region(kernel_scheduler, child_pid, ubase, usize);
 
     // Compute child ESP from parent frame offset
     uint32_t parent_stack_base = (uint32_t)task_slots[current_task_idx].stack;
@@ -2128,7 +2828,7 @@ static int32_t sys_fork_32(InterruptFrame32 *frame) {
     serial_print(" child_slot=");
     serial_print_dec(child_slot);
     serial_print(" child_pid=");
-    serial_print_dec(child_pid);
+    serial_print_dec((uint32_t)child_pid);
     serial_print("\n");
 
     return (int32_t)child_pid;
@@ -2175,13 +2875,16 @@ static int32_t sys_execve_32(InterruptFrame32 *frame, uint32_t path_ptr) {
         }
     }
 
-    // Remap user stack
+    // Remap user stack (guard page below stays unmapped)
     for (uint32_t i = 0; i < USER_STACK_SIZE_32; i += PAGE_SIZE) {
         uint32_t f = frame_alloc_32();
         if (!f) return -1;
+        stack_paint_32((uint8_t *)f, PAGE_SIZE);
+        task_slots[current_task_idx].ustack_frames[i / PAGE_SIZE] = f;
         paging_map_4k_32(kernel_page_dir, USER_STACK_TOP_32 - USER_STACK_SIZE_32 + i,
                          f, PAGE_USER_32);
     }
+    paging_unmap_4k_32(kernel_page_dir, USER_STACK_GUARD_32);
     __asm__ volatile("mov %%cr3, %%eax; mov %%eax, %%cr3" ::: "eax", "memory");
 
     // Reset fd table (keep 0/1/2)
@@ -2211,7 +2914,7 @@ static int32_t sys_sbrk_32(int32_t incr) {
     if (current_task_idx < 0) return -1;
     TaskSlot32 *t = &task_slots[current_task_idx];
     if (t->brk == 0)
-        t->brk = 0x20000000;  // initial program break
+        t->brk = USER_HEAP_BASE_32;  // initial program break
     uint32_t old_brk = t->brk;
     if (incr == 0) return (int32_t)old_brk;
     uint32_t new_brk = old_brk + (uint32_t)incr;
@@ -2219,9 +2922,10 @@ static int32_t sys_sbrk_32(int32_t incr) {
         uint32_t page_start = (old_brk + PAGE_SIZE - 1) & ~(PAGE_SIZE - 1);
         uint32_t page_end   = (new_brk + PAGE_SIZE - 1) & ~(PAGE_SIZE - 1);
         for (uint32_t va = page_start; va < page_end; va += PAGE_SIZE) {
-            uint32_t f = frame_alloc_32();
+            uint32_t f = user_frame_alloc_32();
             if (!f) return -1;
-            paging_map_4k_32(kernel_page_dir, va, f, PAGE_USER_32);
+            paging_map_4k_32(kernel_page_dir, va, f, PAGE_USER_32 | PAGE_WRITABLE_32);
+            (void)swap_track(t->pid, va, f);
         }
     }
     t->brk = new_brk;
@@ -2247,6 +2951,44 @@ static void vma_init_32(VmaEntry32 *vmas) {
         vmas[i].type = VMA_TYPE_NONE;
 }
 
+// Demand-fill the page at addr from t's VMA (zeroed or read from the backing
+// file). Returns 1 if a page was mapped, 0 if addr is not in a fillable VMA.
+// Shared by the #PF handler and usermem fault-in.
+static int vma_demand_fill_32(TaskSlot32 *t, uint32_t addr) {
+    VmaEntry32 *vma = vma_find_32(t->vma_list, addr);
+    if (!vma || vma->type == VMA_TYPE_SHM) return 0;
+    uint32_t page_va = addr & ~0xFFFU;
+    uint32_t fr = user_frame_alloc_32();
+    if (!fr) return 0;
+    uint32_t pg_flags = PAGE_USER_32;
+    if (vma->prot & PROT_WRITE) pg_flags |= PAGE_WRITABLE_32;
+
+    if (vma->type == VMA_TYPE_FILE && vma->path[0]) {
+        uint32_t page_off = (page_va - vma->start) + vma->file_offset;
+        uint32_t need = page_off + PAGE_SIZE;
+        if (need > sizeof(mmap_file_tmp_32)) need = sizeof(mmap_file_tmp_32);
+        int total = kfs_read_file(vma->path, mmap_file_tmp_32, need);
+        if (total > (int)page_off) {
+            int ncp = total - (int)page_off;
+            if (ncp > PAGE_SIZE) ncp = PAGE_SIZE;
+            uint8_t *dst = (uint8_t *)fr;
+            for (int b = 0; b < ncp; b++)
+                dst[b] = mmap_file_tmp_32[page_off + b];
+        }
+    }
+
+    paging_map_4k_32(kernel_page_dir, page_va, fr, pg_flags);
+    paging_flush_tlb_32();
+    if (vma->type == VMA_TYPE_ANON)
+        (void)swap_track(t->pid, page_va, fr);
+    serial_print("[demand-page] va=0x");
+    serial_print_hex(page_va);
+    serial_print(" -> frame=0x");
+    serial_print_hex(fr);
+    serial_print("\n");
+    return 1;
+}
+
 // mmap_file_tmp_32 defined earlier (forward declaration section)
 
 static int32_t sys_mmap_32(uint32_t length, uint32_t prot_flags, uint32_t path_ptr) {
@@ -2306,7 +3048,11 @@ static int32_t sys_munmap_32(uint32_t addr, uint32_t length) {
     (void)length;
 
     for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type == VMA_TYPE_SHM && t->vma_list[i].start == addr)
+            return sys_shm_unmap_32(addr);
         if (t->vma_list[i].type != VMA_TYPE_NONE && t->vma_list[i].start == addr) {
+            if (t->vma_list[i].type == VMA_TYPE_ANON)
+                (void)swap_untrack_range(addr, t->vma_list[i].length);
             t->vma_list[i].type = VMA_TYPE_NONE;
             serial_print("[munmap] va=0x");
             serial_print_hex(addr);
@@ -2317,6 +3063,133 @@ static int32_t sys_munmap_32(uint32_t addr, uint32_t length) {
     return -1;
 }
 
+// =============================================================================
+// Shared memory syscalls (x86) — Rust shm.rs keeps the registry
+// =============================================================================
+
+// All tasks share kernel_page_dir, so a forked child maps a segment at the
+// same address as its parent. Only tear the pages down once no other live
+// task still has a shm VMA there.
+static int shm_va_in_use_32(int skip_slot, uint32_t start) {
+    for (int s = 0; s < MAX_TASKS; s++) {
+        if (s == skip_slot || !task_slots[s].active) continue;
+        for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+            VmaEntry32 *v = &task_slots[s].vma_list[i];
+            if (v->type == VMA_TYPE_SHM && v->start == start) return 1;
+        }
+    }
+    return 0;
+}
+
+static void shm_unmap_pages_32(int slot, VmaEntry32 *v) {
+    if (!shm_va_in_use_32(slot, v->start)) {
+        for (uint32_t off = 0; off < v->length; off += PAGE_SIZE)
+            paging_unmap_4k_32(kernel_page_dir, v->start + off);
+        paging_flush_tlb_32();
+    }
+    v->type = VMA_TYPE_NONE;
+}
+
+static int32_t sys_shm_map_32(uint32_t id, uint32_t prot) {
+    TaskSlot32 *t = &task_slots[current_task_idx];
+    uint32_t length = (uint32_t)shm_size(id);
+    if (length == 0) return SHM_ERR_NOENT;
+
+    int slot = -1;
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type == VMA_TYPE_NONE) { slot = i; break; }
+    }
+    if (slot < 0) return SHM_ERR_LIMIT;
+
+    if (t->mmap_next == 0) t->mmap_next = MMAP_BASE_32;
+    uint32_t va = t->mmap_next;
+    if (!user_ptr_range_valid_32(va, length)) return SHM_ERR_LIMIT;
+
+    uint8_t writable = (prot & PROT_WRITE) ? 1 : 0;
+    size_t base = 0;
+    int32_t rc = shm_map(kernel_scheduler, t->pid, id, va, writable, &base);
+    if (rc < 0) return rc;
+    t->mmap_next += length;
+
+    VmaEntry32 *v = &t->vma_list[slot];
+    v->start  = va;
+    v->length = length;
+    v->type   = VMA_TYPE_SHM;
+    v->prot   = (uint8_t)(prot & 0xFF);
+    v->flags  = 0;
+    v->path[0] = '\0';
+    v->file_offset = id;
+
+    // Segment memory is resident and identity-mapped: map it eagerly.
+    uint32_t pg_flags = PAGE_USER_32 | (writable ? PAGE_WRITABLE_32 : 0);
+    for (uint32_t off = 0; off < length; off += PAGE_SIZE)
+        paging_map_4k_32(kernel_page_dir, va + off, (uint32_t)base + off, pg_flags);
+    paging_flush_tlb_32();
+
+    serial_print("[shm] map id=");
+    serial_print_dec(id);
+    serial_print(" va=0x");
+    serial_print_hex(va);
+    serial_print(" len=0x");
+    serial_print_hex(length);
+    serial_print("\n");
+    return (int32_t)va;
+}
+
+static int32_t sys_shm_unmap_32(uint32_t addr) {
+    if (current_task_idx < 0) return SHM_ERR_INVAL;
+    TaskSlot32 *t = &task_slots[current_task_idx];
+
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        VmaEntry32 *v = &t->vma_list[i];
+        if (v->type != VMA_TYPE_SHM || v->start != addr) continue;
+        int32_t rc = shm_unmap(kernel_scheduler, t->pid, addr);
+        if (rc < 0) return rc;
+        shm_unmap_pages_32(current_task_idx, v);
+        return SHM_OK;
+    }
+    return SHM_ERR_INVAL;
+}
+
+static int32_t sys_shm_32(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
+    if (current_task_idx < 0 || !kernel_scheduler) return SHM_ERR_INVAL;
+    uint32_t pid = task_slots[current_task_idx].pid;
+    char name[SYS_IO_PATH_MAX];
+
+    switch (num) {
+    case SYS_SHM_CREATE:
+        if (copy_user_path_32(name, a1) < 0) return SHM_ERR_INVAL;
+        return shm_create(kernel_scheduler, pid, name, a2, a3);
+    case SYS_SHM_OPEN:
+        if (copy_user_path_32(name, a1) < 0) return SHM_ERR_INVAL;
+        return shm_open(kernel_scheduler, pid, name, (a2 & SHM_O_WRITE) ? 1 : 0);
+    case SYS_SHM_MAP:
+        return sys_shm_map_32(a1, a2);
+    case SYS_SHM_UNMAP:
+        return sys_shm_unmap_32(a1);
+    case SYS_SHM_UNLINK:
+        if (copy_user_path_32(name, a1) < 0) return SHM_ERR_INVAL;
+        return shm_unlink(kernel_scheduler, pid, name);
+    case SYS_SHM_CLOSE:
+        return shm_close(pid, a1);
+    }
+    return SHM_ERR_INVAL;
+}
+
+// Drop a dying task's shared memory: its uring rings, which live in shm
+// segments, then its shm VMAs and its handles and mappings in the Rust
+// registry. sched is NULL on the OOM path, which must not touch the
+// scheduler from inside an allocation.
+static void shm_task_release_32(TaskSlot32 *t, void *sched) {
+    int slot = (int)(t - task_slots);
+    (void)uring_release_process(sched, t->pid);
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type == VMA_TYPE_SHM)
+            shm_unmap_pages_32(slot, &t->vma_list[i]);
+    }
+    (void)shm_release_process(sched, t->pid);
+}
+
 // =============================================================================
 // Phase 11: AI Auto-Tuner decision handler
 // =============================================================================
@@ -2385,6 +3258,21 @@ static void kernel_remediate_handler(const char *action, const char *target,
 static uint32_t g_ai_last_tick  = 0;
 static uint32_t g_ai_last_stat  = 0;
 
+// Send one "key|value" STAT event to the host bridge and the kernel engine.
+static void idle_send_stat_32(const char *key, uint32_t value, uint32_t now) {
+    char buf[32];
+    char *p = buf;
+    for (const char *k = key; *k; k++) *p++ = *k;
+    *p++ = '|';
+    uint32_t v = value; int i = 0; char tmp[12];
+    if (v == 0) { tmp[i++] = '0'; }
+    else { while (v) { tmp[i++] = (char)('0' + v % 10); v /= 10; } }
+    for (int j = i - 1; j >= 0; j--) *p++ = tmp[j];
+    *p = '\0';
+    ai_send_event("STAT", buf);
+    ai_kernel_engine_feed("STAT", buf, now);
+}
+
 void kernel_idle_work(void) {
     uint32_t now = kernel_tick;
     const uint32_t ai_tick_interval = (TIMER_HZ / 2) ? (TIMER_HZ / 2) : 1; // ~500ms
@@ -2399,22 +3287,25 @@ void kernel_idle_work(void) {
     // Every ~1s: send STAT events
     if (now - g_ai_last_stat >= ai_stat_interval) {
         g_ai_last_stat = now;
+        for (int i = 0; i < MAX_TASKS; i++)
+            stack_sync_usage_32(i);
         void *sched = get_kernel_scheduler();
-        if (sched) {
-            uint32_t procs = scheduler_get_process_count(sched);
-            char buf[24];
-            const char *key = "process_count|";
-            char *p = buf;
-            for (const char *k = key; *k; k++) *p++ = *k;
-            uint32_t v = procs; int i = 0; char tmp[12];
-            if (v == 0) { tmp[i++] = '0'; }
-            else { while (v) { tmp[i++] = (char)('0' + v % 10); v /= 10; } }
-            for (int j = i - 1; j >= 0; j--) *p++ = tmp[j];
-            *p = '\0';
-            ai_send_event("STAT", buf);
-            ai_kernel_engine_feed("STAT", buf, now);
+        if (sched)
+            idle_send_stat_32("process_count", scheduler_get_process_count(sched), now);
+        // Cumulative counts; the auto-tuner turns them into a rate.
+        swap_stats_t ss;
+        if (swap_enabled() && swap_get_stats(&ss)) {
+            idle_send_stat_32("swap_out", ss.swap_outs, now);
+            idle_send_stat_32("swap_in", ss.swap_ins, now);
         }
     }
+
+    // Pending uring operations; a syscall must not see the rings mid-update
+    uint32_t eflags;
+    __asm__ volatile("pushfl; popl %0; cli" : "=r"(eflags) :: "memory");
+    uring_poll(get_kernel_scheduler(), now);
+    task_wake_unblocked_32();
+    __asm__ volatile("pushl %0; popfl" :: "r"(eflags) : "memory", "cc");
 }
 
 // =============================================================================
@@ -3691,10 +4582,22 @@ void kernel_main(void) {
     // ----- Phase 16: Enable paging (identity map + PSE 4MB pages) -----
     paging_init_32();
 
+    // Heap growth draws on the frame pool, so register once paging is up
+    heap_set_grow_cb(kernel_heap_grow_cb);
+    heap_set_oom_tasks_cb(kernel_oom_tasks_cb);
+    heap_set_oom_kill_cb(kernel_oom_kill_cb);
+
     // ----- Framebuffer init (after heap) -----
     if (display_mode == 1) {
         volatile struct boot_info *bi = (volatile struct boot_info *)0x5300;
-        fb_init(bi->fb_addr, bi->fb_width, bi->fb_height, bi->fb_pitch, bi->fb_bpp);
+        if (fb_init(bi->fb_addr, bi->fb_width, bi->fb_height, bi->fb_pitch, bi->fb_bpp,
+                    (const struct fb_channels *)&bi->fb_channels) < 0) {
+            serial_print("[fb] Unsupported pixel format, using VGA text mode\n");
+            display_mode = 0;
+        }
+    }
+    if (display_mode == 1) {
+        volatile struct boot_info *bi = (volatile struct boot_info *)0x5300;
         console_init(bi->fb_width, bi->fb_height);
         console_clear();
         serial_print("[fb] Framebuffer mode: ");
@@ -3732,6 +4635,9 @@ void kernel_main(void) {
     pit_init();
     serial_print("[x86] PIT set to 240Hz\n");
 
+    // Kernel info page (read-only time/pid for user space)
+    kinfo_page_init_32();
+
     // Keyboard
     keyboard_init();
     tty_init_32(&kernel_tty0_32);
@@ -3750,6 +4656,8 @@ void kernel_main(void) {
 
     // Syscall subsystem
     syscall_init();
+    syscall_table_init_32();
+    (void)usermem_init(&usermem_ops_32);
     serial_print("[x86] syscall subsystem initialized\n");
 
     // IPC subsystem (Phase 4)
@@ -3784,6 +4692,7 @@ void kernel_main(void) {
     kfs_init();
     // Phase 48: Block cache
     bcache_init();
+    (void)kernel_swap_on();
     userdb_init();
     // Phase 14: Audit log
     auditlog_init();
@@ -3800,13 +4709,20 @@ void kernel_main(void) {
     CliSession *user_session = cli_session_create(shell, "root", CLI_PRIV_ROOT);
     gui_shell = shell;
     gui_session = user_session;
+    // Framebuffer console: shell on tty1, kernel log on tty2
+    if (display_mode == 1 && user_session) {
+        console_assign(0, VT_OWNER_SESSION, user_session->session_id);
+        console_assign(1, VT_OWNER_KERNEL, 0);
+    }
     serial_print("[x86] CLI system initialized\n");
 
     // Scheduler — create and register init process (PID 1)
     kernel_scheduler = scheduler_new();
-    uint32_t init_pid = scheduler_create_process(kernel_scheduler, 10, "init");
-    uint32_t ai_pid   = scheduler_create_process(kernel_scheduler, 8,  "ai_engine");
-    (void)ai_pid;
+    int32_t init_rc = scheduler_create_process(kernel_scheduler, 10, "init");
+    int32_t ai_rc   = scheduler_create_process(kernel_scheduler, 8,  "ai_engine");
+    if (init_rc < 0 || ai_rc < 0)
+        serial_print("[phase3] create_process failed\n");
+    uint32_t init_pid = init_rc > 0 ? (uint32_t)init_rc : 0;
     // Phase 10: Notify AI of initial process creation
     {
         char evbuf[48];
@@ -3828,11 +4744,12 @@ void kernel_main(void) {
     serial_print("[phase18] context switch setup...\n");
     task_register_main_32(init_pid, 24);   // 100ms quantum at 240 Hz
     {
-        uint32_t worker_pid = scheduler_create_process(kernel_scheduler, 9, "worker");
-        int worker_idx = task_create_32(phase18_worker_entry_32, worker_pid, 24);
+        int32_t worker_pid = scheduler_create_process(kernel_scheduler, 9, "worker");
+        int worker_idx = worker_pid > 0
+            ? task_create_32(phase18_worker_entry_32, (uint32_t)worker_pid, 24) : -1;
         if (worker_idx >= 0) {
             serial_print("[phase18] worker task created (pid=");
-            serial_print_dec(worker_pid);
+            serial_print_dec((uint32_t)worker_pid);
             serial_print(")\n");
         }
     }
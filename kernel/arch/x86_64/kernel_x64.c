@@ -11,6 +11,15 @@
 #include "userdb.h"
 #include "auditlog.h"
 #include "klog.h"
+#include "heap.h"
+#include "shm.h"
+#include "swap.h"
+#include "kerrno.h"
+#include "usermem.h"
+#include "strace.h"
+#include "uring.h"
+#include "kinfo.h"
+#include "console.h"
 
 #include "tcp.h"
 
@@ -28,19 +37,19 @@ struct boot_info {
     uint32_t fb_pitch;
     uint32_t fb_bpp;
     uint32_t fb_type;   // 0 = VGA text, 1 = framebuffer
+    // VBE channel layout (mode info RedMaskSize.. BlueFieldPosition), 0 = unknown
+    struct fb_channels {
+        uint8_t red_size, red_pos;
+        uint8_t green_size, green_pos;
+        uint8_t blue_size, blue_pos;
+    } fb_channels;
+    uint8_t  fb_reserved[2];
 };
 static int display_mode = 0;  // 0 = VGA text, 1 = framebuffer, 2 = GUI
 
-// Rust framebuffer/console FFI
-extern void fb_init(uint64_t addr, uint32_t width, uint32_t height,
-                    uint32_t pitch, uint32_t bpp);
-extern void console_init(uint32_t width, uint32_t height);
-extern void console_putchar(uint8_t c);
-extern void console_clear(void);
-extern void console_set_color_vga(uint8_t fg, uint8_t bg);
-extern void console_set_pos(uint32_t row, uint32_t col);
-extern void console_get_pos(uint32_t *row, uint32_t *col);
-extern void console_clear_to_eol(uint32_t row, uint32_t col);
+// Rust framebuffer FFI (console: console.h)
+extern int32_t fb_init(uint64_t addr, uint32_t width, uint32_t height,
+                       uint32_t pitch, uint32_t bpp, const struct fb_channels *channels);
 
 // Rust mouse FFI
 extern void mouse_init(uint32_t screen_w, uint32_t screen_h);
@@ -693,6 +702,7 @@ static void pic_send_eoi(uint8_t irq) {
 
 #define USER_VADDR_MIN_64 0x10000000ULL
 #define USER_VADDR_MAX_64 0x40000000ULL
+#define USER_HEAP_BASE_64 0x10100000ULL   // sbrk start when a task has no break yet
 #define SYS_IO_PATH_MAX   64U
 #define SYS_IO_BUF_MAX    4096U
 
@@ -703,48 +713,40 @@ static int user_ptr_range_valid_64(uint64_t addr, uint64_t len) {
     return addr >= USER_VADDR_MIN_64 && end <= USER_VADDR_MAX_64;
 }
 
+// Copy a NUL-terminated path (at most SYS_IO_PATH_MAX bytes) from user space.
 static int copy_user_path_64(char *dst, uint64_t user_ptr) {
-    if (!dst || user_ptr == 0) return -1;
-    if (user_ptr < USER_VADDR_MIN_64 || user_ptr >= USER_VADDR_MAX_64) return -1;
-
-    const char *src = (const char *)(uintptr_t)user_ptr;
-    for (uint32_t i = 0; i < SYS_IO_PATH_MAX; i++) {
-        char c = src[i];
-        dst[i] = c;
-        if (c == '\0') return 0;
-    }
-    dst[SYS_IO_PATH_MAX - 1] = '\0';
-    return -1;
+    if (!dst) return -EINVAL;
+    intptr_t n = usermem_strncpy_from_user(dst, (size_t)user_ptr, SYS_IO_PATH_MAX);
+    return n < 0 ? (int)n : 0;
 }
 
 static int64_t syscall_vfs_read_64(uint64_t path_ptr, uint64_t user_buf_ptr, uint64_t max_len) {
-    if (max_len == 0 || max_len > SYS_IO_BUF_MAX) return -1;
-    if (!user_ptr_range_valid_64(user_buf_ptr, max_len)) return -1;
+    if (max_len == 0 || max_len > SYS_IO_BUF_MAX) return -EINVAL;
+    if (usermem_access_ok((size_t)user_buf_ptr, (size_t)max_len, 1) < 0) return -EFAULT;
 
     char path[SYS_IO_PATH_MAX];
-    if (copy_user_path_64(path, path_ptr) < 0) return -1;
+    if (copy_user_path_64(path, path_ptr) < 0) return -EFAULT;
 
     static uint8_t kbuf[SYS_IO_BUF_MAX];
     int n = kfs_read_file(path, kbuf, (size_t)max_len);
-    if (n < 0) return -1;
+    if (n < 0) return -ENOENT;
 
-    uint8_t *dst = (uint8_t *)(uintptr_t)user_buf_ptr;
-    for (int i = 0; i < n; i++) dst[i] = kbuf[i];
-    return n;
+    int32_t rc = usermem_copy_to_user((size_t)user_buf_ptr, kbuf, (size_t)n);
+    return rc < 0 ? rc : n;
 }
 
 static int64_t syscall_vfs_write_64(uint64_t path_ptr, uint64_t user_buf_ptr, uint64_t len) {
-    if (len == 0 || len > SYS_IO_BUF_MAX) return -1;
-    if (!user_ptr_range_valid_64(user_buf_ptr, len)) return -1;
+    if (len == 0 || len > SYS_IO_BUF_MAX) return -EINVAL;
 
     char path[SYS_IO_PATH_MAX];
-    if (copy_user_path_64(path, path_ptr) < 0) return -1;
+    if (copy_user_path_64(path, path_ptr) < 0) return -EFAULT;
 
     static uint8_t kbuf[SYS_IO_BUF_MAX];
-    const uint8_t *src = (const uint8_t *)(uintptr_t)user_buf_ptr;
-    for (uint64_t i = 0; i < len; i++) kbuf[i] = src[i];
+    int32_t rc = usermem_copy_from_user(kbuf, (size_t)user_buf_ptr, (size_t)len);
+    if (rc < 0) return rc;
 
-    return kfs_write_file(path, kbuf, (size_t)len);
+    int n = kfs_write_file(path, kbuf, (size_t)len);
+    return n < 0 ? -EIO : n;
 }
 
 static void pit_init(void) {
@@ -761,6 +763,9 @@ static void pit_init(void) {
 
 volatile uint64_t kernel_tick = 0;
 
+// Read-only info page mapped into user space (kinfo.rs); null until mapped.
+static volatile KernelInfoPage *kinfo_page = 0;
+
 uint64_t get_kernel_tick(void) {
     return kernel_tick;
 }
@@ -997,6 +1002,16 @@ int keyboard_read_char(char *out) {
 typedef struct KernelTTY_ KernelTTY;
 static void tty_push_char(KernelTTY *tty, char c);
 static KernelTTY kernel_tty0;
+static KernelTTY *console_tty(uint32_t vt, uint32_t pid);
+
+// Framebuffer console: keys go to the owner of the active virtual console.
+// The shell's console also feeds kernel_tty0, as text mode always has; a
+// process's console feeds its own TTY.
+static uint32_t keyboard_input_owner(uint32_t *id) {
+    *id = 0;
+    if (display_mode != 1) return VT_OWNER_SESSION;
+    return console_input_owner(id);
+}
 
 static void keyboard_handle_scancode(uint8_t scancode) {
     // Extended key prefix (0xE0) — next scancode is an extended key
@@ -1005,6 +1020,11 @@ static void keyboard_handle_scancode(uint8_t scancode) {
     if (kbd_state.extended) {
         kbd_state.extended = 0;
         if (scancode & 0x80) return;  // extended key release — ignore
+        // Shift+PageUp/PageDown page the framebuffer console's scrollback
+        if (kbd_state.shift_held && display_mode == 1 && (scancode == 0x49 || scancode == 0x51)) {
+            console_scroll_view(scancode == 0x49 ? 1 : -1);
+            return;
+        }
         char code = 0;
         switch (scancode) {
             case 0x48: code = (char)0x80; break;  // Up
@@ -1015,7 +1035,8 @@ static void keyboard_handle_scancode(uint8_t scancode) {
             case 0x47: code = (char)0x85; break;  // Home
             case 0x4F: code = (char)0x86; break;  // End
         }
-        if (code) {
+        uint32_t id;
+        if (code && keyboard_input_owner(&id) == VT_OWNER_SESSION) {
             uint32_t next = (kbd_state.write_pos + 1) % KBD_BUFFER_SIZE;
             if (next != kbd_state.read_pos) {
                 kbd_state.buffer[kbd_state.write_pos] = code;
@@ -1040,19 +1061,31 @@ static void keyboard_handle_scancode(uint8_t scancode) {
     if (scancode == 0x38) { kbd_state.alt_held  = 1; return; }
     if (scancode == 0x3A) { kbd_state.caps_lock ^= 1; return; }
 
+    // Alt+F1..F6 switch virtual consoles
+    if (kbd_state.alt_held && display_mode == 1 && scancode >= 0x3B && scancode <= 0x40) {
+        console_switch((uint32_t)(scancode - 0x3B));
+        return;
+    }
+
     if (scancode >= 128) return;
 
     uint8_t shift = kbd_state.shift_held ^ kbd_state.caps_lock;
     char c = shift ? scancode_ascii_shift[scancode] : scancode_ascii[scancode];
     if (!c) return;
 
-    uint32_t next = (kbd_state.write_pos + 1) % KBD_BUFFER_SIZE;
-    if (next != kbd_state.read_pos) {
-        kbd_state.buffer[kbd_state.write_pos] = c;
-        kbd_state.write_pos = next;
+    uint32_t id;
+    uint32_t owner = keyboard_input_owner(&id);
+    if (owner == VT_OWNER_SESSION) {
+        uint32_t next = (kbd_state.write_pos + 1) % KBD_BUFFER_SIZE;
+        if (next != kbd_state.read_pos) {
+            kbd_state.buffer[kbd_state.write_pos] = c;
+            kbd_state.write_pos = next;
+        }
+        // Phase 42: also push to TTY for user-space stdin
+        tty_push_char(&kernel_tty0, c);
+    } else if (owner == VT_OWNER_PROCESS) {
+        tty_push_char(console_tty(console_active(), id), c);
     }
-    // Phase 42: also push to TTY for user-space stdin
-    tty_push_char(&kernel_tty0, c);
 }
 
 // =============================================================================
@@ -1113,20 +1146,56 @@ static void log_page_fault_detail(uint64_t error_code) {
     terminal_writestring("\n");
 }
 
+// Registers at SYSCALL entry as syscall.asm pushes them, lowest address
+// first. The CPU has already replaced rcx with the return RIP and r11 with
+// RFLAGS.
+typedef struct {
+    uint64_t rax, rdx, rsi, rdi, r10, r9, r8;
+    uint64_t r15, r14, r13, r12, rbx, rbp;
+    uint64_t rflags, rip;
+} SyscallEntryFrame;
+
 // Forward declarations
 uint64_t c_syscall_handler(uint64_t num, uint64_t arg1, uint64_t arg2,
-                            uint64_t arg3, uint64_t arg4);
+                            uint64_t arg3, uint64_t arg4,
+                            const SyscallEntryFrame *regs);
 extern uint32_t scheduler_get_process_count(const void *sched);
 extern uint32_t scheduler_get_current_pid(const void *sched);
 extern int scheduler_kill_process(void *sched, uint32_t pid);
 extern uint32_t scheduler_schedule(void *sched);
 extern void *get_kernel_scheduler(void);
+extern uint32_t kernel_current_pid(void);
 extern void scheduler_terminate_current(void *sched, int32_t exit_code);
 extern int32_t scheduler_get_exit_code(const void *sched, size_t pid);
+extern int scheduler_record_stack_usage(void *sched, size_t pid,
+                                        size_t kernel_used, size_t user_used);
+extern int32_t scheduler_get_user_memory_layout(const void *sched, size_t pid,
+                                                uint32_t *out_base, uint32_t *out_size);
+extern int32_t scheduler_set_user_region(void *sched, size_t pid, size_t base, size_t size);
+extern int32_t scheduler_set_parent(void *sched, size_t pid, size_t ppid);
+extern int32_t scheduler_set_uid(void *sched, size_t pid, uint16_t uid);
+extern int32_t scheduler_wake_process(void *sched, size_t pid);
+extern int32_t scheduler_is_waiting(const void *sched, size_t pid);
+extern int64_t syscall_handler(size_t pid, uint32_t num, uint64_t arg1, uint64_t arg2, uint64_t arg3);
+extern int64_t syscall_gate(size_t pid, uint32_t num, uint64_t arg1, uint64_t arg2, uint64_t arg3);
+extern void syscall_account(uint32_t num, int64_t ret);
+
+// Layout of CpuContext (scheduler_base.h)
+typedef struct {
+    uint64_t rip, rsp, rbp;
+    uint64_t rax, rbx, rcx, rdx, rsi, rdi;
+    uint64_t r8, r9, r10, r11, r12, r13, r14, r15;
+    uint64_t rflags;
+} SyscallContext;
+extern int32_t scheduler_save_context(void *sched, size_t pid, const SyscallContext *ctx);
 
 // Phase 18 types & state — must be visible to interrupt_dispatch below
 #define MAX_TASKS          8
 #define TASK_STACK_SIZE    0x4000   // 16 KB per task
+#define USER_STACK_PAGES   4        // 16 KB user stack
+#define KSTACK_REGION      0x0F000000ULL   // 240 MB — guarded kernel stacks (4 KB pages)
+#define KSTACK_SPAN        (0x1000ULL + TASK_STACK_SIZE)   // guard page + stack
+#define STACK_PAINT_BYTE   0xA5     // untouched-stack fill for high-water marks
 
 // =============================================================================
 // Phase 41: Per-process file descriptor table
@@ -1150,14 +1219,38 @@ typedef struct KernelTTY_ {
     char     line_buf[TTY_BUF_SIZE]; // cooked mode: accumulate until Enter
     uint32_t line_len;
     uint8_t  line_ready;         // 1 = line available for read
+    uint32_t owner;              // console TTYs: pid the queued input is for
 } KernelTTY;
 
+// Input queue of each framebuffer virtual console, for the process that
+// owns it (kernel_tty0 is the shell's).
+static KernelTTY console_ttys[CONSOLE_VT_COUNT];
+
 static void tty_init(KernelTTY *tty) {
     for (int i = 0; i < TTY_BUF_SIZE; i++) {
         tty->input_buf[i] = 0; tty->line_buf[i] = 0;
     }
     tty->input_read = tty->input_write = tty->input_count = 0;
     tty->cooked = 1; tty->line_len = 0; tty->line_ready = 0;
+    tty->owner = 0;
+}
+
+// TTY of console vt for process pid. A console handed to another process
+// starts with an empty queue, so keys typed for the old owner stay unread.
+static KernelTTY *console_tty(uint32_t vt, uint32_t pid) {
+    KernelTTY *tty = &console_ttys[vt % CONSOLE_VT_COUNT];
+    if (tty->owner != pid) {
+        tty_init(tty);
+        tty->owner = pid;
+    }
+    return tty;
+}
+
+// Console owned by the running process, or -1 (text mode, or none).
+static int32_t tty_console(void) {
+    if (display_mode != 1) return -1;
+    int32_t vt = console_lookup(VT_OWNER_PROCESS, kernel_current_pid());
+    return vt >= 0 ? vt : -1;
 }
 
 static void tty_push_char(KernelTTY *tty, char c) {
@@ -1209,9 +1302,13 @@ static int tty_read(KernelTTY *tty, char *buf, int max) {
 }
 
 static int tty_write(const char *buf, int len) {
+    // Framebuffer console: a process that owns a virtual console writes there
+    int32_t vt = tty_console();
+    if (vt >= 0) console_write_to((uint32_t)vt, (const uint8_t *)buf, (uint32_t)len);
+
     // Output to VGA + serial
     for (int i = 0; i < len; i++) {
-        terminal_putchar(buf[i]);
+        if (vt < 0) terminal_putchar(buf[i]);
         serial_putchar(buf[i]);
     }
     return len;
@@ -1249,6 +1346,7 @@ typedef struct {
 #define VMA_TYPE_NONE    0
 #define VMA_TYPE_ANON    1
 #define VMA_TYPE_FILE    2
+#define VMA_TYPE_SHM     3   // shared memory segment (file_offset = shm id)
 
 #define PROT_READ    0x01
 #define PROT_WRITE   0x02
@@ -1273,6 +1371,7 @@ typedef struct {
     uint64_t rsp;                  // saved stack pointer (into stack[])
     uint32_t pid;                  // associated scheduler PID
     uint8_t  active;               // 1 = slot in use
+    uint8_t  blocked;              // parked by a syscall until the scheduler wakes it
     uint16_t ticks_remaining;      // ticks until preemption
     uint16_t ticks_total;          // full time-slice (reload value)
     FdEntry  fd_table[FD_MAX];     // Phase 41: per-process fd table
@@ -1280,7 +1379,8 @@ typedef struct {
     uint64_t brk;                  // Phase 43: program break for sbrk
     VmaEntry vma_list[VMA_MAX_PER_TASK]; // Phase 46: mmap regions
     uint64_t mmap_next;            // Phase 46: next mmap virtual address
-    uint8_t  stack[TASK_STACK_SIZE] __attribute__((aligned(16)));
+    uint64_t ustack_frames[USER_STACK_PAGES]; // user stack frames, lowest first
+    uint8_t *stack;                // TASK_STACK_SIZE bytes above an unmapped guard page
 } TaskSlot;
 
 static TaskSlot  task_slots[MAX_TASKS];
@@ -1368,16 +1468,19 @@ static int64_t sys_read_fd(FdEntry *fdt, int fd, uint64_t buf_ptr, uint64_t coun
     if (fd < 0 || fd >= FD_MAX || fdt[fd].type == FD_TYPE_NONE) return -1;
     if (count == 0) return 0;
     if (count > SYS_IO_BUF_MAX) count = SYS_IO_BUF_MAX;
-    if (!user_ptr_range_valid_64(buf_ptr, count)) return -1;
+    if (usermem_access_ok((size_t)buf_ptr, (size_t)count, 1) < 0) return -EFAULT;
 
     if (fdt[fd].type == FD_TYPE_TTY) {
         // stdin read: block-wait for data from TTY
-        // Non-blocking for now: return available data or 0
+        // Non-blocking for now: return available data or 0. A process that
+        // owns a framebuffer console reads that console's keys.
+        int32_t vt = tty_console();
+        KernelTTY *tty = (vt >= 0) ? console_tty((uint32_t)vt, kernel_current_pid()) : &kernel_tty0;
         char tmp[SYS_IO_BUF_MAX];
-        int n = tty_read(&kernel_tty0, tmp, (int)count);
+        int n = tty_read(tty, tmp, (int)count);
         if (n > 0) {
-            char *ubuf = (char *)buf_ptr;
-            for (int i = 0; i < n; i++) ubuf[i] = tmp[i];
+            int32_t rc = usermem_copy_to_user((size_t)buf_ptr, tmp, (size_t)n);
+            if (rc < 0) return rc;
         }
         return n;
     }
@@ -1385,13 +1488,18 @@ static int64_t sys_read_fd(FdEntry *fdt, int fd, uint64_t buf_ptr, uint64_t coun
         uint8_t pidx = fdt[fd].pipe_idx;
         if (pidx >= MAX_PIPES || !kernel_pipes[pidx].active) return -1;
         KernelPipe *p = &kernel_pipes[pidx];
+        // Stage into a kernel buffer; only consume once the copy succeeded
+        static char pipe_tmp_buf[SYS_IO_BUF_MAX];
         int n = 0;
-        char *ubuf = (char *)buf_ptr;
-        while (n < (int)count && p->count > 0) {
-            ubuf[n++] = p->buf[p->read_pos];
-            p->read_pos = (p->read_pos + 1) % PIPE_BUF_SIZE;
-            p->count--;
+        uint32_t pos = p->read_pos;
+        while (n < (int)count && n < (int)p->count) {
+            pipe_tmp_buf[n++] = p->buf[pos];
+            pos = (pos + 1) % PIPE_BUF_SIZE;
         }
+        int32_t rc = usermem_copy_to_user((size_t)buf_ptr, pipe_tmp_buf, (size_t)n);
+        if (rc < 0) return rc;
+        p->read_pos = pos;
+        p->count -= (uint32_t)n;
         return n;
     }
     if (fdt[fd].type == FD_TYPE_FILE) {
@@ -1402,9 +1510,8 @@ static int64_t sys_read_fd(FdEntry *fdt, int fd, uint64_t buf_ptr, uint64_t coun
         int avail = total - (int)fdt[fd].offset;
         if (avail <= 0) return 0; // EOF
         int n = (int)count < avail ? (int)count : avail;
-        uint8_t *ubuf = (uint8_t *)buf_ptr;
-        for (int i = 0; i < n; i++)
-            ubuf[i] = fd_tmp_buf[fdt[fd].offset + (uint32_t)i];
+        int32_t rc = usermem_copy_to_user((size_t)buf_ptr, fd_tmp_buf + fdt[fd].offset, (size_t)n);
+        if (rc < 0) return rc;
         fdt[fd].offset += (uint32_t)n;
         return n;
     }
@@ -1415,34 +1522,34 @@ static int64_t sys_write_fd(FdEntry *fdt, int fd, uint64_t buf_ptr, uint64_t cou
     if (fd < 0 || fd >= FD_MAX || fdt[fd].type == FD_TYPE_NONE) return -1;
     if (count == 0) return 0;
     if (count > SYS_IO_BUF_MAX) count = SYS_IO_BUF_MAX;
-    if (!user_ptr_range_valid_64(buf_ptr, count)) return -1;
 
-    if (fdt[fd].type == FD_TYPE_TTY) {
-        // stdout/stderr: output to terminal
-        return (int64_t)tty_write((const char *)buf_ptr, (int)count);
-    }
+    static char fd_wr_buf[SYS_IO_BUF_MAX];
     if (fdt[fd].type == FD_TYPE_PIPE_W) {
         uint8_t pidx = fdt[fd].pipe_idx;
         if (pidx >= MAX_PIPES || !kernel_pipes[pidx].active) return -1;
         KernelPipe *p = &kernel_pipes[pidx];
-        int n = 0;
-        const char *ubuf = (const char *)buf_ptr;
-        while (n < (int)count && p->count < PIPE_BUF_SIZE) {
-            p->buf[p->write_pos] = ubuf[n++];
+        uint32_t space = PIPE_BUF_SIZE - p->count;
+        if (count > space) count = space;
+        int32_t rc = usermem_copy_from_user(fd_wr_buf, (size_t)buf_ptr, (size_t)count);
+        if (rc < 0) return rc;
+        for (uint64_t n = 0; n < count; n++) {
+            p->buf[p->write_pos] = fd_wr_buf[n];
             p->write_pos = (p->write_pos + 1) % PIPE_BUF_SIZE;
             p->count++;
         }
-        return n;
+        return (int64_t)count;
     }
-    if (fdt[fd].type == FD_TYPE_FILE) {
-        // Write to VFS file
-        static uint8_t fd_wr_buf[SYS_IO_BUF_MAX];
-        const uint8_t *ubuf = (const uint8_t *)buf_ptr;
-        for (int i = 0; i < (int)count; i++) fd_wr_buf[i] = ubuf[i];
-        int rc = kfs_write_file(fdt[fd].path, fd_wr_buf, count);
-        return rc < 0 ? -1 : (int64_t)count;
+    if (fdt[fd].type != FD_TYPE_TTY && fdt[fd].type != FD_TYPE_FILE) return -1;
+
+    int32_t rc = usermem_copy_from_user(fd_wr_buf, (size_t)buf_ptr, (size_t)count);
+    if (rc < 0) return rc;
+    if (fdt[fd].type == FD_TYPE_TTY) {
+        // stdout/stderr: output to terminal
+        return (int64_t)tty_write(fd_wr_buf, (int)count);
     }
-    return -1;
+    // Write to VFS file
+    int wrc = kfs_write_file(fdt[fd].path, (const uint8_t *)fd_wr_buf, count);
+    return wrc < 0 ? -1 : (int64_t)count;
 }
 
 static int64_t sys_close(FdEntry *fdt, int fd) {
@@ -1469,7 +1576,7 @@ static int64_t sys_dup2(FdEntry *fdt, int oldfd, int newfd) {
 }
 
 static int64_t sys_pipe(FdEntry *fdt, uint64_t fds_ptr) {
-    if (!user_ptr_range_valid_64(fds_ptr, 8)) return -1;
+    if (usermem_access_ok((size_t)fds_ptr, 2 * sizeof(int32_t), 1) < 0) return -EFAULT;
     // Find a free pipe
     int pidx = -1;
     for (int i = 0; i < MAX_PIPES; i++)
@@ -1489,9 +1596,8 @@ static int64_t sys_pipe(FdEntry *fdt, uint64_t fds_ptr) {
     kernel_pipes[pidx].read_pos = 0;
     kernel_pipes[pidx].write_pos = 0;
     kernel_pipes[pidx].count = 0;
-    int32_t *ufds = (int32_t *)fds_ptr;
-    ufds[0] = rfd; ufds[1] = wfd;
-    return 0;
+    int32_t ufds[2] = { rfd, wfd };
+    return usermem_copy_to_user((size_t)fds_ptr, ufds, sizeof(ufds));
 }
 
 // =============================================================================
@@ -1508,15 +1614,26 @@ static int elf_exec(const char *path, uint16_t ticks);
 #endif
 static uint64_t frame_alloc(void);
 static void paging_map_4k(uint64_t *pml4, uint64_t virt, uint64_t phys, uint64_t flags);
+static void paging_unmap_4k(uint64_t *pml4, uint64_t virt);
 static void paging_flush_tlb(void);
 static uint64_t *kernel_pml4;
 static uint8_t elf_load_buf[];
-extern uint32_t scheduler_create_process(void *sched, uint8_t priority, const char *command);
+extern int64_t scheduler_create_process(void *sched, uint8_t priority, const char *command);
 // Phase 46 forward declarations
 static VmaEntry *vma_find(VmaEntry *vmas, uint64_t addr);
 static void vma_init(VmaEntry *vmas);
+static int vma_demand_fill(TaskSlot *t, uint64_t addr);
 static int64_t sys_mmap(uint64_t length, uint64_t prot_flags, uint64_t path_ptr);
 static int64_t sys_munmap(uint64_t addr, uint64_t length);
+static int64_t sys_shm(uint64_t num, uint64_t a1, uint64_t a2, uint64_t a3);
+static int64_t sys_shm_unmap(uint64_t addr);
+static void shm_task_release(TaskSlot *t, void *sched);
+static uint64_t user_frame_alloc(void);
+static int  swap_fault_in(uint64_t addr);
+static int  stack_guard_slot(uint64_t addr, uint8_t *kernel_stack);
+static void report_stack_overflow(int slot, uint8_t kernel_stack);
+static void stack_sync_usage(int slot);
+static void stack_paint(uint8_t *base, uint64_t size);
 #ifndef PAGE_SIZE
 #define PAGE_SIZE 4096
 #endif
@@ -1537,11 +1654,13 @@ static int64_t sys_fork(InterruptFrame *frame) {
     // Create scheduler process
     void *sched = get_kernel_scheduler();
     if (!sched) return -1;
-    uint32_t child_pid = scheduler_create_process(sched, 5, "forked");
+    int64_t child_pid = scheduler_create_process(sched, 5, "forked");
+    if (child_pid < 0) return -1;
 
     TaskSlot *child = &task_slots[child_slot];
     child->active = 1;
-    child->pid = child_pid;
+    child->blocked = 0;
+    child->pid = (uint32_t)child_pid;
     child->ticks_remaining = parent->ticks_total;
     child->ticks_total = parent->ticks_total;
     child->ppid_slot = (uint32_t)current_task_idx;
@@ -1554,6 +1673,13 @@ static int64_t sys_fork(InterruptFrame *frame) {
     for (int i = 0; i < VMA_MAX_PER_TASK; i++)
         child->vma_list[i] = parent->vma_list[i];
     child->mmap_next = parent->mmap_next;
+    for (int i = 0; i < USER_STACK_PAGES; i++)   // same VA, same frames
+        child->ustack_frames[i] = parent->ustack_frames[i];
+    shm_fork(sched, parent->pid, child_pid);
+    (void)scheduler_set_parent(sched, child_pid, parent->pid);
+    uint32_t ubase, usize;
+    if (scheduler_get_user_memory_layout(sched, parent->pid, &ubase, &usize) == 0)
+        (void)scheduler_set_user_region(sched, child_pid, ubase, usize);
 
     // Copy kernel stack (contains the iretq frame for the child to resume)
     for (int i = 0; i < TASK_STACK_SIZE; i++)
@@ -1644,13 +1770,16 @@ static int64_t sys_execve(InterruptFrame *frame, uint64_t path_ptr) {
             task_slots[current_task_idx].brk = seg_end;
     }
 
-    // Remap user stack
+    // Remap user stack (guard page below stays unmapped)
     for (uint64_t i = 0; i < 0x4000; i += 0x1000) {
         uint64_t fr = frame_alloc();
         if (!fr) return -1;
+        stack_paint((uint8_t *)fr, 0x1000);
+        task_slots[current_task_idx].ustack_frames[i / 0x1000] = fr;
         paging_map_4k(kernel_pml4, 0x10800000ULL - 0x4000ULL + i, fr,
                       PAGE_PRESENT | PAGE_WRITABLE | PAGE_USER);
     }
+    paging_unmap_4k(kernel_pml4, 0x10800000ULL - 0x4000ULL - 0x1000ULL);
     paging_flush_tlb();
 
     // Reset fd table: keep fd 0/1/2, close rest
@@ -1677,7 +1806,7 @@ static int64_t sys_execve(InterruptFrame *frame, uint64_t path_ptr) {
 static int64_t sys_sbrk(int64_t increment) {
     if (current_task_idx < 0) return -1;
     TaskSlot *t = &task_slots[current_task_idx];
-    if (t->brk == 0) t->brk = 0x10100000ULL; // default heap start
+    if (t->brk == 0) t->brk = USER_HEAP_BASE_64; // default heap start
     uint64_t old_brk = t->brk;
     if (increment == 0) return (int64_t)old_brk;
     uint64_t new_brk = old_brk + (uint64_t)increment;
@@ -1686,9 +1815,10 @@ static int64_t sys_sbrk(int64_t increment) {
         uint64_t page_start = (old_brk + 0xFFF) & ~0xFFFULL;
         uint64_t page_end = (new_brk + 0xFFF) & ~0xFFFULL;
         for (uint64_t va = page_start; va < page_end; va += 0x1000) {
-            uint64_t fr = frame_alloc();
+            uint64_t fr = user_frame_alloc();
             if (!fr) return -1;
             paging_map_4k(kernel_pml4, va, fr, PAGE_PRESENT | PAGE_WRITABLE | PAGE_USER);
+            (void)swap_track(t->pid, va, fr);
         }
         paging_flush_tlb();
     }
@@ -1715,9 +1845,49 @@ static void vma_init(VmaEntry *vmas) {
         vmas[i].type = VMA_TYPE_NONE;
 }
 
-// Temp buffer for file-backed demand paging (shared, only used in #PF handler)
+// Temp buffer for file-backed demand paging (shared by the #PF handler and
+// usermem fault-in; neither can re-enter the other)
 static uint8_t mmap_file_tmp[65536];
 
+// Demand-fill the page at addr from t's VMA (zeroed or read from the backing
+// file). Returns 1 if a page was mapped, 0 if addr is not in a fillable VMA.
+static int vma_demand_fill(TaskSlot *t, uint64_t addr) {
+    VmaEntry *vma = vma_find(t->vma_list, addr);
+    if (!vma || vma->type == VMA_TYPE_SHM) return 0;
+    uint64_t page_va = addr & ~0xFFFULL;
+    uint64_t fr = user_frame_alloc();
+    if (!fr) return 0;
+    uint64_t pg_flags = PAGE_PRESENT | PAGE_USER;
+    if (vma->prot & PROT_WRITE) pg_flags |= PAGE_WRITABLE;
+
+    if (vma->type == VMA_TYPE_FILE && vma->path[0]) {
+        // File-backed: read file data into the frame
+        uint32_t page_off = (uint32_t)(page_va - vma->start) + vma->file_offset;
+        uint32_t need = page_off + PAGE_SIZE;
+        if (need > sizeof(mmap_file_tmp)) need = sizeof(mmap_file_tmp);
+        int total = kfs_read_file(vma->path, mmap_file_tmp, need);
+        if (total > (int)page_off) {
+            int ncp = total - (int)page_off;
+            if (ncp > PAGE_SIZE) ncp = PAGE_SIZE;
+            uint8_t *dst = (uint8_t *)fr;
+            for (int b = 0; b < ncp; b++)
+                dst[b] = mmap_file_tmp[page_off + b];
+        }
+    }
+    // Anonymous: frame already zeroed by user_frame_alloc
+
+    paging_map_4k(kernel_pml4, page_va, fr, pg_flags);
+    paging_flush_tlb();
+    if (vma->type == VMA_TYPE_ANON)
+        (void)swap_track(t->pid, page_va, fr);
+    serial_print("[demand-page] va=0x");
+    serial_print_hex(page_va);
+    serial_print(" -> frame=0x");
+    serial_print_hex(fr);
+    serial_print("\n");
+    return 1;
+}
+
 static int64_t sys_mmap(uint64_t length, uint64_t prot_flags, uint64_t path_ptr) {
     if (current_task_idx < 0) return -1;
     TaskSlot *t = &task_slots[current_task_idx];
@@ -1777,7 +1947,11 @@ static int64_t sys_munmap(uint64_t addr, uint64_t length) {
     (void)length;
 
     for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type == VMA_TYPE_SHM && t->vma_list[i].start == addr)
+            return sys_shm_unmap(addr);
         if (t->vma_list[i].type != VMA_TYPE_NONE && t->vma_list[i].start == addr) {
+            if (t->vma_list[i].type == VMA_TYPE_ANON)
+                (void)swap_untrack_range(addr, t->vma_list[i].length);
             t->vma_list[i].type = VMA_TYPE_NONE;
             serial_print("[munmap] va=0x");
             serial_print_hex(addr);
@@ -1788,6 +1962,170 @@ static int64_t sys_munmap(uint64_t addr, uint64_t length) {
     return -1;
 }
 
+// =============================================================================
+// Shared memory syscalls — Rust shm.rs keeps the registry
+// =============================================================================
+
+// All tasks share kernel_pml4, so a forked child maps a segment at the same
+// address as its parent. Only tear the pages down once no other live task
+// still has a shm VMA there.
+static int shm_va_in_use(int skip_slot, uint64_t start) {
+    for (int s = 0; s < MAX_TASKS; s++) {
+        if (s == skip_slot || !task_slots[s].active) continue;
+        for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+            VmaEntry *v = &task_slots[s].vma_list[i];
+            if (v->type == VMA_TYPE_SHM && v->start == start) return 1;
+        }
+    }
+    return 0;
+}
+
+static void shm_unmap_pages(int slot, VmaEntry *v) {
+    if (!shm_va_in_use(slot, v->start)) {
+        for (uint64_t off = 0; off < v->length; off += PAGE_SIZE)
+            paging_unmap_4k(kernel_pml4, v->start + off);
+        paging_flush_tlb();
+    }
+    v->type = VMA_TYPE_NONE;
+}
+
+static int64_t sys_shm_map(void *sched, uint64_t id, uint64_t prot) {
+    TaskSlot *t = &task_slots[current_task_idx];
+    uint64_t length = (uint64_t)shm_size((uint32_t)id);
+    if (length == 0) return SHM_ERR_NOENT;
+
+    int slot = -1;
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type == VMA_TYPE_NONE) { slot = i; break; }
+    }
+    if (slot < 0) return SHM_ERR_LIMIT;
+
+    if (t->mmap_next == 0) t->mmap_next = MMAP_BASE_64;
+    uint64_t va = t->mmap_next;
+    if (!user_ptr_range_valid_64(va, length)) return SHM_ERR_LIMIT;
+
+    uint8_t writable = (prot & PROT_WRITE) ? 1 : 0;
+    size_t base = 0;
+    int32_t rc = shm_map(sched, t->pid, (uint32_t)id, va, writable, &base);
+    if (rc < 0) return rc;
+    t->mmap_next += length;
+
+    VmaEntry *v = &t->vma_list[slot];
+    v->start  = va;
+    v->length = length;
+    v->type   = VMA_TYPE_SHM;
+    v->prot   = (uint8_t)(prot & 0xFF);
+    v->flags  = 0;
+    v->path[0] = '\0';
+    v->file_offset = (uint32_t)id;
+
+    // Segment memory is resident and identity-mapped: map it eagerly.
+    uint64_t pg_flags = PAGE_PRESENT | PAGE_USER | (writable ? PAGE_WRITABLE : 0);
+    for (uint64_t off = 0; off < length; off += PAGE_SIZE)
+        paging_map_4k(kernel_pml4, va + off, (uint64_t)base + off, pg_flags);
+    paging_flush_tlb();
+
+    serial_print("[shm] map id=");
+    serial_print_uint(id);
+    serial_print(" va=0x");
+    serial_print_hex(va);
+    serial_print(" len=0x");
+    serial_print_hex(length);
+    serial_print("\n");
+    return (int64_t)va;
+}
+
+static int64_t sys_shm_unmap(uint64_t addr) {
+    void *sched = get_kernel_scheduler();
+    if (current_task_idx < 0 || !sched) return SHM_ERR_INVAL;
+    TaskSlot *t = &task_slots[current_task_idx];
+
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        VmaEntry *v = &t->vma_list[i];
+        if (v->type != VMA_TYPE_SHM || v->start != addr) continue;
+        int32_t rc = shm_unmap(sched, t->pid, addr);
+        if (rc < 0) return rc;
+        shm_unmap_pages(current_task_idx, v);
+        return SHM_OK;
+    }
+    return SHM_ERR_INVAL;
+}
+
+static int64_t sys_shm(uint64_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    void *sched = get_kernel_scheduler();
+    if (current_task_idx < 0 || !sched) return SHM_ERR_INVAL;
+    uint32_t pid = task_slots[current_task_idx].pid;
+    char name[SYS_IO_PATH_MAX];
+
+    switch (num) {
+    case SYS_SHM_CREATE:
+        if (copy_user_path_64(name, a1) < 0) return SHM_ERR_INVAL;
+        return shm_create(sched, pid, name, a2, (uint32_t)a3);
+    case SYS_SHM_OPEN:
+        if (copy_user_path_64(name, a1) < 0) return SHM_ERR_INVAL;
+        return shm_open(sched, pid, name, (a2 & SHM_O_WRITE) ? 1 : 0);
+    case SYS_SHM_MAP:
+        return sys_shm_map(sched, a1, a2);
+    case SYS_SHM_UNMAP:
+        return sys_shm_unmap(a1);
+    case SYS_SHM_UNLINK:
+        if (copy_user_path_64(name, a1) < 0) return SHM_ERR_INVAL;
+        return shm_unlink(sched, pid, name);
+    case SYS_SHM_CLOSE:
+        return shm_close(pid, (uint32_t)a1);
+    }
+    return SHM_ERR_INVAL;
+}
+
+// Drop a dying task's shared memory: its uring rings, which live in shm
+// segments, then its shm VMAs and its handles and mappings in the Rust
+// registry. sched is NULL on the OOM path, which must not touch the
+// scheduler from inside an allocation.
+static void shm_task_release(TaskSlot *t, void *sched) {
+    int slot = (int)(t - task_slots);
+    (void)uring_release_process(sched, t->pid);
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type == VMA_TYPE_SHM)
+            shm_unmap_pages(slot, &t->vma_list[i]);
+    }
+    (void)shm_release_process(sched, t->pid);
+}
+
+// Take the current task off the CPU after a syscall parked it in Waiting
+// (uring_enter with min_complete); frame already holds its return value.
+// Returns the RSP of the next runnable task, or 0 to return to the caller
+// when nothing else can run, in which case it is woken again at once.
+static uint64_t task_block_current(InterruptFrame *frame) {
+    TaskSlot *cur = &task_slots[current_task_idx];
+    int next = -1;
+    for (int i = 1; i < MAX_TASKS; i++) {
+        int idx = (current_task_idx + i) % MAX_TASKS;
+        if (task_slots[idx].active && !task_slots[idx].blocked) { next = idx; break; }
+    }
+    if (!context_switch_enabled || next < 0) {
+        (void)scheduler_wake_process(get_kernel_scheduler(), cur->pid);
+        return 0;
+    }
+    cur->blocked = 1;
+    cur->rsp     = (uint64_t)frame;
+    context_switch_count++;
+    current_task_idx = next;
+    kernel_tss.rsp[0] = (uint64_t)(task_slots[next].stack + TASK_STACK_SIZE);
+    kinfo_write_pid(kinfo_page, task_slots[next].pid);
+    return task_slots[next].rsp;
+}
+
+// Let parked tasks run again once the scheduler has woken them
+// (Scheduler::wake_process, called from uring_poll).
+static void task_wake_unblocked(void) {
+    void *sched = get_kernel_scheduler();
+    for (int i = 0; i < MAX_TASKS; i++) {
+        TaskSlot *t = &task_slots[i];
+        if (t->active && t->blocked && !scheduler_is_waiting(sched, t->pid))
+            t->blocked = 0;
+    }
+}
+
 uint64_t interrupt_dispatch(InterruptFrame *frame) {
     uint64_t vec = frame->int_no;
 
@@ -1795,6 +2133,7 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
         // IRQ0: Timer
         kernel_tick++;
         pic_send_eoi(0);
+        kinfo_write_tick(kinfo_page, kernel_tick);
 
         // COM2 polling is simple port I/O — safe in IRQ
         const uint64_t cmd_poll_div = (TIMER_HZ / 10) ? (TIMER_HZ / 10) : 1;
@@ -1818,14 +2157,16 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
                 int next = current_task_idx;
                 for (int tries = 0; tries < MAX_TASKS; tries++) {
                     next = (next + 1) % MAX_TASKS;
-                    if (task_slots[next].active) break;
+                    if (task_slots[next].active && !task_slots[next].blocked) break;
                 }
-                if (next != current_task_idx && task_slots[next].active) {
+                if (next != current_task_idx && task_slots[next].active &&
+                    !task_slots[next].blocked) {
                     context_switch_count++;
                     current_task_idx = next;
                     // Phase 17: update TSS rsp[0] so that Ring 3 → Ring 0
                     // transitions land on the correct per-task kernel stack.
                     kernel_tss.rsp[0] = (uint64_t)(task_slots[next].stack + TASK_STACK_SIZE);
+                    kinfo_write_pid(kinfo_page, task_slots[next].pid);
                     return task_slots[next].rsp;   // asm switches RSP
                 }
             }
@@ -1861,7 +2202,29 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
 
     if (vec == 0x80) {
         // int 0x80 syscall: RAX=num, RBX=a1, RCX=a2, RDX=a3
-        if (frame->rax == SYS_USER_TEST) {
+        uint64_t sc_num = frame->rax, sc_a1 = frame->rbx, sc_a2 = frame->rcx, sc_a3 = frame->rdx;
+        uint32_t sc_pid = (current_task_idx >= 0) ? task_slots[current_task_idx].pid : 0;
+        uint32_t sc_start = (uint32_t)kernel_tick;
+        int sc_table = 0;   // dispatched through the Rust table, which counts it
+        if (sc_pid) {
+            // Registers at entry, for the dump_registers syscall
+            SyscallContext ctx = {
+                frame->rip, frame->rsp, frame->rbp,
+                frame->rax, frame->rbx, frame->rcx, frame->rdx, frame->rsi, frame->rdi,
+                frame->r8, frame->r9, frame->r10, frame->r11,
+                frame->r12, frame->r13, frame->r14, frame->r15,
+                frame->rflags,
+            };
+            (void)scheduler_save_context(get_kernel_scheduler(), sc_pid, &ctx);
+        }
+        // The calls handled below are checked against the caller's
+        // capabilities and seccomp filter first; table calls are checked
+        // inside syscall_handler.
+        int64_t sc_gate = syscall_gate(sc_pid, (uint32_t)sc_num, sc_a1, sc_a2, sc_a3);
+        if (sc_gate < 0) {
+            frame->rax = (uint64_t)sc_gate;
+            sc_table = 1;   // syscall_gate counted the refusal
+        } else if (frame->rax == SYS_USER_TEST) {
             // Phase 17: heartbeat from Ring 3 user program
             static uint64_t user_test_count = 0;
             user_test_count++;
@@ -1879,13 +2242,18 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
             void *sched = get_kernel_scheduler();
             if (sched)
                 scheduler_terminate_current(sched, (int32_t)frame->rbx);
+            if (current_task_idx >= 0) {
+                stack_sync_usage(current_task_idx);
+                shm_task_release(&task_slots[current_task_idx], get_kernel_scheduler());
+                (void)swap_release_process(task_slots[current_task_idx].pid);
+            }
             // Deactivate task slot and context-switch to next
             if (context_switch_enabled && current_task_idx >= 0) {
                 task_slots[current_task_idx].active = 0;
                 int next = -1;
                 for (int i = 0; i < MAX_TASKS; i++) {
                     int idx = (current_task_idx + 1 + i) % MAX_TASKS;
-                    if (task_slots[idx].active) { next = idx; break; }
+                    if (task_slots[idx].active && !task_slots[idx].blocked) { next = idx; break; }
                 }
                 serial_print("[exit] pid=");
                 serial_print_uint(exit_pid);
@@ -1895,6 +2263,7 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
                 if (next >= 0) {
                     current_task_idx = next;
                     kernel_tss.rsp[0] = (uint64_t)(task_slots[next].stack + TASK_STACK_SIZE);
+                    kinfo_write_pid(kinfo_page, task_slots[next].pid);
                     return task_slots[next].rsp;
                 }
             }
@@ -1912,27 +2281,6 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
             } else {
                 frame->rax = (uint64_t)(int64_t)-1;
             }
-        } else if (frame->rax == SYS_GETPID) {
-            // Phase 20: getpid() — return current PID
-            void *sched = get_kernel_scheduler();
-            frame->rax = sched ? scheduler_get_current_pid(sched) : 0;
-        } else if (frame->rax == SYS_KILL) {
-            // Phase 23: kill(pid, sig) — send signal to process
-            uint64_t dst_pid = frame->rbx;
-            uint8_t sig = (uint8_t)frame->rcx;
-            void *sched = get_kernel_scheduler();
-            if (sched) {
-                extern int scheduler_signal_send(void *sched, size_t dst_pid, uint8_t sig);
-                frame->rax = (uint64_t)(int64_t)scheduler_signal_send(sched, dst_pid, sig);
-            } else {
-                frame->rax = (uint64_t)-1;
-            }
-        } else if (frame->rax == SYS_READ) {
-            // Phase 25: read(path_ptr, user_buf_ptr, max_len) -> bytes read
-            frame->rax = (uint64_t)syscall_vfs_read_64(frame->rbx, frame->rcx, frame->rdx);
-        } else if (frame->rax == SYS_WRITE) {
-            // Phase 25: write(path_ptr, user_buf_ptr, len) -> bytes written
-            frame->rax = (uint64_t)syscall_vfs_write_64(frame->rbx, frame->rcx, frame->rdx);
         } else if (frame->rax == SYS_OPEN) {
             // Phase 41: open(path_ptr, flags) -> fd
             FdEntry *fdt = (current_task_idx >= 0) ? task_slots[current_task_idx].fd_table : (FdEntry*)0;
@@ -1979,13 +2327,28 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
             // Phase 48: sync() -> 0 or -1
             frame->rax = (uint64_t)bcache_sync();
         } else {
-            frame->rax = c_syscall_handler(frame->rax, frame->rbx,
-                                            frame->rcx, frame->rdx, 0);
+            // Everything else goes through the Rust syscall table
+            frame->rax = (uint64_t)syscall_handler(sc_pid, (uint32_t)frame->rax,
+                                                   frame->rbx, frame->rcx, frame->rdx);
+            sc_table = 1;
         }
+        // exit and a successful execve returned above and are not traced
+        if (!sc_table)
+            syscall_account((uint32_t)sc_num, (int64_t)frame->rax);
+        strace_record(sc_pid, (uint32_t)sc_num, sc_a1, sc_a2, sc_a3,
+                      (intptr_t)frame->rax, sc_start);
+        // The call parked the caller: run something else until it is woken
+        if (sc_pid && current_task_idx >= 0 &&
+            scheduler_is_waiting(get_kernel_scheduler(), sc_pid))
+            return task_block_current(frame);
         return 0;
     }
 
     if (vec <= 19) {
+        // A swapped-out page is not an error: read it back and retry.
+        if (vec == 14 && swap_fault_in(read_cr2()))
+            return 0;
+
         // CPU Exception
         const char *name = (vec < 20) ? exception_names[vec] : "Unknown";
         terminal_setcolor(make_color(VGA_COLOR_WHITE, VGA_COLOR_RED));
@@ -2005,14 +2368,25 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
         serial_print_hex(frame->rsp);
         serial_print("\n");
 
-        if (vec == 14) {
-            log_page_fault_detail(frame->error_code);
+        if (vec == 14 || vec == 8) {
+            // #DF runs on IST1, so a kernel stack overflow that could not
+            // push its #PF frame still gets here with CR2 in the guard page.
+            uint8_t kguard = 0;
+            int gslot = stack_guard_slot(read_cr2(), &kguard);
+            if (gslot >= 0)
+                report_stack_overflow(gslot, kguard);
+            else if (vec == 14)
+                log_page_fault_detail(frame->error_code);
+            else
+                serial_print("[DF] double fault detected\n");
         } else if (vec == 13) {
             serial_print("[GP] general protection fault detected\n");
-        } else if (vec == 8) {
-            serial_print("[DF] double fault detected\n");
         }
 
+        // A fault in module code is charged to the module; the call is
+        // unwound once the exception has been reported below.
+        int mod_fault = (frame->cs & 0x3) == 0 && vec != 8 && module_fault_enter(vec);
+
         // Phase 10: Notify AI of CPU exception
         {
             char evbuf[64];
@@ -2037,46 +2411,17 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
             ai_kernel_engine_feed(AI_EVT_EXCEPTION, evbuf, kernel_tick);
         }
 
+        if (mod_fault)
+            module_fault_unwind();
+
         if ((frame->cs & 0x3) == 0x3) {
             // Phase 46: demand paging — check VMAs before killing
             if (vec == 14 && current_task_idx >= 0) {
                 uint64_t fault_addr;
                 __asm__ volatile("mov %%cr2, %0" : "=r"(fault_addr));
                 TaskSlot *t = &task_slots[current_task_idx];
-                VmaEntry *vma = vma_find(t->vma_list, fault_addr);
-                if (vma) {
-                    uint64_t page_va = fault_addr & ~0xFFFULL;
-                    uint64_t fr = frame_alloc();
-                    if (fr) {
-                        uint64_t pg_flags = PAGE_PRESENT | PAGE_USER;
-                        if (vma->prot & PROT_WRITE) pg_flags |= PAGE_WRITABLE;
-
-                        if (vma->type == VMA_TYPE_FILE && vma->path[0]) {
-                            // File-backed: read file data into the frame
-                            uint32_t page_off = (uint32_t)(page_va - vma->start) + vma->file_offset;
-                            uint32_t need = page_off + PAGE_SIZE;
-                            if (need > sizeof(mmap_file_tmp)) need = sizeof(mmap_file_tmp);
-                            int total = kfs_read_file(vma->path, mmap_file_tmp, need);
-                            if (total > (int)page_off) {
-                                int ncp = total - (int)page_off;
-                                if (ncp > PAGE_SIZE) ncp = PAGE_SIZE;
-                                uint8_t *dst = (uint8_t *)fr;
-                                for (int b = 0; b < ncp; b++)
-                                    dst[b] = mmap_file_tmp[page_off + b];
-                            }
-                        }
-                        // Anonymous: frame already zeroed by frame_alloc
-
-                        paging_map_4k(kernel_pml4, page_va, fr, pg_flags);
-                        paging_flush_tlb();
-                        serial_print("[demand-page] va=0x");
-                        serial_print_hex(page_va);
-                        serial_print(" -> frame=0x");
-                        serial_print_hex(fr);
-                        serial_print("\n");
-                        return 0;  // Resume user execution
-                    }
-                }
+                if (vma_demand_fill(t, fault_addr))
+                    return 0;  // Resume user execution
             }
 
             // User-mode fault: kill the faulting process and switch to next task
@@ -2093,17 +2438,21 @@ uint64_t interrupt_dispatch(InterruptFrame *frame) {
             }
             // Deactivate the faulting task slot and switch to next via context switch
             if (context_switch_enabled && current_task_idx >= 0) {
+                stack_sync_usage(current_task_idx);
+                shm_task_release(&task_slots[current_task_idx], get_kernel_scheduler());
+                (void)swap_release_process(task_slots[current_task_idx].pid);
                 task_slots[current_task_idx].active = 0;
                 int next = -1;
                 for (int i = 0; i < MAX_TASKS; i++) {
                     int idx = (current_task_idx + 1 + i) % MAX_TASKS;
-                    if (task_slots[idx].active) { next = idx; break; }
+                    if (task_slots[idx].active && !task_slots[idx].blocked) { next = idx; break; }
                 }
                 if (next >= 0) {
                     serial_print("[EXCEPTION] switching to task ");
                     serial_print_uint(next);
                     serial_print("\n");
                     current_task_idx = next;
+                    kinfo_write_pid(kinfo_page, task_slots[next].pid);
                     return task_slots[next].rsp;
                 }
             }
@@ -2136,36 +2485,145 @@ static inline uint64_t rdmsr(uint32_t msr) {
 
 extern void syscall_entry(void);  // Defined in syscall.asm
 
-// Rust syscall_handler (defined in libvernisos_x64.a)
-extern int64_t syscall_handler(uint32_t num, uint64_t arg1, uint64_t arg2, uint64_t arg3);
+// Rust syscall table (defined in libvernisos_x64.a)
+typedef int64_t (*syscall_native_fn)(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3);
+extern int32_t syscall_register(uint32_t num, const char *name, uint8_t argc,
+                                uint64_t caps, syscall_native_fn handler);
+extern int32_t syscall_register_trap(uint32_t num, const char *name, uint8_t argc);
 
 // C-level syscall handler called from syscall.asm
 uint64_t c_syscall_handler(uint64_t num, uint64_t arg1, uint64_t arg2,
-                            uint64_t arg3, uint64_t arg4) {
+                            uint64_t arg3, uint64_t arg4,
+                            const SyscallEntryFrame *regs) {
     (void)arg4;
-    if (num == SYS_READ) {
-        return (uint64_t)syscall_vfs_read_64(arg1, arg2, arg3);
-    }
-    if (num == SYS_WRITE) {
-        return (uint64_t)syscall_vfs_write_64(arg1, arg2, arg3);
+    uint32_t pid = (current_task_idx >= 0) ? task_slots[current_task_idx].pid : 0;
+    uint32_t start = (uint32_t)kernel_tick;
+    if (pid) {
+        // Registers at entry, for the dump_registers syscall; the caller's
+        // stack continues right above the saved frame.
+        SyscallContext ctx = {
+            regs->rip, (uint64_t)(regs + 1), regs->rbp,
+            regs->rax, regs->rbx, regs->rip, regs->rdx, regs->rsi, regs->rdi,
+            regs->r8, regs->r9, regs->r10, regs->rflags,
+            regs->r12, regs->r13, regs->r14, regs->r15,
+            regs->rflags,
+        };
+        (void)scheduler_save_context(get_kernel_scheduler(), pid, &ctx);
     }
-    // Phase 4: IPC syscalls 20-27 → C handler
-    if (num >= SYS_IPC_SEND && num <= SYS_IPC_CHAN_CLOSE) {
-        return (uint64_t)ipc_syscall((uint32_t)num, (uint32_t)arg1,
-                                      (uint32_t)arg2, (uint32_t)arg3);
-    }
-    // Phase 5: Module syscalls 28-32 → C handler
-    if (num >= SYS_MOD_LOAD && num <= SYS_MOD_INFO) {
-        return (uint64_t)module_syscall((uint32_t)num, (uint32_t)arg1,
-                                         (uint32_t)arg2, (uint32_t)arg3);
+    int64_t ret = syscall_handler(pid, (uint32_t)num, arg1, arg2, arg3);
+    strace_record(pid, (uint32_t)num, arg1, arg2, arg3, (intptr_t)ret, start);
+    // SYSRET returns on the caller's stack, so it cannot be switched away
+    // from; a caller the call parked is woken again at once.
+    if (pid && scheduler_is_waiting(get_kernel_scheduler(), pid))
+        (void)scheduler_wake_process(get_kernel_scheduler(), pid);
+    return (uint64_t)ret;
+}
+
+// ---- C handlers registered into the Rust syscall table ----
+
+static int64_t sysent_ipc(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    return ipc_syscall(num, (uint32_t)a1, (uint32_t)a2, (uint32_t)a3);
+}
+
+static int64_t sysent_module(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    return module_syscall(num, (uint32_t)a1, (uint32_t)a2, (uint32_t)a3);
+}
+
+static int64_t sysent_ai(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    return (int32_t)ai_syscall(num, (uint32_t)a1, (uint32_t)a2, (uint32_t)a3);
+}
+
+static int64_t sysent_vfs_read(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    (void)num;
+    return syscall_vfs_read_64(a1, a2, a3);
+}
+
+static int64_t sysent_vfs_write(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    (void)num;
+    return syscall_vfs_write_64(a1, a2, a3);
+}
+
+static int64_t sysent_shm(uint32_t num, uint64_t a1, uint64_t a2, uint64_t a3) {
+    return sys_shm(num, a1, a2, a3);
+}
+
+static const struct {
+    uint32_t num;
+    const char *name;
+    uint8_t argc;
+    uint64_t caps;
+    syscall_native_fn handler;
+} syscall_natives[] = {
+    { SYS_IPC_SEND,         "ipc_send",         2, CAP_IPC_SEND,       sysent_ipc },
+    { SYS_IPC_RECV,         "ipc_recv",         2, CAP_IPC_RECEIVE,    sysent_ipc },
+    { SYS_IPC_QUEUE_CREATE, "ipc_queue_create", 1, CAP_IPC_RECEIVE,    sysent_ipc },
+    { SYS_IPC_QUEUE_CLOSE,  "ipc_queue_close",  1, CAP_IPC_RECEIVE,    sysent_ipc },
+    { SYS_IPC_CHAN_CREATE,  "ipc_chan_create",  2, CAP_CHANNEL_CREATE, sysent_ipc },
+    { SYS_IPC_CHAN_WRITE,   "ipc_chan_write",   3, CAP_IPC_SEND,       sysent_ipc },
+    { SYS_IPC_CHAN_READ,    "ipc_chan_read",    3, CAP_IPC_RECEIVE,    sysent_ipc },
+    { SYS_IPC_CHAN_CLOSE,   "ipc_chan_close",   1, CAP_CHANNEL_CREATE, sysent_ipc },
+    { SYS_MOD_LOAD,         "mod_load",         2, CAP_MODULE_LOAD,    sysent_module },
+    { SYS_MOD_UNLOAD,       "mod_unload",       1, CAP_MODULE_UNLOAD,  sysent_module },
+    { SYS_MOD_LIST,         "mod_list",         0, CAP_NONE,           sysent_module },
+    { SYS_MOD_CALL,         "mod_call",         3, CAP_MODULE_EXECUTE, sysent_module },
+    { SYS_MOD_INFO,         "mod_info",         2, CAP_NONE,           sysent_module },
+    { SYS_SHM_CREATE,       "shm_create",       3, CAP_NONE,           sysent_shm },
+    { SYS_SHM_OPEN,         "shm_open",         2, CAP_NONE,           sysent_shm },
+    { SYS_SHM_MAP,          "shm_map",          2, CAP_NONE,           sysent_shm },
+    { SYS_SHM_UNMAP,        "shm_unmap",        1, CAP_NONE,           sysent_shm },
+    { SYS_SHM_UNLINK,       "shm_unlink",       1, CAP_NONE,           sysent_shm },
+    { SYS_SHM_CLOSE,        "shm_close",        1, CAP_NONE,           sysent_shm },
+    { SYS_AI_QUERY,         "ai_query",         2, CAP_NONE,           sysent_ai },
+    { SYS_AI_STATUS,        "ai_status",        0, CAP_NONE,           sysent_ai },
+    { SYS_AI_EVENT,         "ai_event",         2, CAP_NONE,           sysent_ai },
+    { SYS_READ,             "read",             3, CAP_NONE,           sysent_vfs_read },
+    { SYS_WRITE,            "write",            3, CAP_NONE,           sysent_vfs_write },
+};
+
+// Handled in interrupt_dispatch with the trap frame; registered by name only
+// so the syscall dump and strace can show them.
+static const struct {
+    uint32_t num;
+    const char *name;
+    uint8_t argc;
+} syscall_traps[] = {
+    { SYS_EXIT,     "exit",     1 },
+    { SYS_WAITPID,  "waitpid",  1 },
+    { SYS_OPEN,     "open",     2 },
+    { SYS_READ_FD,  "read_fd",  3 },
+    { SYS_WRITE_FD, "write_fd", 3 },
+    { SYS_CLOSE,    "close",    1 },
+    { SYS_DUP,      "dup",      1 },
+    { SYS_DUP2,     "dup2",     2 },
+    { SYS_PIPE,     "pipe",     1 },
+    { SYS_FORK,     "fork",     0 },
+    { SYS_EXECVE,   "execve",   1 },
+    { SYS_SBRK,     "sbrk",     1 },
+    { SYS_MMAP,     "mmap",     3 },
+    { SYS_MUNMAP,   "munmap",   2 },
+    { SYS_SYNC,     "sync",     0 },
+};
+
+// Register the C-implemented syscalls. Runs right after syscall_init(),
+// which installs the Rust built-ins (getpid, kill, dumps).
+static void syscall_table_init(void) {
+    for (size_t i = 0; i < sizeof(syscall_natives) / sizeof(syscall_natives[0]); i++) {
+        if (syscall_register(syscall_natives[i].num, syscall_natives[i].name,
+                             syscall_natives[i].argc, syscall_natives[i].caps,
+                             syscall_natives[i].handler) != 0) {
+            serial_print("[syscall] register failed: ");
+            serial_print(syscall_natives[i].name);
+            serial_print("\n");
+        }
     }
-    // Phase 8: AI syscalls 40-42 → C handler
-    if (num >= SYS_AI_QUERY && num <= SYS_AI_EVENT) {
-        return (uint64_t)ai_syscall((uint32_t)num, (uint32_t)arg1,
-                                     (uint32_t)arg2, (uint32_t)arg3);
+    for (size_t i = 0; i < sizeof(syscall_traps) / sizeof(syscall_traps[0]); i++) {
+        if (syscall_register_trap(syscall_traps[i].num, syscall_traps[i].name,
+                                  syscall_traps[i].argc) != 0) {
+            serial_print("[syscall] register failed: ");
+            serial_print(syscall_traps[i].name);
+            serial_print("\n");
+        }
     }
-    // Phase 3: all other syscalls → Rust handler
-    return (uint64_t)syscall_handler((uint32_t)num, arg1, arg2, arg3);
 }
 
 static void syscall_hw_init(void) {
@@ -2188,9 +2646,9 @@ static void syscall_hw_init(void) {
 extern void verniskernel_init_heap(uint64_t start, uint64_t size);
 extern void verniskernel_register_print(void (*cb)(const uint8_t *, uint32_t));
 extern void syscall_init(void);   // Rust syscall_init (no-op stub, symbol needed)
-extern int64_t syscall_handler(uint32_t num, uint64_t arg1, uint64_t arg2, uint64_t arg3);
+extern int64_t syscall_handler(size_t pid, uint32_t num, uint64_t arg1, uint64_t arg2, uint64_t arg3);
 extern void *scheduler_new(void);
-extern uint32_t scheduler_create_process(void *sched, uint8_t priority, const char *command);
+extern int64_t scheduler_create_process(void *sched, uint8_t priority, const char *command);
 extern uint32_t scheduler_schedule(void *sched);
 extern uint32_t scheduler_get_process_count(const void *sched);
 extern int scheduler_set_priority(void *sched, uint32_t pid, uint8_t priority);
@@ -2199,6 +2657,14 @@ extern void scheduler_set_quantum(void *sched, uint32_t quantum_ms);
 static void *kernel_scheduler = (void *)0;
 void *get_kernel_scheduler(void) { return kernel_scheduler; }
 uint32_t kernel_get_ticks(void)  { return (uint32_t)kernel_tick; }
+// Pid of the running task, 0 before tasking starts.
+uint32_t kernel_current_pid(void) {
+    return (current_task_idx >= 0) ? task_slots[current_task_idx].pid : 0;
+}
+// Task slot of the running task, 0 (the kernel thread) before tasking starts.
+uint32_t kernel_current_task(void) {
+    return (current_task_idx >= 0) ? (uint32_t)current_task_idx : 0;
+}
 uint32_t kernel_is_gui_mode(void) { return (display_mode == 2) ? 1u : 0u; }
 
 static void rust_print_cb(const uint8_t *ptr, uint32_t len) {
@@ -2287,6 +2753,17 @@ static void paging_map_4k(uint64_t *pml4, uint64_t virt, uint64_t phys, uint64_t
     pt[pti] = (phys & 0xFFFFFFFFF000ULL) | flags;
 }
 
+// Remove a single 4KB mapping (no-op if a level is absent or a huge page).
+static void paging_unmap_4k(uint64_t *pml4, uint64_t virt) {
+    uint64_t *table = pml4;
+    for (int shift = 39; shift >= 21; shift -= 9) {
+        uint64_t e = table[(virt >> shift) & 0x1FF];
+        if (!(e & PAGE_PRESENT) || (e & PAGE_PS)) return;
+        table = (uint64_t *)(e & 0x000FFFFFFFFFF000ULL);
+    }
+    table[(virt >> 12) & 0x1FF] = 0;
+}
+
 // Create a new address space for a user process.
 // Copies kernel PML4 entries so kernel memory is always mapped.
 uint64_t *paging_create_address_space(uint64_t *kernel_pml4) {
@@ -2312,6 +2789,32 @@ static uint64_t *kernel_pml4 = (uint64_t *)0;
 uint64_t *paging_get_kernel_pml4(void) { return kernel_pml4; }
 uint64_t  paging_get_frames_used(void) { return phys_frames_used; }
 
+// Back every task slot's kernel stack with 4 KB pages in KSTACK_REGION.
+// The lowest page of each KSTACK_SPAN window is never mapped, so running
+// off the bottom of a stack faults instead of corrupting the next slot.
+static void kstack_init(void) {
+    for (int i = 0; i < MAX_TASKS; i++) {
+        uint64_t base = KSTACK_REGION + (uint64_t)i * KSTACK_SPAN;
+        for (uint64_t off = 0; off < TASK_STACK_SIZE; off += PAGE_SIZE) {
+            uint64_t frame = frame_alloc();
+            if (!frame) {
+                serial_print("[kstack] FATAL: out of frames for kernel stacks\n");
+                __asm__ volatile("cli");
+                while (1) __asm__ volatile("hlt");
+            }
+            paging_map_4k(kernel_pml4, base + PAGE_SIZE + off, frame,
+                          PAGE_PRESENT | PAGE_WRITABLE);
+        }
+        task_slots[i].stack = (uint8_t *)(base + PAGE_SIZE);
+    }
+    paging_flush_tlb();
+    serial_print("[kstack] ");
+    serial_print_dec(MAX_TASKS);
+    serial_print(" kernel stacks with guard pages at ");
+    serial_print_hex(KSTACK_REGION);
+    serial_print("\n");
+}
+
 // Initialize kernel page tables — called from kernel_main AFTER BSS zeroing
 // and BEFORE sti.  Replaces the bootloader's minimal page tables.
 static void paging_init(void) {
@@ -2356,6 +2859,26 @@ static void paging_init(void) {
     serial_print("[paging] initialized: 128MB identity + fb, ");
     serial_print_dec(phys_frames_used);
     serial_print(" frames used\n");
+
+    kstack_init();
+}
+
+// Fill in the kernel info page and map it read-only for user space at
+// KINFO_VADDR. All tasks share kernel_pml4, so one mapping covers them all.
+static void kinfo_page_init(void) {
+    RtcTime t;
+    rtc_read(&t);
+    uint32_t lo, hi;
+    __asm__ volatile("rdtsc" : "=a"(lo), "=d"(hi));
+    uint64_t wall = kinfo_unix_time(t.year, t.month, t.day, t.hour, t.minute, t.second);
+    KernelInfoPage *p = kinfo_init(TIMER_HZ, wall, ((uint64_t)hi << 32) | lo);
+    paging_map_4k(kernel_pml4, KINFO_VADDR, (uint64_t)p, PAGE_PRESENT | PAGE_USER);
+    paging_flush_tlb();
+    kinfo_page = p;
+    kinfo_write_pid(kinfo_page, current_task_idx >= 0 ? task_slots[current_task_idx].pid : 0);
+    serial_print("[kinfo] info page mapped at ");
+    serial_print_hex(KINFO_VADDR);
+    serial_print("\n");
 }
 
 // =============================================================================
@@ -2371,6 +2894,227 @@ static int task_find(uint32_t pid) {
     return -1;
 }
 
+// =============================================================================
+// Heap growth + OOM kill callbacks (Rust heap.rs)
+// =============================================================================
+
+// Frames kept back for page tables; the heap may only take what is above this.
+#define HEAP_GROW_RESERVE (512 * 1024)
+
+// Hand the Rust heap a contiguous run of frames from the bump allocator.
+// Frames lie in the identity-mapped region, so physical == virtual.
+static size_t kernel_heap_grow_cb(size_t min_bytes, uintptr_t *out_start) {
+    uint64_t avail = phys_alloc_end - phys_next_free;
+    if (!out_start || min_bytes == 0 || min_bytes > avail ||
+        avail - min_bytes < HEAP_GROW_RESERVE) {
+        serial_print("[heap] grow refused: frame pool exhausted\n");
+        return 0;
+    }
+    uint64_t bytes = ((uint64_t)min_bytes + PAGE_SIZE - 1) & ~((uint64_t)PAGE_SIZE - 1);
+    *out_start = (uintptr_t)phys_next_free;
+    phys_next_free   += bytes;
+    phys_frames_used += bytes / PAGE_SIZE;
+    return (size_t)bytes;
+}
+
+// User pages a task holds: its mmap regions, heap and user stack.
+static uint32_t task_user_pages(const TaskSlot *t) {
+    uint64_t bytes = (uint64_t)USER_STACK_PAGES * PAGE_SIZE;
+    if (t->brk > USER_HEAP_BASE_64)
+        bytes += t->brk - USER_HEAP_BASE_64;
+    for (int i = 0; i < VMA_MAX_PER_TASK; i++) {
+        if (t->vma_list[i].type != VMA_TYPE_NONE)
+            bytes += t->vma_list[i].length;
+    }
+    return (uint32_t)(bytes / PAGE_SIZE);
+}
+
+// OOM candidates: live tasks running a user program (kernel threads have no
+// user stack), never the running task, which cannot be torn down from
+// inside its own allocation.
+static uint32_t kernel_oom_tasks_cb(OomCandidate *out, uint32_t max) {
+    uint32_t n = 0;
+    for (int i = 0; i < MAX_TASKS && n < max; i++) {
+        const TaskSlot *t = &task_slots[i];
+        if (!t->active || i == current_task_idx || !t->ustack_frames[0])
+            continue;
+        out[n].pid   = t->pid;
+        out[n].pages = task_user_pages(t);
+        n++;
+    }
+    return n;
+}
+
+// Tear down the OOM victim's task slot and release what it holds. The Rust
+// scheduler marks it killed later, outside the allocator.
+static void kernel_oom_kill_cb(uint32_t pid) {
+    serial_print("[heap] OOM killed pid=");
+    serial_print_dec(pid);
+    serial_print("\n");
+    int slot = task_find(pid);
+    if (slot >= 0 && slot != current_task_idx) {
+        shm_task_release(&task_slots[slot], (void *)0);
+        (void)swap_release_process(pid);
+        module_task_exit((uint32_t)slot);
+        task_slots[slot].active = 0;
+    }
+}
+
+// =============================================================================
+// Anonymous-page swap (Rust swap.rs runs the clock, we do PTEs and disk I/O)
+// =============================================================================
+
+// Once the frame pool drops below this, anonymous user pages are taken from
+// the clock instead, leaving the rest for page tables and heap growth.
+#define SWAP_FRAME_RESERVE (1024 * 1024)
+
+// A swapped-out PTE keeps its permission bits with PAGE_PRESENT clear, so
+// swap_fault_in can restore the original protection.
+#define PTE_FLAGS_MASK 0xFFFULL
+#define PAGE_ACCESSED  0x20ULL
+
+static uint64_t *paging_pte_lookup(uint64_t *pml4, uint64_t virt) {
+    uint64_t *table = pml4;
+    for (int shift = 39; shift >= 21; shift -= 9) {
+        uint64_t e = table[(virt >> shift) & 0x1FF];
+        if (!(e & PAGE_PRESENT) || (e & PAGE_PS)) return (uint64_t *)0;
+        table = (uint64_t *)(e & 0x000FFFFFFFFFF000ULL);
+    }
+    return &table[(virt >> 12) & 0x1FF];
+}
+
+static uint8_t swap_page_referenced(size_t vaddr, size_t frame) {
+    uint64_t *pte = paging_pte_lookup(kernel_pml4, vaddr);
+    if (!pte || !(*pte & PAGE_PRESENT) || (*pte & 0x000FFFFFFFFFF000ULL) != frame)
+        return 0;
+    if (!(*pte & PAGE_ACCESSED)) return 0;
+    *pte &= ~PAGE_ACCESSED;
+    __asm__ volatile("invlpg (%0)" : : "r"(vaddr) : "memory");
+    return 1;
+}
+
+static uint8_t swap_page_unmap(size_t vaddr, size_t frame) {
+    uint64_t *pte = paging_pte_lookup(kernel_pml4, vaddr);
+    if (!pte || !(*pte & PAGE_PRESENT) || (*pte & 0x000FFFFFFFFFF000ULL) != frame)
+        return 0;
+    *pte &= PTE_FLAGS_MASK & ~PAGE_PRESENT;
+    __asm__ volatile("invlpg (%0)" : : "r"(vaddr) : "memory");
+    return 1;
+}
+
+static int32_t swap_write_slot(uint32_t slot, size_t frame) {
+    return kfs_write_at(SWAP_FILE_PATH, slot * PAGE_SIZE,
+                        (const uint8_t *)frame, PAGE_SIZE) < 0 ? -1 : 0;
+}
+
+static int32_t swap_read_slot(uint32_t slot, size_t frame) {
+    return kfs_read_at(SWAP_FILE_PATH, slot * PAGE_SIZE,
+                       (uint8_t *)frame, PAGE_SIZE) < 0 ? -1 : 0;
+}
+
+static const swap_ops_t swap_ops = {
+    swap_page_referenced, swap_page_unmap, swap_write_slot, swap_read_slot,
+};
+
+// Reserve /swap on the root filesystem and hand it to the clock.
+int kernel_swap_on(void) {
+    if (swap_enabled()) return SWAP_ERR_INVAL;
+    if (kfs_reserve_file(SWAP_FILE_PATH, SWAP_FILE_PAGES * PAGE_SIZE) < 0) {
+        serial_print("[swap] cannot reserve " SWAP_FILE_PATH "\n");
+        return SWAP_ERR_IO;
+    }
+    int32_t rc = swap_init(SWAP_FILE_PAGES, &swap_ops);
+    if (rc == SWAP_OK) {
+        serial_print("[swap] enabled: ");
+        serial_print_uint(SWAP_FILE_PAGES);
+        serial_print(" slots on " SWAP_FILE_PATH "\n");
+    }
+    return rc;
+}
+
+// Frame for an anonymous user page. Already zeroed, or 0 if none is left.
+static uint64_t user_frame_alloc(void) {
+    if (swap_enabled() && phys_alloc_end - phys_next_free < SWAP_FRAME_RESERVE) {
+        uint64_t fr = (uint64_t)swap_evict();
+        if (fr) {
+            volatile uint8_t *p = (volatile uint8_t *)fr;
+            for (int i = 0; i < PAGE_SIZE; i++) p[i] = 0;
+            return fr;
+        }
+    }
+    return frame_alloc();
+}
+
+// Bring back the page at addr if the clock swapped it out. Works for kernel
+// faults too (a syscall touching a user buffer). Returns 1 if resolved.
+static int swap_fault_in(uint64_t addr) {
+    uint64_t page_va = addr & ~0xFFFULL;
+    uint64_t *pte = paging_pte_lookup(kernel_pml4, page_va);
+    if (!pte || (*pte & PAGE_PRESENT) || !swap_is_swapped(page_va)) return 0;
+    uint64_t flags = (*pte & PTE_FLAGS_MASK) | PAGE_PRESENT;
+    uint64_t fr = user_frame_alloc();
+    if (!fr || swap_in(page_va, fr) != SWAP_OK) {
+        serial_print("[swap] swap-in failed va=0x");
+        serial_print_hex(page_va);
+        serial_print("\n");
+        return 0;
+    }
+    paging_map_4k(kernel_pml4, page_va, fr, flags);
+    __asm__ volatile("invlpg (%0)" : : "r"(page_va) : "memory");
+    return 1;
+}
+
+// ---- User-memory access primitives (usermem.rs) ----
+
+// Kernel tasks have no user stack; syscalls they make pass kernel pointers.
+static size_t usermem_current_pid(void) {
+    if (current_task_idx < 0) return 0;
+    TaskSlot *t = &task_slots[current_task_idx];
+    return t->ustack_frames[0] ? t->pid : 0;
+}
+
+static uint8_t usermem_find_range(size_t vaddr, usermem_range_t *out) {
+    if (current_task_idx < 0) return 0;
+    TaskSlot *t = &task_slots[current_task_idx];
+    uint64_t heap_end = (t->brk + 0xFFF) & ~0xFFFULL;
+    if (t->brk && vaddr >= USER_HEAP_BASE_64 && vaddr < heap_end) {
+        out->start = USER_HEAP_BASE_64;
+        out->end = heap_end;
+        out->writable = 1;
+        return 1;
+    }
+    VmaEntry *vma = vma_find(t->vma_list, vaddr);
+    if (!vma) return 0;
+    out->start = vma->start;
+    out->end = vma->start + vma->length;
+    out->writable = (vma->prot & PROT_WRITE) ? 1 : 0;
+    return 1;
+}
+
+// Flags of the PTE, or of the 2 MB / 1 GB entry (the identity map), that
+// maps vaddr. 0 for a non-canonical or unmapped address.
+static uint32_t usermem_page_flags(size_t vaddr) {
+    if ((uint64_t)((int64_t)((uint64_t)vaddr << 16) >> 16) != (uint64_t)vaddr) return 0;
+    uint64_t *table = kernel_pml4;
+    for (int shift = 39; shift >= 21; shift -= 9) {
+        uint64_t e = table[(vaddr >> shift) & 0x1FF];
+        if (!(e & PAGE_PRESENT)) return 0;
+        if (e & PAGE_PS) return (uint32_t)(e & PTE_FLAGS_MASK);
+        table = (uint64_t *)(e & 0x000FFFFFFFFFF000ULL);
+    }
+    return (uint32_t)(table[(vaddr >> 12) & 0x1FF] & PTE_FLAGS_MASK);
+}
+
+static uint8_t usermem_fault_in(size_t vaddr) {
+    if (swap_fault_in(vaddr)) return 1;
+    if (current_task_idx < 0) return 0;
+    return vma_demand_fill(&task_slots[current_task_idx], vaddr) ? 1 : 0;
+}
+
+static const usermem_ops_t usermem_ops = {
+    usermem_current_pid, usermem_find_range, usermem_page_flags, usermem_fault_in,
+};
+
 // Create a new kernel-mode task.  Builds a fake InterruptFrame on its stack
 // so the first context-switch into it looks identical to a resumed task.
 static int task_create(void (*entry)(void), uint32_t pid, uint16_t ticks) {
@@ -2380,12 +3124,16 @@ static int task_create(void (*entry)(void), uint32_t pid, uint16_t ticks) {
     if (slot < 0) return -1;
 
     task_slots[slot].active = 1;
+    task_slots[slot].blocked = 0;
     task_slots[slot].pid    = pid;
     task_slots[slot].ticks_remaining = ticks;
     task_slots[slot].ticks_total     = ticks;
     task_slots[slot].brk             = 0;
     task_slots[slot].mmap_next       = 0;
     vma_init(task_slots[slot].vma_list);
+    for (int i = 0; i < USER_STACK_PAGES; i++)
+        task_slots[slot].ustack_frames[i] = 0;   // kernel task: no user stack
+    stack_paint(task_slots[slot].stack, TASK_STACK_SIZE);
 
     uint64_t *sp = (uint64_t*)(task_slots[slot].stack + TASK_STACK_SIZE);
 
@@ -2439,11 +3187,83 @@ static void phase18_worker_entry(void) {
 // so they don't collide with the kernel's 2 MB huge-page mappings).
 #define USER_CODE_VADDR  0x10000000ULL   // 256 MB — user .text page
 #define USER_STACK_TOP   0x10800000ULL   // 264 MB — top of user stack
-#define USER_STACK_SIZE  0x4000ULL       // 16 KB user stack
+#define USER_STACK_SIZE  (USER_STACK_PAGES * 0x1000ULL)  // 16 KB user stack
+#define USER_STACK_GUARD (USER_STACK_TOP - USER_STACK_SIZE - 0x1000ULL)  // unmapped
 
 // Legacy phase17 heartbeat task helpers removed.
 // User-space boot path is now shell-only via ELF loader (/bin/vsh64).
 
+// =============================================================================
+// Stack guard pages + high-water marks
+// =============================================================================
+
+// Fresh stacks are filled with STACK_PAINT_BYTE; the lowest overwritten byte
+// gives the deepest use so far without any per-call instrumentation.
+static void stack_paint(uint8_t *base, uint64_t size) {
+    for (uint64_t i = 0; i < size; i++) base[i] = STACK_PAINT_BYTE;
+}
+
+static uint64_t stack_untouched(const uint8_t *base, uint64_t size) {
+    uint64_t n = 0;
+    while (n < size && base[n] == STACK_PAINT_BYTE) n++;
+    return n;
+}
+
+static uint64_t kstack_used(const TaskSlot *t) {
+    return TASK_STACK_SIZE - stack_untouched(t->stack, TASK_STACK_SIZE);
+}
+
+// User stack frames lie in the identity map, so scan them physically —
+// every task maps its stack at the same virtual address.
+static uint64_t ustack_used(const TaskSlot *t) {
+    uint64_t untouched = 0;
+    for (int p = 0; p < USER_STACK_PAGES; p++) {
+        if (!t->ustack_frames[p]) return 0;   // kernel-only task
+        uint64_t n = stack_untouched((const uint8_t *)t->ustack_frames[p], PAGE_SIZE);
+        untouched += n;
+        if (n < PAGE_SIZE) break;
+    }
+    return USER_STACK_SIZE - untouched;
+}
+
+// Push the slot's current high-water marks into its PCB (not IRQ-safe).
+static void stack_sync_usage(int slot) {
+    if (slot < 0 || slot >= MAX_TASKS || !task_slots[slot].active) return;
+    void *sched = get_kernel_scheduler();
+    if (!sched || task_slots[slot].pid == 0) return;
+    (void)scheduler_record_stack_usage(sched, task_slots[slot].pid,
+                                       (size_t)kstack_used(&task_slots[slot]),
+                                       (size_t)ustack_used(&task_slots[slot]));
+}
+
+// Map a fault address to the slot whose stack guard page it hit, or -1.
+// *kernel_stack is set to 1 for kernel-stack guards, 0 for the user stack.
+static int stack_guard_slot(uint64_t addr, uint8_t *kernel_stack) {
+    if (addr >= KSTACK_REGION &&
+        addr <  KSTACK_REGION + MAX_TASKS * KSTACK_SPAN &&
+        (addr - KSTACK_REGION) % KSTACK_SPAN < PAGE_SIZE) {
+        *kernel_stack = 1;
+        return (int)((addr - KSTACK_REGION) / KSTACK_SPAN);
+    }
+    if (addr >= USER_STACK_GUARD && addr < USER_STACK_GUARD + PAGE_SIZE &&
+        current_task_idx >= 0) {
+        *kernel_stack = 0;
+        return current_task_idx;
+    }
+    return -1;
+}
+
+static void report_stack_overflow(int slot, uint8_t kernel_stack) {
+    uint32_t pid = task_slots[slot].pid;
+    const char *which = kernel_stack ? " (kernel stack)\n" : " (user stack)\n";
+    serial_print("[PF] stack overflow in pid ");
+    serial_print_uint(pid);
+    serial_print(which);
+    terminal_writestring("stack overflow in pid ");
+    vga_print_dec(pid);
+    terminal_writestring(which);
+}
+
 // =============================================================================
 // Phase 19: ELF Loader
 // =============================================================================
@@ -2529,16 +3349,23 @@ static int elf_exec(const char *path, uint16_t ticks) {
     }
 
     // 4. Create a scheduler process
-    uint32_t pid = scheduler_create_process(kernel_scheduler, 5, path);
+    int64_t pid = scheduler_create_process(kernel_scheduler, 5, path);
+    if (pid < 0) {
+        serial_print("[elf] create_process failed\n");
+        return -1;
+    }
 
     task_slots[slot].active          = 1;
-    task_slots[slot].pid             = pid;
+    task_slots[slot].blocked         = 0;
+    task_slots[slot].pid             = (uint32_t)pid;
     task_slots[slot].ticks_remaining = ticks;
     task_slots[slot].ticks_total     = ticks;
     task_slots[slot].brk             = 0;
     fd_table_init(task_slots[slot].fd_table);  // Phase 41: init fd 0/1/2
     vma_init(task_slots[slot].vma_list);       // Phase 46: init VMAs
     task_slots[slot].mmap_next       = 0;
+    (void)scheduler_set_user_region(kernel_scheduler, pid, USER_CODE_VADDR,
+                                    USER_STACK_TOP - USER_CODE_VADDR);
 
     // 5. Map PT_LOAD segments into user space
     Elf64_Phdr *phdr = (Elf64_Phdr *)(elf_load_buf + ehdr->e_phoff);
@@ -2585,7 +3412,7 @@ static int elf_exec(const char *path, uint16_t ticks) {
         }
     }
 
-    // 6. Map user stack (16 KB = 4 pages)
+    // 6. Map user stack (16 KB = 4 pages) above an unmapped guard page
     for (uint64_t i = 0; i < USER_STACK_SIZE; i += PAGE_SIZE) {
         uint64_t frame = frame_alloc();
         if (!frame) {
@@ -2593,12 +3420,16 @@ static int elf_exec(const char *path, uint16_t ticks) {
             task_slots[slot].active = 0;
             return -1;
         }
+        stack_paint((uint8_t *)frame, PAGE_SIZE);
+        task_slots[slot].ustack_frames[i / PAGE_SIZE] = frame;
         paging_map_4k(kernel_pml4, USER_STACK_TOP - USER_STACK_SIZE + i, frame,
                       PAGE_PRESENT | PAGE_WRITABLE | PAGE_USER);
     }
+    paging_unmap_4k(kernel_pml4, USER_STACK_GUARD);
     paging_flush_tlb();
 
     // 7. Build iretq frame on the kernel stack
+    stack_paint(task_slots[slot].stack, TASK_STACK_SIZE);
     uint64_t *sp = (uint64_t*)(task_slots[slot].stack + TASK_STACK_SIZE);
 
     *(--sp) = 0x20 | 3;                          // SS  (0x23)
@@ -2637,18 +3468,19 @@ static int elf_exec(const char *path, uint16_t ticks) {
     return slot;
 }
 
-// Kernel-callable function for CLI "exec" command
-// Returns 0 on success, -1 on failure
-static int kernel_elf_exec(const char *path) {
+// Kernel-callable function for CLI "exec" command; the process runs as the
+// session's uid. Returns 0 on success, -1 on failure
+static int kernel_elf_exec(const char *path, uint16_t uid) {
     int slot = elf_exec(path, 24);
     if (slot >= 0) {
+        (void)scheduler_set_uid(kernel_scheduler, task_slots[slot].pid, uid);
         kernel_tss.rsp[0] = (uint64_t)(task_slots[slot].stack + TASK_STACK_SIZE);
     }
     return slot >= 0 ? 0 : -1;
 }
 
 // Global function pointer for CLI to call
-int (*g_elf_exec_fn)(const char *path) = (void *)0;
+int (*g_elf_exec_fn)(const char *path, uint16_t uid) = (void *)0;
 
 // =============================================================================
 // Phase 11: AI Auto-Tuner decision handler
@@ -2729,6 +3561,21 @@ static void kernel_remediate_handler(const char *action, const char *target,
 static uint64_t g_ai_last_tick  = 0;
 static uint64_t g_ai_last_stat  = 0;
 
+// Send one "key|value" STAT event to the host bridge and the kernel engine.
+static void idle_send_stat(const char *key, uint32_t value, uint64_t now) {
+    char buf[32];
+    char *p = buf;
+    for (const char *k = key; *k; k++) *p++ = *k;
+    *p++ = '|';
+    uint32_t v = value; int i = 0; char tmp[12];
+    if (v == 0) { tmp[i++] = '0'; }
+    else { while (v) { tmp[i++] = (char)('0' + v % 10); v /= 10; } }
+    for (int j = i - 1; j >= 0; j--) *p++ = tmp[j];
+    *p = '\0';
+    ai_send_event("STAT", buf);
+    ai_kernel_engine_feed("STAT", buf, now);
+}
+
 void kernel_idle_work(void) {
     uint64_t now = kernel_tick;
     const uint32_t ai_tick_interval = (TIMER_HZ / 2) ? (TIMER_HZ / 2) : 1; // ~500ms
@@ -2743,22 +3590,25 @@ void kernel_idle_work(void) {
     // Every ~1s: send STAT events
     if (now - g_ai_last_stat >= ai_stat_interval) {
         g_ai_last_stat = now;
+        for (int i = 0; i < MAX_TASKS; i++)
+            stack_sync_usage(i);
         void *sched = get_kernel_scheduler();
-        if (sched) {
-            uint32_t procs = scheduler_get_process_count(sched);
-            char buf[24];
-            const char *key = "process_count|";
-            char *p = buf;
-            for (const char *k = key; *k; k++) *p++ = *k;
-            uint32_t v = procs; int i = 0; char tmp[12];
-            if (v == 0) { tmp[i++] = '0'; }
-            else { while (v) { tmp[i++] = (char)('0' + v % 10); v /= 10; } }
-            for (int j = i - 1; j >= 0; j--) *p++ = tmp[j];
-            *p = '\0';
-            ai_send_event("STAT", buf);
-            ai_kernel_engine_feed("STAT", buf, now);
+        if (sched)
+            idle_send_stat("process_count", scheduler_get_process_count(sched), now);
+        // Cumulative counts; the auto-tuner turns them into a rate.
+        swap_stats_t ss;
+        if (swap_enabled() && swap_get_stats(&ss)) {
+            idle_send_stat("swap_out", ss.swap_outs, now);
+            idle_send_stat("swap_in", ss.swap_ins, now);
         }
     }
+
+    // Pending uring operations; a syscall must not see the rings mid-update
+    uint64_t rflags;
+    __asm__ volatile("pushfq; popq %0; cli" : "=r"(rflags) :: "memory");
+    uring_poll(get_kernel_scheduler(), (uint32_t)now);
+    task_wake_unblocked();
+    __asm__ volatile("pushq %0; popfq" :: "r"(rflags) : "memory", "cc");
 }
 
 // =============================================================================
@@ -4119,11 +4969,24 @@ void kernel_main(void) {
     // ----- Phase 16: Paging — rebuild page tables in kernel memory -----
     paging_init();
 
+    // Heap growth draws on the frame pool, so register once paging is up
+    heap_set_grow_cb(kernel_heap_grow_cb);
+    heap_set_oom_tasks_cb(kernel_oom_tasks_cb);
+    heap_set_oom_kill_cb(kernel_oom_kill_cb);
+
     // ----- Framebuffer init (after heap, before Phase 3) -----
     if (display_mode == 1) {
         volatile struct boot_info *bi = (volatile struct boot_info *)0x5300;
         uint64_t fb_addr = (uint64_t)bi->fb_addr | ((uint64_t)bi->fb_addr_high << 32);
-        fb_init(fb_addr, bi->fb_width, bi->fb_height, bi->fb_pitch, bi->fb_bpp);
+        if (fb_init(fb_addr, bi->fb_width, bi->fb_height, bi->fb_pitch, bi->fb_bpp,
+                    (const struct fb_channels *)&bi->fb_channels) < 0) {
+            serial_print("[fb] Unsupported pixel format, using VGA text mode\n");
+            display_mode = 0;
+        }
+    }
+    if (display_mode == 1) {
+        volatile struct boot_info *bi = (volatile struct boot_info *)0x5300;
+        uint64_t fb_addr = (uint64_t)bi->fb_addr | ((uint64_t)bi->fb_addr_high << 32);
         console_init(bi->fb_width, bi->fb_height);
         console_clear();
         serial_print("[fb] Framebuffer mode: ");
@@ -4158,6 +5021,9 @@ void kernel_main(void) {
     pit_init();
     serial_print("[pit] PIT timer set to 240Hz\n");
 
+    // ----- Kernel info page (read-only time/pid for user space) -----
+    kinfo_page_init();
+
     // ----- Keyboard init -----
     keyboard_init();
     serial_print("[kbd] keyboard buffer initialized\n");
@@ -4179,13 +5045,16 @@ void kernel_main(void) {
     verniskernel_register_print(rust_print_cb);
     serial_print("[phase3] syscall_init...\n");
     syscall_init();
+    syscall_table_init();
+    (void)usermem_init(&usermem_ops);
     serial_print("[phase3] scheduler_new...\n");
     kernel_scheduler = scheduler_new();
     serial_print("[phase3] create_process...\n");
-    uint32_t init_pid = scheduler_create_process(kernel_scheduler, 10, "init");
-    uint32_t ai_pid   = scheduler_create_process(kernel_scheduler, 8,  "ai_engine");
-    (void)init_pid;
-    (void)ai_pid;
+    int64_t init_rc = scheduler_create_process(kernel_scheduler, 10, "init");
+    int64_t ai_rc   = scheduler_create_process(kernel_scheduler, 8,  "ai_engine");
+    if (init_rc < 0 || ai_rc < 0)
+        serial_print("[phase3] create_process failed\n");
+    uint32_t init_pid = init_rc > 0 ? (uint32_t)init_rc : 0;
     // Phase 10: Notify AI of initial process creation
     {
         char evbuf[48];
@@ -4231,6 +5100,7 @@ void kernel_main(void) {
     kfs_init();
     // Phase 48: Block cache
     bcache_init();
+    (void)kernel_swap_on();
     userdb_init();
     // Phase 14: Audit log
     auditlog_init();
@@ -4247,6 +5117,11 @@ void kernel_main(void) {
     CliSession *user_session = cli_session_create(shell, "root", CLI_PRIV_ROOT);
     gui_shell = shell;
     gui_session = user_session;
+    // Framebuffer console: shell on tty1, kernel log on tty2
+    if (display_mode == 1 && user_session) {
+        console_assign(0, VT_OWNER_SESSION, user_session->session_id);
+        console_assign(1, VT_OWNER_KERNEL, 0);
+    }
     serial_print("[phase7] done\n");
 
     // ----- SYSCALL/SYSRET (Phase 5) -----
@@ -4256,11 +5131,12 @@ void kernel_main(void) {
     serial_print("[phase18] context switch setup...\n");
     task_register_main(init_pid, 24);   // 100ms quantum at 240 Hz
     {
-        uint32_t worker_pid = scheduler_create_process(kernel_scheduler, 9, "worker");
-        int worker_idx = task_create(phase18_worker_entry, worker_pid, 24);
+        int64_t worker_pid = scheduler_create_process(kernel_scheduler, 9, "worker");
+        int worker_idx = worker_pid > 0
+            ? task_create(phase18_worker_entry, (uint32_t)worker_pid, 24) : -1;
         if (worker_idx >= 0) {
             serial_print("[phase18] worker task created (pid=");
-            serial_print_dec(worker_pid);
+            serial_print_dec((uint32_t)worker_pid);
             serial_print(")\n");
         }
     }
@@ -27,8 +27,20 @@ syscall_entry:
     push r14
     push r15
 
-    ; Arrange arguments for c_syscall_handler(num, a1, a2, a3, a4):
-    ; System V AMD64: rdi=1st, rsi=2nd, rdx=3rd, rcx=4th, r8=5th
+    ; The rest of the entry registers, so the C side can save the caller's
+    ; context (SyscallEntryFrame in kernel_x64.c, lowest address first)
+    push r8
+    push r9
+    push r10
+    push rdi
+    push rsi
+    push rdx
+    push rax
+    mov r9, rsp        ; r9 = frame, 6th parameter
+    sub rsp, 8         ; 15 pushes: realign to 16 for the call
+
+    ; Arrange arguments for c_syscall_handler(num, a1, a2, a3, a4, frame):
+    ; System V AMD64: rdi=1st, rsi=2nd, rdx=3rd, rcx=4th, r8=5th, r9=6th
     ;   num  = rax  (syscall number)
     ;   arg1 = rdi  (already in rdi)
     ;   arg2 = rsi  (already in rsi)
@@ -43,6 +55,7 @@ syscall_entry:
     call c_syscall_handler
     ; Return value in rax
 
+    add rsp, 8 * 8     ; drop the padding and the saved argument registers
     pop r15
     pop r14
     pop r13
@@ -53,6 +53,12 @@ pub struct AutoTuner {
     event_rate: RateWindow,
     process_count: u32,
     exception_count: u32,
+    /// Cumulative swap counters from the last STAT sample, and the pages
+    /// moved since the sample before it.
+    swap_outs: u32,
+    swap_ins: u32,
+    swap_out_delta: u32,
+    swap_in_delta: u32,
     last_decision_tick: u64,
     decisions: VecDeque<TuningDecision>,
     /// Per-action cooldown: action discriminant → last tick
@@ -73,6 +79,10 @@ impl AutoTuner {
             event_rate: RateWindow::new(1000), // 10-second window
             process_count: 0,
             exception_count: 0,
+            swap_outs: 0,
+            swap_ins: 0,
+            swap_out_delta: 0,
+            swap_in_delta: 0,
             last_decision_tick: 0,
             decisions: VecDeque::with_capacity(32),
             cooldowns: [0u64; 5],
@@ -101,6 +111,14 @@ impl AutoTuner {
                 parse_pipe_fields(data, &mut fields);
                 if fields[0] == "process_count" {
                     self.process_count = parse_u32(fields[1]);
+                } else if fields[0] == "swap_out" {
+                    let total = parse_u32(fields[1]);
+                    self.swap_out_delta = total.saturating_sub(self.swap_outs);
+                    self.swap_outs = total;
+                } else if fields[0] == "swap_in" {
+                    let total = parse_u32(fields[1]);
+                    self.swap_in_delta = total.saturating_sub(self.swap_ins);
+                    self.swap_ins = total;
                 }
             }
             EventType::Exception => {
@@ -142,62 +160,78 @@ impl AutoTuner {
         let load = self.assess_load();
         let rate = self.event_rate.rate();
         let _procs = self.process_count;
+        let swap_activity = self.swap_out_delta.saturating_add(self.swap_in_delta);
 
-        let decision = match load {
-            LoadLevel::Critical => {
-                if !self.on_cooldown(TuneAction::Throttle, now) {
-                    // Widen quantum to reduce context switches under pressure
-                    let quantum = 20 + (rate as u32).min(30);
-                    Some(TuningDecision {
-                        action: TuneAction::SchedQuantum,
-                        target: String::from("scheduler"),
-                        value: quantum,
-                        load,
-                        timestamp: KernelInstant::from_ticks(now),
-                    })
-                } else {
-                    None
+        // Paging traffic trumps scheduler tuning: ask the kernel to free memory
+        let decision = if swap_activity >= config::SWAP_PRESSURE_PAGES
+            && !self.on_cooldown(TuneAction::MemPressure, now)
+        {
+            self.swap_out_delta = 0;
+            self.swap_in_delta = 0;
+            Some(TuningDecision {
+                action: TuneAction::MemPressure,
+                target: String::from("memory"),
+                value: swap_activity,
+                load,
+                timestamp: KernelInstant::from_ticks(now),
+            })
+        } else {
+            match load {
+                LoadLevel::Critical => {
+                    if !self.on_cooldown(TuneAction::Throttle, now) {
+                        // Widen quantum to reduce context switches under pressure
+                        let quantum = 20 + (rate as u32).min(30);
+                        Some(TuningDecision {
+                            action: TuneAction::SchedQuantum,
+                            target: String::from("scheduler"),
+                            value: quantum,
+                            load,
+                            timestamp: KernelInstant::from_ticks(now),
+                        })
+                    } else {
+                        None
+                    }
                 }
-            }
-            LoadLevel::High => {
-                if !self.on_cooldown(TuneAction::SchedQuantum, now) {
-                    let quantum = 15 + (rate as u32 / 2).min(15);
-                    Some(TuningDecision {
-                        action: TuneAction::SchedQuantum,
-                        target: String::from("scheduler"),
-                        value: quantum,
-                        load,
-                        timestamp: KernelInstant::from_ticks(now),
-                    })
-                } else {
-                    None
+                LoadLevel::High => {
+                    if !self.on_cooldown(TuneAction::SchedQuantum, now) {
+                        let quantum = 15 + (rate as u32 / 2).min(15);
+                        Some(TuningDecision {
+                            action: TuneAction::SchedQuantum,
+                            target: String::from("scheduler"),
+                            value: quantum,
+                            load,
+                            timestamp: KernelInstant::from_ticks(now),
+                        })
+                    } else {
+                        None
+                    }
                 }
-            }
-            LoadLevel::Low => {
-                if !self.on_cooldown(TuneAction::SchedQuantum, now) {
-                    Some(TuningDecision {
-                        action: TuneAction::SchedQuantum,
-                        target: String::from("scheduler"),
-                        value: 5, // narrow quantum for responsiveness
-                        load,
-                        timestamp: KernelInstant::from_ticks(now),
-                    })
-                } else {
-                    None
+                LoadLevel::Low => {
+                    if !self.on_cooldown(TuneAction::SchedQuantum, now) {
+                        Some(TuningDecision {
+                            action: TuneAction::SchedQuantum,
+                            target: String::from("scheduler"),
+                            value: 5, // narrow quantum for responsiveness
+                            load,
+                            timestamp: KernelInstant::from_ticks(now),
+                        })
+                    } else {
+                        None
+                    }
                 }
-            }
-            LoadLevel::Normal => {
-                if self.exception_count > 0 && !self.on_cooldown(TuneAction::SchedPrio, now) {
-                    self.exception_count = 0;
-                    Some(TuningDecision {
-                        action: TuneAction::SchedPrio,
-                        target: String::from("scheduler"),
-                        value: self.default_quantum,
-                        load,
-                        timestamp: KernelInstant::from_ticks(now),
-                    })
-                } else {
-                    None
+                LoadLevel::Normal => {
+                    if self.exception_count > 0 && !self.on_cooldown(TuneAction::SchedPrio, now) {
+                        self.exception_count = 0;
+                        Some(TuningDecision {
+                            action: TuneAction::SchedPrio,
+                            target: String::from("scheduler"),
+                            value: self.default_quantum,
+                            load,
+                            timestamp: KernelInstant::from_ticks(now),
+                        })
+                    } else {
+                        None
+                    }
                 }
             }
         };
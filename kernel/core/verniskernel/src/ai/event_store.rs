@@ -1,16 +1,17 @@
 // event_store.rs — Fixed ring buffer event history with query API (Phase 15)
 //
 // Port of ai/event_store.py to Rust no_std.
-// Uses a fixed-size array ring buffer — zero heap allocation for the buffer itself.
+// Uses a fixed-size array ring buffer; records come from the "event_record"
+// slab cache and are overwritten in place once the ring wraps.
 
 use alloc::vec::Vec;
-use alloc::string::String;
 use super::types::*;
+use crate::slab::SlabBox;
 
 const RING_CAPACITY: usize = 256;
 
 pub struct EventStore {
-    events: [Option<EventRecord>; RING_CAPACITY],
+    events: [Option<SlabBox<EventRecord>>; RING_CAPACITY],
     head: usize,       // next write position
     count: usize,      // current number of events
     total_recorded: u64,
@@ -18,7 +19,7 @@ pub struct EventStore {
 
 impl EventStore {
     pub fn new(_capacity: usize) -> Self {
-        const NONE: Option<EventRecord> = None;
+        const NONE: Option<SlabBox<EventRecord>> = None;
         Self {
             events: [NONE; RING_CAPACITY],
             head: 0,
@@ -28,14 +29,12 @@ impl EventStore {
     }
 
     pub fn record(&mut self, event_type: EventType, data: &str, source_pid: u32, now: u64) {
-        let record = EventRecord {
-            timestamp: KernelInstant::from_ticks(now),
-            event_type,
-            data: String::from(data),
-            source_pid,
-        };
-
-        self.events[self.head] = Some(record);
+        let record = EventRecord::new(KernelInstant::from_ticks(now), event_type, data, source_pid);
+
+        match self.events[self.head] {
+            Some(ref mut slot) => **slot = record,
+            None => self.events[self.head] = Some(SlabBox::new(record)),
+        }
         self.head = (self.head + 1) % RING_CAPACITY;
         if self.count < RING_CAPACITY {
             self.count += 1;
@@ -57,7 +56,7 @@ impl EventStore {
         let mut result = Vec::with_capacity(n);
         for i in 0..n {
             let idx = (self.head + RING_CAPACITY - 1 - i) % RING_CAPACITY;
-            if let Some(ref e) = self.events[idx] {
+            if let Some(e) = self.events[idx].as_deref() {
                 result.push(e);
             }
         }
@@ -134,7 +133,7 @@ impl EventStore {
         let count = self.count;
         (0..count).filter_map(move |i| {
             let idx = (start + i) % RING_CAPACITY;
-            self.events[idx].as_ref()
+            self.events[idx].as_deref()
         })
     }
 }
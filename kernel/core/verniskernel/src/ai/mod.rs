@@ -22,6 +22,17 @@ use response_handler::ResponseHandler;
 use auto_tuner::AutoTuner;
 use policy::PolicyConfig;
 use crate::kernel_print_raw;
+use crate::errno::{self, KResult, KernelError};
+use crate::heap::{self, HeapTag};
+
+extern "C" {
+    // module.c: quarantine the module behind a bad-module alert, if any.
+    fn module_quarantine_suspect(now: u64);
+}
+
+/// Pattern rule (MOD then EXCP) that makes the module registry quarantine
+/// the module that just faulted.
+const BAD_MODULE_PATTERN: &str = "bad-module";
 
 // =============================================================================
 // AI Engine — composite struct holding all components
@@ -95,8 +106,12 @@ impl AiEngine {
             }
         }
 
-        // 5. Process detected anomalies
+        // 5. Process detected anomalies. A faulting module is quarantined
+        //    even if the alert itself is deduplicated.
         for a in anomalies {
+            if a.detector == DetectorKind::Pattern && a.title == BAD_MODULE_PATTERN {
+                unsafe { module_quarantine_suspect(now) };
+            }
             self.process_anomaly(&a, now);
         }
 
@@ -143,7 +158,7 @@ impl AiEngine {
 
     /// Load a new policy configuration. Distributes to all components.
     /// Returns true on success.
-    pub fn load_policy(&mut self, config: PolicyConfig) -> bool {
+    pub fn load_policy(&mut self, config: PolicyConfig) -> KResult<()> {
         self.anomaly_detector.reload_config(
             &config.rate_rules,
             &config.pattern_rules,
@@ -155,13 +170,13 @@ impl AiEngine {
         self.process_tracker.reload_config(&config.trust);
         // Store access rules for command-level enforcement
         self.access_rules = config.access_rules;
-        true
+        Ok(())
     }
 
     /// Check if a command is allowed at the given privilege level.
-    /// Returns the required privilege (0=root, 50=admin, 100=user), or 255 if no rule matches.
+    /// Returns the required privilege (0=root, 50=admin, 100=user), or NoEntry if no rule matches.
     /// The caller should allow if session_privilege >= returned value (lower = more privileged).
-    pub fn check_access(&self, command: &str) -> u8 {
+    pub fn check_access(&self, command: &str) -> KResult<u8> {
         let cmd_bytes = command.as_bytes();
         for rule in &self.access_rules {
             let pattern = &rule.pattern[..rule.pattern_len as usize];
@@ -172,13 +187,13 @@ impl AiEngine {
                 if i == pattern.len() || pattern[i] == b'|' {
                     let alt = &pattern[start..i];
                     if Self::cmd_matches(cmd_bytes, alt) {
-                        return rule.min_privilege;
+                        return Ok(rule.min_privilege);
                     }
                     start = i + 1;
                 }
             }
         }
-        255 // No rule matched
+        Err(KernelError::NoEntry)
     }
 
     /// Check if a command matches an alternative pattern.
@@ -200,6 +215,18 @@ impl AiEngine {
 // FFI Exports — called from C kernel
 // =============================================================================
 
+/// Borrow a (pointer, length) string from C. None for a null pointer or
+/// invalid UTF-8; events carrying either are dropped rather than trusted.
+unsafe fn ffi_str<'a>(ptr: *const u8, len: usize) -> Option<&'a str> {
+    if len == 0 {
+        return Some("");
+    }
+    if ptr.is_null() {
+        return None;
+    }
+    core::str::from_utf8(core::slice::from_raw_parts(ptr, len)).ok()
+}
+
 /// Create a new AI engine instance. Returns an opaque pointer.
 #[no_mangle]
 pub extern "C" fn ai_engine_new() -> *mut AiEngine {
@@ -218,13 +245,12 @@ pub unsafe extern "C" fn ai_engine_feed_event(
 ) {
     if engine.is_null() { return; }
     let eng = &mut *engine;
+    let _tag = heap::tag_scope(HeapTag::Ai);
 
-    let evt_str = core::str::from_utf8_unchecked(
-        core::slice::from_raw_parts(event_type, event_type_len)
-    );
-    let data_str = core::str::from_utf8_unchecked(
-        core::slice::from_raw_parts(data, data_len)
-    );
+    let (evt_str, data_str) = match (ffi_str(event_type, event_type_len), ffi_str(data, data_len)) {
+        (Some(e), Some(d)) => (e, d),
+        _ => return,
+    };
 
     eng.feed_event(evt_str, data_str, now_ticks);
 }
@@ -239,9 +265,11 @@ pub unsafe extern "C" fn ai_engine_feed_event_code(
 ) {
     if engine.is_null() { return; }
     let eng = &mut *engine;
-    let data_str = core::str::from_utf8_unchecked(
-        core::slice::from_raw_parts(data, data_len)
-    );
+    let _tag = heap::tag_scope(HeapTag::Ai);
+    let data_str = match ffi_str(data, data_len) {
+        Some(d) => d,
+        None => return,
+    };
     eng.feed_event_code(event_code, data_str, now_ticks);
 }
 
@@ -250,6 +278,7 @@ pub unsafe extern "C" fn ai_engine_feed_event_code(
 pub unsafe extern "C" fn ai_engine_tick(engine: *mut AiEngine, now_ticks: u64) {
     if engine.is_null() { return; }
     let eng = &mut *engine;
+    let _tag = heap::tag_scope(HeapTag::Ai);
     eng.tick(now_ticks);
 }
 
@@ -310,31 +339,29 @@ pub unsafe extern "C" fn ai_engine_free(engine: *mut AiEngine) {
 }
 
 /// Load a binary policy blob into the AI engine.
-/// Returns 1 on success, 0 on failure (bad magic/version/format).
+/// Returns 0 on success, -EINVAL on a bad magic/version/format.
 #[no_mangle]
 pub unsafe extern "C" fn ai_engine_load_policy(
     engine: *mut AiEngine,
     blob: *const u8,
     blob_len: usize,
-) -> u32 {
+) -> i32 {
     if engine.is_null() || blob.is_null() || blob_len < policy::HEADER_SIZE {
-        return 0;
+        return KernelError::Inval.neg();
     }
     let eng = &mut *engine;
     let data = core::slice::from_raw_parts(blob, blob_len);
 
-    match policy::parse_policy_blob(data) {
-        Some(cfg) => {
-            let ver = cfg.version;
-            if eng.load_policy(cfg) {
-                eng.policy_ver = ver;
-                1
-            } else {
-                0
-            }
-        }
-        None => 0,
+    let cfg = match policy::parse_policy_blob(data) {
+        Some(cfg) => cfg,
+        None => return KernelError::Inval.neg(),
+    };
+    let ver = cfg.version;
+    let r = eng.load_policy(cfg);
+    if r.is_ok() {
+        eng.policy_ver = ver;
     }
+    errno::to_c(r)
 }
 
 /// Get the currently loaded policy version.
@@ -346,21 +373,23 @@ pub unsafe extern "C" fn ai_engine_policy_version(engine: *const AiEngine) -> u1
 }
 
 /// Check access for a command at a given privilege level.
-/// Returns: required privilege (0=root, 50=admin, 100=user), or 255 if no rule.
+/// Returns: required privilege (0=root, 50=admin, 100=user), -ENOENT if no
+/// rule matches, or -EINVAL for a bad argument.
 #[no_mangle]
 pub unsafe extern "C" fn ai_engine_check_access(
     engine: *const AiEngine,
     command: *const u8,
     command_len: usize,
-) -> u8 {
+) -> i32 {
     if engine.is_null() || command.is_null() || command_len == 0 {
-        return 255;
+        return KernelError::Inval.neg();
     }
     let eng = &*engine;
-    let cmd_str = core::str::from_utf8_unchecked(
-        core::slice::from_raw_parts(command, command_len)
-    );
-    eng.check_access(cmd_str)
+    let cmd_str = match ffi_str(command, command_len) {
+        Some(c) => c,
+        None => return KernelError::Inval.neg(),
+    };
+    errno::to_c_i32(eng.check_access(cmd_str).map(i32::from))
 }
 
 /// Get the number of loaded access rules.
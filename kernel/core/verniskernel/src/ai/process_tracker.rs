@@ -49,6 +49,7 @@ impl ProcessProfile {
     }
 
     fn recompute_trust(&mut self, tc: &TrustConfig) {
+        let before = self.trust;
         if self.failure_count >= tc.failures_to_untrusted
             || self.denial_count >= tc.denials_to_untrusted
             || self.anomaly_count >= tc.anomalies_to_untrusted
@@ -61,6 +62,10 @@ impl ProcessProfile {
             self.trust = TrustLevel::Suspicious;
         }
         // Trust never goes back up automatically
+        if self.trust != before {
+            // The OOM policy reads trust without borrowing the engine.
+            crate::heap::note_trust(self.pid, self.trust);
+        }
     }
 
     pub fn is_alive(&self) -> bool {
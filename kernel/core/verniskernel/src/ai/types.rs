@@ -232,14 +232,37 @@ impl RemediationAction {
 // Stored event record
 // =============================================================================
 
+/// Bytes of event payload kept inline per record; longer data is truncated.
+pub const EVENT_DATA_MAX: usize = 64;
+
+/// Fixed-size so records can live in the "event_record" slab cache
+/// instead of dragging a heap `String` along with every event.
 #[derive(Debug, Clone)]
 pub struct EventRecord {
     pub timestamp: KernelInstant,
     pub event_type: EventType,
-    pub data: String,
+    data: [u8; EVENT_DATA_MAX],
+    data_len: u8,
     pub source_pid: u32,
 }
 
+impl EventRecord {
+    pub fn new(timestamp: KernelInstant, event_type: EventType, data: &str, source_pid: u32) -> Self {
+        // Truncate on a char boundary so data() stays valid UTF-8
+        let mut n = data.len().min(EVENT_DATA_MAX);
+        while !data.is_char_boundary(n) {
+            n -= 1;
+        }
+        let mut buf = [0u8; EVENT_DATA_MAX];
+        buf[..n].copy_from_slice(&data.as_bytes()[..n]);
+        Self { timestamp, event_type, data: buf, data_len: n as u8, source_pid }
+    }
+
+    pub fn data(&self) -> &str {
+        core::str::from_utf8(&self.data[..self.data_len as usize]).unwrap_or("")
+    }
+}
+
 // =============================================================================
 // Pipe-separated data parser helper
 // =============================================================================
@@ -370,6 +393,9 @@ pub mod config {
     pub const CRITICAL_RATE: f32 = 50.0;
     pub const HIGH_PROC_COUNT: u32 = 8;
     pub const CRITICAL_PROC_COUNT: u32 = 16;
+    // Pages moved to or from swap between two ~1s STAT samples before
+    // the tuner reports memory pressure
+    pub const SWAP_PRESSURE_PAGES: u32 = 16;
     pub const DEFAULT_QUANTUM: u32 = 10;
     pub const TUNER_COOLDOWN_TICKS: u64 = 1500; // 15 seconds
 
@@ -1,104 +1,239 @@
 // Text console on top of framebuffer
-// Tracks cursor position, handles control characters, VGA color translation
+// Output goes through the shared VT interpreter (vt.rs), which tracks the
+// cursor and colors and handles control characters and escape sequences;
+// this file maps its cell operations onto framebuffer pixels.
+//
+// Cells are also kept in a scrollback buffer (scrollback.rs), so lines that
+// scroll off the top can be paged back with Shift+PageUp/PageDown. While
+// the view is scrolled back only the buffer is updated; the next output
+// snaps it to the bottom and redraws. Without the buffer (allocation
+// failed) the console still draws, just without history.
+//
+// There are VT_COUNT virtual consoles, each with its own Vt (cursor,
+// colors) and buffer. Only the active one draws; Alt+F1..F6 switches and
+// redraws from the buffer. A console can be owned by the kernel log, a
+// process or a CLI session: the owner gets its keyboard input, and the
+// C side sends its output there (klog via console_log, process stdout via
+// console_write_to). The legacy single-console calls (console_putchar,
+// console_set_color, ...) act on the output console: the one owned by a
+// session, or console 0.
 
+use core::fmt::Write;
+use core::ptr::addr_of_mut;
+
+use crate::errno::KernelError;
+use crate::font::Grapheme;
 use crate::framebuffer::{
-    fb_clear, fb_draw_char, fb_fill_rect, fb_scroll,
+    fb_draw_glyph, fb_fill_rect, fb_move_rows,
     FONT_HEIGHT, FONT_WIDTH,
 };
+use crate::heap::{self, HeapTag};
+use crate::scrollback::CellBuffer;
+use crate::textbuf::TextBuf;
+use crate::vt::{Vt, VtColor, VtScreen};
 
-/// VGA 16-color palette → 32-bit BGRA
-const VGA_PALETTE: [u32; 16] = [
-    0x00000000, // 0: black
-    0x00AA0000, // 1: blue       (BGRA: B=AA, G=00, R=00)
-    0x0000AA00, // 2: green      (BGRA: B=00, G=AA, R=00)
-    0x00AAAA00, // 3: cyan
-    0x000000AA, // 4: red        (BGRA: B=00, G=00, R=AA)
-    0x00AA00AA, // 5: magenta
-    0x000055AA, // 6: brown
-    0x00AAAAAA, // 7: light grey
-    0x00555555, // 8: dark grey
-    0x00FF5555, // 9: light blue
-    0x0055FF55, // 10: light green
-    0x00FFFF55, // 11: light cyan
-    0x005555FF, // 12: light red
-    0x00FF55FF, // 13: light magenta
-    0x0055FFFF, // 14: yellow
-    0x00FFFFFF, // 15: white
-];
+/// Number of virtual consoles.
+pub const VT_COUNT: usize = 6;
+/// Lines of history kept by default on console 0.
+const CONSOLE_HISTORY: u32 = 200;
+/// Lines of history kept by default on the other consoles.
+const CONSOLE_HISTORY_VT: u32 = 50;
+/// Upper bound for console_set_scrollback.
+const CONSOLE_HISTORY_MAX: u32 = 4000;
 
-struct Console {
-    cursor_row: u32,
-    cursor_col: u32,
-    max_rows: u32,
-    max_cols: u32,
-    fg_color: u32,
-    bg_color: u32,
+// Owner kinds as passed over FFI (include/console.h VT_OWNER_*).
+const VT_OWNER_NONE: u32 = 0;
+const VT_OWNER_KERNEL: u32 = 1;
+const VT_OWNER_PROCESS: u32 = 2;
+const VT_OWNER_SESSION: u32 = 3;
+
+/// Who a console belongs to: its output source and input consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtOwner {
+    None,
+    /// Kernel log output (klog).
+    Kernel,
+    Process(u32),
+    /// CLI session id.
+    Session(u32),
+}
+
+impl VtOwner {
+    fn from_raw(kind: u32, id: u32) -> Option<Self> {
+        match kind {
+            VT_OWNER_NONE => Some(VtOwner::None),
+            VT_OWNER_KERNEL => Some(VtOwner::Kernel),
+            VT_OWNER_PROCESS => Some(VtOwner::Process(id)),
+            VT_OWNER_SESSION => Some(VtOwner::Session(id)),
+            _ => None,
+        }
+    }
+
+    fn raw(self) -> (u32, u32) {
+        match self {
+            VtOwner::None => (VT_OWNER_NONE, 0),
+            VtOwner::Kernel => (VT_OWNER_KERNEL, 0),
+            VtOwner::Process(pid) => (VT_OWNER_PROCESS, pid),
+            VtOwner::Session(id) => (VT_OWNER_SESSION, id),
+        }
+    }
+}
+
+/// Cell grid drawn straight to the framebuffer, mirrored into the
+/// scrollback buffer.
+struct FbScreen {
     initialized: bool,
+    /// Active console: the only one that draws.
+    visible: bool,
+    cells: Option<CellBuffer>,
 }
 
-static mut CONSOLE: Console = Console {
-    cursor_row: 0,
-    cursor_col: 0,
-    max_rows: 0,
-    max_cols: 0,
-    fg_color: 0x00AAAAAA, // light grey
-    bg_color: 0x00000000, // black
-    initialized: false,
-};
+impl FbScreen {
+    /// Whether the framebuffer shows this console's live screen.
+    fn live(&self) -> bool {
+        self.visible && self.cells.as_ref().is_none_or(|c| c.view() == 0)
+    }
 
-#[no_mangle]
-pub unsafe extern "C" fn console_init(width: u32, height: u32) {
-    CONSOLE.max_cols = width / FONT_WIDTH;
-    CONSOLE.max_rows = height / FONT_HEIGHT;
-    CONSOLE.cursor_row = 0;
-    CONSOLE.cursor_col = 0;
-    CONSOLE.fg_color = VGA_PALETTE[15]; // white
-    CONSOLE.bg_color = VGA_PALETTE[0];  // black
-    CONSOLE.initialized = true;
-    fb_clear(CONSOLE.bg_color);
+    /// Draw the current view from the buffer.
+    fn redraw(&self) {
+        let Some(cells) = self.cells.as_ref() else { return };
+        for r in 0..cells.rows() {
+            for (c, cell) in cells.view_row(r).iter().enumerate() {
+                unsafe {
+                    fb_draw_glyph(c as u32 * FONT_WIDTH, r * FONT_HEIGHT,
+                                  &cell.g, cell.fg, cell.bg);
+                }
+            }
+        }
+    }
+
+    /// Return to the live screen before it is written to.
+    fn snap(&mut self) {
+        if let Some(cells) = self.cells.as_mut() {
+            if cells.reset_view() && self.visible {
+                self.redraw();
+            }
+        }
+    }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn console_putchar(c: u8) {
-    if !CONSOLE.initialized {
-        return;
+impl VtScreen for FbScreen {
+    fn put(&mut self, row: u32, col: u32, g: Grapheme, fg: u32, bg: u32) {
+        if let Some(cells) = self.cells.as_mut() {
+            cells.put(row, col, g, fg, bg);
+        }
+        if self.live() {
+            unsafe { fb_draw_glyph(col * FONT_WIDTH, row * FONT_HEIGHT, &g, fg, bg) };
+        }
     }
-    match c {
-        b'\n' => {
-            CONSOLE.cursor_col = 0;
-            CONSOLE.cursor_row += 1;
-        }
-        b'\r' => {
-            CONSOLE.cursor_col = 0;
-        }
-        0x08 => {
-            // Backspace
-            if CONSOLE.cursor_col > 0 {
-                CONSOLE.cursor_col -= 1;
-                let px = CONSOLE.cursor_col * FONT_WIDTH;
-                let py = CONSOLE.cursor_row * FONT_HEIGHT;
-                fb_fill_rect(px, py, FONT_WIDTH, FONT_HEIGHT, CONSOLE.bg_color);
+
+    fn clear(&mut self, row: u32, from: u32, to: u32, bg: u32) {
+        if let Some(cells) = self.cells.as_mut() {
+            cells.clear(row, from, to, bg);
+        }
+        if to > from && self.live() {
+            unsafe {
+                fb_fill_rect(from * FONT_WIDTH, row * FONT_HEIGHT,
+                             (to - from) * FONT_WIDTH, FONT_HEIGHT, bg);
             }
         }
-        _ => {
-            let px = CONSOLE.cursor_col * FONT_WIDTH;
-            let py = CONSOLE.cursor_row * FONT_HEIGHT;
-            fb_draw_char(px, py, c, CONSOLE.fg_color, CONSOLE.bg_color);
-            CONSOLE.cursor_col += 1;
-            if CONSOLE.cursor_col >= CONSOLE.max_cols {
-                CONSOLE.cursor_col = 0;
-                CONSOLE.cursor_row += 1;
+    }
+
+    fn scroll(&mut self, top: u32, bottom: u32, lines: i32, bg: u32) {
+        let span = bottom.saturating_sub(top);
+        let n = lines.unsigned_abs().min(span);
+        if let Some(cells) = self.cells.as_mut() {
+            cells.scroll(top, bottom, lines, bg);
+        }
+        if n == 0 || !self.live() {
+            return;
+        }
+        let keep = (span - n) * FONT_HEIGHT;
+        let width = unsafe { crate::framebuffer::fb_get_width() };
+        unsafe {
+            if lines > 0 {
+                fb_move_rows(top * FONT_HEIGHT, (top + n) * FONT_HEIGHT, keep);
+                fb_fill_rect(0, (bottom - n) * FONT_HEIGHT, width, n * FONT_HEIGHT, bg);
+            } else {
+                fb_move_rows((top + n) * FONT_HEIGHT, top * FONT_HEIGHT, keep);
+                fb_fill_rect(0, top * FONT_HEIGHT, width, n * FONT_HEIGHT, bg);
             }
         }
     }
+}
 
-    // Scroll if needed
-    if CONSOLE.cursor_row >= CONSOLE.max_rows {
-        fb_scroll(1);
-        CONSOLE.cursor_row = CONSOLE.max_rows - 1;
+struct Console {
+    vt: Vt,
+    screen: FbScreen,
+    owner: VtOwner,
+}
+
+impl Console {
+    fn write(&mut self, bytes: &[u8]) {
+        if !self.screen.initialized {
+            return;
+        }
+        self.screen.snap();
+        self.vt.write(&mut self.screen, bytes);
     }
 }
 
+const CONSOLE_INIT: Console = Console {
+    vt: Vt::new(VtColor::Vga(15), VtColor::Vga(0)), // white on black
+    screen: FbScreen { initialized: false, visible: false, cells: None },
+    owner: VtOwner::None,
+};
+
+static mut CONSOLES: [Console; VT_COUNT] = [CONSOLE_INIT; VT_COUNT];
+/// Console shown on the framebuffer.
+static mut ACTIVE: usize = 0;
+/// Console the legacy calls write to.
+static mut OUTPUT: usize = 0;
+
+unsafe fn consoles() -> &'static mut [Console; VT_COUNT] {
+    &mut *addr_of_mut!(CONSOLES)
+}
+
+/// The output console.
+unsafe fn console() -> &'static mut Console {
+    &mut consoles()[OUTPUT]
+}
+
+unsafe fn active() -> &'static mut Console {
+    &mut consoles()[ACTIVE]
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn console_init(width: u32, height: u32) {
+    let (rows, cols) = (height / FONT_HEIGHT, width / FONT_WIDTH);
+    for (i, con) in consoles().iter_mut().enumerate() {
+        con.vt = Vt::new(VtColor::Vga(15), VtColor::Vga(0));
+        con.vt.resize(rows, cols);
+        let (fg, bg) = con.vt.attr.colors();
+        let history = if i == 0 { CONSOLE_HISTORY } else { CONSOLE_HISTORY_VT };
+        con.screen.cells = {
+            let _tag = heap::tag_scope(HeapTag::Gui);
+            CellBuffer::new(rows, cols, history, fg, bg).ok()
+        };
+        con.screen.visible = i == 0;
+        con.screen.initialized = true;
+        con.owner = VtOwner::None;
+        con.vt.clear(&mut con.screen);
+    }
+    ACTIVE = 0;
+    OUTPUT = 0;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn console_putchar(c: u8) {
+    let con = console();
+    if !con.screen.initialized {
+        return;
+    }
+    con.screen.snap();
+    con.vt.feed(&mut con.screen, c);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn console_writestring(s: *const u8, len: u32) {
     if s.is_null() {
@@ -109,55 +244,244 @@ pub unsafe extern "C" fn console_writestring(s: *const u8, len: u32) {
     }
 }
 
+/// Write UTF-8 text (escape sequences included).
+pub unsafe fn console_write_str(s: &str) {
+    console().write(s.as_bytes());
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn console_clear() {
-    if !CONSOLE.initialized {
+    let con = console();
+    if !con.screen.initialized {
         return;
     }
-    fb_clear(CONSOLE.bg_color);
-    CONSOLE.cursor_row = 0;
-    CONSOLE.cursor_col = 0;
+    con.screen.snap();
+    con.vt.clear(&mut con.screen);
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn console_set_color(fg: u32, bg: u32) {
-    CONSOLE.fg_color = fg;
-    CONSOLE.bg_color = bg;
+    console().vt.set_colors(VtColor::Rgb(fg), VtColor::Rgb(bg));
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn console_set_color_vga(fg_index: u8, bg_index: u8) {
-    if (fg_index as usize) < VGA_PALETTE.len() {
-        CONSOLE.fg_color = VGA_PALETTE[fg_index as usize];
-    }
-    if (bg_index as usize) < VGA_PALETTE.len() {
-        CONSOLE.bg_color = VGA_PALETTE[bg_index as usize];
-    }
+    let vt = &mut console().vt;
+    let fg = if fg_index < 16 { VtColor::Vga(fg_index) } else { vt.attr.fg };
+    let bg = if bg_index < 16 { VtColor::Vga(bg_index) } else { vt.attr.bg };
+    vt.set_colors(fg, bg);
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn console_set_pos(row: u32, col: u32) {
-    CONSOLE.cursor_row = row;
-    CONSOLE.cursor_col = col;
+    console().vt.move_to(row, col);
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn console_get_pos(row: *mut u32, col: *mut u32) {
+    let vt = &console().vt;
     if !row.is_null() {
-        *row = CONSOLE.cursor_row;
+        *row = vt.row;
     }
     if !col.is_null() {
-        *col = CONSOLE.cursor_col;
+        *col = vt.col;
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn console_clear_to_eol(row: u32, col: u32) {
-    if !CONSOLE.initialized {
+    let con = console();
+    if !con.screen.initialized {
+        return;
+    }
+    let (_, bg) = con.vt.attr.colors();
+    con.screen.snap();
+    con.screen.clear(row, col, con.vt.cols, bg);
+}
+
+/// Page the active console's view through the scrollback: positive pages
+/// go back into history, negative toward the live screen, half a screen
+/// per page.
+#[no_mangle]
+pub unsafe extern "C" fn console_scroll_view(pages: i32) {
+    let con = active();
+    if !con.screen.initialized {
         return;
     }
-    let px = col * FONT_WIDTH;
-    let py = row * FONT_HEIGHT;
-    let remaining_width = CONSOLE.max_cols.saturating_sub(col) * FONT_WIDTH;
-    fb_fill_rect(px, py, remaining_width, FONT_HEIGHT, CONSOLE.bg_color);
+    let Some(cells) = con.screen.cells.as_mut() else { return };
+    let step = (cells.rows() / 2).max(1) as i32;
+    if cells.scroll_view(pages.saturating_mul(step)) {
+        con.screen.redraw();
+    }
+}
+
+/// Resize the active console's scrollback to `lines` of history, keeping
+/// the screen and as much of the existing history as fits. Returns 0 or a
+/// negative errno.
+#[no_mangle]
+pub unsafe extern "C" fn console_set_scrollback(lines: u32) -> i32 {
+    let con = active();
+    if lines > CONSOLE_HISTORY_MAX {
+        return KernelError::Inval.neg();
+    }
+    let Some(cells) = con.screen.cells.as_ref() else {
+        return KernelError::NoDevice.neg();
+    };
+    let resized = {
+        let _tag = heap::tag_scope(HeapTag::Gui);
+        cells.with_history(lines)
+    };
+    match resized {
+        Ok(cells) => {
+            let was_live = con.screen.live();
+            con.screen.cells = Some(cells);
+            if !was_live {
+                con.screen.redraw();
+            }
+            0
+        }
+        Err(e) => e.neg(),
+    }
+}
+
+/// Lines of history held by the active console and its limit.
+#[no_mangle]
+pub unsafe extern "C" fn console_get_scrollback(used: *mut u32, limit: *mut u32) {
+    let (u, l) = active().screen.cells.as_ref()
+        .map_or((0, 0), |c| (c.history(), c.history_limit()));
+    if !used.is_null() {
+        *used = u;
+    }
+    if !limit.is_null() {
+        *limit = l;
+    }
+}
+
+/// Show console `index` (0-based). Returns 0, -EINVAL, or -ENODEV before
+/// console_init.
+#[no_mangle]
+pub unsafe extern "C" fn console_switch(index: u32) -> i32 {
+    let index = index as usize;
+    if index >= VT_COUNT {
+        return KernelError::Inval.neg();
+    }
+    if !consoles()[index].screen.initialized {
+        return KernelError::NoDevice.neg();
+    }
+    if index == ACTIVE {
+        return 0;
+    }
+    active().screen.visible = false;
+    ACTIVE = index;
+    let con = active();
+    con.screen.visible = true;
+    if con.screen.cells.is_some() {
+        con.screen.redraw();
+    } else {
+        // Nothing to redraw from: start the console over
+        con.vt.clear(&mut con.screen);
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn console_active() -> u32 {
+    ACTIVE as u32
+}
+
+/// Give console `index` to an owner (VT_OWNER_*). An owner holds at most
+/// one console, so any console it had before is released. Returns 0 or
+/// -EINVAL.
+#[no_mangle]
+pub unsafe extern "C" fn console_assign(index: u32, kind: u32, id: u32) -> i32 {
+    let index = index as usize;
+    let Some(owner) = VtOwner::from_raw(kind, id) else {
+        return KernelError::Inval.neg();
+    };
+    if index >= VT_COUNT {
+        return KernelError::Inval.neg();
+    }
+    for con in consoles().iter_mut() {
+        if owner != VtOwner::None && con.owner == owner {
+            con.owner = VtOwner::None;
+        }
+    }
+    consoles()[index].owner = owner;
+    OUTPUT = consoles().iter()
+        .position(|c| matches!(c.owner, VtOwner::Session(_)))
+        .unwrap_or(0);
+    0
+}
+
+/// Console held by an owner, -ENOENT if none, -EINVAL for a bad kind.
+#[no_mangle]
+pub unsafe extern "C" fn console_lookup(kind: u32, id: u32) -> i32 {
+    let Some(owner) = VtOwner::from_raw(kind, id) else {
+        return KernelError::Inval.neg();
+    };
+    match consoles().iter().position(|c| c.owner == owner) {
+        Some(i) if owner != VtOwner::None => i as i32,
+        _ => KernelError::NoEntry.neg(),
+    }
+}
+
+/// Owner of the active console, which receives keyboard input. Returns
+/// its VT_OWNER_* kind and stores the id.
+#[no_mangle]
+pub unsafe extern "C" fn console_input_owner(id: *mut u32) -> u32 {
+    let (kind, owner_id) = active().owner.raw();
+    if !id.is_null() {
+        *id = owner_id;
+    }
+    kind
+}
+
+/// Write bytes to console `index`.
+#[no_mangle]
+pub unsafe extern "C" fn console_write_to(index: u32, s: *const u8, len: u32) {
+    if s.is_null() || index as usize >= VT_COUNT {
+        return;
+    }
+    let bytes = core::slice::from_raw_parts(s, len as usize);
+    consoles()[index as usize].write(bytes);
+}
+
+/// Write bytes to the kernel log console, if one is assigned.
+#[no_mangle]
+pub unsafe extern "C" fn console_log(s: *const u8, len: u32) {
+    if let Some(i) = consoles().iter().position(|c| c.owner == VtOwner::Kernel) {
+        console_write_to(i as u32, s, len);
+    }
+}
+
+unsafe fn render_list(out: &mut TextBuf) -> core::fmt::Result {
+    for (i, con) in consoles().iter().enumerate() {
+        if !con.screen.initialized {
+            continue;
+        }
+        let mark = if i == ACTIVE { '*' } else { ' ' };
+        write!(out, "{}tty{}  {}x{}", mark, i + 1, con.vt.cols, con.vt.rows)?;
+        match con.screen.cells.as_ref() {
+            Some(c) => write!(out, "  history {}/{}", c.history(), c.history_limit())?,
+            None => write!(out, "  no history")?,
+        }
+        match con.owner {
+            VtOwner::None => writeln!(out)?,
+            VtOwner::Kernel => writeln!(out, "  kernel log")?,
+            VtOwner::Process(pid) => writeln!(out, "  pid {}", pid)?,
+            VtOwner::Session(id) => writeln!(out, "  session {}", id)?,
+        }
+    }
+    Ok(())
+}
+
+/// One line per virtual console into buf. Returns length.
+#[no_mangle]
+pub unsafe extern "C" fn console_render_list(buf: *mut u8, len: u32) -> u32 {
+    let mut out = match TextBuf::from_raw(buf, len as usize) {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = render_list(&mut out);
+    out.finish_cstr() as u32
 }
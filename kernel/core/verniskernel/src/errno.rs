@@ -0,0 +1,160 @@
+// errno.rs — Kernel error codes
+//
+// One error type for every Rust subsystem that C or user space can observe.
+// Internally functions return KResult<T>; at the extern "C" boundary the
+// error is folded into the uniform convention:
+//
+//     >= 0   success (0, or a count / pid / handle)
+//     <  0   -errno
+//
+// The numeric values follow Linux/POSIX and are part of the ABI: user space
+// sees them as syscall return values. include/kerrno.h carries the same
+// numbers for C — keep the two in sync and never renumber an entry.
+
+use core::ffi::c_char;
+use core::fmt;
+
+pub type KResult<T> = Result<T, KernelError>;
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelError {
+    Perm         = 1,   // EPERM: caller lacks the capability
+    NoEntry      = 2,   // ENOENT: no such file, module, rule or syscall slot
+    NoProcess    = 3,   // ESRCH: no such pid
+    Interrupted  = 4,   // EINTR
+    Io           = 5,   // EIO: device or backing-store failure
+    TooBig       = 7,   // E2BIG: argument list or payload too long
+    NoExec       = 8,   // ENOEXEC: bad image magic or version
+    BadFd        = 9,   // EBADF
+    NoChild      = 10,  // ECHILD
+    Again        = 11,  // EAGAIN: try again (queue full, not ready)
+    NoMem        = 12,  // ENOMEM
+    Access       = 13,  // EACCES: object exists but access is refused
+    Fault        = 14,  // EFAULT: bad user pointer
+    Busy         = 16,  // EBUSY: object in use
+    Exists       = 17,  // EEXIST
+    NoDevice     = 19,  // ENODEV
+    Inval        = 22,  // EINVAL
+    TooManyFiles = 24,  // EMFILE
+    NoSpace      = 28,  // ENOSPC
+    Range        = 34,  // ERANGE: value or buffer size out of range
+    NameTooLong  = 36,  // ENAMETOOLONG
+    NoSys        = 38,  // ENOSYS: syscall not implemented
+    NoMsg        = 42,  // ENOMSG: queue or channel is empty
+    TimedOut     = 110, // ETIMEDOUT: no answer in time
+    Canceled     = 125, // ECANCELED: operation abandoned before it completed
+}
+
+impl KernelError {
+    /// Positive errno value.
+    pub const fn errno(self) -> i32 {
+        self as i32
+    }
+
+    /// Negative errno, as returned across the C boundary.
+    pub const fn neg(self) -> i32 {
+        -(self as i32)
+    }
+
+    /// Negative errno widened for syscall returns.
+    pub const fn neg_isize(self) -> isize {
+        -(self as i32) as isize
+    }
+
+    /// Map an errno of either sign back to its variant.
+    pub fn from_errno(errno: i32) -> Option<Self> {
+        use KernelError::*;
+        const ALL: &[KernelError] = &[
+            Perm, NoEntry, NoProcess, Interrupted, Io, TooBig, NoExec, BadFd,
+            NoChild, Again, NoMem, Access, Fault, Busy, Exists, NoDevice, Inval,
+            TooManyFiles, NoSpace, Range, NameTooLong, NoSys, NoMsg, TimedOut,
+            Canceled,
+        ];
+        let errno = errno.checked_abs()?;
+        ALL.iter().copied().find(|e| e.errno() == errno)
+    }
+
+    /// Symbolic name ("EINVAL"), for logs and /proc output.
+    pub fn name(self) -> &'static str {
+        let c = self.name_cstr();
+        // Table entries are ASCII
+        unsafe { core::str::from_utf8_unchecked(&c[..c.len() - 1]) }
+    }
+
+    /// NUL-terminated symbolic name.
+    const fn name_cstr(self) -> &'static [u8] {
+        use KernelError::*;
+        match self {
+            Perm         => b"EPERM\0",
+            NoEntry      => b"ENOENT\0",
+            NoProcess    => b"ESRCH\0",
+            Interrupted  => b"EINTR\0",
+            Io           => b"EIO\0",
+            TooBig       => b"E2BIG\0",
+            NoExec       => b"ENOEXEC\0",
+            BadFd        => b"EBADF\0",
+            NoChild      => b"ECHILD\0",
+            Again        => b"EAGAIN\0",
+            NoMem        => b"ENOMEM\0",
+            Access       => b"EACCES\0",
+            Fault        => b"EFAULT\0",
+            Busy         => b"EBUSY\0",
+            Exists       => b"EEXIST\0",
+            NoDevice     => b"ENODEV\0",
+            Inval        => b"EINVAL\0",
+            TooManyFiles => b"EMFILE\0",
+            NoSpace      => b"ENOSPC\0",
+            Range        => b"ERANGE\0",
+            NameTooLong  => b"ENAMETOOLONG\0",
+            NoSys        => b"ENOSYS\0",
+            NoMsg        => b"ENOMSG\0",
+            TimedOut     => b"ETIMEDOUT\0",
+            Canceled     => b"ECANCELED\0",
+        }
+    }
+}
+
+impl fmt::Display for KernelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// =============================================================================
+// C boundary helpers
+// =============================================================================
+
+/// 0 on success, -errno on failure.
+pub fn to_c(r: KResult<()>) -> i32 {
+    match r {
+        Ok(()) => 0,
+        Err(e) => e.neg(),
+    }
+}
+
+/// Non-negative value on success, -errno on failure.
+pub fn to_c_i32(r: KResult<i32>) -> i32 {
+    match r {
+        Ok(v) => v,
+        Err(e) => e.neg(),
+    }
+}
+
+/// Syscall return: non-negative value on success, -errno on failure.
+pub fn to_c_isize(r: KResult<isize>) -> isize {
+    match r {
+        Ok(v) => v,
+        Err(e) => e.neg_isize(),
+    }
+}
+
+/// Name of a (possibly negated) errno value, for C log lines.
+/// Returns "E?" for values outside the table.
+#[no_mangle]
+pub extern "C" fn kernel_strerror(errno: i32) -> *const c_char {
+    match KernelError::from_errno(errno) {
+        Some(e) => e.name_cstr().as_ptr() as *const c_char,
+        None => c"E?".as_ptr(),
+    }
+}
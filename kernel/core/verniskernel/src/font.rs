@@ -0,0 +1,435 @@
+// font.rs — Text glyphs: UTF-8 decoding, loadable PSF2 fonts, glyph cache
+//
+// Every text path (framebuffer, console, compositor, windows) draws
+// Graphemes: a base character plus up to MAX_MARKS combining marks, which is
+// what Thai needs for its above/below vowels and tone marks. glyph() turns a
+// grapheme into an 8x16 bitmap by OR-ing the mark glyphs over the base glyph
+// (PSF fonts draw marks at their final position in the cell) and keeps the
+// result in a small direct-mapped cache.
+//
+// Glyphs come from the active PSF2 font, loaded from VernisFS with
+// font_load(), through its Unicode table; characters it lacks, and
+// everything while no font is loaded, fall back to the built-in VGA font
+// (ASCII only), then to '?'. The cell grid is fixed, so only 8x16 fonts are
+// accepted.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_char;
+use core::fmt::Write;
+use core::ptr::addr_of_mut;
+
+use crate::errno::{KResult, KernelError};
+use crate::font8x16::VGA_FONT_8X16;
+use crate::framebuffer::{FONT_HEIGHT, FONT_WIDTH};
+use crate::heap::{self, HeapTag};
+use crate::textbuf::TextBuf;
+
+extern "C" {
+    fn kfs_read_file(path: *const c_char, buf: *mut u8, max_len: usize) -> i32;
+}
+
+pub const PSF2_MAGIC: u32 = 0x864A_B572;
+/// PSF2 header flag: a Unicode table follows the glyphs.
+pub const PSF2_HAS_UNICODE_TABLE: u32 = 1;
+/// Largest font file font_load() reads.
+pub const PSF2_MAX_FILE: usize = 64 * 1024;
+const PSF2_HEADER_LEN: usize = 32;
+
+/// Combining marks kept per cell; more are dropped.
+pub const MAX_MARKS: usize = 2;
+
+const GLYPH_CACHE_SLOTS: usize = 64;
+
+pub type GlyphBits = [u8; FONT_HEIGHT as usize];
+
+// =============================================================================
+// UTF-8
+// =============================================================================
+
+/// Incremental UTF-8 decoder for byte-at-a-time output paths. Malformed
+/// input (bad or missing continuation bytes, overlong forms, surrogates)
+/// decodes to U+FFFD.
+#[derive(Debug, Clone, Copy)]
+pub struct Utf8Decoder {
+    cp: u32,
+    /// Continuation bytes still expected.
+    need: u8,
+    /// Smallest code point the current sequence may encode.
+    min: u32,
+}
+
+impl Default for Utf8Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Utf8Decoder {
+    pub const fn new() -> Self {
+        Utf8Decoder { cp: 0, need: 0, min: 0 }
+    }
+
+    /// In the middle of a multi-byte sequence.
+    pub fn pending(&self) -> bool {
+        self.need != 0
+    }
+
+    /// Feed one byte. A byte that breaks off a sequence yields U+FFFD and is
+    /// then decoded itself, hence up to two characters.
+    pub fn push(&mut self, b: u8) -> [Option<char>; 2] {
+        if self.need != 0 {
+            if b & 0xC0 == 0x80 {
+                self.cp = (self.cp << 6) | (b & 0x3F) as u32;
+                self.need -= 1;
+                if self.need != 0 {
+                    return [None, None];
+                }
+                let c = if self.cp < self.min { None } else { char::from_u32(self.cp) };
+                return [Some(c.unwrap_or(char::REPLACEMENT_CHARACTER)), None];
+            }
+            self.need = 0;
+            return [Some(char::REPLACEMENT_CHARACTER), self.start(b)];
+        }
+        [self.start(b), None]
+    }
+
+    fn start(&mut self, b: u8) -> Option<char> {
+        let (need, bits, min) = match b {
+            0x00..=0x7F => return Some(b as char),
+            0xC2..=0xDF => (1, b & 0x1F, 0x80),
+            0xE0..=0xEF => (2, b & 0x0F, 0x800),
+            0xF0..=0xF4 => (3, b & 0x07, 0x10000),
+            _ => return Some(char::REPLACEMENT_CHARACTER),
+        };
+        self.need = need;
+        self.cp = bits as u32;
+        self.min = min;
+        None
+    }
+}
+
+/// The longest valid UTF-8 prefix of raw, up to the first NUL. For fixed
+/// byte buffers such as window titles and widget labels.
+pub fn text(raw: &[u8]) -> &str {
+    let raw = &raw[..raw.iter().position(|&b| b == 0).unwrap_or(raw.len())];
+    match core::str::from_utf8(raw) {
+        Ok(s) => s,
+        // valid_up_to() marks a char boundary
+        Err(e) => unsafe { core::str::from_utf8_unchecked(&raw[..e.valid_up_to()]) },
+    }
+}
+
+/// Nonspacing marks drawn over the preceding character: the general
+/// combining blocks and Thai.
+pub fn is_combining(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF |
+        0x20D0..=0x20FF | 0xFE20..=0xFE2F |
+        0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E)
+}
+
+// =============================================================================
+// Graphemes
+// =============================================================================
+
+/// What one character cell shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grapheme {
+    pub base: char,
+    /// '\0' for unused slots.
+    pub marks: [char; MAX_MARKS],
+}
+
+impl Grapheme {
+    pub const BLANK: Grapheme = Grapheme::new(' ');
+
+    pub const fn new(base: char) -> Self {
+        Grapheme { base, marks: ['\0'; MAX_MARKS] }
+    }
+
+    /// Add a combining mark; dropped if the cell is full.
+    pub fn with_mark(mut self, mark: char) -> Self {
+        if let Some(slot) = self.marks.iter_mut().find(|m| **m == '\0') {
+            *slot = mark;
+        }
+        self
+    }
+}
+
+/// Split text into cells: each non-combining character starts one, marks
+/// join the cell before them (or a blank one at the start).
+pub struct Graphemes<'a> {
+    chars: core::iter::Peekable<core::str::Chars<'a>>,
+}
+
+pub fn graphemes(s: &str) -> Graphemes<'_> {
+    Graphemes { chars: s.chars().peekable() }
+}
+
+impl Iterator for Graphemes<'_> {
+    type Item = Grapheme;
+
+    fn next(&mut self) -> Option<Grapheme> {
+        let first = self.chars.next()?;
+        let mut g = if is_combining(first) {
+            Grapheme::BLANK.with_mark(first)
+        } else {
+            Grapheme::new(first)
+        };
+        while let Some(&c) = self.chars.peek() {
+            if !is_combining(c) {
+                break;
+            }
+            g = g.with_mark(c);
+            self.chars.next();
+        }
+        Some(g)
+    }
+}
+
+/// Width of text in cells.
+pub fn cells(s: &str) -> u32 {
+    graphemes(s).count() as u32
+}
+
+// =============================================================================
+// Fonts
+// =============================================================================
+
+/// A loaded PSF2 font (8x16 glyphs).
+pub struct Font {
+    pub name: String,
+    glyphs: Vec<u8>,
+    count: u32,
+    /// Unicode → glyph index; identity for the first 256 glyphs if the file
+    /// has no Unicode table.
+    map: BTreeMap<u32, u32>,
+}
+
+fn le32(raw: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([raw[at], raw[at + 1], raw[at + 2], raw[at + 3]])
+}
+
+impl Font {
+    /// Parse a PSF2 file. EINVAL if it is not PSF2, not 8x16 or truncated.
+    pub fn parse_psf2(name: &str, raw: &[u8]) -> KResult<Font> {
+        if raw.len() < PSF2_HEADER_LEN || le32(raw, 0) != PSF2_MAGIC {
+            return Err(KernelError::Inval);
+        }
+        let header_len = le32(raw, 8) as usize;
+        let flags = le32(raw, 12);
+        let count = le32(raw, 16);
+        let per_glyph = le32(raw, 20) as usize;
+        let (height, width) = (le32(raw, 24), le32(raw, 28));
+        if width != FONT_WIDTH || height != FONT_HEIGHT || per_glyph != FONT_HEIGHT as usize {
+            return Err(KernelError::Inval);
+        }
+        let glyph_end = (count as usize)
+            .checked_mul(per_glyph)
+            .and_then(|n| n.checked_add(header_len))
+            .filter(|&end| header_len >= PSF2_HEADER_LEN && end <= raw.len())
+            .ok_or(KernelError::Inval)?;
+
+        let mut glyphs = Vec::new();
+        glyphs.try_reserve_exact(glyph_end - header_len).map_err(|_| KernelError::NoMem)?;
+        glyphs.extend_from_slice(&raw[header_len..glyph_end]);
+
+        let mut map = BTreeMap::new();
+        if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+            // Per glyph: UTF-8 characters, optionally 0xFE-introduced
+            // sequences (skipped: one cell shows one character), then 0xFF
+            let mut index = 0u32;
+            let mut in_seq = false;
+            let mut dec = Utf8Decoder::new();
+            for &b in &raw[glyph_end..] {
+                if index >= count {
+                    break;
+                }
+                match b {
+                    0xFF => {
+                        index += 1;
+                        in_seq = false;
+                        dec = Utf8Decoder::new();
+                    }
+                    0xFE => in_seq = true,
+                    _ => {
+                        for c in dec.push(b).into_iter().flatten() {
+                            if !in_seq && c != char::REPLACEMENT_CHARACTER {
+                                map.entry(c as u32).or_insert(index);
+                            }
+                        }
+                    }
+                }
+            }
+        } else {
+            for i in 0..count.min(256) {
+                map.insert(i, i);
+            }
+        }
+        Ok(Font { name: String::from(name), glyphs, count, map })
+    }
+
+    fn glyph(&self, c: char) -> Option<GlyphBits> {
+        let index = *self.map.get(&(c as u32))? as usize;
+        let at = index * FONT_HEIGHT as usize;
+        let mut bits = [0u8; FONT_HEIGHT as usize];
+        bits.copy_from_slice(self.glyphs.get(at..at + FONT_HEIGHT as usize)?);
+        Some(bits)
+    }
+}
+
+fn builtin_glyph(c: char) -> Option<GlyphBits> {
+    if !c.is_ascii() {
+        return None;
+    }
+    let at = c as usize * FONT_HEIGHT as usize;
+    let mut bits = [0u8; FONT_HEIGHT as usize];
+    bits.copy_from_slice(VGA_FONT_8X16.get(at..at + FONT_HEIGHT as usize)?);
+    Some(bits)
+}
+
+#[derive(Clone, Copy)]
+struct CacheSlot {
+    key: Grapheme,
+    bits: GlyphBits,
+}
+
+struct Glyphs {
+    font: Option<Font>,
+    cache: [Option<CacheSlot>; GLYPH_CACHE_SLOTS],
+    hits: u32,
+    misses: u32,
+}
+
+static mut GLYPHS: Glyphs = Glyphs {
+    font: None,
+    cache: [None; GLYPH_CACHE_SLOTS],
+    hits: 0,
+    misses: 0,
+};
+
+fn glyphs() -> &'static mut Glyphs {
+    unsafe { &mut *addr_of_mut!(GLYPHS) }
+}
+
+impl Glyphs {
+    fn lookup(&self, c: char) -> Option<GlyphBits> {
+        self.font.as_ref().and_then(|f| f.glyph(c)).or_else(|| builtin_glyph(c))
+    }
+
+    fn compose(&self, g: &Grapheme) -> GlyphBits {
+        let mut bits = self.lookup(g.base)
+            .or_else(|| self.lookup('?'))
+            .unwrap_or([0; FONT_HEIGHT as usize]);
+        for &m in g.marks.iter().filter(|&&m| m != '\0') {
+            if let Some(mark) = self.lookup(m) {
+                for (row, mbits) in bits.iter_mut().zip(mark.iter()) {
+                    *row |= mbits;
+                }
+            }
+        }
+        bits
+    }
+}
+
+/// The bitmap for one cell: a row per byte, MSB leftmost.
+pub fn glyph(g: &Grapheme) -> GlyphBits {
+    let gl = glyphs();
+    // Plain ASCII with the built-in font needs no cache
+    if gl.font.is_none() && g.marks[0] == '\0' && g.base.is_ascii() {
+        if let Some(bits) = builtin_glyph(g.base) {
+            return bits;
+        }
+    }
+    let hash = (g.base as u32)
+        ^ (g.marks[0] as u32).rotate_left(7)
+        ^ (g.marks[1] as u32).rotate_left(14);
+    let slot = (hash.wrapping_mul(0x9E37_79B9) >> 26) as usize % GLYPH_CACHE_SLOTS;
+    if let Some(entry) = gl.cache[slot] {
+        if entry.key == *g {
+            gl.hits = gl.hits.wrapping_add(1);
+            return entry.bits;
+        }
+    }
+    gl.misses = gl.misses.wrapping_add(1);
+    let bits = gl.compose(g);
+    gl.cache[slot] = Some(CacheSlot { key: *g, bits });
+    bits
+}
+
+/// Make font the active one (None: built-in) and drop cached glyphs.
+pub fn set_font(font: Option<Font>) {
+    let gl = glyphs();
+    gl.font = font;
+    gl.cache = [None; GLYPH_CACHE_SLOTS];
+}
+
+/// Read a PSF2 font from VernisFS and make it active.
+pub fn load(path: &str) -> KResult<()> {
+    let _tag = heap::tag_scope(HeapTag::Gui);
+    let mut cpath = Vec::new();
+    cpath.try_reserve_exact(path.len() + 1).map_err(|_| KernelError::NoMem)?;
+    cpath.extend_from_slice(path.as_bytes());
+    cpath.push(0);
+    let mut raw = Vec::new();
+    raw.try_reserve_exact(PSF2_MAX_FILE).map_err(|_| KernelError::NoMem)?;
+    raw.resize(PSF2_MAX_FILE, 0);
+    let n = unsafe { kfs_read_file(cpath.as_ptr() as *const c_char, raw.as_mut_ptr(), raw.len()) };
+    if n < 0 {
+        return Err(KernelError::NoEntry);
+    }
+    let font = Font::parse_psf2(path, &raw[..n as usize])?;
+    set_font(Some(font));
+    Ok(())
+}
+
+fn render_info<W: Write>(out: &mut W) -> core::fmt::Result {
+    let gl = glyphs();
+    match &gl.font {
+        Some(f) => writeln!(out, "font: {} ({} glyphs, {} mapped, 8x16)", f.name, f.count, f.map.len())?,
+        None => writeln!(out, "font: built-in VGA 8x16 (ASCII)")?,
+    }
+    let used = gl.cache.iter().filter(|s| s.is_some()).count();
+    writeln!(out, "glyph cache: {}/{} slots, {} hits, {} misses",
+             used, GLYPH_CACHE_SLOTS, gl.hits, gl.misses)
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Load a PSF2 font from VernisFS. 0, -ENOENT (unreadable), -EINVAL (not an
+/// 8x16 PSF2 font) or -ENOMEM.
+#[no_mangle]
+pub unsafe extern "C" fn font_load(path: *const c_char) -> i32 {
+    if path.is_null() {
+        return KernelError::Inval.neg();
+    }
+    let path = match core::ffi::CStr::from_ptr(path).to_str() {
+        Ok(p) => p,
+        Err(_) => return KernelError::Inval.neg(),
+    };
+    match load(path) {
+        Ok(()) => 0,
+        Err(e) => e.neg(),
+    }
+}
+
+/// Go back to the built-in font.
+#[no_mangle]
+pub extern "C" fn font_reset() {
+    set_font(None);
+}
+
+/// Describe the active font and the glyph cache. Returns length.
+#[no_mangle]
+pub unsafe extern "C" fn font_render_info(buf: *mut u8, len: u32) -> u32 {
+    let mut out = match TextBuf::from_raw(buf, len as usize) {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = render_info(&mut out);
+    out.finish_cstr() as u32
+}
@@ -1,9 +1,14 @@
 // Framebuffer driver — low-level pixel operations + bitmap font rendering
-// Uses VBE linear framebuffer (24bpp or 32bpp)
+// Uses VBE linear framebuffer; the pixel layout (15/16/24/32bpp, any channel
+// positions) is described by a PixelFormat (pixfmt.rs). Colors passed in
+// are 0xRRGGBB and are packed on write.
 
 use core::ptr;
 
-use crate::font8x16::VGA_FONT_8X16;
+use crate::errno::KernelError;
+use crate::font::{self, Grapheme};
+use crate::pixfmt::{Channel, PixelFormat};
+use crate::raster::Surface;
 
 pub const FONT_WIDTH: u32 = 8;
 pub const FONT_HEIGHT: u32 = 16;
@@ -13,32 +18,42 @@ struct FrameBuffer {
     width: u32,
     height: u32,
     pitch: u32, // bytes per scanline
-    bpp: u32,   // bits per pixel (24 or 32)
+    format: PixelFormat,
     initialized: bool,
 }
 
+/// Channel layout from the VBE mode info block, in its field order
+/// (RedMaskSize, RedFieldPosition, ...). All zero when unknown.
+#[repr(C)]
+pub struct FbChannels {
+    pub red_size: u8,
+    pub red_pos: u8,
+    pub green_size: u8,
+    pub green_pos: u8,
+    pub blue_size: u8,
+    pub blue_pos: u8,
+}
+
 static mut FB: FrameBuffer = FrameBuffer {
     addr: core::ptr::null_mut(),
     width: 0,
     height: 0,
     pitch: 0,
-    bpp: 0,
+    format: PixelFormat::XRGB8888,
     initialized: false,
 };
 
-/// Write a pixel at byte offset. Handles 24bpp and 32bpp.
+/// Write a pixel at byte offset.
 #[inline(always)]
 unsafe fn write_pixel(base: *mut u8, color: u32) {
-    if FB.bpp == 32 {
-        ptr::write_volatile(base as *mut u32, color);
-    } else {
-        // 24bpp: write B, G, R bytes
-        ptr::write_volatile(base, color as u8);
-        ptr::write_volatile(base.add(1), (color >> 8) as u8);
-        ptr::write_volatile(base.add(2), (color >> 16) as u8);
-    }
+    let format = FB.format;
+    format.write(base, color);
 }
 
+/// Set up the framebuffer. `channels` may be null, or all zero, for modes
+/// that report no layout; the usual one for `bpp` is assumed. Returns 0, or
+/// -EINVAL if the pixel format is not one we can draw (the framebuffer then
+/// stays off).
 #[no_mangle]
 pub unsafe extern "C" fn fb_init(
     addr: usize,
@@ -46,13 +61,41 @@ pub unsafe extern "C" fn fb_init(
     height: u32,
     pitch: u32,
     bpp: u32,
-) {
+    channels: *const FbChannels,
+) -> i32 {
+    let format = match channels.as_ref() {
+        Some(c) if c.red_size != 0 || c.green_size != 0 || c.blue_size != 0 => PixelFormat::new(
+            bpp,
+            Channel { size: c.red_size, pos: c.red_pos },
+            Channel { size: c.green_size, pos: c.green_pos },
+            Channel { size: c.blue_size, pos: c.blue_pos },
+        ),
+        _ => PixelFormat::from_bpp(bpp),
+    };
+    let Some(format) = format else {
+        FB.initialized = false;
+        return KernelError::Inval.neg();
+    };
     FB.addr = addr as *mut u8;
     FB.width = width;
     FB.height = height;
     FB.pitch = pitch;
-    FB.bpp = bpp;
+    FB.format = format;
     FB.initialized = true;
+    0
+}
+
+/// The framebuffer's pixel format.
+pub unsafe fn fb_format() -> PixelFormat {
+    FB.format
+}
+
+/// A drawing surface over the framebuffer itself, if it is set up.
+pub unsafe fn fb_surface() -> Option<Surface> {
+    if !FB.initialized {
+        return None;
+    }
+    Some(Surface::new(FB.addr, FB.width, FB.height, FB.pitch, FB.format))
 }
 
 #[no_mangle]
@@ -60,7 +103,7 @@ pub unsafe extern "C" fn fb_put_pixel(x: u32, y: u32, color: u32) {
     if !FB.initialized || x >= FB.width || y >= FB.height {
         return;
     }
-    let offset = (y * FB.pitch + x * (FB.bpp / 8)) as usize;
+    let offset = (y * FB.pitch + x * FB.format.bytes) as usize;
     write_pixel(FB.addr.add(offset), color);
 }
 
@@ -75,15 +118,17 @@ pub unsafe extern "C" fn fb_fill_rect(
     if !FB.initialized {
         return;
     }
-    let bpp_bytes = FB.bpp / 8;
+    let format = FB.format;
+    let bpp_bytes = format.bytes;
     let x_end = if x + w > FB.width { FB.width } else { x + w };
     let y_end = if y + h > FB.height { FB.height } else { y + h };
+    if x >= x_end {
+        return;
+    }
 
     for row in y..y_end {
-        let row_base = FB.addr.add((row * FB.pitch) as usize);
-        for col in x..x_end {
-            write_pixel(row_base.add((col * bpp_bytes) as usize), color);
-        }
+        let row_base = FB.addr.add((row * FB.pitch + x * bpp_bytes) as usize);
+        format.fill(row_base, (x_end - x) as usize, color);
     }
 }
 
@@ -95,19 +140,24 @@ pub unsafe extern "C" fn fb_draw_char(
     fg: u32,
     bg: u32,
 ) {
+    fb_draw_glyph(x, y, &Grapheme::new(ch as char), fg, bg);
+}
+
+/// Draw one character cell (base character plus combining marks).
+pub unsafe fn fb_draw_glyph(x: u32, y: u32, g: &Grapheme, fg: u32, bg: u32) {
     if !FB.initialized {
         return;
     }
-    let glyph_offset = (ch as usize) * (FONT_HEIGHT as usize);
-    let font_len = VGA_FONT_8X16.len();
-    let bpp_bytes = FB.bpp / 8;
+    let bits = font::glyph(g);
+    let format = FB.format;
+    let bpp_bytes = format.bytes;
+    let (fg, bg) = (format.pack(fg), format.pack(bg));
 
     for row in 0..FONT_HEIGHT {
         if y + row >= FB.height {
             break;
         }
-        let idx = glyph_offset + row as usize;
-        let glyph_row = if idx < font_len { VGA_FONT_8X16[idx] } else { 0 };
+        let glyph_row = bits[row as usize];
         let row_base = FB.addr.add(((y + row) * FB.pitch) as usize);
 
         for col in 0..FONT_WIDTH {
@@ -120,11 +170,20 @@ pub unsafe extern "C" fn fb_draw_char(
             } else {
                 bg
             };
-            write_pixel(pixel_addr, color);
+            format.store(pixel_addr, color);
         }
     }
 }
 
+/// Draw UTF-8 text starting at (x, y), one cell per grapheme.
+pub unsafe fn fb_draw_str(x: u32, y: u32, s: &str, fg: u32, bg: u32) {
+    let mut cx = x;
+    for g in font::graphemes(s) {
+        fb_draw_glyph(cx, y, &g, fg, bg);
+        cx += FONT_WIDTH;
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn fb_scroll(lines: u32) {
     if !FB.initialized || lines == 0 {
@@ -145,6 +204,19 @@ pub unsafe extern "C" fn fb_scroll(lines: u32) {
     ptr::write_bytes(clear_start, 0, clear_bytes);
 }
 
+/// Move h pixel rows from src_y to dst_y (the ranges may overlap), clipped
+/// to the screen.
+#[no_mangle]
+pub unsafe extern "C" fn fb_move_rows(dst_y: u32, src_y: u32, h: u32) {
+    if !FB.initialized || dst_y >= FB.height || src_y >= FB.height {
+        return;
+    }
+    let h = h.min(FB.height - dst_y).min(FB.height - src_y);
+    let src = FB.addr.add((src_y * FB.pitch) as usize);
+    let dst = FB.addr.add((dst_y * FB.pitch) as usize);
+    ptr::copy(src, dst, (h * FB.pitch) as usize);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn fb_clear(color: u32) {
     if !FB.initialized {
@@ -154,13 +226,10 @@ pub unsafe extern "C" fn fb_clear(color: u32) {
         // Fast path: memset to 0
         ptr::write_bytes(FB.addr, 0, (FB.height * FB.pitch) as usize);
     } else {
-        let bpp_bytes = FB.bpp / 8;
+        let format = FB.format;
         for row in 0..FB.height {
             let row_base = FB.addr.add((row * FB.pitch) as usize);
-            for col in 0..FB.width {
-                let pixel = row_base.add((col * bpp_bytes) as usize) as *mut u32;
-                ptr::write_volatile(pixel, color);
-            }
+            format.fill(row_base, FB.width as usize, color);
         }
     }
 }
@@ -187,11 +256,12 @@ pub unsafe extern "C" fn fb_get_pitch() -> u32 {
 
 #[no_mangle]
 pub unsafe extern "C" fn fb_get_bpp() -> u32 {
-    FB.bpp
+    FB.format.bpp
 }
 
 /// Blit a rectangular region from src buffer to the framebuffer.
-/// src_pitch is the byte stride of the source buffer.
+/// src_pitch is the byte stride of the source buffer, whose pixels are in
+/// the framebuffer's format (see fb_blit_format otherwise).
 #[no_mangle]
 pub unsafe extern "C" fn fb_blit(
     src: *const u8,
@@ -204,7 +274,7 @@ pub unsafe extern "C" fn fb_blit(
     if !FB.initialized || src.is_null() {
         return;
     }
-    let bpp_bytes = FB.bpp / 8;
+    let bpp_bytes = FB.format.bytes;
     let x_end = if x + w > FB.width { FB.width } else { x + w };
     let y_end = if y + h > FB.height { FB.height } else { y + h };
     let copy_w = x_end.saturating_sub(x);
@@ -220,28 +290,39 @@ pub unsafe extern "C" fn fb_blit(
     }
 }
 
+/// Blit a rectangle whose pixels are in `src_format` to the framebuffer,
+/// converting each row.
+pub unsafe fn fb_blit_format(
+    src: *const u8,
+    src_format: &PixelFormat,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    src_pitch: u32,
+) {
+    if !FB.initialized || src.is_null() || x >= FB.width {
+        return;
+    }
+    let format = FB.format;
+    let copy_w = w.min(FB.width - x);
+    let y_end = if y + h > FB.height { FB.height } else { y + h };
+    for row in y..y_end {
+        let src_row = src.add(((row - y) * src_pitch) as usize);
+        let dst_row = FB.addr.add((row * FB.pitch + x * format.bytes) as usize);
+        crate::pixfmt::convert_row(dst_row, &format, src_row, src_format, copy_w as usize);
+    }
+}
+
 /// Write pixel into an arbitrary buffer (not the framebuffer).
-/// Used by compositor/window drawing. Handles 24bpp and 32bpp.
+/// Used by compositor/window drawing.
 #[inline(always)]
-pub unsafe fn write_pixel_buf(base: *mut u8, color: u32, bpp: u32) {
-    if bpp == 32 {
-        ptr::write(base as *mut u32, color);
-    } else {
-        ptr::write(base, color as u8);
-        ptr::write(base.add(1), (color >> 8) as u8);
-        ptr::write(base.add(2), (color >> 16) as u8);
-    }
+pub unsafe fn write_pixel_buf(base: *mut u8, color: u32, format: &PixelFormat) {
+    format.write(base, color);
 }
 
 /// Read pixel from an arbitrary buffer. Returns 0xRRGGBB.
 #[inline(always)]
-pub unsafe fn read_pixel_buf(base: *const u8, bpp: u32) -> u32 {
-    if bpp == 32 {
-        ptr::read(base as *const u32)
-    } else {
-        let b = ptr::read(base) as u32;
-        let g = ptr::read(base.add(1)) as u32;
-        let r = ptr::read(base.add(2)) as u32;
-        b | (g << 8) | (r << 16)
-    }
+pub unsafe fn read_pixel_buf(base: *const u8, format: &PixelFormat) -> u32 {
+    format.read(base)
 }
@@ -4,8 +4,10 @@
 use core::ptr;
 use alloc::vec::Vec;
 
-use crate::font8x16::VGA_FONT_8X16;
-use crate::framebuffer::{FONT_WIDTH, FONT_HEIGHT, write_pixel_buf};
+use crate::font::{self, Grapheme};
+use crate::framebuffer::{FONT_WIDTH, FONT_HEIGHT};
+use crate::pixfmt::PixelFormat;
+use crate::raster::Surface;
 
 pub struct DirtyRect {
     pub x: i32,
@@ -45,7 +47,7 @@ pub struct Compositor {
     pub width: u32,
     pub height: u32,
     pub pitch: u32,   // bytes per scanline in back buffer
-    pub bpp: u32,     // bits per pixel
+    pub format: PixelFormat,  // same as the framebuffer, so present is a copy
     pub initialized: bool,
     pub dirty: bool,
     pub dirty_rect: DirtyRect,  // Bounding box of changed region
@@ -56,7 +58,7 @@ static mut COMP: Compositor = Compositor {
     width: 0,
     height: 0,
     pitch: 0,
-    bpp: 0,
+    format: PixelFormat::XRGB8888,
     initialized: false,
     dirty: false,
     dirty_rect: DirtyRect { x: 0, y: 0, w: 0, h: 0 },
@@ -66,9 +68,8 @@ pub unsafe fn compositor_get() -> &'static mut Compositor {
     &mut COMP
 }
 
-pub unsafe fn compositor_init(width: u32, height: u32, bpp: u32) {
-    let bpp_bytes = bpp / 8;
-    let pitch = width * bpp_bytes;
+pub unsafe fn compositor_init(width: u32, height: u32, format: PixelFormat) {
+    let pitch = width * format.bytes;
     let size = (pitch * height) as usize;
 
     COMP.back_buffer = Vec::with_capacity(size);
@@ -76,7 +77,7 @@ pub unsafe fn compositor_init(width: u32, height: u32, bpp: u32) {
     COMP.width = width;
     COMP.height = height;
     COMP.pitch = pitch;
-    COMP.bpp = bpp;
+    COMP.format = format;
     COMP.initialized = true;
     COMP.dirty = true;
     COMP.dirty_rect = DirtyRect::new(0, 0, width, height);  // Full screen initially
@@ -101,6 +102,23 @@ pub unsafe fn compositor_mark_dirty_rect(x: i32, y: i32, w: u32, h: u32) {
     }
 }
 
+/// A drawing surface over the back buffer. Pass it to
+/// compositor_mark_surface_dirty when done so the drawing gets presented.
+pub unsafe fn compositor_surface() -> Option<Surface> {
+    let comp = &mut *ptr::addr_of_mut!(COMP);
+    if !comp.initialized {
+        return None;
+    }
+    Some(Surface::new(comp.back_buffer.as_mut_ptr(), comp.width, comp.height, comp.pitch, comp.format))
+}
+
+/// Mark everything drawn through a compositor_surface dirty.
+pub unsafe fn compositor_mark_surface_dirty(s: &Surface) {
+    if let Some((x, y, w, h)) = s.damage() {
+        compositor_mark_dirty_rect(x, y, w, h);
+    }
+}
+
 pub unsafe fn compositor_is_dirty() -> bool {
     COMP.dirty
 }
@@ -121,37 +139,10 @@ pub unsafe fn compositor_clear(color: u32) {
     let buf = COMP.back_buffer.as_mut_ptr();
     if color == 0 {
         ptr::write_bytes(buf, 0, total);
-    } else if COMP.bpp == 24 {
-        // Fill 24bpp: write 3 bytes per pixel, but optimize with a repeating pattern
-        let b0 = color as u8;
-        let b1 = (color >> 8) as u8;
-        let b2 = (color >> 16) as u8;
-        // Build a 12-byte repeating pattern (LCM of 3 and 4)
-        let pattern: [u8; 12] = [b0,b1,b2, b0,b1,b2, b0,b1,b2, b0,b1,b2];
-        let row_bytes = (COMP.width * 3) as usize;
-        for row in 0..COMP.height {
-            let row_base = buf.add((row * COMP.pitch) as usize);
-            // Fill 12 bytes at a time
-            let mut off = 0usize;
-            while off + 12 <= row_bytes {
-                ptr::copy_nonoverlapping(pattern.as_ptr(), row_base.add(off), 12);
-                off += 12;
-            }
-            // Remainder
-            while off + 3 <= row_bytes {
-                *row_base.add(off) = b0;
-                *row_base.add(off + 1) = b1;
-                *row_base.add(off + 2) = b2;
-                off += 3;
-            }
-        }
     } else {
-        // 32bpp: fill as u32
-        let buf32 = buf as *mut u32;
-        let count = total / 4;
-        for i in 0..count {
-            ptr::write(buf32.add(i), color);
-        }
+        // The back buffer is tightly packed: one run covers it
+        let format = COMP.format;
+        format.fill(buf, (COMP.width * COMP.height) as usize, color);
     }
 }
 
@@ -161,7 +152,8 @@ pub unsafe fn compositor_fill_rect(x: i32, y: i32, w: u32, h: u32, color: u32) {
     }
     COMP.dirty = true;
     compositor_mark_dirty_rect(x, y, w, h);  // Track this rect as changed
-    let bpp_bytes = COMP.bpp / 8;
+    let format = COMP.format;
+    let bpp_bytes = format.bytes;
     let buf = COMP.back_buffer.as_mut_ptr();
 
     let x0 = if x < 0 { 0 } else { x as u32 };
@@ -184,49 +176,31 @@ pub unsafe fn compositor_fill_rect(x: i32, y: i32, w: u32, h: u32, color: u32) {
     }
 
     let cols = x_end - x0;
+    let row_bytes = (cols * bpp_bytes) as usize;
 
     if color == 0 {
         // Fast memset zero
-        let row_bytes = (cols * bpp_bytes) as usize;
         for row in y0..y_end {
             let dst = buf.add((row * COMP.pitch + x0 * bpp_bytes) as usize);
             ptr::write_bytes(dst, 0, row_bytes);
         }
-    } else if bpp_bytes == 3 {
-        // 24bpp: build one template row, memcpy to the rest
-        let row0_base = buf.add((y0 * COMP.pitch + x0 * 3) as usize);
-        let b0 = color as u8;
-        let b1 = (color >> 8) as u8;
-        let b2 = (color >> 16) as u8;
-        // Fill first row pixel by pixel
-        for col in 0..cols {
-            let off = (col * 3) as usize;
-            *row0_base.add(off) = b0;
-            *row0_base.add(off + 1) = b1;
-            *row0_base.add(off + 2) = b2;
-        }
-        // Copy first row to remaining rows
-        let row_bytes = (cols * 3) as usize;
-        for row in (y0 + 1)..y_end {
-            let dst = buf.add((row * COMP.pitch + x0 * 3) as usize);
-            ptr::copy_nonoverlapping(row0_base, dst, row_bytes);
-        }
     } else {
-        // 32bpp: fill as u32, first row then memcpy
-        let row0_base = buf.add((y0 * COMP.pitch + x0 * 4) as usize) as *mut u32;
-        for col in 0..cols {
-            ptr::write(row0_base.add(col as usize), color);
-        }
-        let row_bytes = (cols * 4) as usize;
-        let row0_src = row0_base as *const u8;
+        // Fill the first row, memcpy to the rest
+        let row0_base = buf.add((y0 * COMP.pitch + x0 * bpp_bytes) as usize);
+        format.fill(row0_base, cols as usize, color);
         for row in (y0 + 1)..y_end {
-            let dst = buf.add((row * COMP.pitch + x0 * 4) as usize);
-            ptr::copy_nonoverlapping(row0_src, dst, row_bytes);
+            let dst = buf.add((row * COMP.pitch + x0 * bpp_bytes) as usize);
+            ptr::copy_nonoverlapping(row0_base, dst, row_bytes);
         }
     }
 }
 
 pub unsafe fn compositor_draw_char(x: i32, y: i32, ch: u8, fg: u32, bg: u32) {
+    compositor_draw_glyph(x, y, &Grapheme::new(ch as char), fg, bg);
+}
+
+/// Draw one character cell (base character plus combining marks).
+pub unsafe fn compositor_draw_glyph(x: i32, y: i32, g: &Grapheme, fg: u32, bg: u32) {
     if !COMP.initialized || x >= COMP.width as i32 || y >= COMP.height as i32 {
         return;
     }
@@ -234,10 +208,11 @@ pub unsafe fn compositor_draw_char(x: i32, y: i32, ch: u8, fg: u32, bg: u32) {
     // Mark the 8x16 character region as dirty
     compositor_mark_dirty_rect(x, y, FONT_WIDTH, FONT_HEIGHT);
     
-    let bpp_bytes = COMP.bpp / 8;
+    let format = COMP.format;
+    let bpp_bytes = format.bytes;
     let buf = COMP.back_buffer.as_mut_ptr();
-    let glyph_offset = (ch as usize) * (FONT_HEIGHT as usize);
-    let font_len = VGA_FONT_8X16.len();
+    let bits = font::glyph(g);
+    let (fg, bg) = (format.pack(fg), format.pack(bg));
 
     for row in 0..FONT_HEIGHT {
         let py = y + row as i32;
@@ -247,8 +222,7 @@ pub unsafe fn compositor_draw_char(x: i32, y: i32, ch: u8, fg: u32, bg: u32) {
         if py >= COMP.height as i32 {
             break;
         }
-        let idx = glyph_offset + row as usize;
-        let glyph_row = if idx < font_len { VGA_FONT_8X16[idx] } else { 0 };
+        let glyph_row = bits[row as usize];
         let row_base = buf.add((py as u32 * COMP.pitch) as usize);
 
         for col in 0..FONT_WIDTH {
@@ -264,22 +238,16 @@ pub unsafe fn compositor_draw_char(x: i32, y: i32, ch: u8, fg: u32, bg: u32) {
             } else {
                 bg
             };
-            write_pixel_buf(
-                row_base.add((px as u32 * bpp_bytes) as usize),
-                color,
-                COMP.bpp,
-            );
+            format.store(row_base.add((px as u32 * bpp_bytes) as usize), color);
         }
     }
 }
 
-pub unsafe fn compositor_draw_string(x: i32, y: i32, s: &[u8], fg: u32, bg: u32) {
+/// Draw UTF-8 text, one cell per grapheme.
+pub unsafe fn compositor_draw_string(x: i32, y: i32, s: &str, fg: u32, bg: u32) {
     let mut cx = x;
-    for &ch in s {
-        if ch == 0 {
-            break;
-        }
-        compositor_draw_char(cx, y, ch, fg, bg);
+    for g in font::graphemes(s) {
+        compositor_draw_glyph(cx, y, &g, fg, bg);
         cx += FONT_WIDTH as i32;
     }
 }
@@ -298,7 +266,7 @@ pub unsafe fn compositor_blit(
         return;
     }
     COMP.dirty = true;
-    let bpp_bytes = COMP.bpp / 8;
+    let bpp_bytes = COMP.format.bytes;
     let buf = COMP.back_buffer.as_mut_ptr();
 
     // Clip to screen bounds
@@ -340,7 +308,7 @@ pub unsafe fn compositor_present() {
     }
     
     // Use dirty rect, not full screen
-    let bpp_bytes = COMP.bpp / 8;
+    let bpp_bytes = COMP.format.bytes;
     let blit_x = if COMP.dirty_rect.x < 0 { 0 } else { COMP.dirty_rect.x as u32 };
     let blit_y = if COMP.dirty_rect.y < 0 { 0 } else { COMP.dirty_rect.y as u32 };
     
@@ -382,7 +350,7 @@ pub unsafe fn compositor_present_rect(x: i32, y: i32, w: u32, h: u32) {
         return;
     }
 
-    let bpp_bytes = COMP.bpp / 8;
+    let bpp_bytes = COMP.format.bytes;
 
     let x0 = if x < 0 { 0 } else { x as u32 };
     let y0 = if y < 0 { 0 } else { y as u32 };
@@ -107,12 +107,12 @@ unsafe fn cursor_save_under(x: i32, y: i32) {
         return;
     };
 
-    let bpp_bytes = (comp.bpp / 8) as usize;
+    let bpp_bytes = comp.format.bytes as usize;
     let row_bytes = (cw as usize) * bpp_bytes;
     let buf = comp.back_buffer.as_ptr();
 
     for row in 0..(ch as usize) {
-        let src = buf.add(((cy as u32 + row as u32) * comp.pitch + (cx as u32) * (comp.bpp / 8)) as usize);
+        let src = buf.add(((cy as u32 + row as u32) * comp.pitch + (cx as u32) * comp.format.bytes) as usize);
         let dst = CURSOR_SAVED.as_mut_ptr().add(row * row_bytes);
         core::ptr::copy_nonoverlapping(src, dst, row_bytes);
     }
@@ -135,13 +135,13 @@ unsafe fn cursor_restore_under() {
         return;
     }
 
-    let bpp_bytes = (comp.bpp / 8) as usize;
+    let bpp_bytes = comp.format.bytes as usize;
     let row_bytes = (CURSOR_W_CLIP as usize) * bpp_bytes;
     let dst_buf = comp.back_buffer.as_mut_ptr();
 
     for row in 0..(CURSOR_H_CLIP as usize) {
         let src = CURSOR_SAVED.as_ptr().add(row * row_bytes);
-        let dst = dst_buf.add(((CURSOR_Y as u32 + row as u32) * comp.pitch + (CURSOR_X as u32) * (comp.bpp / 8)) as usize);
+        let dst = dst_buf.add(((CURSOR_Y as u32 + row as u32) * comp.pitch + (CURSOR_X as u32) * comp.format.bytes) as usize);
         core::ptr::copy_nonoverlapping(src, dst, row_bytes);
     }
 }
@@ -179,7 +179,7 @@ fn draw_cursor_shape(x: i32, y: i32) {
         return;
     }
 
-    let bpp_bytes = comp.bpp / 8;
+    let bpp_bytes = comp.format.bytes;
     let buf = comp.back_buffer.as_mut_ptr();
 
     for row in 0..CURSOR_H {
@@ -206,7 +206,7 @@ fn draw_cursor_shape(x: i32, y: i32) {
                 crate::framebuffer::write_pixel_buf(
                     row_base.add((px as u32 * bpp_bytes) as usize),
                     color,
-                    comp.bpp,
+                    &comp.format,
                 );
             }
         }
@@ -2,6 +2,7 @@
 
 use crate::gui::compositor;
 use crate::gui::window;
+use crate::font;
 use crate::framebuffer::FONT_WIDTH;
 
 const TASKBAR_HEIGHT: u32 = 32;
@@ -32,7 +33,7 @@ pub unsafe fn desktop_draw_taskbar() {
     compositor::compositor_fill_rect(0, bar_y, SCREEN_W, 1, 0x333355);
 
     // "VernisOS" label on the left
-    compositor::compositor_draw_string(8, bar_y + 8, b"VernisOS", 0x55AAFF, TASKBAR_BG_COLOR);
+    compositor::compositor_draw_string(8, bar_y + 8, "VernisOS", 0x55AAFF, TASKBAR_BG_COLOR);
 
     // Window entries
     let wm = window::wm_get();
@@ -51,14 +52,15 @@ pub unsafe fn desktop_draw_taskbar() {
             };
 
             // Draw button background
-            let btn_w = (w.title_len as u32 * FONT_WIDTH + 16).min(150);
+            let title = font::text(&w.title[..w.title_len]);
+            let btn_w = (font::cells(title) * FONT_WIDTH + 16).min(150);
             compositor::compositor_fill_rect(tx, bar_y + 4, btn_w, TASKBAR_HEIGHT - 8, bg);
 
             // Draw title
             compositor::compositor_draw_string(
                 tx + 4,
                 bar_y + 8,
-                &w.title[..w.title_len],
+                title,
                 TASKBAR_TEXT_COLOR,
                 bg,
             );
@@ -9,6 +9,7 @@ pub mod widgets;
 pub mod terminal;
 
 use crate::mouse;
+use crate::heap::{self, HeapTag};
 
 extern "C" {
     fn kernel_get_ticks() -> u32;
@@ -38,13 +39,13 @@ unsafe fn gui_frame_interval_ticks() -> u32 {
 /// Initialize all GUI subsystems.
 #[no_mangle]
 pub unsafe extern "C" fn gui_init(screen_w: u32, screen_h: u32) {
-    let bpp = crate::framebuffer::fb_get_bpp();
+    let format = crate::framebuffer::fb_format();
 
     // Init compositor (allocates back buffer)
-    compositor::compositor_init(screen_w, screen_h, bpp);
+    compositor::compositor_init(screen_w, screen_h, format);
 
     // Init window manager
-    window::wm_init(bpp);
+    window::wm_init(format);
 
     // Init desktop
     desktop::desktop_init(screen_w, screen_h);
@@ -58,9 +59,9 @@ pub unsafe extern "C" fn gui_init(screen_w: u32, screen_h: u32) {
 
     // Show welcome message in terminal
     terminal::terminal_set_color(10, 0);
-    terminal::terminal_write_string(b"VernisOS GUI Terminal\n");
+    terminal::terminal_write_string("VernisOS GUI Terminal\n");
     terminal::terminal_set_color(7, 0);
-    terminal::terminal_write_string(b"Type 'help' for available commands.\n\n");
+    terminal::terminal_write_string("Type 'help' for available commands.\n\n");
     terminal::terminal_show_prompt();
 
     PREV_BUTTONS = 0;
@@ -74,6 +75,8 @@ pub unsafe extern "C" fn gui_init(screen_w: u32, screen_h: u32) {
 /// Skips rendering when nothing has changed (idle optimization).
 #[no_mangle]
 pub unsafe extern "C" fn gui_main_loop_tick() {
+    let _tag = heap::tag_scope(HeapTag::Gui);
+
     // Allow C-side CLI background tasks (e.g. ps realtime in GUI terminal)
     // to update without blocking this render loop.
     cli_gui_tick();
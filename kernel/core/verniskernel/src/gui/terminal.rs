@@ -1,74 +1,116 @@
 // Terminal Widget — text-mode terminal emulator inside a GUI window
 
+use core::ptr::addr_of_mut;
+
 use crate::framebuffer::{FONT_WIDTH, FONT_HEIGHT};
 use crate::gui::window;
+use crate::font::Grapheme;
+use crate::vt::{Vt, VtColor, VtScreen};
 
 const TERM_COLS: usize = 80;
 const TERM_ROWS: usize = 40;
 const TERM_INPUT_MAX: usize = 256;
 
-// VGA 16-color palette → BGRA (used for terminal text colors)
-const VGA_PALETTE: [u32; 16] = [
-    0x000000, // 0  black
-    0x0000AA, // 1  blue
-    0x00AA00, // 2  green
-    0x00AAAA, // 3  cyan
-    0xAA0000, // 4  red
-    0xAA00AA, // 5  magenta
-    0xAA5500, // 6  brown
-    0xAAAAAA, // 7  light grey
-    0x555555, // 8  dark grey
-    0x5555FF, // 9  light blue
-    0x55FF55, // 10 light green
-    0x55FFFF, // 11 light cyan
-    0xFF5555, // 12 light red
-    0xFF55FF, // 13 light magenta
-    0xFFFF55, // 14 yellow
-    0xFFFFFF, // 15 white
-];
+pub struct TermGrid {
+    pub window_id: u32,
+    pub chars: [[Grapheme; TERM_COLS]; TERM_ROWS],
+    pub fg_colors: [[u32; TERM_COLS]; TERM_ROWS],
+    pub bg_colors: [[u32; TERM_COLS]; TERM_ROWS],
+    pub dirty_rows: [bool; TERM_ROWS],  // per-row dirty tracking (fine-grain)
+}
+
+impl VtScreen for TermGrid {
+    fn put(&mut self, row: u32, col: u32, g: Grapheme, fg: u32, bg: u32) {
+        let (r, c) = (row as usize, col as usize);
+        if r < TERM_ROWS && c < TERM_COLS {
+            self.chars[r][c] = g;
+            self.fg_colors[r][c] = fg;
+            self.bg_colors[r][c] = bg;
+            self.dirty_rows[r] = true;
+        }
+    }
+
+    fn clear(&mut self, row: u32, from: u32, to: u32, bg: u32) {
+        let r = row as usize;
+        if r >= TERM_ROWS {
+            return;
+        }
+        for c in (from as usize)..(to as usize).min(TERM_COLS) {
+            self.chars[r][c] = Grapheme::BLANK;
+            self.bg_colors[r][c] = bg;
+        }
+        self.dirty_rows[r] = true;
+    }
+
+    fn scroll(&mut self, top: u32, bottom: u32, lines: i32, bg: u32) {
+        let (top, bottom) = (top as usize, (bottom as usize).min(TERM_ROWS));
+        let n = (lines.unsigned_abs() as usize).min(bottom.saturating_sub(top));
+        if n == 0 {
+            return;
+        }
+        if lines > 0 {
+            for r in top..bottom - n {
+                self.chars[r] = self.chars[r + n];
+                self.fg_colors[r] = self.fg_colors[r + n];
+                self.bg_colors[r] = self.bg_colors[r + n];
+            }
+        } else {
+            for r in (top + n..bottom).rev() {
+                self.chars[r] = self.chars[r - n];
+                self.fg_colors[r] = self.fg_colors[r - n];
+                self.bg_colors[r] = self.bg_colors[r - n];
+            }
+        }
+        let blank = if lines > 0 { bottom - n..bottom } else { top..top + n };
+        for r in blank {
+            self.clear(r as u32, 0, TERM_COLS as u32, bg);
+        }
+        for r in top..bottom {
+            self.dirty_rows[r] = true;  // All rows in the region dirty after scroll
+        }
+    }
+
+    fn set_title(&mut self, title: &[u8]) {
+        unsafe { window::wm_set_title(self.window_id, title) };
+    }
+}
 
 pub struct Terminal {
     pub window_id: u32,
-    pub chars: [[u8; TERM_COLS]; TERM_ROWS],
-    pub fg_colors: [[u8; TERM_COLS]; TERM_ROWS],
-    pub bg_colors: [[u8; TERM_COLS]; TERM_ROWS],
-    pub cursor_row: usize,
-    pub cursor_col: usize,
-    pub fg: u8,
-    pub bg: u8,
+    pub grid: TermGrid,
+    /// Cursor, colors and escape-sequence state.
+    pub vt: Vt,
     pub input_line: [u8; TERM_INPUT_MAX],
     pub input_len: usize,
     pub input_cursor: usize,
     pub initialized: bool,
     pub prompt_shown: bool,
     pub dirty: bool,               // ANY row dirty = trigger render pass
-    pub dirty_rows: [bool; TERM_ROWS],  // per-row dirty tracking (fine-grain)
-    pub prev_cursor_row: usize,    // previous cursor position for partial redraw
-    pub prev_cursor_col: usize,
 }
 
+const TERM_FG: VtColor = VtColor::Vga(7);  // light grey
+const TERM_BG: VtColor = VtColor::Vga(0);  // black
+
 static mut TERM: Terminal = Terminal {
     window_id: 0,
-    chars: [[b' '; TERM_COLS]; TERM_ROWS],
-    fg_colors: [[7; TERM_COLS]; TERM_ROWS],
-    bg_colors: [[0; TERM_COLS]; TERM_ROWS],
-    cursor_row: 0,
-    cursor_col: 0,
-    fg: 7,
-    bg: 0,
+    grid: TermGrid {
+        window_id: 0,
+        chars: [[Grapheme::BLANK; TERM_COLS]; TERM_ROWS],
+        fg_colors: [[0xAAAAAA; TERM_COLS]; TERM_ROWS],
+        bg_colors: [[0x000000; TERM_COLS]; TERM_ROWS],
+        dirty_rows: [true; TERM_ROWS],  // All rows dirty initially
+    },
+    vt: Vt::new(TERM_FG, TERM_BG),
     input_line: [0; TERM_INPUT_MAX],
     input_len: 0,
     input_cursor: 0,
     initialized: false,
     prompt_shown: false,
     dirty: true,
-    dirty_rows: [true; TERM_ROWS],  // All rows dirty initially
-    prev_cursor_row: 0,
-    prev_cursor_col: 0,
 };
 
 pub unsafe fn terminal_get() -> &'static mut Terminal {
-    &mut TERM
+    &mut *addr_of_mut!(TERM)
 }
 
 pub unsafe fn terminal_create(x: i32, y: i32) -> u32 {
@@ -79,28 +121,19 @@ pub unsafe fn terminal_create(x: i32, y: i32) -> u32 {
     let win_h = content_h + window::TITLE_BAR_HEIGHT + window::BORDER_WIDTH;
 
     let id = window::wm_create_window(b"Terminal", x, y, win_w, win_h);
-    TERM.window_id = id;
-    TERM.cursor_row = 0;
-    TERM.cursor_col = 0;
-    TERM.fg = 7;  // light grey
-    TERM.bg = 0;  // black
-    TERM.input_len = 0;
-    TERM.input_cursor = 0;
-    TERM.initialized = true;
-    TERM.prompt_shown = false;
-    TERM.dirty = true;
-    TERM.dirty_rows = [true; TERM_ROWS];  // All rows dirty initially
-    TERM.prev_cursor_row = 0;
-    TERM.prev_cursor_col = 0;
+    let term = terminal_get();
+    term.window_id = id;
+    term.grid.window_id = id;
+    term.vt = Vt::new(TERM_FG, TERM_BG);
+    term.vt.resize(TERM_ROWS as u32, TERM_COLS as u32);
+    term.input_len = 0;
+    term.input_cursor = 0;
+    term.initialized = true;
+    term.prompt_shown = false;
+    term.dirty = true;
 
     // Clear terminal buffer
-    for r in 0..TERM_ROWS {
-        for c in 0..TERM_COLS {
-            TERM.chars[r][c] = b' ';
-            TERM.fg_colors[r][c] = 7;
-            TERM.bg_colors[r][c] = 0;
-        }
-    }
+    term.vt.clear(&mut term.grid);
 
     // Fill window content with black
     window::wm_window_fill(id, 0x000000);
@@ -108,147 +141,77 @@ pub unsafe fn terminal_create(x: i32, y: i32) -> u32 {
     id
 }
 
-unsafe fn terminal_scroll() {
-    // Move all rows up by 1
-    for r in 0..TERM_ROWS - 1 {
-        TERM.chars[r] = TERM.chars[r + 1];
-        TERM.fg_colors[r] = TERM.fg_colors[r + 1];
-        TERM.bg_colors[r] = TERM.bg_colors[r + 1];
-        TERM.dirty_rows[r] = true;  // All rows dirty after scroll
-    }
-    // Clear last row
-    let last = TERM_ROWS - 1;
-    for c in 0..TERM_COLS {
-        TERM.chars[last][c] = b' ';
-        TERM.fg_colors[last][c] = TERM.fg;
-        TERM.bg_colors[last][c] = TERM.bg;
-    }
-    TERM.dirty_rows[last] = true;
-}
-
 pub unsafe fn terminal_putchar(ch: u8) {
-    if !TERM.initialized {
+    let term = terminal_get();
+    if !term.initialized {
         return;
     }
-    TERM.dirty = true;
-    match ch {
-        b'\n' => {
-            TERM.dirty_rows[TERM.cursor_row] = true;
-            TERM.cursor_col = 0;
-            TERM.cursor_row += 1;
-            if TERM.cursor_row >= TERM_ROWS {
-                terminal_scroll();
-                TERM.cursor_row = TERM_ROWS - 1;
-            }
-            TERM.dirty_rows[TERM.cursor_row] = true;
-        }
-        b'\r' => {
-            TERM.dirty_rows[TERM.cursor_row] = true;
-            TERM.cursor_col = 0;
-        }
-        8 | 127 => {
-            // Backspace
-            TERM.dirty_rows[TERM.cursor_row] = true;
-            if TERM.cursor_col > 0 {
-                TERM.cursor_col -= 1;
-                TERM.chars[TERM.cursor_row][TERM.cursor_col] = b' ';
-            }
-        }
-        _ => {
-            if TERM.cursor_col >= TERM_COLS {
-                TERM.dirty_rows[TERM.cursor_row] = true;
-                TERM.cursor_col = 0;
-                TERM.cursor_row += 1;
-                if TERM.cursor_row >= TERM_ROWS {
-                    terminal_scroll();
-                    TERM.cursor_row = TERM_ROWS - 1;
-                }
-            }
-            TERM.chars[TERM.cursor_row][TERM.cursor_col] = ch;
-            TERM.fg_colors[TERM.cursor_row][TERM.cursor_col] = TERM.fg;
-            TERM.bg_colors[TERM.cursor_row][TERM.cursor_col] = TERM.bg;
-            TERM.dirty_rows[TERM.cursor_row] = true;
-            TERM.cursor_col += 1;
-        }
-    }
+    term.dirty = true;
+    // DEL from the keyboard path is a backspace
+    let ch = if ch == 127 { 8 } else { ch };
+    // The cursor block is drawn over its row: redraw the row it leaves
+    let old_row = term.vt.row as usize;
+    term.vt.feed(&mut term.grid, ch);
+    term.grid.dirty_rows[old_row] = true;
+    term.grid.dirty_rows[term.vt.row as usize] = true;
 }
 
-pub unsafe fn terminal_write_string(s: &[u8]) {
-    for &ch in s {
-        if ch == 0 {
-            break;
-        }
+pub unsafe fn terminal_write_string(s: &str) {
+    for &ch in s.as_bytes() {
         terminal_putchar(ch);
     }
 }
 
 pub unsafe fn terminal_set_color(fg: u8, bg: u8) {
-    TERM.fg = fg & 0x0F;
-    TERM.bg = bg & 0x0F;
+    terminal_get().vt.set_colors(VtColor::Vga(fg & 0x0F), VtColor::Vga(bg & 0x0F));
 }
 
 /// Render the terminal buffer into the window's content buffer.
 /// Only re-renders when content has changed.
 pub unsafe fn terminal_render() {
-    if !TERM.initialized || !TERM.dirty {
+    let term = terminal_get();
+    if !term.initialized || !term.dirty {
         return;
     }
-    
+
     // Check if any row is dirty
-    let mut any_dirty = false;
-    for row in 0..TERM_ROWS {
-        if TERM.dirty_rows[row] {
-            any_dirty = true;
-            break;
-        }
-    }
-    
-    if !any_dirty {
-        TERM.dirty = false;
+    if !term.grid.dirty_rows.iter().any(|&d| d) {
+        term.dirty = false;
         return;
     }
-    
-    let id = TERM.window_id;
-    
+
+    let id = term.window_id;
+
     // Use fast batch rendering: only one find_window() call per row instead of 80!
     for row in 0..TERM_ROWS {
-        if !TERM.dirty_rows[row] {
+        if !term.grid.dirty_rows[row] {
             continue;
         }
-        
-        // Batch render entire row (80 chars) in one call + single find_window()
-        let mut chars = [0u8; TERM_COLS];
-        let mut fgs = [0u32; TERM_COLS];
-        let mut bgs = [0u32; TERM_COLS];
-        
-        for col in 0..TERM_COLS {
-            chars[col] = TERM.chars[row][col];
-            fgs[col] = VGA_PALETTE[TERM.fg_colors[row][col] as usize & 0x0F];
-            bgs[col] = VGA_PALETTE[TERM.bg_colors[row][col] as usize & 0x0F];
-        }
-        
+
         // Fast path: one window lookup for 80 characters
-        window::wm_render_rows_direct(id, row as u32, &chars, &fgs, &bgs);
-        
-        TERM.dirty_rows[row] = false;
+        window::wm_render_rows_direct(id, row as u32, &term.grid.chars[row],
+                                      &term.grid.fg_colors[row], &term.grid.bg_colors[row]);
+
+        term.grid.dirty_rows[row] = false;
     }
-    
+
     // Draw cursor
-    if TERM.cursor_row < TERM_ROWS && TERM.cursor_col < TERM_COLS {
-        let cx = (TERM.cursor_col as u32) * FONT_WIDTH;
-        let cy = (TERM.cursor_row as u32) * FONT_HEIGHT;
+    let (row, col) = (term.vt.row, term.vt.col);
+    if term.vt.cursor_visible && (row as usize) < TERM_ROWS && (col as usize) < TERM_COLS {
+        let cx = col * FONT_WIDTH;
+        let cy = row * FONT_HEIGHT;
         window::wm_window_fill_rect(id, cx, cy, FONT_WIDTH, FONT_HEIGHT, 0xAAAAAAu32);
     }
-    
-    TERM.dirty = false;
+
+    term.dirty = false;
 }
 
 /// Show the shell prompt.
 pub unsafe fn terminal_show_prompt() {
     terminal_set_color(10, 0); // light green
-    terminal_write_string(b"root@vernisOS");
+    terminal_write_string("root@vernisOS");
     terminal_set_color(7, 0);  // light grey
-    terminal_write_string(b":~$ ");
+    terminal_write_string(":~$ ");
     TERM.prompt_shown = true;
 }
 
@@ -357,21 +320,13 @@ pub unsafe extern "C" fn gui_terminal_putchar(ch: u8) {
 /// C FFI: clear the GUI terminal (called by `clear` command).
 #[no_mangle]
 pub unsafe extern "C" fn gui_terminal_clear() {
-    if !TERM.initialized {
+    let term = terminal_get();
+    if !term.initialized {
         return;
     }
-    // Clear all character cells
-    for r in 0..TERM_ROWS {
-        for c in 0..TERM_COLS {
-            TERM.chars[r][c] = b' ';
-            TERM.fg_colors[r][c] = 7;
-            TERM.bg_colors[r][c] = 0;
-        }
-        TERM.dirty_rows[r] = true;  // Mark all rows dirty
-    }
-    TERM.cursor_row = 0;
-    TERM.cursor_col = 0;
-    TERM.dirty = true;
+    // Clear all character cells (marks every row dirty) and home the cursor
+    term.vt.clear(&mut term.grid);
+    term.dirty = true;
 }
 
 /// Get the terminal window ID.
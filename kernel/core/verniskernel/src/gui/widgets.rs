@@ -1,5 +1,6 @@
 // Basic widget toolkit — Button, Label, TextInput
 
+use crate::font;
 use crate::framebuffer::{FONT_WIDTH, FONT_HEIGHT};
 use crate::gui::window;
 
@@ -210,13 +211,14 @@ pub unsafe fn widget_draw_all(window_id: u32) {
                 window::wm_window_fill_rect(window_id, w.x, w.y, w.w, 1, 0x666688);
                 window::wm_window_fill_rect(window_id, w.x, w.y, 1, w.h, 0x666688);
                 // Text centered
-                let text_x = w.x + (w.w.saturating_sub(*label_len as u32 * FONT_WIDTH)) / 2;
+                let label_w = font::cells(font::text(&label[..*label_len])) * FONT_WIDTH;
+                let text_x = w.x + (w.w.saturating_sub(label_w)) / 2;
                 let text_y = w.y + (w.h.saturating_sub(FONT_HEIGHT)) / 2;
                 window::wm_window_draw_string(
                     window_id,
                     text_x,
                     text_y,
-                    &label[..*label_len],
+                    font::text(&label[..*label_len]),
                     0xFFFFFF,
                     0x444466,
                 );
@@ -226,7 +228,7 @@ pub unsafe fn widget_draw_all(window_id: u32) {
                     window_id,
                     w.x,
                     w.y,
-                    &text[..*text_len],
+                    font::text(&text[..*text_len]),
                     0xCCCCCC,
                     0x000000, // transparent-ish -- use window bg
                 );
@@ -265,7 +267,7 @@ pub unsafe fn widget_draw_all(window_id: u32) {
                     window_id,
                     w.x + 4,
                     w.y + 4,
-                    &buffer[..*len],
+                    font::text(&buffer[..*len]),
                     0xCCCCCC,
                     bg,
                 );
@@ -2,8 +2,11 @@
 
 use alloc::vec::Vec;
 use core::ptr;
-use crate::framebuffer::{FONT_WIDTH, FONT_HEIGHT, write_pixel_buf};
+use crate::framebuffer::{FONT_WIDTH, FONT_HEIGHT};
 use crate::gui::compositor;
+use crate::font::{self, Grapheme};
+use crate::pixfmt::PixelFormat;
+use crate::raster::Surface;
 
 pub const TITLE_BAR_HEIGHT: u32 = 24;
 pub const BORDER_WIDTH: u32 = 1;
@@ -49,7 +52,7 @@ pub struct WindowManager {
     pub dragging: Option<u32>,
     pub drag_offset_x: i32,
     pub drag_offset_y: i32,
-    pub bpp: u32,
+    pub format: PixelFormat,  // content buffers use the framebuffer's format
     pub wins_dirty: bool,   // Set when window layout/visibility changes
 }
 
@@ -60,7 +63,7 @@ static mut WM: WindowManager = WindowManager {
     dragging: None,
     drag_offset_x: 0,
     drag_offset_y: 0,
-    bpp: 24,
+    format: PixelFormat::XRGB8888,
     wins_dirty: true,  // Initially dirty (need full redraw)
 };
 
@@ -68,12 +71,12 @@ pub unsafe fn wm_get() -> &'static mut WindowManager {
     &mut WM
 }
 
-pub unsafe fn wm_init(bpp: u32) {
+pub unsafe fn wm_init(format: PixelFormat) {
     WM.windows = Vec::new();
     WM.z_order = Vec::new();
     WM.next_id = 1;
     WM.dragging = None;
-    WM.bpp = bpp;
+    WM.format = format;
 }
 
 pub unsafe fn wm_create_window(title: &[u8], x: i32, y: i32, w: u32, h: u32) -> u32 {
@@ -90,8 +93,7 @@ pub unsafe fn wm_create_window(title: &[u8], x: i32, y: i32, w: u32, h: u32) ->
 
     let content_w = w.saturating_sub(BORDER_WIDTH * 2);
     let content_h = h.saturating_sub(TITLE_BAR_HEIGHT + BORDER_WIDTH);
-    let bpp_bytes = WM.bpp / 8;
-    let content_pitch = content_w * bpp_bytes;
+    let content_pitch = content_w * WM.format.bytes;
     let buf_size = (content_pitch * content_h) as usize;
 
     let mut content_buf = Vec::with_capacity(buf_size);
@@ -133,6 +135,16 @@ pub unsafe fn wm_close_window(id: u32) {
     WM.wins_dirty = true;  // Window list changed
 }
 
+pub unsafe fn wm_set_title(id: u32, title: &[u8]) {
+    let wm = wm_get();
+    let Some(w) = find_window(wm, id) else { return; };
+    let len = title.len().min(MAX_TITLE_LEN);
+    w.title = [0; 64];
+    w.title[..len].copy_from_slice(&title[..len]);
+    w.title_len = len;
+    wm.wins_dirty = true;  // Title bar changed
+}
+
 pub unsafe fn wm_focus_window(id: u32) {
     // Move to top of z_order
     WM.z_order.retain(|&wid| wid != id);
@@ -168,45 +180,36 @@ pub unsafe fn wm_window_fill(id: u32, color: u32) {
         if color == 0 {
             // Fast path: memset zero
             ptr::write_bytes(buf, 0, total);
-        } else if WM.bpp == 24 {
-            let b0 = color as u8;
-            let b1 = (color >> 8) as u8;
-            let b2 = (color >> 16) as u8;
-            let mut off = 0usize;
-            while off + 3 <= total {
-                *buf.add(off) = b0;
-                *buf.add(off + 1) = b1;
-                *buf.add(off + 2) = b2;
-                off += 3;
-            }
         } else {
-            let buf32 = buf as *mut u32;
-            let count = total / 4;
-            for i in 0..count {
-                ptr::write(buf32.add(i), color);
-            }
+            let format = WM.format;
+            format.fill(buf, total / format.bytes as usize, color);
         }
     }
 }
 
 /// Draw a character into a window's content buffer.
 pub unsafe fn wm_window_draw_char(id: u32, x: u32, y: u32, ch: u8, fg: u32, bg: u32) {
-    let bpp = WM.bpp;
+    wm_window_draw_glyph(id, x, y, &Grapheme::new(ch as char), fg, bg);
+}
+
+/// Draw one character cell (base character plus combining marks) into a
+/// window's content buffer.
+pub unsafe fn wm_window_draw_glyph(id: u32, x: u32, y: u32, g: &Grapheme, fg: u32, bg: u32) {
+    let format = WM.format;
     if let Some(w) = find_window(&mut WM, id) {
         let cw = w.content_width();
         let ch_h = w.content_height();
         let pitch = w.content_pitch;
-        let bpp_bytes = bpp / 8;
+        let bpp_bytes = format.bytes;
         let buf = w.content_buf.as_mut_ptr();
-        let font = &crate::font8x16::VGA_FONT_8X16;
-        let glyph_offset = (ch as usize) * (FONT_HEIGHT as usize);
+        let bits = font::glyph(g);
+        let (fg, bg) = (format.pack(fg), format.pack(bg));
 
         for row in 0..FONT_HEIGHT {
             if y + row >= ch_h {
                 break;
             }
-            let idx = glyph_offset + row as usize;
-            let glyph_row = if idx < font.len() { font[idx] } else { 0 };
+            let glyph_row = bits[row as usize];
             let row_base = buf.add(((y + row) * pitch) as usize);
 
             for col in 0..FONT_WIDTH {
@@ -218,44 +221,36 @@ pub unsafe fn wm_window_draw_char(id: u32, x: u32, y: u32, ch: u8, fg: u32, bg:
                 } else {
                     bg
                 };
-                write_pixel_buf(
-                    row_base.add(((x + col) * bpp_bytes) as usize),
-                    color,
-                    bpp,
-                );
+                format.store(row_base.add(((x + col) * bpp_bytes) as usize), color);
             }
         }
     }
 }
 
-/// Draw a string into a window's content buffer.
-pub unsafe fn wm_window_draw_string(id: u32, x: u32, y: u32, s: &[u8], fg: u32, bg: u32) {
+/// Draw UTF-8 text into a window's content buffer, one cell per grapheme.
+pub unsafe fn wm_window_draw_string(id: u32, x: u32, y: u32, s: &str, fg: u32, bg: u32) {
     let mut cx = x;
-    for &ch in s {
-        if ch == 0 {
-            break;
-        }
-        wm_window_draw_char(id, cx, y, ch, fg, bg);
+    for g in font::graphemes(s) {
+        wm_window_draw_glyph(id, cx, y, &g, fg, bg);
         cx += FONT_WIDTH;
     }
 }
 
 /// Fast batch rendering: render multiple characters in a row without repeated find_window() calls.
 /// Called by terminal_render() to avoid O(n) linear search per character.
-pub unsafe fn wm_render_rows_direct(id: u32, row: u32, chars: &[u8], fgs: &[u32], bgs: &[u32]) {
+pub unsafe fn wm_render_rows_direct(id: u32, row: u32, chars: &[Grapheme], fgs: &[u32], bgs: &[u32]) {
     if let Some(w) = find_window(&mut WM, id) {
         let cw = w.content_width();
         let ch_h = w.content_height();
         let pitch = w.content_pitch;
-        let bpp = WM.bpp;
-        let bpp_bytes = bpp / 8;
+        let format = WM.format;
+        let bpp_bytes = format.bytes;
         let buf = w.content_buf.as_mut_ptr();
-        let font = &crate::font8x16::VGA_FONT_8X16;
         
         let base_y = row * FONT_HEIGHT;
         let mut col = 0u32;
         
-        for &ch in chars {
+        for g in chars {
             if col >= 80 {
                 break;
             }
@@ -265,9 +260,9 @@ pub unsafe fn wm_render_rows_direct(id: u32, row: u32, chars: &[u8], fgs: &[u32]
                 break;
             }
             
-            let fg = fgs[col as usize];
-            let bg = bgs[col as usize];
-            let glyph_offset = (ch as usize) * (FONT_HEIGHT as usize);
+            let fg = format.pack(fgs[col as usize]);
+            let bg = format.pack(bgs[col as usize]);
+            let bits = font::glyph(g);
             
             // Render character glyph
             for glyph_row in 0..FONT_HEIGHT {
@@ -275,8 +270,7 @@ pub unsafe fn wm_render_rows_direct(id: u32, row: u32, chars: &[u8], fgs: &[u32]
                     break;
                 }
                 
-                let idx = glyph_offset + glyph_row as usize;
-                let glyph_bits = if idx < font.len() { font[idx] } else { 0 };
+                let glyph_bits = bits[glyph_row as usize];
                 let row_base = buf.add(((base_y + glyph_row) * pitch) as usize);
                 
                 for glyph_col in 0..FONT_WIDTH {
@@ -291,11 +285,7 @@ pub unsafe fn wm_render_rows_direct(id: u32, row: u32, chars: &[u8], fgs: &[u32]
                         bg
                     };
                     
-                    write_pixel_buf(
-                        row_base.add((pixel_x * bpp_bytes) as usize),
-                        color,
-                        bpp,
-                    );
+                    format.store(row_base.add((pixel_x * bpp_bytes) as usize), color);
                 }
             }
             
@@ -307,14 +297,23 @@ pub unsafe fn wm_render_rows_direct(id: u32, row: u32, chars: &[u8], fgs: &[u32]
     }
 }
 
+/// A drawing surface over a window's content area. It is only valid until
+/// the window is closed.
+pub unsafe fn wm_window_surface(id: u32) -> Option<Surface> {
+    let format = WM.format;
+    let win = find_window(&mut *ptr::addr_of_mut!(WM), id)?;
+    let (cw, ch) = (win.content_width(), win.content_height());
+    Surface::from_slice(&mut win.content_buf, cw, ch, win.content_pitch, format)
+}
+
 /// Draw a filled rectangle in a window's content buffer.
 pub unsafe fn wm_window_fill_rect(id: u32, x: u32, y: u32, w: u32, h: u32, color: u32) {
-    let bpp = WM.bpp;
+    let format = WM.format;
     if let Some(win) = find_window(&mut WM, id) {
         let cw = win.content_width();
         let ch = win.content_height();
         let pitch = win.content_pitch;
-        let bpp_bytes = bpp / 8;
+        let bpp_bytes = format.bytes;
         let buf = win.content_buf.as_mut_ptr();
 
         let x_end = if x + w > cw { cw } else { x + w };
@@ -325,41 +324,21 @@ pub unsafe fn wm_window_fill_rect(id: u32, x: u32, y: u32, w: u32, h: u32, color
         }
 
         let cols = x_end - x;
+        let row_bytes = (cols * bpp_bytes) as usize;
 
         if color == 0 {
-            let row_bytes = (cols * bpp_bytes) as usize;
             for row in y..y_end {
                 let dst = buf.add((row * pitch + x * bpp_bytes) as usize);
                 ptr::write_bytes(dst, 0, row_bytes);
             }
-        } else if bpp_bytes == 3 {
+        } else {
             // Fill first row, memcpy to rest
-            let row0 = buf.add((y * pitch + x * 3) as usize);
-            let b0 = color as u8;
-            let b1 = (color >> 8) as u8;
-            let b2 = (color >> 16) as u8;
-            for col in 0..cols {
-                let off = (col * 3) as usize;
-                *row0.add(off) = b0;
-                *row0.add(off + 1) = b1;
-                *row0.add(off + 2) = b2;
-            }
-            let row_bytes = (cols * 3) as usize;
+            let row0 = buf.add((y * pitch + x * bpp_bytes) as usize);
+            format.fill(row0, cols as usize, color);
             for row in (y + 1)..y_end {
-                let dst = buf.add((row * pitch + x * 3) as usize);
+                let dst = buf.add((row * pitch + x * bpp_bytes) as usize);
                 ptr::copy_nonoverlapping(row0, dst, row_bytes);
             }
-        } else {
-            let row0 = buf.add((y * pitch + x * 4) as usize) as *mut u32;
-            for col in 0..cols {
-                ptr::write(row0.add(col as usize), color);
-            }
-            let row_bytes = (cols * 4) as usize;
-            let row0_src = row0 as *const u8;
-            for row in (y + 1)..y_end {
-                let dst = buf.add((row * pitch + x * 4) as usize);
-                ptr::copy_nonoverlapping(row0_src, dst, row_bytes);
-            }
         }
     }
 }
@@ -426,7 +405,7 @@ pub unsafe fn wm_compose_all() {
             compositor::compositor_draw_string(
                 title_x,
                 title_y,
-                &w.title[..w.title_len],
+                font::text(&w.title[..w.title_len]),
                 0xFFFFFF,
                 title_color,
             );
@@ -0,0 +1,707 @@
+// heap.rs — Kernel heap: accounting, leak tracking, growth and OOM handling
+//
+// `KernelHeap` wraps the buddy allocator used as `#[global_allocator]` and
+// keeps running totals (bytes in use, peak, per-size-class counts). When the
+// buddy heap cannot satisfy a request the slow path tries, in order:
+//
+//   1. reaping empty slabs (slab::reap_all)
+//   2. growing the heap with memory handed out by the C frame allocator
+//   3. the OOM policy: kill the largest untrusted user task
+//
+// and only then lets the allocation fail into `alloc_error_handler`. The
+// kill does not hand frames back: user pages come from the C bump frame
+// allocator, which never frees. It helps the failing allocation only when
+// the victim's teardown frees heap objects (its shm segments, which also
+// back its uring rings); otherwise it just stops the task and the
+// allocation still fails.
+//
+// Leak tracking is optional (off by default): while enabled every live
+// allocation is recorded with the subsystem tag active at allocation time
+// and the tick it was made, so long-lived allocations can be reported per
+// subsystem. Like the rest of the kernel this is single-core `static mut`
+// state; the allocator must not be used from IRQ context.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::{self, Write};
+
+use buddy_system_allocator::LockedHeap;
+
+use crate::ai::types::TrustLevel;
+use crate::scheduler::Scheduler;
+use crate::textbuf::TextBuf;
+
+extern "C" {
+    fn kernel_get_ticks() -> u32;
+}
+
+/// Minimum amount requested from the frame allocator per heap growth.
+pub const HEAP_GROW_CHUNK: usize = 256 * 1024;
+const PAGE_SIZE: usize = 4096;
+
+/// Size classes: <=8, 16, 32, ... 4096, >4096.
+pub const HEAP_SIZE_CLASSES: usize = 11;
+
+/// Capacity of the live-allocation table used by leak tracking.
+pub const LEAK_TABLE_SIZE: usize = 1024;
+const LEAK_REPORT_MAX: usize = 32;
+
+// =============================================================================
+// Subsystem tags
+// =============================================================================
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapTag {
+    Untagged = 0,
+    Scheduler = 1,
+    Ai = 2,
+    Gui = 3,
+    Module = 4,
+    Slab = 5,
+    Ipc = 6,
+    Fs = 7,
+}
+
+pub const HEAP_TAG_COUNT: usize = 8;
+
+const TAG_NAMES: [&str; HEAP_TAG_COUNT] =
+    ["untagged", "scheduler", "ai", "gui", "module", "slab", "ipc", "fs"];
+
+impl HeapTag {
+    pub fn from_u8(v: u8) -> Self {
+        match v {
+            1 => HeapTag::Scheduler,
+            2 => HeapTag::Ai,
+            3 => HeapTag::Gui,
+            4 => HeapTag::Module,
+            5 => HeapTag::Slab,
+            6 => HeapTag::Ipc,
+            7 => HeapTag::Fs,
+            _ => HeapTag::Untagged,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        TAG_NAMES[self as usize]
+    }
+}
+
+static mut CURRENT_TAG: u8 = HeapTag::Untagged as u8;
+
+/// Attributes allocations to `tag` until dropped, then restores the
+/// previous tag. Scopes nest.
+pub struct TagScope {
+    prev: u8,
+}
+
+pub fn tag_scope(tag: HeapTag) -> TagScope {
+    let prev = unsafe { CURRENT_TAG };
+    unsafe { CURRENT_TAG = tag as u8; }
+    TagScope { prev }
+}
+
+impl Drop for TagScope {
+    fn drop(&mut self) {
+        unsafe { CURRENT_TAG = self.prev; }
+    }
+}
+
+// =============================================================================
+// Statistics
+// =============================================================================
+
+/// Heap totals, shared with C (include/heap.h).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct HeapStats {
+    pub heap_size: usize,       // bytes managed by the buddy heap (initial + grown)
+    pub bytes_in_use: usize,    // requested bytes currently allocated
+    pub peak_bytes: usize,      // high-water mark of bytes_in_use
+    pub allocator_bytes: usize, // bytes in use including buddy rounding
+    pub alloc_count: u32,
+    pub free_count: u32,
+    pub failed_allocs: u32,
+    pub grow_count: u32,
+    pub oom_kills: u32,
+    pub reaped_bytes: u32,
+    pub class_live: [u32; HEAP_SIZE_CLASSES],
+    pub class_total: [u32; HEAP_SIZE_CLASSES],
+}
+
+impl HeapStats {
+    const fn new() -> Self {
+        Self {
+            heap_size: 0,
+            bytes_in_use: 0,
+            peak_bytes: 0,
+            allocator_bytes: 0,
+            alloc_count: 0,
+            free_count: 0,
+            failed_allocs: 0,
+            grow_count: 0,
+            oom_kills: 0,
+            reaped_bytes: 0,
+            class_live: [0; HEAP_SIZE_CLASSES],
+            class_total: [0; HEAP_SIZE_CLASSES],
+        }
+    }
+}
+
+/// Per-subsystem counters; only maintained while leak tracking is enabled.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct HeapTagStats {
+    pub live_allocs: u32,
+    pub live_bytes: usize,
+    pub total_allocs: u32,
+}
+
+impl HeapTagStats {
+    const fn new() -> Self {
+        Self { live_allocs: 0, live_bytes: 0, total_allocs: 0 }
+    }
+}
+
+static mut STATS: HeapStats = HeapStats::new();
+static mut TAG_STATS: [HeapTagStats; HEAP_TAG_COUNT] = [HeapTagStats::new(); HEAP_TAG_COUNT];
+
+fn size_class(size: usize) -> usize {
+    if size <= 8 {
+        return 0;
+    }
+    let bits = (usize::BITS - (size - 1).leading_zeros()) as usize;
+    (bits - 3).min(HEAP_SIZE_CLASSES - 1)
+}
+
+fn class_label(class: usize) -> usize {
+    8 << class
+}
+
+fn stats() -> &'static mut HeapStats {
+    unsafe { &mut *core::ptr::addr_of_mut!(STATS) }
+}
+
+fn tag_stats() -> &'static mut [HeapTagStats; HEAP_TAG_COUNT] {
+    unsafe { &mut *core::ptr::addr_of_mut!(TAG_STATS) }
+}
+
+// =============================================================================
+// Leak tracking — open-addressed table of live allocations
+// =============================================================================
+
+const SLOT_EMPTY: usize = 0;
+const SLOT_DELETED: usize = usize::MAX;
+
+#[derive(Clone, Copy)]
+struct LiveAlloc {
+    addr: usize,
+    size: usize,
+    tick: u32,
+    tag: u8,
+}
+
+impl LiveAlloc {
+    const EMPTY: Self = Self { addr: SLOT_EMPTY, size: 0, tick: 0, tag: 0 };
+}
+
+static mut LEAK_TRACKING: bool = false;
+static mut LIVE: [LiveAlloc; LEAK_TABLE_SIZE] = [LiveAlloc::EMPTY; LEAK_TABLE_SIZE];
+static mut LIVE_COUNT: usize = 0;
+static mut UNTRACKED: u32 = 0;
+
+fn live_table() -> &'static mut [LiveAlloc; LEAK_TABLE_SIZE] {
+    unsafe { &mut *core::ptr::addr_of_mut!(LIVE) }
+}
+
+fn live_hash(addr: usize) -> usize {
+    ((addr >> 3).wrapping_mul(0x9E37_79B9)) & (LEAK_TABLE_SIZE - 1)
+}
+
+fn live_insert(addr: usize, size: usize, tag: u8) -> bool {
+    let table = live_table();
+    let mut idx = live_hash(addr);
+    for _ in 0..LEAK_TABLE_SIZE {
+        let slot = &mut table[idx];
+        if slot.addr == SLOT_EMPTY || slot.addr == SLOT_DELETED {
+            *slot = LiveAlloc { addr, size, tick: unsafe { kernel_get_ticks() }, tag };
+            unsafe { LIVE_COUNT += 1; }
+            return true;
+        }
+        idx = (idx + 1) & (LEAK_TABLE_SIZE - 1);
+    }
+    false
+}
+
+fn live_remove(addr: usize) -> Option<LiveAlloc> {
+    let table = live_table();
+    let mut idx = live_hash(addr);
+    for _ in 0..LEAK_TABLE_SIZE {
+        let slot = &mut table[idx];
+        if slot.addr == SLOT_EMPTY {
+            return None;
+        }
+        if slot.addr == addr {
+            let rec = *slot;
+            slot.addr = SLOT_DELETED;
+            unsafe { LIVE_COUNT -= 1; }
+            return Some(rec);
+        }
+        idx = (idx + 1) & (LEAK_TABLE_SIZE - 1);
+    }
+    None
+}
+
+/// Enable or disable leak tracking. Both transitions clear the live table
+/// and per-tag counters, since frees are only matched while tracking is on.
+pub fn set_leak_tracking(enable: bool) {
+    *live_table() = [LiveAlloc::EMPTY; LEAK_TABLE_SIZE];
+    *tag_stats() = [HeapTagStats::new(); HEAP_TAG_COUNT];
+    unsafe {
+        LIVE_COUNT = 0;
+        UNTRACKED = 0;
+        LEAK_TRACKING = enable;
+    }
+}
+
+pub fn leak_tracking() -> bool {
+    unsafe { LEAK_TRACKING }
+}
+
+fn account_alloc(ptr: *mut u8, layout: Layout) {
+    let s = stats();
+    let class = size_class(layout.size());
+    s.alloc_count = s.alloc_count.wrapping_add(1);
+    s.bytes_in_use += layout.size();
+    if s.bytes_in_use > s.peak_bytes {
+        s.peak_bytes = s.bytes_in_use;
+    }
+    s.class_live[class] = s.class_live[class].wrapping_add(1);
+    s.class_total[class] = s.class_total[class].wrapping_add(1);
+
+    if leak_tracking() {
+        let tag = unsafe { CURRENT_TAG } as usize % HEAP_TAG_COUNT;
+        if live_insert(ptr as usize, layout.size(), tag as u8) {
+            let t = &mut tag_stats()[tag];
+            t.live_allocs += 1;
+            t.live_bytes += layout.size();
+            t.total_allocs = t.total_allocs.wrapping_add(1);
+        } else {
+            unsafe { UNTRACKED = UNTRACKED.wrapping_add(1); }
+        }
+    }
+}
+
+fn account_free(ptr: *mut u8, layout: Layout) {
+    let s = stats();
+    let class = size_class(layout.size());
+    s.free_count = s.free_count.wrapping_add(1);
+    s.bytes_in_use = s.bytes_in_use.saturating_sub(layout.size());
+    s.class_live[class] = s.class_live[class].saturating_sub(1);
+
+    if leak_tracking() {
+        if let Some(rec) = live_remove(ptr as usize) {
+            let t = &mut tag_stats()[rec.tag as usize];
+            t.live_allocs = t.live_allocs.saturating_sub(1);
+            t.live_bytes = t.live_bytes.saturating_sub(rec.size);
+        }
+    }
+}
+
+// =============================================================================
+// Global allocator
+// =============================================================================
+
+/// Returns the start of a physically contiguous, identity-mapped region of
+/// at least `min_bytes` bytes via `out_start`, and its size (0 = refused).
+pub type HeapGrowCallback = unsafe extern "C" fn(min_bytes: usize, out_start: *mut usize) -> usize;
+
+/// A task the OOM policy may kill, as reported by the arch code.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OomCandidate {
+    pub pid: u32,
+    pub pages: u32, // user pages the task holds
+}
+
+/// Fills `out` with up to `max` killable tasks (live tasks running a user
+/// program, never the current one) and returns how many it wrote.
+pub type OomTasksCallback = unsafe extern "C" fn(out: *mut OomCandidate, max: u32) -> u32;
+
+/// Invoked with the OOM victim: C drops its task slot and releases its
+/// shared memory, uring rings and swap slots.
+pub type OomKillCallback = unsafe extern "C" fn(pid: u32);
+
+static mut GROW_CB: Option<HeapGrowCallback> = None;
+static mut OOM_TASKS_CB: Option<OomTasksCallback> = None;
+static mut OOM_KILL_CB: Option<OomKillCallback> = None;
+static mut IN_RECLAIM: bool = false;
+
+pub struct KernelHeap {
+    inner: LockedHeap<32>,
+}
+
+impl KernelHeap {
+    pub const fn empty() -> Self {
+        Self { inner: LockedHeap::empty() }
+    }
+
+    /// # Safety
+    /// `start..start + size` must be unused, writable memory owned by the heap.
+    pub unsafe fn init(&self, start: usize, size: usize) {
+        self.inner.lock().init(start, size);
+        stats().heap_size = size;
+    }
+
+    /// Snapshot of the totals, including the buddy allocator's own figure.
+    pub fn stats(&self) -> HeapStats {
+        let mut s = *stats();
+        s.allocator_bytes = self.inner.lock().stats_alloc_actual();
+        s
+    }
+
+    fn try_alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.inner.alloc(layout) }
+    }
+
+    /// Slow path: reap slabs, grow, then OOM-kill, retrying after each step.
+    fn alloc_slow(&self, layout: Layout) -> *mut u8 {
+        // Reclaim steps free or allocate memory themselves; never recurse.
+        if unsafe { IN_RECLAIM } {
+            return core::ptr::null_mut();
+        }
+        unsafe { IN_RECLAIM = true; }
+        let ptr = self.reclaim(layout);
+        unsafe { IN_RECLAIM = false; }
+        ptr
+    }
+
+    fn reclaim(&self, layout: Layout) -> *mut u8 {
+        let reaped = crate::slab::reap_all();
+        if reaped > 0 {
+            let s = stats();
+            s.reaped_bytes = s.reaped_bytes.wrapping_add(reaped as u32);
+            let ptr = self.try_alloc(layout);
+            if !ptr.is_null() {
+                return ptr;
+            }
+        }
+
+        if self.grow(layout) {
+            let ptr = self.try_alloc(layout);
+            if !ptr.is_null() {
+                return ptr;
+            }
+        }
+
+        // Retry in case the victim's teardown freed heap objects; its
+        // user frames are not reclaimed (see the top of this file).
+        if oom_kill(layout) {
+            let ptr = self.try_alloc(layout);
+            if ptr.is_null() {
+                crate::kernel_print_raw("[heap] OOM: the kill freed no heap memory for this request\n");
+            }
+            return ptr;
+        }
+        core::ptr::null_mut()
+    }
+
+    fn grow(&self, layout: Layout) -> bool {
+        let cb = match unsafe { GROW_CB } {
+            Some(cb) => cb,
+            None => return false,
+        };
+        // Buddy blocks are power-of-two sized and aligned; ask for enough
+        // headroom that a block of the requested order fits.
+        let want = layout.size().max(layout.align()).next_power_of_two() * 2;
+        let want = (want.max(HEAP_GROW_CHUNK) + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+
+        let mut start = 0usize;
+        let got = unsafe { cb(want, &mut start) };
+        if got == 0 || start == 0 {
+            return false;
+        }
+        unsafe { self.inner.lock().add_to_heap(start, start + got); }
+
+        let s = stats();
+        s.heap_size += got;
+        s.grow_count = s.grow_count.wrapping_add(1);
+
+        let mut buf = [0u8; 96];
+        let mut out = TextBuf::new(&mut buf);
+        let _ = writeln!(out, "[heap] grew by {} KB at {:#x} (total {} KB)",
+                         got / 1024, start, s.heap_size / 1024);
+        crate::kernel_print_raw(out.as_str());
+        true
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut ptr = self.try_alloc(layout);
+        if ptr.is_null() {
+            ptr = self.alloc_slow(layout);
+        }
+        if ptr.is_null() {
+            let s = stats();
+            s.failed_allocs = s.failed_allocs.wrapping_add(1);
+        } else {
+            account_alloc(ptr, layout);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        account_free(ptr, layout);
+        self.inner.dealloc(ptr, layout);
+    }
+}
+
+// =============================================================================
+// OOM policy
+// =============================================================================
+
+// The allocator can run while the scheduler or the AI engine is borrowed, so
+// the OOM path touches neither: candidates come from the arch task table,
+// trust from marks the process tracker records here, and the victim's PCB is
+// marked killed later by reap_oom_kills().
+
+const OOM_MAX_CANDIDATES: usize = 16;
+const OOM_TRUST_MARKS: usize = 32;
+const OOM_PENDING_MAX: usize = 8;
+
+#[derive(Clone, Copy)]
+struct TrustMark {
+    pid: u32,
+    trust: TrustLevel,
+}
+
+static mut TRUST_MARKS: [TrustMark; OOM_TRUST_MARKS] =
+    [TrustMark { pid: 0, trust: TrustLevel::Normal }; OOM_TRUST_MARKS];
+static mut TRUST_NEXT: usize = 0;
+static mut OOM_PENDING: [u32; OOM_PENDING_MAX] = [0; OOM_PENDING_MAX];
+
+/// Record that `pid` dropped to `trust`. Pids are never reused, so the
+/// oldest mark is overwritten when the table is full.
+pub fn note_trust(pid: u32, trust: TrustLevel) {
+    let marks = unsafe { &mut *core::ptr::addr_of_mut!(TRUST_MARKS) };
+    if let Some(m) = marks.iter_mut().find(|m| m.pid == pid) {
+        m.trust = trust;
+        return;
+    }
+    unsafe {
+        marks[TRUST_NEXT] = TrustMark { pid, trust };
+        TRUST_NEXT = (TRUST_NEXT + 1) % OOM_TRUST_MARKS;
+    }
+}
+
+fn trust_of(pid: u32) -> TrustLevel {
+    let marks = unsafe { &*core::ptr::addr_of!(TRUST_MARKS) };
+    marks.iter()
+        .find(|m| m.pid == pid && pid != 0)
+        .map(|m| m.trust)
+        .unwrap_or(TrustLevel::Normal)
+}
+
+/// Pick the OOM victim: the largest untrusted or suspicious candidate task.
+/// Normal and trusted tasks are never killed.
+fn pick_victim(cands: &[OomCandidate]) -> Option<(u32, usize, TrustLevel)> {
+    cands.iter()
+        .filter(|c| c.pid != 0)
+        .map(|c| (c.pid, c.pages as usize * PAGE_SIZE, trust_of(c.pid)))
+        .filter(|&(_, _, trust)| matches!(trust, TrustLevel::Untrusted | TrustLevel::Suspicious))
+        .max_by_key(|&(_, size, _)| size)
+}
+
+fn oom_kill(layout: Layout) -> bool {
+    let (tasks_cb, kill_cb) = match unsafe { (OOM_TASKS_CB, OOM_KILL_CB) } {
+        (Some(t), Some(k)) => (t, k),
+        _ => return false,
+    };
+    let mut cands = [OomCandidate::default(); OOM_MAX_CANDIDATES];
+    let n = unsafe { tasks_cb(cands.as_mut_ptr(), OOM_MAX_CANDIDATES as u32) } as usize;
+
+    let mut buf = [0u8; 128];
+    let mut out = TextBuf::new(&mut buf);
+    let (pid, size, trust) = match pick_victim(&cands[..n.min(OOM_MAX_CANDIDATES)]) {
+        Some(v) => v,
+        None => {
+            let _ = writeln!(out, "[heap] OOM: {} bytes (align {}) and no untrusted process to kill",
+                             layout.size(), layout.align());
+            crate::kernel_print_raw(out.as_str());
+            return false;
+        }
+    };
+
+    let _ = writeln!(out, "[heap] OOM: {} bytes (align {}); killing pid {} ({} KB, trust {:?})",
+                     layout.size(), layout.align(), pid, size / 1024, trust);
+    crate::kernel_print_raw(out.as_str());
+
+    unsafe { kill_cb(pid); }
+    let pending = unsafe { &mut *core::ptr::addr_of_mut!(OOM_PENDING) };
+    match pending.iter_mut().find(|p| **p == 0) {
+        Some(slot) => *slot = pid,
+        None => crate::kernel_print_raw("[heap] OOM kill queue full\n"),
+    }
+    let s = stats();
+    s.oom_kills = s.oom_kills.wrapping_add(1);
+    true
+}
+
+/// Mark processes the OOM policy killed as terminated and drop their
+/// seccomp filters. Call where no scheduler borrow is live.
+pub fn reap_oom_kills(sched: &mut Scheduler) {
+    let pending = unsafe { &mut *core::ptr::addr_of_mut!(OOM_PENDING) };
+    for slot in pending.iter_mut().filter(|p| **p != 0) {
+        let pid = core::mem::replace(slot, 0) as usize;
+        if sched.kill_process(pid).is_ok() {
+            if let Some(p) = sched.processes.get_mut(&pid) {
+                p.seccomp = None;
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Reports
+// =============================================================================
+
+/// Render heap totals and the size-class histogram.
+pub fn render_stats<W: Write>(s: &HeapStats, out: &mut W) -> fmt::Result {
+    writeln!(out, "heap_size:      {} KB", s.heap_size / 1024)?;
+    writeln!(out, "in_use:         {} bytes ({} with rounding)", s.bytes_in_use, s.allocator_bytes)?;
+    writeln!(out, "peak:           {} bytes", s.peak_bytes)?;
+    writeln!(out, "allocs/frees:   {} / {}", s.alloc_count, s.free_count)?;
+    writeln!(out, "failed:         {}", s.failed_allocs)?;
+    writeln!(out, "grown:          {} times", s.grow_count)?;
+    writeln!(out, "oom_kills:      {}", s.oom_kills)?;
+    writeln!(out, "slab_reaped:    {} bytes", s.reaped_bytes)?;
+    writeln!(out, "# class      live     total")?;
+    for c in 0..HEAP_SIZE_CLASSES {
+        if c == HEAP_SIZE_CLASSES - 1 {
+            write!(out, ">{:<8}", class_label(c - 1))?;
+        } else {
+            write!(out, "<={:<7}", class_label(c))?;
+        }
+        writeln!(out, " {:>8} {:>9}", s.class_live[c], s.class_total[c])?;
+    }
+    Ok(())
+}
+
+/// Render per-subsystem usage and allocations older than `min_age` ticks.
+pub fn render_leaks<W: Write>(out: &mut W, min_age: u32) -> fmt::Result {
+    if !leak_tracking() {
+        return writeln!(out, "leak tracking disabled");
+    }
+    let (live, untracked) = unsafe { (LIVE_COUNT, UNTRACKED) };
+    writeln!(out, "tracked: {} live allocations, {} untracked (table full)", live, untracked)?;
+    writeln!(out, "# tag         live      bytes     total")?;
+    for (i, t) in tag_stats().iter().enumerate() {
+        writeln!(out, "{:<10} {:>7} {:>10} {:>9}", TAG_NAMES[i], t.live_allocs, t.live_bytes, t.total_allocs)?;
+    }
+
+    let now = unsafe { kernel_get_ticks() };
+    writeln!(out, "# address            size  tag        age(ticks)")?;
+    let mut shown = 0usize;
+    let mut skipped = 0usize;
+    for rec in live_table().iter() {
+        if rec.addr == SLOT_EMPTY || rec.addr == SLOT_DELETED {
+            continue;
+        }
+        let age = now.wrapping_sub(rec.tick);
+        if age < min_age {
+            continue;
+        }
+        if shown == LEAK_REPORT_MAX {
+            skipped += 1;
+            continue;
+        }
+        writeln!(out, "{:#018x} {:>6}  {:<10} {}", rec.addr, rec.size, TAG_NAMES[rec.tag as usize], age)?;
+        shown += 1;
+    }
+    if skipped > 0 {
+        writeln!(out, "... {} more", skipped)?;
+    }
+    Ok(())
+}
+
+/// Print diagnostics for an allocation that could not be satisfied.
+pub fn report_alloc_failure(s: &HeapStats, layout: Layout) {
+    let mut buf = [0u8; 1024];
+    let mut out = TextBuf::new(&mut buf);
+    let _ = writeln!(out, "[heap] FATAL: out of memory allocating {} bytes (align {})",
+                     layout.size(), layout.align());
+    let _ = render_stats(s, &mut out);
+    crate::kernel_print_raw(out.as_str());
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+#[no_mangle]
+pub extern "C" fn heap_set_grow_cb(cb: Option<HeapGrowCallback>) {
+    unsafe { GROW_CB = cb; }
+}
+
+#[no_mangle]
+pub extern "C" fn heap_set_oom_tasks_cb(cb: Option<OomTasksCallback>) {
+    unsafe { OOM_TASKS_CB = cb; }
+}
+
+#[no_mangle]
+pub extern "C" fn heap_set_oom_kill_cb(cb: Option<OomKillCallback>) {
+    unsafe { OOM_KILL_CB = cb; }
+}
+
+#[no_mangle]
+pub extern "C" fn heap_get_stats(out: *mut HeapStats) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    unsafe { *out = crate::ALLOCATOR.stats(); }
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn heap_get_tag_stats(tag: u8, out: *mut HeapTagStats) -> bool {
+    if out.is_null() || tag as usize >= HEAP_TAG_COUNT {
+        return false;
+    }
+    unsafe { *out = tag_stats()[tag as usize]; }
+    true
+}
+
+/// Set the subsystem tag for subsequent allocations; returns the previous tag.
+#[no_mangle]
+pub extern "C" fn heap_set_tag(tag: u8) -> u8 {
+    unsafe {
+        let prev = CURRENT_TAG;
+        CURRENT_TAG = HeapTag::from_u8(tag) as u8;
+        prev
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn heap_set_leak_tracking(enable: bool) {
+    set_leak_tracking(enable);
+}
+
+#[no_mangle]
+pub extern "C" fn heap_render_info(buf: *mut u8, buf_len: u32) -> u32 {
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = render_stats(&crate::ALLOCATOR.stats(), &mut out);
+    out.finish_cstr() as u32
+}
+
+#[no_mangle]
+pub extern "C" fn heap_render_leaks(buf: *mut u8, buf_len: u32, min_age_ticks: u32) -> u32 {
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = render_leaks(&mut out, min_age_ticks);
+    out.finish_cstr() as u32
+}
@@ -0,0 +1,131 @@
+// kinfo.rs — Read-only kernel info page shared with user space (vDSO-like)
+//
+// One page, mapped read-only at KINFO_VADDR into every user address space,
+// that lets a process read the time and its own PID without a syscall. This
+// module owns the layout and the page itself; the arch code maps it and keeps
+// it current from the timer IRQ and the task-switch paths, using the inline
+// writers in include/kinfo.h (the IRQ path makes no Rust calls).
+//
+// Updates follow a seqlock: the writer makes `seq` odd, stores the fields and
+// makes it even again. A reader copies the fields between two reads of `seq`
+// and retries if the two differ or the first was odd (userlib/libc.c).
+//
+// All tasks share one page table, so `pid` is that of the task currently on
+// the CPU — which is always the reader.
+
+use core::ptr::addr_of_mut;
+
+/// "VKIP", little-endian.
+pub const KINFO_MAGIC: u32 = 0x5049_4B56;
+pub const KINFO_VERSION: u32 = 1;
+/// User virtual address of the page, just below the user range.
+pub const KINFO_VADDR: usize = 0x0FFF_F000;
+const KINFO_PAGE_SIZE: usize = 4096;
+
+/// Page contents, shared with C (include/kinfo.h) and userlib (libc.h).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KernelInfoPage {
+    pub magic: u32,
+    pub version: u32,
+    /// Seqlock counter; odd while an update is in progress.
+    pub seq: u32,
+    pub timer_hz: u32,
+    /// Timer ticks since boot.
+    pub ticks: u64,
+    /// Unix time (seconds) at tick 0, from the RTC.
+    pub wall_base: u64,
+    /// PID of the running task, 0 for the kernel.
+    pub pid: u32,
+    pub _pad: u32,
+    /// Random per-boot identifier (UUID v4 layout).
+    pub boot_id: [u8; 16],
+}
+
+const _: () = assert!(core::mem::size_of::<KernelInfoPage>() == 56);
+
+#[repr(C, align(4096))]
+struct InfoPage {
+    info: KernelInfoPage,
+    _fill: [u8; KINFO_PAGE_SIZE - core::mem::size_of::<KernelInfoPage>()],
+}
+
+// Lives in the kernel image, which is identity-mapped, so its address is also
+// the physical frame the arch code maps for user space.
+static mut PAGE: InfoPage = InfoPage {
+    info: KernelInfoPage {
+        magic: 0,
+        version: 0,
+        seq: 0,
+        timer_hz: 0,
+        ticks: 0,
+        wall_base: 0,
+        pid: 0,
+        _pad: 0,
+        boot_id: [0; 16],
+    },
+    _fill: [0; KINFO_PAGE_SIZE - core::mem::size_of::<KernelInfoPage>()],
+};
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn make_boot_id(seed: u64) -> [u8; 16] {
+    let mut state = seed;
+    let mut id = [0u8; 16];
+    id[..8].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+    id[8..].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+    id[6] = (id[6] & 0x0F) | 0x40;
+    id[8] = (id[8] & 0x3F) | 0x80;
+    id
+}
+
+/// Seconds since the Unix epoch for a UTC calendar date (days-from-civil).
+pub fn unix_time(year: u32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> u64 {
+    let y = if month <= 2 { year as i64 - 1 } else { year as i64 };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86_400 + hour as i64 * 3600 + min as i64 * 60 + sec as i64;
+    secs.max(0) as u64
+}
+
+/// Fill in the page. Returns it for the arch code to map at KINFO_VADDR and
+/// to pass to the include/kinfo.h writers.
+pub fn init(timer_hz: u32, wall_base: u64, seed: u64) -> *mut KernelInfoPage {
+    let info = unsafe { &mut (*addr_of_mut!(PAGE)).info };
+    info.magic = KINFO_MAGIC;
+    info.version = KINFO_VERSION;
+    info.seq = 0;
+    info.timer_hz = timer_hz;
+    info.ticks = 0;
+    info.wall_base = wall_base;
+    info.pid = 0;
+    info.boot_id = make_boot_id(seed ^ wall_base.rotate_left(32));
+    info
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Initialise the page; `seed` should be something boot-unique (e.g. TSC).
+#[no_mangle]
+pub extern "C" fn kinfo_init(timer_hz: u32, wall_base: u64, seed: u64) -> *mut KernelInfoPage {
+    init(timer_hz, wall_base, seed)
+}
+
+/// Unix time for an RTC reading (UTC).
+#[no_mangle]
+pub extern "C" fn kinfo_unix_time(year: u32, month: u32, day: u32,
+                                  hour: u32, min: u32, sec: u32) -> u64 {
+    unix_time(year, month, day, hour, min, sec)
+}
@@ -3,46 +3,93 @@
 
 extern crate alloc;
 
+use core::fmt::Write;
 use core::panic::PanicInfo;
-use buddy_system_allocator::LockedHeap;
 // use alloc::string::ToString;
 
 #[global_allocator]
-static ALLOCATOR: LockedHeap<32> = LockedHeap::empty();
+static ALLOCATOR: heap::KernelHeap = heap::KernelHeap::empty();
 
 #[no_mangle]
 pub extern "C" fn verniskernel_init_heap(heap_start: usize, heap_size: usize) {
-    unsafe { ALLOCATOR.lock().init(heap_start, heap_size); }
+    unsafe { ALLOCATOR.init(heap_start, heap_size); }
+    slab::init();
 }
 
 #[alloc_error_handler]
-fn alloc_error_handler(_layout: core::alloc::Layout) -> ! {
+fn alloc_error_handler(layout: core::alloc::Layout) -> ! {
+    heap::report_alloc_failure(&ALLOCATOR.stats(), layout);
     loop {}
 }
 
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
+fn panic(info: &PanicInfo) -> ! {
+    let mut buf = [0u8; 256];
+    let mut out = textbuf::TextBuf::new(&mut buf);
+    let _ = writeln!(out, "\n[PANIC] {}", info);
+    kernel_print_raw(out.as_str());
     loop {}
 }
 
+pub mod errno;
 pub mod memory;
+pub mod heap;
+pub mod slab;
+pub mod shm;
+pub mod swap;
+pub mod usermem;
+pub mod strace;
+pub mod seccomp;
+pub mod uring;
+pub mod kinfo;
+pub mod textbuf;
 pub mod scheduler;
 pub mod syscall;
 pub mod module_registry;
+pub mod modsign;
+pub mod modparam;
 pub mod ai;
 pub mod font8x16;
+pub mod font;
+pub mod pixfmt;
+pub mod raster;
 pub mod framebuffer;
+pub mod vt;
+pub mod scrollback;
 pub mod console;
 pub mod mouse;
 pub mod gui;
 
+// Re-export kernel error codes
+pub use errno::{KernelError, KResult, kernel_strerror};
+
 // Re-export FFI functions from module_registry to ensure they're linked
 pub use module_registry::{
     module_registry_new,
     module_registry_register,
     module_registry_unregister,
+    module_registry_acquire,
+    module_registry_release,
+    module_registry_render_tree,
+    module_registry_resolve,
+    module_registry_symbol_next,
     module_registry_count,
     module_registry_get_name,
+    module_registry_start,
+    module_registry_record_fault,
+    module_registry_quarantine_suspect,
+    module_registry_param_get,
+    module_registry_param_set,
+    module_registry_render_params,
+    module_registry_next,
+    module_registry_render_proc,
+};
+
+// Re-export module signature policy FFI
+pub use modsign::{
+    ModManifest,
+    module_sig_set_policy,
+    module_sig_get_policy,
 };
 
 // Re-export Phase 6 scheduler functions for user process/sandbox support
@@ -55,6 +102,106 @@ pub use scheduler::{
     scheduler_revoke_capability,
     scheduler_has_capability,
     scheduler_get_user_memory_layout,
+    scheduler_set_user_region,
+    scheduler_set_parent,
+};
+
+// Re-export heap accounting / OOM FFI
+pub use heap::{
+    heap_set_grow_cb,
+    heap_set_oom_kill_cb,
+    heap_get_stats,
+    heap_get_tag_stats,
+    heap_set_tag,
+    heap_set_leak_tracking,
+    heap_render_info,
+    heap_render_leaks,
+};
+
+// Re-export slab cache FFI (object caches + /proc/slabinfo)
+pub use slab::{
+    slab_cache_create,
+    slab_cache_alloc,
+    slab_cache_free,
+    slab_cache_active,
+    slab_reap_all,
+    slab_render_info,
+};
+
+// Re-export shared memory FFI
+pub use shm::{
+    shm_create,
+    shm_open,
+    shm_size,
+    shm_map,
+    shm_unmap,
+    shm_close,
+    shm_unlink,
+    shm_fork,
+    shm_release_process,
+    shm_render_info,
+};
+
+// Re-export swap FFI
+pub use swap::{
+    SwapOps,
+    SwapStats,
+    swap_init,
+    swap_enabled,
+    swap_track,
+    swap_evict,
+    swap_is_swapped,
+    swap_in,
+    swap_untrack_range,
+    swap_release_process,
+    swap_get_stats,
+    swap_render_info,
+};
+
+// Re-export user-memory access FFI
+pub use usermem::{
+    UserMemOps,
+    UserRange,
+    usermem_init,
+    usermem_access_ok,
+    usermem_copy_from_user,
+    usermem_copy_to_user,
+    usermem_strncpy_from_user,
+};
+
+// Re-export syscall tracing FFI
+pub use strace::{
+    strace_attach,
+    strace_detach,
+    strace_set_filter,
+    strace_filter_all,
+    strace_set_ai_forward,
+    strace_record,
+    strace_render,
+    strace_clear,
+};
+
+// Re-export seccomp filter FFI
+pub use seccomp::{
+    SeccompRule,
+    seccomp_install,
+    seccomp_depth,
+};
+
+// Re-export submission/completion ring FFI
+pub use uring::{
+    UringSqe,
+    UringCqe,
+    uring_poll,
+    uring_release_process,
+    uring_render_info,
+};
+
+// Re-export kernel info page FFI
+pub use kinfo::{
+    KernelInfoPage,
+    kinfo_init,
+    kinfo_unix_time,
 };
 
 // Re-export ps/process-list FFI (Phase 7 CLI integration)
@@ -0,0 +1,258 @@
+// modparam.rs — Typed module parameters
+//
+// A v2 manifest (MOD_MANIFEST_MAGIC_V2, see modsign.rs) declares up to
+// MOD_MAX_PARAMS parameters after the common manifest fields. Each one is an
+// int, a bool or a short string bound to a variable in the module's code
+// section (by offset), with a default, an allowed range and the privilege
+// needed to read it and to change it at runtime.
+//
+// The registry keeps the current values and writes every change straight
+// into the module's variable, so module code just reads its own globals:
+// defaults and load-time assignments ("name=value ...") are stored before
+// init runs, and a runtime change is followed by a call to the manifest's
+// notify callback with the parameter's index as argument.
+//
+// Privileges use the CLI scale (include/cli.h): 0 root, 50 admin, 100 user;
+// a caller may access a parameter if its privilege is at most the one
+// declared.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::errno::{KResult, KernelError};
+
+pub const MOD_MAX_PARAMS: usize = 8;
+/// Bytes per parameter name, including the NUL.
+pub const MOD_PARAM_NAME_LEN: usize = 16;
+/// Bytes per string value, including the NUL.
+pub const MOD_PARAM_STR_LEN: usize = 16;
+
+// ModParamDecl.kind
+pub const MOD_PARAM_INT: u8 = 0;
+pub const MOD_PARAM_BOOL: u8 = 1;
+pub const MOD_PARAM_STR: u8 = 2;
+
+// ModParamDecl.flags
+/// Settable only at load time.
+pub const MOD_PARAM_RO: u8 = 1 << 0;
+
+/// No notify callback (ModParamTable.notify_off).
+pub const MOD_PARAM_NO_NOTIFY: u32 = 0xFFFF_FFFF;
+
+/// One declared parameter, shared with C (include/module.h) and
+/// ai/tools/modsign.py.
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy)]
+pub struct ModParamDecl {
+    /// NUL-padded; letters, digits and '_' only.
+    pub name:       [u8; MOD_PARAM_NAME_LEN],
+    pub kind:       u8,
+    pub flags:      u8,
+    pub read_priv:  u8,
+    pub write_priv: u8,
+    /// Offset of the variable in the code section: an i32 for int and bool,
+    /// MOD_PARAM_STR_LEN bytes for a string.
+    pub offset:     u32,
+    /// Inclusive int range; for a string, max is the longest allowed value
+    /// (0: up to MOD_PARAM_STR_LEN - 1).
+    pub min:        i32,
+    pub max:        i32,
+    /// Default for int and bool.
+    pub def:        i32,
+    pub def_str:    [u8; MOD_PARAM_STR_LEN],
+}
+
+const _: () = assert!(core::mem::size_of::<ModParamDecl>() == 52);
+
+/// Parameter section of a v2 manifest.
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy)]
+pub struct ModParamTable {
+    pub count:      u32,
+    /// Code offset of the notify callback, or MOD_PARAM_NO_NOTIFY.
+    pub notify_off: u32,
+    pub decls:      [ModParamDecl; MOD_MAX_PARAMS],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamValue {
+    Int(i32),
+    Bool(bool),
+    Str(String),
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamValue::Int(v) => write!(f, "{}", v),
+            ParamValue::Bool(v) => f.write_str(if *v { "true" } else { "false" }),
+            ParamValue::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+/// A declared parameter and its current value.
+#[derive(Debug, Clone)]
+pub struct ModParam {
+    pub decl:  ModParamDecl,
+    pub value: ParamValue,
+}
+
+impl ModParam {
+    pub fn name(&self) -> &str {
+        str_field(&self.decl.name)
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.decl.kind {
+            MOD_PARAM_INT => "int",
+            MOD_PARAM_BOOL => "bool",
+            _ => "string",
+        }
+    }
+
+    pub fn may_read(&self, privilege: u8) -> bool {
+        privilege <= self.decl.read_priv
+    }
+
+    /// Runtime write check: EACCES for a load-time-only parameter, EPERM if
+    /// the caller is not privileged enough.
+    pub fn check_write(&self, privilege: u8) -> KResult<()> {
+        if self.decl.flags & MOD_PARAM_RO != 0 {
+            return Err(KernelError::Access);
+        }
+        if privilege > self.decl.write_priv {
+            return Err(KernelError::Perm);
+        }
+        Ok(())
+    }
+
+    /// Parse text as a value of this parameter: ERANGE if it is outside the
+    /// declared range, EINVAL if it does not parse.
+    pub fn parse(&self, text: &str) -> KResult<ParamValue> {
+        let d = self.decl;
+        match d.kind {
+            MOD_PARAM_INT => {
+                let v = parse_int(text).ok_or(KernelError::Inval)?;
+                let (min, max) = (d.min, d.max);
+                if v < min || v > max {
+                    return Err(KernelError::Range);
+                }
+                Ok(ParamValue::Int(v))
+            }
+            MOD_PARAM_BOOL => match text {
+                "1" | "y" | "yes" | "on" | "true" => Ok(ParamValue::Bool(true)),
+                "0" | "n" | "no" | "off" | "false" => Ok(ParamValue::Bool(false)),
+                _ => Err(KernelError::Inval),
+            },
+            _ => {
+                if text.len() > str_max(&d) {
+                    return Err(KernelError::Range);
+                }
+                Ok(ParamValue::Str(String::from(text)))
+            }
+        }
+    }
+
+    /// Write the current value into the module's variable.
+    ///
+    /// # Safety
+    /// code must point to the module's code section, which decls() has
+    /// checked the offset against.
+    pub unsafe fn store(&self, code: *mut u8) {
+        let at = code.add(self.decl.offset as usize);
+        match &self.value {
+            ParamValue::Int(v) => core::ptr::write_unaligned(at as *mut i32, *v),
+            ParamValue::Bool(v) => core::ptr::write_unaligned(at as *mut i32, *v as i32),
+            ParamValue::Str(s) => {
+                let mut raw = [0u8; MOD_PARAM_STR_LEN];
+                raw[..s.len()].copy_from_slice(s.as_bytes());
+                core::ptr::copy_nonoverlapping(raw.as_ptr(), at, MOD_PARAM_STR_LEN);
+            }
+        }
+    }
+}
+
+// A NUL-padded field as text ("" if not UTF-8).
+fn str_field(raw: &[u8]) -> &str {
+    let len = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    core::str::from_utf8(&raw[..len]).unwrap_or("")
+}
+
+fn str_max(d: &ModParamDecl) -> usize {
+    let max = d.max;
+    if max <= 0 || max as usize >= MOD_PARAM_STR_LEN {
+        MOD_PARAM_STR_LEN - 1
+    } else {
+        max as usize
+    }
+}
+
+/// Decimal or 0x-prefixed hex, optionally negative.
+fn parse_int(text: &str) -> Option<i32> {
+    let (neg, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let v = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+    i32::try_from(if neg { -v } else { v }).ok()
+}
+
+/// Check a manifest's parameter table against a code section of code_size
+/// bytes and return the parameters at their defaults, with the notify
+/// callback's code offset (MOD_PARAM_NO_NOTIFY if none). EINVAL for a
+/// malformed declaration.
+pub fn decls(table: &ModParamTable, code_size: u32) -> KResult<(Vec<ModParam>, u32)> {
+    let count = table.count as usize;
+    if count > MOD_MAX_PARAMS {
+        return Err(KernelError::TooBig);
+    }
+    let notify = table.notify_off;
+    if notify != MOD_PARAM_NO_NOTIFY && notify >= code_size {
+        return Err(KernelError::Inval);
+    }
+    let decls = table.decls;
+    let mut params: Vec<ModParam> = Vec::new();
+    params.try_reserve(count).map_err(|_| KernelError::NoMem)?;
+    for d in &decls[..count] {
+        let name = str_field(&d.name);
+        if name.is_empty()
+            || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+            || params.iter().any(|p| p.name() == name) {
+            return Err(KernelError::Inval);
+        }
+        let size = match d.kind {
+            MOD_PARAM_INT | MOD_PARAM_BOOL => 4,
+            MOD_PARAM_STR => MOD_PARAM_STR_LEN as u32,
+            _ => return Err(KernelError::Inval),
+        };
+        if d.offset.checked_add(size).is_none_or(|end| end > code_size) {
+            return Err(KernelError::Inval);
+        }
+        let mut p = ModParam { decl: *d, value: ParamValue::Int(0) };
+        let mut text = [0u8; 12];
+        let mut t = crate::textbuf::TextBuf::new(&mut text);
+        let def = match d.kind {
+            MOD_PARAM_STR => str_field(&d.def_str),
+            _ => {
+                let v = d.def;
+                let _ = write!(t, "{}", v);
+                t.as_str()
+            }
+        };
+        p.value = p.parse(def).map_err(|_| KernelError::Inval)?;
+        params.push(p);
+    }
+    Ok((params, notify))
+}
+
+/// Split load-time assignments "name=value name=value ..." into pairs.
+/// Items without '=' are rejected with EINVAL.
+pub fn assignments(text: &str) -> impl Iterator<Item = KResult<(&str, &str)>> {
+    text.split_ascii_whitespace()
+        .map(|item| item.split_once('=').ok_or(KernelError::Inval))
+}
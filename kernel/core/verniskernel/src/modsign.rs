@@ -0,0 +1,450 @@
+// modsign.rs — Module manifest and signature verification
+//
+// A signed module carries a ModManifest after its code: name, version,
+// dependencies, the capabilities it needs and the SHA-256 of its image (the
+// module header and the code, so the function table, hook indices and
+// exports are covered as well as the code they point into), followed by an RSA-2048 PKCS#1 v1.5 signature over the SHA-256 of all the
+// manifest bytes before the signature. The public key is compiled in below
+// (regenerate with `ai/tools/modsign.py genkey`; the private half never
+// enters the tree). Modules are signed with `ai/tools/modsign.py sign`.
+//
+// A v2 manifest (MOD_MANIFEST_MAGIC_V2) adds the module's parameter
+// declarations (modparam.rs) between the image hash and the signature, so
+// they are signed too.
+//
+// The registry calls check() before accepting a module. What a failure does
+// depends on the policy: Enforce (the default) rejects the load with EACCES,
+// Warn only reports it, Off skips verification. Every failure is reported as a MOD and
+// a DENY event to the AI bridge and engine, and recorded in the audit log.
+
+use core::ffi::{c_char, c_void};
+use core::fmt::Write;
+use core::ptr::addr_of_mut;
+
+use crate::errno::{KResult, KernelError};
+use crate::modparam::ModParamTable;
+use crate::module_registry::{MOD_MAX_DEPS, MOD_NAME_LEN};
+use crate::scheduler::Scheduler;
+use crate::textbuf::TextBuf;
+
+extern "C" {
+    fn sha256_hash(data: *const u8, len: usize, digest: *mut u8);
+    fn get_kernel_scheduler() -> *mut c_void;
+    fn kernel_current_pid() -> u32;
+    fn kernel_get_ticks() -> u32;
+    fn ai_send_event(event_type: *const c_char, data: *const c_char);
+    fn ai_kernel_engine_feed(event_type: *const c_char, data: *const c_char, now: u64);
+    fn auditlog_record(timestamp: u64, username: *const c_char, command: *const c_char,
+                       privilege: u8, required: u8);
+}
+
+/// "MANF", little-endian.
+pub const MOD_MANIFEST_MAGIC: u32 = 0x464E_414D;
+/// "MNF2", little-endian: manifest with parameter declarations.
+pub const MOD_MANIFEST_MAGIC_V2: u32 = 0x3246_4E4D;
+pub const MOD_SIG_LEN: usize = 256;
+const SHA256_LEN: usize = 32;
+/// SYS_MOD_LOAD, used as the syscall number in DENY events.
+const SYS_MOD_LOAD: u32 = 28;
+
+// Policies (include/module.h MOD_SIG_*)
+pub const MOD_SIG_OFF: u32 = 0;
+pub const MOD_SIG_WARN: u32 = 1;
+pub const MOD_SIG_ENFORCE: u32 = 2;
+
+/// Manifest appended after a module's code, shared with C (include/module.h)
+/// and ai/tools/modsign.py.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct ModManifest {
+    pub magic:       u32,
+    /// Module version, major << 16 | minor.
+    pub version:     u32,
+    /// Capabilities the loading process must hold (CAP_*).
+    pub caps:        u64,
+    pub name:        [u8; MOD_NAME_LEN],
+    pub dep_count:   u32,
+    pub deps:        [[u8; MOD_NAME_LEN]; MOD_MAX_DEPS],
+    /// SHA-256 of the module header and code section.
+    pub image_sha256: [u8; SHA256_LEN],
+    pub signature:   [u8; MOD_SIG_LEN],
+}
+
+pub const MOD_MANIFEST_LEN: usize = core::mem::size_of::<ModManifest>();
+const _: () = assert!(MOD_MANIFEST_LEN == 428);
+/// Bytes covered by the signature.
+const SIGNED_LEN: usize = MOD_MANIFEST_LEN - MOD_SIG_LEN;
+
+/// Version 2 manifest: the v1 fields, then the parameter table, then the
+/// signature over everything before it.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct ModManifestV2 {
+    pub magic:       u32,
+    pub version:     u32,
+    pub caps:        u64,
+    pub name:        [u8; MOD_NAME_LEN],
+    pub dep_count:   u32,
+    pub deps:        [[u8; MOD_NAME_LEN]; MOD_MAX_DEPS],
+    pub image_sha256: [u8; SHA256_LEN],
+    pub params:      ModParamTable,
+    pub signature:   [u8; MOD_SIG_LEN],
+}
+
+pub const MOD_MANIFEST_V2_LEN: usize = core::mem::size_of::<ModManifestV2>();
+const _: () = assert!(MOD_MANIFEST_V2_LEN == 852);
+
+// Enforce from boot: under Warn an unsigned module gets kernel code
+// execution before anyone has a chance to look at the report. `modsig warn`
+// (root only) relaxes it for development.
+static mut POLICY: u32 = MOD_SIG_ENFORCE;
+
+fn policy() -> u32 {
+    unsafe { *addr_of_mut!(POLICY) }
+}
+
+// =============================================================================
+// RSA-2048 verification (e = 65537)
+// =============================================================================
+
+const LIMBS: usize = MOD_SIG_LEN / 4;
+type Big = [u32; LIMBS];
+
+/// Module signing public key: RSA-2048 modulus, big-endian.
+static MODSIGN_PUBKEY: [u8; MOD_SIG_LEN] = [
+    0xa4, 0x14, 0x4e, 0x33, 0x1e, 0x9b, 0xd7, 0x3a, 0x8c, 0x04, 0x3e, 0x1d, 0xb2, 0xbc, 0xdb, 0x93,
+    0xc1, 0xdc, 0x45, 0xd0, 0xfa, 0xae, 0x25, 0x68, 0x96, 0x35, 0xe2, 0x47, 0xdf, 0x89, 0x18, 0x42,
+    0xad, 0x50, 0x3e, 0xf9, 0x0b, 0x9d, 0x0d, 0x34, 0x47, 0x0a, 0x77, 0x3a, 0xf8, 0xa2, 0x2a, 0xd2,
+    0xe5, 0x99, 0x43, 0x8c, 0x0e, 0x90, 0xbe, 0x88, 0xc5, 0x2f, 0x87, 0x80, 0x2a, 0x1f, 0x12, 0x5e,
+    0xeb, 0x87, 0x7d, 0xf0, 0xb9, 0x78, 0x72, 0x6d, 0xac, 0x4b, 0x19, 0x30, 0x5a, 0xd8, 0x92, 0x92,
+    0x38, 0xf9, 0x79, 0x00, 0xf9, 0x8b, 0x75, 0x56, 0xd6, 0xc3, 0x54, 0xf1, 0x21, 0xd3, 0x67, 0x9c,
+    0xfb, 0x92, 0x9a, 0x9c, 0xac, 0x39, 0x79, 0x39, 0x8b, 0xb3, 0x94, 0x04, 0x32, 0x47, 0x98, 0xfe,
+    0x3f, 0x25, 0x1b, 0xac, 0xbb, 0x2e, 0x4d, 0xbf, 0x11, 0x8f, 0xe4, 0x9f, 0xc2, 0x17, 0x85, 0xf1,
+    0x73, 0x0e, 0x96, 0x1b, 0xad, 0x3b, 0xee, 0x97, 0xd4, 0xde, 0x2a, 0x20, 0x20, 0xdc, 0x95, 0xc0,
+    0xb9, 0xc7, 0xc2, 0x97, 0x29, 0x58, 0x30, 0x63, 0x74, 0xf7, 0x2b, 0x1f, 0xb4, 0xd7, 0x89, 0x34,
+    0x9d, 0xf0, 0x02, 0xb5, 0x63, 0xad, 0x47, 0x13, 0x52, 0x1a, 0x87, 0xf5, 0xe7, 0xdd, 0x78, 0x46,
+    0x41, 0x14, 0xe3, 0x42, 0x7c, 0xab, 0x19, 0xcb, 0x88, 0x9a, 0x0f, 0xb2, 0xe3, 0x27, 0x98, 0x42,
+    0x45, 0x25, 0x07, 0x25, 0x02, 0x87, 0x46, 0x9c, 0xf1, 0xfa, 0xdf, 0xd6, 0xf8, 0x7f, 0x1d, 0x6d,
+    0x92, 0x4c, 0x47, 0xb9, 0xb9, 0x6d, 0xa8, 0x1e, 0xc1, 0xe2, 0x00, 0xc4, 0x88, 0x10, 0x58, 0x48,
+    0xec, 0xcd, 0x49, 0xa7, 0xcd, 0x64, 0xd0, 0x2c, 0xed, 0xf4, 0x35, 0x1e, 0xfa, 0x88, 0x1c, 0x27,
+    0x07, 0xb8, 0xdb, 0x03, 0x56, 0xf3, 0x1b, 0x47, 0xa6, 0xd7, 0xb7, 0xd6, 0xa3, 0x41, 0xc8, 0xa9,
+];
+
+// ASN.1 DigestInfo prefix for SHA-256 (PKCS#1 v1.5, RFC 8017 §9.2)
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01,
+    0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+
+fn big_from_be(bytes: &[u8; MOD_SIG_LEN]) -> Big {
+    let mut out = [0u32; LIMBS];
+    for (i, limb) in out.iter_mut().enumerate() {
+        let at = MOD_SIG_LEN - 4 * (i + 1);
+        *limb = u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    }
+    out
+}
+
+fn big_to_be(a: &Big) -> [u8; MOD_SIG_LEN] {
+    let mut out = [0u8; MOD_SIG_LEN];
+    for (i, limb) in a.iter().enumerate() {
+        let at = MOD_SIG_LEN - 4 * (i + 1);
+        out[at..at + 4].copy_from_slice(&limb.to_be_bytes());
+    }
+    out
+}
+
+fn big_ge(a: &Big, b: &Big) -> bool {
+    for i in (0..LIMBS).rev() {
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+    true
+}
+
+fn big_sub(a: &mut Big, b: &Big) {
+    let mut borrow = 0u64;
+    for i in 0..LIMBS {
+        let d = (a[i] as u64).wrapping_sub(b[i] as u64).wrapping_sub(borrow);
+        a[i] = d as u32;
+        borrow = (d >> 63) & 1;
+    }
+}
+
+// Montgomery product a * b * R^-1 mod n, with R = 2^(32 * LIMBS).
+fn mont_mul(a: &Big, b: &Big, n: &Big, n0: u32) -> Big {
+    let mut t = [0u32; LIMBS + 2];
+    for &bi in b.iter() {
+        let mut c = 0u64;
+        for j in 0..LIMBS {
+            let x = t[j] as u64 + a[j] as u64 * bi as u64 + c;
+            t[j] = x as u32;
+            c = x >> 32;
+        }
+        let x = t[LIMBS] as u64 + c;
+        t[LIMBS] = x as u32;
+        t[LIMBS + 1] = (x >> 32) as u32;
+
+        let m = t[0].wrapping_mul(n0);
+        let mut c = (t[0] as u64 + m as u64 * n[0] as u64) >> 32;
+        for j in 1..LIMBS {
+            let x = t[j] as u64 + m as u64 * n[j] as u64 + c;
+            t[j - 1] = x as u32;
+            c = x >> 32;
+        }
+        let x = t[LIMBS] as u64 + c;
+        t[LIMBS - 1] = x as u32;
+        t[LIMBS] = t[LIMBS + 1] + (x >> 32) as u32;
+        t[LIMBS + 1] = 0;
+    }
+    let mut out = [0u32; LIMBS];
+    out.copy_from_slice(&t[..LIMBS]);
+    if t[LIMBS] != 0 || big_ge(&out, n) {
+        big_sub(&mut out, n);
+    }
+    out
+}
+
+/// sig^65537 mod n.
+fn rsa_public(sig: &Big, n: &Big) -> Big {
+    // n0 = -n^-1 mod 2^32 (Newton iteration; n is odd)
+    let mut inv = 1u32;
+    for _ in 0..5 {
+        inv = inv.wrapping_mul(2u32.wrapping_sub(n[0].wrapping_mul(inv)));
+    }
+    let n0 = inv.wrapping_neg();
+
+    // R^2 mod n by doubling 1 up 2 * 32 * LIMBS times
+    let mut r2 = [0u32; LIMBS];
+    r2[0] = 1;
+    for _ in 0..2 * 32 * LIMBS {
+        let carry = r2[LIMBS - 1] >> 31;
+        for i in (1..LIMBS).rev() {
+            r2[i] = (r2[i] << 1) | (r2[i - 1] >> 31);
+        }
+        r2[0] <<= 1;
+        if carry != 0 || big_ge(&r2, n) {
+            big_sub(&mut r2, n);
+        }
+    }
+
+    let s = mont_mul(sig, &r2, n, n0);
+    let mut x = s;
+    for _ in 0..16 {
+        x = mont_mul(&x, &x, n, n0);
+    }
+    x = mont_mul(&x, &s, n, n0);
+    let mut one = [0u32; LIMBS];
+    one[0] = 1;
+    mont_mul(&x, &one, n, n0)
+}
+
+fn sha256(data: &[u8]) -> [u8; SHA256_LEN] {
+    let mut digest = [0u8; SHA256_LEN];
+    unsafe { sha256_hash(data.as_ptr(), data.len(), digest.as_mut_ptr()) };
+    digest
+}
+
+/// Check an RSA-2048 PKCS#1 v1.5 SHA-256 signature of msg against the
+/// compiled-in key.
+fn verify_signature(msg: &[u8], sig: &[u8; MOD_SIG_LEN]) -> bool {
+    let n = big_from_be(&MODSIGN_PUBKEY);
+    let s = big_from_be(sig);
+    if big_ge(&s, &n) {
+        return false;
+    }
+    let em = big_to_be(&rsa_public(&s, &n));
+
+    // 00 01 FF..FF 00 || DigestInfo || H
+    let tail = SHA256_DIGEST_INFO.len() + SHA256_LEN;
+    let pad_end = MOD_SIG_LEN - tail - 1;
+    let mut ok = em[0] == 0x00 && em[1] == 0x01 && em[pad_end] == 0x00;
+    ok &= em[2..pad_end].iter().all(|&b| b == 0xFF);
+    ok &= em[pad_end + 1..pad_end + 1 + SHA256_DIGEST_INFO.len()] == SHA256_DIGEST_INFO;
+    ok && em[MOD_SIG_LEN - SHA256_LEN..] == sha256(msg)
+}
+
+// =============================================================================
+// Manifest checks
+// =============================================================================
+
+/// What the loader knows about the module, to hold the manifest against.
+pub struct Candidate<'a> {
+    pub name: &'a str,
+    pub deps: &'a [&'a str],
+    /// Header and code section, as hashed by the signer.
+    pub image: &'a [u8],
+    /// Raw manifest bytes (everything after the code), if the image
+    /// carries one.
+    pub manifest: Option<&'a [u8]>,
+}
+
+/// Verified manifest contents kept by the registry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Verified {
+    pub signed: bool,
+    pub version: u32,
+    pub caps: u64,
+}
+
+fn name_eq(raw: &[u8; MOD_NAME_LEN], name: &str) -> bool {
+    let len = raw.iter().position(|&b| b == 0).unwrap_or(MOD_NAME_LEN);
+    &raw[..len] == name.as_bytes()
+}
+
+// The fields both manifest versions share (signature included, wherever it
+// is) and the number of bytes it signs.
+fn parse(raw: &[u8]) -> Result<(ModManifest, usize), &'static str> {
+    if raw.len() < 4 {
+        return Err("short manifest");
+    }
+    let (signed_len, len) = match u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) {
+        MOD_MANIFEST_MAGIC => (SIGNED_LEN, MOD_MANIFEST_LEN),
+        MOD_MANIFEST_MAGIC_V2 => (MOD_MANIFEST_V2_LEN - MOD_SIG_LEN, MOD_MANIFEST_V2_LEN),
+        _ => return Err("bad manifest magic"),
+    };
+    if raw.len() < len {
+        return Err("short manifest");
+    }
+    let mut m: ModManifest = unsafe { core::ptr::read_unaligned(raw.as_ptr() as *const ModManifest) };
+    m.signature.copy_from_slice(&raw[signed_len..len]);
+    Ok((m, signed_len))
+}
+
+/// The parameter table of a v2 manifest; None for any other manifest.
+pub fn param_table(raw: &[u8]) -> Option<ModParamTable> {
+    if raw.len() < MOD_MANIFEST_V2_LEN || raw[..4] != MOD_MANIFEST_MAGIC_V2.to_le_bytes() {
+        return None;
+    }
+    let m: ModManifestV2 = unsafe { core::ptr::read_unaligned(raw.as_ptr() as *const ModManifestV2) };
+    Some(m.params)
+}
+
+fn verify(c: &Candidate<'_>) -> Result<Verified, &'static str> {
+    let raw = c.manifest.ok_or("unsigned")?;
+    let (m, signed_len) = parse(raw)?;
+    if !verify_signature(&raw[..signed_len], &m.signature) {
+        return Err("bad signature");
+    }
+    if !name_eq(&m.name, c.name) {
+        return Err("name mismatch");
+    }
+    let dep_count = m.dep_count as usize;
+    let deps = m.deps;
+    if dep_count != c.deps.len()
+        || !deps[..dep_count.min(MOD_MAX_DEPS)].iter().all(|d| c.deps.iter().any(|n| name_eq(d, n))) {
+        return Err("dependency mismatch");
+    }
+    if m.image_sha256 != sha256(c.image) {
+        return Err("image hash mismatch");
+    }
+    let caps = m.caps;
+    if !loader_has_caps(caps) {
+        return Err("missing capabilities");
+    }
+    Ok(Verified { signed: true, version: m.version, caps })
+}
+
+/// Pid of the loading process: the task that is running, None before
+/// tasking starts (the kernel itself is loading).
+fn loader_pid() -> Option<usize> {
+    match unsafe { kernel_current_pid() } {
+        0 => None,
+        pid => Some(pid as usize),
+    }
+}
+
+fn loader_has_caps(caps: u64) -> bool {
+    let pid = match loader_pid() {
+        Some(pid) => pid,
+        None => return true,
+    };
+    let sched = match unsafe { (get_kernel_scheduler() as *mut Scheduler).as_ref() } {
+        Some(s) => s,
+        None => return false,
+    };
+    sched.processes.get(&pid).is_some_and(|p| p.has_capability(caps))
+}
+
+fn report_failure(name: &str, reason: &str) {
+    let pid = loader_pid();
+    let now = unsafe { kernel_get_ticks() } as u64;
+
+    let mut mod_raw = [0u8; 64];
+    let mut data = TextBuf::new(&mut mod_raw);
+    let _ = write!(data, "{}|sigfail|{}", name, reason);
+    data.finish_cstr();
+    let mut deny_raw = [0u8; 64];
+    let mut data = TextBuf::new(&mut deny_raw);
+    let _ = write!(data, "{}|{}|modsig:{}", pid.unwrap_or(0), SYS_MOD_LOAD, name);
+    data.finish_cstr();
+    let mut user_raw = [0u8; 16];
+    let mut user = TextBuf::new(&mut user_raw);
+    match pid {
+        Some(pid) => { let _ = write!(user, "pid {}", pid); }
+        None => { let _ = user.write_str("kernel"); }
+    }
+    user.finish_cstr();
+    let mut cmd_raw = [0u8; 48];
+    let mut cmd = TextBuf::new(&mut cmd_raw);
+    let _ = write!(cmd, "modload {}: {}", name, reason);
+    cmd.finish_cstr();
+
+    let evt_mod = c"MOD".as_ptr();
+    let evt_deny = c"DENY".as_ptr();
+    unsafe {
+        ai_send_event(evt_mod, mod_raw.as_ptr() as *const c_char);
+        ai_kernel_engine_feed(evt_mod, mod_raw.as_ptr() as *const c_char, now);
+        ai_send_event(evt_deny, deny_raw.as_ptr() as *const c_char);
+        ai_kernel_engine_feed(evt_deny, deny_raw.as_ptr() as *const c_char, now);
+        auditlog_record(now, user_raw.as_ptr() as *const c_char,
+                        cmd_raw.as_ptr() as *const c_char, 0, 0);
+    }
+    crate::kernel_print_raw("[modsign] ");
+    crate::kernel_print_raw(name);
+    crate::kernel_print_raw(": ");
+    crate::kernel_print_raw(reason);
+    crate::kernel_print_raw(if policy() == MOD_SIG_ENFORCE { " (rejected)\n" } else { " (allowed)\n" });
+}
+
+/// Apply the signature policy to a module about to be registered. Returns
+/// the verified manifest contents (default for an unverified module that
+/// the policy lets through) or EACCES under Enforce.
+pub fn check(c: &Candidate<'_>) -> KResult<Verified> {
+    let policy = policy();
+    if policy == MOD_SIG_OFF {
+        return Ok(Verified::default());
+    }
+    match verify(c) {
+        Ok(v) => Ok(v),
+        Err(reason) => {
+            report_failure(c.name, reason);
+            if policy == MOD_SIG_ENFORCE {
+                Err(KernelError::Access)
+            } else {
+                Ok(Verified::default())
+            }
+        }
+    }
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Set the signature policy (MOD_SIG_OFF / WARN / ENFORCE). Returns 0 or
+/// -EINVAL.
+#[no_mangle]
+pub extern "C" fn module_sig_set_policy(p: u32) -> i32 {
+    if p > MOD_SIG_ENFORCE {
+        return KernelError::Inval.neg();
+    }
+    unsafe { *addr_of_mut!(POLICY) = p };
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn module_sig_get_policy() -> u32 {
+    policy()
+}
@@ -1,17 +1,234 @@
 // module_registry.rs — Phase 5: Module Registry (Rust side)
 //
 // Mirrors the C slot table in a BTreeMap for structured queries.
-// C (module.c) is the source of truth; Rust is notified via FFI on load/unload.
+// C (module.c) owns the slots and runs module code; this registry decides
+// whether a load or unload may proceed:
+//
+//   - A module declares the modules it depends on by name. Every dependency
+//     must already be registered, so loads happen in dependency order and
+//     the graph can never contain a cycle.
+//   - Each module has a reference count of active callers (module_call holds
+//     one for the duration of the call).
+//   - An unload is refused with EBUSY while the module has dependents or
+//     references. With MOD_UNLOAD_CASCADE its dependents are unloaded first,
+//     provided none of them is referenced either.
+//
+// The registry also holds the global symbol table: every named export of
+// every module, keyed by name. Registering a module whose export clashes
+// with an existing symbol fails with EEXIST; unregistering drops its
+// symbols. module.c and dylib.c both resolve names through it. A symbol
+// flagged MOD_SYM_GPL resolves only for the kernel and for modules that
+// declare MOD_FLAG_GPL.
+//
+// Before anything is registered, the module's signed manifest is checked
+// against the signature policy (modsign.rs).
+//
+// Lifecycle: a module is registered Loading, and becomes Live once its init
+// hook (if any) returns success, or Failed if it does not. Unloading runs
+// the exit hook of Live modules before removal. The hooks are run by the
+// registry through module_run_hook (module.c), which contains a fault the
+// same way module_call does. Only Live modules can be called, resolved or
+// depended on.
+//
+// Modules with a v2 manifest declare typed parameters (modparam.rs). Their
+// values live here: defaults and the assignments given at load time are
+// applied at registration, before init, and runtime changes by
+// "module.param" name are checked against the caller's privilege and
+// announced to the module through its notify callback.
+//
+// Faults in module code are counted per module. When the AI engine's
+// bad-module pattern fires, the most recently faulted module is moved to
+// Quarantined: it stays loaded, but every call into it fails with EACCES
+// until it is unloaded.
 
 use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::ffi::c_char;
+use core::fmt::{self, Write};
 use core::ptr;
 
+use crate::errno::{self, KResult, KernelError};
+use crate::heap::{self, HeapTag};
+use crate::modparam::{self, ModParam, MOD_PARAM_NO_NOTIFY};
+use crate::modsign::{self, Candidate};
+use crate::textbuf::TextBuf;
+
+extern "C" {
+    fn kernel_get_ticks() -> u32;
+    // module.c: call the hook of module mid at addr with arg, containing a
+    // fault. Returns the hook's result, or -EFAULT if it faulted.
+    fn module_run_hook(mid: u32, addr: u32, arg: u32) -> i32;
+}
+
+/// Dependencies one module may declare (include/module.h).
+pub const MOD_MAX_DEPS: usize = 4;
+/// Bytes per name in the C dependency table, including the NUL.
+pub const MOD_NAME_LEN: usize = 24;
+
+/// module_registry_unregister flag: unload dependents first.
+pub const MOD_UNLOAD_CASCADE: u32 = 1 << 0;
+
+/// Module flag: GPL-compatible, may resolve GPL-only symbols.
+pub const MOD_FLAG_GPL: u32 = 1 << 0;
+/// Symbol flag: GPL-only export.
+pub const MOD_SYM_GPL: u16 = 1 << 0;
+/// Resolve requester meaning the kernel itself (no visibility limits).
+pub const MOD_RESOLVE_KERNEL: u32 = 0xFFFF_FFFF;
+
+/// A fault older than this (ticks) no longer makes a module the suspect of
+/// a bad-module alert; matches the default pattern window.
+pub const MOD_FAULT_WINDOW: u64 = 200;
+
 // =============================================================================
 // Data types
 // =============================================================================
 
+/// Lifecycle state, shared with C (include/module.h MOD_STATE_*).
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModState {
+    Loading     = 0,
+    Live        = 1,
+    Unloading   = 2,
+    Failed      = 3,
+    Quarantined = 4,
+}
+
+impl ModState {
+    pub fn name(self) -> &'static str {
+        match self {
+            ModState::Loading     => "loading",
+            ModState::Live        => "live",
+            ModState::Unloading   => "unloading",
+            ModState::Failed      => "failed",
+            ModState::Quarantined => "quarantined",
+        }
+    }
+
+    /// State column of /proc/modules (Linux spelling for the shared states).
+    fn proc_name(self) -> &'static str {
+        match self {
+            ModState::Loading     => "Loading",
+            ModState::Live        => "Live",
+            ModState::Unloading   => "Unloading",
+            ModState::Failed      => "Failed",
+            ModState::Quarantined => "Quarantined",
+        }
+    }
+
+    /// Why a module in this state cannot be used.
+    fn error(self) -> KernelError {
+        match self {
+            ModState::Loading     => KernelError::Again,
+            ModState::Live | ModState::Unloading => KernelError::Busy,
+            ModState::Failed      => KernelError::Io,
+            ModState::Quarantined => KernelError::Access,
+        }
+    }
+}
+
+/// One exported symbol, shared with C (include/module.h).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ModSymbol {
+    /// NUL-padded name.
+    pub name:    [u8; MOD_NAME_LEN],
+    pub mid:     u32,
+    pub fn_id:   u32,
+    pub addr:    u32,
+    pub version: u16,
+    pub flags:   u16,
+}
+
+/// Snapshot of one module for introspection, shared with C
+/// (include/module.h ModRecord).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ModRecord {
+    pub mid:       u32,
+    /// ModState as u32.
+    pub state:     u32,
+    /// NUL-padded, truncated to fit.
+    pub name:      [u8; MOD_NAME_LEN],
+    pub base_addr: u32,
+    pub code_size: u32,
+    pub fn_count:  u32,
+    pub refcount:  u32,
+    pub faults:    u32,
+    pub reserved:  u32,
+    /// Tick of registration.
+    pub loaded_at: u64,
+    /// Calls made into the module (module_call).
+    pub calls:     u64,
+}
+
+const _: () = assert!(core::mem::size_of::<ModRecord>() == 72);
+
+impl ModSymbol {
+    pub fn name(&self) -> &str {
+        c_name(&self.name).unwrap_or("?")
+    }
+}
+
+/// Registration request built by module.c (include/module.h).
+#[repr(C)]
+pub struct ModuleDesc {
+    pub mid:       u32,
+    pub name:      *const c_char,
+    pub base_addr: u32,
+    pub code_size: u32,
+    pub fn_count:  u32,
+    pub flags:     u32,
+    /// dep_count consecutive MOD_NAME_LEN-byte, NUL-padded names.
+    pub deps:      *const u8,
+    pub dep_count: u32,
+    pub syms:      *const ModSymbol,
+    pub sym_count: u32,
+    /// Code section (code_size bytes).
+    pub code:      *const u8,
+    /// Header and code section (image_len bytes), hashed for the manifest
+    /// check.
+    pub image:     *const u8,
+    pub image_len: u32,
+    /// Manifest following the code (manifest_len bytes), or null if the
+    /// image has none.
+    pub manifest:  *const u8,
+    pub manifest_len: u32,
+    /// Absolute addresses of the init and exit hooks, 0 for none.
+    pub init_fn:   u32,
+    pub exit_fn:   u32,
+    /// Load-time parameter assignments ("name=value ..."), or null.
+    pub params:    *const c_char,
+}
+
+/// A module to register, after FFI decoding.
+pub struct NewModule<'a> {
+    pub mid:       u32,
+    pub name:      String,
+    pub base_addr: u32,
+    pub code_size: u32,
+    pub fn_count:  u32,
+    pub flags:     u32,
+    pub deps:      &'a [&'a str],
+    pub syms:      &'a [ModSymbol],
+    pub code:      &'a [u8],
+    pub image:     &'a [u8],
+    pub manifest:  Option<&'a [u8]>,
+    pub init_fn:   u32,
+    pub exit_fn:   u32,
+    pub params:    &'a str,
+}
+
+// A NUL-padded C name as text; None if empty or not UTF-8.
+fn c_name(raw: &[u8]) -> Option<&str> {
+    let len = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    match core::str::from_utf8(&raw[..len]) {
+        Ok(s) if !s.is_empty() => Some(s),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ModuleEntry {
     pub mid:       u32,
@@ -19,27 +236,321 @@ pub struct ModuleEntry {
     pub base_addr: u32,
     pub code_size: u32,
     pub fn_count:  u32,
+    pub flags:     u32,
+    /// Passed signature verification; version and caps come from the manifest.
+    pub signed:    bool,
+    pub version:   u32,
+    pub caps:      u64,
+    /// Modules this one calls into, resolved to mids at registration.
+    pub deps:      Vec<u32>,
+    /// Active callers.
+    pub refcount:  u32,
+    pub state:     ModState,
+    /// Tick of the last state change.
+    pub since:     u64,
+    pub loaded_at: u64,
+    pub init_fn:   u32,
+    pub exit_fn:   u32,
+    /// Faults contained in calls into this module, and the tick of the last.
+    pub faults:    u32,
+    pub last_fault: u64,
+    /// Calls taken through acquire().
+    pub calls:     u64,
+    /// Address of the code section, where parameter variables live.
+    pub code_addr: u32,
+    /// Declared parameters with their current values, and the notify
+    /// callback's address (0 for none).
+    pub params:    Vec<ModParam>,
+    pub notify_fn: u32,
 }
 
 pub struct ModuleRegistry {
     entries: BTreeMap<u32, ModuleEntry>,
+    symbols: BTreeMap<String, ModSymbol>,
 }
 
 impl ModuleRegistry {
     pub fn new() -> Self {
         ModuleRegistry {
             entries: BTreeMap::new(),
+            symbols: BTreeMap::new(),
         }
     }
 
-    pub fn register(&mut self, mid: u32, name: String,
-                    base_addr: u32, code_size: u32, fn_count: u32) -> bool {
-        self.entries.insert(mid, ModuleEntry { mid, name, base_addr, code_size, fn_count });
-        true
+    pub fn find(&self, name: &str) -> Option<u32> {
+        self.entries.values().find(|e| e.name == name).map(|e| e.mid)
+    }
+
+    /// Register a module in the Loading state, with its parameters set to
+    /// their defaults and then to the load-time assignments. Fails with
+    /// ENOENT if a dependency is not loaded (or not Live) or an assigned
+    /// parameter is not declared, with EINVAL or ERANGE for a bad value,
+    /// with EEXIST if one of its exports is already defined and with EACCES
+    /// if the signature policy rejects it.
+    pub fn register(&mut self, m: NewModule<'_>) -> KResult<()> {
+        if self.entries.contains_key(&m.mid) {
+            return Err(KernelError::Exists);
+        }
+        if m.deps.len() > MOD_MAX_DEPS {
+            return Err(KernelError::TooBig);
+        }
+        let mut deps = Vec::new();
+        deps.try_reserve(m.deps.len()).map_err(|_| KernelError::NoMem)?;
+        for dep in m.deps {
+            let dmid = self.find(dep)
+                .filter(|d| self.entries.get(d).is_some_and(|e| e.state == ModState::Live))
+                .ok_or(KernelError::NoEntry)?;
+            if !deps.contains(&dmid) {
+                deps.push(dmid);
+            }
+        }
+        for (i, sym) in m.syms.iter().enumerate() {
+            let name = c_name(&sym.name).ok_or(KernelError::Inval)?;
+            if self.symbols.contains_key(name)
+                || m.syms[..i].iter().any(|o| c_name(&o.name) == Some(name)) {
+                return Err(KernelError::Exists);
+            }
+        }
+        let verified = modsign::check(&Candidate {
+            name: &m.name,
+            deps: m.deps,
+            image: m.image,
+            manifest: m.manifest,
+        })?;
+        let (mut params, notify_off) = match m.manifest.and_then(modsign::param_table) {
+            Some(table) => modparam::decls(&table, m.code.len() as u32)?,
+            None => (Vec::new(), MOD_PARAM_NO_NOTIFY),
+        };
+        for assignment in modparam::assignments(m.params) {
+            let (name, text) = assignment?;
+            let p = params.iter_mut().find(|p| p.name() == name).ok_or(KernelError::NoEntry)?;
+            p.value = p.parse(text)?;
+        }
+        let code_addr = m.code.as_ptr() as usize;
+        for p in &params {
+            unsafe { p.store(code_addr as *mut u8) };
+        }
+        let notify_fn = if notify_off == MOD_PARAM_NO_NOTIFY { 0 } else { code_addr as u32 + notify_off };
+        for sym in m.syms {
+            let mut sym = *sym;
+            sym.mid = m.mid;
+            self.symbols.insert(sym.name().to_string(), sym);
+        }
+        let now = unsafe { kernel_get_ticks() } as u64;
+        self.entries.insert(m.mid, ModuleEntry {
+            mid: m.mid, name: m.name, base_addr: m.base_addr, code_size: m.code_size,
+            fn_count: m.fn_count, flags: m.flags, signed: verified.signed,
+            version: verified.version, caps: verified.caps, deps, refcount: 0,
+            state: ModState::Loading, since: now, loaded_at: now,
+            init_fn: m.init_fn, exit_fn: m.exit_fn, faults: 0, last_fault: 0, calls: 0,
+            code_addr: code_addr as u32, params, notify_fn,
+        });
+        Ok(())
+    }
+
+    fn set_state(&mut self, mid: u32, state: ModState) {
+        if let Some(e) = self.entries.get_mut(&mid) {
+            e.state = state;
+            e.since = unsafe { kernel_get_ticks() } as u64;
+        }
+    }
+
+    /// The init hook to run for a Loading module (0 if it has none).
+    pub fn init_hook(&self, mid: u32) -> KResult<u32> {
+        let e = self.entries.get(&mid).ok_or(KernelError::NoEntry)?;
+        if e.state != ModState::Loading {
+            return Err(e.state.error());
+        }
+        Ok(e.init_fn)
+    }
+
+    /// Record the init hook's result: Live on success, Failed otherwise.
+    /// A failure is returned as its errno, or EIO if it is not one.
+    pub fn finish_init(&mut self, mid: u32, rc: i32) -> KResult<()> {
+        if rc >= 0 {
+            self.set_state(mid, ModState::Live);
+            return Ok(());
+        }
+        self.set_state(mid, ModState::Failed);
+        Err(KernelError::from_errno(rc).unwrap_or(KernelError::Io))
+    }
+
+    /// Charge a fault in module code to mid.
+    pub fn record_fault(&mut self, mid: u32, now: u64) {
+        if let Some(e) = self.entries.get_mut(&mid) {
+            e.faults = e.faults.saturating_add(1);
+            e.last_fault = now;
+        }
+    }
+
+    /// Quarantine the Live module that faulted most recently, if that was
+    /// within MOD_FAULT_WINDOW of now. Returns its mid.
+    pub fn quarantine_suspect(&mut self, now: u64) -> Option<u32> {
+        let mid = self.entries.values()
+            .filter(|e| e.state == ModState::Live && e.faults > 0
+                        && now.saturating_sub(e.last_fault) <= MOD_FAULT_WINDOW)
+            .max_by_key(|e| e.last_fault)
+            .map(|e| e.mid)?;
+        self.set_state(mid, ModState::Quarantined);
+        Some(mid)
+    }
+
+    /// Look up an exported symbol on behalf of requester (a mid, or
+    /// MOD_RESOLVE_KERNEL). GPL-only symbols are refused with EPERM to
+    /// modules that are not GPL-compatible.
+    pub fn resolve(&self, name: &str, requester: u32) -> KResult<&ModSymbol> {
+        let sym = self.symbols.get(name).ok_or(KernelError::NoEntry)?;
+        if let Some(owner) = self.entries.get(&sym.mid) {
+            if owner.state != ModState::Live {
+                return Err(owner.state.error());
+            }
+        }
+        if sym.flags & MOD_SYM_GPL != 0 && requester != MOD_RESOLVE_KERNEL {
+            let gpl = self.entries.get(&requester).is_some_and(|e| e.flags & MOD_FLAG_GPL != 0);
+            if !gpl {
+                return Err(KernelError::Perm);
+            }
+        }
+        Ok(sym)
+    }
+
+    // "module.param" → (mid, parameter index).
+    fn find_param(&self, path: &str) -> KResult<(u32, usize)> {
+        let (module, param) = path.rsplit_once('.').ok_or(KernelError::Inval)?;
+        let mid = self.find(module).ok_or(KernelError::NoEntry)?;
+        let e = &self.entries[&mid];
+        let idx = e.params.iter().position(|p| p.name() == param).ok_or(KernelError::NoEntry)?;
+        Ok((mid, idx))
+    }
+
+    /// Write the value of "module.param" to out. EPERM if privilege is not
+    /// enough to read it.
+    pub fn param_get<W: Write>(&self, path: &str, privilege: u8, out: &mut W) -> KResult<()> {
+        let (mid, idx) = self.find_param(path)?;
+        let p = &self.entries[&mid].params[idx];
+        if !p.may_read(privilege) {
+            return Err(KernelError::Perm);
+        }
+        write!(out, "{}", p.value).map_err(|_| KernelError::Range)
+    }
+
+    /// Change "module.param" at runtime and store it in the module. The
+    /// module must be Live; EACCES for a load-time-only parameter, EPERM if
+    /// privilege is not enough. Returns (mid, notify callback, index) so the
+    /// caller can notify the module.
+    pub fn param_set(&mut self, path: &str, text: &str, privilege: u8) -> KResult<(u32, u32, u32)> {
+        let (mid, idx) = self.find_param(path)?;
+        let e = self.entries.get_mut(&mid).ok_or(KernelError::NoEntry)?;
+        if e.state != ModState::Live {
+            return Err(e.state.error());
+        }
+        let p = &mut e.params[idx];
+        p.check_write(privilege)?;
+        p.value = p.parse(text)?;
+        unsafe { p.store(e.code_addr as usize as *mut u8) };
+        Ok((mid, e.notify_fn, idx as u32))
+    }
+
+    /// Every parameter readable at privilege, one per line.
+    pub fn dump_params<W: Write>(&self, privilege: u8, out: &mut W) -> fmt::Result {
+        let mut any = false;
+        for e in self.entries.values() {
+            for p in e.params.iter().filter(|p| p.may_read(privilege)) {
+                write!(out, "{}.{} = {} ({}", e.name, p.name(), p.value, p.kind_name())?;
+                if p.decl.kind == modparam::MOD_PARAM_INT {
+                    let (min, max) = (p.decl.min, p.decl.max);
+                    write!(out, " {}..{}", min, max)?;
+                }
+                let ro = p.decl.flags & modparam::MOD_PARAM_RO != 0;
+                writeln!(out, ", {})", if ro { "ro" } else { "rw" })?;
+                any = true;
+            }
+        }
+        if !any {
+            writeln!(out, "(no module parameters)")?;
+        }
+        Ok(())
+    }
+
+    /// The index-th symbol in name order.
+    pub fn symbol_at(&self, index: usize) -> Option<&ModSymbol> {
+        self.symbols.values().nth(index)
+    }
+
+    /// Modules that declared a dependency on mid.
+    pub fn dependents(&self, mid: u32) -> impl Iterator<Item = u32> + '_ {
+        self.entries.values().filter(move |e| e.deps.contains(&mid)).map(|e| e.mid)
+    }
+
+    /// Take a reference on mid (an active caller). Only Live modules can be
+    /// called: EAGAIN while loading, EIO if init failed, EACCES if
+    /// quarantined.
+    pub fn acquire(&mut self, mid: u32) -> KResult<()> {
+        let e = self.entries.get_mut(&mid).ok_or(KernelError::NoEntry)?;
+        if e.state != ModState::Live {
+            return Err(e.state.error());
+        }
+        e.refcount = e.refcount.saturating_add(1);
+        e.calls = e.calls.wrapping_add(1);
+        Ok(())
+    }
+
+    pub fn release(&mut self, mid: u32) {
+        if let Some(e) = self.entries.get_mut(&mid) {
+            e.refcount = e.refcount.saturating_sub(1);
+        }
+    }
+
+    // Post-order walk over dependents: every module appears after the ones
+    // that depend on it.
+    fn collect_unload(&self, mid: u32, out: &mut Vec<u32>) -> KResult<()> {
+        if out.contains(&mid) {
+            return Ok(());
+        }
+        for d in self.dependents(mid) {
+            self.collect_unload(d, out)?;
+        }
+        out.try_reserve(1).map_err(|_| KernelError::NoMem)?;
+        out.push(mid);
+        Ok(())
+    }
+
+    /// Start unloading mid (and, with MOD_UNLOAD_CASCADE, everything
+    /// depending on it): every module in the set moves to Unloading. Returns
+    /// the set in unload order, dependents first, each with the exit hook to
+    /// run (0 unless it was Live). Nothing changes unless the whole set can
+    /// go; finish with remove().
+    pub fn begin_unload(&mut self, mid: u32, flags: u32) -> KResult<Vec<(u32, u32)>> {
+        if !self.entries.contains_key(&mid) {
+            return Err(KernelError::NoEntry);
+        }
+        if flags & MOD_UNLOAD_CASCADE == 0 && self.dependents(mid).next().is_some() {
+            return Err(KernelError::Busy);
+        }
+        let mut order = Vec::new();
+        self.collect_unload(mid, &mut order)?;
+        if order.iter().any(|m| self.entries.get(m).is_some_and(|e| {
+            e.refcount > 0 || matches!(e.state, ModState::Loading | ModState::Unloading)
+        })) {
+            return Err(KernelError::Busy);
+        }
+        let mut hooks = Vec::new();
+        hooks.try_reserve(order.len()).map_err(|_| KernelError::NoMem)?;
+        for &m in &order {
+            let exit = self.entries.get(&m)
+                .map_or(0, |e| if e.state == ModState::Live { e.exit_fn } else { 0 });
+            hooks.push((m, exit));
+            self.set_state(m, ModState::Unloading);
+        }
+        Ok(hooks)
     }
 
-    pub fn unregister(&mut self, mid: u32) -> bool {
-        self.entries.remove(&mid).is_some()
+    /// Drop modules whose unload has begun, and their symbols.
+    pub fn remove(&mut self, mids: &[u32]) {
+        for m in mids {
+            self.entries.remove(m);
+        }
+        self.symbols.retain(|_, s| !mids.contains(&s.mid));
     }
 
     pub fn get(&self, mid: u32) -> Option<&ModuleEntry> {
@@ -49,10 +560,85 @@ impl ModuleRegistry {
     pub fn count(&self) -> u32 {
         self.entries.len() as u32
     }
+
+    /// The first module with mid >= from, as a record.
+    pub fn record_from(&self, from: u32) -> Option<ModRecord> {
+        let (_, e) = self.entries.range(from..).next()?;
+        let mut name = [0u8; MOD_NAME_LEN];
+        let n = e.name.len().min(MOD_NAME_LEN - 1);
+        name[..n].copy_from_slice(&e.name.as_bytes()[..n]);
+        Some(ModRecord {
+            mid: e.mid,
+            state: e.state as u32,
+            name,
+            base_addr: e.base_addr,
+            code_size: e.code_size,
+            fn_count: e.fn_count,
+            refcount: e.refcount,
+            faults: e.faults,
+            reserved: 0,
+            loaded_at: e.loaded_at,
+            calls: e.calls,
+        })
+    }
+
+    /// /proc/modules: one line per module in the Linux layout
+    /// "name size refcount users state base", where users lists the modules
+    /// depending on it ("-" if none), each followed by a comma.
+    pub fn dump_proc<W: Write>(&self, out: &mut W) -> fmt::Result {
+        for e in self.entries.values() {
+            write!(out, "{} {} {} ", e.name, e.code_size, e.refcount)?;
+            let mut users = 0;
+            for d in self.dependents(e.mid) {
+                if let Some(u) = self.entries.get(&d) {
+                    write!(out, "{},", u.name)?;
+                    users += 1;
+                }
+            }
+            if users == 0 {
+                out.write_char('-')?;
+            }
+            writeln!(out, " {} {:#010x}", e.state.proc_name(), e.base_addr)?;
+        }
+        Ok(())
+    }
+
+    fn dump_node<W: Write>(&self, out: &mut W, mid: u32, depth: usize) -> fmt::Result {
+        let e = match self.entries.get(&mid) {
+            Some(e) => e,
+            None => return Ok(()),
+        };
+        for _ in 0..depth {
+            out.write_str("  ")?;
+        }
+        let users = self.dependents(mid).count();
+        writeln!(out, "{}{} [mid {}] {} since={} refs={} used_by={} faults={} base={:#x} size={}{}",
+                 if depth > 0 { "`- " } else { "" }, e.name, e.mid, e.state.name(), e.since,
+                 e.refcount, users, e.faults, e.base_addr, e.code_size,
+                 if e.signed { "" } else { " (unsigned)" })?;
+        for d in self.dependents(mid) {
+            self.dump_node(out, d, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Dependency tree for lsmod: each module with no dependencies as a
+    /// root, and under every module the modules that depend on it.
+    pub fn dump_tree<W: Write>(&self, out: &mut W) -> fmt::Result {
+        if self.entries.is_empty() {
+            return writeln!(out, "(no modules loaded)");
+        }
+        for e in self.entries.values().filter(|e| e.deps.is_empty()) {
+            self.dump_node(out, e.mid, 0)?;
+        }
+        Ok(())
+    }
 }
 
 // =============================================================================
 // FFI exports — called from module.c
+//
+// Status returns are 0 or -errno (include/kerrno.h).
 // =============================================================================
 
 #[no_mangle]
@@ -61,35 +647,360 @@ pub extern "C" fn module_registry_new() -> *mut ModuleRegistry {
     Box::into_raw(Box::new(ModuleRegistry::new()))
 }
 
+// Register the module described by desc. Returns 0 or -errno: -ENOENT for a
+// missing dependency or unknown parameter, -EEXIST for a mid or symbol
+// already registered, -EINVAL / -ERANGE for a bad parameter value.
 #[no_mangle]
 pub extern "C" fn module_registry_register(
-    reg:       *mut ModuleRegistry,
-    mid:       u32,
-    name_ptr:  *const c_char,
-    base_addr: u32,
-    code_size: u32,
-    fn_count:  u32,
+    reg:  *mut ModuleRegistry,
+    desc: *const ModuleDesc,
 ) -> i32 {
-    if reg.is_null() || name_ptr.is_null() { return -1; }
+    if reg.is_null() || desc.is_null() { return KernelError::Inval.neg(); }
     let reg = unsafe { &mut *reg };
+    let desc = unsafe { &*desc };
+    if desc.name.is_null() { return KernelError::Inval.neg(); }
+    if desc.dep_count as usize > MOD_MAX_DEPS { return KernelError::TooBig.neg(); }
+    if (desc.dep_count > 0 && desc.deps.is_null())
+        || (desc.sym_count > 0 && desc.syms.is_null()) {
+        return KernelError::Inval.neg();
+    }
+    let _tag = heap::tag_scope(HeapTag::Module);
 
     let name = unsafe {
-        let c_str = core::ffi::CStr::from_ptr(name_ptr);
+        let c_str = core::ffi::CStr::from_ptr(desc.name);
         match c_str.to_str() {
             Ok(s)  => s.to_string(),
             Err(_) => String::from("?"),
         }
     };
 
-    reg.register(mid, name, base_addr, code_size, fn_count);
+    let mut dep_names: [&str; MOD_MAX_DEPS] = [""; MOD_MAX_DEPS];
+    for (i, slot) in dep_names.iter_mut().enumerate().take(desc.dep_count as usize) {
+        let raw = unsafe {
+            core::slice::from_raw_parts(desc.deps.add(i * MOD_NAME_LEN), MOD_NAME_LEN)
+        };
+        *slot = match c_name(raw) {
+            Some(s) => s,
+            None => return KernelError::Inval.neg(),
+        };
+    }
+    let syms: &[ModSymbol] = if desc.sym_count == 0 {
+        &[]
+    } else {
+        unsafe { core::slice::from_raw_parts(desc.syms, desc.sym_count as usize) }
+    };
+    let code: &[u8] = if desc.code.is_null() {
+        &[]
+    } else {
+        unsafe { core::slice::from_raw_parts(desc.code, desc.code_size as usize) }
+    };
+    let image: &[u8] = if desc.image.is_null() {
+        &[]
+    } else {
+        unsafe { core::slice::from_raw_parts(desc.image, desc.image_len as usize) }
+    };
+    let manifest = if desc.manifest.is_null() {
+        None
+    } else {
+        Some(unsafe { core::slice::from_raw_parts(desc.manifest, desc.manifest_len as usize) })
+    };
+    let params = if desc.params.is_null() {
+        ""
+    } else {
+        match unsafe { core::ffi::CStr::from_ptr(desc.params) }.to_str() {
+            Ok(s) => s,
+            Err(_) => return KernelError::Inval.neg(),
+        }
+    };
+
+    errno::to_c(reg.register(NewModule {
+        mid:       desc.mid,
+        name,
+        base_addr: desc.base_addr,
+        code_size: desc.code_size,
+        fn_count:  desc.fn_count,
+        flags:     desc.flags,
+        deps:      &dep_names[..desc.dep_count as usize],
+        syms,
+        code,
+        image,
+        manifest,
+        init_fn:   desc.init_fn,
+        exit_fn:   desc.exit_fn,
+        params,
+    }))
+}
+
+// Run the init hook of a newly registered module and make it Live, or
+// Failed if the hook returns a negative value or faults. Returns 0 or the
+// hook's -errno (-EIO if it returned something else negative).
+#[no_mangle]
+pub extern "C" fn module_registry_start(reg: *mut ModuleRegistry, mid: u32) -> i32 {
+    if reg.is_null() { return KernelError::Inval.neg(); }
+    // No borrow of the registry is held while module code runs: the hook
+    // may call back into it (module_call, a fault being recorded).
+    let init = match unsafe { (*reg).init_hook(mid) } {
+        Ok(addr) => addr,
+        Err(e) => return e.neg(),
+    };
+    let rc = if init != 0 { unsafe { module_run_hook(mid, init, mid) } } else { 0 };
+    errno::to_c(unsafe { (*reg).finish_init(mid, rc) })
+}
+
+// Unregister mid. Refused with -EBUSY while it has references, or dependents
+// unless flags has MOD_UNLOAD_CASCADE. The exit hook of each Live module is
+// run before it is removed. The removed mids, dependents first, are written
+// to removed (up to max; may be null) so C can free their slots. Returns the
+// number removed or -errno.
+#[no_mangle]
+pub extern "C" fn module_registry_unregister(
+    reg:     *mut ModuleRegistry,
+    mid:     u32,
+    flags:   u32,
+    removed: *mut u32,
+    max:     u32,
+) -> i32 {
+    if reg.is_null() { return KernelError::Inval.neg(); }
+    let _tag = heap::tag_scope(HeapTag::Module);
+    let hooks = match unsafe { (*reg).begin_unload(mid, flags) } {
+        Ok(h) => h,
+        Err(e) => return e.neg(),
+    };
+    let mut order = Vec::new();
+    if order.try_reserve(hooks.len()).is_err() {
+        return KernelError::NoMem.neg();
+    }
+    // Exit hooks run without a registry borrow held, as in start. Their
+    // result cannot stop the unload.
+    for &(m, exit) in &hooks {
+        if exit != 0 {
+            let _ = unsafe { module_run_hook(m, exit, m) };
+        }
+        order.push(m);
+    }
+    unsafe { (*reg).remove(&order) };
+    if !removed.is_null() {
+        for (i, m) in order.iter().take(max as usize).enumerate() {
+            unsafe { *removed.add(i) = *m; }
+        }
+    }
+    order.len() as i32
+}
+
+// Read parameter "module.param" as text into buf (NUL-terminated) on behalf
+// of a caller at privilege (0 root .. 100 user). Returns the length, or
+// -ENOENT, -EPERM.
+#[no_mangle]
+pub extern "C" fn module_registry_param_get(
+    reg:       *const ModuleRegistry,
+    path:      *const c_char,
+    privilege: u8,
+    buf:       *mut u8,
+    buf_len:   u32,
+) -> i32 {
+    if reg.is_null() || path.is_null() { return KernelError::Inval.neg(); }
+    let reg = unsafe { &*reg };
+    let path = match unsafe { core::ffi::CStr::from_ptr(path) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return KernelError::Inval.neg(),
+    };
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return KernelError::Inval.neg(),
+    };
+    match reg.param_get(path, privilege, &mut out) {
+        Ok(()) => out.finish_cstr() as i32,
+        Err(e) => e.neg(),
+    }
+}
+
+// Set parameter "module.param" from text on behalf of a caller at privilege,
+// then call the module's notify callback with the parameter's index.
+// Returns 0 or -errno (-EACCES load-time only, -EPERM not privileged,
+// -EINVAL / -ERANGE bad value).
+#[no_mangle]
+pub extern "C" fn module_registry_param_set(
+    reg:       *mut ModuleRegistry,
+    path:      *const c_char,
+    value:     *const c_char,
+    privilege: u8,
+) -> i32 {
+    if reg.is_null() || path.is_null() || value.is_null() { return KernelError::Inval.neg(); }
+    let (path, value) = match unsafe {
+        (core::ffi::CStr::from_ptr(path).to_str(), core::ffi::CStr::from_ptr(value).to_str())
+    } {
+        (Ok(p), Ok(v)) => (p, v),
+        _ => return KernelError::Inval.neg(),
+    };
+    let _tag = heap::tag_scope(HeapTag::Module);
+    let (mid, notify, idx) = match unsafe { (*reg).param_set(path, value, privilege) } {
+        Ok(r) => r,
+        Err(e) => return e.neg(),
+    };
+    // As with the lifecycle hooks, no registry borrow is held here
+    if notify != 0 {
+        let _ = unsafe { module_run_hook(mid, notify, idx) };
+    }
     0
 }
 
+// Render every parameter readable at privilege into buf (NUL-terminated).
+// Returns length.
 #[no_mangle]
-pub extern "C" fn module_registry_unregister(reg: *mut ModuleRegistry, mid: u32) -> i32 {
-    if reg.is_null() { return -1; }
+pub extern "C" fn module_registry_render_params(
+    reg:       *const ModuleRegistry,
+    privilege: u8,
+    buf:       *mut u8,
+    buf_len:   u32,
+) -> u32 {
+    if reg.is_null() { return 0; }
+    let reg = unsafe { &*reg };
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = reg.dump_params(privilege, &mut out);
+    out.finish_cstr() as u32
+}
+
+// Charge a fault in module code to mid (called from the exception path).
+#[no_mangle]
+pub extern "C" fn module_registry_record_fault(reg: *mut ModuleRegistry, mid: u32, now: u64) {
+    if reg.is_null() { return; }
+    let reg = unsafe { &mut *reg };
+    reg.record_fault(mid, now);
+}
+
+// Quarantine the module that faulted most recently (within MOD_FAULT_WINDOW
+// of now). Returns its mid, or -ENOENT if no module is a suspect.
+#[no_mangle]
+pub extern "C" fn module_registry_quarantine_suspect(reg: *mut ModuleRegistry, now: u64) -> i32 {
+    if reg.is_null() { return KernelError::Inval.neg(); }
     let reg = unsafe { &mut *reg };
-    if reg.unregister(mid) { 0 } else { -1 }
+    match reg.quarantine_suspect(now) {
+        Some(mid) => mid as i32,
+        None => KernelError::NoEntry.neg(),
+    }
+}
+
+// Take / drop a caller reference on mid. acquire returns 0 or -ENOENT.
+#[no_mangle]
+pub extern "C" fn module_registry_acquire(reg: *mut ModuleRegistry, mid: u32) -> i32 {
+    if reg.is_null() { return KernelError::Inval.neg(); }
+    let reg = unsafe { &mut *reg };
+    errno::to_c(reg.acquire(mid))
+}
+
+#[no_mangle]
+pub extern "C" fn module_registry_release(reg: *mut ModuleRegistry, mid: u32) {
+    if reg.is_null() { return; }
+    let reg = unsafe { &mut *reg };
+    reg.release(mid);
+}
+
+// Resolve an exported symbol by name for requester (a mid or
+// MOD_RESOLVE_KERNEL) into *out. Returns 0, -ENOENT or -EPERM.
+#[no_mangle]
+pub extern "C" fn module_registry_resolve(
+    reg:       *const ModuleRegistry,
+    name:      *const c_char,
+    requester: u32,
+    out:       *mut ModSymbol,
+) -> i32 {
+    if reg.is_null() || name.is_null() || out.is_null() { return KernelError::Inval.neg(); }
+    let reg = unsafe { &*reg };
+    let name = match unsafe { core::ffi::CStr::from_ptr(name) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return KernelError::Inval.neg(),
+    };
+    match reg.resolve(name, requester) {
+        Ok(sym) => {
+            unsafe { *out = *sym; }
+            0
+        }
+        Err(e) => e.neg(),
+    }
+}
+
+// Symbol iteration: start with *cursor = 0; each call fills *out with the
+// next symbol (in name order) and advances *cursor. Returns 0, or -ENOENT
+// once the table is exhausted.
+#[no_mangle]
+pub extern "C" fn module_registry_symbol_next(
+    reg:    *const ModuleRegistry,
+    cursor: *mut u32,
+    out:    *mut ModSymbol,
+) -> i32 {
+    if reg.is_null() || cursor.is_null() || out.is_null() { return KernelError::Inval.neg(); }
+    let reg = unsafe { &*reg };
+    let index = unsafe { *cursor };
+    match reg.symbol_at(index as usize) {
+        Some(sym) => {
+            unsafe {
+                *out = *sym;
+                *cursor = index + 1;
+            }
+            0
+        }
+        None => KernelError::NoEntry.neg(),
+    }
+}
+
+// Module iteration: start with *cursor = 0; each call fills *out with the
+// next module (in mid order) and advances *cursor past it. Returns 0, or
+// -ENOENT once every module has been visited.
+#[no_mangle]
+pub extern "C" fn module_registry_next(
+    reg:    *const ModuleRegistry,
+    cursor: *mut u32,
+    out:    *mut ModRecord,
+) -> i32 {
+    if reg.is_null() || cursor.is_null() || out.is_null() { return KernelError::Inval.neg(); }
+    let reg = unsafe { &*reg };
+    match reg.record_from(unsafe { *cursor }) {
+        Some(rec) => {
+            unsafe {
+                *out = rec;
+                *cursor = rec.mid + 1;
+            }
+            0
+        }
+        None => KernelError::NoEntry.neg(),
+    }
+}
+
+// Render /proc/modules into buf (NUL-terminated). Returns length.
+#[no_mangle]
+pub extern "C" fn module_registry_render_proc(
+    reg:     *const ModuleRegistry,
+    buf:     *mut u8,
+    buf_len: u32,
+) -> u32 {
+    if reg.is_null() { return 0; }
+    let reg = unsafe { &*reg };
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = reg.dump_proc(&mut out);
+    out.finish_cstr() as u32
+}
+
+// Render the dependency tree into buf (NUL-terminated). Returns length.
+#[no_mangle]
+pub extern "C" fn module_registry_render_tree(
+    reg:     *const ModuleRegistry,
+    buf:     *mut u8,
+    buf_len: u32,
+) -> u32 {
+    if reg.is_null() { return 0; }
+    let reg = unsafe { &*reg };
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = reg.dump_tree(&mut out);
+    out.finish_cstr() as u32
 }
 
 #[no_mangle]
@@ -99,8 +1010,8 @@ pub extern "C" fn module_registry_count(reg: *const ModuleRegistry) -> u32 {
     reg.count()
 }
 
-// Fill name_buf (len bytes) with the name of module mid.
-// Returns 0 on success, -1 if not found or buf too small.
+// Fill name_buf (len bytes) with the name of module mid, truncating to fit.
+// Returns 0 on success, -ENOENT if not found, -EINVAL for a bad buffer.
 #[no_mangle]
 pub extern "C" fn module_registry_get_name(
     reg:      *const ModuleRegistry,
@@ -108,11 +1019,11 @@ pub extern "C" fn module_registry_get_name(
     name_buf: *mut u8,
     buf_len:  u32,
 ) -> i32 {
-    if reg.is_null() || name_buf.is_null() || buf_len == 0 { return -1; }
+    if reg.is_null() || name_buf.is_null() || buf_len == 0 { return KernelError::Inval.neg(); }
     let reg = unsafe { &*reg };
 
     match reg.get(mid) {
-        None => -1,
+        None => KernelError::NoEntry.neg(),
         Some(entry) => {
             let bytes = entry.name.as_bytes();
             let copy_len = if bytes.len() < (buf_len as usize - 1) {
@@ -0,0 +1,271 @@
+// pixfmt.rs — Framebuffer pixel formats
+//
+// A PixelFormat describes how a 0xRRGGBB color is stored in memory: bytes
+// per pixel and each channel's size and bit position, as reported by the
+// VBE mode info block (RedMaskSize/RedFieldPosition and so on). Pixels are
+// little-endian, 2, 3 or 4 bytes wide.
+//
+// The common layouts (XRGB8888, RGB888, RGB565, RGB555) get fast pack,
+// unpack and fill paths; anything else with channels of at most 8 bits goes
+// through the generic shift-and-mask path. The framebuffer, the compositor
+// back buffer and window content buffers all use the framebuffer's format,
+// so copies between them stay plain memcpy; convert_row handles the rest.
+
+use core::ptr;
+
+/// One color channel: `size` bits starting at bit `pos`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channel {
+    pub size: u8,
+    pub pos: u8,
+}
+
+impl Channel {
+    const fn new(size: u8, pos: u8) -> Self {
+        Channel { size, pos }
+    }
+
+    fn mask(self) -> u32 {
+        ((1u32 << self.size) - 1) << self.pos
+    }
+
+    // 8-bit value → channel bits in place.
+    fn pack(self, v: u32) -> u32 {
+        (v >> (8 - self.size)) << self.pos
+    }
+
+    // Channel bits → 8-bit value, scaled so full intensity stays 0xFF.
+    fn unpack(self, px: u32) -> u32 {
+        let max = (1u32 << self.size) - 1;
+        let v = (px >> self.pos) & max;
+        (v * 255 + max / 2) / max
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Xrgb8888,
+    Rgb888,
+    Rgb565,
+    Rgb555,
+    Generic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+    /// Color depth: 15, 16, 24 or 32.
+    pub bpp: u32,
+    /// Bytes per pixel: 2, 3 or 4.
+    pub bytes: u32,
+    pub red: Channel,
+    pub green: Channel,
+    pub blue: Channel,
+    layout: Layout,
+}
+
+impl PixelFormat {
+    /// 32bpp, blue in the low byte: the usual VBE/GOP direct-color mode.
+    pub const XRGB8888: PixelFormat = PixelFormat {
+        bpp: 32,
+        bytes: 4,
+        red: Channel::new(8, 16),
+        green: Channel::new(8, 8),
+        blue: Channel::new(8, 0),
+        layout: Layout::Xrgb8888,
+    };
+
+    /// A format from channel sizes and positions. None if the depth is not
+    /// 15/16/24/32, a channel is empty, wider than 8 bits or outside the
+    /// pixel, or two channels overlap.
+    pub fn new(bpp: u32, red: Channel, green: Channel, blue: Channel) -> Option<Self> {
+        let bytes = match bpp {
+            15 | 16 => 2,
+            24 => 3,
+            32 => 4,
+            _ => return None,
+        };
+        let mut used = 0u32;
+        for c in [red, green, blue] {
+            if c.size == 0 || c.size > 8 || c.pos as u32 + c.size as u32 > bytes * 8 {
+                return None;
+            }
+            if used & c.mask() != 0 {
+                return None;
+            }
+            used |= c.mask();
+        }
+        let rgb = |r: (u8, u8), g: (u8, u8), b: (u8, u8)| {
+            red == Channel::new(r.0, r.1) && green == Channel::new(g.0, g.1)
+                && blue == Channel::new(b.0, b.1)
+        };
+        let layout = if bytes == 4 && rgb((8, 16), (8, 8), (8, 0)) {
+            Layout::Xrgb8888
+        } else if bytes == 3 && rgb((8, 16), (8, 8), (8, 0)) {
+            Layout::Rgb888
+        } else if bytes == 2 && rgb((5, 11), (6, 5), (5, 0)) {
+            Layout::Rgb565
+        } else if bytes == 2 && rgb((5, 10), (5, 5), (5, 0)) {
+            Layout::Rgb555
+        } else {
+            Layout::Generic
+        };
+        Some(PixelFormat { bpp, bytes, red, green, blue, layout })
+    }
+
+    /// The conventional layout for a depth, for modes that report no masks.
+    pub fn from_bpp(bpp: u32) -> Option<Self> {
+        match bpp {
+            32 | 24 => Self::new(bpp, Channel::new(8, 16), Channel::new(8, 8), Channel::new(8, 0)),
+            16 => Self::new(16, Channel::new(5, 11), Channel::new(6, 5), Channel::new(5, 0)),
+            15 => Self::new(15, Channel::new(5, 10), Channel::new(5, 5), Channel::new(5, 0)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.layout {
+            Layout::Xrgb8888 => "XRGB8888",
+            Layout::Rgb888 => "RGB888",
+            Layout::Rgb565 => "RGB565",
+            Layout::Rgb555 => "RGB555",
+            Layout::Generic => "custom",
+        }
+    }
+
+    /// 0xRRGGBB → pixel value.
+    #[inline(always)]
+    pub fn pack(&self, rgb: u32) -> u32 {
+        match self.layout {
+            Layout::Xrgb8888 | Layout::Rgb888 => rgb & 0x00FF_FFFF,
+            Layout::Rgb565 => {
+                ((rgb >> 8) & 0xF800) | ((rgb >> 5) & 0x07E0) | ((rgb >> 3) & 0x001F)
+            }
+            Layout::Rgb555 => {
+                ((rgb >> 9) & 0x7C00) | ((rgb >> 6) & 0x03E0) | ((rgb >> 3) & 0x001F)
+            }
+            Layout::Generic => {
+                self.red.pack((rgb >> 16) & 0xFF)
+                    | self.green.pack((rgb >> 8) & 0xFF)
+                    | self.blue.pack(rgb & 0xFF)
+            }
+        }
+    }
+
+    /// Pixel value → 0xRRGGBB.
+    #[inline(always)]
+    pub fn unpack(&self, px: u32) -> u32 {
+        match self.layout {
+            Layout::Xrgb8888 | Layout::Rgb888 => px & 0x00FF_FFFF,
+            Layout::Rgb565 => {
+                let (r, g, b) = ((px >> 11) & 0x1F, (px >> 5) & 0x3F, px & 0x1F);
+                (((r << 3) | (r >> 2)) << 16) | (((g << 2) | (g >> 4)) << 8) | ((b << 3) | (b >> 2))
+            }
+            Layout::Rgb555 => {
+                let (r, g, b) = ((px >> 10) & 0x1F, (px >> 5) & 0x1F, px & 0x1F);
+                (((r << 3) | (r >> 2)) << 16) | (((g << 3) | (g >> 2)) << 8) | ((b << 3) | (b >> 2))
+            }
+            Layout::Generic => {
+                (self.red.unpack(px) << 16) | (self.green.unpack(px) << 8) | self.blue.unpack(px)
+            }
+        }
+    }
+
+    /// Store a pixel value at p.
+    #[inline(always)]
+    pub unsafe fn store(&self, p: *mut u8, px: u32) {
+        match self.bytes {
+            4 => ptr::write_unaligned(p as *mut u32, px),
+            2 => ptr::write_unaligned(p as *mut u16, px as u16),
+            _ => {
+                ptr::write(p, px as u8);
+                ptr::write(p.add(1), (px >> 8) as u8);
+                ptr::write(p.add(2), (px >> 16) as u8);
+            }
+        }
+    }
+
+    /// Load the pixel value at p.
+    #[inline(always)]
+    pub unsafe fn load(&self, p: *const u8) -> u32 {
+        match self.bytes {
+            4 => ptr::read_unaligned(p as *const u32),
+            2 => ptr::read_unaligned(p as *const u16) as u32,
+            _ => {
+                ptr::read(p) as u32 | (ptr::read(p.add(1)) as u32) << 8
+                    | (ptr::read(p.add(2)) as u32) << 16
+            }
+        }
+    }
+
+    /// Write color 0xRRGGBB at p.
+    #[inline(always)]
+    pub unsafe fn write(&self, p: *mut u8, rgb: u32) {
+        self.store(p, self.pack(rgb));
+    }
+
+    /// Read the color at p as 0xRRGGBB.
+    #[inline(always)]
+    pub unsafe fn read(&self, p: *const u8) -> u32 {
+        self.unpack(self.load(p))
+    }
+
+    /// Fill n pixels starting at p with color 0xRRGGBB.
+    pub unsafe fn fill(&self, p: *mut u8, n: usize, rgb: u32) {
+        let px = self.pack(rgb);
+        match self.bytes {
+            4 => {
+                for i in 0..n {
+                    ptr::write_unaligned((p as *mut u32).add(i), px);
+                }
+            }
+            2 => {
+                for i in 0..n {
+                    ptr::write_unaligned((p as *mut u16).add(i), px as u16);
+                }
+            }
+            _ => {
+                // 24bpp: four pixels make a 12-byte pattern
+                let b = [px as u8, (px >> 8) as u8, (px >> 16) as u8];
+                let pattern = [b[0], b[1], b[2], b[0], b[1], b[2],
+                               b[0], b[1], b[2], b[0], b[1], b[2]];
+                let total = n * 3;
+                let mut off = 0;
+                while off + 12 <= total {
+                    ptr::copy_nonoverlapping(pattern.as_ptr(), p.add(off), 12);
+                    off += 12;
+                }
+                while off < total {
+                    ptr::write(p.add(off), b[off % 3]);
+                    off += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Copy n pixels from src (in src_fmt) to dst (in dst_fmt), converting as
+/// needed. Same-format rows are a plain copy.
+pub unsafe fn convert_row(
+    dst: *mut u8,
+    dst_fmt: &PixelFormat,
+    src: *const u8,
+    src_fmt: &PixelFormat,
+    n: usize,
+) {
+    if dst_fmt == src_fmt {
+        ptr::copy_nonoverlapping(src, dst, n * dst_fmt.bytes as usize);
+        return;
+    }
+    let (sb, db) = (src_fmt.bytes as usize, dst_fmt.bytes as usize);
+    if src_fmt.layout == Layout::Xrgb8888 {
+        // 0xRRGGBB already: skip the unpack
+        for i in 0..n {
+            let rgb = ptr::read_unaligned(src.add(i * 4) as *const u32);
+            dst_fmt.write(dst.add(i * db), rgb);
+        }
+    } else {
+        for i in 0..n {
+            dst_fmt.write(dst.add(i * db), src_fmt.read(src.add(i * sb)));
+        }
+    }
+}
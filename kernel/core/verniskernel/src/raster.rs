@@ -0,0 +1,610 @@
+// raster.rs — 2D software rasteriser
+//
+// A Surface is a rectangle of pixels in some PixelFormat: the framebuffer
+// (fb_surface), the compositor back buffer (compositor_surface) or a window
+// content buffer (wm_window_surface). Everything drawn through it is clipped
+// to the surface and to an optional clip rectangle, so callers can pass any
+// coordinates.
+//
+// Colors are 0xRRGGBB. Shapes are drawn at the surface's global alpha
+// (set_alpha, default opaque); blend_pixel, blend_rect and blit_argb take
+// 0xAARRGGBB and composite source-over onto the surface. Surfaces have no
+// alpha channel of their own, so the result is always opaque.
+//
+// No floating point: anti-aliasing and gradients use 8-bit coverage and
+// integer or 16.16 fixed-point arithmetic. Nothing allocates.
+//
+// The surface remembers the bounding box of everything it touched
+// (damage); compositor users pass that on to compositor_mark_dirty_rect.
+
+use core::mem;
+
+use crate::errno::{KResult, KernelError};
+use crate::pixfmt::PixelFormat;
+
+/// Most vertices fill_polygon accepts.
+pub const MAX_POLY_POINTS: usize = 64;
+
+// Half-open box [x0, x1) x [y0, y1).
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Bounds {
+    const EMPTY: Bounds = Bounds { x0: 0, y0: 0, x1: 0, y1: 0 };
+
+    fn is_empty(&self) -> bool {
+        self.x0 >= self.x1 || self.y0 >= self.y1
+    }
+}
+
+/// A two-stop linear gradient: `from` at (x0, y0), `to` at (x1, y1), with
+/// color constant along lines perpendicular to the axis between them and
+/// clamped beyond the ends.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearGradient {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+    pub from: u32,
+    pub to: u32,
+}
+
+impl LinearGradient {
+    /// Left to right across [x0, x1].
+    pub fn horizontal(x0: i32, x1: i32, from: u32, to: u32) -> Self {
+        LinearGradient { x0, y0: 0, x1, y1: 0, from, to }
+    }
+
+    /// Top to bottom across [y0, y1].
+    pub fn vertical(y0: i32, y1: i32, from: u32, to: u32) -> Self {
+        LinearGradient { x0: 0, y0, x1: 0, y1, from, to }
+    }
+}
+
+/// 0xAARRGGBB from an alpha and a 0xRRGGBB color.
+pub const fn argb(a: u8, rgb: u32) -> u32 {
+    ((a as u32) << 24) | (rgb & 0x00FF_FFFF)
+}
+
+/// `src` over `dst` at alpha a (0..=255), both 0xRRGGBB.
+pub fn blend(dst: u32, src: u32, a: u32) -> u32 {
+    if a >= 255 {
+        return src & 0x00FF_FFFF;
+    }
+    if a == 0 {
+        return dst & 0x00FF_FFFF;
+    }
+    let mix = |shift: u32| {
+        let s = (src >> shift) & 0xFF;
+        let d = (dst >> shift) & 0xFF;
+        ((s * a + d * (255 - a) + 127) / 255) << shift
+    };
+    mix(16) | mix(8) | mix(0)
+}
+
+/// Linear interpolation between two 0xRRGGBB colors, t in 0..=255.
+pub fn lerp(from: u32, to: u32, t: u32) -> u32 {
+    blend(from, to, t)
+}
+
+// a * b / 255, rounded, for 8-bit alphas.
+fn mul8(a: u32, b: u32) -> u32 {
+    (a * b + 127) / 255
+}
+
+fn isqrt(n: i64) -> i64 {
+    if n <= 0 {
+        return 0;
+    }
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+// Half-width of a filled circle of radius r at vertical distance dy from
+// its center. The +r puts the edge at r + 1/2, matching the midpoint
+// outline.
+fn circle_span(r: i32, dy: i32) -> i32 {
+    let (r, dy) = (r as i64, dy as i64);
+    isqrt(r * r + r - dy * dy) as i32
+}
+
+// Products of two coordinates can overflow i64, so these work in i128.
+fn div_floor(a: i128, b: i128) -> i64 {
+    let q = a / b;
+    let q = if (a % b != 0) && ((a < 0) != (b < 0)) { q - 1 } else { q };
+    q.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+fn div_round(a: i128, b: i128) -> i64 {
+    div_floor(2 * a + b, 2 * b)
+}
+
+pub struct Surface {
+    base: *mut u8,
+    width: u32,
+    height: u32,
+    pitch: u32,
+    format: PixelFormat,
+    clip: Bounds,
+    alpha: u8,
+    damage: Bounds,
+}
+
+impl Surface {
+    /// A surface over `height` rows of `pitch` bytes at `base`.
+    ///
+    /// # Safety
+    /// `base` must stay valid for writes of `height * pitch` bytes, and
+    /// `width * format.bytes <= pitch`, for as long as the surface is used.
+    pub unsafe fn new(base: *mut u8, width: u32, height: u32, pitch: u32, format: PixelFormat) -> Self {
+        let width = width.min(i32::MAX as u32);
+        let height = height.min(i32::MAX as u32);
+        Surface {
+            base,
+            width,
+            height,
+            pitch,
+            format,
+            clip: Bounds { x0: 0, y0: 0, x1: width as i32, y1: height as i32 },
+            alpha: 255,
+            damage: Bounds::EMPTY,
+        }
+    }
+
+    /// A surface over a byte buffer, or None if the buffer is too small.
+    pub fn from_slice(buf: &mut [u8], width: u32, height: u32, pitch: u32, format: PixelFormat) -> Option<Self> {
+        let row = width.checked_mul(format.bytes)?;
+        let need = (pitch as usize).checked_mul(height as usize)?;
+        if row > pitch || need > buf.len() {
+            return None;
+        }
+        Some(unsafe { Surface::new(buf.as_mut_ptr(), width, height, pitch, format) })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> &PixelFormat {
+        &self.format
+    }
+
+    /// Restrict drawing to a rectangle (intersected with the surface).
+    pub fn set_clip(&mut self, x: i32, y: i32, w: u32, h: u32) {
+        let x1 = (x as i64 + w as i64).clamp(0, self.width as i64) as i32;
+        let y1 = (y as i64 + h as i64).clamp(0, self.height as i64) as i32;
+        let x0 = x.clamp(0, x1);
+        let y0 = y.clamp(0, y1);
+        self.clip = Bounds { x0, y0, x1, y1 };
+    }
+
+    /// Drop the clip rectangle: the whole surface is drawable again.
+    pub fn reset_clip(&mut self) {
+        self.clip = Bounds { x0: 0, y0: 0, x1: self.width as i32, y1: self.height as i32 };
+    }
+
+    /// Opacity applied to every shape drawn from now on (255 = opaque).
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    /// Bounding box (x, y, w, h) of every pixel written so far, if any.
+    pub fn damage(&self) -> Option<(i32, i32, u32, u32)> {
+        let d = self.damage;
+        if d.is_empty() {
+            return None;
+        }
+        Some((d.x0, d.y0, (d.x1 - d.x0) as u32, (d.y1 - d.y0) as u32))
+    }
+
+    /// Forget the damage recorded so far.
+    pub fn clear_damage(&mut self) {
+        self.damage = Bounds::EMPTY;
+    }
+
+    fn touch(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let d = &mut self.damage;
+        if d.is_empty() {
+            *d = Bounds { x0, y0, x1, y1 };
+        } else {
+            d.x0 = d.x0.min(x0);
+            d.y0 = d.y0.min(y0);
+            d.x1 = d.x1.max(x1);
+            d.y1 = d.y1.max(y1);
+        }
+    }
+
+    // Pointer to (x, y), which must be inside the surface.
+    fn at(&self, x: i32, y: i32) -> *mut u8 {
+        let off = y as usize * self.pitch as usize + x as usize * self.format.bytes as usize;
+        unsafe { self.base.add(off) }
+    }
+
+    // Pixels [x0, x1) of row y at alpha a, clipped.
+    fn span(&mut self, y: i32, x0: i32, x1: i32, rgb: u32, a: u32) {
+        let c = self.clip;
+        if a == 0 || y < c.y0 || y >= c.y1 {
+            return;
+        }
+        let (x0, x1) = (x0.max(c.x0), x1.min(c.x1));
+        if x0 >= x1 {
+            return;
+        }
+        self.touch(x0, y, x1, y + 1);
+        let p = self.at(x0, y);
+        let n = (x1 - x0) as usize;
+        let format = self.format;
+        unsafe {
+            if a >= 255 {
+                format.fill(p, n, rgb);
+            } else {
+                let bytes = format.bytes as usize;
+                for i in 0..n {
+                    let q = p.add(i * bytes);
+                    format.write(q, blend(format.read(q), rgb, a));
+                }
+            }
+        }
+    }
+
+    // One pixel at coverage cov (0..=255), scaled by the global alpha.
+    fn plot(&mut self, x: i32, y: i32, rgb: u32, cov: u32) {
+        let a = mul8(cov, self.alpha as u32);
+        self.span(y, x, x.saturating_add(1), rgb, a);
+    }
+
+    /// The color at (x, y), or None outside the surface.
+    pub fn pixel(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some(unsafe { self.format.read(self.at(x, y)) })
+    }
+
+    pub fn put_pixel(&mut self, x: i32, y: i32, rgb: u32) {
+        self.plot(x, y, rgb, 255);
+    }
+
+    /// Composite one 0xAARRGGBB pixel source-over.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, argb: u32) {
+        self.plot(x, y, argb, argb >> 24);
+    }
+
+    pub fn fill(&mut self, rgb: u32) {
+        self.fill_rect(0, 0, self.width, self.height, rgb);
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, rgb: u32) {
+        self.blend_rect(x, y, w, h, argb(255, rgb));
+    }
+
+    /// Fill a rectangle with a 0xAARRGGBB color, source-over.
+    pub fn blend_rect(&mut self, x: i32, y: i32, w: u32, h: u32, argb: u32) {
+        let a = mul8(argb >> 24, self.alpha as u32);
+        let x1 = (x as i64 + w as i64).min(i32::MAX as i64) as i32;
+        let y0 = y.max(self.clip.y0);
+        let y1 = ((y as i64 + h as i64).min(self.clip.y1 as i64)) as i32;
+        for row in y0..y1 {
+            self.span(row, x, x1, argb, a);
+        }
+    }
+
+    /// One-pixel rectangle outline.
+    pub fn rect(&mut self, x: i32, y: i32, w: u32, h: u32, rgb: u32) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let a = self.alpha as u32;
+        let x1 = x.saturating_add(w as i32 - 1);
+        let y1 = y.saturating_add(h as i32 - 1);
+        self.span(y, x, x1.saturating_add(1), rgb, a);
+        if y1 > y {
+            self.span(y1, x, x1.saturating_add(1), rgb, a);
+        }
+        for row in y.saturating_add(1).max(self.clip.y0)..y1.min(self.clip.y1) {
+            self.span(row, x, x.saturating_add(1), rgb, a);
+            if x1 > x {
+                self.span(row, x1, x1.saturating_add(1), rgb, a);
+            }
+        }
+    }
+
+    // Cohen–Sutherland against the clip box grown by `margin` pixels, so
+    // long lines cost only their visible part. None if nothing is visible.
+    fn clip_line(&self, p0: (i32, i32), p1: (i32, i32), margin: i64) -> Option<((i32, i32), (i32, i32))> {
+        let c = self.clip;
+        if c.is_empty() {
+            return None;
+        }
+        let (xmin, ymin) = (c.x0 as i64 - margin, c.y0 as i64 - margin);
+        let (xmax, ymax) = (c.x1 as i64 - 1 + margin, c.y1 as i64 - 1 + margin);
+        let code = |x: i64, y: i64| {
+            (x < xmin) as u8 | ((x > xmax) as u8) << 1 | ((y < ymin) as u8) << 2 | ((y > ymax) as u8) << 3
+        };
+        let (mut x0, mut y0) = (p0.0 as i64, p0.1 as i64);
+        let (mut x1, mut y1) = (p1.0 as i64, p1.1 as i64);
+        let (mut c0, mut c1) = (code(x0, y0), code(x1, y1));
+        loop {
+            if c0 | c1 == 0 {
+                return Some(((x0 as i32, y0 as i32), (x1 as i32, y1 as i32)));
+            }
+            if c0 & c1 != 0 {
+                return None;
+            }
+            let out = if c0 != 0 { c0 } else { c1 };
+            let (dx, dy) = (x1 - x0, y1 - y0);
+            let (x, y) = if out & 8 != 0 {
+                (x0 + div_round(dx as i128 * (ymax - y0) as i128, dy as i128), ymax)
+            } else if out & 4 != 0 {
+                (x0 + div_round(dx as i128 * (ymin - y0) as i128, dy as i128), ymin)
+            } else if out & 2 != 0 {
+                (xmax, y0 + div_round(dy as i128 * (xmax - x0) as i128, dx as i128))
+            } else {
+                (xmin, y0 + div_round(dy as i128 * (xmin - x0) as i128, dx as i128))
+            };
+            if out == c0 {
+                (x0, y0, c0) = (x, y, code(x, y));
+            } else {
+                (x1, y1, c1) = (x, y, code(x, y));
+            }
+        }
+    }
+
+    /// Bresenham line, both endpoints included.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, rgb: u32) {
+        let Some(((mut x, mut y), (x1, y1))) = self.clip_line((x0, y0), (x1, y1), 0) else {
+            return;
+        };
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.plot(x, y, rgb, 255);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Anti-aliased (Wu) line, both endpoints included.
+    pub fn line_aa(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, rgb: u32) {
+        let Some(((mut x0, mut y0), (mut x1, mut y1))) = self.clip_line((x0, y0), (x1, y1), 1) else {
+            return;
+        };
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            mem::swap(&mut x0, &mut y0);
+            mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            mem::swap(&mut x0, &mut x1);
+            mem::swap(&mut y0, &mut y1);
+        }
+        let dx = (x1 - x0) as i64;
+        let dy = (y1 - y0) as i64;
+        // 16.16 fixed point
+        let grad = if dx == 0 { 0 } else { (dy << 16) / dx };
+        let mut y = (y0 as i64) << 16;
+        for x in x0..=x1 {
+            let yi = (y >> 16) as i32;
+            let frac = ((y & 0xFFFF) >> 8) as u32;
+            let (a, b) = if steep { ((yi, x), (yi + 1, x)) } else { ((x, yi), (x, yi + 1)) };
+            self.plot(a.0, a.1, rgb, 255 - frac);
+            if frac != 0 {
+                self.plot(b.0, b.1, rgb, frac);
+            }
+            y += grad;
+        }
+    }
+
+    // (cx ± a, cy ± b) for the quadrants selected by `quads` (bit 0: +x+y,
+    // 1: -x+y, 2: +x-y, 3: -x-y), each distinct pixel once.
+    fn plot_quads(&mut self, cx: i32, cy: i32, a: i32, b: i32, rgb: u32, quads: u8) {
+        let pts = [(a, b), (-a, b), (a, -b), (-a, -b)];
+        for i in 0..4 {
+            // With a or b zero, mirrors land on a point already drawn
+            let selected = |j: usize| quads & (1 << j) != 0;
+            if !selected(i) || (0..i).any(|j| selected(j) && pts[j] == pts[i]) {
+                continue;
+            }
+            self.plot(cx + pts[i].0, cy + pts[i].1, rgb, 255);
+        }
+    }
+
+    // Midpoint arc of radius r around (cx, cy) in the given quadrants.
+    fn arc(&mut self, cx: i32, cy: i32, r: i32, rgb: u32, quads: u8) {
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            self.plot_quads(cx, cy, x, y, rgb, quads);
+            if x != y {
+                self.plot_quads(cx, cy, y, x, rgb, quads);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Circle outline of radius r around (cx, cy).
+    pub fn circle(&mut self, cx: i32, cy: i32, r: u32, rgb: u32) {
+        let r = r.min(i32::MAX as u32 / 2) as i32;
+        self.arc(cx, cy, r, rgb, 0xF);
+    }
+
+    /// Filled circle of radius r around (cx, cy).
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, r: u32, rgb: u32) {
+        let r = r.min(i32::MAX as u32 / 2) as i32;
+        let a = self.alpha as u32;
+        let rows = self.clip.y0.saturating_sub(cy).max(-r)..=(self.clip.y1 - 1).saturating_sub(cy).min(r);
+        for dy in rows {
+            let hw = circle_span(r, dy);
+            self.span(cy + dy, cx - hw, cx + hw + 1, rgb, a);
+        }
+    }
+
+    /// Rounded rectangle outline; corner radius r is clamped to fit.
+    pub fn round_rect(&mut self, x: i32, y: i32, w: u32, h: u32, r: u32, rgb: u32) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let r = r.min((w - 1) / 2).min((h - 1) / 2) as i32;
+        let (x1, y1) = (x + w as i32 - 1, y + h as i32 - 1);
+        let a = self.alpha as u32;
+        // Corner arcs include their end pixels; the edges stop short of them
+        self.arc(x + r, y + r, r, rgb, 8);
+        self.arc(x1 - r, y + r, r, rgb, 4);
+        self.arc(x + r, y1 - r, r, rgb, 2);
+        self.arc(x1 - r, y1 - r, r, rgb, 1);
+        self.span(y, x + r + 1, x1 - r, rgb, a);
+        if y1 > y {
+            self.span(y1, x + r + 1, x1 - r, rgb, a);
+        }
+        for row in (y + r + 1).max(self.clip.y0)..(y1 - r).min(self.clip.y1) {
+            self.span(row, x, x + 1, rgb, a);
+            if x1 > x {
+                self.span(row, x1, x1 + 1, rgb, a);
+            }
+        }
+    }
+
+    /// Filled rounded rectangle; corner radius r is clamped to fit.
+    pub fn fill_round_rect(&mut self, x: i32, y: i32, w: u32, h: u32, r: u32, rgb: u32) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let r = r.min((w - 1) / 2).min((h - 1) / 2) as i32;
+        let (x1, y1) = (x + w as i32, y + h as i32);
+        let a = self.alpha as u32;
+        for row in y.max(self.clip.y0)..y1.min(self.clip.y1) {
+            // Distance into the corner band, 0 on the straight part
+            let dy = (y + r - row).max(row - (y1 - 1 - r)).max(0);
+            let inset = if dy == 0 { 0 } else { r - circle_span(r, dy) };
+            self.span(row, x + inset, x1 - inset, rgb, a);
+        }
+    }
+
+    /// Closed polygon outline.
+    pub fn polygon(&mut self, points: &[(i32, i32)], rgb: u32) {
+        for i in 0..points.len() {
+            let (p, q) = (points[i], points[(i + 1) % points.len()]);
+            self.line(p.0, p.1, q.0, q.1, rgb);
+        }
+    }
+
+    /// Fill a polygon (even-odd rule). A pixel is inside if its center is.
+    /// Inval for fewer than 3 or more than MAX_POLY_POINTS vertices.
+    pub fn fill_polygon(&mut self, points: &[(i32, i32)], rgb: u32) -> KResult<()> {
+        if points.len() < 3 || points.len() > MAX_POLY_POINTS {
+            return Err(KernelError::Inval);
+        }
+        let ymin = points.iter().map(|p| p.1).min().unwrap_or(0).max(self.clip.y0);
+        let ymax = points.iter().map(|p| p.1).max().unwrap_or(0).min(self.clip.y1);
+        let a = self.alpha as u32;
+        let mut xs = [0i32; MAX_POLY_POINTS];
+        for y in ymin..ymax {
+            // Sample at the pixel center, y + 1/2, in half-pixel units
+            let yc = 2 * y as i64 + 1;
+            let mut n = 0;
+            for i in 0..points.len() {
+                let (p, q) = (points[i], points[(i + 1) % points.len()]);
+                let (py, qy) = (2 * p.1 as i64, 2 * q.1 as i64);
+                if (py <= yc) == (qy <= yc) {
+                    continue;
+                }
+                // Crossing x = p.x + (yc - py) * dx / dy; the first pixel
+                // whose center is at or right of it is ceil(x - 1/2)
+                let den = (qy - py) as i128;
+                let num = p.0 as i128 * den + (yc - py) as i128 * (q.0 - p.0) as i128;
+                let (num, den) = if den < 0 { (-num, -den) } else { (num, den) };
+                xs[n] = (-div_floor(den - 2 * num, 2 * den)).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+                n += 1;
+            }
+            let xs = &mut xs[..n];
+            xs.sort_unstable();
+            for &[l, r] in xs.as_chunks::<2>().0 {
+                self.span(y, l, r, rgb, a);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill a rectangle with a linear gradient (coordinates are surface
+    /// coordinates, like the rectangle's).
+    pub fn fill_gradient(&mut self, x: i32, y: i32, w: u32, h: u32, g: &LinearGradient) {
+        let a = self.alpha as u32;
+        let x1 = (x as i64 + w as i64).min(self.clip.x1 as i64) as i32;
+        let y1 = (y as i64 + h as i64).min(self.clip.y1 as i64) as i32;
+        let x0 = x.max(self.clip.x0);
+        let (dx, dy) = ((g.x1 - g.x0) as i64, (g.y1 - g.y0) as i64);
+        let len2 = dx * dx + dy * dy;
+        let t_at = |px: i32, py: i32| -> u32 {
+            if len2 == 0 {
+                return 0;
+            }
+            let dot = (px - g.x0) as i64 * dx + (py - g.y0) as i64 * dy;
+            (dot * 255 / len2).clamp(0, 255) as u32
+        };
+        for row in y.max(self.clip.y0)..y1 {
+            if dx == 0 {
+                // Vertical: one color per row
+                let rgb = lerp(g.from, g.to, t_at(x0, row));
+                self.span(row, x0, x1, rgb, a);
+                continue;
+            }
+            for col in x0..x1 {
+                let rgb = lerp(g.from, g.to, t_at(col, row));
+                self.span(row, col, col + 1, rgb, a);
+            }
+        }
+    }
+
+    /// Composite a w x h image of 0xAARRGGBB pixels (row-major, no
+    /// padding) source-over at (x, y). Does nothing if `src` is too short.
+    pub fn blit_argb(&mut self, x: i32, y: i32, w: u32, h: u32, src: &[u32]) {
+        if (w as usize).saturating_mul(h as usize) > src.len() {
+            return;
+        }
+        let c = self.clip;
+        let row0 = (c.y0 as i64 - y as i64).max(0);
+        let row1 = (c.y1 as i64 - y as i64).min(h as i64);
+        let col0 = (c.x0 as i64 - x as i64).max(0);
+        let col1 = (c.x1 as i64 - x as i64).min(w as i64);
+        for row in row0..row1 {
+            let line = &src[row as usize * w as usize..][..w as usize];
+            for col in col0..col1 {
+                let px = line[col as usize];
+                self.plot(x + col as i32, y + row as i32, px, px >> 24);
+            }
+        }
+    }
+}
@@ -4,12 +4,18 @@ use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 use core::option::Option::{Some, None};
 use core::result::Result::{Ok, Err};
 use core::default::Default;
 use core::ptr;
 use core::time::Duration;
 use core::ffi::c_char;
+use core::fmt::{self, Write};
+use crate::errno::{self, KResult, KernelError};
+use crate::slab::SlabBox;
+use crate::heap::{self, HeapTag};
+use crate::seccomp::SeccompFilter;
 
 extern "C" {
     fn kernel_get_ticks() -> u32;
@@ -57,6 +63,24 @@ pub enum ProcessState {
     Zombie,
 }
 
+impl ProcessState {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Standby => "standby",
+            Self::Running => "running",
+            Self::Waiting => "waiting",
+            Self::Suspended => "suspended",
+            Self::Terminated => "terminated",
+            Self::Zombie => "zombie",
+        }
+    }
+}
+
+/// Registers of a process as of its last syscall, shared with C
+/// (include/scheduler_base.h). On i386 the r* fields hold the e* registers
+/// and r8-r15 stay 0.
+#[repr(C)]
 #[derive(Debug, Clone)]
 pub struct CpuContext {
     // จำลอง register context สำหรับ x86_64
@@ -114,6 +138,8 @@ pub struct MemoryInfo {
     pub data_size: usize,           // Data segment size
     pub stack_size: usize,          // Stack size
     pub heap_size: usize,           // Heap size
+    pub kernel_stack_peak: usize,   // Deepest kernel stack use seen (bytes)
+    pub user_stack_peak: usize,     // Deepest user stack use seen (bytes)
 }
 
 impl Default for MemoryInfo {
@@ -126,6 +152,8 @@ impl Default for MemoryInfo {
             data_size: 0,
             stack_size: 0,
             heap_size: 0,
+            kernel_stack_peak: 0,
+            user_stack_peak: 0,
         }
     }
 }
@@ -145,10 +173,33 @@ pub enum PrivilegeRing {
     Ring3 = 3,  // User
 }
 
+// Capability bits — mirror include/sandbox.h
+pub const CAP_SERIAL_WRITE: u64    = 0x0000_0001;
+pub const CAP_SERIAL_READ: u64     = 0x0000_0002;
+pub const CAP_VGA_WRITE: u64       = 0x0000_0004;
+pub const CAP_IO_PORT: u64         = 0x0000_0008;
+pub const CAP_ALLOC_MEMORY: u64    = 0x0000_0010;
+pub const CAP_MAP_MEMORY: u64      = 0x0000_0020;
+pub const CAP_PROTECT_MEMORY: u64  = 0x0000_0040;
+pub const CAP_CREATE_PROCESS: u64  = 0x0000_0100;
+pub const CAP_KILL_PROCESS: u64    = 0x0000_0200;
+pub const CAP_CHANGE_PRIORITY: u64 = 0x0000_0400;
+pub const CAP_IPC_SEND: u64        = 0x0000_1000;
+pub const CAP_IPC_RECEIVE: u64     = 0x0000_2000;
+pub const CAP_CHANNEL_CREATE: u64  = 0x0000_4000;
+pub const CAP_FILE_IO: u64         = 0x0000_8000;
+pub const CAP_MODULE_LOAD: u64     = 0x0001_0000;
+pub const CAP_MODULE_UNLOAD: u64   = 0x0002_0000;
+pub const CAP_MODULE_EXECUTE: u64  = 0x0004_0000;
+pub const CAP_SYS_DEBUG: u64       = 0x0010_0000;
+pub const CAP_SYS_REBOOT: u64      = 0x0020_0000;
+pub const CAP_SYS_TIME: u64        = 0x0040_0000;
+
 #[derive(Debug, Clone)]
 pub struct ProcessControlBlock {
     pub pid: usize,
     pub ppid: Option<usize>,        // Parent PID
+    pub pgid: usize,                // Process group (inherited from the parent)
     pub state: ProcessState,
     pub priority: u8,               // Static priority (0-139, lower is higher priority)
     pub nice: i8,                   // Nice value (-20 to 19)
@@ -171,6 +222,8 @@ pub struct ProcessControlBlock {
     pub user_memory_base: usize,    // For user processes: heap/stack base
     pub user_memory_size: usize,    // For user processes: allocated size
     pub capability_denials: u64,    // Count of blocked operations
+    pub uid: u16,                   // File owner identity for kfs_check_perm (0 = root)
+    pub seccomp: Option<Arc<SeccompFilter>>, // Syscall filter stack (seccomp.rs)
     pub cached_effective_priority: u8, // Cached: priority + nice*2 (Phase 15)
     
     // Phase 23: Signals
@@ -182,6 +235,7 @@ impl ProcessControlBlock {
         Self {
             pid,
             ppid: None,
+            pgid: pid,
             state: ProcessState::New,
             priority,
             nice: 0,
@@ -204,6 +258,8 @@ impl ProcessControlBlock {
             user_memory_base: 0,
             user_memory_size: 0,
             capability_denials: 0,
+            uid: 0,
+            seccomp: None,
             cached_effective_priority: priority,
             
             // Phase 23: No pending signals initially
@@ -211,6 +267,10 @@ impl ProcessControlBlock {
         }
     }
 
+    pub fn has_capability(&self, cap: u64) -> bool {
+        (self.capabilities & cap) == cap
+    }
+
     pub fn get_effective_priority(&self) -> u8 {
         self.cached_effective_priority
     }
@@ -266,7 +326,7 @@ impl ProcessControlBlock {
 }
 
 pub struct Scheduler {
-    pub processes: BTreeMap<usize, ProcessControlBlock>, // เปลี่ยนจาก HashMap เป็น BTreeMap
+    pub processes: BTreeMap<usize, SlabBox<ProcessControlBlock>>, // PCBs live in the "pcb" slab cache
     pub current_pid: Option<usize>,
     pub next_pid: usize,            // Next available PID
     pub total_processes_created: usize,
@@ -300,7 +360,7 @@ impl Scheduler {
         let mut proc = ProcessControlBlock::new(pid, priority, command);
         proc.state = ProcessState::Standby;
         
-        self.processes.insert(pid, proc);
+        self.processes.insert(pid, SlabBox::new(proc));
         pid
     }
 
@@ -310,7 +370,7 @@ impl Scheduler {
         if !matches!(proc.state, ProcessState::Suspended | ProcessState::Zombie) {
             proc.state = ProcessState::Standby;
         }
-        self.processes.insert(pid, proc);
+        self.processes.insert(pid, SlabBox::new(proc));
         pid
     }
 
@@ -391,15 +451,18 @@ impl Scheduler {
         }
     }
 
-    pub fn wake_process(&mut self, pid: usize) -> bool {
-        if let Some(proc) = self.processes.get_mut(&pid) {
-            if matches!(proc.state, ProcessState::Waiting) {
-                proc.state = ProcessState::Standby;
-                proc.wait_reason = None;
-                return true;
-            }
+    pub fn process_mut(&mut self, pid: usize) -> KResult<&mut ProcessControlBlock> {
+        self.processes.get_mut(&pid).map(|p| &mut **p).ok_or(KernelError::NoProcess)
+    }
+
+    pub fn wake_process(&mut self, pid: usize) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        if !matches!(proc.state, ProcessState::Waiting) {
+            return Err(KernelError::Inval);
         }
-        false
+        proc.state = ProcessState::Standby;
+        proc.wait_reason = None;
+        Ok(())
     }
 
     pub fn terminate_current(&mut self, exit_code: i32) {
@@ -412,59 +475,71 @@ impl Scheduler {
         }
     }
 
-    pub fn kill_process(&mut self, pid: usize) -> bool {
-        if let Some(proc) = self.processes.get_mut(&pid) {
-            proc.state = ProcessState::Terminated;
-            proc.exit_code = Some(-1); // Killed
-            return true;
+    /// Mark pid terminated with exit_code, as the exit syscall does.
+    pub fn exit_process(&mut self, pid: usize, exit_code: i32) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        proc.state = ProcessState::Terminated;
+        proc.exit_code = Some(exit_code);
+        if self.current_pid == Some(pid) {
+            self.current_pid = None;
         }
-        false
+        Ok(())
     }
 
-    pub fn suspend_process(&mut self, pid: usize) -> bool {
-        if let Some(proc) = self.processes.get_mut(&pid) {
-            if matches!(proc.state, ProcessState::Running | ProcessState::Standby) {
-                proc.state = ProcessState::Suspended;
-                return true;
-            }
-        }
-        false
+    pub fn kill_process(&mut self, pid: usize) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        proc.state = ProcessState::Terminated;
+        proc.exit_code = Some(-1); // Killed
+        Ok(())
     }
 
-    pub fn resume_process(&mut self, pid: usize) -> bool {
-        if let Some(proc) = self.processes.get_mut(&pid) {
-            if matches!(proc.state, ProcessState::Suspended) {
-                proc.state = ProcessState::Standby;
-                return true;
-            }
-        }
-        false
+    /// Fold a stack high-water sample into the process. Peaks only grow, so
+    /// the values survive exit and can be read back from the zombie.
+    pub fn record_stack_usage(&mut self, pid: usize, kernel_used: usize, user_used: usize) -> KResult<()> {
+        let info = &mut self.process_mut(pid)?.memory_info;
+        info.kernel_stack_peak = info.kernel_stack_peak.max(kernel_used);
+        info.user_stack_peak = info.user_stack_peak.max(user_used);
+        Ok(())
     }
 
-    pub fn set_priority(&mut self, pid: usize, priority: u8) -> bool {
-        if let Some(proc) = self.processes.get_mut(&pid) {
-            proc.priority = priority;
-            proc.recompute_effective_priority();
-            return true;
+    pub fn suspend_process(&mut self, pid: usize) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        if !matches!(proc.state, ProcessState::Running | ProcessState::Standby) {
+            return Err(KernelError::Inval);
         }
-        false
+        proc.state = ProcessState::Suspended;
+        Ok(())
     }
 
-    pub fn set_nice(&mut self, pid: usize, nice: i8) -> bool {
-        if let Some(proc) = self.processes.get_mut(&pid) {
-            proc.nice = nice.max(-20).min(19);
-            proc.recompute_effective_priority();
-            return true;
+    pub fn resume_process(&mut self, pid: usize) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        if !matches!(proc.state, ProcessState::Suspended) {
+            return Err(KernelError::Inval);
         }
-        false
+        proc.state = ProcessState::Standby;
+        Ok(())
+    }
+
+    pub fn set_priority(&mut self, pid: usize, priority: u8) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        proc.priority = priority;
+        proc.recompute_effective_priority();
+        Ok(())
+    }
+
+    pub fn set_nice(&mut self, pid: usize, nice: i8) -> KResult<()> {
+        let proc = self.process_mut(pid)?;
+        proc.nice = nice.max(-20).min(19);
+        proc.recompute_effective_priority();
+        Ok(())
     }
 
     pub fn get_process_info(&self, pid: usize) -> Option<&ProcessControlBlock> {
-        self.processes.get(&pid)
+        self.processes.get(&pid).map(|p| &**p)
     }
 
     pub fn get_current_process(&self) -> Option<&ProcessControlBlock> {
-        self.current_pid.and_then(|pid| self.processes.get(&pid))
+        self.current_pid.and_then(|pid| self.processes.get(&pid)).map(|p| &**p)
     }
 
     pub fn get_process_count(&self) -> usize {
@@ -504,17 +579,27 @@ impl Scheduler {
         }
     }
 
-    pub fn dump(&self) {
-        // println!("=== Process Dump ===");
-        // println!("Current PID: {:?}", self.current_pid);
-        // println!("Total Processes: {}", self.processes.len());
-        // for (pid, proc) in &self.processes {
-        //     println!(
-        //         "PID: {}, State: {:?}, Priority: {}, Nice: {}, Command: {}, CPU Time: {:.2}s",
-        //         pid, proc.state, proc.get_effective_priority(), proc.nice, 
-        //         proc.command, proc.cpu_time.as_secs_f64()
-        //     );
-        // }
+    /// Process table with states and priorities.
+    pub fn dump<W: Write>(&self, out: &mut W) -> fmt::Result {
+        match self.current_pid {
+            Some(pid) => write!(out, "current pid {}", pid)?,
+            None => write!(out, "current pid -")?,
+        }
+        writeln!(out, ", {} processes ({} created), {} context switches",
+                 self.processes.len(), self.total_processes_created, self.context_switches)?;
+        writeln!(out, "  PID  PPID  PGID  STATE       PRIO  NICE  TYPE    CPU(ms)  COMMAND")?;
+        for (pid, proc) in &self.processes {
+            let ptype = match proc.process_type {
+                ProcessType::Kernel => "kernel",
+                ProcessType::System => "system",
+                ProcessType::User => "user",
+            };
+            writeln!(out, "{:>5} {:>5} {:>5}  {:<10} {:>5} {:>5}  {:<6} {:>8}  {}",
+                     pid, proc.ppid.unwrap_or(0), proc.pgid, proc.state.name(),
+                     proc.get_effective_priority(), proc.nice, ptype,
+                     proc.cpu_time.as_millis(), proc.command)?;
+        }
+        Ok(())
     }
 
     pub fn cleanup_zombies(&mut self) -> usize {
@@ -534,27 +619,17 @@ impl Scheduler {
     }
 
     // Phase 23: Signal handling
-    pub fn signal_send(&mut self, dst_pid: usize, sig: u8) -> i32 {
-        // Send signal to process
-        if let Some(proc) = self.processes.get_mut(&dst_pid) {
-            proc.signal_send(sig);
-            0
-        } else {
-            -1
+    pub fn signal_send(&mut self, dst_pid: usize, sig: u8) -> KResult<()> {
+        if sig >= 64 {
+            return Err(KernelError::Inval);
         }
+        self.process_mut(dst_pid)?.signal_send(sig);
+        Ok(())
     }
 
-    pub fn get_pending_signal(&mut self, obj_pid: usize) -> i32 {
-        // Get next pending signal from process (-1 if none)
-        if let Some(proc) = self.processes.get_mut(&obj_pid) {
-            if let Some(sig) = proc.get_pending_signal() {
-                sig as i32
-            } else {
-                -1
-            }
-        } else {
-            -1
-        }
+    /// Next pending signal of obj_pid, or None if nothing is pending.
+    pub fn get_pending_signal(&mut self, obj_pid: usize) -> KResult<Option<u8>> {
+        Ok(self.process_mut(obj_pid)?.get_pending_signal())
     }
 }
 
@@ -587,6 +662,8 @@ pub struct PsRow {
     pub mem_virt:     usize,  // Virtual memory size (bytes)
     pub uptime_secs:  u64,    // Seconds since process creation
     pub command:  [u8; 32],
+    pub kstack_peak:  usize,  // Kernel stack high-water mark (bytes)
+    pub ustack_peak:  usize,  // User stack high-water mark (bytes)
 }
 
 /// Fill `pids_out[0..max]` with active PIDs. Returns actual count written.
@@ -609,18 +686,18 @@ pub extern "C" fn scheduler_get_pid_list(
     n
 }
 
-/// Fill `out` with a PsRow for the given PID. Returns true on success.
+/// Fill `out` with a PsRow for the given PID. Returns 0, -EINVAL or -ESRCH.
 #[no_mangle]
 pub extern "C" fn scheduler_get_ps_row(
     sched: *const Scheduler,
     pid: usize,
     out: *mut PsRow,
-) -> bool {
-    if sched.is_null() || out.is_null() { return false; }
+) -> i32 {
+    if sched.is_null() || out.is_null() { return KernelError::Inval.neg(); }
     let sched = unsafe { &*sched };
     let pcb = match sched.processes.get(&pid) {
         Some(p) => p,
-        None => return false,
+        None => return KernelError::NoProcess.neg(),
     };
     let row = unsafe { &mut *out };
     row.pid      = pcb.pid;
@@ -649,7 +726,9 @@ pub extern "C" fn scheduler_get_ps_row(
     let n = bytes.len().min(31);
     row.command[..n].copy_from_slice(&bytes[..n]);
     row.command[n] = 0;
-    true
+    row.kstack_peak  = pcb.memory_info.kernel_stack_peak;
+    row.ustack_peak  = pcb.memory_info.user_stack_peak;
+    0
 }
 
 #[repr(C)]
@@ -690,11 +769,12 @@ pub extern "C" fn scheduler_create_process(
     sched: *mut Scheduler,
     priority: u8,
     command: *const c_char,
-) -> usize {
+) -> isize {
     if sched.is_null() || command.is_null() {
-        return 0;
+        return KernelError::Inval.neg_isize();
     }
     let sched = unsafe { &mut *sched };
+    let _tag = heap::tag_scope(HeapTag::Scheduler);
     
     // Create CString from raw pointer safely
     let command_str = unsafe {
@@ -705,7 +785,7 @@ pub extern "C" fn scheduler_create_process(
         }
     };
     
-    sched.create_process(priority, command_str)
+    sched.create_process(priority, command_str) as isize
 }
 
 #[no_mangle]
@@ -731,9 +811,9 @@ pub extern "C" fn scheduler_get_process_info(
     sched: *const Scheduler,
     pid: usize,
     out_info: *mut FfiProcessInfo,
-) -> bool {
+) -> i32 {
     if sched.is_null() || out_info.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &*sched };
     if let Some(proc) = sched.get_process_info(pid) {
@@ -753,9 +833,9 @@ pub extern "C" fn scheduler_get_process_info(
             cpu_time_secs: proc.cpu_time.as_secs(),
         };
         unsafe { ptr::write(out_info, info); }
-        true
+        0
     } else {
-        false
+        KernelError::NoProcess.neg()
     }
 }
 
@@ -789,39 +869,64 @@ pub extern "C" fn scheduler_block_current(sched: *mut Scheduler, reason: *const
 }
 
 #[no_mangle]
-pub extern "C" fn scheduler_wake_process(sched: *mut Scheduler, pid: usize) -> bool {
+pub extern "C" fn scheduler_wake_process(sched: *mut Scheduler, pid: usize) -> i32 {
     if sched.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &mut *sched };
-    sched.wake_process(pid)
+    errno::to_c(sched.wake_process(pid))
 }
 
+/// 1 if pid is Waiting (parked by a blocking syscall until woken), else 0.
 #[no_mangle]
-pub extern "C" fn scheduler_suspend_process(sched: *mut Scheduler, pid: usize) -> bool {
+pub extern "C" fn scheduler_is_waiting(sched: *const Scheduler, pid: usize) -> i32 {
+    let sched = match unsafe { sched.as_ref() } {
+        Some(s) => s,
+        None => return 0,
+    };
+    sched.processes.get(&pid).is_some_and(|p| matches!(p.state, ProcessState::Waiting)) as i32
+}
+
+#[no_mangle]
+pub extern "C" fn scheduler_suspend_process(sched: *mut Scheduler, pid: usize) -> i32 {
     if sched.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &mut *sched };
-    sched.suspend_process(pid)
+    errno::to_c(sched.suspend_process(pid))
 }
 
 #[no_mangle]
-pub extern "C" fn scheduler_resume_process(sched: *mut Scheduler, pid: usize) -> bool {
+pub extern "C" fn scheduler_resume_process(sched: *mut Scheduler, pid: usize) -> i32 {
     if sched.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &mut *sched };
-    sched.resume_process(pid)
+    errno::to_c(sched.resume_process(pid))
 }
 
 #[no_mangle]
-pub extern "C" fn scheduler_kill_process(sched: *mut Scheduler, pid: usize) -> bool {
+pub extern "C" fn scheduler_kill_process(sched: *mut Scheduler, pid: usize) -> i32 {
     if sched.is_null() {
-        return false;
+        return KernelError::Inval.neg();
+    }
+    let sched = unsafe { &mut *sched };
+    errno::to_c(sched.kill_process(pid))
+}
+
+/// Record stack high-water marks (bytes) sampled by the arch code.
+#[no_mangle]
+pub extern "C" fn scheduler_record_stack_usage(
+    sched: *mut Scheduler,
+    pid: usize,
+    kernel_used: usize,
+    user_used: usize,
+) -> i32 {
+    if sched.is_null() {
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &mut *sched };
-    sched.kill_process(pid)
+    errno::to_c(sched.record_stack_usage(pid, kernel_used, user_used))
 }
 
 #[no_mangle]
@@ -853,12 +958,12 @@ pub extern "C" fn scheduler_get_waiting_process_count(sched: *const Scheduler) -
 }
 
 #[no_mangle]
-pub extern "C" fn scheduler_get_scheduler_stats(sched: *const Scheduler, out_stats: *mut SchedulerStats) -> bool {
-    if sched.is_null() || out_stats.is_null() { return false; }
+pub extern "C" fn scheduler_get_scheduler_stats(sched: *const Scheduler, out_stats: *mut SchedulerStats) -> i32 {
+    if sched.is_null() || out_stats.is_null() { return KernelError::Inval.neg(); }
     let scheduler = unsafe { &*sched };
     let stats = scheduler.get_scheduler_stats();
     unsafe { ptr::write(out_stats, stats); }
-    true
+    0
 }
 
 #[no_mangle]
@@ -869,10 +974,10 @@ pub extern "C" fn scheduler_cleanup_zombies(sched: *mut Scheduler) -> usize {
 }
 
 #[no_mangle]
-pub extern "C" fn scheduler_set_priority(sched: *mut Scheduler, pid: usize, priority: u8) -> bool {
-    if sched.is_null() { return false; }
+pub extern "C" fn scheduler_set_priority(sched: *mut Scheduler, pid: usize, priority: u8) -> i32 {
+    if sched.is_null() { return KernelError::Inval.neg(); }
     let scheduler = unsafe { &mut *sched };
-    scheduler.set_priority(pid, priority)
+    errno::to_c(scheduler.set_priority(pid, priority))
 }
 
 #[no_mangle]
@@ -887,10 +992,10 @@ pub extern "C" fn scheduler_set_quantum(sched: *mut Scheduler, quantum_ms: u32)
 }
 
 #[no_mangle]
-pub extern "C" fn scheduler_set_nice(sched: *mut Scheduler, pid: usize, nice: i8) -> bool {
-    if sched.is_null() { return false; }
+pub extern "C" fn scheduler_set_nice(sched: *mut Scheduler, pid: usize, nice: i8) -> i32 {
+    if sched.is_null() { return KernelError::Inval.neg(); }
     let scheduler = unsafe { &mut *sched };
-    scheduler.set_nice(pid, nice)
+    errno::to_c(scheduler.set_nice(pid, nice))
 }
 
 #[no_mangle]
@@ -921,11 +1026,12 @@ pub extern "C" fn scheduler_create_user_process(
     sched: *mut Scheduler,
     priority: u8,
     command: *const c_char,
-) -> usize {
+) -> isize {
     if sched.is_null() || command.is_null() {
-        return 0;
+        return KernelError::Inval.neg_isize();
     }
     let sched = unsafe { &mut *sched };
+    let _tag = heap::tag_scope(HeapTag::Scheduler);
     
     let command_str = unsafe {
         let c_str = core::ffi::CStr::from_ptr(command);
@@ -941,12 +1047,12 @@ pub extern "C" fn scheduler_create_user_process(
     if let Some(proc) = sched.processes.get_mut(&pid) {
         proc.process_type = ProcessType::User;
         proc.privilege_ring = PrivilegeRing::Ring3;
-        proc.capabilities = 0x0000000000001B26u64; // CAP_SERIAL_WRITE | CAP_ALLOC_MEMORY | CAP_IPC_SEND | CAP_IPC_RECEIVE
+        proc.capabilities = 0x0000000000009B26u64; // CAP_SERIAL_WRITE | CAP_ALLOC_MEMORY | CAP_IPC_SEND | CAP_IPC_RECEIVE | CAP_FILE_IO
         proc.user_memory_base = 0x1000000;  // 16MB user space start
         proc.user_memory_size = 4 * 1024 * 1024; // 4MB per user process
     }
     
-    pid
+    pid as isize
 }
 
 #[no_mangle]
@@ -954,11 +1060,12 @@ pub extern "C" fn scheduler_create_system_process(
     sched: *mut Scheduler,
     priority: u8,
     command: *const c_char,
-) -> usize {
+) -> isize {
     if sched.is_null() || command.is_null() {
-        return 0;
+        return KernelError::Inval.neg_isize();
     }
     let sched = unsafe { &mut *sched };
+    let _tag = heap::tag_scope(HeapTag::Scheduler);
     
     let command_str = unsafe {
         let c_str = core::ffi::CStr::from_ptr(command);
@@ -979,7 +1086,7 @@ pub extern "C" fn scheduler_create_system_process(
         proc.user_memory_size = 0; // System procs not sandboxed
     }
     
-    pid
+    pid as isize
 }
 
 #[no_mangle]
@@ -1021,18 +1128,12 @@ pub extern "C" fn scheduler_grant_capability(
     sched: *mut Scheduler,
     pid: usize,
     cap: u64,
-) -> bool {
+) -> i32 {
     if sched.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &mut *sched };
-    
-    if let Some(proc) = sched.processes.get_mut(&pid) {
-        proc.capabilities |= cap;
-        true
-    } else {
-        false
-    }
+    errno::to_c(sched.process_mut(pid).map(|proc| proc.capabilities |= cap))
 }
 
 #[no_mangle]
@@ -1040,18 +1141,12 @@ pub extern "C" fn scheduler_revoke_capability(
     sched: *mut Scheduler,
     pid: usize,
     cap: u64,
-) -> bool {
+) -> i32 {
     if sched.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &mut *sched };
-    
-    if let Some(proc) = sched.processes.get_mut(&pid) {
-        proc.capabilities &= !cap;
-        true
-    } else {
-        false
-    }
+    errno::to_c(sched.process_mut(pid).map(|proc| proc.capabilities &= !cap))
 }
 
 #[no_mangle]
@@ -1078,9 +1173,9 @@ pub extern "C" fn scheduler_get_user_memory_layout(
     pid: usize,
     out_base: *mut u32,
     out_size: *mut u32,
-) -> bool {
+) -> i32 {
     if sched.is_null() || out_base.is_null() || out_size.is_null() {
-        return false;
+        return KernelError::Inval.neg();
     }
     let sched = unsafe { &*sched };
     
@@ -1089,8 +1184,70 @@ pub extern "C" fn scheduler_get_user_memory_layout(
             ptr::write(out_base, proc.user_memory_base as u32);
             ptr::write(out_size, proc.user_memory_size as u32);
         }
-        true
+        0
     } else {
-        false
+        KernelError::NoProcess.neg()
+    }
+}
+
+/// Set the user region usermem.rs validates syscall pointers against. The
+/// arch code calls this once it knows where the task's image and stack live.
+#[no_mangle]
+pub extern "C" fn scheduler_set_user_region(
+    sched: *mut Scheduler,
+    pid: usize,
+    base: usize,
+    size: usize,
+) -> i32 {
+    if sched.is_null() || base.checked_add(size).is_none() {
+        return KernelError::Inval.neg();
+    }
+    let sched = unsafe { &mut *sched };
+    errno::to_c(sched.process_mut(pid).map(|proc| {
+        proc.user_memory_base = base;
+        proc.user_memory_size = size;
+    }))
+}
+
+/// Record ppid as the parent of pid. The child joins the parent's process
+/// group and inherits its uid and seccomp filters. Returns 0, -EINVAL or
+/// -ESRCH.
+#[no_mangle]
+pub extern "C" fn scheduler_set_parent(sched: *mut Scheduler, pid: usize, ppid: usize) -> i32 {
+    if sched.is_null() || pid == ppid {
+        return KernelError::Inval.neg();
     }
+    let sched = unsafe { &mut *sched };
+    let (pgid, uid, seccomp) = match sched.processes.get(&ppid) {
+        Some(parent) => (parent.pgid, parent.uid, parent.seccomp.clone()),
+        None => return KernelError::NoProcess.neg(),
+    };
+    errno::to_c(sched.process_mut(pid).map(|proc| {
+        proc.ppid = Some(ppid);
+        proc.pgid = pgid;
+        proc.uid = uid;
+        proc.seccomp = seccomp;
+    }))
+}
+
+/// Set the uid pid acts as for file permission checks (the session that
+/// started it). Returns 0, -EINVAL or -ESRCH.
+#[no_mangle]
+pub extern "C" fn scheduler_set_uid(sched: *mut Scheduler, pid: usize, uid: u16) -> i32 {
+    if sched.is_null() {
+        return KernelError::Inval.neg();
+    }
+    let sched = unsafe { &mut *sched };
+    errno::to_c(sched.process_mut(pid).map(|proc| proc.uid = uid))
+}
+
+/// Store the registers pid had at syscall entry, for the register dump.
+/// Returns 0, -EINVAL or -ESRCH.
+#[no_mangle]
+pub extern "C" fn scheduler_save_context(sched: *mut Scheduler, pid: usize, ctx: *const CpuContext) -> i32 {
+    if sched.is_null() || ctx.is_null() {
+        return KernelError::Inval.neg();
+    }
+    let sched = unsafe { &mut *sched };
+    errno::to_c(sched.process_mut(pid).map(|proc| proc.context = unsafe { (*ctx).clone() }))
 }
\ No newline at end of file
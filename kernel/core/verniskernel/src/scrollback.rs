@@ -0,0 +1,216 @@
+// scrollback.rs — Character-cell screen buffer with scrollback history
+//
+// A CellBuffer is a ring of lines, each `cols` cells wide. The newest `rows`
+// lines are the live screen, which a Vt draws into through VtScreen; lines
+// scrolled off the top of the full screen stay behind it as history, up to
+// the configured limit, after which the oldest are reused.
+//
+// The view is what a renderer shows: `view` lines back from the live screen
+// (0 = live). Moving the view never touches the live screen or the Vt
+// cursor, so output can keep arriving while the user reads history; the
+// owner decides whether new output snaps the view back (the console does).
+
+use alloc::vec::Vec;
+
+use crate::errno::{KResult, KernelError};
+use crate::font::Grapheme;
+use crate::vt::VtScreen;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub g: Grapheme,
+    pub fg: u32,
+    pub bg: u32,
+}
+
+impl Cell {
+    pub const fn blank(fg: u32, bg: u32) -> Self {
+        Cell { g: Grapheme::BLANK, fg, bg }
+    }
+}
+
+pub struct CellBuffer {
+    rows: u32,
+    cols: u32,
+    /// capacity lines of cols cells.
+    cells: Vec<Cell>,
+    capacity: u32,
+    /// Ring index of the oldest line.
+    first: u32,
+    /// Lines in use, screen included (rows..=capacity).
+    lines: u32,
+    view: u32,
+}
+
+impl CellBuffer {
+    /// A blank rows x cols screen keeping up to history lines behind it.
+    pub fn new(rows: u32, cols: u32, history: u32, fg: u32, bg: u32) -> KResult<Self> {
+        if rows == 0 || cols == 0 {
+            return Err(KernelError::Inval);
+        }
+        let capacity = rows.checked_add(history).ok_or(KernelError::Inval)?;
+        let total = (capacity as usize)
+            .checked_mul(cols as usize)
+            .ok_or(KernelError::NoMem)?;
+        let mut cells = Vec::new();
+        cells.try_reserve_exact(total).map_err(|_| KernelError::NoMem)?;
+        cells.resize(total, Cell::blank(fg, bg));
+        Ok(CellBuffer { rows, cols, cells, capacity, first: 0, lines: rows, view: 0 })
+    }
+
+    /// The same screen (and as much history as fits) with a new history
+    /// limit. The view returns to the live screen.
+    pub fn with_history(&self, history: u32) -> KResult<Self> {
+        let blank = self.line(0)[0];
+        let mut out = CellBuffer::new(self.rows, self.cols, history, blank.fg, blank.bg)?;
+        let keep = self.lines.min(out.capacity);
+        for i in 0..keep {
+            let src = self.lines - keep + i;
+            let at = out.index(i);
+            out.cells[at..at + self.cols as usize].copy_from_slice(self.line(src));
+        }
+        out.lines = keep;
+        Ok(out)
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u32 {
+        self.cols
+    }
+
+    /// Lines of history behind the live screen.
+    pub fn history(&self) -> u32 {
+        self.lines - self.rows
+    }
+
+    pub fn history_limit(&self) -> u32 {
+        self.capacity - self.rows
+    }
+
+    // Offset in cells of logical line n (0 = oldest).
+    fn index(&self, n: u32) -> usize {
+        (((self.first + n) % self.capacity) * self.cols) as usize
+    }
+
+    fn line(&self, n: u32) -> &[Cell] {
+        let at = self.index(n);
+        &self.cells[at..at + self.cols as usize]
+    }
+
+    fn line_mut(&mut self, n: u32) -> &mut [Cell] {
+        let at = self.index(n);
+        let cols = self.cols as usize;
+        &mut self.cells[at..at + cols]
+    }
+
+    /// Row r of the live screen.
+    pub fn screen_row(&self, r: u32) -> &[Cell] {
+        self.line(self.history() + r)
+    }
+
+    fn screen_row_mut(&mut self, r: u32) -> &mut [Cell] {
+        let n = self.history() + r;
+        self.line_mut(n)
+    }
+
+    /// Row r of the view.
+    pub fn view_row(&self, r: u32) -> &[Cell] {
+        self.line(self.history() - self.view + r)
+    }
+
+    /// Lines the view is scrolled back (0: live screen).
+    pub fn view(&self) -> u32 {
+        self.view
+    }
+
+    /// Move the view back into history (positive) or toward the live screen
+    /// (negative), clamped. Returns whether it moved.
+    pub fn scroll_view(&mut self, lines: i32) -> bool {
+        let target = (self.view as i64 + lines as i64).clamp(0, self.history() as i64) as u32;
+        let moved = target != self.view;
+        self.view = target;
+        moved
+    }
+
+    /// Back to the live screen. Returns whether the view moved.
+    pub fn reset_view(&mut self) -> bool {
+        let moved = self.view != 0;
+        self.view = 0;
+        moved
+    }
+
+    fn copy_row(&mut self, src: u32, dst: u32) {
+        let from = self.index(self.history() + src);
+        let to = self.index(self.history() + dst);
+        self.cells.copy_within(from..from + self.cols as usize, to);
+    }
+
+    // Append a blank line at the bottom, moving the screen down one line in
+    // the ring; the top screen line becomes history.
+    fn push_line(&mut self, bg: u32) {
+        if self.lines < self.capacity {
+            self.lines += 1;
+        } else {
+            self.first = (self.first + 1) % self.capacity;
+        }
+        // A scrolled-back view stays on the text it shows, unless that text
+        // was the oldest line and has just been reused
+        if self.view != 0 {
+            self.view = (self.view + 1).min(self.history());
+        }
+        let row = self.rows - 1;
+        for cell in self.screen_row_mut(row) {
+            *cell = Cell::blank(cell.fg, bg);
+        }
+    }
+}
+
+impl VtScreen for CellBuffer {
+    fn put(&mut self, row: u32, col: u32, g: Grapheme, fg: u32, bg: u32) {
+        if row < self.rows && col < self.cols {
+            self.screen_row_mut(row)[col as usize] = Cell { g, fg, bg };
+        }
+    }
+
+    fn clear(&mut self, row: u32, from: u32, to: u32, bg: u32) {
+        if row >= self.rows {
+            return;
+        }
+        let to = to.min(self.cols);
+        for cell in &mut self.screen_row_mut(row)[from.min(to) as usize..to as usize] {
+            *cell = Cell::blank(cell.fg, bg);
+        }
+    }
+
+    fn scroll(&mut self, top: u32, bottom: u32, lines: i32, bg: u32) {
+        let bottom = bottom.min(self.rows);
+        let n = lines.unsigned_abs().min(bottom.saturating_sub(top));
+        if n == 0 {
+            return;
+        }
+        // Scrolling the whole screen up feeds the history
+        if lines > 0 && top == 0 && bottom == self.rows {
+            for _ in 0..n {
+                self.push_line(bg);
+            }
+            return;
+        }
+        let blank = if lines > 0 {
+            for r in top..bottom - n {
+                self.copy_row(r + n, r);
+            }
+            bottom - n..bottom
+        } else {
+            for r in (top + n..bottom).rev() {
+                self.copy_row(r - n, r);
+            }
+            top..top + n
+        };
+        for r in blank {
+            self.clear(r, 0, self.cols, bg);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// seccomp.rs — Per-process syscall filter programs
+//
+// A filter is a short list of rules plus a default action. Rules are tried in
+// order; the first whose syscall number and (optional) argument comparison
+// match decides the action, otherwise the default applies. Actions follow
+// Linux seccomp: ALLOW, ERRNO (fail the call with a chosen errno), TRAP (fail
+// with ENOSYS and raise SIGSYS) and KILL (terminate the process).
+//
+// A process holds a stack of filters. Installing a filter pushes onto the
+// stack and every filter in it is evaluated, the most restrictive verdict
+// winning (KILL > TRAP > ERRNO > ALLOW), so a process can only tighten its
+// restrictions. Stacks are shared by reference and inherited across fork.
+//
+// syscall.rs evaluates the caller's stack after the capability check, for
+// every syscall: table calls in syscall_handler, and the calls the arch
+// dispatcher handles itself in syscall_gate.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::errno::{self, KResult, KernelError};
+use crate::heap::{self, HeapTag};
+use crate::scheduler::Scheduler;
+
+pub const SECCOMP_MAX_RULES: usize = 32;
+/// Filters one process may stack.
+pub const SECCOMP_MAX_DEPTH: u8 = 8;
+
+/// Rule nr matching every syscall.
+pub const SECCOMP_NR_ANY: u32 = 0xFFFF_FFFF;
+/// Rule arg meaning "no argument comparison".
+pub const SECCOMP_ARG_NONE: u8 = 0xFF;
+
+// Argument comparisons
+pub const SECCOMP_OP_EQ: u8 = 0;
+pub const SECCOMP_OP_NE: u8 = 1;
+pub const SECCOMP_OP_LT: u8 = 2;
+pub const SECCOMP_OP_LE: u8 = 3;
+pub const SECCOMP_OP_GT: u8 = 4;
+pub const SECCOMP_OP_GE: u8 = 5;
+/// Any bit of value set in the argument.
+pub const SECCOMP_OP_BITS_SET: u8 = 6;
+/// No bit of value set in the argument.
+pub const SECCOMP_OP_BITS_CLEAR: u8 = 7;
+
+// Actions: kind in the high 16 bits, errno (ERRNO only) in the low 16.
+pub const SECCOMP_RET_KILL: u32 = 0x0000_0000;
+pub const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+pub const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+pub const SECCOMP_RET_ALLOW: u32 = 0x7FFF_0000;
+const SECCOMP_RET_KIND: u32 = 0xFFFF_0000;
+const SECCOMP_RET_DATA: u32 = 0x0000_FFFF;
+
+/// One rule, shared with C (include/seccomp.h).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SeccompRule {
+    pub nr: u32,
+    /// Argument index 0-2, or SECCOMP_ARG_NONE.
+    pub arg: u8,
+    pub op: u8,
+    pub _pad: u16,
+    pub action: u32,
+    pub value: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Kill,
+    Trap,
+    Errno(KernelError),
+    Allow,
+}
+
+impl Action {
+    pub fn from_raw(raw: u32) -> KResult<Self> {
+        match raw & SECCOMP_RET_KIND {
+            SECCOMP_RET_KILL if raw == SECCOMP_RET_KILL => Ok(Self::Kill),
+            SECCOMP_RET_TRAP if raw == SECCOMP_RET_TRAP => Ok(Self::Trap),
+            SECCOMP_RET_ALLOW if raw == SECCOMP_RET_ALLOW => Ok(Self::Allow),
+            SECCOMP_RET_ERRNO => KernelError::from_errno((raw & SECCOMP_RET_DATA) as i32)
+                .map(Self::Errno)
+                .ok_or(KernelError::Inval),
+            _ => Err(KernelError::Inval),
+        }
+    }
+
+    /// Lower is more restrictive.
+    fn rank(self) -> u8 {
+        match self {
+            Self::Kill => 0,
+            Self::Trap => 1,
+            Self::Errno(_) => 2,
+            Self::Allow => 3,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Kill => write!(f, "kill"),
+            Self::Trap => write!(f, "trap"),
+            Self::Errno(e) => write!(f, "errno {}", e.name()),
+            Self::Allow => write!(f, "allow"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rule {
+    nr: u32,
+    cmp: Option<(usize, u8, usize)>,
+    action: Action,
+}
+
+impl Rule {
+    fn from_raw(r: &SeccompRule) -> KResult<Self> {
+        let cmp = match r.arg {
+            SECCOMP_ARG_NONE => None,
+            0..=2 if r.op <= SECCOMP_OP_BITS_CLEAR => Some((r.arg as usize, r.op, r.value)),
+            _ => return Err(KernelError::Inval),
+        };
+        Ok(Self { nr: r.nr, cmp, action: Action::from_raw(r.action)? })
+    }
+
+    fn matches(&self, num: u32, args: &[usize; 3]) -> bool {
+        if self.nr != SECCOMP_NR_ANY && self.nr != num {
+            return false;
+        }
+        let (idx, op, v) = match self.cmp {
+            Some(c) => c,
+            None => return true,
+        };
+        let a = args[idx];
+        match op {
+            SECCOMP_OP_EQ => a == v,
+            SECCOMP_OP_NE => a != v,
+            SECCOMP_OP_LT => a < v,
+            SECCOMP_OP_LE => a <= v,
+            SECCOMP_OP_GT => a > v,
+            SECCOMP_OP_GE => a >= v,
+            SECCOMP_OP_BITS_SET => a & v != 0,
+            _ => a & v == 0,
+        }
+    }
+}
+
+/// One installed filter and the filters installed before it.
+#[derive(Debug)]
+pub struct SeccompFilter {
+    rules: Vec<Rule>,
+    default: Action,
+    depth: u8,
+    prev: Option<Arc<SeccompFilter>>,
+}
+
+impl SeccompFilter {
+    /// Build a filter from raw rules, stacked on top of prev.
+    pub fn new(rules: &[SeccompRule], default: u32,
+               prev: Option<Arc<SeccompFilter>>) -> KResult<Arc<Self>> {
+        if rules.len() > SECCOMP_MAX_RULES {
+            return Err(KernelError::TooBig);
+        }
+        let depth = prev.as_ref().map(|p| p.depth).unwrap_or(0) + 1;
+        if depth > SECCOMP_MAX_DEPTH {
+            return Err(KernelError::NoSpace);
+        }
+        let default = Action::from_raw(default)?;
+        let _tag = heap::tag_scope(HeapTag::Scheduler);
+        let mut parsed = Vec::new();
+        parsed.try_reserve_exact(rules.len()).map_err(|_| KernelError::NoMem)?;
+        for r in rules {
+            parsed.push(Rule::from_raw(r)?);
+        }
+        Ok(Arc::new(Self { rules: parsed, default, depth, prev }))
+    }
+
+    fn eval_one(&self, num: u32, args: &[usize; 3]) -> Action {
+        self.rules.iter()
+            .find(|r| r.matches(num, args))
+            .map(|r| r.action)
+            .unwrap_or(self.default)
+    }
+
+    /// Verdict of the whole stack: the most restrictive filter wins.
+    pub fn evaluate(&self, num: u32, args: &[usize; 3]) -> Action {
+        let mut verdict = Action::Allow;
+        let mut f = Some(self);
+        while let Some(filter) = f {
+            let a = filter.eval_one(num, args);
+            if a.rank() < verdict.rank() {
+                verdict = a;
+            }
+            if verdict == Action::Kill {
+                break;
+            }
+            f = filter.prev.as_deref();
+        }
+        verdict
+    }
+
+    /// Filters in the stack, this one included.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+}
+
+/// Push a filter onto the stack of pid.
+pub fn install(sched: &mut Scheduler, pid: usize, rules: &[SeccompRule], default: u32) -> KResult<()> {
+    let proc = sched.process_mut(pid)?;
+    proc.seccomp = Some(SeccompFilter::new(rules, default, proc.seccomp.clone())?);
+    Ok(())
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Install a filter of count rules on pid. 0, -EINVAL, -ESRCH, -E2BIG (too
+/// many rules), -ENOSPC (stack full) or -ENOMEM.
+#[no_mangle]
+pub extern "C" fn seccomp_install(
+    sched: *mut Scheduler,
+    pid: usize,
+    rules: *const SeccompRule,
+    count: usize,
+    default_action: u32,
+) -> i32 {
+    if sched.is_null() || (rules.is_null() && count != 0) {
+        return KernelError::Inval.neg();
+    }
+    let rules = if count == 0 { &[][..] } else { unsafe { core::slice::from_raw_parts(rules, count) } };
+    errno::to_c(install(unsafe { &mut *sched }, pid, rules, default_action))
+}
+
+/// Number of filters stacked on pid (0 if none), or -ESRCH.
+#[no_mangle]
+pub extern "C" fn seccomp_depth(sched: *const Scheduler, pid: usize) -> i32 {
+    match unsafe { sched.as_ref() }.and_then(|s| s.processes.get(&pid)) {
+        Some(p) => p.seccomp.as_ref().map(|f| f.depth() as i32).unwrap_or(0),
+        None => KernelError::NoProcess.neg(),
+    }
+}
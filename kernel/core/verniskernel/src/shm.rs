@@ -0,0 +1,594 @@
+// shm.rs — Named shared memory segments
+//
+// Segments are zero-filled, page-aligned blocks carved from the kernel heap
+// (identity-mapped, so their address is also their physical address). This
+// module keeps the bookkeeping: names, per-process handles and mappings,
+// reference counts and permission checks against the caller's capability
+// bitmask. Installing the pages into a task's address space is done by the
+// arch code (sys_shm_map / sys_shm_unmap), which calls in here first.
+//
+// A segment holds one reference per open handle and one per mapping. It is
+// freed once it has been unlinked and the last reference is dropped; until
+// unlinked it persists even with no users, like a POSIX shm object.
+
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ffi::c_char;
+use core::fmt::{self, Write};
+
+use crate::errno::KernelError;
+use crate::heap::{self, HeapTag};
+use crate::scheduler::{
+    ProcessControlBlock, Scheduler,
+    CAP_ALLOC_MEMORY, CAP_IPC_RECEIVE, CAP_IPC_SEND,
+};
+use crate::textbuf::TextBuf;
+
+pub const SHM_NAME_MAX: usize = 32;
+pub const SHM_MAX_SEGMENTS: usize = 32;
+pub const SHM_MAX_SIZE: usize = 1024 * 1024;
+const SHM_PAGE_SIZE: usize = 4096;
+
+// Segment mode bits: the owner always has read/write access.
+pub const SHM_MODE_OTHER_READ: u32 = 0x1;
+pub const SHM_MODE_OTHER_WRITE: u32 = 0x2;
+
+// Error codes returned across FFI (include/shm.h), negative errno values
+pub const SHM_OK: i32 = 0;
+pub const SHM_ERR_INVAL: i32 = KernelError::Inval.neg();
+pub const SHM_ERR_NOENT: i32 = KernelError::NoEntry.neg();
+pub const SHM_ERR_EXIST: i32 = KernelError::Exists.neg();
+pub const SHM_ERR_PERM: i32 = KernelError::Access.neg();
+pub const SHM_ERR_NOMEM: i32 = KernelError::NoMem.neg();
+pub const SHM_ERR_LIMIT: i32 = KernelError::NoSpace.neg();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShmError {
+    Invalid,
+    NotFound,
+    Exists,
+    PermissionDenied,
+    NoMemory,
+    Limit,
+}
+
+impl ShmError {
+    pub fn code(self) -> i32 {
+        match self {
+            ShmError::Invalid => SHM_ERR_INVAL,
+            ShmError::NotFound => SHM_ERR_NOENT,
+            ShmError::Exists => SHM_ERR_EXIST,
+            ShmError::PermissionDenied => SHM_ERR_PERM,
+            ShmError::NoMemory => SHM_ERR_NOMEM,
+            ShmError::Limit => SHM_ERR_LIMIT,
+        }
+    }
+}
+
+// =============================================================================
+// Data types
+// =============================================================================
+
+pub struct ShmSegment {
+    pub id: u32,
+    name: [u8; SHM_NAME_MAX],
+    name_len: u8,
+    pub size: usize,
+    base: *mut u8,
+    pub owner_pid: usize,
+    pub mode: u32,
+    pub refcount: u32,
+    pub map_count: u32,
+    pub unlinked: bool,
+}
+
+impl ShmSegment {
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len as usize]).unwrap_or("?")
+    }
+
+    pub fn base(&self) -> usize {
+        self.base as usize
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::from_size_align(self.size, SHM_PAGE_SIZE).unwrap()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ShmHandle {
+    pid: usize,
+    id: u32,
+    writable: bool,
+}
+
+#[derive(Clone, Copy)]
+struct ShmMapping {
+    pid: usize,
+    id: u32,
+    vaddr: usize,
+}
+
+pub struct ShmRegistry {
+    segments: BTreeMap<u32, ShmSegment>,
+    handles: Vec<ShmHandle>,
+    mappings: Vec<ShmMapping>,
+    next_id: u32,
+}
+
+static mut SHM: ShmRegistry = ShmRegistry::new();
+
+fn registry() -> &'static mut ShmRegistry {
+    unsafe { &mut *core::ptr::addr_of_mut!(SHM) }
+}
+
+// The heap's OOM killer can release a victim's segments from inside an
+// allocation made while the registry is being updated. Such releases are
+// queued and applied at the start of the next registry operation.
+const DEFERRED_MAX: usize = 8;
+static mut BUSY: bool = false;
+static mut DEFERRED: [usize; DEFERRED_MAX] = [0; DEFERRED_MAX];
+
+struct BusyGuard;
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        unsafe { BUSY = false; }
+    }
+}
+
+/// Apply deferred releases (when a scheduler is available), then mark the
+/// registry busy until the returned guard is dropped.
+fn begin(sched: *mut Scheduler) -> BusyGuard {
+    if !sched.is_null() {
+        let deferred = unsafe { &mut *core::ptr::addr_of_mut!(DEFERRED) };
+        for slot in deferred.iter_mut().filter(|p| **p != 0) {
+            let pid = core::mem::replace(slot, 0);
+            registry().release_process(unsafe { sched.as_mut() }, pid);
+        }
+    }
+    unsafe { BUSY = true; }
+    BusyGuard
+}
+
+/// Pid 0 is the kernel itself rather than a task.
+const KERNEL_PID: usize = 0;
+
+/// Owner always passes; others need the matching mode bit. Only the kernel
+/// context (pid 0) bypasses the mode bits: ELF programs run as Kernel-type
+/// processes, so the process type says nothing about trust.
+fn mode_allows(seg: &ShmSegment, proc: &ProcessControlBlock, write: bool) -> bool {
+    if proc.pid == seg.owner_pid || proc.pid == KERNEL_PID {
+        return true;
+    }
+    if write {
+        seg.mode & SHM_MODE_OTHER_WRITE != 0
+    } else {
+        seg.mode & (SHM_MODE_OTHER_READ | SHM_MODE_OTHER_WRITE) != 0
+    }
+}
+
+fn process(sched: &Scheduler, pid: usize) -> Result<&ProcessControlBlock, ShmError> {
+    sched.processes.get(&pid).map(|p| &**p).ok_or(ShmError::Invalid)
+}
+
+fn adjust_shared_size(sched: &mut Scheduler, pid: usize, bytes: usize, add: bool) {
+    if let Some(p) = sched.processes.get_mut(&pid) {
+        let info = &mut p.memory_info;
+        info.shared_memory_size = if add {
+            info.shared_memory_size + bytes
+        } else {
+            info.shared_memory_size.saturating_sub(bytes)
+        };
+    }
+}
+
+impl Default for ShmRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShmRegistry {
+    pub const fn new() -> Self {
+        Self {
+            segments: BTreeMap::new(),
+            handles: Vec::new(),
+            mappings: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&ShmSegment> {
+        self.segments.get(&id)
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<u32> {
+        self.segments.values()
+            .find(|s| !s.unlinked && s.name() == name)
+            .map(|s| s.id)
+    }
+
+    fn handle_index(&self, pid: usize, id: u32) -> Option<usize> {
+        self.handles.iter().position(|h| h.pid == pid && h.id == id)
+    }
+
+    pub fn create(&mut self, sched: &Scheduler, pid: usize, name: &str, size: usize, mode: u32)
+        -> Result<u32, ShmError>
+    {
+        if name.is_empty() || name.len() >= SHM_NAME_MAX || size == 0 || size > SHM_MAX_SIZE {
+            return Err(ShmError::Invalid);
+        }
+        if !process(sched, pid)?.has_capability(CAP_ALLOC_MEMORY) {
+            return Err(ShmError::PermissionDenied);
+        }
+        if self.find_by_name(name).is_some() {
+            return Err(ShmError::Exists);
+        }
+        if self.segments.len() >= SHM_MAX_SEGMENTS {
+            return Err(ShmError::Limit);
+        }
+
+        let size = (size + SHM_PAGE_SIZE - 1) & !(SHM_PAGE_SIZE - 1);
+        let layout = Layout::from_size_align(size, SHM_PAGE_SIZE).map_err(|_| ShmError::Invalid)?;
+        let base = {
+            let _tag = heap::tag_scope(HeapTag::Ipc);
+            unsafe { alloc_zeroed(layout) }
+        };
+        if base.is_null() {
+            return Err(ShmError::NoMemory);
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut name_buf = [0u8; SHM_NAME_MAX];
+        name_buf[..name.len()].copy_from_slice(name.as_bytes());
+        self.segments.insert(id, ShmSegment {
+            id,
+            name: name_buf,
+            name_len: name.len() as u8,
+            size,
+            base,
+            owner_pid: pid,
+            mode: mode & (SHM_MODE_OTHER_READ | SHM_MODE_OTHER_WRITE),
+            refcount: 1,
+            map_count: 0,
+            unlinked: false,
+        });
+        self.handles.push(ShmHandle { pid, id, writable: true });
+        Ok(id)
+    }
+
+    pub fn open(&mut self, sched: &Scheduler, pid: usize, name: &str, write: bool)
+        -> Result<u32, ShmError>
+    {
+        let proc = process(sched, pid)?;
+        let id = self.find_by_name(name).ok_or(ShmError::NotFound)?;
+        let seg = &self.segments[&id];
+
+        let caps = if write { CAP_IPC_RECEIVE | CAP_IPC_SEND } else { CAP_IPC_RECEIVE };
+        if !proc.has_capability(caps) || !mode_allows(seg, proc, write) {
+            return Err(ShmError::PermissionDenied);
+        }
+
+        if let Some(i) = self.handle_index(pid, id) {
+            self.handles[i].writable |= write;
+            return Ok(id);
+        }
+        self.handles.push(ShmHandle { pid, id, writable: write });
+        if let Some(seg) = self.segments.get_mut(&id) {
+            seg.refcount += 1;
+        }
+        Ok(id)
+    }
+
+    /// Record a mapping of `id` at `vaddr` in `pid`. Returns the segment's
+    /// base address and size for the caller to install the pages.
+    pub fn map(&mut self, sched: &mut Scheduler, pid: usize, id: u32, vaddr: usize, write: bool)
+        -> Result<(usize, usize), ShmError>
+    {
+        let h = self.handle_index(pid, id).ok_or(ShmError::NotFound)?;
+        if write && !self.handles[h].writable {
+            return Err(ShmError::PermissionDenied);
+        }
+        if vaddr == 0 || !vaddr.is_multiple_of(SHM_PAGE_SIZE) {
+            return Err(ShmError::Invalid);
+        }
+        let seg = self.segments.get_mut(&id).ok_or(ShmError::NotFound)?;
+        seg.refcount += 1;
+        seg.map_count += 1;
+        let (base, size) = (seg.base(), seg.size);
+
+        self.mappings.push(ShmMapping { pid, id, vaddr });
+        adjust_shared_size(sched, pid, size, true);
+        Ok((base, size))
+    }
+
+    /// Drop the mapping at `vaddr`. Returns (segment id, size).
+    pub fn unmap(&mut self, sched: &mut Scheduler, pid: usize, vaddr: usize)
+        -> Result<(u32, usize), ShmError>
+    {
+        let i = self.mappings.iter()
+            .position(|m| m.pid == pid && m.vaddr == vaddr)
+            .ok_or(ShmError::Invalid)?;
+        let (id, size) = self.drop_mapping(i);
+        adjust_shared_size(sched, pid, size, false);
+        Ok((id, size))
+    }
+
+    fn drop_mapping(&mut self, i: usize) -> (u32, usize) {
+        let m = self.mappings.swap_remove(i);
+        let size = self.segments.get(&m.id).map(|s| s.size).unwrap_or(0);
+        if let Some(seg) = self.segments.get_mut(&m.id) {
+            seg.map_count = seg.map_count.saturating_sub(1);
+        }
+        self.release(m.id);
+        (m.id, size)
+    }
+
+    /// Take a kernel reference on a segment `pid` has mapped writable, for
+    /// in-kernel users of its memory (uring.rs). Returns (base, size); the
+    /// segment stays allocated until the matching unpin().
+    pub fn pin(&mut self, pid: usize, id: u32) -> Result<(usize, usize), ShmError> {
+        let h = self.handle_index(pid, id).ok_or(ShmError::NotFound)?;
+        if !self.handles[h].writable {
+            return Err(ShmError::PermissionDenied);
+        }
+        if !self.mappings.iter().any(|m| m.pid == pid && m.id == id) {
+            return Err(ShmError::Invalid);
+        }
+        let seg = self.segments.get_mut(&id).ok_or(ShmError::NotFound)?;
+        seg.refcount += 1;
+        Ok((seg.base(), seg.size))
+    }
+
+    pub fn unpin(&mut self, id: u32) {
+        self.release(id);
+    }
+
+    pub fn close(&mut self, pid: usize, id: u32) -> Result<(), ShmError> {
+        let i = self.handle_index(pid, id).ok_or(ShmError::NotFound)?;
+        self.handles.swap_remove(i);
+        self.release(id);
+        Ok(())
+    }
+
+    /// Remove the name. Only the owner, or the kernel context, may
+    /// unlink; the memory is freed once the last reference goes away.
+    pub fn unlink(&mut self, sched: &Scheduler, pid: usize, name: &str) -> Result<(), ShmError> {
+        if pid != KERNEL_PID {
+            process(sched, pid)?;
+        }
+        let id = self.find_by_name(name).ok_or(ShmError::NotFound)?;
+        let seg = self.segments.get_mut(&id).ok_or(ShmError::NotFound)?;
+        if seg.owner_pid != pid && pid != KERNEL_PID {
+            return Err(ShmError::PermissionDenied);
+        }
+        seg.unlinked = true;
+        if seg.refcount == 0 {
+            self.destroy(id);
+        }
+        Ok(())
+    }
+
+    /// Give `child` copies of the parent's handles and mappings (fork).
+    pub fn fork(&mut self, sched: &mut Scheduler, parent: usize, child: usize) {
+        let handles: Vec<ShmHandle> = self.handles.iter()
+            .filter(|h| h.pid == parent)
+            .map(|h| ShmHandle { pid: child, ..*h })
+            .collect();
+        let mappings: Vec<ShmMapping> = self.mappings.iter()
+            .filter(|m| m.pid == parent)
+            .map(|m| ShmMapping { pid: child, ..*m })
+            .collect();
+
+        for h in &handles {
+            if let Some(seg) = self.segments.get_mut(&h.id) {
+                seg.refcount += 1;
+            }
+        }
+        for m in &mappings {
+            if let Some(seg) = self.segments.get_mut(&m.id) {
+                seg.refcount += 1;
+                seg.map_count += 1;
+                let size = seg.size;
+                adjust_shared_size(sched, child, size, true);
+            }
+        }
+        self.handles.extend(handles);
+        self.mappings.extend(mappings);
+    }
+
+    /// Drop every handle and mapping held by `pid`. Returns how many
+    /// references were released. Without a scheduler (the OOM killer) the
+    /// dying process's shared-size figure is left as it was.
+    pub fn release_process(&mut self, mut sched: Option<&mut Scheduler>, pid: usize) -> usize {
+        let mut released = 0;
+        while let Some(i) = self.mappings.iter().position(|m| m.pid == pid) {
+            let (_, size) = self.drop_mapping(i);
+            if let Some(s) = sched.as_deref_mut() {
+                adjust_shared_size(s, pid, size, false);
+            }
+            released += 1;
+        }
+        while let Some(i) = self.handles.iter().position(|h| h.pid == pid) {
+            let id = self.handles[i].id;
+            let _ = self.close(pid, id);
+            released += 1;
+        }
+        released
+    }
+
+    fn release(&mut self, id: u32) {
+        let destroy = match self.segments.get_mut(&id) {
+            Some(seg) => {
+                seg.refcount = seg.refcount.saturating_sub(1);
+                seg.refcount == 0 && seg.unlinked
+            }
+            None => false,
+        };
+        if destroy {
+            self.destroy(id);
+        }
+    }
+
+    fn destroy(&mut self, id: u32) {
+        if let Some(seg) = self.segments.remove(&id) {
+            unsafe { dealloc(seg.base, seg.layout()); }
+        }
+    }
+
+    pub fn render<W: Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "# id  name                             size  owner mode refs maps")?;
+        for s in self.segments.values() {
+            writeln!(out, "{:>4}  {:<30} {:>7} {:>6}  {}{}  {:>4} {:>4}{}",
+                     s.id, s.name(), s.size, s.owner_pid,
+                     if s.mode & SHM_MODE_OTHER_READ != 0 { 'r' } else { '-' },
+                     if s.mode & SHM_MODE_OTHER_WRITE != 0 { 'w' } else { '-' },
+                     s.refcount, s.map_count,
+                     if s.unlinked { " (deleted)" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+/// See ShmRegistry::pin.
+pub fn pin(sched: *mut Scheduler, pid: usize, id: u32) -> Result<(usize, usize), ShmError> {
+    let _busy = begin(sched);
+    registry().pin(pid, id)
+}
+
+pub fn unpin(sched: *mut Scheduler, id: u32) {
+    let _busy = begin(sched);
+    registry().unpin(id);
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+unsafe fn name_arg<'a>(name: *const c_char) -> Option<&'a str> {
+    if name.is_null() {
+        return None;
+    }
+    core::ffi::CStr::from_ptr(name).to_str().ok()
+}
+
+fn to_code<T>(r: Result<T, ShmError>, ok: impl FnOnce(T) -> i32) -> i32 {
+    match r {
+        Ok(v) => ok(v),
+        Err(e) => e.code(),
+    }
+}
+
+/// Create a named segment owned by `pid`. Returns segment id or SHM_ERR_*.
+#[no_mangle]
+pub extern "C" fn shm_create(
+    sched: *mut Scheduler,
+    pid: usize,
+    name: *const c_char,
+    size: usize,
+    mode: u32,
+) -> i32 {
+    if sched.is_null() { return SHM_ERR_INVAL; }
+    let _busy = begin(sched);
+    let sched = unsafe { &*sched };
+    let name = match unsafe { name_arg(name) } { Some(n) => n, None => return SHM_ERR_INVAL };
+    to_code(registry().create(sched, pid, name, size, mode), |id| id as i32)
+}
+
+/// Open an existing segment by name. Returns segment id or SHM_ERR_*.
+#[no_mangle]
+pub extern "C" fn shm_open(sched: *mut Scheduler, pid: usize, name: *const c_char, write: bool) -> i32 {
+    if sched.is_null() { return SHM_ERR_INVAL; }
+    let _busy = begin(sched);
+    let sched = unsafe { &*sched };
+    let name = match unsafe { name_arg(name) } { Some(n) => n, None => return SHM_ERR_INVAL };
+    to_code(registry().open(sched, pid, name, write), |id| id as i32)
+}
+
+/// Size in bytes of segment id (page-rounded), or 0 if it does not exist.
+#[no_mangle]
+pub extern "C" fn shm_size(id: u32) -> usize {
+    registry().get(id).map(|s| s.size).unwrap_or(0)
+}
+
+/// Record a mapping of id at vaddr. On success writes the segment's
+/// physical base to *out_base and returns SHM_OK.
+#[no_mangle]
+pub extern "C" fn shm_map(
+    sched: *mut Scheduler,
+    pid: usize,
+    id: u32,
+    vaddr: usize,
+    write: bool,
+    out_base: *mut usize,
+) -> i32 {
+    if sched.is_null() || out_base.is_null() { return SHM_ERR_INVAL; }
+    let _busy = begin(sched);
+    let sched = unsafe { &mut *sched };
+    to_code(registry().map(sched, pid, id, vaddr, write), |(base, _)| {
+        unsafe { *out_base = base; }
+        SHM_OK
+    })
+}
+
+/// Drop the mapping at vaddr. Returns the unmapped size or SHM_ERR_*.
+#[no_mangle]
+pub extern "C" fn shm_unmap(sched: *mut Scheduler, pid: usize, vaddr: usize) -> i32 {
+    if sched.is_null() { return SHM_ERR_INVAL; }
+    let _busy = begin(sched);
+    let sched = unsafe { &mut *sched };
+    to_code(registry().unmap(sched, pid, vaddr), |(_, size)| size as i32)
+}
+
+#[no_mangle]
+pub extern "C" fn shm_close(pid: usize, id: u32) -> i32 {
+    let _busy = begin(core::ptr::null_mut());
+    to_code(registry().close(pid, id), |_| SHM_OK)
+}
+
+#[no_mangle]
+pub extern "C" fn shm_unlink(sched: *mut Scheduler, pid: usize, name: *const c_char) -> i32 {
+    if sched.is_null() { return SHM_ERR_INVAL; }
+    let _busy = begin(sched);
+    let sched = unsafe { &*sched };
+    let name = match unsafe { name_arg(name) } { Some(n) => n, None => return SHM_ERR_INVAL };
+    to_code(registry().unlink(sched, pid, name), |_| SHM_OK)
+}
+
+#[no_mangle]
+pub extern "C" fn shm_fork(sched: *mut Scheduler, parent_pid: usize, child_pid: usize) {
+    if sched.is_null() { return; }
+    let _busy = begin(sched);
+    registry().fork(unsafe { &mut *sched }, parent_pid, child_pid);
+}
+
+/// Release everything `pid` holds (exit / kill). The caller must already
+/// have removed the pages from the task's address space. `sched` may be
+/// null when called from the heap's OOM killer.
+#[no_mangle]
+pub extern "C" fn shm_release_process(sched: *mut Scheduler, pid: usize) -> u32 {
+    if unsafe { BUSY } {
+        let deferred = unsafe { &mut *core::ptr::addr_of_mut!(DEFERRED) };
+        match deferred.iter_mut().find(|p| **p == 0) {
+            Some(slot) => *slot = pid,
+            None => crate::kernel_print_raw("[shm] deferred release queue full\n"),
+        }
+        return 0;
+    }
+    let _busy = begin(sched);
+    registry().release_process(unsafe { sched.as_mut() }, pid) as u32
+}
+
+#[no_mangle]
+pub extern "C" fn shm_render_info(buf: *mut u8, buf_len: u32) -> u32 {
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = registry().render(&mut out);
+    out.finish_cstr() as u32
+}
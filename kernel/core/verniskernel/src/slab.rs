@@ -0,0 +1,458 @@
+// slab.rs — Object-cache (slab) allocator for fixed-size kernel objects
+//
+// Hot, fixed-size kernel objects (PCBs, AI event records) are
+// carved out of page-sized slabs taken from the global buddy heap and recycled
+// through per-cache free lists, so they no longer fragment the general heap.
+// Every cache is named and keeps object counters that `render_slabinfo()`
+// formats as a /proc/slabinfo-style table.
+//
+// Each slab keeps a bitmap of its live objects, so a double free or a pointer
+// into the middle of an object is refused (and counted) instead of
+// corrupting the free list; frees from C go through the same check.
+//
+// Like the rest of the kernel, caches live in a single-core `static mut`
+// registry; callers must not allocate from IRQ context.
+
+use alloc::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use alloc::vec::Vec;
+use core::ffi::c_char;
+use core::fmt::{self, Write};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use crate::heap::{self, HeapTag};
+use crate::textbuf::TextBuf;
+
+pub const SLAB_PAGE_SIZE: usize = 4096;
+pub const SLAB_NAME_LEN: usize = 16;
+const MIN_OBJS_PER_SLAB: usize = 8;
+/// Objects are at least 8 bytes and slabs past one page hold under 16, so a
+/// slab never has more than SLAB_PAGE_SIZE / 8 objects.
+const MAX_OBJS_PER_SLAB: usize = SLAB_PAGE_SIZE / 8;
+const LIVE_WORDS: usize = MAX_OBJS_PER_SLAB / 64;
+
+pub type SlabId = u32;
+
+/// Well-known caches, created in this order by `init()`.
+pub const SLAB_CACHE_PCB: SlabId = 0;
+pub const SLAB_CACHE_EVENT_RECORD: SlabId = 1;
+
+// =============================================================================
+// Slab cache
+// =============================================================================
+
+struct FreeObj {
+    next: *mut FreeObj,
+}
+
+struct Slab {
+    base: *mut u8,
+    in_use: u32,
+    /// Bit i set while object i is allocated.
+    live: [u64; LIVE_WORDS],
+}
+
+impl Slab {
+    fn is_live(&self, i: usize) -> bool {
+        self.live[i / 64] & (1u64 << (i % 64)) != 0
+    }
+
+    fn set_live(&mut self, i: usize, on: bool) {
+        if on {
+            self.live[i / 64] |= 1u64 << (i % 64);
+        } else {
+            self.live[i / 64] &= !(1u64 << (i % 64));
+        }
+    }
+}
+
+pub struct SlabCache {
+    name: [u8; SLAB_NAME_LEN],
+    name_len: u8,
+    obj_size: usize,
+    align: usize,
+    slab_bytes: usize,
+    objs_per_slab: usize,
+    free_list: *mut FreeObj,
+    slabs: Vec<Slab>,
+    active_objs: usize,
+    alloc_count: u64,
+    free_count: u64,
+    grow_failures: u32,
+    bad_frees: u32,
+}
+
+impl SlabCache {
+    pub fn new(name: &str, size: usize, align: usize) -> Self {
+        let mut name_buf = [0u8; SLAB_NAME_LEN];
+        let nb = name.as_bytes();
+        let nlen = nb.len().min(SLAB_NAME_LEN);
+        name_buf[..nlen].copy_from_slice(&nb[..nlen]);
+
+        // Every free object doubles as a free-list link.
+        let align = align.max(core::mem::align_of::<FreeObj>()).next_power_of_two();
+        let raw = size.max(core::mem::size_of::<FreeObj>());
+        let obj_size = (raw + align - 1) & !(align - 1);
+
+        let pages = (obj_size * MIN_OBJS_PER_SLAB).div_ceil(SLAB_PAGE_SIZE);
+        let slab_bytes = pages.max(1) * SLAB_PAGE_SIZE;
+
+        Self {
+            name: name_buf,
+            name_len: nlen as u8,
+            obj_size,
+            align,
+            slab_bytes,
+            objs_per_slab: (slab_bytes / obj_size).min(MAX_OBJS_PER_SLAB),
+            free_list: ptr::null_mut(),
+            slabs: Vec::new(),
+            active_objs: 0,
+            alloc_count: 0,
+            free_count: 0,
+            grow_failures: 0,
+            bad_frees: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len as usize]).unwrap_or("?")
+    }
+
+    pub fn obj_size(&self) -> usize {
+        self.obj_size
+    }
+
+    pub fn active_objs(&self) -> usize {
+        self.active_objs
+    }
+
+    pub fn total_objs(&self) -> usize {
+        self.slabs.len() * self.objs_per_slab
+    }
+
+    /// Bytes of heap held by this cache (used + free objects).
+    pub fn footprint(&self) -> usize {
+        self.slabs.len() * self.slab_bytes
+    }
+
+    fn slab_layout(&self) -> Layout {
+        // slab_bytes is a non-zero multiple of the page size
+        unsafe { Layout::from_size_align_unchecked(self.slab_bytes, SLAB_PAGE_SIZE) }
+    }
+
+    /// Take one fresh slab from the heap and thread its objects onto the free list.
+    fn grow(&mut self) -> bool {
+        let base = {
+            let _tag = heap::tag_scope(HeapTag::Slab);
+            unsafe {
+                GROWING = true;
+                let base = alloc(self.slab_layout());
+                GROWING = false;
+                base
+            }
+        };
+        if base.is_null() {
+            self.grow_failures += 1;
+            return false;
+        }
+        // Push in reverse so objects are handed out in address order.
+        for i in (0..self.objs_per_slab).rev() {
+            let obj = unsafe { base.add(i * self.obj_size) } as *mut FreeObj;
+            unsafe { (*obj).next = self.free_list; }
+            self.free_list = obj;
+        }
+        self.slabs.push(Slab { base, in_use: 0, live: [0; LIVE_WORDS] });
+        true
+    }
+
+    /// (slab, object) indices of an object start inside this cache.
+    fn locate(&self, p: *mut u8) -> Option<(usize, usize)> {
+        let addr = p as usize;
+        let span = self.objs_per_slab * self.obj_size;
+        self.slabs.iter().enumerate().find_map(|(i, s)| {
+            let off = addr.checked_sub(s.base as usize)?;
+            (off < span && off % self.obj_size == 0).then_some((i, off / self.obj_size))
+        })
+    }
+
+    /// Allocate one object. Returns null if the heap cannot supply a new slab.
+    pub fn alloc(&mut self) -> *mut u8 {
+        if self.free_list.is_null() && !self.grow() {
+            return ptr::null_mut();
+        }
+        let obj = self.free_list;
+        self.free_list = unsafe { (*obj).next };
+        let p = obj as *mut u8;
+        if let Some((idx, obj)) = self.locate(p) {
+            self.slabs[idx].in_use += 1;
+            self.slabs[idx].set_live(obj, true);
+        }
+        self.active_objs += 1;
+        self.alloc_count += 1;
+        p
+    }
+
+    /// Return an object to the free list. Pointers that do not belong to this
+    /// cache, or whose object is already free, are rejected rather than
+    /// corrupting the list.
+    pub fn free(&mut self, p: *mut u8) -> bool {
+        let (idx, i) = match self.locate(p) {
+            Some(at) if self.slabs[at.0].is_live(at.1) => at,
+            _ => {
+                self.bad_frees += 1;
+                return false;
+            }
+        };
+        self.slabs[idx].set_live(i, false);
+        let obj = p as *mut FreeObj;
+        unsafe { (*obj).next = self.free_list; }
+        self.free_list = obj;
+        self.slabs[idx].in_use = self.slabs[idx].in_use.saturating_sub(1);
+        self.active_objs = self.active_objs.saturating_sub(1);
+        self.free_count += 1;
+        true
+    }
+
+    /// Release completely unused slabs back to the heap. Returns bytes freed.
+    pub fn reap(&mut self) -> usize {
+        if self.slabs.iter().all(|s| s.in_use != 0) {
+            return 0;
+        }
+
+        // Unlink every free object that lives in an empty slab.
+        let mut kept: *mut FreeObj = ptr::null_mut();
+        let mut cur = self.free_list;
+        while !cur.is_null() {
+            let next = unsafe { (*cur).next };
+            let in_empty = self.locate(cur as *mut u8)
+                .map(|(i, _)| self.slabs[i].in_use == 0)
+                .unwrap_or(false);
+            if !in_empty {
+                unsafe { (*cur).next = kept; }
+                kept = cur;
+            }
+            cur = next;
+        }
+        self.free_list = kept;
+
+        let layout = self.slab_layout();
+        let before = self.slabs.len();
+        self.slabs.retain(|s| {
+            if s.in_use == 0 {
+                unsafe { dealloc(s.base, layout); }
+                false
+            } else {
+                true
+            }
+        });
+        (before - self.slabs.len()) * self.slab_bytes
+    }
+}
+
+// =============================================================================
+// Cache registry
+// =============================================================================
+
+static mut SLAB_CACHES: Vec<SlabCache> = Vec::new();
+static mut GROWING: bool = false;
+
+fn caches() -> &'static mut Vec<SlabCache> {
+    unsafe { &mut *ptr::addr_of_mut!(SLAB_CACHES) }
+}
+
+/// Create the well-known caches. Called once, right after the heap is up.
+pub fn init() {
+    if !caches().is_empty() {
+        return;
+    }
+    create_cache("pcb", core::mem::size_of::<crate::scheduler::ProcessControlBlock>(),
+                 core::mem::align_of::<crate::scheduler::ProcessControlBlock>());
+    create_cache("event_record", core::mem::size_of::<crate::ai::types::EventRecord>(),
+                 core::mem::align_of::<crate::ai::types::EventRecord>());
+}
+
+/// Look up a cache by name, creating it if it does not exist yet.
+/// An existing cache is only reused if its objects are large enough. Names
+/// longer than SLAB_NAME_LEN are refused: they could never be found again.
+pub fn create_cache(name: &str, size: usize, align: usize) -> Option<SlabId> {
+    if size == 0 || name.is_empty() || name.len() > SLAB_NAME_LEN {
+        return None;
+    }
+    let list = caches();
+    if let Some(pos) = list.iter().position(|c| c.name() == name) {
+        let c = &list[pos];
+        return if c.obj_size >= size && c.align >= align { Some(pos as SlabId) } else { None };
+    }
+    list.push(SlabCache::new(name, size, align));
+    Some((list.len() - 1) as SlabId)
+}
+
+pub fn find_cache(name: &str) -> Option<SlabId> {
+    caches().iter().position(|c| c.name() == name).map(|p| p as SlabId)
+}
+
+pub fn cache(id: SlabId) -> Option<&'static mut SlabCache> {
+    caches().get_mut(id as usize)
+}
+
+pub fn cache_alloc(id: SlabId) -> *mut u8 {
+    match cache(id) {
+        Some(c) => c.alloc(),
+        None => ptr::null_mut(),
+    }
+}
+
+pub fn cache_free(id: SlabId, p: *mut u8) -> bool {
+    match cache(id) {
+        Some(c) => c.free(p),
+        None => false,
+    }
+}
+
+/// Release empty slabs from every cache. Returns total bytes given back.
+pub fn reap_all() -> usize {
+    // Called from the heap's OOM path; a cache in the middle of grow() is
+    // already borrowed, so skip reaping until it returns.
+    if unsafe { GROWING } {
+        return 0;
+    }
+    caches().iter_mut().map(|c| c.reap()).sum()
+}
+
+/// Render a /proc/slabinfo-style table.
+pub fn render_slabinfo<W: Write>(out: &mut W) -> fmt::Result {
+    writeln!(out, "slabinfo - version: 2.1 (vernisos)")?;
+    writeln!(out, "# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab> : slabdata <num_slabs> : stats <allocs> <frees> <grow_fail> <bad_free>")?;
+    for c in caches().iter() {
+        writeln!(out, "{:<17} {:>6} {:>6} {:>6} {:>4} {:>4} : slabdata {:>4} : stats {:>8} {:>8} {:>4} {:>4}",
+                 c.name(), c.active_objs, c.total_objs(), c.obj_size, c.objs_per_slab,
+                 c.slab_bytes / SLAB_PAGE_SIZE, c.slabs.len(),
+                 c.alloc_count, c.free_count, c.grow_failures, c.bad_frees)?;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// SlabBox — owning pointer to an object living in a named cache
+// =============================================================================
+
+/// Types that are allocated from a dedicated slab cache.
+pub trait SlabObject: Sized {
+    const CACHE_NAME: &'static str;
+}
+
+impl SlabObject for crate::scheduler::ProcessControlBlock {
+    const CACHE_NAME: &'static str = "pcb";
+}
+
+impl SlabObject for crate::ai::types::EventRecord {
+    const CACHE_NAME: &'static str = "event_record";
+}
+
+pub struct SlabBox<T: SlabObject> {
+    ptr: NonNull<T>,
+    cache: SlabId,
+    _marker: PhantomData<T>,
+}
+
+impl<T: SlabObject> SlabBox<T> {
+    pub fn new(value: T) -> Self {
+        let layout = Layout::new::<T>();
+        let cache = create_cache(T::CACHE_NAME, layout.size(), layout.align())
+            .unwrap_or_else(|| handle_alloc_error(layout));
+        let raw = cache_alloc(cache) as *mut T;
+        let ptr = match NonNull::new(raw) {
+            Some(p) => p,
+            None => handle_alloc_error(layout),
+        };
+        unsafe { ptr::write(ptr.as_ptr(), value); }
+        Self { ptr, cache, _marker: PhantomData }
+    }
+
+    pub fn as_ptr(this: &Self) -> *const T {
+        this.ptr.as_ptr()
+    }
+}
+
+impl<T: SlabObject> Deref for SlabBox<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: SlabObject> DerefMut for SlabBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: SlabObject> Drop for SlabBox<T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()); }
+        cache_free(self.cache, self.ptr.as_ptr() as *mut u8);
+    }
+}
+
+impl<T: SlabObject + Clone> Clone for SlabBox<T> {
+    fn clone(&self) -> Self {
+        SlabBox::new((**self).clone())
+    }
+}
+
+impl<T: SlabObject + fmt::Debug> fmt::Debug for SlabBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+// =============================================================================
+// FFI exports — C subsystems (/proc, drivers) use the same caches
+// =============================================================================
+
+/// Create (or look up) a named cache. Returns its id, or -1 on bad arguments.
+#[no_mangle]
+pub unsafe extern "C" fn slab_cache_create(name: *const c_char, obj_size: u32, align: u32) -> i32 {
+    if name.is_null() { return -1; }
+    let name = match core::ffi::CStr::from_ptr(name).to_str() {
+        Ok(s) => s,
+        Err(_) => return -1,
+    };
+    match create_cache(name, obj_size as usize, align.max(1) as usize) {
+        Some(id) => id as i32,
+        None => -1,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn slab_cache_alloc(id: u32) -> *mut u8 {
+    cache_alloc(id)
+}
+
+/// Returns 0 on success, -1 if `ptr` is not a live object of cache `id`.
+#[no_mangle]
+pub extern "C" fn slab_cache_free(id: u32, ptr: *mut u8) -> i32 {
+    if cache_free(id, ptr) { 0 } else { -1 }
+}
+
+#[no_mangle]
+pub extern "C" fn slab_cache_active(id: u32) -> u32 {
+    cache(id).map(|c| c.active_objs() as u32).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn slab_reap_all() -> u32 {
+    reap_all() as u32
+}
+
+/// Render /proc/slabinfo text into `buf`. Returns bytes written (NUL-terminated).
+#[no_mangle]
+pub unsafe extern "C" fn slab_render_info(buf: *mut u8, buf_len: u32) -> u32 {
+    let mut out = match TextBuf::from_raw(buf, buf_len as usize) {
+        Some(b) => b,
+        None => return 0,
+    };
+    let _ = render_slabinfo(&mut out);
+    out.finish_cstr() as u32
+}
@@ -0,0 +1,428 @@
+// strace.rs — Per-process syscall tracing
+//
+// Tracing is off until something attaches: a single pid, every member of a
+// process group, or all processes. The arch syscall paths report each
+// completed call through strace_record(); for a traced caller whose syscall
+// passes the number filter, the call (number, arguments, return value, start
+// tick and duration in ticks) goes into that process's ring, overwriting the
+// oldest record once the ring is full.
+//
+// Rings live in a fixed table of STRACE_MAX_PROCS slots. A slot is claimed
+// the first time a traced pid makes a call, and is kept after the process
+// exits so its last calls can still be read; strace_clear() frees them.
+// When every slot is taken, calls from further pids are only counted.
+//
+// With AI forwarding on, every traced call is also fed to the in-kernel AI
+// engine as a SYSCALL event ("pid|num"), which is what drives the process
+// tracker's per-process syscall_count.
+//
+// Numbers at or above SYSCALL_MAX (the ring 3 heartbeat) are never traced.
+
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_void};
+use core::fmt::{self, Write};
+
+use crate::errno::{self, KResult, KernelError};
+use crate::heap::{self, HeapTag};
+use crate::scheduler::Scheduler;
+use crate::syscall::{self, SYSCALL_MAX};
+use crate::textbuf::TextBuf;
+
+extern "C" {
+    fn get_kernel_scheduler() -> *mut c_void;
+    fn kernel_get_ticks() -> u32;
+    fn ai_kernel_engine_feed_code(event_code: u8, data: *const c_char, now: u64);
+}
+
+pub const STRACE_RING_SIZE: usize = 64;
+pub const STRACE_MAX_PROCS: usize = 16;
+pub const STRACE_MAX_TARGETS: usize = 8;
+
+// Target kinds shared with C (include/strace.h)
+pub const STRACE_TARGET_PID: u32 = 0;
+pub const STRACE_TARGET_GROUP: u32 = 1;
+pub const STRACE_TARGET_ALL: u32 = 2;
+
+/// AI_EVT_CODE_SYSCALL from include/ai_bridge.h
+const AI_EVT_CODE_SYSCALL: u8 = 8;
+
+const FILTER_WORDS: usize = SYSCALL_MAX / 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceTarget {
+    All,
+    Pid(usize),
+    Group(usize),
+}
+
+impl TraceTarget {
+    fn from_c(kind: u32, id: usize) -> KResult<Self> {
+        match kind {
+            STRACE_TARGET_PID if id != 0 => Ok(Self::Pid(id)),
+            STRACE_TARGET_GROUP if id != 0 => Ok(Self::Group(id)),
+            STRACE_TARGET_ALL => Ok(Self::All),
+            _ => Err(KernelError::Inval),
+        }
+    }
+}
+
+impl fmt::Display for TraceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Pid(pid) => write!(f, "pid {}", pid),
+            Self::Group(pgid) => write!(f, "group {}", pgid),
+        }
+    }
+}
+
+/// One completed syscall.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceRecord {
+    pub num: u32,
+    pub start_tick: u32,
+    pub ticks: u32,
+    pub args: [usize; 3],
+    pub ret: isize,
+}
+
+pub struct TraceRing {
+    pub pid: usize,
+    /// Calls seen, including those overwritten since.
+    pub total: u64,
+    head: usize,
+    records: Vec<TraceRecord>,
+}
+
+impl TraceRing {
+    fn new(pid: usize) -> KResult<Self> {
+        let _tag = heap::tag_scope(HeapTag::Scheduler);
+        let mut records = Vec::new();
+        records.try_reserve_exact(STRACE_RING_SIZE).map_err(|_| KernelError::NoMem)?;
+        Ok(Self { pid, total: 0, head: 0, records })
+    }
+
+    fn push(&mut self, rec: TraceRecord) {
+        if self.records.len() < STRACE_RING_SIZE {
+            self.records.push(rec);
+        } else {
+            self.records[self.head] = rec;
+        }
+        self.head = (self.head + 1) % STRACE_RING_SIZE;
+        self.total += 1;
+    }
+
+    /// Records oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &TraceRecord> {
+        let split = if self.records.len() < STRACE_RING_SIZE { 0 } else { self.head };
+        self.records[split..].iter().chain(self.records[..split].iter())
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.total - self.records.len() as u64
+    }
+}
+
+pub struct Tracer {
+    targets: [Option<TraceTarget>; STRACE_MAX_TARGETS],
+    /// Bit n set: syscall n is traced.
+    filter: [u64; FILTER_WORDS],
+    forward_ai: bool,
+    rings: [Option<TraceRing>; STRACE_MAX_PROCS],
+    /// Traced calls that found no free ring slot.
+    ring_misses: u64,
+}
+
+static mut TRACER: Tracer = Tracer::new();
+
+fn tracer() -> &'static mut Tracer {
+    unsafe { &mut *core::ptr::addr_of_mut!(TRACER) }
+}
+
+/// Process group of pid, or pid itself if the scheduler does not know it.
+fn pgid_of(pid: usize) -> usize {
+    unsafe { (get_kernel_scheduler() as *const Scheduler).as_ref() }
+        .and_then(|s| s.processes.get(&pid))
+        .map(|p| p.pgid)
+        .unwrap_or(pid)
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracer {
+    const NO_RING: Option<TraceRing> = None;
+
+    pub const fn new() -> Self {
+        Self {
+            targets: [None; STRACE_MAX_TARGETS],
+            filter: [!0; FILTER_WORDS],
+            forward_ai: false,
+            rings: [Self::NO_RING; STRACE_MAX_PROCS],
+            ring_misses: 0,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.targets.iter().any(|t| t.is_some())
+    }
+
+    pub fn attach(&mut self, target: TraceTarget) -> KResult<()> {
+        if self.targets.contains(&Some(target)) {
+            return Ok(());
+        }
+        let slot = self.targets.iter_mut().find(|t| t.is_none()).ok_or(KernelError::NoSpace)?;
+        *slot = Some(target);
+        Ok(())
+    }
+
+    /// Remove one target. Detaching All stops tracing altogether.
+    pub fn detach(&mut self, target: TraceTarget) -> KResult<()> {
+        if target == TraceTarget::All {
+            self.targets = [None; STRACE_MAX_TARGETS];
+            return Ok(());
+        }
+        let slot = self.targets.iter_mut().find(|t| **t == Some(target)).ok_or(KernelError::NoEntry)?;
+        *slot = None;
+        Ok(())
+    }
+
+    pub fn set_filter(&mut self, num: u32, on: bool) -> KResult<()> {
+        let n = num as usize;
+        if n >= SYSCALL_MAX {
+            return Err(KernelError::Inval);
+        }
+        let bit = 1u64 << (n % 64);
+        if on {
+            self.filter[n / 64] |= bit;
+        } else {
+            self.filter[n / 64] &= !bit;
+        }
+        Ok(())
+    }
+
+    pub fn filter_all(&mut self, on: bool) {
+        self.filter = [if on { !0 } else { 0 }; FILTER_WORDS];
+    }
+
+    fn filtered_in(&self, num: u32) -> bool {
+        let n = num as usize;
+        n < SYSCALL_MAX && self.filter[n / 64] & (1u64 << (n % 64)) != 0
+    }
+
+    fn traces(&self, pid: usize) -> bool {
+        let mut pgid = None;
+        self.targets.iter().flatten().any(|t| match *t {
+            TraceTarget::All => true,
+            TraceTarget::Pid(p) => p == pid,
+            TraceTarget::Group(g) => *pgid.get_or_insert_with(|| pgid_of(pid)) == g,
+        })
+    }
+
+    pub fn ring(&self, pid: usize) -> Option<&TraceRing> {
+        self.rings.iter().flatten().find(|r| r.pid == pid)
+    }
+
+    fn ring_for(&mut self, pid: usize) -> Option<&mut TraceRing> {
+        let idx = match self.rings.iter().position(|r| r.as_ref().is_some_and(|r| r.pid == pid)) {
+            Some(i) => i,
+            None => {
+                let i = self.rings.iter().position(|r| r.is_none())?;
+                self.rings[i] = Some(TraceRing::new(pid).ok()?);
+                i
+            }
+        };
+        self.rings[idx].as_mut()
+    }
+
+    pub fn record(&mut self, pid: usize, rec: TraceRecord) {
+        if pid == 0 || !self.active() || !self.filtered_in(rec.num) || !self.traces(pid) {
+            return;
+        }
+        match self.ring_for(pid) {
+            Some(ring) => ring.push(rec),
+            None => self.ring_misses += 1,
+        }
+        if self.forward_ai {
+            let mut raw = [0u8; 32];
+            let mut data = TextBuf::new(&mut raw);
+            let _ = write!(data, "{}|{}", pid, rec.num);
+            data.finish_cstr();
+            let now = rec.start_tick.wrapping_add(rec.ticks) as u64;
+            unsafe { ai_kernel_engine_feed_code(AI_EVT_CODE_SYSCALL, raw.as_ptr() as *const c_char, now) };
+        }
+    }
+
+    /// Drop the ring of pid, or every ring for pid 0.
+    pub fn clear(&mut self, pid: usize) {
+        for slot in self.rings.iter_mut() {
+            if pid == 0 || slot.as_ref().is_some_and(|r| r.pid == pid) {
+                *slot = None;
+            }
+        }
+        if pid == 0 {
+            self.ring_misses = 0;
+        }
+    }
+
+    /// One strace-style line: `[pid 4] 1203 write(1, 0x10002000, 12) = 12 <0>`.
+    pub fn format_record<W: Write>(out: &mut W, pid: usize, rec: &TraceRecord) -> fmt::Result {
+        let entry = syscall::lookup(rec.num);
+        let argc = entry.map(|e| e.argc as usize).unwrap_or(3);
+        write!(out, "[pid {}] {} ", pid, rec.start_tick)?;
+        match entry {
+            Some(e) => write!(out, "{}(", e.name)?,
+            None => write!(out, "syscall_{}(", rec.num)?,
+        }
+        for (i, &a) in rec.args.iter().take(argc).enumerate() {
+            if i > 0 {
+                write!(out, ", ")?;
+            }
+            // Small values read better as numbers, large ones are pointers.
+            if a < 0x10000 {
+                write!(out, "{}", a)?;
+            } else {
+                write!(out, "{:#x}", a)?;
+            }
+        }
+        write!(out, ") = {}", rec.ret)?;
+        // Trap-frame syscalls still return a bare -1 rather than -errno.
+        if rec.ret < 0 && entry.is_some_and(|e| !e.is_trap()) {
+            if let Some(e) = i32::try_from(-rec.ret).ok().and_then(KernelError::from_errno) {
+                write!(out, " {}", e.name())?;
+            }
+        }
+        writeln!(out, " <{}>", rec.ticks)
+    }
+
+    /// Tracer state and a summary of every ring.
+    pub fn render_summary<W: Write>(&self, out: &mut W) -> fmt::Result {
+        write!(out, "strace: ")?;
+        if self.active() {
+            for (i, t) in self.targets.iter().flatten().enumerate() {
+                write!(out, "{}{}", if i > 0 { ", " } else { "" }, t)?;
+            }
+            writeln!(out)?;
+        } else {
+            writeln!(out, "off")?;
+        }
+        let traced = self.filter.iter().map(|w| w.count_ones()).sum::<u32>();
+        write!(out, "filter: ")?;
+        if traced as usize == SYSCALL_MAX {
+            write!(out, "all")?;
+        } else {
+            let mut first = true;
+            for num in (0..SYSCALL_MAX as u32).filter(|&n| self.filtered_in(n)) {
+                write!(out, "{}{}", if first { "" } else { "," }, num)?;
+                first = false;
+            }
+            if first {
+                write!(out, "none")?;
+            }
+        }
+        writeln!(out, "  ai-forward: {}  ring-misses: {}",
+                 if self.forward_ai { "on" } else { "off" }, self.ring_misses)?;
+        writeln!(out, "# pid   calls     kept  dropped")?;
+        for r in self.rings.iter().flatten() {
+            writeln!(out, "{:>5}  {:>6}  {:>7}  {:>7}", r.pid, r.total, r.records.len(), r.dropped())?;
+        }
+        Ok(())
+    }
+
+    /// Recorded calls of pid, oldest first, or the summary for pid 0.
+    pub fn render<W: Write>(&self, out: &mut W, pid: usize) -> fmt::Result {
+        if pid == 0 {
+            return self.render_summary(out);
+        }
+        let ring = match self.ring(pid) {
+            Some(r) => r,
+            None => return writeln!(out, "strace: no calls recorded for pid {}", pid),
+        };
+        if ring.dropped() > 0 {
+            writeln!(out, "... {} earlier calls dropped", ring.dropped())?;
+        }
+        for rec in ring.iter() {
+            Self::format_record(out, pid, rec)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tracer summary, for the syscall dump.
+pub fn render_summary<W: Write>(out: &mut W) -> fmt::Result {
+    tracer().render_summary(out)
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Start tracing a pid, a process group or everything. 0, -EINVAL or -ENOSPC.
+#[no_mangle]
+pub extern "C" fn strace_attach(kind: u32, id: usize) -> i32 {
+    errno::to_c(TraceTarget::from_c(kind, id).and_then(|t| tracer().attach(t)))
+}
+
+/// Stop tracing a target; kind ALL drops every target. 0, -EINVAL or -ENOENT.
+#[no_mangle]
+pub extern "C" fn strace_detach(kind: u32, id: usize) -> i32 {
+    errno::to_c(TraceTarget::from_c(kind, id).and_then(|t| tracer().detach(t)))
+}
+
+/// Include (on != 0) or exclude syscall num. 0 or -EINVAL.
+#[no_mangle]
+pub extern "C" fn strace_set_filter(num: u32, on: u8) -> i32 {
+    errno::to_c(tracer().set_filter(num, on != 0))
+}
+
+/// Include (on != 0) or exclude every syscall number.
+#[no_mangle]
+pub extern "C" fn strace_filter_all(on: u8) {
+    tracer().filter_all(on != 0)
+}
+
+/// Forward traced calls to the AI engine as SYSCALL events.
+#[no_mangle]
+pub extern "C" fn strace_set_ai_forward(on: u8) {
+    tracer().forward_ai = on != 0;
+}
+
+/// Report a completed syscall. Called by the arch syscall paths for every
+/// call; returns immediately while nothing is attached.
+#[no_mangle]
+pub extern "C" fn strace_record(
+    pid: usize,
+    num: u32,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    ret: isize,
+    start_tick: u32,
+) {
+    let t = tracer();
+    if !t.active() {
+        return;
+    }
+    let ticks = unsafe { kernel_get_ticks() }.wrapping_sub(start_tick);
+    t.record(pid, TraceRecord { num, start_tick, ticks, args: [a1, a2, a3], ret });
+}
+
+/// Render the calls of pid (0: tracer summary) into buf as a C string.
+#[no_mangle]
+pub extern "C" fn strace_render(pid: usize, buf: *mut u8, buf_len: u32) -> u32 {
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = tracer().render(&mut out, pid);
+    out.finish_cstr() as u32
+}
+
+/// Free the ring of pid, or every ring for pid 0.
+#[no_mangle]
+pub extern "C" fn strace_clear(pid: usize) {
+    tracer().clear(pid)
+}
@@ -0,0 +1,430 @@
+// swap.rs — Second-chance (clock) page replacement onto a swap file
+//
+// Optional: everything here stays inert until the arch code has preallocated
+// a swap file and called swap_init(). This module owns the policy and the
+// bookkeeping — which anonymous user pages are resident, where the clock hand
+// is, which swap slots are in use and which process owns them. The arch code
+// supplies the mechanism through SwapOps: testing/clearing PTE accessed bits,
+// clearing PTEs, and copying a page between a frame and a swap-file slot.
+//
+// All tasks share one page table, so entries are keyed by virtual address: a
+// page is whatever the shared PTE maps at that address. The owning pid only
+// decides whose exit releases the entry.
+//
+// Both tables are sized once in swap_init(), so nothing on the page-fault
+// path allocates or can re-enter the heap's OOM handling.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::errno::KernelError;
+use crate::heap::{self, HeapTag};
+use crate::textbuf::TextBuf;
+
+const SWAP_PAGE_SIZE: usize = 4096;
+pub const SWAP_MAX_RESIDENT: usize = 1024;
+pub const SWAP_MAX_SLOTS: u32 = 4096;
+
+// Return codes shared with C (include/swap.h), negative errno values
+pub const SWAP_OK: i32 = 0;
+pub const SWAP_ERR_INVAL: i32 = KernelError::Inval.neg();
+pub const SWAP_ERR_NOENT: i32 = KernelError::NoEntry.neg();
+pub const SWAP_ERR_IO: i32 = KernelError::Io.neg();
+pub const SWAP_ERR_NOMEM: i32 = KernelError::NoMem.neg();
+
+/// Page-table and swap-file primitives provided by the arch code.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SwapOps {
+    /// Test and clear the accessed bit of the PTE at vaddr, if it maps frame.
+    pub page_referenced: extern "C" fn(vaddr: usize, frame: usize) -> u8,
+    /// Clear the PTE at vaddr if it still maps frame. Returns 0 if it did not
+    /// (the page was remapped behind our back and the entry is stale).
+    pub page_unmap: extern "C" fn(vaddr: usize, frame: usize) -> u8,
+    /// Copy one page from frame into swap slot. 0 on success.
+    pub write_slot: extern "C" fn(slot: u32, frame: usize) -> i32,
+    /// Copy one page from swap slot into frame. 0 on success.
+    pub read_slot: extern "C" fn(slot: u32, frame: usize) -> i32,
+}
+
+/// Swap counters, shared with C (include/swap.h).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SwapStats {
+    pub slots_total: u32,
+    pub slots_used: u32,
+    pub resident_pages: u32,
+    pub swap_outs: u32,
+    pub swap_ins: u32,
+    pub second_chances: u32,
+    pub io_errors: u32,
+    pub evict_failures: u32,
+}
+
+impl SwapStats {
+    const fn zero() -> Self {
+        Self {
+            slots_total: 0,
+            slots_used: 0,
+            resident_pages: 0,
+            swap_outs: 0,
+            swap_ins: 0,
+            second_chances: 0,
+            io_errors: 0,
+            evict_failures: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ResidentPage {
+    pid: usize,
+    vaddr: usize,
+    frame: usize,
+}
+
+#[derive(Clone, Copy)]
+struct SwappedPage {
+    pid: usize,
+    vaddr: usize,
+    slot: u32,
+}
+
+pub struct SwapState {
+    ops: Option<SwapOps>,
+    slot_map: Vec<u64>,           // bit set = slot in use
+    slot_count: u32,
+    resident: Vec<ResidentPage>,  // clock ring, in tracking order
+    swapped: Vec<SwappedPage>,
+    hand: usize,
+    stats: SwapStats,
+}
+
+static mut SWAP: SwapState = SwapState::new();
+
+fn state() -> &'static mut SwapState {
+    unsafe { &mut *core::ptr::addr_of_mut!(SWAP) }
+}
+
+fn page_of(vaddr: usize) -> usize {
+    vaddr & !(SWAP_PAGE_SIZE - 1)
+}
+
+impl Default for SwapState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SwapState {
+    pub const fn new() -> Self {
+        Self {
+            ops: None,
+            slot_map: Vec::new(),
+            slot_count: 0,
+            resident: Vec::new(),
+            swapped: Vec::new(),
+            hand: 0,
+            stats: SwapStats::zero(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.ops.is_some()
+    }
+
+    pub fn init(&mut self, slots: u32, ops: SwapOps) -> Result<(), i32> {
+        if self.enabled() || slots == 0 || slots > SWAP_MAX_SLOTS {
+            return Err(SWAP_ERR_INVAL);
+        }
+        let _tag = heap::tag_scope(HeapTag::Fs);
+        let words = (slots as usize).div_ceil(64);
+        let mut slot_map = Vec::new();
+        let mut resident = Vec::new();
+        let mut swapped = Vec::new();
+        if slot_map.try_reserve_exact(words).is_err()
+            || resident.try_reserve_exact(SWAP_MAX_RESIDENT).is_err()
+            || swapped.try_reserve_exact(slots as usize).is_err()
+        {
+            return Err(SWAP_ERR_NOMEM);
+        }
+        slot_map.resize(words, 0);
+
+        self.slot_map = slot_map;
+        self.slot_count = slots;
+        self.resident = resident;
+        self.swapped = swapped;
+        self.hand = 0;
+        self.stats = SwapStats::zero();
+        self.stats.slots_total = slots;
+        self.ops = Some(ops);
+        Ok(())
+    }
+
+    fn alloc_slot(&mut self) -> Option<u32> {
+        for (w, word) in self.slot_map.iter_mut().enumerate() {
+            if *word == u64::MAX {
+                continue;
+            }
+            let bit = word.trailing_ones();
+            let slot = (w * 64) as u32 + bit;
+            if slot >= self.slot_count {
+                return None;
+            }
+            *word |= 1u64 << bit;
+            return Some(slot);
+        }
+        None
+    }
+
+    fn free_slot(&mut self, slot: u32) {
+        let (w, bit) = ((slot / 64) as usize, slot % 64);
+        if let Some(word) = self.slot_map.get_mut(w) {
+            *word &= !(1u64 << bit);
+        }
+    }
+
+    fn remove_resident(&mut self, idx: usize) {
+        self.resident.remove(idx);
+        if idx < self.hand {
+            self.hand -= 1;
+        }
+        if self.hand >= self.resident.len() {
+            self.hand = 0;
+        }
+    }
+
+    /// Drop whatever was tracked at vaddr (resident or swapped).
+    fn forget(&mut self, vaddr: usize) {
+        if let Some(i) = self.resident.iter().position(|p| p.vaddr == vaddr) {
+            self.remove_resident(i);
+        }
+        if let Some(i) = self.swapped.iter().position(|p| p.vaddr == vaddr) {
+            let slot = self.swapped.swap_remove(i).slot;
+            self.free_slot(slot);
+        }
+    }
+
+    /// Start tracking a freshly mapped anonymous page. Returns false if swap
+    /// is off or the clock ring is full (the page then simply stays pinned).
+    pub fn track(&mut self, pid: usize, vaddr: usize, frame: usize) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+        let vaddr = page_of(vaddr);
+        self.forget(vaddr);
+        if self.resident.len() >= self.resident.capacity() {
+            return false;
+        }
+        self.resident.push(ResidentPage { pid, vaddr, frame });
+        true
+    }
+
+    /// Run the clock until it finds a page whose accessed bit is clear, write
+    /// it to a free slot, unmap it and hand its frame back to the caller.
+    pub fn evict(&mut self) -> Result<usize, i32> {
+        let ops = self.ops.ok_or(SWAP_ERR_INVAL)?;
+        if self.resident.is_empty() {
+            self.stats.evict_failures += 1;
+            return Err(SWAP_ERR_NOENT);
+        }
+        let slot = match self.alloc_slot() {
+            Some(s) => s,
+            None => {
+                self.stats.evict_failures += 1;
+                return Err(SWAP_ERR_NOMEM);
+            }
+        };
+
+        // Two sweeps clear every accessed bit, so a victim is always found.
+        if self.hand >= self.resident.len() {
+            self.hand = 0;
+        }
+        for _ in 0..2 * self.resident.len() {
+            let p = self.resident[self.hand];
+            if (ops.page_referenced)(p.vaddr, p.frame) == 0 {
+                break;
+            }
+            self.stats.second_chances += 1;
+            self.hand = (self.hand + 1) % self.resident.len();
+        }
+
+        let victim = self.resident[self.hand];
+        if (ops.write_slot)(slot, victim.frame) != 0 {
+            self.stats.io_errors += 1;
+            self.free_slot(slot);
+            return Err(SWAP_ERR_IO);
+        }
+        let hand = self.hand;
+        self.remove_resident(hand);
+        if (ops.page_unmap)(victim.vaddr, victim.frame) == 0 {
+            // Nothing maps the frame any more: reclaim it without a slot.
+            self.free_slot(slot);
+            return Ok(victim.frame);
+        }
+        self.swapped.push(SwappedPage { pid: victim.pid, vaddr: victim.vaddr, slot });
+        self.stats.swap_outs += 1;
+        Ok(victim.frame)
+    }
+
+    pub fn is_swapped(&self, vaddr: usize) -> bool {
+        let vaddr = page_of(vaddr);
+        self.swapped.iter().any(|p| p.vaddr == vaddr)
+    }
+
+    /// Read the page swapped out at vaddr into frame. The caller maps it.
+    pub fn swap_in(&mut self, vaddr: usize, frame: usize) -> Result<(), i32> {
+        let ops = self.ops.ok_or(SWAP_ERR_NOENT)?;
+        let vaddr = page_of(vaddr);
+        let idx = self.swapped.iter().position(|p| p.vaddr == vaddr).ok_or(SWAP_ERR_NOENT)?;
+        let page = self.swapped[idx];
+        if (ops.read_slot)(page.slot, frame) != 0 {
+            self.stats.io_errors += 1;
+            return Err(SWAP_ERR_IO);
+        }
+        self.swapped.swap_remove(idx);
+        self.free_slot(page.slot);
+        self.stats.swap_ins += 1;
+        if self.resident.len() < self.resident.capacity() {
+            self.resident.push(ResidentPage { pid: page.pid, vaddr, frame });
+        }
+        Ok(())
+    }
+
+    fn release_where(&mut self, drop: impl Fn(usize, usize) -> bool) -> u32 {
+        let mut released = 0u32;
+        let mut i = 0;
+        while i < self.resident.len() {
+            let p = self.resident[i];
+            if drop(p.pid, p.vaddr) {
+                self.remove_resident(i);
+                released += 1;
+            } else {
+                i += 1;
+            }
+        }
+        let mut i = 0;
+        while i < self.swapped.len() {
+            let p = self.swapped[i];
+            if drop(p.pid, p.vaddr) {
+                self.swapped.swap_remove(i);
+                self.free_slot(p.slot);
+                released += 1;
+            } else {
+                i += 1;
+            }
+        }
+        released
+    }
+
+    /// Forget pages in [start, start + len) (munmap).
+    pub fn untrack_range(&mut self, start: usize, len: usize) -> u32 {
+        let end = start.saturating_add(len);
+        self.release_where(|_, va| va >= start && va < end)
+    }
+
+    /// Forget every page owned by pid and free its swap slots (exit/kill).
+    pub fn release_process(&mut self, pid: usize) -> u32 {
+        self.release_where(|owner, _| owner == pid)
+    }
+
+    pub fn stats(&self) -> SwapStats {
+        let mut s = self.stats;
+        s.slots_used = self.slot_map.iter().map(|w| w.count_ones()).sum();
+        s.resident_pages = self.resident.len() as u32;
+        s
+    }
+
+    pub fn render<W: Write>(&self, out: &mut W) -> fmt::Result {
+        if !self.enabled() {
+            return writeln!(out, "swap: off");
+        }
+        let s = self.stats();
+        writeln!(out, "swap: {} slots ({} KiB), {} used",
+                 s.slots_total, s.slots_total as usize * SWAP_PAGE_SIZE / 1024, s.slots_used)?;
+        writeln!(out, "resident: {} pages tracked, clock hand at {}", s.resident_pages, self.hand)?;
+        writeln!(out, "swap-outs {}  swap-ins {}  second-chances {}  io-errors {}  evict-failures {}",
+                 s.swap_outs, s.swap_ins, s.second_chances, s.io_errors, s.evict_failures)?;
+        writeln!(out, "# pid   vaddr       slot")?;
+        for p in &self.swapped {
+            writeln!(out, "{:>5}   {:#010x}  {:>4}", p.pid, p.vaddr, p.slot)?;
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Enable swap with slot_count page-sized slots. Returns SWAP_OK or SWAP_ERR_*.
+#[no_mangle]
+pub extern "C" fn swap_init(slot_count: u32, ops: *const SwapOps) -> i32 {
+    if ops.is_null() {
+        return SWAP_ERR_INVAL;
+    }
+    match state().init(slot_count, unsafe { *ops }) {
+        Ok(()) => SWAP_OK,
+        Err(e) => e,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn swap_enabled() -> bool {
+    state().enabled()
+}
+
+#[no_mangle]
+pub extern "C" fn swap_track(pid: usize, vaddr: usize, frame: usize) -> bool {
+    state().track(pid, vaddr, frame)
+}
+
+/// Evict one page. Returns the freed frame, or 0 if nothing could be evicted.
+#[no_mangle]
+pub extern "C" fn swap_evict() -> usize {
+    state().evict().unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn swap_is_swapped(vaddr: usize) -> bool {
+    state().is_swapped(vaddr)
+}
+
+/// Fill frame with the page swapped out at vaddr. Returns SWAP_OK,
+/// SWAP_ERR_NOENT if vaddr is not swapped out, or SWAP_ERR_IO.
+#[no_mangle]
+pub extern "C" fn swap_in(vaddr: usize, frame: usize) -> i32 {
+    match state().swap_in(vaddr, frame) {
+        Ok(()) => SWAP_OK,
+        Err(e) => e,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn swap_untrack_range(start: usize, len: usize) -> u32 {
+    state().untrack_range(start, len)
+}
+
+#[no_mangle]
+pub extern "C" fn swap_release_process(pid: usize) -> u32 {
+    state().release_process(pid)
+}
+
+#[no_mangle]
+pub extern "C" fn swap_get_stats(out: *mut SwapStats) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    unsafe { *out = state().stats(); }
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn swap_render_info(buf: *mut u8, buf_len: u32) -> u32 {
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = state().render(&mut out);
+    out.finish_cstr() as u32
+}
@@ -1,294 +1,669 @@
-// syscall.rs
+// syscall.rs — Table-driven syscall dispatch
+//
+// Every syscall that reaches Rust is looked up in one registration table
+// holding its handler, argument count, required capability bits and name.
+// Built-in handlers live in this file; the arch code registers its C
+// handlers (IPC, modules, AI, path I/O, shared memory) with
+// syscall_register() right after syscall_init().
+//
+// Dispatch runs against the live kernel Scheduler. The arch code passes the
+// pid of the task that trapped (0 for kernel context); the calling process must
+// hold every capability bit of the entry; otherwise the call is refused,
+// counted in the PCB's capability_denials and reported as a DENY event.
+// A process that passes the capability check then runs its seccomp filter
+// stack (seccomp.rs); anything but ALLOW is counted and reported the same way.
+// Syscalls that need the trap frame (exit, fork, execve, ...) are still
+// handled by the arch dispatcher before it falls through to syscall_handler;
+// the arch code registers them as Trap entries so they are named in the table
+// and in strace output, runs them past syscall_gate() (capabilities and
+// seccomp) first, and reports their results through syscall_account() so the
+// per-syscall call/error counters cover them too.
+//
+// Built-in handlers return KResult; every syscall result reaching user space
+// is a non-negative value or -errno (include/kerrno.h). User pointers are
+// only read or written through usermem.rs.
 
-// use alloc::string::String;
-use alloc::vec::Vec;
-use core::option::Option::{Some, None};
-use core::result::Result::Ok;
 use alloc::format;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_void, CStr};
+use core::fmt::{self, Write};
+
+use crate::errno::{self, KResult, KernelError};
+use crate::scheduler::{Scheduler, CAP_KILL_PROCESS, CAP_SERIAL_WRITE, CAP_SYS_DEBUG};
+use crate::seccomp::{self, Action, SeccompRule, SECCOMP_MAX_RULES};
+use crate::textbuf::TextBuf;
+use crate::uring;
+use crate::usermem;
+
+extern "C" {
+    fn get_kernel_scheduler() -> *mut c_void;
+    fn kernel_get_ticks() -> u32;
+    fn kernel_current_pid() -> u32;
+    fn ai_send_event(event_type: *const c_char, data: *const c_char);
+    fn ai_kernel_engine_feed(event_type: *const c_char, data: *const c_char, now: u64);
+}
 
-// Kernel structures
-pub struct ProcessContext {
-    pub pid: u32,
-    pub registers: [u32; 16], // Simplified register set
-    pub status: ProcessStatus,
-}
-
-#[derive(Debug)]
-pub enum ProcessStatus {
-    Running,
-    Ready,
-    Blocked,
-    Zombie,
-}
-
-pub struct Scheduler {
-    pub current_pid: Option<u32>,
-    pub ready_queue: Vec<u32>,
-}
-
-// Global kernel state (ในระบบจริงจะอยู่ใน kernel space)
-static mut CURRENT_PROCESS: Option<ProcessContext> = None;
-static mut SCHEDULER: Scheduler = Scheduler {
-    current_pid: None,
-    ready_queue: Vec::new(),
-};
-
-// Console driver interface
-// คอมเมนต์หรือเอาออก extern "C" fn console_write
-
-#[derive(Debug)]
-pub enum SyscallNumber {
-    Write = 1,
-    Exit = 2,
-    GetPid = 3,
-    DumpRegisters = 10,
-    DumpScheduler = 11,
-    DumpMemory = 12,
-    DumpSyscalls = 13,
-    DumpAll = 14,
-}
-
-impl SyscallNumber {
-    pub fn from_u32(num: u32) -> Option<Self> {
-        match num {
-            1 => Some(SyscallNumber::Write),
-            2 => Some(SyscallNumber::Exit),
-            3 => Some(SyscallNumber::GetPid),
-            10 => Some(SyscallNumber::DumpRegisters),
-            11 => Some(SyscallNumber::DumpScheduler),
-            12 => Some(SyscallNumber::DumpMemory),
-            13 => Some(SyscallNumber::DumpSyscalls),
-            14 => Some(SyscallNumber::DumpAll),
-            _ => None,
-        }
+/// Syscall numbers are table indices; anything at or above this is ENOSYS.
+pub const SYSCALL_MAX: usize = 128;
+
+// Return values shared with C (include/syscall_base.h)
+pub const SYSCALL_ERR_PERM: isize = KernelError::Perm.neg_isize();
+pub const SYSCALL_ERR_NOSYS: isize = KernelError::NoSys.neg_isize();
+
+/// Largest console write; longer buffers get E2BIG.
+pub const SYS_WRITE_MAX: usize = 4096;
+
+/// Dump output is capped at this many bytes, whatever the caller's buffer.
+pub const SYS_DUMP_MAX: usize = 8192;
+/// Bytes covered by the dump_memory syscall.
+pub const SYS_DUMP_MEMORY_BYTES: usize = 256;
+
+// Built-in syscall numbers
+pub const SYS_CONSOLE_WRITE: u32 = 1;
+pub const SYS_LEGACY_EXIT: u32 = 2;
+pub const SYS_LEGACY_GETPID: u32 = 3;
+pub const SYS_DUMP_REGISTERS: u32 = 10;
+pub const SYS_DUMP_SCHEDULER: u32 = 11;
+pub const SYS_DUMP_MEMORY: u32 = 12;
+pub const SYS_DUMP_SYSCALLS: u32 = 13;
+pub const SYS_DUMP_ALL: u32 = 14;
+pub const SYS_GETPID: u32 = 62;
+pub const SYS_KILL: u32 = 63;
+pub const SYS_SECCOMP: u32 = 79;
+pub const SYS_URING_SETUP: u32 = 80;
+pub const SYS_URING_ENTER: u32 = 81;
+pub const SYS_URING_DESTROY: u32 = 82;
+
+// Signals raised by seccomp verdicts
+const SIGKILL: u8 = 9;
+const SIGSYS: u8 = 31;
+
+/// C handler registered by the arch code. Receives the syscall number so one
+/// function can serve a whole range (e.g. IPC 20-27). Returns a value or -errno.
+pub type NativeHandler = extern "C" fn(num: u32, a1: usize, a2: usize, a3: usize) -> isize;
+
+/// Rust handler. Arguments beyond the entry's argc are passed as 0.
+pub type BuiltinHandler = fn(&SyscallCtx, [usize; 3]) -> KResult<isize>;
+
+#[derive(Clone, Copy)]
+pub enum Handler {
+    Builtin(BuiltinHandler),
+    Native(NativeHandler),
+    /// Handled by the arch trap dispatcher; never dispatched from here.
+    Trap,
+}
+
+#[derive(Clone, Copy)]
+pub struct SyscallEntry {
+    pub name: &'static str,
+    pub argc: u8,
+    pub caps: u64,
+    pub handler: Handler,
+}
+
+impl SyscallEntry {
+    pub fn is_trap(&self) -> bool {
+        matches!(self.handler, Handler::Trap)
     }
 }
 
-#[no_mangle]
-pub extern "C" fn syscall_handler(sys_num: u32, arg1: usize, arg2: usize, _arg3: usize) -> isize {
-    match SyscallNumber::from_u32(sys_num) {
-        Some(SyscallNumber::Write) => sys_write(arg1 as *const u8, arg2),
-        Some(SyscallNumber::Exit) => {
-            sys_exit(arg1 as i32);
-            0
-        }
-        Some(SyscallNumber::GetPid) => sys_getpid(),
-        Some(SyscallNumber::DumpRegisters) => {
-            sys_dump_registers();
-            0
-        }
-        Some(SyscallNumber::DumpScheduler) => {
-            sys_dump_scheduler();
-            0
-        }
-        Some(SyscallNumber::DumpMemory) => {
-            sys_dump_memory(arg1);
-            0
-        }
-        Some(SyscallNumber::DumpSyscalls) => {
-            sys_dump_syscalls();
-            0
+/// What a handler knows about its caller.
+pub struct SyscallCtx {
+    pub num: u32,
+    pub pid: Option<usize>,
+    /// The live scheduler. Handlers must not hold a reference to it across
+    /// calls into C.
+    pub sched: *mut Scheduler,
+}
+
+/// Per-number counters for the syscall dump.
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallStats {
+    pub calls: u64,
+    /// Calls that returned -errno, refusals included.
+    pub errors: u64,
+    /// Calls refused by the capability check or a seccomp filter.
+    pub denied: u64,
+}
+
+impl SyscallStats {
+    const ZERO: Self = Self { calls: 0, errors: 0, denied: 0 };
+}
+
+pub struct SyscallTable {
+    entries: [Option<SyscallEntry>; SYSCALL_MAX],
+    stats: [SyscallStats; SYSCALL_MAX],
+}
+
+static mut TABLE: SyscallTable = SyscallTable::new();
+
+fn table() -> &'static mut SyscallTable {
+    unsafe { &mut *core::ptr::addr_of_mut!(TABLE) }
+}
+
+impl Default for SyscallTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyscallTable {
+    pub const fn new() -> Self {
+        Self { entries: [None; SYSCALL_MAX], stats: [SyscallStats::ZERO; SYSCALL_MAX] }
+    }
+
+    /// Install an entry. Fails if num is out of range or already taken.
+    pub fn register(&mut self, num: u32, entry: SyscallEntry) -> KResult<()> {
+        match self.entries.get_mut(num as usize) {
+            Some(slot @ None) => {
+                *slot = Some(entry);
+                Ok(())
+            }
+            Some(Some(_)) => Err(KernelError::Exists),
+            None => Err(KernelError::Inval),
         }
-        Some(SyscallNumber::DumpAll) => {
-            sys_dump_all();
-            0
+    }
+
+    pub fn get(&self, num: u32) -> Option<&SyscallEntry> {
+        self.entries.get(num as usize).and_then(|e| e.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &SyscallEntry)> {
+        self.entries.iter().enumerate()
+            .filter_map(|(n, e)| e.as_ref().map(|e| (n as u32, e)))
+    }
+
+    pub fn stats(&self, num: u32) -> Option<&SyscallStats> {
+        self.stats.get(num as usize)
+    }
+
+    /// Count one completed call of num.
+    fn account(&mut self, num: u32, ret: isize, denied: bool) {
+        if let Some(st) = self.stats.get_mut(num as usize) {
+            st.calls += 1;
+            if ret < 0 {
+                st.errors += 1;
+            }
+            if denied {
+                st.denied += 1;
+            }
         }
-        // Phase 4 IPC syscalls (20-27) are intercepted in C interrupt_dispatch
-        // before reaching this handler, so they will not appear here.
-        None => {
-            // Syscalls 20-27 are IPC — handled in C before reaching here
-            -1
+    }
+
+    fn register_builtins(&mut self) {
+        const BUILTINS: &[(u32, &str, u8, u64, BuiltinHandler)] = &[
+            (SYS_CONSOLE_WRITE,  "console_write",  2, CAP_SERIAL_WRITE, sys_console_write),
+            (SYS_LEGACY_EXIT,    "exit",           1, 0,                sys_legacy_exit),
+            (SYS_LEGACY_GETPID,  "getpid",         0, 0,                sys_getpid_entry),
+            (SYS_DUMP_REGISTERS, "dump_registers", 2, CAP_SYS_DEBUG,    sys_dump_registers_entry),
+            (SYS_DUMP_SCHEDULER, "dump_scheduler", 2, CAP_SYS_DEBUG,    sys_dump_scheduler_entry),
+            (SYS_DUMP_MEMORY,    "dump_memory",    3, CAP_SYS_DEBUG,    sys_dump_memory_entry),
+            (SYS_DUMP_SYSCALLS,  "dump_syscalls",  2, CAP_SYS_DEBUG,    sys_dump_syscalls_entry),
+            (SYS_DUMP_ALL,       "dump_all",       2, CAP_SYS_DEBUG,    sys_dump_all_entry),
+            (SYS_GETPID,         "getpid",         0, 0,                sys_getpid_entry),
+            (SYS_KILL,           "kill",           2, CAP_KILL_PROCESS, sys_kill),
+            (SYS_SECCOMP,        "seccomp",        3, 0,                sys_seccomp),
+            (SYS_URING_SETUP,    "uring_setup",    3, 0,                sys_uring_setup),
+            (SYS_URING_ENTER,    "uring_enter",    3, 0,                sys_uring_enter),
+            (SYS_URING_DESTROY,  "uring_destroy",  1, 0,                sys_uring_destroy),
+        ];
+        for &(num, name, argc, caps, f) in BUILTINS {
+            let _ = self.register(num, SyscallEntry { name, argc, caps, handler: Handler::Builtin(f) });
         }
     }
 }
 
 // ===========================
-// System Call Implementations
+// Dispatch
 // ===========================
 
-pub fn sys_write(ptr: *const u8, len: usize) -> isize {
-    unsafe {
-        if !is_valid_user_memory(ptr, len) {
-            return -1; // EFAULT
-        }
+/// Copy of the table entry for num, if registered.
+pub fn lookup(num: u32) -> Option<SyscallEntry> {
+    table().get(num).copied()
+}
 
-        let slice = core::slice::from_raw_parts(ptr, len);
-        if let Ok(msg) = core::str::from_utf8(slice) {
-            kernel_print(msg);
-            msg.len() as isize
-        } else {
-            -2 // EINVAL: invalid utf-8
-        }
-    }
+fn kernel_scheduler() -> *mut Scheduler {
+    unsafe { get_kernel_scheduler() as *mut Scheduler }
 }
 
-pub fn sys_exit(code: i32) {
+/// Report a refused call as "pid|num|name" to the host bridge and the
+/// in-kernel AI engine.
+fn report_denial(pid: usize, num: u32, name: &str) {
+    use core::fmt::Write;
+    let mut raw = [0u8; 64];
+    let mut data = TextBuf::new(&mut raw);
+    let _ = write!(data, "{}|{}|{}", pid, num, name);
+    data.finish_cstr();
+    let evt = c"DENY".as_ptr();
+    let data = raw.as_ptr() as *const c_char;
     unsafe {
-        if let Some(ref mut process) = CURRENT_PROCESS {
-            process.status = ProcessStatus::Zombie;
-            kernel_print(&format!("[EXIT] Process {} exited with code {}\n", process.pid, code));
-        }
-        schedule_next();
+        ai_send_event(evt, data);
+        ai_kernel_engine_feed(evt, data, kernel_get_ticks() as u64);
     }
 }
 
-pub fn sys_getpid() -> isize {
-    unsafe {
-        match CURRENT_PROCESS {
-            Some(ref process) => process.pid as isize,
-            None => -1,
+/// Capability gate for the calling task's pid. Kernel context (pid 0, early
+/// boot) is always allowed.
+fn check_caps(sched: *mut Scheduler, pid: usize, num: u32, entry: &SyscallEntry) -> KResult<()> {
+    let sched = match unsafe { sched.as_mut() } {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+    if let Some(proc) = sched.processes.get_mut(&pid) {
+        if !proc.has_capability(entry.caps) {
+            proc.capability_denials += 1;
+            report_denial(pid, num, entry.name);
+            return Err(KernelError::Perm);
         }
     }
+    Ok(())
 }
 
-pub fn sys_dump_registers() {
-    unsafe {
-        if let Some(ref process) = CURRENT_PROCESS {
-            kernel_print(&format!("[DUMP] Registers for PID {}:\n", process.pid));
-            for (i, reg) in process.registers.iter().enumerate() {
-                kernel_print(&format!("  R{}: 0x{:08X}\n", i, reg));
-            }
-        } else {
-            kernel_print("[ERROR] No current process\n");
+/// Seccomp gate, run after check_caps() for a calling process. TRAP raises
+/// SIGSYS and fails with ENOSYS; KILL raises SIGKILL, marks the process
+/// killed and fails with EPERM.
+fn check_filter(sched: *mut Scheduler, pid: usize, num: u32, entry: &SyscallEntry,
+                args: &[usize; 3]) -> KResult<()> {
+    let sched = match unsafe { sched.as_mut() } {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+    let proc = match sched.processes.get_mut(&pid) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    let action = match &proc.seccomp {
+        Some(filter) => filter.evaluate(num, args),
+        None => return Ok(()),
+    };
+    let err = match action {
+        Action::Allow => return Ok(()),
+        Action::Errno(e) => e,
+        Action::Trap => {
+            proc.signal_send(SIGSYS);
+            KernelError::NoSys
         }
+        Action::Kill => {
+            proc.signal_send(SIGKILL);
+            KernelError::Perm
+        }
+    };
+    proc.capability_denials += 1;
+    if action == Action::Kill {
+        let _ = sched.kill_process(pid);
     }
+    report_denial(pid, num, entry.name);
+    Err(err)
 }
 
-pub fn sys_dump_scheduler() {
-    unsafe {
-        let sched_ptr = core::ptr::addr_of!(SCHEDULER);
-        kernel_print("[DUMP] Scheduler State:\n");
-        let pid = (*sched_ptr).current_pid;
-        kernel_print(&format!("  Current PID: {:?}\n", pid));
-        let len = (*sched_ptr).ready_queue.len();
-        kernel_print(&format!("  Ready Queue Length: {}\n", len));
-        kernel_print("  Ready Queue PIDs: [");
-        let queue = &(*sched_ptr).ready_queue;
-        for (i, pid) in queue.iter().enumerate() {
-            if i > 0 { kernel_print(", "); }
-            kernel_print(&format!("{}", pid));
-        }
-        kernel_print("]\n");
+/// Capability check, then the seccomp filter, for the task `pid` (0 =
+/// kernel context, always admitted). Returns the arguments with those past
+/// the entry's argc zeroed.
+fn admit(sched: *mut Scheduler, pid: Option<usize>, num: u32, entry: &SyscallEntry,
+         args: [usize; 3]) -> KResult<[usize; 3]> {
+    if let Some(s) = unsafe { sched.as_mut() } {
+        crate::heap::reap_oom_kills(s);
+    }
+    let mut args = args;
+    for a in args.iter_mut().skip(entry.argc as usize) {
+        *a = 0;
+    }
+    if let Some(pid) = pid {
+        check_caps(sched, pid, num, entry)?;
+        check_filter(sched, pid, num, entry, &args)?;
     }
+    Ok(args)
 }
 
-pub fn sys_dump_memory(addr: usize) {
-    unsafe {
-        if !is_valid_user_memory(addr as *const u8, 1) {
-            kernel_print("[ERROR] Invalid memory address\n");
-            return;
-        }
+fn caller(pid: usize) -> Option<usize> {
+    if pid != 0 { Some(pid) } else { None }
+}
+
+/// Dispatch `sys_num` for the task `pid` (0 = kernel context).
+#[no_mangle]
+pub extern "C" fn syscall_handler(pid: usize, sys_num: u32, arg1: usize, arg2: usize, arg3: usize) -> isize {
+    let entry = match table().get(sys_num) {
+        Some(e) if !e.is_trap() => *e,
+        _ => return SYSCALL_ERR_NOSYS,
+    };
+    let sched = kernel_scheduler();
+    let pid = caller(pid);
+    let args = match admit(sched, pid, sys_num, &entry, [arg1, arg2, arg3]) {
+        Ok(args) => args,
+        Err(e) => return refuse(sys_num, e),
+    };
+    let ret = match entry.handler {
+        Handler::Builtin(f) => errno::to_c_isize(f(&SyscallCtx { num: sys_num, pid, sched }, args)),
+        Handler::Native(f) => f(sys_num, args[0], args[1], args[2]),
+        Handler::Trap => SYSCALL_ERR_NOSYS,
+    };
+    table().account(sys_num, ret, false);
+    ret
+}
 
-        let value = core::ptr::read_volatile(addr as *const u8);
-        kernel_print(&format!("[DUMP] Memory at 0x{:X} => 0x{:02X}\n", addr, value));
+/// Run the capability check and seccomp filter for a Trap entry before the
+/// arch dispatcher handles it. Returns 0 to go ahead, or the -errno to hand
+/// back (already counted as refused). Other numbers return 0; they are
+/// checked by syscall_handler.
+#[no_mangle]
+pub extern "C" fn syscall_gate(pid: usize, sys_num: u32, arg1: usize, arg2: usize, arg3: usize) -> isize {
+    let entry = match table().get(sys_num) {
+        Some(e) if e.is_trap() => *e,
+        _ => return 0,
+    };
+    match admit(kernel_scheduler(), caller(pid), sys_num, &entry, [arg1, arg2, arg3]) {
+        Ok(_) => 0,
+        Err(e) => refuse(sys_num, e),
     }
 }
 
-pub fn sys_dump_syscalls() {
-    kernel_print("[DUMP] Available System Calls:\n");
-    kernel_print("  1  - Write:         Write string to console\n");
-    kernel_print("  2  - Exit:          Exit process with code\n");
-    kernel_print("  3  - GetPid:        Get current process ID\n");
-    kernel_print("  10 - DumpRegisters: Dump current process registers\n");
-    kernel_print("  11 - DumpScheduler: Dump scheduler state\n");
-    kernel_print("  12 - DumpMemory:    Dump memory at address\n");
-    kernel_print("  13 - DumpSyscalls:  Show this help\n");
-    kernel_print("  14 - DumpAll:       Dump all system information\n");
+fn refuse(num: u32, e: KernelError) -> isize {
+    table().account(num, e.neg_isize(), true);
+    e.neg_isize()
 }
 
-pub fn sys_dump_all() {
-    kernel_print("=== VERNISOS SYSTEM DUMP ===\n");
+/// Count a syscall the arch trap dispatcher handled itself.
+#[no_mangle]
+pub extern "C" fn syscall_account(num: u32, ret: isize) {
+    table().account(num, ret, false);
+}
 
-    // Current process info
-    unsafe {
-        if let Some(ref process) = CURRENT_PROCESS {
-            kernel_print(&format!("Current Process: PID={}, Status={:?}\n",
-                process.pid, process.status));
-        } else {
-            kernel_print("No current process\n");
-        }
+/// Register a C handler. `name` must point to a string with static lifetime
+/// (a literal). Returns 0, -EEXIST if num is already registered, or -EINVAL
+/// if num is out of range or an argument is invalid.
+#[no_mangle]
+pub extern "C" fn syscall_register(
+    num: u32,
+    name: *const c_char,
+    argc: u8,
+    caps: u64,
+    handler: Option<NativeHandler>,
+) -> i32 {
+    let handler = match handler {
+        Some(h) if !name.is_null() && argc <= 3 => h,
+        _ => return KernelError::Inval.neg(),
+    };
+    let name: &'static str = match unsafe { CStr::from_ptr(name) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return KernelError::Inval.neg(),
+    };
+    let entry = SyscallEntry { name, argc, caps, handler: Handler::Native(handler) };
+    errno::to_c(table().register(num, entry))
+}
+
+/// Name a syscall the arch trap dispatcher handles itself. Same return
+/// values and `name` lifetime rule as syscall_register().
+#[no_mangle]
+pub extern "C" fn syscall_register_trap(num: u32, name: *const c_char, argc: u8) -> i32 {
+    if name.is_null() || argc > 3 {
+        return KernelError::Inval.neg();
     }
+    let name: &'static str = match unsafe { CStr::from_ptr(name) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return KernelError::Inval.neg(),
+    };
+    let entry = SyscallEntry { name, argc, caps: 0, handler: Handler::Trap };
+    errno::to_c(table().register(num, entry))
+}
+
+#[no_mangle]
+pub extern "C" fn syscall_init() {
+    table().register_builtins();
+}
+
+// ===========================
+// Built-in handlers
+// ===========================
+
+fn sys_console_write(_ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    sys_write(a[0] as *const u8, a[1])
+}
+
+fn sys_legacy_exit(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    let pid = ctx.pid.ok_or(KernelError::NoProcess)?;
+    let sched = unsafe { ctx.sched.as_mut() }.ok_or(KernelError::NoProcess)?;
+    kernel_print(&format!("[EXIT] Process {} exited with code {}\n", pid, a[0] as i32));
+    sched.exit_process(pid, a[0] as i32)?;
+    Ok(0)
+}
+
+fn sys_getpid_entry(ctx: &SyscallCtx, _a: [usize; 3]) -> KResult<isize> {
+    Ok(ctx.pid.map(|p| p as isize).unwrap_or(0))
+}
+
+fn sys_kill(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    let sched = unsafe { ctx.sched.as_mut() }.ok_or(KernelError::NoProcess)?;
+    let sig = u8::try_from(a[1]).map_err(|_| KernelError::Inval)?;
+    sched.signal_send(a[0], sig)?;
+    Ok(0)
+}
+
+/// seccomp(rules_ptr, count, default_action): stack a filter on the caller.
+fn sys_seccomp(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    let pid = ctx.pid.ok_or(KernelError::NoProcess)?;
+    let default = u32::try_from(a[2]).map_err(|_| KernelError::Inval)?;
+    let count = a[1];
+    if count > SECCOMP_MAX_RULES {
+        return Err(KernelError::TooBig);
+    }
+    let mut rules = Vec::new();
+    rules.try_reserve_exact(count).map_err(|_| KernelError::NoMem)?;
+    for i in 0..count {
+        let mut raw = [0u8; core::mem::size_of::<SeccompRule>()];
+        let uaddr = a[0].checked_add(i * raw.len()).ok_or(KernelError::Fault)?;
+        usermem::copy_from_user(&mut raw, uaddr)?;
+        rules.push(unsafe { core::ptr::read_unaligned(raw.as_ptr() as *const SeccompRule) });
+    }
+    let sched = unsafe { ctx.sched.as_mut() }.ok_or(KernelError::NoProcess)?;
+    seccomp::install(sched, pid, &rules, default)?;
+    Ok(0)
+}
+
+fn u32_arg(v: usize) -> KResult<u32> {
+    u32::try_from(v).map_err(|_| KernelError::Inval)
+}
+
+fn sys_uring_setup(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    let pid = ctx.pid.ok_or(KernelError::NoProcess)?;
+    let id = uring::setup(ctx.sched, pid, u32_arg(a[0])?, u32_arg(a[1])?, u32_arg(a[2])?)?;
+    Ok(id as isize)
+}
+
+fn sys_uring_enter(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    let pid = ctx.pid.ok_or(KernelError::NoProcess)?;
+    let ready = uring::enter(ctx.sched, pid, u32_arg(a[0])?, u32_arg(a[1])?, u32_arg(a[2])?)?;
+    Ok(ready as isize)
+}
+
+fn sys_uring_destroy(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    let pid = ctx.pid.ok_or(KernelError::NoProcess)?;
+    uring::destroy(ctx.sched, pid, u32_arg(a[0])?)?;
+    Ok(0)
+}
 
-    // Scheduler state
-    sys_dump_scheduler();
-    
-    // Process registers
-    sys_dump_registers();
-    
-    // Available syscalls
-    sys_dump_syscalls();
+fn sys_dump_registers_entry(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    dump_to_user(a[0], a[1], |out| dump_registers(out, ctx.pid))
+}
+
+fn sys_dump_scheduler_entry(_ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    dump_to_user(a[0], a[1], |out| dump_scheduler(out))
+}
+
+fn sys_dump_memory_entry(_ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    dump_to_user(a[0], a[1], |out| dump_memory(out, a[2], SYS_DUMP_MEMORY_BYTES))
+}
+
+fn sys_dump_syscalls_entry(_ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    dump_to_user(a[0], a[1], |out| dump_syscalls(out))
+}
+
+fn sys_dump_all_entry(ctx: &SyscallCtx, a: [usize; 3]) -> KResult<isize> {
+    dump_to_user(a[0], a[1], |out| dump_all(out, ctx.pid))
+}
 
-    kernel_print("=== END SYSTEM DUMP ===\n");
+/// Render into a kernel buffer of min(ulen, SYS_DUMP_MAX) bytes and copy it,
+/// NUL-terminated, to the caller. Returns the length without the NUL.
+fn dump_to_user(ubuf: usize, ulen: usize,
+                render: impl FnOnce(&mut TextBuf) -> fmt::Result) -> KResult<isize> {
+    if ulen == 0 {
+        return Err(KernelError::Inval);
+    }
+    let len = ulen.min(SYS_DUMP_MAX);
+    let mut raw = Vec::new();
+    raw.try_reserve_exact(len).map_err(|_| KernelError::NoMem)?;
+    raw.resize(len, 0);
+    let mut out = TextBuf::new(&mut raw);
+    let _ = render(&mut out);
+    let n = out.finish_cstr();
+    usermem::copy_to_user(ubuf, &raw[..n + 1])?;
+    Ok(n as isize)
 }
 
 // ===========================
-// Helper Functions
+// System Call Implementations
 // ===========================
 
-fn is_valid_user_memory(ptr: *const u8, len: usize) -> bool {
-    // Basic validation - ในระบบจริงจะต้องตรวจสอบ memory mapping
-    if ptr.is_null() || len == 0 {
-        return false;
+pub fn sys_write(ptr: *const u8, len: usize) -> KResult<isize> {
+    if len > SYS_WRITE_MAX {
+        return Err(KernelError::TooBig);
     }
-    
-    // ตรวจสอบว่าไม่เกิน address space limit
-    let end_addr = ptr as usize + len;
-    if end_addr < ptr as usize {
-        return false; // Integer overflow
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len).map_err(|_| KernelError::NoMem)?;
+    buf.resize(len, 0);
+    usermem::copy_from_user(&mut buf, ptr as usize)?;
+    let msg = core::str::from_utf8(&buf).map_err(|_| KernelError::Inval)?;
+    kernel_print(msg);
+    Ok(msg.len() as isize)
+}
+
+pub fn sys_exit(code: i32) {
+    let sched = match unsafe { kernel_scheduler().as_mut() } {
+        Some(s) => s,
+        None => return,
+    };
+    if let Some(pid) = sched.current_pid {
+        kernel_print(&format!("[EXIT] Process {} exited with code {}\n", pid, code));
     }
-    
-    // ในระบบจริงจะตรวจสอบกับ page table และ memory permissions
-    true
+    sched.terminate_current(code);
 }
 
-fn kernel_print(msg: &str) {
-    crate::kernel_print_raw(msg);
+pub fn sys_getpid() -> KResult<isize> {
+    unsafe { kernel_scheduler().as_ref() }
+        .and_then(|s| s.current_pid)
+        .map(|p| p as isize)
+        .ok_or(KernelError::NoProcess)
 }
 
-fn schedule_next() {
-    unsafe {
-        let mut_sched_ptr = core::ptr::addr_of_mut!(SCHEDULER);
-        if let Some(current) = (*mut_sched_ptr).current_pid {
-            if let Some(ref process) = CURRENT_PROCESS {
-                if !matches!(process.status, ProcessStatus::Zombie) {
-                    (*mut_sched_ptr).ready_queue.push(current);
-                }
+// ===========================
+// Debug dumps
+// ===========================
+
+/// Hex digits of a full-width address on this target.
+const ADDR_DIGITS: usize = core::mem::size_of::<usize>() * 2;
+
+/// Registers pid had at its last syscall.
+pub fn dump_registers<W: Write>(out: &mut W, pid: Option<usize>) -> fmt::Result {
+    let sched = unsafe { kernel_scheduler().as_ref() };
+    let proc = match (sched, pid) {
+        (Some(s), Some(pid)) => s.processes.get(&pid),
+        _ => None,
+    };
+    let proc = match proc {
+        Some(p) => p,
+        None => return writeln!(out, "registers: no current process"),
+    };
+    let c = &proc.context;
+    let w = ADDR_DIGITS;
+    writeln!(out, "registers of pid {} at its last syscall:", proc.pid)?;
+    writeln!(out, "  rip {:0w$x}  rsp {:0w$x}  rbp {:0w$x}  rflags {:0w$x}", c.rip, c.rsp, c.rbp, c.rflags)?;
+    writeln!(out, "  rax {:0w$x}  rbx {:0w$x}  rcx {:0w$x}  rdx {:0w$x}", c.rax, c.rbx, c.rcx, c.rdx)?;
+    writeln!(out, "  rsi {:0w$x}  rdi {:0w$x}", c.rsi, c.rdi)?;
+    if cfg!(target_pointer_width = "64") {
+        writeln!(out, "  r8  {:0w$x}  r9  {:0w$x}  r10 {:0w$x}  r11 {:0w$x}", c.r8, c.r9, c.r10, c.r11)?;
+        writeln!(out, "  r12 {:0w$x}  r13 {:0w$x}  r14 {:0w$x}  r15 {:0w$x}", c.r12, c.r13, c.r14, c.r15)?;
+    }
+    Ok(())
+}
+
+pub fn dump_scheduler<W: Write>(out: &mut W) -> fmt::Result {
+    match unsafe { kernel_scheduler().as_ref() } {
+        Some(s) => s.dump(out),
+        None => writeln!(out, "scheduler: not ready"),
+    }
+}
+
+/// Hexdump of [addr, addr + len), at most SYS_DUMP_MEMORY_BYTES, read
+/// through usermem so a user caller only sees its own memory and a kernel
+/// caller only mapped pages. Stops at the first line it may not read, or
+/// once out is full.
+pub fn dump_memory(out: &mut TextBuf, addr: usize, len: usize) -> fmt::Result {
+    let w = ADDR_DIGITS;
+    let len = len.min(SYS_DUMP_MEMORY_BYTES);
+    let mut off = 0;
+    while off < len && !out.truncated() {
+        let line_addr = match addr.checked_add(off) {
+            Some(a) => a,
+            None => break,
+        };
+        let n = (len - off).min(16);
+        let mut line = [0u8; 16];
+        if usermem::peek(&mut line[..n], line_addr).is_err() {
+            return writeln!(out, "{:0w$x}  <unreadable>", line_addr);
+        }
+        write!(out, "{:0w$x} ", line_addr)?;
+        for (i, b) in line.iter().enumerate() {
+            if i == 8 {
+                write!(out, " ")?;
+            }
+            if i < n {
+                write!(out, " {:02x}", b)?;
+            } else {
+                write!(out, "   ")?;
             }
         }
-        if let Some(next_pid) = (*mut_sched_ptr).ready_queue.pop() {
-            (*mut_sched_ptr).current_pid = Some(next_pid);
-            kernel_print(&format!("[SCHED] Switching to PID {}\n", next_pid));
-        } else {
-            (*mut_sched_ptr).current_pid = None;
-            kernel_print("[SCHED] No ready processes\n");
+        write!(out, "  |")?;
+        for &b in &line[..n] {
+            let ch = if (0x20..0x7F).contains(&b) { b as char } else { '.' };
+            write!(out, "{}", ch)?;
         }
+        writeln!(out, "|")?;
+        off += n;
+    }
+    Ok(())
+}
+
+/// Registered syscalls with their counters, then the strace summary.
+pub fn dump_syscalls<W: Write>(out: &mut W) -> fmt::Result {
+    writeln!(out, "  NUM  NAME              ARGS  CAPS        CALLS   ERRORS   DENIED")?;
+    for (num, e) in table().iter() {
+        let st = table().stats(num).copied().unwrap_or(SyscallStats::ZERO);
+        let kind = if e.is_trap() { " (trap)" } else { "" };
+        writeln!(out, "  {:>3}  {:<16}  {:>4}  {:#010x} {:>8} {:>8} {:>8}{}",
+                 num, e.name, e.argc, e.caps, st.calls, st.errors, st.denied, kind)?;
     }
+    crate::strace::render_summary(out)
+}
+
+pub fn dump_all<W: Write>(out: &mut W, pid: Option<usize>) -> fmt::Result {
+    writeln!(out, "=== scheduler ===")?;
+    dump_scheduler(out)?;
+    writeln!(out, "=== registers ===")?;
+    dump_registers(out, pid)?;
+    writeln!(out, "=== syscalls ===")?;
+    dump_syscalls(out)
 }
 
 // ===========================
-// Initialization
+// Helper Functions
 // ===========================
 
-pub fn init() {
-    unsafe {
-        let mut_sched_ptr = core::ptr::addr_of_mut!(SCHEDULER);
-        (*mut_sched_ptr).current_pid = Some(1);
-        (*mut_sched_ptr).ready_queue.clear();
-        (*mut_sched_ptr).ready_queue.push(2);
-        (*mut_sched_ptr).ready_queue.push(3);
-        CURRENT_PROCESS = Some(ProcessContext {
-            pid: 1,
-            registers: [0; 16],
-            status: ProcessStatus::Running,
-        });
-        kernel_print("[INIT] Syscall handler initialized\n");
-        kernel_print("[INIT] Current PID: 1, Ready Queue: [2, 3]\n");
-    }
+fn kernel_print(msg: &str) {
+    crate::kernel_print_raw(msg);
 }
 
 #[no_mangle]
 pub extern "C" fn ffi_sys_write(ptr: *const u8, len: usize) -> isize {
-    sys_write(ptr, len)
+    errno::to_c_isize(sys_write(ptr, len))
 }
 
 #[no_mangle]
@@ -298,57 +673,74 @@ pub extern "C" fn ffi_sys_exit(code: i32) {
 
 #[no_mangle]
 pub extern "C" fn ffi_sys_getpid() -> isize {
-    sys_getpid()
+    errno::to_c_isize(sys_getpid())
+}
+
+/// Render into a kernel buffer; bytes written without the NUL, or -EINVAL.
+fn dump_to_buf(buf: *mut u8, len: usize, render: impl FnOnce(&mut TextBuf) -> fmt::Result) -> isize {
+    let mut out = match unsafe { TextBuf::from_raw(buf, len) } {
+        Some(t) => t,
+        None => return KernelError::Inval.neg_isize(),
+    };
+    let _ = render(&mut out);
+    out.finish_cstr() as isize
 }
 
+/// The running task's pid, None before tasking starts.
+fn running_pid() -> Option<usize> {
+    caller(unsafe { kernel_current_pid() } as usize)
+}
+
+/// Registers of pid, or of the running task for pid 0.
 #[no_mangle]
-pub extern "C" fn ffi_sys_dump_registers() {
-    sys_dump_registers()
+pub extern "C" fn ffi_sys_dump_registers(pid: usize, buf: *mut u8, len: usize) -> isize {
+    let pid = if pid != 0 { Some(pid) } else { running_pid() };
+    dump_to_buf(buf, len, |out| dump_registers(out, pid))
 }
 
 #[no_mangle]
-pub extern "C" fn ffi_sys_dump_scheduler() {
-    sys_dump_scheduler()
+pub extern "C" fn ffi_sys_dump_scheduler(buf: *mut u8, len: usize) -> isize {
+    dump_to_buf(buf, len, |out| dump_scheduler(out))
 }
 
 #[no_mangle]
-pub extern "C" fn ffi_sys_dump_memory(addr: usize) {
-    sys_dump_memory(addr)
+pub extern "C" fn ffi_sys_dump_memory(addr: usize, count: usize, buf: *mut u8, len: usize) -> isize {
+    let count = count.min(SYS_DUMP_MEMORY_BYTES);
+    dump_to_buf(buf, len, |out| dump_memory(out, addr, count))
 }
 
 #[no_mangle]
-pub extern "C" fn ffi_sys_dump_syscalls() {
-    sys_dump_syscalls()
+pub extern "C" fn ffi_sys_dump_syscalls(buf: *mut u8, len: usize) -> isize {
+    dump_to_buf(buf, len, |out| dump_syscalls(out))
 }
 
 #[no_mangle]
-pub extern "C" fn ffi_sys_dump_all() {
-    sys_dump_all()
+pub extern "C" fn ffi_sys_dump_all(buf: *mut u8, len: usize) -> isize {
+    dump_to_buf(buf, len, |out| dump_all(out, running_pid()))
 }
 
 #[no_mangle]
 pub extern "C" fn ffi_test_simple() {}
 
 // Phase 23: Signal handling FFI (void ptr = &mut Scheduler from scheduler.rs)
+// Returns 0, -ESRCH for an unknown pid or -EINVAL for a bad signal number.
 #[no_mangle]
 pub extern "C" fn scheduler_signal_send(sched_ptr: *mut core::ffi::c_void, dst_pid: usize, sig: u8) -> i32 {
-    use crate::scheduler::Scheduler;
     unsafe {
-        if sched_ptr.is_null() { return -1; }
+        if sched_ptr.is_null() { return KernelError::Inval.neg(); }
         let sched = &mut *(sched_ptr as *mut Scheduler);
-        sched.signal_send(dst_pid, sig)
+        errno::to_c(sched.signal_send(dst_pid, sig))
     }
 }
 
+/// Next pending signal number, -EAGAIN if none is pending, or -ESRCH.
 #[no_mangle]
 pub extern "C" fn scheduler_get_pending_signal(sched_ptr: *mut core::ffi::c_void, pid: usize) -> i32 {
-    use crate::scheduler::Scheduler;
     unsafe {
-        if sched_ptr.is_null() { return -1; }
+        if sched_ptr.is_null() { return KernelError::Inval.neg(); }
         let sched = &mut *(sched_ptr as *mut Scheduler);
-        sched.get_pending_signal(pid)
+        let sig = sched.get_pending_signal(pid)
+            .and_then(|sig| sig.ok_or(KernelError::Again));
+        errno::to_c_i32(sig.map(i32::from))
     }
 }
-
-#[no_mangle]
-pub extern "C" fn syscall_init() {}
\ No newline at end of file
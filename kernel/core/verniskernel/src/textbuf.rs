@@ -0,0 +1,85 @@
+// textbuf.rs — Fixed-capacity text writer for /proc-style renderers
+//
+// Lets Rust code use `write!` into a caller-provided byte buffer (usually a
+// C buffer handed across FFI) without allocating. Output that does not fit
+// is silently truncated and `truncated()` reports it.
+
+use core::fmt;
+
+pub struct TextBuf<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    truncated: bool,
+}
+
+impl<'a> TextBuf<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0, truncated: false }
+    }
+
+    /// Wrap a raw C buffer. Returns None for a null pointer or zero length.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for writes of `len` bytes for the lifetime `'a`.
+    pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> Option<Self> {
+        if ptr.is_null() || len == 0 {
+            return None;
+        }
+        Some(Self::new(core::slice::from_raw_parts_mut(ptr, len)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// The output as text. If truncation split a UTF-8 sequence, the
+    /// partial character is dropped.
+    pub fn as_str(&self) -> &str {
+        let bytes = self.as_bytes();
+        match core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+        }
+    }
+
+    /// Append raw bytes, truncating at capacity.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        let room = self.buf.len() - self.len;
+        let n = bytes.len().min(room);
+        self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+        if n < bytes.len() {
+            self.truncated = true;
+        }
+    }
+
+    /// NUL-terminate the output (overwriting the last byte if full) and
+    /// return the string length, excluding the terminator.
+    pub fn finish_cstr(self) -> usize {
+        if self.buf.is_empty() {
+            return 0;
+        }
+        let end = if self.len < self.buf.len() { self.len } else { self.buf.len() - 1 };
+        self.buf[end] = 0;
+        end
+    }
+}
+
+impl fmt::Write for TextBuf<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_bytes(s.as_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,540 @@
+// uring.rs — Asynchronous submission/completion rings
+//
+// A process batches work instead of paying an int 0x80 round trip per
+// operation. It creates and maps a shared memory segment (shm.rs), then
+// registers it as a ring with uring_setup. The segment is laid out as
+//
+//   [UringHeader][sq_entries x UringSqe][cq_entries x UringCqe][data area]
+//
+// The process fills SQEs and advances sq_tail, then calls uring_enter. The
+// kernel consumes SQEs into its pending list and completes them on its own
+// schedule: once right away in uring_enter, then from the idle loop
+// (uring_poll). Each finished operation posts a CQE and advances cq_tail;
+// the process reaps CQEs and advances cq_head. Operation buffers and paths
+// are offsets into the data area, so the kernel reaches them through the
+// segment's kernel address from any context, without the caller's page
+// tables.
+//
+// SQEs are only taken while a CQE slot is guaranteed for them, so the
+// completion ring cannot overflow. The indices the process writes (sq_tail,
+// cq_head) are clamped to the ring sizes before use. uring_enter with
+// min_complete parks the caller in Waiting until that many CQEs are ready;
+// uring_poll then wakes it through Scheduler::wake_process, and the arch
+// code keeps the task off the CPU in between.
+
+use alloc::vec::Vec;
+use core::ffi::c_char;
+use core::fmt::{self, Write};
+use core::ptr::{self, addr_of, addr_of_mut};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::errno::{KResult, KernelError};
+use crate::heap::{self, HeapTag};
+use crate::scheduler::{ProcessState, Scheduler, CAP_FILE_IO, CAP_IPC_RECEIVE, CAP_IPC_SEND};
+use crate::shm;
+use crate::textbuf::TextBuf;
+
+extern "C" {
+    fn kernel_get_ticks() -> u32;
+    fn kernel_get_timer_hz() -> u32;
+    fn ipc_send(src_pid: u32, dst_pid: u32, msg_type: u32, data: *const u8, len: u32) -> i32;
+    fn ipc_recv(qid: u32, out: *mut u8) -> i32;
+    fn kfs_read_at(path: *const c_char, offset: u32, buf: *mut u8, len: u32) -> i32;
+    fn kfs_write_at(path: *const c_char, offset: u32, data: *const u8, len: u32) -> i32;
+    fn kfs_path_is_system(path: *const c_char) -> i32;
+    fn kfs_check_perm(path: *const c_char, uid: u16, op: u8) -> i32;
+    fn ai_query_submit(query: *const c_char) -> i32;
+    fn ai_query_collect(seq: u32, resp: *mut c_char, max_len: usize) -> i32;
+    fn ai_query_cancel(seq: u32);
+}
+
+pub const URING_MAX_RINGS: usize = 8;
+/// Upper bound for sq_entries and cq_entries (each a power of two).
+pub const URING_MAX_ENTRIES: u32 = 256;
+/// AI queries unanswered after this long fail with ETIMEDOUT.
+pub const URING_AI_TIMEOUT_SECS: u32 = 5;
+
+// Opcodes
+pub const URING_OP_NOP: u8 = 0;
+pub const URING_OP_IPC_SEND: u8 = 1;
+pub const URING_OP_IPC_RECV: u8 = 2;
+pub const URING_OP_KFS_READ: u8 = 3;
+pub const URING_OP_KFS_WRITE: u8 = 4;
+pub const URING_OP_AI_QUERY: u8 = 5;
+pub const URING_OP_SLEEP: u8 = 6;
+
+const HEADER_SIZE: usize = 64;
+const SQE_SIZE: usize = 32;
+const CQE_SIZE: usize = 16;
+// include/ipc.h: IpcMessage is 64 bytes with a 48-byte payload
+const IPC_MSG_SIZE: u32 = 64;
+const IPC_MSG_PAYLOAD: u32 = 48;
+// Longest kfs path, NUL included (SYS_IO_PATH_MAX in the arch code)
+const PATH_MAX: usize = 64;
+
+/// Start of the segment, shared with user space (include/uring.h).
+/// sq_tail and cq_head are written by the process, sq_head and cq_tail by
+/// the kernel; the rest is set up once by uring_setup.
+#[repr(C)]
+pub struct UringHeader {
+    pub sq_head: u32,
+    pub sq_tail: u32,
+    pub sq_mask: u32,
+    pub sq_off: u32,
+    pub cq_head: u32,
+    pub cq_tail: u32,
+    pub cq_mask: u32,
+    pub cq_off: u32,
+    pub data_off: u32,
+    pub data_len: u32,
+    _reserved: [u32; 6],
+}
+
+/// Submission entry. Same layout on i386 and x86_64 (32 bytes).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UringSqe {
+    pub opcode: u8,
+    pub flags: u8,
+    pub _pad: u16,
+    /// IPC_SEND: destination pid. IPC_RECV: queue id. KFS_*: segment
+    /// offset of the NUL-terminated path.
+    pub target: u32,
+    /// Segment offset and length of the data buffer.
+    pub addr: u32,
+    pub len: u32,
+    /// IPC_SEND: message type. KFS_*: file offset. SLEEP: ticks.
+    pub arg: u64,
+    pub user_data: u64,
+}
+
+/// Completion entry (16 bytes). res is a byte count or 0, or -errno.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UringCqe {
+    pub user_data: u64,
+    pub res: i32,
+    pub flags: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Pending {
+    sqe: UringSqe,
+    start: u32,
+    /// Sequence number of a submitted AI query, 0 before submission.
+    ai_seq: u32,
+}
+
+enum Progress {
+    Done(i32),
+    Waiting,
+}
+
+struct Ring {
+    id: u32,
+    pid: usize,
+    /// Owner's uid at setup; KFS ops are checked against it.
+    uid: u16,
+    shm_id: u32,
+    base: usize,
+    size: usize,
+    sq_entries: u32,
+    cq_entries: u32,
+    data_off: usize,
+    pending: Vec<Pending>,
+    /// CQEs the owner is parked on, 0 if it is not waiting.
+    wait_for: u32,
+    submitted: u64,
+    completed: u64,
+}
+
+impl Ring {
+    fn hdr(&self) -> *mut UringHeader {
+        self.base as *mut UringHeader
+    }
+
+    /// CQEs posted and not yet reaped. cq_head is written by the process,
+    /// so a bogus value is clamped to the ring size.
+    fn cq_ready(&self) -> u32 {
+        let h = self.hdr();
+        let ready = unsafe {
+            ptr::read_volatile(addr_of!((*h).cq_tail))
+                .wrapping_sub(ptr::read_volatile(addr_of!((*h).cq_head)))
+        };
+        ready.min(self.cq_entries)
+    }
+
+    /// SQEs queued and not yet taken, clamped to the ring size (sq_tail is
+    /// written by the process).
+    fn sq_queued(&self) -> u32 {
+        let h = self.hdr();
+        let queued = unsafe {
+            ptr::read_volatile(addr_of!((*h).sq_tail))
+                .wrapping_sub(ptr::read_volatile(addr_of!((*h).sq_head)))
+        };
+        queued.min(self.sq_entries)
+    }
+
+    /// CQE slots not yet promised to a posted or pending operation.
+    fn cq_room(&self) -> u32 {
+        let pending = u32::try_from(self.pending.len()).unwrap_or(u32::MAX);
+        self.cq_entries.saturating_sub(self.cq_ready()).saturating_sub(pending)
+    }
+
+    fn post(&mut self, user_data: u64, res: i32) {
+        let h = self.hdr();
+        unsafe {
+            let tail = ptr::read_volatile(addr_of!((*h).cq_tail));
+            let slot = self.base + HEADER_SIZE + self.sq_entries as usize * SQE_SIZE
+                + (tail & (self.cq_entries - 1)) as usize * CQE_SIZE;
+            ptr::write_volatile(slot as *mut UringCqe, UringCqe { user_data, res, flags: 0 });
+            compiler_fence(Ordering::Release);
+            ptr::write_volatile(addr_of_mut!((*h).cq_tail), tail.wrapping_add(1));
+        }
+        self.completed += 1;
+    }
+
+    /// Take up to max SQEs, as long as each is sure of a CQE slot. Ops the
+    /// caller may not run complete at once with EPERM.
+    fn harvest(&mut self, max: u32, caps: u64, now: u32) -> u32 {
+        let h = self.hdr();
+        let max = max.min(self.sq_queued());
+        let mut taken = 0;
+        while taken < max && self.cq_room() > 0 {
+            let head = unsafe { ptr::read_volatile(addr_of!((*h).sq_head)) };
+            compiler_fence(Ordering::Acquire);
+            let slot = self.base + HEADER_SIZE + (head & (self.sq_entries - 1)) as usize * SQE_SIZE;
+            let sqe = unsafe { ptr::read_volatile(slot as *const UringSqe) };
+            unsafe { ptr::write_volatile(addr_of_mut!((*h).sq_head), head.wrapping_add(1)) };
+            taken += 1;
+            self.submitted += 1;
+
+            let need = match sqe.opcode {
+                URING_OP_IPC_SEND => CAP_IPC_SEND,
+                URING_OP_IPC_RECV => CAP_IPC_RECEIVE,
+                URING_OP_KFS_READ | URING_OP_KFS_WRITE => CAP_FILE_IO,
+                _ => 0,
+            };
+            if caps & need != need {
+                self.post(sqe.user_data, KernelError::Perm.neg());
+                continue;
+            }
+            // Room was reserved at setup for cq_entries pending ops
+            self.pending.push(Pending { sqe, start: now, ai_seq: 0 });
+        }
+        taken
+    }
+
+    /// Kernel pointer to [addr, addr + len) of the data area.
+    fn buf(&self, addr: u32, len: u32) -> KResult<*mut u8> {
+        let (addr, len) = (addr as usize, len as usize);
+        match addr.checked_add(len) {
+            Some(end) if addr >= self.data_off && end <= self.size => Ok((self.base + addr) as *mut u8),
+            _ => Err(KernelError::Fault),
+        }
+    }
+
+    /// Kernel pointer to a NUL-terminated string of at most max bytes
+    /// (NUL included) at off in the data area.
+    fn cstr(&self, off: u32, max: usize) -> KResult<*const c_char> {
+        let off = off as usize;
+        if off < self.data_off || off >= self.size {
+            return Err(KernelError::Fault);
+        }
+        let avail = (self.size - off).min(max);
+        let s = unsafe { core::slice::from_raw_parts((self.base + off) as *const u8, avail) };
+        if s.contains(&0) {
+            Ok(s.as_ptr() as *const c_char)
+        } else {
+            Err(KernelError::NameTooLong)
+        }
+    }
+
+    /// The kfs_*_at calls check nothing, so check for them: system files
+    /// (the swap file) are never reachable, others by the owner's mode bits.
+    fn kfs_access(&self, path: *const c_char, op: u8) -> KResult<()> {
+        if unsafe { kfs_path_is_system(path) } != 0 {
+            return Err(KernelError::Access);
+        }
+        if unsafe { kfs_check_perm(path, self.uid, op) } < 0 {
+            return Err(KernelError::Access);
+        }
+        Ok(())
+    }
+
+    fn step(&self, p: &mut Pending, now: u32, hz: u32) -> KResult<Progress> {
+        let sqe = p.sqe;
+        let done = |rc: i32| Ok(Progress::Done(rc));
+        match sqe.opcode {
+            URING_OP_NOP => done(0),
+            URING_OP_IPC_SEND => {
+                if sqe.len > IPC_MSG_PAYLOAD {
+                    return Err(KernelError::TooBig);
+                }
+                let data = self.buf(sqe.addr, sqe.len)?;
+                let rc = unsafe { ipc_send(self.pid as u32, sqe.target, sqe.arg as u32, data, sqe.len) };
+                // Queue full: try again on the next pass
+                if rc == KernelError::Again.neg() { Ok(Progress::Waiting) } else { done(rc) }
+            }
+            URING_OP_IPC_RECV => {
+                if sqe.len < IPC_MSG_SIZE {
+                    return Err(KernelError::Inval);
+                }
+                let out = self.buf(sqe.addr, IPC_MSG_SIZE)?;
+                let rc = unsafe { ipc_recv(sqe.target, out) };
+                if rc == KernelError::NoMsg.neg() { Ok(Progress::Waiting) } else { done(rc) }
+            }
+            URING_OP_KFS_READ | URING_OP_KFS_WRITE => {
+                let path = self.cstr(sqe.target, PATH_MAX)?;
+                let buf = self.buf(sqe.addr, sqe.len)?;
+                let off = u32::try_from(sqe.arg).map_err(|_| KernelError::Inval)?;
+                self.kfs_access(path, if sqe.opcode == URING_OP_KFS_READ { b'r' } else { b'w' })?;
+                if sqe.opcode == URING_OP_KFS_READ {
+                    let n = unsafe { kfs_read_at(path, off, buf, sqe.len) };
+                    done(if n < 0 { KernelError::NoEntry.neg() } else { n })
+                } else {
+                    let n = unsafe { kfs_write_at(path, off, buf, sqe.len) };
+                    done(if n < 0 { KernelError::Io.neg() } else { n })
+                }
+            }
+            URING_OP_AI_QUERY => {
+                // The query is read from the buffer, the answer replaces it
+                let buf = self.buf(sqe.addr, sqe.len)?;
+                let timed_out = now.wrapping_sub(p.start) >= URING_AI_TIMEOUT_SECS * hz;
+                if p.ai_seq == 0 {
+                    let query = self.cstr(sqe.addr, sqe.len as usize)?;
+                    let seq = unsafe { ai_query_submit(query) };
+                    if seq == KernelError::Busy.neg() && !timed_out {
+                        return Ok(Progress::Waiting);
+                    }
+                    if seq < 0 {
+                        return done(if timed_out { KernelError::TimedOut.neg() } else { seq });
+                    }
+                    p.ai_seq = seq as u32;
+                }
+                let rc = unsafe { ai_query_collect(p.ai_seq, buf as *mut c_char, sqe.len as usize) };
+                if rc != KernelError::Again.neg() {
+                    return done(rc);
+                }
+                if timed_out {
+                    unsafe { ai_query_cancel(p.ai_seq) };
+                    return done(KernelError::TimedOut.neg());
+                }
+                Ok(Progress::Waiting)
+            }
+            URING_OP_SLEEP => {
+                if now.wrapping_sub(p.start) as u64 >= sqe.arg { done(0) } else { Ok(Progress::Waiting) }
+            }
+            _ => Err(KernelError::Inval),
+        }
+    }
+
+    /// Run every pending op once; post the finished ones in order.
+    fn process(&mut self, now: u32, hz: u32) {
+        let mut i = 0;
+        while i < self.pending.len() {
+            let mut p = self.pending[i];
+            let progress = self.step(&mut p, now, hz).unwrap_or_else(|e| Progress::Done(e.neg()));
+            match progress {
+                Progress::Done(res) => {
+                    self.pending.remove(i);
+                    self.post(p.sqe.user_data, res);
+                }
+                Progress::Waiting => {
+                    self.pending[i] = p;
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// Drop pending work, forgetting outstanding AI queries.
+    fn cancel_all(&mut self) {
+        for p in self.pending.drain(..) {
+            if p.ai_seq != 0 {
+                unsafe { ai_query_cancel(p.ai_seq) };
+            }
+        }
+    }
+}
+
+struct Rings {
+    rings: Vec<Ring>,
+    next_id: u32,
+}
+
+static mut RINGS: Rings = Rings { rings: Vec::new(), next_id: 1 };
+
+fn rings() -> &'static mut Rings {
+    unsafe { &mut *addr_of_mut!(RINGS) }
+}
+
+fn timer_hz() -> u32 {
+    let hz = unsafe { kernel_get_timer_hz() };
+    if hz == 0 { 100 } else { hz }
+}
+
+fn shm_error(e: shm::ShmError) -> KernelError {
+    KernelError::from_errno(e.code()).unwrap_or(KernelError::Inval)
+}
+
+fn find(pid: usize, id: u32) -> KResult<usize> {
+    rings().rings.iter()
+        .position(|r| r.id == id && r.pid == pid)
+        .ok_or(KernelError::NoEntry)
+}
+
+/// Lay out rings of sq_entries/cq_entries in shm segment shm_id, which pid
+/// must have mapped writable. Returns the ring id.
+pub fn setup(sched: *mut Scheduler, pid: usize, shm_id: u32, sq_entries: u32, cq_entries: u32)
+    -> KResult<u32>
+{
+    for n in [sq_entries, cq_entries] {
+        if n == 0 || n > URING_MAX_ENTRIES || !n.is_power_of_two() {
+            return Err(KernelError::Inval);
+        }
+    }
+    let uid = unsafe { sched.as_ref() }
+        .and_then(|s| s.processes.get(&pid))
+        .ok_or(KernelError::NoProcess)?
+        .uid;
+    let r = rings();
+    if r.rings.len() >= URING_MAX_RINGS {
+        return Err(KernelError::NoSpace);
+    }
+    if r.rings.iter().any(|ring| ring.shm_id == shm_id) {
+        return Err(KernelError::Busy);
+    }
+    let data_off = HEADER_SIZE + sq_entries as usize * SQE_SIZE + cq_entries as usize * CQE_SIZE;
+    let (base, size) = shm::pin(sched, pid, shm_id).map_err(shm_error)?;
+    let _tag = heap::tag_scope(HeapTag::Ipc);
+    let mut pending = Vec::new();
+    let ok = data_off <= size
+        && pending.try_reserve_exact(cq_entries as usize).is_ok()
+        && r.rings.try_reserve(1).is_ok();
+    if !ok {
+        shm::unpin(sched, shm_id);
+        return Err(if data_off > size { KernelError::Range } else { KernelError::NoMem });
+    }
+
+    let h = base as *mut UringHeader;
+    unsafe {
+        ptr::write_bytes(base as *mut u8, 0, HEADER_SIZE);
+        (*h).sq_mask = sq_entries - 1;
+        (*h).sq_off = HEADER_SIZE as u32;
+        (*h).cq_mask = cq_entries - 1;
+        (*h).cq_off = (HEADER_SIZE + sq_entries as usize * SQE_SIZE) as u32;
+        (*h).data_off = data_off as u32;
+        (*h).data_len = (size - data_off) as u32;
+    }
+    let id = r.next_id;
+    r.next_id += 1;
+    r.rings.push(Ring {
+        id, pid, uid, shm_id, base, size, sq_entries, cq_entries, data_off,
+        pending, wait_for: 0, submitted: 0, completed: 0,
+    });
+    Ok(id)
+}
+
+/// Submit up to to_submit SQEs and run what can finish now. Returns the
+/// number of CQEs ready. If that is below min_complete the caller is parked
+/// until uring_poll has posted enough, and gets min_complete back; waiting
+/// for more CQEs than are ready or in flight is EINVAL.
+pub fn enter(sched: *mut Scheduler, pid: usize, id: u32, to_submit: u32, min_complete: u32)
+    -> KResult<u32>
+{
+    let sched = unsafe { sched.as_mut() }.ok_or(KernelError::NoProcess)?;
+    let caps = sched.processes.get(&pid).ok_or(KernelError::NoProcess)?.capabilities;
+    let ring = &mut rings().rings[find(pid, id)?];
+    if min_complete > ring.cq_entries {
+        return Err(KernelError::Inval);
+    }
+    let now = unsafe { kernel_get_ticks() };
+    ring.harvest(to_submit, caps, now);
+    ring.process(now, timer_hz());
+    let ready = ring.cq_ready();
+    if ready >= min_complete {
+        return Ok(ready);
+    }
+    if ready as usize + ring.pending.len() < min_complete as usize {
+        return Err(KernelError::Inval);
+    }
+    let proc = sched.process_mut(pid)?;
+    proc.state = ProcessState::Waiting;
+    proc.wait_reason = Some(alloc::string::String::from("uring"));
+    ring.wait_for = min_complete;
+    Ok(min_complete)
+}
+
+/// Unregister a ring; pending operations are abandoned.
+pub fn destroy(sched: *mut Scheduler, pid: usize, id: u32) -> KResult<()> {
+    let mut ring = rings().rings.swap_remove(find(pid, id)?);
+    ring.cancel_all();
+    shm::unpin(sched, ring.shm_id);
+    Ok(())
+}
+
+/// Drop every ring of pid (exit/kill). Returns how many were dropped.
+pub fn release_process(sched: *mut Scheduler, pid: usize) -> usize {
+    let mut n = 0;
+    while let Some(i) = rings().rings.iter().position(|r| r.pid == pid) {
+        let mut ring = rings().rings.swap_remove(i);
+        ring.cancel_all();
+        shm::unpin(sched, ring.shm_id);
+        n += 1;
+    }
+    n
+}
+
+/// Progress every ring and wake owners whose completions are ready.
+pub fn poll(sched: *mut Scheduler, now: u32) {
+    let hz = timer_hz();
+    for ring in rings().rings.iter_mut() {
+        if !ring.pending.is_empty() {
+            ring.process(now, hz);
+        }
+        if ring.wait_for != 0 && ring.cq_ready() >= ring.wait_for {
+            ring.wait_for = 0;
+            if let Some(s) = unsafe { sched.as_mut() } {
+                let _ = s.wake_process(ring.pid);
+            }
+        }
+    }
+}
+
+pub fn render<W: Write>(out: &mut W) -> fmt::Result {
+    writeln!(out, "# id   pid  shm   sq   cq  pending  ready  waitfor  submitted  completed")?;
+    for r in rings().rings.iter() {
+        writeln!(out, "{:>4} {:>5} {:>4} {:>4} {:>4} {:>8} {:>6} {:>8} {:>10} {:>10}",
+                 r.id, r.pid, r.shm_id, r.sq_entries, r.cq_entries, r.pending.len(),
+                 r.cq_ready(), r.wait_for, r.submitted, r.completed)?;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Idle-loop hook: run pending ring operations and wake waiting owners.
+/// Call with interrupts off.
+#[no_mangle]
+pub extern "C" fn uring_poll(sched: *mut Scheduler, now: u32) {
+    poll(sched, now);
+}
+
+#[no_mangle]
+pub extern "C" fn uring_release_process(sched: *mut Scheduler, pid: usize) -> u32 {
+    release_process(sched, pid) as u32
+}
+
+/// Render the ring table into buf (NUL-terminated). Returns length.
+#[no_mangle]
+pub extern "C" fn uring_render_info(buf: *mut u8, buf_len: u32) -> u32 {
+    let mut out = match unsafe { TextBuf::from_raw(buf, buf_len as usize) } {
+        Some(t) => t,
+        None => return 0,
+    };
+    let _ = render(&mut out);
+    out.finish_cstr() as u32
+}
@@ -0,0 +1,271 @@
+// usermem.rs — Validated access to user memory
+//
+// Syscall handlers must never dereference a user pointer directly. Every
+// access goes through this module, which checks each page of the range
+// against the calling process before touching it:
+//
+//   1. the page lies in the process's user region (PCB user_memory_base /
+//      user_memory_size), its sbrk heap or one of its mmap VMAs;
+//   2. the PTE is present and user-accessible, and writable for writes.
+//
+// A page that is not present (swapped out, or a VMA page not yet demand
+// filled) is faulted in through the arch code and re-checked. Copies run a
+// page at a time, validating each page right before it is copied, so a
+// fault-in further along the range cannot pull an already checked page out
+// from under the copy.
+//
+// The arch code supplies page-table access through UserMemOps, in the same
+// way swap.rs gets SwapOps. Until usermem_init() runs, and for kernel tasks,
+// only the null and wrap-around checks apply.
+
+use core::ffi::c_void;
+use core::ptr::{self, addr_of_mut};
+
+use crate::errno::{self, KResult, KernelError};
+use crate::scheduler::Scheduler;
+
+extern "C" {
+    fn get_kernel_scheduler() -> *mut c_void;
+}
+
+const PAGE_SIZE: usize = 4096;
+
+// PTE bits, identical on i386 and x86_64
+pub const PTE_PRESENT: u32 = 0x01;
+pub const PTE_WRITABLE: u32 = 0x02;
+pub const PTE_USER: u32 = 0x04;
+
+/// A user address range reported by the arch code, [start, end).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UserRange {
+    pub start: usize,
+    pub end: usize,
+    pub writable: u8,
+}
+
+/// Page-table and address-space primitives provided by the arch code.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UserMemOps {
+    /// Pid of the calling user task, 0 in kernel context.
+    pub current_pid: extern "C" fn() -> usize,
+    /// Fill out with the sbrk heap or mmap VMA of the current task that
+    /// contains vaddr. Returns 0 if vaddr is in neither.
+    pub find_range: extern "C" fn(vaddr: usize, out: *mut UserRange) -> u8,
+    /// Low flag bits of the PTE (or large-page entry) mapping vaddr, 0 if
+    /// there is none.
+    pub page_flags: extern "C" fn(vaddr: usize) -> u32,
+    /// Make the page at vaddr present (swap-in or demand fill). 1 on success.
+    pub fault_in: extern "C" fn(vaddr: usize) -> u8,
+}
+
+static mut OPS: Option<UserMemOps> = None;
+
+fn ops() -> Option<UserMemOps> {
+    unsafe { *addr_of_mut!(OPS) }
+}
+
+/// Caller of the current syscall.
+struct Caller {
+    ops: UserMemOps,
+    /// PCB user region [base, end); empty when the PCB has none.
+    base: usize,
+    end: usize,
+}
+
+impl Caller {
+    /// None in kernel context, where only the null/wrap checks apply.
+    fn current() -> Option<Self> {
+        let ops = ops()?;
+        let pid = (ops.current_pid)();
+        if pid == 0 {
+            return None;
+        }
+        let (base, size) = unsafe { (get_kernel_scheduler() as *const Scheduler).as_ref() }
+            .and_then(|s| s.processes.get(&pid))
+            .map(|p| (p.user_memory_base, p.user_memory_size))
+            .unwrap_or((0, 0));
+        Some(Self { ops, base, end: base.saturating_add(size) })
+    }
+
+    fn owns(&self, page: usize, write: bool) -> bool {
+        if page >= self.base && page < self.end {
+            return true;
+        }
+        let mut r = UserRange { start: 0, end: 0, writable: 0 };
+        (self.ops.find_range)(page, &mut r) != 0
+            && page >= r.start
+            && page < r.end
+            && (!write || r.writable != 0)
+    }
+
+    fn check_page(&self, page: usize, write: bool) -> KResult<()> {
+        if !self.owns(page, write) {
+            return Err(KernelError::Fault);
+        }
+        let need = PTE_PRESENT | PTE_USER | if write { PTE_WRITABLE } else { 0 };
+        let mut flags = (self.ops.page_flags)(page);
+        if flags & PTE_PRESENT == 0 && (self.ops.fault_in)(page) != 0 {
+            flags = (self.ops.page_flags)(page);
+        }
+        if flags & need == need {
+            Ok(())
+        } else {
+            Err(KernelError::Fault)
+        }
+    }
+}
+
+/// Walk [uaddr, uaddr + len) one page-bounded chunk at a time, validating
+/// each page before handing (uaddr, offset, n) to f. f returns false to stop.
+fn walk(uaddr: usize, len: usize, write: bool,
+        mut f: impl FnMut(usize, usize, usize) -> bool) -> KResult<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    if uaddr == 0 || uaddr.checked_add(len).is_none() {
+        return Err(KernelError::Fault);
+    }
+    let caller = match Caller::current() {
+        Some(c) => c,
+        None => {
+            f(uaddr, 0, len);
+            return Ok(());
+        }
+    };
+    let mut off = 0;
+    while off < len {
+        let addr = uaddr + off;
+        let n = (PAGE_SIZE - (addr & (PAGE_SIZE - 1))).min(len - off);
+        caller.check_page(addr & !(PAGE_SIZE - 1), write)?;
+        if !f(addr, off, n) {
+            break;
+        }
+        off += n;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// API
+// =============================================================================
+
+/// Check that the calling process may access [uaddr, uaddr + len).
+pub fn access_ok(uaddr: usize, len: usize, write: bool) -> KResult<()> {
+    walk(uaddr, len, write, |_, _, _| true)
+}
+
+/// Copy dst.len() bytes from user address usrc.
+pub fn copy_from_user(dst: &mut [u8], usrc: usize) -> KResult<()> {
+    walk(usrc, dst.len(), false, |addr, off, n| {
+        unsafe { ptr::copy_nonoverlapping(addr as *const u8, dst.as_mut_ptr().add(off), n) };
+        true
+    })
+}
+
+/// copy_from_user for reads of arbitrary addresses (debug dumps). In kernel
+/// context, where copy_from_user trusts the pointer, every page must also be
+/// mapped present; nothing is faulted in.
+pub fn peek(dst: &mut [u8], src: usize) -> KResult<()> {
+    if Caller::current().is_none() {
+        let ops = ops().ok_or(KernelError::Fault)?;
+        walk(src, dst.len(), false, |_, _, _| true)?;
+        let end = src + dst.len();
+        let mut page = src & !(PAGE_SIZE - 1);
+        while page < end {
+            if (ops.page_flags)(page) & PTE_PRESENT == 0 {
+                return Err(KernelError::Fault);
+            }
+            page = match page.checked_add(PAGE_SIZE) {
+                Some(p) => p,
+                None => break,
+            };
+        }
+    }
+    copy_from_user(dst, src)
+}
+
+/// Copy src to user address udst.
+pub fn copy_to_user(udst: usize, src: &[u8]) -> KResult<()> {
+    walk(udst, src.len(), true, |addr, off, n| {
+        unsafe { ptr::copy_nonoverlapping(src.as_ptr().add(off), addr as *mut u8, n) };
+        true
+    })
+}
+
+/// Copy a NUL-terminated string from user address usrc into dst, reading at
+/// most dst.len() bytes. dst is always NUL-terminated on success; returns the
+/// string length without the NUL. NameTooLong if no NUL fits in dst.
+pub fn strncpy_from_user(dst: &mut [u8], usrc: usize) -> KResult<usize> {
+    if dst.is_empty() {
+        return Err(KernelError::Inval);
+    }
+    let mut found = None;
+    walk(usrc, dst.len(), false, |addr, off, n| {
+        for i in 0..n {
+            let b = unsafe { ptr::read_volatile((addr + i) as *const u8) };
+            dst[off + i] = b;
+            if b == 0 {
+                found = Some(off + i);
+                return false;
+            }
+        }
+        true
+    })?;
+    found.ok_or(KernelError::NameTooLong)
+}
+
+// =============================================================================
+// FFI
+// =============================================================================
+
+/// Install the arch page-table callbacks. 0 or -EINVAL.
+#[no_mangle]
+pub extern "C" fn usermem_init(ops: *const UserMemOps) -> i32 {
+    if ops.is_null() {
+        return KernelError::Inval.neg();
+    }
+    unsafe { *addr_of_mut!(OPS) = Some(*ops) };
+    0
+}
+
+/// 0 if the caller may access the range (write != 0: for writing), else -EFAULT.
+#[no_mangle]
+pub extern "C" fn usermem_access_ok(uaddr: usize, len: usize, write: u8) -> i32 {
+    errno::to_c(access_ok(uaddr, len, write != 0))
+}
+
+#[no_mangle]
+pub extern "C" fn usermem_copy_from_user(dst: *mut u8, usrc: usize, len: usize) -> i32 {
+    if len == 0 {
+        return 0;
+    }
+    if dst.is_null() {
+        return KernelError::Inval.neg();
+    }
+    let dst = unsafe { core::slice::from_raw_parts_mut(dst, len) };
+    errno::to_c(copy_from_user(dst, usrc))
+}
+
+#[no_mangle]
+pub extern "C" fn usermem_copy_to_user(udst: usize, src: *const u8, len: usize) -> i32 {
+    if len == 0 {
+        return 0;
+    }
+    if src.is_null() {
+        return KernelError::Inval.neg();
+    }
+    let src = unsafe { core::slice::from_raw_parts(src, len) };
+    errno::to_c(copy_to_user(udst, src))
+}
+
+/// String length (without the NUL) or -errno.
+#[no_mangle]
+pub extern "C" fn usermem_strncpy_from_user(dst: *mut u8, usrc: usize, max: usize) -> isize {
+    if dst.is_null() {
+        return KernelError::Inval.neg_isize();
+    }
+    let dst = unsafe { core::slice::from_raw_parts_mut(dst, max) };
+    errno::to_c_isize(strncpy_from_user(dst, usrc).map(|n| n as isize))
+}
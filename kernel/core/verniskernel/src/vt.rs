@@ -0,0 +1,590 @@
+// vt.rs — VT100/ANSI escape sequence interpreter
+//
+// One parser shared by the framebuffer console (console.rs) and the GUI
+// terminal (gui/terminal.rs). `Vt` owns everything a terminal keeps between
+// bytes — cursor, pending wrap, scroll region, saved cursor, attributes and
+// the partial escape sequence — and drives a `VtScreen`, which only has to
+// put, clear and scroll cells.
+//
+// Handled:
+//   C0      BS (erases, as the console always has), HT, LF (also returns the
+//           carriage: kernel output uses bare "\n"), CR, BEL (ignored)
+//   ESC     7 8 (save/restore cursor), D (index), E (next line),
+//           M (reverse index), c (reset); charset selections are skipped
+//   CSI     A B C D E F G H f d (cursor), J K (erase display/line),
+//           X (erase chars), L M (insert/delete lines), S T (scroll),
+//           r (scroll region), s u (save/restore), m (SGR), ?25 h/l
+//   SGR     0 1 22 7 27, 30-37 39 40-47 49 90-97 100-107,
+//           38/48 with ;5;n (256 colors) or ;2;r;g;b (truecolor)
+//   OSC     0 and 2 (title), terminated by BEL or ESC \
+//
+// Printable text is UTF-8 (font.rs): each character takes a cell, and a
+// combining mark is added to the cell written just before it.
+//
+// Colors are resolved to 0xRRGGBB, the framebuffer's native order.
+
+use crate::font::{self, Grapheme, Utf8Decoder};
+
+/// Standard VGA/ANSI 16-color palette, 0xRRGGBB.
+pub const VGA_PALETTE: [u32; 16] = [
+    0x000000, // 0  black
+    0x0000AA, // 1  blue
+    0x00AA00, // 2  green
+    0x00AAAA, // 3  cyan
+    0xAA0000, // 4  red
+    0xAA00AA, // 5  magenta
+    0xAA5500, // 6  brown
+    0xAAAAAA, // 7  light grey
+    0x555555, // 8  dark grey
+    0x5555FF, // 9  light blue
+    0x55FF55, // 10 light green
+    0x55FFFF, // 11 light cyan
+    0xFF5555, // 12 light red
+    0xFF55FF, // 13 light magenta
+    0xFFFF55, // 14 yellow
+    0xFFFFFF, // 15 white
+];
+
+/// ANSI color number (SGR 30-37) → VGA palette index.
+const ANSI_TO_VGA: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+const MAX_PARAMS: usize = 16;
+const TITLE_MAX: usize = 63;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtColor {
+    /// VGA palette index (0-15).
+    Vga(u8),
+    /// xterm 256-color index.
+    Indexed(u8),
+    Rgb(u32),
+}
+
+impl VtColor {
+    pub fn rgb(self) -> u32 {
+        match self {
+            VtColor::Vga(i) => VGA_PALETTE[(i & 0x0F) as usize],
+            VtColor::Indexed(i) => xterm_color(i),
+            VtColor::Rgb(c) => c & 0x00FF_FFFF,
+        }
+    }
+}
+
+/// xterm 256-color index → 0xRRGGBB: 16 ANSI colors, a 6x6x6 cube, then a
+/// 24-step grey ramp.
+pub fn xterm_color(i: u8) -> u32 {
+    match i {
+        0..=15 => VGA_PALETTE[ANSI_TO_VGA[(i & 7) as usize] as usize + if i >= 8 { 8 } else { 0 }],
+        16..=231 => {
+            let n = i - 16;
+            let level = |v: u8| -> u32 { if v == 0 { 0 } else { 55 + v as u32 * 40 } };
+            (level(n / 36) << 16) | (level((n / 6) % 6) << 8) | level(n % 6)
+        }
+        _ => {
+            let g = 8 + (i - 232) as u32 * 10;
+            (g << 16) | (g << 8) | g
+        }
+    }
+}
+
+/// Character attributes set by SGR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtAttr {
+    pub fg: VtColor,
+    pub bg: VtColor,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl VtAttr {
+    /// Resolved (fg, bg): bold brightens the eight base colors, reverse
+    /// swaps the pair.
+    pub fn colors(&self) -> (u32, u32) {
+        let fg = match self.fg {
+            VtColor::Vga(i) if self.bold && i < 8 => VtColor::Vga(i + 8),
+            c => c,
+        };
+        if self.reverse {
+            (self.bg.rgb(), fg.rgb())
+        } else {
+            (fg.rgb(), self.bg.rgb())
+        }
+    }
+}
+
+/// Cell operations a Vt drives. Rows and columns are 0-based; ranges are
+/// half-open.
+pub trait VtScreen {
+    fn put(&mut self, row: u32, col: u32, g: Grapheme, fg: u32, bg: u32);
+    /// Blank cols [from, to) of row with bg.
+    fn clear(&mut self, row: u32, from: u32, to: u32, bg: u32);
+    /// Move rows [top, bottom) up by lines (down if negative), blanking the
+    /// rows uncovered with bg.
+    fn scroll(&mut self, top: u32, bottom: u32, lines: i32, bg: u32);
+    fn set_title(&mut self, _title: &[u8]) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    /// ESC followed by an intermediate ('(' ')' '#' ...): skip one byte.
+    EscSkip,
+    Csi,
+    Osc,
+    /// ESC seen inside an OSC string (expecting '\').
+    OscEsc,
+}
+
+/// The cell last printed, which a following combining mark joins.
+#[derive(Debug, Clone, Copy)]
+struct LastCell {
+    row: u32,
+    col: u32,
+    g: Grapheme,
+    fg: u32,
+    bg: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Saved {
+    row: u32,
+    col: u32,
+    attr: VtAttr,
+}
+
+pub struct Vt {
+    pub rows: u32,
+    pub cols: u32,
+    pub row: u32,
+    pub col: u32,
+    pub attr: VtAttr,
+    pub cursor_visible: bool,
+    default_attr: VtAttr,
+    /// Last column written; the next printable wraps first.
+    wrap_pending: bool,
+    /// Scroll region [top, bottom).
+    top: u32,
+    bottom: u32,
+    saved: Saved,
+    state: State,
+    params: [u16; MAX_PARAMS],
+    nparams: usize,
+    private: bool,
+    osc: [u8; TITLE_MAX + 3],
+    osc_len: usize,
+    utf8: Utf8Decoder,
+    last: Option<LastCell>,
+}
+
+impl Vt {
+    pub const fn new(fg: VtColor, bg: VtColor) -> Self {
+        let attr = VtAttr { fg, bg, bold: false, reverse: false };
+        Vt {
+            rows: 0,
+            cols: 0,
+            row: 0,
+            col: 0,
+            attr,
+            cursor_visible: true,
+            default_attr: attr,
+            wrap_pending: false,
+            top: 0,
+            bottom: 0,
+            saved: Saved { row: 0, col: 0, attr },
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            nparams: 0,
+            private: false,
+            osc: [0; TITLE_MAX + 3],
+            osc_len: 0,
+            utf8: Utf8Decoder::new(),
+            last: None,
+        }
+    }
+
+    /// Set the screen size; resets the scroll region and clamps the cursor.
+    pub fn resize(&mut self, rows: u32, cols: u32) {
+        self.rows = rows;
+        self.cols = cols;
+        self.top = 0;
+        self.bottom = rows;
+        self.row = self.row.min(rows.saturating_sub(1));
+        self.col = self.col.min(cols.saturating_sub(1));
+        self.wrap_pending = false;
+    }
+
+    /// Colors out of band (console_set_color_vga and friends); they become
+    /// the colors SGR 0/39/49 return to.
+    pub fn set_colors(&mut self, fg: VtColor, bg: VtColor) {
+        self.attr.fg = fg;
+        self.attr.bg = bg;
+        self.default_attr.fg = fg;
+        self.default_attr.bg = bg;
+    }
+
+    /// Move the cursor, clamped to the screen.
+    pub fn move_to(&mut self, row: u32, col: u32) {
+        self.row = row.min(self.rows.saturating_sub(1));
+        self.col = col.min(self.cols.saturating_sub(1));
+        self.wrap_pending = false;
+    }
+
+    /// Blank the whole screen and home the cursor.
+    pub fn clear<S: VtScreen>(&mut self, screen: &mut S) {
+        let (_, bg) = self.attr.colors();
+        for r in 0..self.rows {
+            screen.clear(r, 0, self.cols, bg);
+        }
+        self.move_to(0, 0);
+    }
+
+    pub fn feed<S: VtScreen>(&mut self, screen: &mut S, b: u8) {
+        if self.rows == 0 || self.cols == 0 {
+            return;
+        }
+        match self.state {
+            State::Ground if b >= 0x80 || self.utf8.pending() => {
+                for c in self.utf8.push(b).into_iter().flatten() {
+                    if c.is_ascii() {
+                        self.ground(screen, c as u8);
+                    } else {
+                        self.print(screen, c);
+                    }
+                }
+            }
+            State::Ground => self.ground(screen, b),
+            State::Escape => self.escape(screen, b),
+            State::EscSkip => self.state = State::Ground,
+            State::Csi => self.csi_byte(screen, b),
+            State::Osc => match b {
+                0x07 => self.osc_end(screen),
+                0x1B => self.state = State::OscEsc,
+                _ => {
+                    if self.osc_len < self.osc.len() {
+                        self.osc[self.osc_len] = b;
+                        self.osc_len += 1;
+                    }
+                }
+            },
+            State::OscEsc => {
+                if b == b'\\' {
+                    self.osc_end(screen);
+                } else {
+                    self.state = State::Ground;
+                }
+            }
+        }
+    }
+
+    pub fn write<S: VtScreen>(&mut self, screen: &mut S, s: &[u8]) {
+        for &b in s {
+            self.feed(screen, b);
+        }
+    }
+
+    fn ground<S: VtScreen>(&mut self, screen: &mut S, b: u8) {
+        if b < 0x20 || b == 0x7F {
+            self.last = None;
+        }
+        match b {
+            0x1B => self.state = State::Escape,
+            b'\n' | 0x0B | 0x0C => {
+                self.col = 0;
+                self.line_feed(screen);
+            }
+            b'\r' => {
+                self.col = 0;
+                self.wrap_pending = false;
+            }
+            0x08 => {
+                if self.wrap_pending {
+                    self.wrap_pending = false;
+                } else if self.col > 0 {
+                    self.col -= 1;
+                }
+                let (_, bg) = self.attr.colors();
+                screen.clear(self.row, self.col, self.col + 1, bg);
+            }
+            b'\t' => {
+                let next = (self.col / 8 + 1) * 8;
+                self.col = next.min(self.cols - 1);
+                self.wrap_pending = false;
+            }
+            0x00..=0x1F | 0x7F => {}
+            _ => self.print(screen, b as char),
+        }
+    }
+
+    fn print<S: VtScreen>(&mut self, screen: &mut S, c: char) {
+        if font::is_combining(c) {
+            if let Some(last) = &mut self.last {
+                last.g = last.g.with_mark(c);
+                screen.put(last.row, last.col, last.g, last.fg, last.bg);
+                return;
+            }
+        }
+        if self.wrap_pending {
+            self.col = 0;
+            self.line_feed(screen);
+        }
+        let (fg, bg) = self.attr.colors();
+        let g = if font::is_combining(c) { Grapheme::BLANK.with_mark(c) } else { Grapheme::new(c) };
+        screen.put(self.row, self.col, g, fg, bg);
+        self.last = Some(LastCell { row: self.row, col: self.col, g, fg, bg });
+        if self.col + 1 >= self.cols {
+            self.wrap_pending = true;
+        } else {
+            self.col += 1;
+        }
+    }
+
+    fn line_feed<S: VtScreen>(&mut self, screen: &mut S) {
+        self.wrap_pending = false;
+        if self.row + 1 == self.bottom {
+            self.scroll(screen, 1);
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    fn reverse_index<S: VtScreen>(&mut self, screen: &mut S) {
+        self.wrap_pending = false;
+        if self.row == self.top {
+            self.scroll(screen, -1);
+        } else if self.row > 0 {
+            self.row -= 1;
+        }
+    }
+
+    fn scroll<S: VtScreen>(&mut self, screen: &mut S, lines: i32) {
+        let (_, bg) = self.attr.colors();
+        screen.scroll(self.top, self.bottom, lines, bg);
+    }
+
+    fn escape<S: VtScreen>(&mut self, screen: &mut S, b: u8) {
+        self.state = State::Ground;
+        match b {
+            b'[' => {
+                self.params = [0; MAX_PARAMS];
+                self.nparams = 0;
+                self.private = false;
+                self.state = State::Csi;
+            }
+            b']' => {
+                self.osc_len = 0;
+                self.state = State::Osc;
+            }
+            b'7' => self.save(),
+            b'8' => self.restore(),
+            b'D' => self.line_feed(screen),
+            b'E' => {
+                self.col = 0;
+                self.line_feed(screen);
+            }
+            b'M' => self.reverse_index(screen),
+            b'c' => {
+                self.attr = self.default_attr;
+                self.cursor_visible = true;
+                let rows = self.rows;
+                self.resize(rows, self.cols);
+                self.clear(screen);
+            }
+            b'(' | b')' | b'*' | b'+' | b'#' | b'%' => self.state = State::EscSkip,
+            _ => {}
+        }
+    }
+
+    fn save(&mut self) {
+        self.saved = Saved { row: self.row, col: self.col, attr: self.attr };
+    }
+
+    fn restore(&mut self) {
+        let s = self.saved;
+        self.attr = s.attr;
+        self.move_to(s.row, s.col);
+    }
+
+    fn csi_byte<S: VtScreen>(&mut self, screen: &mut S, b: u8) {
+        match b {
+            b'0'..=b'9' => {
+                if self.nparams == 0 {
+                    self.nparams = 1;
+                }
+                let p = &mut self.params[self.nparams - 1];
+                *p = p.saturating_mul(10).saturating_add((b - b'0') as u16);
+            }
+            b';' | b':' => {
+                if self.nparams == 0 {
+                    self.nparams = 1;
+                }
+                if self.nparams < MAX_PARAMS {
+                    self.nparams += 1;
+                }
+            }
+            b'?' | b'>' | b'=' | b'<' => self.private = true,
+            // Intermediates: accepted, not interpreted
+            0x20..=0x2F => {}
+            0x40..=0x7E => {
+                self.state = State::Ground;
+                self.csi_dispatch(screen, b);
+            }
+            0x1B => self.state = State::Escape,
+            // Controls execute in the middle of a sequence
+            0x00..=0x1F => self.ground(screen, b),
+            _ => self.state = State::Ground,
+        }
+    }
+
+    /// Parameter i, with 0 (or missing) meaning def.
+    fn param(&self, i: usize, def: u32) -> u32 {
+        match self.params.get(i) {
+            Some(&p) if i < self.nparams && p != 0 => p as u32,
+            _ => def,
+        }
+    }
+
+    fn csi_dispatch<S: VtScreen>(&mut self, screen: &mut S, b: u8) {
+        if self.private {
+            if self.param(0, 0) == 25 {
+                match b {
+                    b'h' => self.cursor_visible = true,
+                    b'l' => self.cursor_visible = false,
+                    _ => {}
+                }
+            }
+            return;
+        }
+        let n = self.param(0, 1);
+        let (_, bg) = self.attr.colors();
+        match b {
+            // Vertical moves stop at the scroll region's margins when
+            // starting inside it
+            b'A' => {
+                let floor = if self.row >= self.top { self.top } else { 0 };
+                self.move_to(self.row.saturating_sub(n).max(floor), self.col);
+            }
+            b'B' => {
+                let limit = if self.row < self.bottom { self.bottom - 1 } else { self.rows - 1 };
+                self.move_to(self.row.saturating_add(n).min(limit), self.col);
+            }
+            b'C' => self.move_to(self.row, self.col.saturating_add(n)),
+            b'D' => self.move_to(self.row, self.col.saturating_sub(n)),
+            b'E' => self.move_to(self.row.saturating_add(n), 0),
+            b'F' => self.move_to(self.row.saturating_sub(n), 0),
+            b'G' => self.move_to(self.row, n - 1),
+            b'd' => self.move_to(n - 1, self.col),
+            b'H' | b'f' => self.move_to(n - 1, self.param(1, 1) - 1),
+            b'J' => {
+                let (row, col) = (self.row, self.col);
+                let (first, last) = match self.param(0, 0) {
+                    0 => {
+                        screen.clear(row, col, self.cols, bg);
+                        (row + 1, self.rows)
+                    }
+                    1 => {
+                        screen.clear(row, 0, col + 1, bg);
+                        (0, row)
+                    }
+                    _ => (0, self.rows),
+                };
+                for r in first..last {
+                    screen.clear(r, 0, self.cols, bg);
+                }
+            }
+            b'K' => {
+                let (from, to) = match self.param(0, 0) {
+                    0 => (self.col, self.cols),
+                    1 => (0, self.col + 1),
+                    _ => (0, self.cols),
+                };
+                screen.clear(self.row, from, to, bg);
+            }
+            b'X' => {
+                let to = self.col.saturating_add(n).min(self.cols);
+                screen.clear(self.row, self.col, to, bg);
+            }
+            b'L' | b'M' => {
+                if self.row >= self.top && self.row < self.bottom {
+                    let lines = n.min(self.bottom - self.row) as i32;
+                    screen.scroll(self.row, self.bottom, if b == b'L' { -lines } else { lines }, bg);
+                    self.col = 0;
+                    self.wrap_pending = false;
+                }
+            }
+            b'S' => self.scroll(screen, n.min(self.rows) as i32),
+            b'T' => self.scroll(screen, -(n.min(self.rows) as i32)),
+            b'r' => {
+                let top = self.param(0, 1) - 1;
+                let bottom = self.param(1, self.rows).min(self.rows);
+                if top + 1 < bottom {
+                    self.top = top;
+                    self.bottom = bottom;
+                    self.move_to(0, 0);
+                }
+            }
+            b's' => self.save(),
+            b'u' => self.restore(),
+            b'm' => self.sgr(),
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self) {
+        if self.nparams == 0 {
+            self.attr = self.default_attr;
+            return;
+        }
+        let mut i = 0;
+        while i < self.nparams {
+            let p = self.params[i] as u32;
+            match p {
+                0 => self.attr = self.default_attr,
+                1 => self.attr.bold = true,
+                22 => self.attr.bold = false,
+                7 => self.attr.reverse = true,
+                27 => self.attr.reverse = false,
+                30..=37 => self.attr.fg = VtColor::Vga(ANSI_TO_VGA[(p - 30) as usize]),
+                39 => self.attr.fg = self.default_attr.fg,
+                40..=47 => self.attr.bg = VtColor::Vga(ANSI_TO_VGA[(p - 40) as usize]),
+                49 => self.attr.bg = self.default_attr.bg,
+                90..=97 => self.attr.fg = VtColor::Vga(ANSI_TO_VGA[(p - 90) as usize] + 8),
+                100..=107 => self.attr.bg = VtColor::Vga(ANSI_TO_VGA[(p - 100) as usize] + 8),
+                38 | 48 => {
+                    let (color, used) = self.extended_color(i + 1);
+                    if let Some(c) = color {
+                        if p == 38 { self.attr.fg = c } else { self.attr.bg = c }
+                    }
+                    i += used;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// 5;n or 2;r;g;b starting at params[i]. Returns the color and the
+    /// number of parameters consumed.
+    fn extended_color(&self, i: usize) -> (Option<VtColor>, usize) {
+        let get = |k: usize| if k < self.nparams { self.params[k] as u32 } else { 0 };
+        match get(i) {
+            5 => (Some(VtColor::Indexed(get(i + 1).min(255) as u8)), 2),
+            2 => {
+                let c = |k| get(k).min(255);
+                (Some(VtColor::Rgb((c(i + 1) << 16) | (c(i + 2) << 8) | c(i + 3))), 4)
+            }
+            _ => (None, 1),
+        }
+    }
+
+    fn osc_end<S: VtScreen>(&mut self, screen: &mut S) {
+        self.state = State::Ground;
+        let s = &self.osc[..self.osc_len];
+        if let Some(sep) = s.iter().position(|&c| c == b';') {
+            if &s[..sep] == b"0" || &s[..sep] == b"2" {
+                let title = &s[sep + 1..];
+                screen.set_title(&title[..title.len().min(TITLE_MAX)]);
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@
 // Kernel ↔ Python AI Engine via COM2 serial port (0x2F8)
 
 #include "ai_bridge.h"
+#include "usermem.h"
 #include <stddef.h>
 
 extern void serial_print(const char *s);
@@ -30,6 +31,16 @@ static uint32_t       g_next_seq    = 1;   // rolling sequence number for pairin
 static AiTuneHandler  g_tune_handler = (void*)0;
 static AiRemediateHandler g_remediate_handler = (void*)0;
 
+// Asynchronous queries: responses wait here until collected
+typedef struct {
+    uint32_t seq;      // 0 = slot free
+    int      done;
+    int      len;
+    char     resp[AI_MSG_MAX];
+} AiAsyncSlot;
+
+static AiAsyncSlot g_async[AI_ASYNC_MAX];
+
 // =============================================================================
 // COM2 Init
 // =============================================================================
@@ -132,6 +143,41 @@ static int ai_readline(char *buf, size_t max_len) {
     return 0;  // no complete line yet
 }
 
+// Parse "RESP|<seq>|<payload>" without modifying line. Returns 1 on a match.
+static int ai_parse_resp(const char *line, uint32_t *seq, const char **payload) {
+    if (line[0] != 'R' || line[1] != 'E' || line[2] != 'S' ||
+        line[3] != 'P' || line[4] != '|')
+        return 0;
+    uint32_t v = 0;
+    const char *p = line + 5;
+    while (*p >= '0' && *p <= '9') v = v * 10 + (uint32_t)(*p++ - '0');
+    if (*p != '|') return 0;
+    *seq = v;
+    *payload = p + 1;
+    return 1;
+}
+
+// Store a RESP line for an outstanding asynchronous query. Returns 1 if the
+// line was one.
+static int ai_store_async_resp(const char *line) {
+    uint32_t seq;
+    const char *payload;
+    if (!ai_parse_resp(line, &seq, &payload)) return 0;
+    for (int i = 0; i < AI_ASYNC_MAX; i++) {
+        AiAsyncSlot *s = &g_async[i];
+        if (s->seq != seq || s->done) continue;
+        int n = 0;
+        while (payload[n] && n < AI_MSG_MAX - 1) { s->resp[n] = payload[n]; n++; }
+        s->resp[n] = '\0';
+        s->len  = n;
+        s->done = 1;
+        return 1;
+    }
+    return 0;
+}
+
+static void ai_handle_cmd(char *line);
+
 // =============================================================================
 // Public API
 // =============================================================================
@@ -152,23 +198,16 @@ int ai_query_sync(const char *query, char *resp_buf, size_t max_len) {
         int n = ai_readline(line, AI_MSG_MAX);
         if (n <= 0) continue;
 
-        // Parse "RESP|<seq>|<payload>"
-        if (line[0] != 'R' || line[1] != 'E' || line[2] != 'S' ||
-            line[3] != 'P' || line[4] != '|') continue;
-
-        // Find second '|'
-        char *p = line + 5;
-        while (*p && *p != '|') p++;
-        if (*p != '|') continue;
-        *p = '\0';
-
-        // Check sequence matches
-        uint32_t recv_seq = 0;
-        for (char *d = line + 5; *d; d++) recv_seq = recv_seq * 10 + (uint32_t)(*d - '0');
-        if (recv_seq != seq) continue;
+        // Parse "RESP|<seq>|<payload>"; keep answers to async queries
+        uint32_t recv_seq;
+        const char *payload;
+        if (!ai_parse_resp(line, &recv_seq, &payload)) continue;
+        if (recv_seq != seq) {
+            ai_store_async_resp(line);
+            continue;
+        }
 
         // Copy payload
-        char *payload = p + 1;
         size_t i = 0;
         while (payload[i] && i < max_len - 1) { resp_buf[i] = payload[i]; i++; }
         resp_buf[i] = '\0';
@@ -180,6 +219,53 @@ int ai_query_sync(const char *query, char *resp_buf, size_t max_len) {
     return -1;  // timeout
 }
 
+int32_t ai_query_submit(const char *query) {
+    if (!query) return -EINVAL;
+    if (!g_com2_hw) return -ENODEV;
+    AiAsyncSlot *slot = (void*)0;
+    for (int i = 0; i < AI_ASYNC_MAX; i++) {
+        if (g_async[i].seq == 0) { slot = &g_async[i]; break; }
+    }
+    if (!slot) return -EBUSY;
+
+    // Keep seq a positive int32_t; 0 marks a free slot
+    if (g_next_seq == 0 || g_next_seq > 0x7FFFFFFFu) g_next_seq = 1;
+    uint32_t seq = g_next_seq++;
+    slot->seq  = seq;
+    slot->done = 0;
+    slot->len  = 0;
+    ai_send_frame("REQ", seq, query);
+    return (int32_t)seq;
+}
+
+int32_t ai_query_collect(uint32_t seq, char *resp_buf, size_t max_len) {
+    if (!resp_buf || max_len == 0) return -EINVAL;
+    // Drain whatever COM2 has; CMD frames are handled as ai_poll_cmd() would
+    char line[AI_MSG_MAX];
+    while (g_com2_hw && ai_readline(line, AI_MSG_MAX) > 0) {
+        if (!ai_store_async_resp(line))
+            ai_handle_cmd(line);
+    }
+    for (int i = 0; i < AI_ASYNC_MAX; i++) {
+        AiAsyncSlot *s = &g_async[i];
+        if (seq == 0 || s->seq != seq) continue;
+        if (!s->done) return -EAGAIN;
+        size_t n = 0;
+        while (n < (size_t)s->len && n < max_len - 1) { resp_buf[n] = s->resp[n]; n++; }
+        resp_buf[n] = '\0';
+        s->seq = 0;
+        return (int32_t)n;
+    }
+    return -ENOENT;
+}
+
+void ai_query_cancel(uint32_t seq) {
+    for (int i = 0; i < AI_ASYNC_MAX; i++) {
+        if (seq != 0 && g_async[i].seq == seq)
+            g_async[i].seq = 0;
+    }
+}
+
 void ai_send_event(const char *event_type, const char *data) {
     if (!g_com2_hw) return;   // no COM2 hardware — skip serial write
     ai_puts("EVT|");
@@ -242,6 +328,11 @@ void ai_poll_cmd(void) {
     int n = ai_readline(line, AI_MSG_MAX);
     if (n <= 0) return;  // no complete line yet
 
+    if (ai_store_async_resp(line)) return;
+    ai_handle_cmd(line);
+}
+
+static void ai_handle_cmd(char *line) {
     // Expect: CMD|<seq>|TUNE|<action>|<target>|<value>|<reason>
     // Or the AutoTunerModule sends: CMD|0|TUNE|action|target|value|reason
     // Wire format from to_cmd_payload(): "TUNE|action|target|value|reason"
@@ -356,15 +447,25 @@ uint32_t ai_syscall(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
     (void)a3;
     switch (num) {
         case SYS_AI_QUERY: {
-            const char *query    = (const char *)(uintptr_t)a1;
-            char       *resp_buf = (char *)(uintptr_t)a2;
-            return (uint32_t)ai_query_sync(query, resp_buf, AI_MSG_MAX);
+            static char query[AI_MSG_MAX];
+            static char resp[AI_MSG_MAX];
+            intptr_t rc = usermem_strncpy_from_user(query, a1, sizeof(query));
+            if (rc < 0) return (uint32_t)rc;
+            if (usermem_access_ok(a2, AI_MSG_MAX, 1) < 0) return (uint32_t)-EFAULT;
+            int n = ai_query_sync(query, resp, AI_MSG_MAX);
+            if (n < 0) return (uint32_t)n;
+            rc = usermem_copy_to_user(a2, resp, (size_t)n + 1);
+            return rc < 0 ? (uint32_t)rc : (uint32_t)n;
         }
         case SYS_AI_STATUS:
             return (uint32_t)g_status;
         case SYS_AI_EVENT: {
-            const char *etype = (const char *)(uintptr_t)a1;
-            const char *data  = (const char *)(uintptr_t)a2;
+            static char etype[32];
+            static char data[AI_MSG_MAX];
+            intptr_t rc = usermem_strncpy_from_user(etype, a1, sizeof(etype));
+            if (rc < 0) return (uint32_t)rc;
+            rc = usermem_strncpy_from_user(data, a2, sizeof(data));
+            if (rc < 0) return (uint32_t)rc;
             ai_send_event(etype, data);
             return 0;
         }
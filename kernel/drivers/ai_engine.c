@@ -3,6 +3,7 @@
 // Wraps Rust FFI functions and integrates with kernel subsystems.
 
 #include "ai_bridge.h"
+#include "slab.h"
 #include <stdint.h>
 #include <stddef.h>
 
@@ -24,9 +25,9 @@ extern uint32_t ai_engine_anomaly_count(const void *engine);
 extern uint32_t ai_engine_active_procs(const void *engine);
 extern uint32_t ai_engine_decision_count(const void *engine);
 extern void     ai_engine_free(void *engine);
-extern uint32_t ai_engine_load_policy(void *engine, const uint8_t *blob, size_t len);
+extern int32_t  ai_engine_load_policy(void *engine, const uint8_t *blob, size_t len);
 extern uint16_t ai_engine_policy_version(const void *engine);
-extern uint8_t  ai_engine_check_access(const void *engine, const uint8_t *cmd, size_t cmd_len);
+extern int32_t  ai_engine_check_access(const void *engine, const uint8_t *cmd, size_t cmd_len);
 extern uint32_t ai_engine_access_rule_count(const void *engine);
 
 // External kernel functions
@@ -34,7 +35,7 @@ extern void serial_print(const char *s);
 extern void serial_print_dec(uint32_t val);
 extern void *scheduler_new(void);
 extern void  scheduler_set_quantum(void *sched, uint32_t quantum);
-extern void  scheduler_set_priority(void *sched, uint32_t pid, uint8_t priority);
+extern int32_t scheduler_set_priority(void *sched, uint32_t pid, uint8_t priority);
 
 // Global AI engine instance
 static void *g_ai_engine = NULL;
@@ -53,6 +54,19 @@ static void kernel_ai_tune_cb(const uint8_t *action, size_t action_len,
                                const uint8_t *target, size_t target_len,
                                uint32_t value) {
     (void)target; (void)target_len;
+
+    // "MEM_PRESSURE": the pager is busy (value = pages swapped in the last
+    // sample); hand idle slab pages back to the heap.
+    if (action_len >= 12 && action[0] == 'M') {
+        uint32_t reaped = slab_reap_all();
+        serial_print("[ai-engine] memory pressure: swap activity=");
+        serial_print_dec(value);
+        serial_print(" slab bytes reaped=");
+        serial_print_dec(reaped);
+        serial_print("\n");
+        return;
+    }
+
     if (!g_kernel_scheduler) return;
 
     // Check action: "SCHED_QUANTUM" or "SCHED_PRIO"
@@ -106,6 +120,12 @@ void ai_kernel_engine_init(void *scheduler) {
     serial_print("[ai-engine] In-kernel Rust AI engine ready\n");
 }
 
+// Raw engine pointer for Rust subsystems that read engine state (e.g. the
+// heap OOM policy consulting process trust). NULL before init.
+void *ai_kernel_engine_handle(void) {
+    return g_ai_engine;
+}
+
 void ai_kernel_engine_feed(const char *event_type, const char *data, uint64_t now) {
     if (!g_ai_engine) return;
     g_ticks = now;
@@ -155,15 +175,18 @@ uint32_t ai_kernel_engine_active_procs(void) {
     return ai_engine_active_procs(g_ai_engine);
 }
 
-uint32_t ai_kernel_engine_load_policy(const uint8_t *blob, size_t len) {
-    if (!g_ai_engine || !blob || len < 8) return 0;
-    uint32_t result = ai_engine_load_policy(g_ai_engine, blob, len);
-    if (result) {
+int32_t ai_kernel_engine_load_policy(const uint8_t *blob, size_t len) {
+    if (!g_ai_engine) return -ENODEV;
+    if (!blob || len < 8) return -EINVAL;
+    int32_t result = ai_engine_load_policy(g_ai_engine, blob, len);
+    if (result == 0) {
         serial_print("[ai-engine] Policy loaded v");
         serial_print_dec((uint32_t)ai_engine_policy_version(g_ai_engine));
         serial_print("\n");
     } else {
-        serial_print("[ai-engine] Policy load FAILED\n");
+        serial_print("[ai-engine] Policy load FAILED: ");
+        serial_print(kernel_strerror(result));
+        serial_print("\n");
     }
     return result;
 }
@@ -173,8 +196,8 @@ uint16_t ai_kernel_engine_policy_version(void) {
     return ai_engine_policy_version(g_ai_engine);
 }
 
-uint8_t ai_kernel_engine_check_access(const char *command, size_t len) {
-    if (!g_ai_engine || !command || len == 0) return 255;
+int32_t ai_kernel_engine_check_access(const char *command, size_t len) {
+    if (!g_ai_engine) return -ENODEV;
     return ai_engine_check_access(g_ai_engine, (const uint8_t *)command, len);
 }
 
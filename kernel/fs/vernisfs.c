@@ -432,6 +432,83 @@ int vfs_mkdir(const char *path) {
     return -1;
 }
 
+// Reserve a contiguous extent of at least `size` bytes for path without
+// writing its contents (swap files, preallocated logs). An existing file
+// that is already large enough is kept as-is, provided it was reserved
+// (VFS_FLAG_SYSTEM): a user cannot plant a file of their own there.
+// Returns the file size, or -1 if the file is too small or there is no room.
+int vfs_reserve_file(const char *path, uint32_t size) {
+    if (!g_vfs_ready || size == 0) return -1;
+    const VfsFileEntry *existing = vfs_find_file(path);
+    if (existing) {
+        if (existing->type != VFS_TYPE_REGULAR || existing->size < size ||
+            !(existing->flags & VFS_FLAG_SYSTEM)) return -1;
+        return (int)existing->size;
+    }
+
+    uint32_t sectors = (size + 511) / 512;
+    if (g_superblock.total_data_sectors &&
+        g_superblock.first_free_sector + sectors > g_superblock.total_data_sectors)
+        return -1;
+
+    for (uint16_t i = 0; i < VFS_MAX_FILES; i++) {
+        if (g_filetable[i].type != VFS_TYPE_EMPTY) continue;
+        vfs_strncpy(g_filetable[i].filename, path, VFS_MAX_FILENAME);
+        g_filetable[i].type  = VFS_TYPE_REGULAR;
+        g_filetable[i].flags = VFS_FLAG_SYSTEM;
+        g_filetable[i].mode  = 0600;
+        g_filetable[i].uid   = 0;
+        g_filetable[i].gid   = 0;
+        g_filetable[i].start_sector = g_superblock.first_free_sector;
+        g_filetable[i].size  = sectors * 512;
+        g_superblock.first_free_sector += sectors;
+        g_superblock.file_count++;
+        return vfs_flush_metadata() == 0 ? (int)g_filetable[i].size : -1;
+    }
+    return -1;
+}
+
+// Sector-granular positional I/O inside an existing file's extent. offset
+// and len must be multiples of 512; the file is never resized.
+static int vfs_extent_check(const VfsFileEntry *entry, uint32_t offset, uint32_t len) {
+    if (!entry || entry->type != VFS_TYPE_REGULAR) return -1;
+    if ((offset | len) & 511) return -1;
+    if (offset > entry->size || len > entry->size - offset) return -1;
+    return 0;
+}
+
+int vfs_read_at(const char *path, uint32_t offset, uint8_t *buf, uint32_t len) {
+    const VfsFileEntry *entry = vfs_find_file(path);
+    if (!buf || vfs_extent_check(entry, offset, len) < 0) return -1;
+
+    uint32_t abs_sector = VFS_START_SECTOR + VFS_DATA_SECTOR + entry->start_sector + offset / 512;
+    uint32_t sectors = len / 512;
+    uint32_t done = 0;
+    while (done < sectors) {
+        uint8_t chunk = (sectors - done > 128) ? 128 : (uint8_t)(sectors - done);
+        if (g_disk_read(abs_sector + done, chunk, buf + done * 512) < 0)
+            return -1;
+        done += chunk;
+    }
+    return (int)len;
+}
+
+int vfs_write_at(const char *path, uint32_t offset, const uint8_t *data, uint32_t len) {
+    const VfsFileEntry *entry = vfs_find_file(path);
+    if (!data || vfs_extent_check(entry, offset, len) < 0) return -1;
+
+    uint32_t abs_sector = VFS_START_SECTOR + VFS_DATA_SECTOR + entry->start_sector + offset / 512;
+    uint32_t sectors = len / 512;
+    uint32_t done = 0;
+    while (done < sectors) {
+        uint8_t chunk = (sectors - done > 128) ? 128 : (uint8_t)(sectors - done);
+        if (g_disk_write(abs_sector + done, chunk, data + done * 512) < 0)
+            return -1;
+        done += chunk;
+    }
+    return (int)len;
+}
+
 int vfs_append_file(const char *path, const uint8_t *data, size_t len) {
     const VfsFileEntry *entry = vfs_find_file(path);
     if (!entry) return vfs_write_file(path, data, len);
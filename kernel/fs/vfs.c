@@ -4,6 +4,13 @@
 
 #include "vfs.h"
 #include "scheduler_base.h"
+#include "slab.h"
+#include "heap.h"
+#include "shm.h"
+#include "swap.h"
+#include "strace.h"
+#include "uring.h"
+#include "module.h"
 
 typedef struct {
     const VfsFileEntry *(*find_file)(const char *path);
@@ -57,10 +64,42 @@ static const VfsFileEntry KFS_NODE_PROC_PS = {
     .filename = "/proc/ps",
     .type = VFS_TYPE_REGULAR,
 };
+static const VfsFileEntry KFS_NODE_PROC_STACKS = {
+    .filename = "/proc/stacks",
+    .type = VFS_TYPE_REGULAR,
+};
 static const VfsFileEntry KFS_NODE_PROC_FS = {
     .filename = "/proc/fs",
     .type = VFS_TYPE_REGULAR,
 };
+static const VfsFileEntry KFS_NODE_PROC_SLABINFO = {
+    .filename = "/proc/slabinfo",
+    .type = VFS_TYPE_REGULAR,
+};
+static const VfsFileEntry KFS_NODE_PROC_HEAPINFO = {
+    .filename = "/proc/heapinfo",
+    .type = VFS_TYPE_REGULAR,
+};
+static const VfsFileEntry KFS_NODE_PROC_SHM = {
+    .filename = "/proc/shm",
+    .type = VFS_TYPE_REGULAR,
+};
+static const VfsFileEntry KFS_NODE_PROC_SWAP = {
+    .filename = "/proc/swap",
+    .type = VFS_TYPE_REGULAR,
+};
+static const VfsFileEntry KFS_NODE_PROC_STRACE = {
+    .filename = "/proc/strace",
+    .type = VFS_TYPE_REGULAR,
+};
+static const VfsFileEntry KFS_NODE_PROC_URING = {
+    .filename = "/proc/uring",
+    .type = VFS_TYPE_REGULAR,
+};
+static const VfsFileEntry KFS_NODE_PROC_MODULES = {
+    .filename = "/proc/modules",
+    .type = VFS_TYPE_REGULAR,
+};
 static const VfsFileEntry KFS_NODE_DEV_NULL = {
     .filename = "/dev/null",
     .type = VFS_TYPE_REGULAR,
@@ -85,18 +124,41 @@ static int kfs_path_is_pseudo(const char *path) {
            kfs_streq(path, "/dev") ||
            kfs_streq(path, "/proc/uptime") ||
            kfs_streq(path, "/proc/ps") ||
+           kfs_streq(path, "/proc/stacks") ||
            kfs_streq(path, "/proc/fs") ||
+           kfs_streq(path, "/proc/slabinfo") ||
+           kfs_streq(path, "/proc/heapinfo") ||
+           kfs_streq(path, "/proc/shm") ||
+           kfs_streq(path, "/proc/swap") ||
+           kfs_streq(path, "/proc/strace") ||
+           kfs_streq(path, "/proc/uring") ||
+           kfs_streq(path, "/proc/modules") ||
            kfs_streq(path, "/dev/null") ||
            kfs_streq(path, "/dev/zero");
 }
 
+// Files the kernel reserved for itself (VFS_FLAG_SYSTEM: the swap file) are
+// only reachable through the positional calls below, never by path.
+int kfs_path_is_system(const char *path) {
+    const VfsFileEntry *e = g_ops ? g_ops->find_file(path) : (const VfsFileEntry *)0;
+    return e && (e->flags & VFS_FLAG_SYSTEM);
+}
+
 static const VfsFileEntry *kfs_find_pseudo(const char *path) {
     if (!path) return (const VfsFileEntry *)0;
     if (kfs_streq(path, "/proc")) return &KFS_NODE_PROC;
     if (kfs_streq(path, "/dev")) return &KFS_NODE_DEV;
     if (kfs_streq(path, "/proc/uptime")) return &KFS_NODE_PROC_UPTIME;
     if (kfs_streq(path, "/proc/ps")) return &KFS_NODE_PROC_PS;
+    if (kfs_streq(path, "/proc/stacks")) return &KFS_NODE_PROC_STACKS;
     if (kfs_streq(path, "/proc/fs")) return &KFS_NODE_PROC_FS;
+    if (kfs_streq(path, "/proc/slabinfo")) return &KFS_NODE_PROC_SLABINFO;
+    if (kfs_streq(path, "/proc/heapinfo")) return &KFS_NODE_PROC_HEAPINFO;
+    if (kfs_streq(path, "/proc/shm")) return &KFS_NODE_PROC_SHM;
+    if (kfs_streq(path, "/proc/swap")) return &KFS_NODE_PROC_SWAP;
+    if (kfs_streq(path, "/proc/strace")) return &KFS_NODE_PROC_STRACE;
+    if (kfs_streq(path, "/proc/uring")) return &KFS_NODE_PROC_URING;
+    if (kfs_streq(path, "/proc/modules")) return &KFS_NODE_PROC_MODULES;
     if (kfs_streq(path, "/dev/null")) return &KFS_NODE_DEV_NULL;
     if (kfs_streq(path, "/dev/zero")) return &KFS_NODE_DEV_ZERO;
     return (const VfsFileEntry *)0;
@@ -189,7 +251,7 @@ static int kfs_read_proc_ps(uint8_t *buf, size_t max_len) {
         size_t n = scheduler_get_pid_list(sched, pids, 64);
         for (size_t i = 0; i < n; i++) {
             PsRow row;
-            if (!scheduler_get_ps_row(sched, pids[i], &row)) continue;
+            if (scheduler_get_ps_row(sched, pids[i], &row) != 0) continue;
             pos = kfs_append_u64(out, (int)sizeof(out), pos, row.pid);
             pos = kfs_append_str(out, (int)sizeof(out), pos, " ");
             pos = kfs_append_u64(out, (int)sizeof(out), pos, row.state);
@@ -210,6 +272,119 @@ static int kfs_read_proc_ps(uint8_t *buf, size_t max_len) {
     return (int)copy;
 }
 
+// Per-process stack high-water marks in bytes (sampled about once a second).
+static int kfs_read_proc_stacks(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[4096];
+    int pos = 0;
+    pos = kfs_append_str(out, (int)sizeof(out), pos, "PID KSTACK_PEAK USTACK_PEAK CMD\n");
+
+    struct Scheduler *sched = get_kernel_scheduler();
+    if (!sched) {
+        pos = kfs_append_str(out, (int)sizeof(out), pos, "(scheduler not ready)\n");
+    } else {
+        size_t pids[64];
+        size_t n = scheduler_get_pid_list(sched, pids, 64);
+        for (size_t i = 0; i < n; i++) {
+            PsRow row;
+            if (scheduler_get_ps_row(sched, pids[i], &row) != 0) continue;
+            pos = kfs_append_u64(out, (int)sizeof(out), pos, row.pid);
+            pos = kfs_append_str(out, (int)sizeof(out), pos, " ");
+            pos = kfs_append_u64(out, (int)sizeof(out), pos, row.kstack_peak);
+            pos = kfs_append_str(out, (int)sizeof(out), pos, " ");
+            pos = kfs_append_u64(out, (int)sizeof(out), pos, row.ustack_peak);
+            pos = kfs_append_str(out, (int)sizeof(out), pos, " ");
+            pos = kfs_append_str(out, (int)sizeof(out), pos, row.command);
+            pos = kfs_append_str(out, (int)sizeof(out), pos, "\n");
+            if (pos >= (int)sizeof(out) - 64) break;
+        }
+    }
+
+    size_t copy = (size_t)pos;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+static int kfs_read_proc_slabinfo(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[2048];
+    uint32_t n = slab_render_info((uint8_t *)out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+static int kfs_read_proc_heapinfo(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[1024];
+    uint32_t n = heap_render_info((uint8_t *)out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+static int kfs_read_proc_shm(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[2048];
+    uint32_t n = shm_render_info((uint8_t *)out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+static int kfs_read_proc_swap(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[2048];
+    uint32_t n = swap_render_info((uint8_t *)out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+// Tracer state and per-process call counts; the calls themselves are read
+// with the shell's `strace show <pid>`.
+static int kfs_read_proc_strace(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[2048];
+    uint32_t n = strace_render(0, (uint8_t *)out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+static int kfs_read_proc_uring(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[2048];
+    uint32_t n = uring_render_info((uint8_t *)out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
+static int kfs_read_proc_modules(uint8_t *buf, size_t max_len) {
+    if (!buf || max_len == 0) return -1;
+    char out[2048];
+    uint32_t n = module_render_proc(out, (uint32_t)sizeof(out));
+
+    size_t copy = (size_t)n;
+    if (copy > max_len) copy = max_len;
+    for (size_t i = 0; i < copy; i++) buf[i] = (uint8_t)out[i];
+    return (int)copy;
+}
+
 static int kfs_list_dir_add(char out[][VFS_MAX_FILENAME], int max, int count, const char *path) {
     if (count < 0 || count >= max) return count;
     kfs_copy_str(out[count], VFS_MAX_FILENAME, path);
@@ -368,8 +543,17 @@ int kfs_read_file(const char *path, uint8_t *buf, size_t max_len) {
     }
     if (kfs_streq(path, "/proc/uptime")) return kfs_read_proc_uptime(buf, max_len);
     if (kfs_streq(path, "/proc/ps")) return kfs_read_proc_ps(buf, max_len);
+    if (kfs_streq(path, "/proc/stacks")) return kfs_read_proc_stacks(buf, max_len);
     if (kfs_streq(path, "/proc/fs")) return kfs_read_proc_fs(buf, max_len);
+    if (kfs_streq(path, "/proc/slabinfo")) return kfs_read_proc_slabinfo(buf, max_len);
+    if (kfs_streq(path, "/proc/heapinfo")) return kfs_read_proc_heapinfo(buf, max_len);
+    if (kfs_streq(path, "/proc/shm")) return kfs_read_proc_shm(buf, max_len);
+    if (kfs_streq(path, "/proc/swap")) return kfs_read_proc_swap(buf, max_len);
+    if (kfs_streq(path, "/proc/strace")) return kfs_read_proc_strace(buf, max_len);
+    if (kfs_streq(path, "/proc/uring")) return kfs_read_proc_uring(buf, max_len);
+    if (kfs_streq(path, "/proc/modules")) return kfs_read_proc_modules(buf, max_len);
     if (kfs_streq(path, "/proc") || kfs_streq(path, "/dev")) return -1;
+    if (kfs_path_is_system(path)) return -1;
     return g_ops ? g_ops->read_file(path, buf, max_len) : -1;
 }
 
@@ -377,7 +561,7 @@ int kfs_write_file(const char *path, const uint8_t *data, size_t len) {
     (void)data;
     if (!path) return -1;
     if (kfs_streq(path, "/dev/null") || kfs_streq(path, "/dev/zero")) return (int)len;
-    if (kfs_path_is_pseudo(path)) return -1;
+    if (kfs_path_is_pseudo(path) || kfs_path_is_system(path)) return -1;
     return g_ops ? g_ops->write_file(path, data, len) : -1;
 }
 
@@ -385,7 +569,7 @@ int kfs_append_file(const char *path, const uint8_t *data, size_t len) {
     (void)data;
     if (!path) return -1;
     if (kfs_streq(path, "/dev/null") || kfs_streq(path, "/dev/zero")) return (int)len;
-    if (kfs_path_is_pseudo(path)) return -1;
+    if (kfs_path_is_pseudo(path) || kfs_path_is_system(path)) return -1;
     return g_ops ? g_ops->append_file(path, data, len) : -1;
 }
 
@@ -395,7 +579,7 @@ int kfs_mkdir(const char *path) {
 }
 
 int kfs_delete_file(const char *path) {
-    if (kfs_path_is_pseudo(path)) return -1;
+    if (kfs_path_is_pseudo(path) || kfs_path_is_system(path)) return -1;
     return g_ops ? g_ops->delete_file(path) : -1;
 }
 
@@ -416,7 +600,15 @@ int kfs_list_dir(const char *dir_path, char out[][VFS_MAX_FILENAME], int max) {
     if (kfs_streq(dir_path, "/proc")) {
         count = kfs_list_dir_add(out, max, count, "/proc/uptime");
         count = kfs_list_dir_add(out, max, count, "/proc/ps");
+        count = kfs_list_dir_add(out, max, count, "/proc/stacks");
         count = kfs_list_dir_add(out, max, count, "/proc/fs");
+        count = kfs_list_dir_add(out, max, count, "/proc/slabinfo");
+        count = kfs_list_dir_add(out, max, count, "/proc/heapinfo");
+        count = kfs_list_dir_add(out, max, count, "/proc/shm");
+        count = kfs_list_dir_add(out, max, count, "/proc/swap");
+        count = kfs_list_dir_add(out, max, count, "/proc/strace");
+        count = kfs_list_dir_add(out, max, count, "/proc/uring");
+        count = kfs_list_dir_add(out, max, count, "/proc/modules");
         return count;
     }
     if (kfs_streq(dir_path, "/dev")) {
@@ -432,17 +624,35 @@ uint16_t kfs_file_count(void) {
     return g_ops ? g_ops->file_count() : 0;
 }
 
+// Positional I/O is VernisFS-specific (contiguous extents), like chmod below.
+// These are kernel-internal and do reach VFS_FLAG_SYSTEM files; callers
+// acting for a process must check permissions and the flag themselves.
+int kfs_reserve_file(const char *path, uint32_t size) {
+    if (!path || !g_ops || kfs_path_is_pseudo(path)) return -1;
+    return vfs_reserve_file(path, size);
+}
+
+int kfs_read_at(const char *path, uint32_t offset, uint8_t *buf, uint32_t len) {
+    if (!path || !g_ops || kfs_path_is_pseudo(path)) return -1;
+    return vfs_read_at(path, offset, buf, len);
+}
+
+int kfs_write_at(const char *path, uint32_t offset, const uint8_t *data, uint32_t len) {
+    if (!path || !g_ops || kfs_path_is_pseudo(path)) return -1;
+    return vfs_write_at(path, offset, data, len);
+}
+
 // =============================================================================
 // Phase 47: File permissions
 // =============================================================================
 
 int kfs_chmod(const char *path, uint16_t mode) {
-    if (!path || kfs_path_is_pseudo(path)) return -1;
+    if (!path || kfs_path_is_pseudo(path) || kfs_path_is_system(path)) return -1;
     return vfs_chmod(path, mode);
 }
 
 int kfs_chown(const char *path, uint16_t uid, uint16_t gid) {
-    if (!path || kfs_path_is_pseudo(path)) return -1;
+    if (!path || kfs_path_is_pseudo(path) || kfs_path_is_system(path)) return -1;
     return vfs_chown(path, uid, gid);
 }
 
@@ -7,6 +7,7 @@
 //                   (will be used as AI IPC bridge in Phase 8)
 
 #include "ipc.h"
+#include "usermem.h"
 #include <stddef.h>
 
 // Forward declarations of kernel helpers from kernel_x86.c
@@ -314,15 +315,21 @@ int32_t ipc_syscall(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
 
     // SYS_IPC_SEND (20): a1=dst_pid, a2=ptr to IpcMessage
     case SYS_IPC_SEND: {
-        if (!a2) return IPC_ERR_INVAL;
-        const IpcMessage *m = (const IpcMessage *)a2;
-        return ipc_send(m->src_pid, a1, m->type, m->data, m->len);
+        IpcMessage m;
+        int32_t rc = usermem_copy_from_user(&m, a2, sizeof(m));
+        if (rc < 0) return rc;
+        return ipc_send(m.src_pid, a1, m.type, m.data, m.len);
     }
 
     // SYS_IPC_RECV (21): a1=qid, a2=ptr to IpcMessage output buffer
     case SYS_IPC_RECV: {
-        if (!a2) return IPC_ERR_INVAL;
-        return ipc_recv(a1, (IpcMessage *)a2);
+        // Check the buffer first so a bad pointer does not consume a message
+        int32_t rc = usermem_access_ok(a2, sizeof(IpcMessage), 1);
+        if (rc < 0) return rc;
+        IpcMessage m;
+        rc = ipc_recv(a1, &m);
+        if (rc < 0) return rc;
+        return usermem_copy_to_user(a2, &m, sizeof(m));
     }
 
     // SYS_IPC_QUEUE_CREATE (22): a1=pid → returns qid
@@ -339,14 +346,27 @@ int32_t ipc_syscall(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
         return ipc_channel_create(a1, a2);
 
     // SYS_IPC_CHAN_WRITE (25): a1=cid, a2=ptr, a3=len
-    case SYS_IPC_CHAN_WRITE:
-        if (!a2 || !a3) return IPC_ERR_INVAL;
-        return ipc_channel_write(a1, (const uint8_t *)a2, a3);
+    case SYS_IPC_CHAN_WRITE: {
+        if (!a3) return IPC_ERR_INVAL;
+        uint8_t kbuf[IPC_CHAN_BUF_SIZE];
+        if (a3 > sizeof(kbuf)) a3 = sizeof(kbuf);   // channel holds no more
+        int32_t rc = usermem_copy_from_user(kbuf, a2, a3);
+        if (rc < 0) return rc;
+        return ipc_channel_write(a1, kbuf, a3);
+    }
 
     // SYS_IPC_CHAN_READ (26): a1=cid, a2=ptr, a3=max_len
-    case SYS_IPC_CHAN_READ:
-        if (!a2 || !a3) return IPC_ERR_INVAL;
-        return ipc_channel_read(a1, (uint8_t *)a2, a3);
+    case SYS_IPC_CHAN_READ: {
+        if (!a3) return IPC_ERR_INVAL;
+        uint8_t kbuf[IPC_CHAN_BUF_SIZE];
+        if (a3 > sizeof(kbuf)) a3 = sizeof(kbuf);
+        int32_t rc = usermem_access_ok(a2, a3, 1);
+        if (rc < 0) return rc;
+        int32_t n = ipc_channel_read(a1, kbuf, a3);
+        if (n <= 0) return n;
+        rc = usermem_copy_to_user(a2, kbuf, (size_t)n);
+        return rc < 0 ? rc : n;
+    }
 
     // SYS_IPC_CHAN_CLOSE (27): a1=cid
     case SYS_IPC_CHAN_CLOSE:
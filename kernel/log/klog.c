@@ -1,9 +1,11 @@
 // klog.c — Kernel Structured Logging System (Phase 16)
 //
-// Ring buffer backed, serial-output structured logging.
+// Ring buffer backed, serial-output structured logging. Lines are also
+// written to the framebuffer console owned by the kernel log, if any.
 // Format: [tick][LEVEL][tag] message
 
 #include "klog.h"
+#include "console.h"
 
 // External kernel functions
 extern void serial_print(const char *s);
@@ -59,6 +61,27 @@ static const char *klog_level_short(KlogLevel level) {
     }
 }
 
+static size_t klog_append(char *dst, size_t pos, size_t cap, const char *src) {
+    while (*src && pos + 1 < cap) dst[pos++] = *src++;
+    return pos;
+}
+
+// Same line as serial, to the kernel log virtual console
+static void klog_console_line(const char *ticks, KlogLevel level, const KlogEntry *entry) {
+    char line[KLOG_TAG_MAX + KLOG_MSG_MAX + 40];
+    size_t n = 0;
+    n = klog_append(line, n, sizeof(line), "[");
+    n = klog_append(line, n, sizeof(line), ticks);
+    n = klog_append(line, n, sizeof(line), "][");
+    n = klog_append(line, n, sizeof(line), klog_level_short(level));
+    n = klog_append(line, n, sizeof(line), "][");
+    n = klog_append(line, n, sizeof(line), entry->tag);
+    n = klog_append(line, n, sizeof(line), "] ");
+    n = klog_append(line, n, sizeof(line), entry->message);
+    n = klog_append(line, n, sizeof(line), "\n");
+    console_log((const uint8_t *)line, (uint32_t)n);
+}
+
 void klog_init(void) {
     for (uint32_t i = 0; i < KLOG_RING_SIZE; i++) {
         g_ring[i].timestamp = 0;
@@ -100,6 +123,7 @@ void klog(KlogLevel level, const char *tag, const char *msg) {
         serial_print("] ");
         serial_print(entry->message);
         serial_print("\n");
+        klog_console_line(tickbuf, level, entry);
     }
 }
 
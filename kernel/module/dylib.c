@@ -64,7 +64,7 @@ void dylib_init(void) {
     serial_print("[dylib] initialized\n");
 }
 
-int32_t dylib_open(const char *path, const char *name) {
+int32_t dylib_open(const char *path, const char *name, const char *params) {
     if (!path || !path[0]) return DYLIB_ERR_INVAL;
 
     int32_t free_slot = -1;
@@ -78,7 +78,7 @@ int32_t dylib_open(const char *path, const char *name) {
     if (n < (int)sizeof(ModHeader)) return DYLIB_ERR_FORMAT;
 
     uint32_t addr = (uint32_t)(uintptr_t)g_dylib_storage[free_slot];
-    int32_t mid = module_load(addr, (uint32_t)n);
+    int32_t mid = module_load_params(addr, (uint32_t)n, params);
     if (mid < 0) return mid;
 
     DylibSlot *s = &g_dylibs[free_slot];
@@ -106,7 +106,8 @@ int32_t dylib_close(uint32_t handle) {
     int32_t idx = dylib_find_handle(handle);
     if (idx < 0) return DYLIB_ERR_NOTFOUND;
 
-    module_unload(g_dylibs[idx].mid);
+    int32_t rc = module_unload(g_dylibs[idx].mid, 0);
+    if (rc < 0) return rc;   // still depended on or in use
     d_memset(&g_dylibs[idx], 0, sizeof(DylibSlot));
     d_memset(g_dylib_storage[idx], 0, DYLIB_MAX_FILE_SIZE);
     return DYLIB_OK;
@@ -124,6 +125,19 @@ int32_t dylib_list(void) {
         serial_print(" path=");
         serial_print(g_dylibs[i].path);
         serial_print("\n");
+
+        // Named exports from the shared symbol table
+        ModSymbol sym;
+        uint32_t cursor = 0;
+        while (module_symbol_next(&cursor, &sym) == MOD_OK) {
+            if (sym.mid != g_dylibs[i].mid) continue;
+            serial_print("    ");
+            serial_print(sym.name);
+            serial_print(" -> fn");
+            serial_print_dec(sym.fn_id);
+            if (sym.flags & MOD_SYM_GPL) serial_print(" [gpl]");
+            serial_print("\n");
+        }
         count++;
     }
     if (!count) serial_print("  (none)\n");
@@ -137,6 +151,14 @@ int32_t dylib_resolve(uint32_t handle, const char *symbol) {
     ModInfo info;
     if (module_info(g_dylibs[idx].mid, &info) != MOD_OK) return DYLIB_ERR_NOTFOUND;
 
+    // Named exports go through the registry's symbol table; the fnN form
+    // still works for modules without one
+    ModSymbol sym;
+    if (module_resolve(symbol, MOD_RESOLVE_KERNEL, &sym) == MOD_OK) {
+        if (sym.mid != g_dylibs[idx].mid) return DYLIB_ERR_NOTFOUND;
+        return (int32_t)sym.fn_id;
+    }
+
     int32_t fn_id = dylib_symbol_to_fnid(symbol);
     if (fn_id < 0) return fn_id;
     if ((uint32_t)fn_id >= info.fn_count) return DYLIB_ERR_NOTFOUND;
@@ -3,7 +3,14 @@
 //
 // Architecture:
 //   C side  : validates headers, manages slot table, executes module functions
-//   Rust side: ModuleRegistry (BTreeMap) tracks metadata for queries
+//   Rust side: ModuleRegistry (BTreeMap) tracks metadata, the dependency
+//              graph, reference counts and lifecycle state, decides whether
+//              a load or unload may proceed and runs init/exit hooks
+//
+// Every entry into module code (calls and hooks) goes through
+// module_invoke(), which records a recovery point. A fault in module code
+// is charged to the module and unwound back to that point by the exception
+// handler (module_fault_enter / module_fault_unwind) instead of halting.
 //
 // Modules are flat binaries already present in kernel memory.
 // No filesystem access yet — modules embedded in BSS or passed by address.
@@ -11,20 +18,39 @@
 #include "module.h"
 #include "dylib.h"
 #include "ai_bridge.h"
+#include "usermem.h"
 #include <stddef.h>
 
 // Forward declarations of kernel helpers (defined in kernel_x86.c / kernel_x64.c)
 extern void serial_print(const char *s);
 extern void serial_print_hex(uint32_t val);
 extern void serial_print_dec(uint32_t val);
+extern uint32_t kernel_get_ticks(void);
+extern uint32_t kernel_current_task(void);
 
 // Rust ModuleRegistry FFI
 extern void    *module_registry_new(void);
-extern int32_t  module_registry_register(void *reg, uint32_t mid,
-                                          const char *name, uint32_t base_addr,
-                                          uint32_t code_size, uint32_t fn_count);
-extern int32_t  module_registry_unregister(void *reg, uint32_t mid);
+extern int32_t  module_registry_register(void *reg, const ModuleDesc *desc);
+extern int32_t  module_registry_unregister(void *reg, uint32_t mid, uint32_t flags,
+                                            uint32_t *removed, uint32_t max);
+extern int32_t  module_registry_acquire(void *reg, uint32_t mid);
+extern void     module_registry_release(void *reg, uint32_t mid);
+extern uint32_t module_registry_render_tree(void *reg, char *buf, uint32_t len);
+extern int32_t  module_registry_resolve(void *reg, const char *name,
+                                         uint32_t requester, ModSymbol *out);
+extern int32_t  module_registry_symbol_next(void *reg, uint32_t *cursor, ModSymbol *out);
+extern int32_t  module_registry_next(void *reg, uint32_t *cursor, ModRecord *out);
+extern uint32_t module_registry_render_proc(void *reg, char *buf, uint32_t len);
 extern uint32_t module_registry_count(void *reg);
+extern int32_t  module_registry_start(void *reg, uint32_t mid);
+extern void     module_registry_record_fault(void *reg, uint32_t mid, uint64_t now);
+extern int32_t  module_registry_quarantine_suspect(void *reg, uint64_t now);
+extern int32_t  module_registry_param_get(void *reg, const char *path, uint8_t privilege,
+                                           char *buf, uint32_t len);
+extern int32_t  module_registry_param_set(void *reg, const char *path, const char *value,
+                                           uint8_t privilege);
+extern uint32_t module_registry_render_params(void *reg, uint8_t privilege,
+                                               char *buf, uint32_t len);
 
 // =============================================================================
 // Internal slot table
@@ -35,6 +61,7 @@ typedef struct {
     uint32_t mid;
     char     name[MOD_NAME_LEN];
     uint32_t base_addr;              // address of ModHeader (start of binary)
+    uint32_t code_off;               // header size: code starts here
     uint32_t code_size;
     uint32_t fn_count;
     uint32_t fn_offsets[MOD_MAX_EXPORTS];
@@ -43,6 +70,26 @@ typedef struct {
 static ModSlot   g_slots[MOD_MAX_MODULES];
 static void     *g_registry = NULL;   // Rust ModuleRegistry*
 
+// A call into module code in progress. Calls nest (a module may call
+// another), so each frame links to the one it interrupted.
+typedef struct ModCallFrame {
+    struct ModCallFrame *prev;
+    uint32_t             mid;
+    void                *jmp[5];     // __builtin_setjmp buffer
+} ModCallFrame;
+
+// Module code runs with interrupts enabled, so the timer may switch to
+// another task in the middle of a call. Each task slot keeps its own chain;
+// a fault is unwound on the task that took it.
+#define MOD_CALL_TASKS 8              // MAX_TASKS in kernel_x86.c / kernel_x64.c
+
+static ModCallFrame *g_call[MOD_CALL_TASKS];
+
+static ModCallFrame **mod_call_chain(void) {
+    uint32_t t = kernel_current_task();
+    return &g_call[t < MOD_CALL_TASKS ? t : 0];
+}
+
 // =============================================================================
 // Helpers
 // =============================================================================
@@ -84,6 +131,10 @@ void module_init(void) {
 // =============================================================================
 
 int32_t module_load(uint32_t addr, uint32_t size) {
+    return module_load_params(addr, size, (const char *)0);
+}
+
+int32_t module_load_params(uint32_t addr, uint32_t size, const char *params) {
     if (!addr || size < sizeof(ModHeader))
         return MOD_ERR_INVAL;
 
@@ -94,7 +145,7 @@ int32_t module_load(uint32_t addr, uint32_t size) {
         serial_print("[module] load: bad magic\n");
         return MOD_ERR_MAGIC;
     }
-    if (hdr->version != MOD_VERSION) {
+    if (hdr->version < MOD_VERSION_V1 || hdr->version > MOD_VERSION) {
         serial_print("[module] load: unsupported version\n");
         return MOD_ERR_MAGIC;
     }
@@ -102,11 +153,57 @@ int32_t module_load(uint32_t addr, uint32_t size) {
         serial_print("[module] load: fn_count exceeds limit\n");
         return MOD_ERR_INVAL;
     }
-    if (size < sizeof(ModHeader) + hdr->code_size) {
+
+    // v2 adds the dependency table between header and code, v3 the
+    // exports, v4 the lifecycle hooks
+    uint32_t code_off = (uint32_t)sizeof(ModHeader);
+    const ModHeaderV2 *hdr2 = (const ModHeaderV2 *)0;
+    const ModHeaderV3 *hdr3 = (const ModHeaderV3 *)0;
+    const ModHeaderV4 *hdr4 = (const ModHeaderV4 *)0;
+    if (hdr->version != MOD_VERSION_V1) {
+        code_off = (hdr->version == MOD_VERSION)    ? (uint32_t)sizeof(ModHeaderV4) :
+                   (hdr->version == MOD_VERSION_V3) ? (uint32_t)sizeof(ModHeaderV3)
+                                                    : (uint32_t)sizeof(ModHeaderV2);
+        if (size < code_off) return MOD_ERR_INVAL;
+        hdr2 = (const ModHeaderV2 *)(uintptr_t)addr;
+        if (hdr2->dep_count > MOD_MAX_DEPS) {
+            serial_print("[module] load: dep_count exceeds limit\n");
+            return MOD_ERR_INVAL;
+        }
+    }
+    if (hdr->version >= MOD_VERSION_V3) {
+        hdr3 = (const ModHeaderV3 *)(uintptr_t)addr;
+        if (hdr3->sym_count > MOD_MAX_EXPORTS) {
+            serial_print("[module] load: sym_count exceeds limit\n");
+            return MOD_ERR_INVAL;
+        }
+        for (uint32_t i = 0; i < hdr3->sym_count; i++) {
+            if (hdr3->syms[i].fn_id >= hdr->fn_count) {
+                serial_print("[module] load: export names a bad fn_id\n");
+                return MOD_ERR_RANGE;
+            }
+        }
+    }
+    if (hdr->version == MOD_VERSION) {
+        hdr4 = (const ModHeaderV4 *)(uintptr_t)addr;
+        if ((hdr4->init_fn != MOD_FN_NONE && hdr4->init_fn >= hdr->fn_count) ||
+            (hdr4->exit_fn != MOD_FN_NONE && hdr4->exit_fn >= hdr->fn_count)) {
+            serial_print("[module] load: hook names a bad fn_id\n");
+            return MOD_ERR_RANGE;
+        }
+    }
+    if (size < code_off + hdr->code_size) {
         serial_print("[module] load: size too small for code\n");
         return MOD_ERR_INVAL;
     }
 
+    // Signatures are checked by the registry: without one, only an Off
+    // policy lets a module in
+    if (!g_registry && module_sig_get_policy() != MOD_SIG_OFF) {
+        serial_print("[module] load: no registry to verify the signature\n");
+        return MOD_ERR_ACCESS;
+    }
+
     // Find free slot
     int32_t slot_idx = -1;
     for (int i = 0; i < MOD_MAX_MODULES; i++) {
@@ -122,6 +219,7 @@ int32_t module_load(uint32_t addr, uint32_t size) {
     slot->in_use    = 1;
     slot->mid       = (uint32_t)slot_idx;
     slot->base_addr = addr;
+    slot->code_off  = code_off;
     slot->code_size = hdr->code_size;
     slot->fn_count  = hdr->fn_count;
 
@@ -135,10 +233,87 @@ int32_t module_load(uint32_t addr, uint32_t size) {
     for (uint32_t i = 0; i < hdr->fn_count; i++)
         slot->fn_offsets[i] = hdr->fn_offsets[i];
 
-    // Notify Rust registry
-    if (g_registry)
-        module_registry_register(g_registry, slot->mid, slot->name,
-                                 slot->base_addr, slot->code_size, slot->fn_count);
+    // Register with Rust; refused if a dependency is not loaded or an
+    // export clashes with an existing symbol
+    if (g_registry) {
+        ModSymbol syms[MOD_MAX_EXPORTS];
+        uint32_t sym_count = hdr3 ? hdr3->sym_count : 0;
+        for (uint32_t i = 0; i < sym_count; i++) {
+            const ModExportDecl *d = &hdr3->syms[i];
+            mod_memcpy(syms[i].name, d->name, MOD_NAME_LEN);
+            syms[i].mid     = slot->mid;
+            syms[i].fn_id   = d->fn_id;
+            syms[i].addr    = slot->base_addr + code_off + slot->fn_offsets[d->fn_id];
+            syms[i].version = d->version;
+            syms[i].flags   = d->flags;
+        }
+        ModuleDesc desc;
+        desc.mid       = slot->mid;
+        desc.name      = slot->name;
+        desc.base_addr = slot->base_addr;
+        desc.code_size = slot->code_size;
+        desc.fn_count  = slot->fn_count;
+        desc.flags     = hdr3 ? hdr3->mod_flags : 0;
+        desc.deps      = hdr2 ? &hdr2->deps[0][0] : (const char *)0;
+        desc.dep_count = hdr2 ? hdr2->dep_count : 0;
+        desc.syms      = syms;
+        desc.sym_count = sym_count;
+        desc.code      = (const uint8_t *)(uintptr_t)(addr + code_off);
+        desc.image     = (const uint8_t *)(uintptr_t)addr;
+        desc.image_len = code_off + hdr->code_size;
+        desc.manifest  = (const uint8_t *)0;
+        desc.manifest_len = 0;
+        desc.init_fn   = 0;
+        desc.exit_fn   = 0;
+        if (hdr4 && hdr4->init_fn != MOD_FN_NONE)
+            desc.init_fn = slot->base_addr + code_off + slot->fn_offsets[hdr4->init_fn];
+        if (hdr4 && hdr4->exit_fn != MOD_FN_NONE)
+            desc.exit_fn = slot->base_addr + code_off + slot->fn_offsets[hdr4->exit_fn];
+        desc.params    = params;
+        if (size >= code_off + hdr->code_size + sizeof(ModManifest)) {
+            const ModManifest *man =
+                (const ModManifest *)(uintptr_t)(addr + code_off + hdr->code_size);
+            if (man->magic == MOD_MANIFEST_MAGIC || man->magic == MOD_MANIFEST_MAGIC_V2) {
+                desc.manifest     = (const uint8_t *)man;
+                desc.manifest_len = size - code_off - hdr->code_size;
+            }
+        }
+
+        int32_t rc = module_registry_register(g_registry, &desc);
+        if (rc < 0) {
+            serial_print("[module] load: '");
+            serial_print(slot->name);
+            serial_print(rc == MOD_ERR_NOTFOUND ? "' has a missing dependency or unknown parameter\n" :
+                         rc == MOD_ERR_EXISTS   ? "' exports a symbol already defined\n" :
+                         rc == MOD_ERR_ACCESS   ? "' failed signature verification\n" :
+                         rc == MOD_ERR_RANGE    ? "' has a parameter value out of range\n"
+                                                : "' rejected by registry\n");
+            mod_memset(slot, 0, sizeof(ModSlot));
+            return rc;
+        }
+    }
+
+    // Phase 10: Notify AI of module load. The engine sees it too, so an
+    // exception soon after can be matched to the module (bad-module).
+    {
+        char evbuf[64];
+        ai_build_event(evbuf, sizeof(evbuf), slot->name, "load", (void*)0);
+        ai_send_event(AI_EVT_MODULE, evbuf);
+        ai_kernel_engine_feed(AI_EVT_MODULE, evbuf, kernel_get_ticks());
+    }
+
+    // Run the init hook; on failure the module stays loaded as Failed
+    if (g_registry) {
+        int32_t rc = module_registry_start(g_registry, slot->mid);
+        if (rc < 0) {
+            serial_print("[module] load: '");
+            serial_print(slot->name);
+            serial_print("' init failed: ");
+            serial_print(kernel_strerror(rc));
+            serial_print("\n");
+            return rc;
+        }
+    }
 
     serial_print("[module] loaded '");
     serial_print(slot->name);
@@ -150,13 +325,6 @@ int32_t module_load(uint32_t addr, uint32_t size) {
     serial_print_hex(slot->base_addr);
     serial_print("\n");
 
-    // Phase 10: Notify AI of module load
-    {
-        char evbuf[64];
-        ai_build_event(evbuf, sizeof(evbuf), slot->name, "load", (void*)0);
-        ai_send_event(AI_EVT_MODULE, evbuf);
-    }
-
     return (int32_t)slot->mid;
 }
 
@@ -164,10 +332,9 @@ int32_t module_load(uint32_t addr, uint32_t size) {
 // module_unload
 // =============================================================================
 
-int32_t module_unload(uint32_t mid) {
-    if (mid >= MOD_MAX_MODULES) return MOD_ERR_INVAL;
+static void module_free_slot(uint32_t mid) {
     ModSlot *slot = &g_slots[mid];
-    if (!slot->in_use) return MOD_ERR_NOTFOUND;
+    if (!slot->in_use) return;
 
     serial_print("[module] unloaded '");
     serial_print(slot->name);
@@ -182,13 +349,84 @@ int32_t module_unload(uint32_t mid) {
         ai_send_event(AI_EVT_MODULE, evbuf);
     }
 
-    if (g_registry)
-        module_registry_unregister(g_registry, mid);
-
     mod_memset(slot, 0, sizeof(ModSlot));
+}
+
+int32_t module_unload(uint32_t mid, uint32_t flags) {
+    if (mid >= MOD_MAX_MODULES) return MOD_ERR_INVAL;
+    if (!g_slots[mid].in_use) return MOD_ERR_NOTFOUND;
+
+    if (!g_registry) {
+        module_free_slot(mid);
+        return MOD_OK;
+    }
+
+    // The registry checks dependents and references, and hands back every
+    // module to drop (dependents first when cascading).
+    uint32_t removed[MOD_MAX_MODULES];
+    int32_t n = module_registry_unregister(g_registry, mid, flags,
+                                           removed, MOD_MAX_MODULES);
+    if (n < 0) {
+        serial_print("[module] unload: '");
+        serial_print(g_slots[mid].name);
+        serial_print(n == MOD_ERR_BUSY ? "' is in use\n" : "' refused\n");
+        return n;
+    }
+    for (int32_t i = 0; i < n && i < MOD_MAX_MODULES; i++) {
+        if (removed[i] < MOD_MAX_MODULES)
+            module_free_slot(removed[i]);
+    }
     return MOD_OK;
 }
 
+int32_t module_resolve(const char *name, uint32_t requester, ModSymbol *out) {
+    if (!name || !out) return MOD_ERR_INVAL;
+    if (!g_registry) return MOD_ERR_NOTFOUND;
+    return module_registry_resolve(g_registry, name, requester, out);
+}
+
+int32_t module_symbol_next(uint32_t *cursor, ModSymbol *out) {
+    if (!cursor || !out) return MOD_ERR_INVAL;
+    if (!g_registry) return MOD_ERR_NOTFOUND;
+    return module_registry_symbol_next(g_registry, cursor, out);
+}
+
+int32_t module_next(uint32_t *cursor, ModRecord *out) {
+    if (!cursor || !out) return MOD_ERR_INVAL;
+    if (!g_registry) return MOD_ERR_NOTFOUND;
+    return module_registry_next(g_registry, cursor, out);
+}
+
+int32_t module_param_get(const char *path, uint8_t privilege, char *buf, uint32_t len) {
+    if (!path || !buf || !len) return MOD_ERR_INVAL;
+    if (!g_registry) return MOD_ERR_NOTFOUND;
+    return module_registry_param_get(g_registry, path, privilege, buf, len);
+}
+
+int32_t module_param_set(const char *path, const char *value, uint8_t privilege) {
+    if (!path || !value) return MOD_ERR_INVAL;
+    if (!g_registry) return MOD_ERR_NOTFOUND;
+    return module_registry_param_set(g_registry, path, value, privilege);
+}
+
+uint32_t module_render_params(uint8_t privilege, char *buf, uint32_t len) {
+    if (!buf || !len) return 0;
+    if (!g_registry) { buf[0] = '\0'; return 0; }
+    return module_registry_render_params(g_registry, privilege, buf, len);
+}
+
+uint32_t module_render_tree(char *buf, uint32_t len) {
+    if (!buf || !len) return 0;
+    if (!g_registry) { buf[0] = '\0'; return 0; }
+    return module_registry_render_tree(g_registry, buf, len);
+}
+
+uint32_t module_render_proc(char *buf, uint32_t len) {
+    if (!buf || !len) return 0;
+    if (!g_registry) { buf[0] = '\0'; return 0; }
+    return module_registry_render_proc(g_registry, buf, len);
+}
+
 // =============================================================================
 // module_list
 // =============================================================================
@@ -217,18 +455,90 @@ int32_t module_list(void) {
 // module_call
 // =============================================================================
 
+// Run module code at fn_addr on behalf of mid. If it faults, the exception
+// handler unwinds to the __builtin_setjmp below and MOD_ERR_FAULT is
+// returned; the interrupt flag is restored since no iret was executed.
+static int32_t module_invoke(uint32_t mid, uint32_t fn_addr, uint32_t arg) {
+    ModCallFrame frame;
+    ModCallFrame **chain = mod_call_chain();
+    uintptr_t flags;
+    __asm__ volatile("pushf; pop %0" : "=r"(flags));
+
+    frame.prev = *chain;
+    frame.mid  = mid;
+    if (__builtin_setjmp(frame.jmp)) {
+        *chain = frame.prev;
+        if (flags & 0x200) __asm__ volatile("sti");
+        return MOD_ERR_FAULT;
+    }
+    *chain = &frame;
+    ModFn fn = (ModFn)(uintptr_t)fn_addr;
+    int32_t ret = (int32_t)fn(arg);
+    *chain = frame.prev;
+    return ret;
+}
+
+// Init/exit hooks and parameter notifications, run by the registry
+// (module_registry_start / _unregister / _param_set)
+int32_t module_run_hook(uint32_t mid, uint32_t addr, uint32_t arg) {
+    return module_invoke(mid, addr, arg);
+}
+
+int module_fault_enter(uint32_t vec) {
+    ModCallFrame *call = *mod_call_chain();
+    if (!call) return 0;
+    uint32_t mid = call->mid;
+    if (g_registry)
+        module_registry_record_fault(g_registry, mid, kernel_get_ticks());
+    serial_print("[module] fault in '");
+    serial_print(mid < MOD_MAX_MODULES ? g_slots[mid].name : "?");
+    serial_print("' vec=");
+    serial_print_dec(vec);
+    serial_print(", unwinding call\n");
+    return 1;
+}
+
+void module_fault_unwind(void) {
+    __builtin_longjmp((*mod_call_chain())->jmp, 1);
+}
+
+void module_task_exit(uint32_t task) {
+    if (task < MOD_CALL_TASKS) g_call[task] = NULL;
+}
+
+void module_quarantine_suspect(uint64_t now) {
+    if (!g_registry) return;
+    int32_t mid = module_registry_quarantine_suspect(g_registry, now);
+    if (mid < 0 || mid >= MOD_MAX_MODULES) return;
+
+    serial_print("[module] quarantined '");
+    serial_print(g_slots[mid].name);
+    serial_print("' after bad-module alert\n");
+    char evbuf[64];
+    ai_build_event(evbuf, sizeof(evbuf), g_slots[mid].name, "quarantine", (void*)0);
+    ai_send_event(AI_EVT_MODULE, evbuf);
+}
+
 int32_t module_call(uint32_t mid, uint32_t fn_id, uint32_t arg) {
     if (mid >= MOD_MAX_MODULES) return MOD_ERR_INVAL;
     const ModSlot *slot = &g_slots[mid];
     if (!slot->in_use)          return MOD_ERR_NOTFOUND;
     if (fn_id >= slot->fn_count) return MOD_ERR_RANGE;
 
-    // Code section starts right after the ModHeader
-    uint32_t code_base = slot->base_addr + (uint32_t)sizeof(ModHeader);
+    // Code section starts right after the header
+    uint32_t code_base = slot->base_addr + slot->code_off;
     uint32_t fn_addr   = code_base + slot->fn_offsets[fn_id];
 
-    ModFn fn = (ModFn)(uintptr_t)fn_addr;
-    return (int32_t)fn(arg);
+    // Hold a reference for the duration of the call so the module cannot
+    // be unloaded underneath it; refused unless the module is Live
+    if (g_registry) {
+        int32_t rc = module_registry_acquire(g_registry, mid);
+        if (rc < 0) return rc;
+    }
+    int32_t ret = module_invoke(mid, fn_addr, arg);
+    if (g_registry)
+        module_registry_release(g_registry, mid);
+    return ret;
 }
 
 // =============================================================================
@@ -256,13 +566,20 @@ int32_t module_info(uint32_t mid, ModInfo *out) {
 int32_t module_syscall(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
     switch (num) {
 
-    // SYS_MOD_LOAD (28): a1=addr, a2=size → mid
-    case SYS_MOD_LOAD:
-        return module_load(a1, a2);
+    // SYS_MOD_LOAD (28): a1=addr, a2=size, a3=params string or 0 → mid
+    case SYS_MOD_LOAD: {
+        int32_t rc = usermem_access_ok(a1, a2, 0);
+        if (rc < 0) return rc;
+        if (!a3) return module_load(a1, a2);
+        char params[MOD_PARAMS_MAX];
+        intptr_t n = usermem_strncpy_from_user(params, a3, sizeof(params));
+        if (n < 0) return (int32_t)n;
+        return module_load_params(a1, a2, params);
+    }
 
-    // SYS_MOD_UNLOAD (29): a1=mid
+    // SYS_MOD_UNLOAD (29): a1=mid, a2=flags
     case SYS_MOD_UNLOAD:
-        return module_unload(a1);
+        return module_unload(a1, a2);
 
     // SYS_MOD_LIST (30): → count
     case SYS_MOD_LIST:
@@ -273,9 +590,12 @@ int32_t module_syscall(uint32_t num, uint32_t a1, uint32_t a2, uint32_t a3) {
         return module_call(a1, a2, a3);
 
     // SYS_MOD_INFO (32): a1=mid, a2=ptr to ModInfo
-    case SYS_MOD_INFO:
-        if (!a2) return MOD_ERR_INVAL;
-        return module_info(a1, (ModInfo *)(uintptr_t)a2);
+    case SYS_MOD_INFO: {
+        ModInfo info;
+        int32_t rc = module_info(a1, &info);
+        if (rc < 0) return rc;
+        return usermem_copy_to_user(a2, &info, sizeof(info));
+    }
 
     default:
         return MOD_ERR_INVAL;
@@ -45,20 +45,20 @@ bool policy_check_command(const char *command, uint8_t privilege) {
     }
 
     // Query AI engine for access check (try full command first, then first word)
-    uint8_t required = ai_kernel_engine_check_access(command, full_cmd_len);
+    int32_t required = ai_kernel_engine_check_access(command, full_cmd_len);
 
-    if (required == 255 && full_cmd_len != cmd_len) {
+    if (required == -ENOENT && full_cmd_len != cmd_len) {
         // No match with two words — try single word
         required = ai_kernel_engine_check_access(command, cmd_len);
     }
 
-    if (required == 255) {
-        // No rule found — default: allow (no policy restriction)
+    if (required < 0) {
+        // No rule found (or engine not up) — default: allow (no policy restriction)
         return true;
     }
 
     // Map policy privilege to CLI privilege
-    uint8_t min_cli_priv = policy_map_privilege(required);
+    uint8_t min_cli_priv = policy_map_privilege((uint8_t)required);
 
     // Check: session privilege must be <= required (lower = more privileged)
     return privilege <= min_cli_priv;
@@ -122,8 +122,8 @@ void policy_load_from_disk(void) {
     serial_print(" sections\n");
 
     // Feed to AI engine
-    uint32_t ok = ai_kernel_engine_load_policy(policy_buf, offset);
-    if (ok) {
+    int32_t rc = ai_kernel_engine_load_policy(policy_buf, offset);
+    if (rc == 0) {
         serial_print("[policy] Loaded OK, version ");
         serial_print_dec(ai_kernel_engine_policy_version());
         serial_print("\n");
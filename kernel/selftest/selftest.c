@@ -207,7 +207,7 @@ int selftest_access_rules(void) {
 
     // Test 2: check_access("shutdown") → should return 0 (root)
     {
-        uint8_t priv = ai_kernel_engine_check_access("shutdown", 8);
+        int32_t priv = ai_kernel_engine_check_access("shutdown", 8);
         if (priv == 0) test_ok("check_access(shutdown) → 0 (root)");
         else {
             cli_printf("    expected 0, got %d\n", (int)priv);
@@ -216,12 +216,12 @@ int selftest_access_rules(void) {
         }
     }
 
-    // Test 3: check_access("help") → should return 100 (user) or 255 (no rule)
+    // Test 3: check_access("help") → should return 100 (user) or -ENOENT (no rule)
     {
-        uint8_t priv = ai_kernel_engine_check_access("help", 4);
-        if (priv == 100 || priv == 255) test_ok("check_access(help) → user/no-rule");
+        int32_t priv = ai_kernel_engine_check_access("help", 4);
+        if (priv == 100 || priv == -ENOENT) test_ok("check_access(help) → user/no-rule");
         else {
-            cli_printf("    expected 100 or 255, got %d\n", (int)priv);
+            cli_printf("    expected 100 or %d, got %d\n", -ENOENT, (int)priv);
             test_fail("check_access(help) unexpected");
             fails++;
         }
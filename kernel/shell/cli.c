@@ -122,8 +122,13 @@ static int cli_cmd_tcpstat(CliSession *session, const ParsedCommand *cmd) {
 #include "klog.h"
 #include "ipc.h"
 #include "dylib.h"
+#include "module.h"
 #include "vfs.h"
 #include "bcache.h"
+#include "strace.h"
+#include "syscall_base.h"
+#include "font.h"
+#include "console.h"
 #include <stddef.h>
 #include <stdarg.h>
 
@@ -147,7 +152,7 @@ extern void kernel_idle_work(void);
 extern void system_shutdown(void);
 extern void system_restart(void);
 // Phase 19: ELF exec function pointer (set by kernel)
-extern int (*g_elf_exec_fn)(const char *path);
+extern int (*g_elf_exec_fn)(const char *path, uint16_t uid);
 // Phase 22: PCI + Network exports
 extern int  kernel_pci_count(void);
 extern void kernel_pci_get(int idx, uint16_t *vendor, uint16_t *device,
@@ -269,9 +274,17 @@ static int cli_cmd_dlsym(CliSession *session, const ParsedCommand *cmd);
 static int cli_cmd_dlcall(CliSession *session, const ParsedCommand *cmd);
 static int cli_cmd_dlclose(CliSession *session, const ParsedCommand *cmd);
 static int cli_cmd_dllist(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_lsmod(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_modsig(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_modparam(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_font(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_scrollback(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_vt(CliSession *session, const ParsedCommand *cmd);
 static int cli_cmd_chmod(CliSession *session, const ParsedCommand *cmd);
 static int cli_cmd_chown(CliSession *session, const ParsedCommand *cmd);
 static int cli_cmd_sync(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_strace(CliSession *session, const ParsedCommand *cmd);
+static int cli_cmd_sysdump(CliSession *session, const ParsedCommand *cmd);
 // static int simple_atoi(const char *s); // removed duplicate declaration
 static uint64_t simple_atou64(const char *s);
 
@@ -331,10 +344,18 @@ static const CliBuiltinCommand BUILTIN_COMMANDS[] = {
     { "dlcall",   "Call resolved symbol",    cli_cmd_dlcall,    CLI_PRIV_USER  },
     { "dlclose",  "Unload shared library",   cli_cmd_dlclose,   CLI_PRIV_ADMIN },
     { "dllist",   "List shared libraries",   cli_cmd_dllist,    CLI_PRIV_USER  },
+    { "lsmod",    "Show module dependency tree", cli_cmd_lsmod,  CLI_PRIV_USER  },
+    { "modsig",   "Module signature policy",  cli_cmd_modsig,   CLI_PRIV_ROOT  },
+    { "modparam", "Show/set module parameters", cli_cmd_modparam, CLI_PRIV_USER },
+    { "font",     "Show/load console font",  cli_cmd_font,     CLI_PRIV_ADMIN },
+    { "scrollback", "Show/set console history", cli_cmd_scrollback, CLI_PRIV_ADMIN },
+    { "vt",       "List/switch/assign consoles", cli_cmd_vt,     CLI_PRIV_ADMIN },
 
     { "chmod",    "Change file permissions",  cli_cmd_chmod,    CLI_PRIV_USER  },
     { "chown",    "Change file owner/group",  cli_cmd_chown,    CLI_PRIV_ROOT  },
     { "sync",     "Flush block cache",        cli_cmd_sync,     CLI_PRIV_USER  },
+    { "strace",   "Trace process syscalls",   cli_cmd_strace,   CLI_PRIV_ADMIN },
+    { "sysdump",  "Dump kernel debug state",  cli_cmd_sysdump,  CLI_PRIV_ADMIN },
     { "tcphandshake", "Test TCP handshake",    cli_cmd_tcphandshake, CLI_PRIV_USER },
     { "tcpstat",  "Show TCP sockets",         cli_cmd_tcpstat,  CLI_PRIV_USER  },
 };
@@ -857,7 +878,7 @@ static int ps_render_table(void *sched) {
 
         for (size_t pid = 1; pid <= probe_limit && found < 32; pid++) {
             PsRow probe;
-            if (scheduler_get_ps_row(sched, pid, &probe)) {
+            if (scheduler_get_ps_row(sched, pid, &probe) == 0) {
                 pids[found++] = pid;
             }
         }
@@ -878,7 +899,7 @@ static int ps_render_table(void *sched) {
 
     for (size_t i = 0; i < count; i++) {
         PsRow row;
-        if (!scheduler_get_ps_row(sched, pids[i], &row)) continue;
+        if (scheduler_get_ps_row(sched, pids[i], &row) != 0) continue;
 
         const char *st   = (row.state < 7) ? state_name[row.state] : "?";
         const char *type = (row.ptype < 3) ? type_name[row.ptype]  : "?";
@@ -2087,6 +2108,121 @@ static int cli_cmd_sync(CliSession *session, const ParsedCommand *cmd) {
     return CLI_OK;
 }
 
+// strace — per-process syscall tracing
+static int cli_cmd_strace(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    static uint8_t out[6144];
+    const char *sub = (cmd->argc >= 2) ? cmd->argv[1] : "status";
+    const char *arg = (cmd->argc >= 3) ? cmd->argv[2] : (const char *)0;
+    int rc = 0;
+
+    if (cli_streq(sub, "status")) {
+        strace_render(0, out, sizeof(out));
+        cli_printf("%s", (const char *)out);
+        return CLI_OK;
+    } else if (cli_streq(sub, "show") && arg) {
+        strace_render((size_t)simple_atoi(arg), out, sizeof(out));
+        cli_printf("%s", (const char *)out);
+        return CLI_OK;
+    } else if ((cli_streq(sub, "pid") || cli_streq(sub, "group")) && arg) {
+        uint32_t kind = cli_streq(sub, "pid") ? STRACE_TARGET_PID : STRACE_TARGET_GROUP;
+        rc = strace_attach(kind, (size_t)simple_atoi(arg));
+    } else if (cli_streq(sub, "all")) {
+        rc = strace_attach(STRACE_TARGET_ALL, 0);
+    } else if (cli_streq(sub, "off")) {
+        // "strace off" stops everything, "strace off pid|group <id>" one target
+        if (arg && cmd->argc >= 4) {
+            uint32_t kind = cli_streq(arg, "group") ? STRACE_TARGET_GROUP : STRACE_TARGET_PID;
+            rc = strace_detach(kind, (size_t)simple_atoi(cmd->argv[3]));
+        } else {
+            rc = strace_detach(STRACE_TARGET_ALL, 0);
+        }
+    } else if (cli_streq(sub, "filter") && arg) {
+        // "strace filter all|none" or "strace filter <num> on|off"
+        if (cli_streq(arg, "all") || cli_streq(arg, "none")) {
+            strace_filter_all(cli_streq(arg, "all") ? 1 : 0);
+        } else {
+            uint8_t on = (cmd->argc >= 4 && cli_streq(cmd->argv[3], "off")) ? 0 : 1;
+            rc = strace_set_filter((uint32_t)simple_atoi(arg), on);
+        }
+    } else if (cli_streq(sub, "ai") && arg) {
+        strace_set_ai_forward(cli_streq(arg, "on") ? 1 : 0);
+    } else if (cli_streq(sub, "clear")) {
+        strace_clear(arg ? (size_t)simple_atoi(arg) : 0);
+    } else {
+        cli_printf("Usage: strace [status]\n");
+        cli_printf("       strace pid <pid> | group <pgid> | all\n");
+        cli_printf("       strace off [pid <pid> | group <pgid>]\n");
+        cli_printf("       strace show <pid>\n");
+        cli_printf("       strace filter all | none | <num> [on|off]\n");
+        cli_printf("       strace ai on|off\n");
+        cli_printf("       strace clear [pid]\n");
+        return 1;
+    }
+
+    if (rc < 0) {
+        cli_printf("strace: %s\n", kernel_strerror(rc));
+        return 1;
+    }
+    return CLI_OK;
+}
+
+// Decimal or 0x-prefixed hex; 0 on anything else
+static size_t cli_parse_addr(const char *s) {
+    size_t v = 0;
+    if (s[0] == '0' && (s[1] == 'x' || s[1] == 'X')) {
+        for (s += 2; *s; s++) {
+            char c = *s;
+            if (c >= '0' && c <= '9')      v = v * 16 + (size_t)(c - '0');
+            else if (c >= 'a' && c <= 'f') v = v * 16 + (size_t)(c - 'a' + 10);
+            else if (c >= 'A' && c <= 'F') v = v * 16 + (size_t)(c - 'A' + 10);
+            else return 0;
+        }
+        return v;
+    }
+    for (; *s; s++) {
+        if (*s < '0' || *s > '9') return 0;
+        v = v * 10 + (size_t)(*s - '0');
+    }
+    return v;
+}
+
+// sysdump — the sys_dump_* debug views (registers, scheduler, memory, syscalls)
+static int cli_cmd_sysdump(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    static char out[SYS_DUMP_MAX];
+    const char *sub = (cmd->argc >= 2) ? cmd->argv[1] : "all";
+    const char *arg = (cmd->argc >= 3) ? cmd->argv[2] : (const char *)0;
+    ssize_t rc;
+
+    if (cli_streq(sub, "regs")) {
+        rc = ffi_sys_dump_registers(arg ? (size_t)simple_atoi(arg) : 0, out, sizeof(out));
+    } else if (cli_streq(sub, "sched")) {
+        rc = ffi_sys_dump_scheduler(out, sizeof(out));
+    } else if (cli_streq(sub, "mem") && arg) {
+        size_t count = (cmd->argc >= 4) ? cli_parse_addr(cmd->argv[3]) : SYS_DUMP_MEMORY_BYTES;
+        if (count > SYS_DUMP_MEMORY_BYTES) count = SYS_DUMP_MEMORY_BYTES;
+        rc = ffi_sys_dump_memory(cli_parse_addr(arg), count, out, sizeof(out));
+    } else if (cli_streq(sub, "syscalls")) {
+        rc = ffi_sys_dump_syscalls(out, sizeof(out));
+    } else if (cli_streq(sub, "all")) {
+        rc = ffi_sys_dump_all(out, sizeof(out));
+    } else {
+        cli_printf("Usage: sysdump [all]\n");
+        cli_printf("       sysdump regs [pid]\n");
+        cli_printf("       sysdump sched | syscalls\n");
+        cli_printf("       sysdump mem <addr> [count]\n");
+        return 1;
+    }
+
+    if (rc < 0) {
+        cli_printf("sysdump: %s\n", kernel_strerror((int32_t)rc));
+        return 1;
+    }
+    cli_printf("%s", out);
+    return CLI_OK;
+}
+
 // exec — Load and run an ELF binary from VernisFS (Phase 19)
 static int cli_cmd_exec(CliSession *session, const ParsedCommand *cmd) {
     if (cmd->argc < 2) {
@@ -2103,7 +2239,7 @@ static int cli_cmd_exec(CliSession *session, const ParsedCommand *cmd) {
         return 1;
     }
     cli_printf("Loading %s...\n", cmd->argv[1]);
-    int ret = g_elf_exec_fn(cmd->argv[1]);
+    int ret = g_elf_exec_fn(cmd->argv[1], (uint16_t)session->uid);
     if (ret < 0) {
         cli_printf("exec: failed to load %s\n", cmd->argv[1]);
         return 1;
@@ -2675,11 +2811,35 @@ static int cli_cmd_usockclose(CliSession *session, const ParsedCommand *cmd) {
 static int cli_cmd_dlopen(CliSession *session, const ParsedCommand *cmd) {
     (void)session;
     if (cmd->argc < 2) {
-        cli_printf("Usage: dlopen <path> [name]\n");
+        cli_printf("Usage: dlopen <path> [name] [param=value ...]\n");
         return 1;
     }
-    const char *name = (cmd->argc >= 3) ? cmd->argv[2] : 0;
-    int rc = dylib_open(cmd->argv[1], name);
+    // Arguments containing '=' are module parameters, passed on as one
+    // space-separated string
+    const char *name = 0;
+    char params[MOD_PARAMS_MAX];
+    int plen = 0;
+    params[0] = '\0';
+    for (int i = 2; i < cmd->argc; i++) {
+        const char *a = cmd->argv[i];
+        int is_param = 0;
+        for (const char *c = a; *c; c++)
+            if (*c == '=') { is_param = 1; break; }
+        if (!is_param) {
+            if (!name) name = a;
+            continue;
+        }
+        int alen = 0;
+        while (a[alen]) alen++;
+        if (plen + alen + 2 > (int)sizeof(params)) {
+            cli_printf("dlopen: too many parameters\n");
+            return 1;
+        }
+        if (plen) params[plen++] = ' ';
+        for (int j = 0; j < alen; j++) params[plen++] = a[j];
+        params[plen] = '\0';
+    }
+    int rc = dylib_open(cmd->argv[1], name, plen ? params : 0);
     if (rc < 0) {
         cli_printf("dlopen: failed (%d)\n", rc);
         return 1;
@@ -2745,6 +2905,134 @@ static int cli_cmd_dllist(CliSession *session, const ParsedCommand *cmd) {
     return CLI_OK;
 }
 
+static int cli_cmd_lsmod(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    (void)cmd;
+    static char out[2048];
+    module_render_tree(out, sizeof(out));
+    cli_printf("%s", out);
+    return CLI_OK;
+}
+
+static int cli_cmd_modsig(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    static const char *names[] = { "off", "warn", "enforce" };
+    if (cmd->argc >= 2) {
+        uint32_t p;
+        for (p = 0; p < 3; p++)
+            if (cli_streq(cmd->argv[1], names[p])) break;
+        if (p == 3) {
+            cli_printf("Usage: modsig [off|warn|enforce]\n");
+            return 1;
+        }
+        module_sig_set_policy(p);
+    }
+    uint32_t cur = module_sig_get_policy();
+    cli_printf("module signature policy: %s\n", cur < 3 ? names[cur] : "?");
+    return CLI_OK;
+}
+
+static int cli_cmd_modparam(CliSession *session, const ParsedCommand *cmd) {
+    uint8_t priv = (uint8_t)session->privilege;
+    if (cmd->argc < 2) {
+        static char out[2048];
+        module_render_params(priv, out, sizeof(out));
+        cli_printf("%s", out);
+        return CLI_OK;
+    }
+    if (cmd->argc >= 3) {
+        int rc = module_param_set(cmd->argv[1], cmd->argv[2], priv);
+        if (rc < 0) {
+            cli_printf("modparam: %s: %s\n", cmd->argv[1], kernel_strerror(rc));
+            return 1;
+        }
+    }
+    char val[MOD_PARAM_STR_LEN + 16];
+    int rc = module_param_get(cmd->argv[1], priv, val, sizeof(val));
+    if (rc < 0) {
+        cli_printf("modparam: %s: %s\n", cmd->argv[1], kernel_strerror(rc));
+        return 1;
+    }
+    cli_printf("%s = %s\n", cmd->argv[1], val);
+    return CLI_OK;
+}
+
+static int cli_cmd_font(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    if (cmd->argc >= 3 && cli_streq(cmd->argv[1], "load")) {
+        int rc = font_load(cmd->argv[2]);
+        if (rc < 0) {
+            cli_printf("font: %s: %s\n", cmd->argv[2], kernel_strerror(rc));
+            return 1;
+        }
+    } else if (cmd->argc >= 2 && cli_streq(cmd->argv[1], "reset")) {
+        font_reset();
+    } else if (cmd->argc >= 2) {
+        cli_printf("Usage: font [load <path.psf> | reset]\n");
+        return 1;
+    }
+    char out[160];
+    font_render_info((uint8_t *)out, sizeof(out));
+    cli_printf("%s", out);
+    return CLI_OK;
+}
+
+static int cli_cmd_scrollback(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    if (cmd->argc >= 2) {
+        int32_t rc = console_set_scrollback((uint32_t)simple_atoi(cmd->argv[1]));
+        if (rc < 0) {
+            cli_printf("scrollback: %s\n", kernel_strerror(rc));
+            return 1;
+        }
+    }
+    uint32_t used = 0, limit = 0;
+    console_get_scrollback(&used, &limit);
+    cli_printf("Console scrollback: %d/%d lines (Shift+PageUp/PageDown to page)\n",
+               (int)used, (int)limit);
+    return CLI_OK;
+}
+
+// vt                                  list consoles (* = shown)
+// vt <n>                              show console n (1-based, like Alt+Fn)
+// vt assign <n> kernel|none           give console n to the kernel log / nobody
+// vt assign <n> pid|session <id>      give console n to a process / session
+static int cli_cmd_vt(CliSession *session, const ParsedCommand *cmd) {
+    (void)session;
+    int32_t rc = 0;
+    if (cmd->argc >= 4 && cli_streq(cmd->argv[1], "assign")) {
+        uint32_t index = (uint32_t)simple_atoi(cmd->argv[2]) - 1;
+        const char *kind = cmd->argv[3];
+        uint32_t id = cmd->argc >= 5 ? (uint32_t)simple_atoi(cmd->argv[4]) : 0;
+        if (cli_streq(kind, "kernel"))       rc = console_assign(index, VT_OWNER_KERNEL, 0);
+        else if (cli_streq(kind, "none"))    rc = console_assign(index, VT_OWNER_NONE, 0);
+        else if (cli_streq(kind, "pid") && cmd->argc >= 5)
+            rc = console_assign(index, VT_OWNER_PROCESS, id);
+        else if (cli_streq(kind, "session") && cmd->argc >= 5)
+            rc = console_assign(index, VT_OWNER_SESSION, id);
+        else {
+            cli_printf("Usage: vt assign <n> kernel|none|pid <pid>|session <id>\n");
+            return 1;
+        }
+    } else if (cmd->argc == 2) {
+        rc = console_switch((uint32_t)simple_atoi(cmd->argv[1]) - 1);
+    } else if (cmd->argc > 2) {
+        cli_printf("Usage: vt [<n> | assign <n> <owner>]\n");
+        return 1;
+    }
+    if (rc < 0) {
+        cli_printf("vt: %s\n", kernel_strerror(rc));
+        return 1;
+    }
+    char out[512];
+    if (console_render_list((uint8_t *)out, sizeof(out)) == 0) {
+        cli_printf("vt: no framebuffer console\n");
+        return 1;
+    }
+    cli_printf("%s", out);
+    return CLI_OK;
+}
+
 // =============================================================================
 // Shell Loop
 // =============================================================================
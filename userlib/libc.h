@@ -3,6 +3,7 @@
 #define VERNIS_USERLIB_LIBC_H
 
 #include <stddef.h>
+#include <stdint.h>
 
 size_t strlen(const char *s);
 int strcmp(const char *a, const char *b);
@@ -18,4 +19,13 @@ int printf(const char *fmt, ...);
 void *malloc(size_t size);
 void free(void *ptr);
 
+/* Read-only kernel info page (KernelInfoPage). Mapped at KINFO_VADDR in
+ * every process and updated by the kernel under a seqlock. */
+#include "kinfo.h"
+
+typedef int64_t time_t;
+
+int getpid(void);
+time_t time(time_t *tloc);
+
 #endif
\ No newline at end of file
@@ -58,7 +58,8 @@ static inline void _exit(int code) {
     __builtin_unreachable();
 }
 
-static inline int getpid(void) {
+/* getpid() itself reads the kernel info page — see libc.h */
+static inline int sys_getpid(void) {
     return (int)_syscall3(SYS_GETPID, 0, 0, 0);
 }
 
@@ -131,4 +132,161 @@ static inline int munmap(void *addr, size_t length) {
     return (int)_syscall3(SYS_MUNMAP, (size_t)addr, length, 0);
 }
 
+/* Shared memory segments — see include/shm.h */
+#define SYS_SHM_CREATE  33
+#define SYS_SHM_OPEN    34
+#define SYS_SHM_MAP     35
+#define SYS_SHM_UNMAP   36
+#define SYS_SHM_UNLINK  37
+#define SYS_SHM_CLOSE   38
+
+#define SHM_MODE_OTHER_READ   0x1
+#define SHM_MODE_OTHER_WRITE  0x2
+#define SHM_O_WRITE           0x1
+
+/* Returns segment id (>= 1) or a negative SHM_ERR_* code */
+static inline int shm_create(const char *name, size_t size, int mode) {
+    return (int)_syscall3(SYS_SHM_CREATE, (size_t)name, size, (size_t)mode);
+}
+
+static inline int shm_open(const char *name, int flags) {
+    return (int)_syscall3(SYS_SHM_OPEN, (size_t)name, (size_t)flags, 0);
+}
+
+/* Map segment id; returns the mapped address or a negative error code */
+static inline void *shm_map(int id, int prot) {
+    return (void *)_syscall3(SYS_SHM_MAP, (size_t)id, (size_t)prot, 0);
+}
+
+static inline int shm_unmap(void *addr) {
+    return (int)_syscall3(SYS_SHM_UNMAP, (size_t)addr, 0, 0);
+}
+
+static inline int shm_unlink(const char *name) {
+    return (int)_syscall3(SYS_SHM_UNLINK, (size_t)name, 0, 0);
+}
+
+static inline int shm_close(int id) {
+    return (int)_syscall3(SYS_SHM_CLOSE, (size_t)id, 0, 0);
+}
+
+/* Syscall filters — see include/seccomp.h */
+#define SYS_SECCOMP     79
+
+#define SECCOMP_NR_ANY      0xFFFFFFFFu
+#define SECCOMP_ARG_NONE    0xFF
+
+#define SECCOMP_OP_EQ          0
+#define SECCOMP_OP_NE          1
+#define SECCOMP_OP_LT          2
+#define SECCOMP_OP_LE          3
+#define SECCOMP_OP_GT          4
+#define SECCOMP_OP_GE          5
+#define SECCOMP_OP_BITS_SET    6
+#define SECCOMP_OP_BITS_CLEAR  7
+
+#define SECCOMP_RET_KILL     0x00000000u
+#define SECCOMP_RET_TRAP     0x00030000u
+#define SECCOMP_RET_ERRNO(e) (0x00050000u | ((uint32_t)(e) & 0xFFFFu))
+#define SECCOMP_RET_ALLOW    0x7FFF0000u
+
+struct seccomp_rule {
+    uint32_t nr;
+    uint8_t  arg;
+    uint8_t  op;
+    uint16_t _pad;
+    uint32_t action;
+    size_t   value;
+};
+
+/* Stack a filter on the calling process; it can never be removed */
+static inline int seccomp(const struct seccomp_rule *rules, size_t count, uint32_t default_action) {
+    return (int)_syscall3(SYS_SECCOMP, (size_t)rules, count, default_action);
+}
+
+/* Submission/completion rings — see include/uring.h. The ring lives in a
+ * writable shm segment mapped by the caller; buffers and paths in SQEs are
+ * offsets from the start of that segment, inside its data area. */
+#define SYS_URING_SETUP    80
+#define SYS_URING_ENTER    81
+#define SYS_URING_DESTROY  82
+
+#define URING_OP_NOP        0
+#define URING_OP_IPC_SEND   1
+#define URING_OP_IPC_RECV   2
+#define URING_OP_KFS_READ   3
+#define URING_OP_KFS_WRITE  4
+#define URING_OP_AI_QUERY   5
+#define URING_OP_SLEEP      6
+
+struct uring_hdr {
+    volatile uint32_t sq_head, sq_tail;
+    uint32_t sq_mask, sq_off;
+    volatile uint32_t cq_head, cq_tail;
+    uint32_t cq_mask, cq_off;
+    uint32_t data_off, data_len;
+    uint32_t _reserved[6];
+};
+
+struct uring_sqe {
+    uint8_t  opcode;
+    uint8_t  flags;
+    uint16_t _pad;
+    uint32_t target;
+    uint32_t addr;
+    uint32_t len;
+    uint64_t arg;
+    uint64_t user_data;
+} __attribute__((packed));
+
+struct uring_cqe {
+    uint64_t user_data;
+    int32_t  res;
+    uint32_t flags;
+} __attribute__((packed));
+
+static inline int uring_setup(int shm_id, unsigned sq_entries, unsigned cq_entries) {
+    return (int)_syscall3(SYS_URING_SETUP, (size_t)shm_id, sq_entries, cq_entries);
+}
+
+/* Returns the number of completions ready; blocks until min_complete are */
+static inline int uring_enter(int ring, unsigned to_submit, unsigned min_complete) {
+    return (int)_syscall3(SYS_URING_ENTER, (size_t)ring, to_submit, min_complete);
+}
+
+static inline int uring_destroy(int ring) {
+    return (int)_syscall3(SYS_URING_DESTROY, (size_t)ring, 0, 0);
+}
+
+/* Debug dumps (needs CAP_SYS_DEBUG): NUL-terminated text in buf, returns
+ * its length or -errno. Output is capped at SYS_DUMP_MAX bytes. */
+#define SYS_DUMP_REGISTERS  10
+#define SYS_DUMP_SCHEDULER  11
+#define SYS_DUMP_MEMORY     12
+#define SYS_DUMP_SYSCALLS   13
+#define SYS_DUMP_ALL        14
+#define SYS_DUMP_MAX           8192
+#define SYS_DUMP_MEMORY_BYTES  256
+
+static inline int dump_registers(char *buf, size_t len) {
+    return (int)_syscall3(SYS_DUMP_REGISTERS, (size_t)buf, len, 0);
+}
+
+static inline int dump_scheduler(char *buf, size_t len) {
+    return (int)_syscall3(SYS_DUMP_SCHEDULER, (size_t)buf, len, 0);
+}
+
+/* Hexdump of SYS_DUMP_MEMORY_BYTES at addr, which must be the caller's own */
+static inline int dump_memory(char *buf, size_t len, const void *addr) {
+    return (int)_syscall3(SYS_DUMP_MEMORY, (size_t)buf, len, (size_t)addr);
+}
+
+static inline int dump_syscalls(char *buf, size_t len) {
+    return (int)_syscall3(SYS_DUMP_SYSCALLS, (size_t)buf, len, 0);
+}
+
+static inline int dump_all(char *buf, size_t len) {
+    return (int)_syscall3(SYS_DUMP_ALL, (size_t)buf, len, 0);
+}
+
 #endif /* VERNIS_SYSCALL_H */